@@ -0,0 +1,99 @@
+//! Timing of the per-scan hot path on realistic ticker volumes.
+//!
+//! Run with `cargo bench --bench scan`. Cases run on both `PriceMap` and
+//! the interned `PriceSnapshot`. Criterion keeps each run's estimates in
+//! `target/criterion` and reports the change against the previous one;
+//! `--save-baseline <name>` and `--baseline <name>` compare against a
+//! named run instead.
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::Decimal;
+use std::sync::Arc;
+use triangular_arbitrage::arbitrage::ArbitrageEngine;
+use triangular_arbitrage::config::Config;
+use triangular_arbitrage::exchanges::snapshot::{PriceSnapshot, SymbolTable};
+use triangular_arbitrage::exchanges::PriceMap;
+use triangular_arbitrage::symbols::{SymbolFilter, SymbolFilterConfig};
+
+const SYMBOLS: usize = 2_000;
+
+/// About `SYMBOLS` USDT, BTC and leveraged-token tickers plus the pairs
+/// the engine actually trades, with consistent triangles so the run
+/// measures the scan itself rather than opportunity bookkeeping.
+fn price_map(rng: &mut StdRng, skew: f64) -> PriceMap {
+    let mut prices = PriceMap::new();
+    for i in 0..SYMBOLS {
+        let quote = match i % 10 {
+            0..=6 => "USDT",
+            7 | 8 => "BTC",
+            _ => "UPUSDT",
+        };
+        let price = rng.gen_range(0.0001..50_000.0) * (1.0 + rng.gen_range(-skew..=skew));
+        prices.insert(format!("TOKEN{}{}", i, quote), Decimal::from_f64_retain(price).unwrap_or(Decimal::ONE).round_dp(8));
+    }
+    
+    let quotes = [
+        ("BTCUSDT", 64_000.0), ("ETHUSDT", 3_200.0), ("BNBUSDT", 580.0), ("ADAUSDT", 0.45),
+        ("DOTUSDT", 6.5), ("SOLUSDT", 145.0), ("ETHBTC", 0.05), ("BNBBTC", 580.0 / 64_000.0),
+        ("ADAETH", 0.45 / 3_200.0),
+    ];
+    for (symbol, price) in quotes {
+        let price: f64 = price * (1.0 + rng.gen_range(-skew..=skew));
+        prices.insert(symbol.to_string(), Decimal::from_f64_retain(price).unwrap().round_dp(8));
+    }
+    prices
+}
+
+fn scan(c: &mut Criterion) {
+    let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+    let mut rng = StdRng::seed_from_u64(42);
+    let binance = price_map(&mut rng, 0.0);
+    let bybit = price_map(&mut rng, 0.001);
+    
+    let engine = ArbitrageEngine::from_config(&Config::default());
+    c.bench_function("analyze_opportunities (PriceMap)", |b| {
+        b.iter(|| runtime.block_on(engine.analyze_opportunities(&binance, &bybit)).unwrap())
+    });
+    
+    let symbols = Arc::new(SymbolTable::new());
+    let binance_snapshot = PriceSnapshot::from_price_map(symbols.clone(), &binance);
+    let bybit_snapshot = PriceSnapshot::from_price_map(symbols.clone(), &bybit);
+    let engine = ArbitrageEngine::from_config(&Config::default());
+    c.bench_function("analyze_opportunities (PriceSnapshot)", |b| {
+        b.iter(|| runtime.block_on(engine.analyze_opportunities(&binance_snapshot, &bybit_snapshot)).unwrap())
+    });
+    
+    let filter = SymbolFilter::new(SymbolFilterConfig::default());
+    c.bench_function("symbol filter (defaults)", |b| b.iter(|| filter.filter("Binance", binance.clone())));
+    
+    let filter = SymbolFilter::new(SymbolFilterConfig {
+        allowlist: vec!["*USDT".to_string(), "*BTC".to_string(), "ETH*".to_string()],
+        blacklist: vec!["TOKEN1*".to_string()],
+        ..SymbolFilterConfig::default()
+    });
+    c.bench_function("symbol filter (allowlist + blacklist)", |b| b.iter(|| filter.filter("Binance", binance.clone())));
+    c.bench_function("symbol filter (allowlist + blacklist, snapshot)", |b| {
+        b.iter(|| {
+            let mut snapshot = binance_snapshot.clone();
+            filter.filter_snapshot("Binance", &mut snapshot);
+            snapshot
+        })
+    });
+    
+    c.bench_function("price map clone", |b| b.iter(|| binance.clone()));
+    c.bench_function("price snapshot clone", |b| b.iter(|| binance_snapshot.clone()));
+    c.bench_function("price snapshot fill (known symbols)", |b| {
+        b.iter(|| {
+            let mut snapshot = PriceSnapshot::new(symbols.clone());
+            for (symbol, price) in &binance {
+                snapshot.insert(symbol, *price);
+            }
+            snapshot
+        })
+    });
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
@@ -0,0 +1,647 @@
+use crate::arbitrage::breaker::CircuitBreakers;
+use crate::arbitrage::kill_switch::KillSwitch;
+use crate::arbitrage::outage::OutageDetector;
+use crate::arbitrage::queue::OpportunityQueue;
+use crate::arbitrage::price_cache::PriceCache;
+use crate::control::{BotControl, PauseScope};
+use crate::exchanges::budget::{self, RequestBudget};
+use crate::exchanges::dns::{self, DnsCache};
+use crate::exchanges::errors::{self, ErrorCounters};
+use crate::exchanges::reconnect::{self, StreamHealth};
+use crate::exchanges::ExchangeClient;
+use crate::health::HealthState;
+use crate::heatmap;
+use crate::metrics::MarketMetrics;
+use crate::portfolio::PortfolioStore;
+use crate::replay::SnapshotStore;
+use crate::report::ReportStore;
+use crate::secrets::SecretString;
+use crate::slo::SloMonitor;
+use anyhow::Result;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::info;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// Small HTTP server exposing operational endpoints:
+/// `/healthz` (liveness) and `/readyz` (readiness), both returning the
+/// JSON health report with 200 or 503, `/metrics` (Prometheus text) when
+/// enabled, plus the `/control/*` API, including the per-endpoint error
+/// counts on `/control/errors` and each exchange client's health on
+/// `/control/health`, the market data SLO alarm on `/control/slo`, the
+/// depth streams' reconnection state on `/market/depth-streams`, and the
+/// opportunity heatmap on
+/// `/opportunities/heatmap` while market snapshots are recorded.
+///
+/// `/control/*` requires `Authorization: Bearer <token>` once a control
+/// token is set; without one it is only served on a loopback address.
+pub struct AdminServer {
+    addr: SocketAddr,
+    health: Arc<HealthState>,
+    max_scan_age: chrono::Duration,
+    control: Option<Arc<BotControl>>,
+    portfolio: Option<PortfolioStore>,
+    reports: Option<ReportStore>,
+    snapshots: Option<SnapshotStore>,
+    breakers: Option<Arc<CircuitBreakers>>,
+    kill_switch: Option<Arc<KillSwitch>>,
+    outages: Option<Arc<OutageDetector>>,
+    market_metrics: Option<Arc<MarketMetrics>>,
+    dns_caches: Vec<Arc<DnsCache>>,
+    request_budgets: Vec<Arc<RequestBudget>>,
+    error_counters: Vec<Arc<ErrorCounters>>,
+    depth_streams: Vec<Arc<StreamHealth>>,
+    exchanges: Vec<ExchangeClient>,
+    prices: Option<Arc<PriceCache>>,
+    execution_queue: Option<Arc<OpportunityQueue>>,
+    slo: Option<Arc<SloMonitor>>,
+    control_token: Option<SecretString>,
+    metrics: bool,
+}
+
+impl AdminServer {
+    pub fn new(addr: SocketAddr, health: Arc<HealthState>, max_scan_age: chrono::Duration) -> Self {
+        Self { addr, health, max_scan_age, control: None, portfolio: None, reports: None, snapshots: None, breakers: None, kill_switch: None, outages: None, market_metrics: None, dns_caches: Vec::new(), request_budgets: Vec::new(), error_counters: Vec::new(), depth_streams: Vec::new(), exchanges: Vec::new(), prices: None, execution_queue: None, slo: None, control_token: None, metrics: false }
+    }
+    
+    pub fn with_control(mut self, control: Arc<BotControl>) -> Self {
+        self.control = Some(control);
+        self
+    }
+    
+    pub fn with_portfolio(mut self, portfolio: PortfolioStore) -> Self {
+        self.portfolio = Some(portfolio);
+        self
+    }
+    
+    pub fn with_reports(mut self, reports: ReportStore) -> Self {
+        self.reports = Some(reports);
+        self
+    }
+    
+    pub fn with_snapshots(mut self, snapshots: SnapshotStore) -> Self {
+        self.snapshots = Some(snapshots);
+        self
+    }
+    
+    pub fn with_breakers(mut self, breakers: Arc<CircuitBreakers>) -> Self {
+        self.breakers = Some(breakers);
+        self
+    }
+    
+    pub fn with_kill_switch(mut self, kill_switch: Arc<KillSwitch>) -> Self {
+        self.kill_switch = Some(kill_switch);
+        self
+    }
+    
+    pub fn with_outages(mut self, outages: Arc<OutageDetector>) -> Self {
+        self.outages = Some(outages);
+        self
+    }
+    
+    /// Opportunity and execution series appended to `/metrics`.
+    pub fn with_market_metrics(mut self, metrics: Arc<MarketMetrics>) -> Self {
+        self.market_metrics = Some(metrics);
+        self
+    }
+    
+    /// DNS lookup and host latency series appended to `/metrics`.
+    pub fn with_dns_caches(mut self, caches: Vec<Arc<DnsCache>>) -> Self {
+        self.dns_caches = caches;
+        self
+    }
+    
+    /// Request weight series appended to `/metrics`.
+    pub fn with_request_budgets(mut self, budgets: Vec<Arc<RequestBudget>>) -> Self {
+        self.request_budgets = budgets;
+        self
+    }
+    
+    /// Request error series appended to `/metrics` and served on `/control/errors`.
+    pub fn with_error_counters(mut self, counters: Vec<Arc<ErrorCounters>>) -> Self {
+        self.error_counters = counters;
+        self
+    }
+    
+    /// SLO alarm served on `/control/slo`, its series appended to `/metrics`.
+    pub fn with_slo(mut self, slo: Arc<SloMonitor>) -> Self {
+        self.slo = Some(slo);
+        self
+    }
+    
+    /// Depth stream series appended to `/metrics` and served on `/market/depth-streams`.
+    pub fn with_depth_streams(mut self, streams: Vec<Arc<StreamHealth>>) -> Self {
+        self.depth_streams = streams;
+        self
+    }
+    
+    /// Clients whose health is served on `/control/health`.
+    pub fn with_exchanges(mut self, exchanges: Vec<ExchangeClient>) -> Self {
+        self.exchanges = exchanges;
+        self
+    }
+    
+    /// Per-exchange price cache served with its data age on `/market/price-age`.
+    pub fn with_price_cache(mut self, prices: Arc<PriceCache>) -> Self {
+        self.prices = Some(prices);
+        self
+    }
+    
+    /// Execution queue depth and throughput series appended to `/metrics`.
+    pub fn with_execution_queue(mut self, queue: Arc<OpportunityQueue>) -> Self {
+        self.execution_queue = Some(queue);
+        self
+    }
+    
+    /// Bearer token every `/control/*` request has to carry.
+    pub fn with_control_token(mut self, token: SecretString) -> Self {
+        self.control_token = Some(token);
+        self
+    }
+    
+    pub fn with_metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled;
+        self
+    }
+    
+    pub async fn serve(self) -> Result<()> {
+        let state = Arc::new(self);
+        let make_svc = {
+            let state = state.clone();
+            make_service_fn(move |_| {
+                let state = state.clone();
+                async move {
+                    Ok::<_, Infallible>(service_fn(move |req| {
+                        let state = state.clone();
+                        async move { Ok::<_, Infallible>(state.route(&req)) }
+                    }))
+                }
+            })
+        };
+        
+        let server = Server::try_bind(&state.addr)
+            .map_err(|e| anyhow::anyhow!("Failed to bind admin server on {}: {}", state.addr, e))?;
+        info!("Admin server listening on {}", state.addr);
+        server.serve(make_svc).await?;
+        Ok(())
+    }
+    
+    fn route(&self, req: &Request<Body>) -> Response<Body> {
+        if req.uri().path().starts_with("/control/") {
+            if let Some(rejection) = self.reject_control(req) {
+                return rejection;
+            }
+        }
+        
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, "/healthz") => {
+                let healthy = self.health.is_live(self.max_scan_age);
+                self.report_response(healthy)
+            }
+            (&Method::GET, "/readyz") => {
+                let ready = self.health.is_ready(self.max_scan_age);
+                self.report_response(ready)
+            }
+            (&Method::GET, "/metrics") if self.metrics => {
+                let mut body = self.health.prometheus_metrics(self.max_scan_age);
+                if let Some(metrics) = &self.market_metrics {
+                    body.push_str(&metrics.prometheus_metrics());
+                }
+                if !self.dns_caches.is_empty() {
+                    body.push_str(&dns::prometheus_metrics(&self.dns_caches));
+                }
+                if !self.request_budgets.is_empty() {
+                    body.push_str(&budget::prometheus_metrics(&self.request_budgets));
+                }
+                if !self.error_counters.is_empty() {
+                    body.push_str(&errors::prometheus_metrics(&self.error_counters));
+                }
+                if !self.depth_streams.is_empty() {
+                    body.push_str(&reconnect::prometheus_metrics(&self.depth_streams));
+                }
+                if let Some(slo) = &self.slo {
+                    body.push_str(&slo.prometheus_metrics());
+                }
+                if let Some(queue) = &self.execution_queue {
+                    body.push_str(&queue.prometheus_metrics());
+                }
+                Response::builder()
+                    .status(StatusCode::OK)
+                    .header("Content-Type", "text/plain; version=0.0.4")
+                    .body(Body::from(body))
+                    .unwrap_or_else(|_| Response::new(Body::empty()))
+            }
+            (&Method::GET, "/portfolio/equity") => self.equity_curve(req),
+            (&Method::GET, "/execution/slippage") => self.slippage_stats(req),
+            (&Method::GET, "/market/price-age") => self.price_ages(req),
+            (&Method::GET, "/market/depth-streams") if !self.depth_streams.is_empty() => {
+                let statuses: Vec<_> = self.depth_streams.iter().map(|stream| stream.status()).collect();
+                json_response(StatusCode::OK, &statuses)
+            }
+            (&Method::GET, "/opportunities/heatmap") => self.opportunity_heatmap(req),
+            (_, path) if path.starts_with("/control/breakers") => self.route_breakers(req),
+            (_, path) if path.starts_with("/control/kill-switch") => self.route_kill_switch(req),
+            (&Method::GET, "/control/errors") if !self.error_counters.is_empty() => self.error_counts(req),
+            (&Method::GET, "/control/health") if !self.exchanges.is_empty() => self.exchange_health(req),
+            (&Method::GET, "/control/slo") => match &self.slo {
+                Some(slo) => json_response(StatusCode::OK, &slo.status()),
+                None => not_found(),
+            },
+            (&Method::GET, "/control/outages") => match &self.outages {
+                Some(outages) => json_response(StatusCode::OK, &outages.states()),
+                None => not_found(),
+            },
+            (_, path) if path.starts_with("/control/") => self.route_control(req),
+            _ => not_found(),
+        }
+    }
+    
+    /// The response refusing a `/control/*` request, or `None` when it may
+    /// go through.
+    fn reject_control(&self, req: &Request<Body>) -> Option<Response<Body>> {
+        let token = match &self.control_token {
+            Some(token) => token,
+            None if self.addr.ip().is_loopback() => return None,
+            None => return Some(json_response(
+                StatusCode::FORBIDDEN,
+                &serde_json::json!({ "error": "the control API needs a token when the admin server is not on a loopback address" }),
+            )),
+        };
+        
+        let presented = req.headers()
+            .get(hyper::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match presented {
+            Some(presented) if constant_time_eq(presented.as_bytes(), token.expose().as_bytes()) => None,
+            _ => {
+                let mut response = json_response(StatusCode::UNAUTHORIZED, &serde_json::json!({ "error": "unauthorized" }));
+                response.headers_mut().insert(hyper::header::WWW_AUTHENTICATE, hyper::header::HeaderValue::from_static("Bearer"));
+                Some(response)
+            }
+        }
+    }
+    
+    fn route_control(&self, req: &Request<Body>) -> Response<Body> {
+        let control = match &self.control {
+            Some(control) => control,
+            None => return not_found(),
+        };
+        
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, "/control/status") => json_response(StatusCode::OK, &control.status()),
+            (&Method::POST, action @ ("/control/pause" | "/control/resume")) => {
+                let scope = match query_param(req, "scope").unwrap_or_else(|| "all".to_string()).parse::<PauseScope>() {
+                    Ok(scope) => scope,
+                    Err(e) => return json_response(
+                        StatusCode::BAD_REQUEST,
+                        &serde_json::json!({ "error": e.to_string() }),
+                    ),
+                };
+                
+                if action == "/control/pause" {
+                    info!("Pausing {:?} via control API", scope);
+                    control.pause(scope);
+                } else {
+                    info!("Resuming {:?} via control API", scope);
+                    control.resume(scope);
+                }
+                json_response(StatusCode::OK, &control.status())
+            }
+            _ => not_found(),
+        }
+    }
+    
+    /// `GET /control/breakers` lists breaker states; `POST
+    /// /control/breakers/reset?exchange=` closes one (the global breaker
+    /// when no exchange is given).
+    fn route_breakers(&self, req: &Request<Body>) -> Response<Body> {
+        let breakers = match &self.breakers {
+            Some(breakers) => breakers,
+            None => return not_found(),
+        };
+        
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, "/control/breakers") => json_response(StatusCode::OK, &breakers.states()),
+            (&Method::POST, "/control/breakers/reset") => {
+                let exchange = query_param(req, "exchange");
+                info!("Resetting {} circuit breaker via control API", exchange.as_deref().unwrap_or("global"));
+                breakers.reset(exchange.as_deref());
+                json_response(StatusCode::OK, &breakers.states())
+            }
+            _ => not_found(),
+        }
+    }
+    
+    /// `GET /control/kill-switch` reports the daily loss kill switch;
+    /// `POST /control/kill-switch/reset` re-enables execution after it tripped.
+    fn route_kill_switch(&self, req: &Request<Body>) -> Response<Body> {
+        let kill_switch = match &self.kill_switch {
+            Some(kill_switch) => kill_switch,
+            None => return not_found(),
+        };
+        
+        match (req.method(), req.uri().path()) {
+            (&Method::GET, "/control/kill-switch") => json_response(StatusCode::OK, &kill_switch.status()),
+            (&Method::POST, "/control/kill-switch/reset") => {
+                info!("Resetting daily loss kill switch via control API");
+                kill_switch.reset();
+                json_response(StatusCode::OK, &kill_switch.status())
+            }
+            _ => not_found(),
+        }
+    }
+    
+    /// Equity curve for the last `hours` (default 24) of portfolio snapshots.
+    fn equity_curve(&self, req: &Request<Body>) -> Response<Body> {
+        let portfolio = match &self.portfolio {
+            Some(portfolio) => portfolio,
+            None => return not_found(),
+        };
+        
+        let hours = query_param(req, "hours").and_then(|h| h.parse::<i64>().ok()).unwrap_or(24);
+        match portfolio.equity_curve(chrono::Utc::now() - chrono::Duration::hours(hours)) {
+            Ok(curve) => json_response(StatusCode::OK, &curve),
+            Err(e) => json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &serde_json::json!({ "error": e.to_string() }),
+            ),
+        }
+    }
+    
+    /// Aggregate execution slippage over the last `hours` (default 24).
+    fn slippage_stats(&self, req: &Request<Body>) -> Response<Body> {
+        let reports = match &self.reports {
+            Some(reports) => reports,
+            None => return not_found(),
+        };
+        
+        let hours = query_param(req, "hours").and_then(|h| h.parse::<i64>().ok()).unwrap_or(24);
+        match reports.slippage_stats(chrono::Utc::now() - chrono::Duration::hours(hours)) {
+            Ok(stats) => json_response(StatusCode::OK, &stats),
+            Err(e) => json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &serde_json::json!({ "error": e.to_string() }),
+            ),
+        }
+    }
+    
+    /// Average net profit by path and UTC hour over the last `days` (default 7)
+    /// of market snapshots.
+    fn opportunity_heatmap(&self, req: &Request<Body>) -> Response<Body> {
+        let snapshots = match &self.snapshots {
+            Some(snapshots) => snapshots,
+            None => return not_found(),
+        };
+        
+        let days = query_param(req, "days").and_then(|d| d.parse::<u32>().ok()).unwrap_or(7);
+        match heatmap::load(snapshots, days, chrono::Utc::now()) {
+            Ok(heatmap) => json_response(StatusCode::OK, &heatmap),
+            Err(e) => json_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &serde_json::json!({ "error": e.to_string() }),
+            ),
+        }
+    }
+    
+    /// Age of every cached price, optionally filtered by `exchange` and `symbol`.
+    fn price_ages(&self, req: &Request<Body>) -> Response<Body> {
+        let prices = match &self.prices {
+            Some(prices) => prices,
+            None => return not_found(),
+        };
+        
+        let exchange = query_param(req, "exchange");
+        let symbol = query_param(req, "symbol");
+        let ages: Vec<_> = prices.ages(exchange.as_deref(), chrono::Utc::now()).into_iter()
+            .filter(|age| symbol.as_ref().is_none_or(|symbol| age.entry.symbol.eq_ignore_ascii_case(symbol)))
+            .collect();
+        json_response(StatusCode::OK, &ages)
+    }
+    
+    /// Failed requests by endpoint, kind and code, optionally of one `exchange`.
+    fn error_counts(&self, req: &Request<Body>) -> Response<Body> {
+        let exchange = query_param(req, "exchange");
+        let counts: Vec<_> = self.error_counters.iter()
+            .filter(|counters| exchange.as_ref().is_none_or(|exchange| counters.exchange().eq_ignore_ascii_case(exchange)))
+            .flat_map(|counters| counters.counts())
+            .collect();
+        json_response(StatusCode::OK, &counts)
+    }
+    
+    /// Health of each exchange client, optionally of one `exchange`.
+    fn exchange_health(&self, req: &Request<Body>) -> Response<Body> {
+        let exchange = query_param(req, "exchange");
+        let health: Vec<_> = self.exchanges.iter()
+            .filter(|client| exchange.as_ref().is_none_or(|exchange| client.name().eq_ignore_ascii_case(exchange)))
+            .map(ExchangeClient::health)
+            .collect();
+        json_response(StatusCode::OK, &health)
+    }
+    
+    fn report_response(&self, ok: bool) -> Response<Body> {
+        let status = if ok { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+        json_response(status, &self.health.report(self.max_scan_age))
+    }
+}
+
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    req.uri().query().and_then(|query| {
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.into_owned())
+    })
+}
+
+/// Compares without returning early, so response times do not reveal how
+/// much of a guessed token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+fn not_found() -> Response<Body> {
+    json_response(StatusCode::NOT_FOUND, &serde_json::json!({ "error": "not found" }))
+}
+
+fn json_response<T: serde::Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_string(body).unwrap_or_else(|_| "{}".to_string());
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body))
+        .unwrap_or_else(|_| Response::new(Body::empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn server(health: Arc<HealthState>) -> AdminServer {
+        AdminServer::new("127.0.0.1:0".parse().unwrap(), health, chrono::Duration::seconds(60))
+    }
+    
+    fn get(path: &str) -> Request<Body> {
+        Request::builder().method(Method::GET).uri(path).body(Body::empty()).unwrap()
+    }
+    
+    #[test]
+    fn test_probe_status_codes() {
+        let health = Arc::new(HealthState::new());
+        let admin = server(health.clone());
+        
+        assert_eq!(admin.route(&get("/healthz")).status(), StatusCode::OK);
+        assert_eq!(admin.route(&get("/readyz")).status(), StatusCode::SERVICE_UNAVAILABLE);
+        
+        health.record_exchange_success("Binance");
+        health.record_scan();
+        assert_eq!(admin.route(&get("/readyz")).status(), StatusCode::OK);
+        
+        assert_eq!(admin.route(&get("/unknown")).status(), StatusCode::NOT_FOUND);
+        assert_eq!(admin.route(&get("/metrics")).status(), StatusCode::NOT_FOUND);
+        assert_eq!(admin.route(&get("/control/status")).status(), StatusCode::NOT_FOUND);
+    }
+    
+    #[test]
+    fn test_metrics_when_enabled() {
+        let admin = server(Arc::new(HealthState::new())).with_metrics(true);
+        let response = admin.route(&get("/metrics"));
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers()["Content-Type"].to_str().unwrap().starts_with("text/plain"));
+    }
+    
+    #[tokio::test]
+    async fn test_price_age_filters_by_exchange_and_symbol() {
+        let prices = Arc::new(PriceCache::new());
+        let now = chrono::Utc::now();
+        prices.observe("Binance", "BTCUSDT", rust_decimal::Decimal::from(64000), None, now);
+        prices.observe("Bybit", "BTCUSDT", rust_decimal::Decimal::from(64010), Some(now - chrono::Duration::seconds(5)), now);
+        prices.observe("Bybit", "ETHUSDT", rust_decimal::Decimal::from(3000), None, now);
+        let admin = server(Arc::new(HealthState::new())).with_price_cache(prices);
+        
+        let response = admin.route(&get("/market/price-age?exchange=bybit&symbol=BTCUSDT"));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let ages: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(ages.len(), 1);
+        assert_eq!(ages[0]["exchange"], "Bybit");
+        assert!(ages[0]["age_ms"].as_i64().unwrap() >= 5000);
+    }
+    
+    #[tokio::test]
+    async fn test_error_counts_filter_by_exchange() {
+        let binance = Arc::new(ErrorCounters::new("Binance"));
+        let bybit = Arc::new(ErrorCounters::new("Bybit"));
+        binance.record_transport("/api/v3/ticker/price");
+        bybit.record_transport("/v5/market/tickers");
+        let admin = server(Arc::new(HealthState::new())).with_error_counters(vec![binance, bybit]);
+        
+        let response = admin.route(&get("/control/errors?exchange=bybit"));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let counts: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(counts.len(), 1);
+        assert_eq!(counts[0]["endpoint"], "/v5/market/tickers");
+        assert_eq!(counts[0]["kind"], "network");
+        assert_eq!(counts[0]["count"], 1);
+    }
+    
+    #[tokio::test]
+    async fn test_exchange_health_filters_by_exchange() {
+        use crate::exchanges::simulated::{MatchingEngine, SimulatedExchange};
+        let exchanges = ["Binance", "Bybit"].into_iter()
+            .map(|name| ExchangeClient::Simulated(Arc::new(SimulatedExchange::new(name, MatchingEngine::new()))))
+            .collect();
+        let admin = server(Arc::new(HealthState::new())).with_exchanges(exchanges);
+        
+        let response = admin.route(&get("/control/health?exchange=bybit"));
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let health: Vec<serde_json::Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0]["exchange"], "Bybit");
+        assert_eq!(health[0]["requests"], 0);
+        assert!(health[0]["last_success"].is_null());
+    }
+    
+    #[test]
+    fn test_breaker_status_and_reset() {
+        let breakers = Arc::new(CircuitBreakers::new(1, 5));
+        breakers.record_exchange_failure("Bybit", "HTTP 503");
+        let admin = server(Arc::new(HealthState::new())).with_breakers(breakers.clone());
+        let post = |path: &str| Request::builder().method(Method::POST).uri(path).body(Body::empty()).unwrap();
+        
+        assert_eq!(admin.route(&get("/control/breakers")).status(), StatusCode::OK);
+        assert!(breakers.is_exchange_open("Bybit"));
+        assert_eq!(admin.route(&post("/control/breakers/reset?exchange=Bybit")).status(), StatusCode::OK);
+        assert!(!breakers.is_exchange_open("Bybit"));
+    }
+    
+    #[test]
+    fn test_kill_switch_status_and_reset() {
+        let kill_switch = Arc::new(KillSwitch::new(rust_decimal::Decimal::from(50)));
+        kill_switch.record(rust_decimal::Decimal::from(-80));
+        let admin = server(Arc::new(HealthState::new())).with_kill_switch(kill_switch.clone());
+        let post = |path: &str| Request::builder().method(Method::POST).uri(path).body(Body::empty()).unwrap();
+        
+        assert_eq!(admin.route(&get("/control/kill-switch")).status(), StatusCode::OK);
+        assert_eq!(admin.route(&get("/control/kill-switch/reset")).status(), StatusCode::NOT_FOUND);
+        assert!(kill_switch.is_tripped());
+        assert_eq!(admin.route(&post("/control/kill-switch/reset")).status(), StatusCode::OK);
+        assert!(!kill_switch.is_tripped());
+    }
+    
+    #[test]
+    fn test_control_pause_and_resume() {
+        let control = Arc::new(BotControl::default());
+        let admin = server(Arc::new(HealthState::new())).with_control(control.clone());
+        let post = |path: &str| Request::builder().method(Method::POST).uri(path).body(Body::empty()).unwrap();
+        
+        assert_eq!(admin.route(&post("/control/pause?scope=execution")).status(), StatusCode::OK);
+        assert!(!control.is_scanning_paused());
+        assert!(!control.status().execution_allowed);
+        
+        assert_eq!(admin.route(&post("/control/pause")).status(), StatusCode::OK);
+        assert!(control.is_scanning_paused());
+        
+        assert_eq!(admin.route(&post("/control/resume?scope=all")).status(), StatusCode::OK);
+        assert!(control.status().execution_allowed);
+        
+        assert_eq!(admin.route(&post("/control/pause?scope=bogus")).status(), StatusCode::BAD_REQUEST);
+        assert_eq!(admin.route(&get("/control/status")).status(), StatusCode::OK);
+    }
+    
+    #[test]
+    fn test_control_requires_token() {
+        let control = Arc::new(BotControl::default());
+        let health = Arc::new(HealthState::new());
+        let with_auth = |path: &str, auth: &str| Request::builder()
+            .method(Method::POST)
+            .uri(path)
+            .header("Authorization", auth)
+            .body(Body::empty())
+            .unwrap();
+        
+        let exposed = AdminServer::new("0.0.0.0:0".parse().unwrap(), health.clone(), chrono::Duration::seconds(60))
+            .with_control(control.clone());
+        assert_eq!(exposed.route(&get("/control/status")).status(), StatusCode::FORBIDDEN);
+        assert_eq!(exposed.route(&get("/healthz")).status(), StatusCode::OK);
+        
+        let admin = AdminServer::new("0.0.0.0:0".parse().unwrap(), health, chrono::Duration::seconds(60))
+            .with_control(control.clone())
+            .with_control_token(SecretString::new("s3cret"));
+        let response = admin.route(&get("/control/status"));
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers()["WWW-Authenticate"], "Bearer");
+        assert_eq!(admin.route(&with_auth("/control/pause", "Bearer wrong")).status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(admin.route(&with_auth("/control/kill-switch/reset", "s3cret")).status(), StatusCode::UNAUTHORIZED);
+        assert!(!control.is_scanning_paused());
+        
+        assert_eq!(admin.route(&with_auth("/control/pause", "Bearer s3cret")).status(), StatusCode::OK);
+        assert!(control.is_scanning_paused());
+        
+        let local = server(Arc::new(HealthState::new())).with_control(control).with_control_token(SecretString::new("s3cret"));
+        assert_eq!(local.route(&get("/control/status")).status(), StatusCode::UNAUTHORIZED);
+    }
+}
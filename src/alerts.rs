@@ -0,0 +1,357 @@
+use crate::arbitrage::ArbitrageOpportunity;
+use crate::currency::CurrencyConverter;
+use crate::notify::{
+    DiscordNotifier, EmailNotifier, FileNotifier, LogNotifier, Notification, Notifier, RateLimitConfig, RateLimited,
+    TelegramNotifier, WebhookNotifier,
+};
+use crate::summary::SmtpConfig;
+use crate::symbols::glob_match;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use log::warn;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+/// Name of the channel that is always available and writes to the log.
+pub const LOG_CHANNEL: &str = "log";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertConfig {
+    pub enabled: bool,
+    /// Never execute, whatever `trading.enable_execution` says, and detect
+    /// down to the lowest rule threshold
+    pub watch_only: bool,
+    /// Minimum time between two alerts for the same rule, venue and legs
+    pub cooldown_seconds: u64,
+    /// Threshold for opportunities no rule matches; unset sends no alert for them
+    pub default_min_net_profit_percentage: Option<Decimal>,
+    pub default_channel: String,
+    pub channels: HashMap<String, AlertChannel>,
+    pub rules: Vec<AlertRule>,
+    /// Limits applied to every channel, `log` included
+    pub rate_limit: RateLimitConfig,
+}
+
+impl Default for AlertConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            watch_only: false,
+            cooldown_seconds: 60,
+            default_min_net_profit_percentage: None,
+            default_channel: LOG_CHANNEL.to_string(),
+            channels: HashMap::new(),
+            rules: Vec::new(),
+            rate_limit: RateLimitConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertChannel {
+    Log,
+    /// POSTs `{"text": ..., "opportunity": ...}`, which chat webhooks such
+    /// as Slack's display as a message
+    Webhook { url: String },
+    /// Appends one JSON object per line
+    File { path: String },
+    /// Messages from a Telegram bot, whose token is read from `bot_token_env`
+    Telegram {
+        chat_id: String,
+        #[serde(default = "default_telegram_token_env")]
+        bot_token_env: String,
+        #[serde(default = "default_telegram_api_url")]
+        api_url: String,
+    },
+    /// A Discord channel webhook
+    Discord { url: String },
+    Email(SmtpConfig),
+}
+
+fn default_telegram_token_env() -> String {
+    "TELEGRAM_BOT_TOKEN".to_string()
+}
+
+fn default_telegram_api_url() -> String {
+    "https://api.telegram.org".to_string()
+}
+
+impl AlertChannel {
+    pub fn notifier(&self, http: &reqwest::Client) -> Box<dyn Notifier> {
+        match self {
+            AlertChannel::Log => Box::new(LogNotifier),
+            AlertChannel::Webhook { url } => Box::new(WebhookNotifier::new(url, http.clone())),
+            AlertChannel::File { path } => Box::new(FileNotifier::new(path)),
+            AlertChannel::Telegram { chat_id, bot_token_env, api_url } => {
+                Box::new(TelegramNotifier::new(api_url, bot_token_env, chat_id, http.clone()))
+            }
+            AlertChannel::Discord { url } => Box::new(DiscordNotifier::new(url, http.clone())),
+            AlertChannel::Email(smtp) => Box::new(EmailNotifier::new(smtp.clone())),
+        }
+    }
+}
+
+/// Alert threshold for one path or pair. `symbols` lists the leg symbols
+/// in any order: the three legs of a triangle, or the one symbol of a
+/// cross-exchange pair.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub symbols: Vec<String>,
+    /// Venue pattern such as `Binance` or `Binance->*`; any venue when unset
+    #[serde(default)]
+    pub exchange: Option<String>,
+    pub min_net_profit_percentage: Decimal,
+    /// Channel from `channels`, or `log`; `default_channel` when unset
+    #[serde(default)]
+    pub channel: Option<String>,
+}
+
+impl AlertRule {
+    fn matches(&self, opportunity: &ArbitrageOpportunity) -> bool {
+        let rule_symbols: BTreeSet<&str> = self.symbols.iter().map(String::as_str).collect();
+        let legs: BTreeSet<&str> = opportunity.execution_steps.iter().map(|s| s.symbol.as_str()).collect();
+        rule_symbols == legs && self.exchange.as_deref().is_none_or(|p| glob_match(p, &opportunity.exchange))
+    }
+}
+
+impl AlertConfig {
+    pub fn validate(&self) -> Result<()> {
+        let channel_exists = |name: &str| name == LOG_CHANNEL || self.channels.contains_key(name);
+        if !channel_exists(&self.default_channel) {
+            return Err(anyhow::anyhow!("alerts.default_channel '{}' is not configured", self.default_channel));
+        }
+        for (name, channel) in &self.channels {
+            match channel {
+                AlertChannel::Webhook { url } | AlertChannel::Discord { url } | AlertChannel::Telegram { api_url: url, .. } => {
+                    url::Url::parse(url).map_err(|e| anyhow::anyhow!("Invalid URL for alert channel '{}': {}", name, e))?;
+                }
+                AlertChannel::Email(smtp) => smtp.validate().with_context(|| format!("Invalid alert channel '{}'", name))?,
+                AlertChannel::Log | AlertChannel::File { .. } => {}
+            }
+            if let AlertChannel::Telegram { chat_id, .. } = channel {
+                if chat_id.is_empty() {
+                    return Err(anyhow::anyhow!("Telegram alert channel '{}' needs a chat_id", name));
+                }
+            }
+        }
+        if self.rate_limit.max_per_minute == 0 {
+            return Err(anyhow::anyhow!("alerts.rate_limit.max_per_minute must be at least 1"));
+        }
+        for rule in &self.rules {
+            if rule.symbols.is_empty() {
+                return Err(anyhow::anyhow!("alerts.rules entries need at least one symbol"));
+            }
+            if let Some(channel) = rule.channel.as_deref().filter(|c| !channel_exists(c)) {
+                return Err(anyhow::anyhow!("Alert rule for {} uses unknown channel '{}'", rule.symbols.join(","), channel));
+            }
+        }
+        Ok(())
+    }
+
+    /// Lowest threshold any alert fires at, which watch-only mode detects down to.
+    pub fn lowest_threshold(&self) -> Option<Decimal> {
+        self.rules.iter()
+            .map(|r| r.min_net_profit_percentage)
+            .chain(self.default_min_net_profit_percentage)
+            .min()
+    }
+}
+
+/// Sends opportunities that clear their path's threshold to the path's
+/// channel, at most once per cooldown.
+pub struct AlertNotifier {
+    config: AlertConfig,
+    channels: HashMap<String, RateLimited>,
+    last_sent: DashMap<String, DateTime<Utc>>,
+    currency: Arc<CurrencyConverter>,
+}
+
+impl AlertNotifier {
+    pub fn new(config: AlertConfig) -> Self {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(5))
+            .build()
+            .unwrap_or_default();
+        let channels = config.channels.iter()
+            .map(|(name, channel)| (name.as_str(), channel))
+            .chain([(LOG_CHANNEL, &AlertChannel::Log)])
+            .map(|(name, channel)| (name.to_string(), RateLimited::new(name, channel.notifier(&http), &config.rate_limit)))
+            .collect();
+        Self { config, channels, last_sent: DashMap::new(), currency: Arc::new(CurrencyConverter::default()) }
+    }
+
+    /// Shows alert amounts in the home currency of `currency`.
+    pub fn with_currency(mut self, currency: Arc<CurrencyConverter>) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    /// Channel name for each opportunity due an alert at `now`, marking it
+    /// sent. The first matching rule decides.
+    pub fn due<'a>(&self, opportunities: &'a [ArbitrageOpportunity], now: DateTime<Utc>) -> Vec<(String, &'a ArbitrageOpportunity)> {
+        let cooldown = Duration::seconds(self.config.cooldown_seconds as i64);
+        let mut due = Vec::new();
+        for opportunity in opportunities {
+            let (rule_index, threshold, channel) = match self.config.rules.iter().position(|r| r.matches(opportunity)) {
+                Some(i) => {
+                    let rule = &self.config.rules[i];
+                    (Some(i), rule.min_net_profit_percentage, rule.channel.as_deref())
+                }
+                None => match self.config.default_min_net_profit_percentage {
+                    Some(threshold) => (None, threshold, None),
+                    None => continue,
+                },
+            };
+            if opportunity.net_profit_percentage < threshold {
+                continue;
+            }
+
+            let mut legs: Vec<&str> = opportunity.execution_steps.iter().map(|s| s.symbol.as_str()).collect();
+            legs.dedup();
+            let key = format!("{:?}|{}|{}", rule_index, opportunity.exchange, legs.join(","));
+            if self.last_sent.get(&key).is_some_and(|at| now.signed_duration_since(*at) < cooldown) {
+                continue;
+            }
+            self.last_sent.insert(key, now);
+            due.push((channel.unwrap_or(&self.config.default_channel).to_string(), opportunity));
+        }
+        due
+    }
+
+    /// Sends the alerts due for this scan. Delivery failures are logged and
+    /// never interrupt scanning.
+    pub async fn notify(&self, opportunities: &[ArbitrageOpportunity]) {
+        for (channel, opportunity) in self.due(opportunities, Utc::now()) {
+            self.send(&channel, &Notification::opportunity(opportunity, &self.currency)).await;
+        }
+    }
+
+    /// Sends a message about the bot itself to the default channel.
+    /// Delivery failures are logged.
+    pub async fn notify_text(&self, text: &str) {
+        self.send(&self.config.default_channel, &Notification::text(text)).await;
+    }
+
+    async fn send(&self, channel: &str, notification: &Notification) {
+        let Some(notifier) = self.channels.get(channel).or_else(|| self.channels.get(LOG_CHANNEL)) else { return };
+        if let Err(e) = notifier.send(notification).await {
+            warn!("Failed to send alert to channel '{}': {:#}", channel, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::risk::Strategy;
+    use crate::arbitrage::ExecutionStep;
+    use crate::exchanges::OrderSide;
+
+    fn opportunity(exchange: &str, symbols: &[&str], net_profit: &str) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            exchange: exchange.to_string(),
+            strategy: Strategy::Triangular,
+            path: Vec::new(),
+            profit_percentage: Decimal::from_str_exact(net_profit).unwrap(),
+            net_profit_percentage: Decimal::from_str_exact(net_profit).unwrap(),
+            required_amount: Decimal::from(1000),
+            estimated_profit_usd: Decimal::ONE,
+            risk_score: 0.0,
+            execution_steps: symbols.iter().map(|symbol| ExecutionStep {
+                exchange: exchange.to_string(),
+                action: String::new(),
+                symbol: symbol.to_string(),
+                side: OrderSide::Buy,
+                quantity: Decimal::ONE,
+                expected_price: Decimal::ONE,
+                fees: Decimal::ZERO,
+                client_order_id: String::new(),
+            }).collect(),
+            leg_quotes: Vec::new(),
+            timestamp: Utc::now(),
+            scan_id: 0,
+        }
+    }
+
+    fn rule(symbols: &[&str], exchange: Option<&str>, threshold: &str, channel: Option<&str>) -> AlertRule {
+        AlertRule {
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+            exchange: exchange.map(str::to_string),
+            min_net_profit_percentage: Decimal::from_str_exact(threshold).unwrap(),
+            channel: channel.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_per_path_thresholds_and_channels() {
+        let config = AlertConfig {
+            enabled: true,
+            channels: HashMap::from([("desk".to_string(), AlertChannel::File { path: "alerts.jsonl".to_string() })]),
+            rules: vec![
+                rule(&["ETHUSDT", "BTCUSDT", "ETHBTC"], Some("Binance"), "0.2", Some("desk")),
+                rule(&["SOLUSDT"], None, "0.5", None),
+            ],
+            ..AlertConfig::default()
+        };
+        config.validate().unwrap();
+        assert_eq!(config.lowest_threshold(), Some(Decimal::from_str_exact("0.2").unwrap()));
+
+        let notifier = AlertNotifier::new(config);
+        let opportunities = vec![
+            opportunity("Binance", &["BTCUSDT", "ETHBTC", "ETHUSDT"], "0.25"),
+            opportunity("Bybit", &["BTCUSDT", "ETHBTC", "ETHUSDT"], "0.25"),
+            opportunity("Binance->Bybit", &["SOLUSDT", "SOLUSDT"], "0.4"),
+            opportunity("Bybit->Binance", &["SOLUSDT", "SOLUSDT"], "0.6"),
+        ];
+        let due = notifier.due(&opportunities, Utc::now());
+        let sent: Vec<(&str, &str)> = due.iter().map(|(c, o)| (c.as_str(), o.exchange.as_str())).collect();
+        assert_eq!(sent, vec![("desk", "Binance"), ("log", "Bybit->Binance")]);
+    }
+
+    #[test]
+    fn test_cooldown_suppresses_repeats() {
+        let config = AlertConfig {
+            default_min_net_profit_percentage: Some(Decimal::ZERO),
+            cooldown_seconds: 60,
+            ..AlertConfig::default()
+        };
+        let notifier = AlertNotifier::new(config);
+        let opportunities = vec![opportunity("Binance", &["BTCUSDT", "ETHBTC", "ETHUSDT"], "0.1")];
+        let now = Utc::now();
+        assert_eq!(notifier.due(&opportunities, now).len(), 1);
+        assert!(notifier.due(&opportunities, now + Duration::seconds(30)).is_empty());
+        assert_eq!(notifier.due(&opportunities, now + Duration::seconds(61)).len(), 1);
+    }
+
+    #[test]
+    fn test_unknown_channel_is_rejected() {
+        let config = AlertConfig {
+            rules: vec![rule(&["BTCUSDT"], None, "0.1", Some("pager"))],
+            ..AlertConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_file_channel_appends_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("alerts.jsonl");
+        let config = AlertConfig {
+            default_min_net_profit_percentage: Some(Decimal::ZERO),
+            default_channel: "file".to_string(),
+            channels: HashMap::from([("file".to_string(), AlertChannel::File { path: path.display().to_string() })]),
+            ..AlertConfig::default()
+        };
+        let notifier = AlertNotifier::new(config);
+        notifier.notify(&[
+            opportunity("Binance", &["BTCUSDT", "ETHBTC", "ETHUSDT"], "0.1"),
+            opportunity("Bybit", &["BTCUSDT", "ETHBTC", "ETHUSDT"], "0.1"),
+        ]).await;
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 2);
+    }
+}
@@ -0,0 +1,189 @@
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::warn;
+use serde::Serialize;
+
+#[derive(Debug, Clone)]
+struct BreakerCounters {
+    failure_count: u32,
+    last_failure: Option<DateTime<Utc>>,
+    last_reason: Option<String>,
+}
+
+/// Trips after `threshold` consecutive failures and stays open until
+/// `reset_timeout` has passed since the last one.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    counters: std::sync::Mutex<BreakerCounters>,
+    threshold: u32,
+    reset_timeout: chrono::Duration,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerState {
+    pub name: String,
+    pub open: bool,
+    pub failure_count: u32,
+    pub last_failure: Option<DateTime<Utc>>,
+    pub last_reason: Option<String>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, reset_timeout_minutes: i64) -> Self {
+        Self {
+            counters: std::sync::Mutex::new(BreakerCounters {
+                failure_count: 0,
+                last_failure: None,
+                last_reason: None,
+            }),
+            threshold,
+            reset_timeout: chrono::Duration::minutes(reset_timeout_minutes),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        let counters = self.counters.lock().unwrap();
+        if counters.failure_count >= self.threshold {
+            if let Some(last_failure) = counters.last_failure {
+                let elapsed = Utc::now().signed_duration_since(last_failure);
+                return elapsed < self.reset_timeout;
+            }
+        }
+        false
+    }
+
+    pub fn record_failure(&self, reason: &str) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.failure_count += 1;
+        counters.last_failure = Some(Utc::now());
+        counters.last_reason = Some(reason.to_string());
+    }
+
+    pub fn reset(&self) {
+        let mut counters = self.counters.lock().unwrap();
+        counters.failure_count = 0;
+        counters.last_failure = None;
+        counters.last_reason = None;
+    }
+
+    pub fn state(&self, name: &str) -> BreakerState {
+        let open = self.is_open();
+        let counters = self.counters.lock().unwrap();
+        BreakerState {
+            name: name.to_string(),
+            open,
+            failure_count: counters.failure_count,
+            last_failure: counters.last_failure,
+            last_reason: counters.last_reason.clone(),
+        }
+    }
+}
+
+/// The engine-wide breaker, tripped by execution failures and abnormal
+/// slippage, plus one breaker per exchange tripped by that venue's API
+/// errors and order rejections. Shared with the admin server.
+#[derive(Debug)]
+pub struct CircuitBreakers {
+    global: CircuitBreaker,
+    exchanges: DashMap<String, CircuitBreaker>,
+    threshold: u32,
+    reset_timeout_minutes: i64,
+}
+
+impl CircuitBreakers {
+    pub fn new(threshold: u32, reset_timeout_minutes: i64) -> Self {
+        Self {
+            global: CircuitBreaker::new(threshold, reset_timeout_minutes),
+            exchanges: DashMap::new(),
+            threshold,
+            reset_timeout_minutes,
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.global.is_open()
+    }
+
+    pub fn is_exchange_open(&self, exchange: &str) -> bool {
+        self.exchanges.get(exchange).map(|b| b.is_open()).unwrap_or(false)
+    }
+
+    pub fn record_failure(&self, reason: &str) {
+        self.global.record_failure(reason);
+        if self.global.is_open() {
+            warn!("Circuit breaker opened: {}", reason);
+        }
+    }
+
+    pub fn record_success(&self) {
+        self.global.reset();
+    }
+
+    pub fn record_exchange_failure(&self, exchange: &str, reason: &str) {
+        let breaker = self.exchanges.entry(exchange.to_string())
+            .or_insert_with(|| CircuitBreaker::new(self.threshold, self.reset_timeout_minutes));
+        breaker.record_failure(reason);
+        if breaker.is_open() {
+            warn!("{} circuit breaker opened: {}", exchange, reason);
+        }
+    }
+
+    pub fn record_exchange_success(&self, exchange: &str) {
+        if let Some(breaker) = self.exchanges.get(exchange) {
+            breaker.reset();
+        }
+    }
+
+    /// Manually closes the global breaker (`None`) or one exchange's.
+    pub fn reset(&self, exchange: Option<&str>) {
+        match exchange {
+            Some(exchange) => self.record_exchange_success(exchange),
+            None => self.global.reset(),
+        }
+    }
+
+    pub fn states(&self) -> Vec<BreakerState> {
+        let mut states = vec![self.global.state("global")];
+        let mut exchanges: Vec<BreakerState> = self.exchanges.iter()
+            .map(|entry| entry.value().state(entry.key()))
+            .collect();
+        exchanges.sort_by(|a, b| a.name.cmp(&b.name));
+        states.extend(exchanges);
+        states
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_trips_at_threshold() {
+        let breakers = CircuitBreakers::new(2, 5);
+        breakers.record_exchange_failure("Bybit", "timeout");
+        assert!(!breakers.is_exchange_open("Bybit"));
+        breakers.record_exchange_failure("Bybit", "timeout");
+        assert!(breakers.is_exchange_open("Bybit"));
+        assert!(!breakers.is_exchange_open("Binance"));
+        assert!(!breakers.is_open());
+
+        breakers.record_exchange_success("Bybit");
+        assert!(!breakers.is_exchange_open("Bybit"));
+    }
+
+    #[test]
+    fn test_states_list_global_first() {
+        let breakers = CircuitBreakers::new(1, 5);
+        breakers.record_failure("slippage 2.1%");
+        breakers.record_exchange_failure("Binance", "rejected");
+
+        let states = breakers.states();
+        assert_eq!(states[0].name, "global");
+        assert!(states[0].open);
+        assert_eq!(states[0].last_reason.as_deref(), Some("slippage 2.1%"));
+        assert_eq!(states[1].name, "Binance");
+
+        breakers.reset(None);
+        assert!(!breakers.is_open());
+    }
+}
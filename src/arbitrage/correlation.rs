@@ -0,0 +1,277 @@
+//! Historical volatility of the configured assets and the correlation of
+//! their returns, computed from stored klines.
+//!
+//! Each asset is tracked through its USDT market on one venue. Returns are
+//! taken from consecutive closes of the candles all assets share, so a gap
+//! in one series does not pair returns over different periods. The matrix
+//! is recomputed every `refresh_interval_minutes` and handed to the risk
+//! model, where triangles over volatile, weakly correlated assets score
+//! higher.
+
+use crate::exchanges::{Kline, KlineInterval};
+use crate::history::backfill_klines;
+use crate::storage::LocalStore;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::warn;
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CorrelationConfig {
+    pub enabled: bool,
+    /// Assets whose USDT markets are tracked, such as BTC and ETH
+    pub assets: Vec<String>,
+    /// Venue whose candles are used
+    pub exchange: String,
+    /// Candle interval: 1m, 5m, 15m, 1h, 4h or 1d
+    pub interval: String,
+    /// Returns the statistics cover, counted in candles
+    pub lookback: usize,
+    pub refresh_interval_minutes: u64,
+    /// Download the candles missing from the store before each refresh
+    pub backfill: bool,
+    /// Per-candle return standard deviation (percent) treated as maximum risk
+    pub max_volatility_pct: f64,
+}
+
+impl Default for CorrelationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            assets: Vec::new(),
+            exchange: "Binance".to_string(),
+            interval: "1h".to_string(),
+            lookback: 168,
+            refresh_interval_minutes: 60,
+            backfill: true,
+            max_volatility_pct: 2.0,
+        }
+    }
+}
+
+impl CorrelationConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.assets.len() < 2 {
+            return Err(anyhow::anyhow!("risk.scoring.correlation needs at least two assets"));
+        }
+        if !["binance", "bybit"].contains(&self.exchange.to_lowercase().as_str()) {
+            return Err(anyhow::anyhow!("risk.scoring.correlation.exchange must be Binance or Bybit"));
+        }
+        self.kline_interval().context("Invalid risk.scoring.correlation.interval")?;
+        if self.lookback < 3 {
+            return Err(anyhow::anyhow!("risk.scoring.correlation.lookback must be at least 3 candles"));
+        }
+        if self.refresh_interval_minutes == 0 {
+            return Err(anyhow::anyhow!("risk.scoring.correlation.refresh_interval_minutes must be greater than 0"));
+        }
+        if self.max_volatility_pct <= 0.0 {
+            return Err(anyhow::anyhow!("risk.scoring.correlation.max_volatility_pct must be greater than 0"));
+        }
+        Ok(())
+    }
+
+    pub fn kline_interval(&self) -> Result<KlineInterval> {
+        Ok(self.interval.parse::<KlineInterval>()?)
+    }
+
+    fn symbol(asset: &str) -> String {
+        format!("{}USDT", asset)
+    }
+}
+
+/// Volatility of each asset and correlation of each pair over the same
+/// candles, both from simple per-candle returns.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CorrelationMatrix {
+    /// Returns each statistic was computed from
+    pub samples: usize,
+    /// Standard deviation of per-candle returns in percent, per asset
+    pub volatility_pct: BTreeMap<String, f64>,
+    /// Pearson correlation of returns per asset pair, in name order
+    pub correlations: BTreeMap<(String, String), f64>,
+    pub computed_at: Option<DateTime<Utc>>,
+}
+
+impl CorrelationMatrix {
+    /// Statistics of the last `lookback` returns all `series` share; empty
+    /// when they share fewer than three.
+    pub fn compute(series: &[(String, Vec<Kline>)], lookback: usize, now: DateTime<Utc>) -> Self {
+        let closes: Vec<(&str, HashMap<DateTime<Utc>, Decimal>)> = series.iter()
+            .filter(|(_, klines)| !klines.is_empty())
+            .map(|(asset, klines)| (asset.as_str(), klines.iter().map(|k| (k.open_time, k.close)).collect()))
+            .collect();
+        let Some((_, first)) = closes.first() else { return Self::default() };
+        let mut shared: Vec<DateTime<Utc>> = first.keys()
+            .filter(|time| closes.iter().all(|(_, c)| c.contains_key(time)))
+            .copied()
+            .collect();
+        shared.sort();
+        let shared = &shared[shared.len().saturating_sub(lookback + 1)..];
+        if shared.len() < 4 {
+            return Self::default();
+        }
+
+        let returns: Vec<(&str, Vec<f64>)> = closes.iter()
+            .map(|(asset, c)| (*asset, shared.windows(2)
+                .map(|w| {
+                    let (previous, close) = (c[&w[0]], c[&w[1]]);
+                    if previous > Decimal::ZERO { ((close - previous) / previous).to_f64().unwrap_or(0.0) } else { 0.0 }
+                })
+                .collect()))
+            .collect();
+        let mut matrix = Self { samples: shared.len() - 1, computed_at: Some(now), ..Self::default() };
+        for (i, (asset, a)) in returns.iter().enumerate() {
+            matrix.volatility_pct.insert(asset.to_string(), standard_deviation(a) * 100.0);
+            for (other, b) in &returns[i + 1..] {
+                if let Some(correlation) = pearson(a, b) {
+                    matrix.correlations.insert(pair(asset, other), correlation);
+                }
+            }
+        }
+        matrix
+    }
+
+    pub fn volatility_pct(&self, asset: &str) -> Option<f64> {
+        self.volatility_pct.get(asset).copied()
+    }
+
+    pub fn correlation(&self, a: &str, b: &str) -> Option<f64> {
+        if a == b {
+            return self.volatility_pct.contains_key(a).then_some(1.0);
+        }
+        self.correlations.get(&pair(a, b)).copied()
+    }
+
+    /// Highest volatility among `assets` and lowest correlation between
+    /// any two of them, over the assets the matrix tracks.
+    pub fn worst_of(&self, assets: &BTreeSet<&str>) -> (Option<f64>, Option<f64>) {
+        let assets: Vec<&str> = assets.iter().copied().collect();
+        let volatility = assets.iter().filter_map(|a| self.volatility_pct(a)).reduce(f64::max);
+        let correlation = assets.iter().enumerate()
+            .flat_map(|(i, a)| assets[i + 1..].iter().map(move |b| (*a, *b)))
+            .filter_map(|(a, b)| self.correlation(a, b))
+            .reduce(f64::min);
+        (volatility, correlation)
+    }
+}
+
+fn pair(a: &str, b: &str) -> (String, String) {
+    if a <= b { (a.to_string(), b.to_string()) } else { (b.to_string(), a.to_string()) }
+}
+
+fn standard_deviation(samples: &[f64]) -> f64 {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    (samples.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+}
+
+/// Correlation of two equally long series; `None` when either is flat.
+fn pearson(a: &[f64], b: &[f64]) -> Option<f64> {
+    let n = a.len() as f64;
+    let (mean_a, mean_b) = (a.iter().sum::<f64>() / n, b.iter().sum::<f64>() / n);
+    let covariance: f64 = a.iter().zip(b).map(|(x, y)| (x - mean_a) * (y - mean_b)).sum();
+    let spread_a: f64 = a.iter().map(|x| (x - mean_a).powi(2)).sum();
+    let spread_b: f64 = b.iter().map(|y| (y - mean_b).powi(2)).sum();
+    let denominator = (spread_a * spread_b).sqrt();
+    (denominator > 0.0).then(|| (covariance / denominator).clamp(-1.0, 1.0))
+}
+
+/// Keeps the configured assets' candles up to date in the local store and
+/// computes their matrix.
+#[derive(Debug, Clone)]
+pub struct CorrelationService {
+    config: CorrelationConfig,
+    store: LocalStore,
+}
+
+impl CorrelationService {
+    pub fn new(config: CorrelationConfig, store: LocalStore) -> Self {
+        Self { config, store }
+    }
+
+    pub fn config(&self) -> &CorrelationConfig {
+        &self.config
+    }
+
+    /// Backfills the lookback window when `backfill` is set, then computes
+    /// the matrix from whatever the store holds. An asset whose download
+    /// fails is computed from the candles already stored.
+    pub async fn refresh(&self) -> Result<CorrelationMatrix> {
+        let interval = self.config.kline_interval()?;
+        let now = Utc::now();
+        if self.config.backfill {
+            let start = now - interval.duration() * (self.config.lookback as i32 + 1);
+            for asset in &self.config.assets {
+                let symbol = CorrelationConfig::symbol(asset);
+                if let Err(e) = backfill_klines(&self.store, &self.config.exchange, &symbol, interval, start..now).await {
+                    warn!("Failed to download {} {} candles for the correlation matrix: {:#}", symbol, interval, e);
+                }
+            }
+        }
+        let series = self.config.assets.iter()
+            .map(|asset| {
+                let klines = self.store.load_klines(&self.config.exchange, &CorrelationConfig::symbol(asset), interval)?;
+                Ok((asset.clone(), klines))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(CorrelationMatrix::compute(&series, self.config.lookback, now))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn klines(closes: &[i64]) -> Vec<Kline> {
+        let start = "2024-03-01T00:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        closes.iter().enumerate()
+            .map(|(i, close)| Kline {
+                open_time: start + chrono::Duration::hours(i as i64),
+                open: Decimal::from(*close),
+                high: Decimal::from(*close),
+                low: Decimal::from(*close),
+                close: Decimal::from(*close),
+                volume: Decimal::ONE,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_volatility_and_correlation_of_shared_returns() {
+        let series = vec![
+            ("BTC".to_string(), klines(&[100, 102, 100, 104, 100, 103])),
+            // Moves with BTC, twice as far
+            ("ETH".to_string(), klines(&[100, 104, 100, 108, 100, 106])),
+            // Moves against BTC
+            ("SOL".to_string(), klines(&[100, 98, 100, 96, 100, 97])),
+        ];
+        let matrix = CorrelationMatrix::compute(&series, 100, Utc::now());
+        assert_eq!(matrix.samples, 5);
+        assert!(matrix.volatility_pct("ETH").unwrap() > matrix.volatility_pct("BTC").unwrap() * 1.5);
+        assert!(matrix.correlation("ETH", "BTC").unwrap() > 0.99);
+        assert!(matrix.correlation("BTC", "SOL").unwrap() < -0.99);
+        assert_eq!(matrix.correlation("BTC", "BTC"), Some(1.0));
+        assert_eq!(matrix.correlation("BTC", "XRP"), None);
+
+        let (volatility, correlation) = matrix.worst_of(&BTreeSet::from(["BTC", "ETH", "SOL", "XRP"]));
+        assert_eq!(volatility, matrix.volatility_pct("ETH"));
+        assert!(correlation.unwrap() < -0.99);
+
+        // Only the last three returns
+        assert_eq!(CorrelationMatrix::compute(&series, 3, Utc::now()).samples, 3);
+    }
+
+    #[test]
+    fn test_candles_missing_from_one_series_are_skipped() {
+        let mut eth = klines(&[100, 104, 100, 108, 100, 106]);
+        eth.remove(2);
+        let series = vec![("BTC".to_string(), klines(&[100, 102, 100, 104, 100, 103])), ("ETH".to_string(), eth)];
+        assert_eq!(CorrelationMatrix::compute(&series, 100, Utc::now()).samples, 4);
+
+        let short = vec![("BTC".to_string(), klines(&[100, 102, 100])), ("ETH".to_string(), klines(&[100, 104, 100]))];
+        assert_eq!(CorrelationMatrix::compute(&short, 100, Utc::now()), CorrelationMatrix::default());
+    }
+}
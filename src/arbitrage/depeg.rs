@@ -0,0 +1,172 @@
+use super::ArbitrageOpportunity;
+use crate::config::StablecoinConfig;
+use crate::exchanges::snapshot::PriceLookup;
+use crate::symbols::split_symbol;
+use dashmap::DashMap;
+use log::{info, warn};
+use rust_decimal::Decimal;
+
+/// How far each configured stablecoin trades from $1 on each venue, read
+/// from its USDT pair on every scan. USDT has no pair of its own; it counts
+/// as off its peg when every other stablecoin moves the same way against
+/// it, by the smallest of those moves.
+#[derive(Debug)]
+pub struct DepegGuard {
+    assets: Vec<String>,
+    max_depeg_percentage: Decimal,
+    /// Deviation from $1 in percent, keyed by venue and asset
+    deviations: DashMap<(String, String), Decimal>,
+}
+
+impl DepegGuard {
+    pub fn new(config: &StablecoinConfig) -> Self {
+        Self {
+            assets: config.assets.clone(),
+            max_depeg_percentage: config.max_depeg_percentage,
+            deviations: DashMap::new(),
+        }
+    }
+
+    fn is_enabled(&self) -> bool {
+        self.max_depeg_percentage > Decimal::ZERO
+    }
+
+    fn is_depegged(&self, deviation: Decimal) -> bool {
+        deviation.abs() > self.max_depeg_percentage
+    }
+
+    pub fn observe(&self, exchange: &str, prices: &impl PriceLookup) {
+        if !self.is_enabled() {
+            return;
+        }
+        for asset in self.assets.iter().filter(|asset| *asset != "USDT") {
+            let Some(price) = prices.price(&format!("{}USDT", asset)).filter(|p| *p > Decimal::ZERO) else { continue };
+            let deviation = (price - Decimal::ONE) * Decimal::ONE_HUNDRED;
+            let previous = self.deviations.insert((exchange.to_string(), asset.clone()), deviation);
+            let was_depegged = previous.is_some_and(|d| self.is_depegged(d));
+            if self.is_depegged(deviation) && !was_depegged {
+                warn!("{} trades {:.4}% from $1 on {}, halting execution on paths through it", asset, deviation, exchange);
+            } else if was_depegged && !self.is_depegged(deviation) {
+                info!("{} is back within {}% of $1 on {}", asset, self.max_depeg_percentage, exchange);
+            }
+        }
+    }
+
+    /// Last deviation of `asset` from $1 on `exchange`, in percent.
+    pub fn deviation(&self, exchange: &str, asset: &str) -> Option<Decimal> {
+        if asset == "USDT" {
+            return self.usdt_deviation(exchange);
+        }
+        self.deviations.get(&(exchange.to_string(), asset.to_string())).map(|d| *d)
+    }
+
+    fn usdt_deviation(&self, exchange: &str) -> Option<Decimal> {
+        let others: Vec<Decimal> = self.deviations.iter()
+            .filter(|entry| entry.key().0 == exchange)
+            .map(|entry| *entry.value())
+            .collect();
+        if others.len() < 2 || !others.iter().all(|d| self.is_depegged(*d)) {
+            return None;
+        }
+        if !(others.iter().all(|d| d.is_sign_positive()) || others.iter().all(|d| d.is_sign_negative())) {
+            return None;
+        }
+        // One unit of the stablecoin closest to its USDT parity buys 1 + d/100 USDT
+        let closest = others.into_iter().min_by_key(|d| d.abs())?;
+        let price = Decimal::ONE + closest / Decimal::ONE_HUNDRED;
+        Some((Decimal::ONE / price - Decimal::ONE) * Decimal::ONE_HUNDRED)
+    }
+
+    /// The first configured stablecoin traded by `opportunity` that is off
+    /// its peg on the venue of that leg, with its deviation in percent.
+    pub fn depegged(&self, opportunity: &ArbitrageOpportunity) -> Option<(String, Decimal)> {
+        if !self.is_enabled() {
+            return None;
+        }
+        for step in &opportunity.execution_steps {
+            let Some((base, quote)) = split_symbol(&step.symbol) else { continue };
+            // USDT first: when it moves, every other stablecoin looks off its peg too
+            for asset in [quote, base] {
+                if !self.assets.iter().any(|a| a == asset) {
+                    continue;
+                }
+                if let Some(deviation) = self.deviation(&step.exchange, asset).filter(|d| self.is_depegged(*d)) {
+                    return Some((asset.to_string(), deviation));
+                }
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::risk::Strategy;
+    use crate::arbitrage::ExecutionStep;
+    use crate::exchanges::{new_client_order_id, OrderSide, PriceMap};
+    use chrono::Utc;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+
+    fn prices(pairs: &[(&str, &str)]) -> PriceMap {
+        pairs.iter().map(|(symbol, price)| (symbol.to_string(), dec(price))).collect()
+    }
+
+    fn opportunity(symbols: &[&str]) -> ArbitrageOpportunity {
+        let step = |symbol: &str| ExecutionStep {
+            exchange: "Binance".to_string(),
+            action: String::new(),
+            symbol: symbol.to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::ONE,
+            expected_price: Decimal::ONE,
+            fees: Decimal::ZERO,
+            client_order_id: new_client_order_id(),
+        };
+        ArbitrageOpportunity {
+            exchange: "Binance".to_string(),
+            strategy: Strategy::Triangular,
+            path: Vec::new(),
+            profit_percentage: Decimal::ONE,
+            net_profit_percentage: Decimal::ONE,
+            required_amount: Decimal::from(1000),
+            estimated_profit_usd: Decimal::from(10),
+            risk_score: 0.0,
+            execution_steps: symbols.iter().map(|s| step(s)).collect(),
+            leg_quotes: Vec::new(),
+            timestamp: Utc::now(),
+            scan_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_paths_through_a_depegged_stablecoin_are_flagged() {
+        let guard = DepegGuard::new(&StablecoinConfig::default());
+        guard.observe("Binance", &prices(&[("USDCUSDT", "0.9990"), ("FDUSDUSDT", "0.9920")]));
+
+        assert_eq!(guard.deviation("Binance", "FDUSD"), Some(dec("-0.8")));
+        assert_eq!(guard.deviation("Bybit", "FDUSD"), None);
+        assert_eq!(guard.depegged(&opportunity(&["USDCUSDT", "FDUSDUSDC", "FDUSDUSDT"])), Some(("FDUSD".to_string(), dec("-0.8"))));
+        assert_eq!(guard.depegged(&opportunity(&["BTCUSDT", "ETHBTC", "ETHUSDT"])), None);
+        assert_eq!(guard.depegged(&opportunity(&["BTCUSDC", "ETHBTC", "ETHUSDC"])), None);
+        let disabled = DepegGuard::new(&StablecoinConfig { max_depeg_percentage: Decimal::ZERO, ..StablecoinConfig::default() });
+        disabled.observe("Binance", &prices(&[("FDUSDUSDT", "0.9")]));
+        assert_eq!(disabled.depegged(&opportunity(&["FDUSDUSDT"])), None);
+    }
+
+    #[test]
+    fn test_usdt_depegs_when_every_other_stablecoin_moves_against_it() {
+        let guard = DepegGuard::new(&StablecoinConfig::default());
+        guard.observe("Binance", &prices(&[("USDCUSDT", "1.0101"), ("FDUSDUSDT", "1.0204"), ("DAIUSDT", "0.9999")]));
+        assert_eq!(guard.deviation("Binance", "USDT"), None);
+
+        guard.observe("Binance", &prices(&[("DAIUSDT", "1.0101")]));
+        let deviation = guard.deviation("Binance", "USDT").unwrap();
+        assert_eq!(deviation.round_dp(2), dec("-1.00"));
+        let (asset, _) = guard.depegged(&opportunity(&["BTCUSDT", "ETHBTC", "ETHUSDT"])).unwrap();
+        assert_eq!(asset, "USDT");
+    }
+}
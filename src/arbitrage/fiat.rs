@@ -0,0 +1,227 @@
+use super::ExecutionStep;
+use crate::exchanges::snapshot::PriceLookup;
+use crate::exchanges::fees::VenueFees;
+use crate::exchanges::{new_client_order_id, OrderSide};
+use crate::precision::PricePrecision;
+use rust_decimal::Decimal;
+
+/// Conversion between a fiat currency and USDT on one venue. Fiats are
+/// listed either as `F/USDT` (EURUSDT) or as `USDT/F` (USDTTRY); both are
+/// normalized to USDT per unit of fiat.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FxRate {
+    pub symbol: String,
+    pub price: Decimal,
+    /// Whether `symbol` is `USDT/F`, so that its price is fiat per USDT
+    pub inverted: bool,
+}
+
+impl FxRate {
+    pub fn lookup(prices: &impl PriceLookup, fiat: &str) -> Option<Self> {
+        let direct = format!("{}USDT", fiat);
+        if let Some(price) = prices.price(&direct).filter(|p| *p > Decimal::ZERO) {
+            return Some(Self { symbol: direct, price, inverted: false });
+        }
+        let inverse = format!("USDT{}", fiat);
+        let price = prices.price(&inverse).filter(|p| *p > Decimal::ZERO)?;
+        Some(Self { symbol: inverse, price, inverted: true })
+    }
+
+    pub fn usdt_per_unit(&self) -> Decimal {
+        if self.inverted { Decimal::ONE / self.price } else { self.price }
+    }
+
+    /// The order turning `fiat_amount` into USDT, or USDT into `fiat_amount`.
+    fn step(&self, exchange: &str, fiat: &str, to_usdt: bool, fiat_amount: Decimal, taker_fee: Decimal) -> ExecutionStep {
+        let (action, side, quantity) = match (to_usdt, self.inverted) {
+            (true, false) => (format!("Sell {} for USDT", fiat), OrderSide::Sell, fiat_amount),
+            (true, true) => (format!("Buy USDT with {}", fiat), OrderSide::Buy, fiat_amount / self.price),
+            (false, false) => (format!("Buy {} with USDT", fiat), OrderSide::Buy, fiat_amount),
+            (false, true) => (format!("Sell USDT for {}", fiat), OrderSide::Sell, fiat_amount / self.price),
+        };
+        ExecutionStep {
+            exchange: exchange.to_string(),
+            action,
+            symbol: self.symbol.clone(),
+            side,
+            quantity,
+            expected_price: self.price,
+            fees: fiat_amount * self.usdt_per_unit() * taker_fee,
+            client_order_id: new_client_order_id(),
+        }
+    }
+}
+
+/// Prices of a cycle between `A/USDT` and a fiat market `A/F` (e.g.
+/// BTCUSDT and BTCEUR), closed through the venue's FX pair. Profit is
+/// measured in USDT after normalizing the fiat price of A.
+#[derive(Debug, Clone)]
+pub struct FiatCycle {
+    pub asset: String,
+    pub fiat: String,
+    pub a_usdt: Decimal,
+    pub a_fiat: Decimal,
+    pub fx: FxRate,
+}
+
+impl FiatCycle {
+    pub fn lookup(prices: &impl PriceLookup, asset: &str, fiat: &str) -> Option<Self> {
+        let a_usdt = prices.price(&format!("{}USDT", asset)).filter(|p| *p > Decimal::ZERO)?;
+        let a_fiat = prices.price(&format!("{}{}", asset, fiat)).filter(|p| *p > Decimal::ZERO)?;
+        let fx = FxRate::lookup(prices, fiat)?;
+        Some(Self { asset: asset.to_string(), fiat: fiat.to_string(), a_usdt, a_fiat, fx })
+    }
+
+    fn a_usdt_symbol(&self) -> String {
+        format!("{}USDT", self.asset)
+    }
+
+    fn a_fiat_symbol(&self) -> String {
+        format!("{}{}", self.asset, self.fiat)
+    }
+
+    /// USDT value of A on its fiat market.
+    pub fn normalized_price(&self) -> Decimal {
+        self.a_fiat * self.fx.usdt_per_unit()
+    }
+
+    /// USDT back per USDT spent on USDT -> A -> F -> USDT: buy A, sell it
+    /// for fiat, convert the fiat back.
+    pub fn forward_return(&self) -> Decimal {
+        self.normalized_price() / self.a_usdt
+    }
+
+    /// USDT back per USDT spent on USDT -> F -> A -> USDT. The reciprocal of
+    /// `forward_return`.
+    pub fn reverse_return(&self) -> Decimal {
+        self.a_usdt / self.normalized_price()
+    }
+
+    /// Legs of the cycle, each sized to what the previous one leaves after
+    /// fees.
+    pub fn steps(&self, exchange: &str, forward: bool, usdt_amount: Decimal, fees: &VenueFees) -> Vec<ExecutionStep> {
+        let (taker_fee, keep) = (fees.taker_fee(), fees.received_factor());
+        let step = |action: String, symbol: String, side: OrderSide, quantity: Decimal, price: Decimal, fees: Decimal| ExecutionStep {
+            exchange: exchange.to_string(),
+            action,
+            symbol,
+            side,
+            quantity,
+            expected_price: price,
+            fees,
+            client_order_id: new_client_order_id(),
+        };
+        if forward {
+            let quantity = usdt_amount / self.a_usdt;
+            let fiat_amount = quantity * keep * self.a_fiat;
+            vec![
+                step(format!("Buy {} with USDT", self.asset), self.a_usdt_symbol(), OrderSide::Buy,
+                     quantity, self.a_usdt, usdt_amount * taker_fee),
+                step(format!("Sell {} for {}", self.asset, self.fiat), self.a_fiat_symbol(), OrderSide::Sell,
+                     quantity * keep, self.a_fiat, fiat_amount * taker_fee),
+                self.fx.step(exchange, &self.fiat, true, fiat_amount * keep, taker_fee),
+            ]
+        } else {
+            let fiat_amount = usdt_amount / self.fx.usdt_per_unit();
+            let quantity = fiat_amount * keep / self.a_fiat;
+            vec![
+                self.fx.step(exchange, &self.fiat, false, fiat_amount, taker_fee),
+                step(format!("Buy {} with {}", self.asset, self.fiat), self.a_fiat_symbol(), OrderSide::Buy,
+                     quantity, self.a_fiat, fiat_amount * keep * taker_fee),
+                step(format!("Sell {} for USDT", self.asset), self.a_usdt_symbol(), OrderSide::Sell,
+                     quantity * keep, self.a_usdt, quantity * keep * self.a_usdt * taker_fee),
+            ]
+        }
+    }
+
+    pub fn path(&self, exchange: &str, forward: bool, precision: &PricePrecision) -> Vec<String> {
+        let a_usdt = precision.format_price(exchange, &self.a_usdt_symbol(), self.a_usdt);
+        let a_fiat = precision.format_price(exchange, &self.a_fiat_symbol(), self.a_fiat);
+        let fx = format!("{} at {}", self.fx.symbol, precision.format_price(exchange, &self.fx.symbol, self.fx.price));
+        if forward {
+            vec![
+                format!("Buy {} with USDT at {}", self.asset, a_usdt),
+                format!("Sell {} for {} at {}", self.asset, self.fiat, a_fiat),
+                format!("Convert {} to USDT via {}", self.fiat, fx),
+            ]
+        } else {
+            vec![
+                format!("Convert USDT to {} via {}", self.fiat, fx),
+                format!("Buy {} with {} at {}", self.asset, self.fiat, a_fiat),
+                format!("Sell {} for USDT at {}", self.asset, a_usdt),
+            ]
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::ArbitrageEngine;
+    use crate::config::Config;
+    use crate::exchanges::{PriceMap, TradingFees};
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+
+    fn prices(pairs: &[(&str, &str)]) -> PriceMap {
+        pairs.iter().map(|(symbol, price)| (symbol.to_string(), dec(price))).collect()
+    }
+
+    #[test]
+    fn test_fiat_prices_are_normalized_through_either_fx_listing() {
+        let eur = FiatCycle::lookup(&prices(&[("BTCUSDT", "60000"), ("BTCEUR", "55000"), ("EURUSDT", "1.1")]), "BTC", "EUR").unwrap();
+        assert_eq!(eur.normalized_price(), dec("60500"));
+        assert!(eur.forward_return() > Decimal::ONE);
+        assert_eq!((eur.forward_return() * eur.reverse_return()).round_dp(12), Decimal::ONE);
+
+        // TRY is only listed as USDTTRY
+        let lira = FiatCycle::lookup(&prices(&[("BTCUSDT", "60000"), ("BTCTRY", "2040000"), ("USDTTRY", "34")]), "BTC", "TRY").unwrap();
+        assert!(lira.fx.inverted);
+        assert_eq!(lira.normalized_price().round_dp(6), dec("60000"));
+        assert!(FiatCycle::lookup(&prices(&[("BTCUSDT", "60000"), ("BTCGBP", "47000")]), "BTC", "GBP").is_none());
+    }
+
+    #[test]
+    fn test_steps_carry_quantities_through_the_fx_leg() {
+        let cycle = FiatCycle::lookup(&prices(&[("ETHUSDT", "3000"), ("ETHTRY", "103000"), ("USDTTRY", "34")]), "ETH", "TRY").unwrap();
+
+        let no_fees = VenueFees {
+            rates: TradingFees { maker_fee: Decimal::ZERO, taker_fee: Decimal::ZERO, withdrawal_fee: Decimal::ZERO },
+            bnb_fee_multiplier: None,
+        };
+        let forward = cycle.steps("Binance", true, dec("3000"), &no_fees);
+        assert_eq!(forward.iter().map(|s| s.symbol.as_str()).collect::<Vec<_>>(), ["ETHUSDT", "ETHTRY", "USDTTRY"]);
+        assert!(forward.iter().map(|s| s.side.clone()).eq([OrderSide::Buy, OrderSide::Sell, OrderSide::Buy]));
+        assert_eq!(forward[1].quantity, Decimal::ONE);
+        // 103000 TRY buys 3029.41 USDT
+        assert_eq!(forward[2].quantity.round_dp(2), dec("3029.41"));
+
+        let reverse = cycle.steps("Binance", false, dec("3400"), &no_fees);
+        assert_eq!(reverse.iter().map(|s| s.symbol.as_str()).collect::<Vec<_>>(), ["USDTTRY", "ETHTRY", "ETHUSDT"]);
+        assert!(reverse.iter().map(|s| s.side.clone()).eq([OrderSide::Sell, OrderSide::Buy, OrderSide::Sell]));
+        assert_eq!(reverse[0].quantity.round_dp(12), dec("3400"));
+        assert_eq!(reverse[1].quantity, reverse[2].quantity);
+
+        // Fees taken from each fill shrink the legs after it
+        let reverse = cycle.steps("Binance", false, dec("3400"), &VenueFees::default());
+        assert_eq!(reverse[2].quantity, reverse[1].quantity * dec("0.999"));
+    }
+
+    #[tokio::test]
+    async fn test_engine_trades_fiat_markets_priced_off_usdt() {
+        // BTC is 1% rich on BTCEUR once converted at EURUSDT
+        let prices = prices(&[("BTCUSDT", "60000"), ("BTCEUR", "55090.90909"), ("EURUSDT", "1.1")]);
+        let mut config = Config::default();
+        config.trading.min_profit_threshold = dec("0.5");
+        assert!(ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+
+        config.fiat.enabled = true;
+        let found = ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].execution_steps.iter().map(|s| s.symbol.as_str()).collect::<Vec<_>>(), ["BTCUSDT", "BTCEUR", "EURUSDT"]);
+        assert_eq!(found[0].profit_percentage.round_dp(2), Decimal::ONE);
+        assert_eq!(found[0].net_profit_percentage.round_dp(2), dec("0.7"));
+    }
+}
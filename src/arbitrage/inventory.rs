@@ -0,0 +1,257 @@
+use super::ArbitrageOpportunity;
+use crate::config::InventoryConfig;
+use crate::exchanges::{Balance, OrderSide};
+use crate::executor::LegFill;
+use crate::precision::format_usd;
+use crate::symbols::split_symbol;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::debug;
+use rust_decimal::Decimal;
+use std::sync::Mutex;
+
+/// Free balance of every asset on every venue, reloaded from the
+/// exchanges every `refresh_interval_seconds` and moved by fills in between.
+/// Cross-exchange opportunities are sized to what is already held: the
+/// base asset where it is sold, the quote asset where it is bought.
+#[derive(Debug)]
+pub struct InventoryTracker {
+    config: InventoryConfig,
+    balances: DashMap<(String, String), Decimal>,
+    refreshed_at: Mutex<Option<DateTime<Utc>>>,
+}
+
+impl InventoryTracker {
+    pub fn new(config: InventoryConfig) -> Self {
+        Self { config, balances: DashMap::new(), refreshed_at: Mutex::new(None) }
+    }
+
+    /// Whether balances should be reloaded at `now`.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        let interval = chrono::Duration::seconds(self.config.refresh_interval_seconds as i64);
+        match *self.refreshed_at.lock().unwrap_or_else(|e| e.into_inner()) {
+            Some(at) => now.signed_duration_since(at) >= interval,
+            None => true,
+        }
+    }
+
+    /// Replaces every balance held on `exchange`.
+    pub fn update(&self, exchange: &str, balances: &[Balance], now: DateTime<Utc>) {
+        self.balances.retain(|(venue, _), _| venue != exchange);
+        for balance in balances {
+            self.balances.insert((exchange.to_string(), balance.asset.clone()), balance.free);
+        }
+        *self.refreshed_at.lock().unwrap_or_else(|e| e.into_inner()) = Some(now);
+    }
+
+    pub fn available(&self, exchange: &str, asset: &str) -> Decimal {
+        self.balances.get(&(exchange.to_string(), asset.to_string())).map(|b| *b).unwrap_or(Decimal::ZERO)
+    }
+
+    fn total(&self, asset: &str) -> Decimal {
+        self.balances.iter().filter(|entry| entry.key().1 == asset).map(|entry| *entry.value()).sum()
+    }
+
+    /// Share of `asset`'s combined holdings that sits on `exchange`, in
+    /// percent; `None` when none is held anywhere.
+    pub fn skew_percentage(&self, exchange: &str, asset: &str) -> Option<Decimal> {
+        let total = self.total(asset);
+        (total > Decimal::ZERO).then(|| self.available(exchange, asset) / total * Decimal::ONE_HUNDRED)
+    }
+
+    /// Largest fraction of a cross-exchange opportunity that held balances
+    /// cover without leaving more than `max_skew_percentage` of the base or
+    /// quote asset on one venue. Trades that move inventory back towards
+    /// balance are never limited by the skew.
+    pub fn cross_exchange_scale(&self, opportunity: &ArbitrageOpportunity) -> Decimal {
+        let sell = opportunity.execution_steps.iter().find(|s| s.side == OrderSide::Sell);
+        let buy = opportunity.execution_steps.iter().find(|s| s.side == OrderSide::Buy);
+        let (Some(sell), Some(buy)) = (sell, buy) else { return Decimal::ZERO };
+        let Some((base, quote)) = split_symbol(&sell.symbol) else { return Decimal::ZERO };
+        if sell.quantity <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+
+        let max_share = self.config.max_skew_percentage / Decimal::ONE_HUNDRED;
+        let quote_spent = buy.quantity * buy.expected_price + buy.fees;
+        let quote_received = sell.quantity * sell.expected_price;
+        let limits = [
+            self.available(&sell.exchange, base) / sell.quantity,
+            self.available(&buy.exchange, quote) / quote_spent,
+            // The buy venue gains base, the sell venue gains quote
+            (max_share * self.total(base) - self.available(&buy.exchange, base)) / buy.quantity,
+            (max_share * self.total(quote) - self.available(&sell.exchange, quote)) / quote_received,
+        ];
+        limits.into_iter().fold(Decimal::ONE, Decimal::min).max(Decimal::ZERO)
+    }
+
+    /// Shrinks a cross-exchange opportunity to the inventory on both
+    /// venues. Returns false when less than `min_fill_percentage` of it is
+    /// covered.
+    pub fn fit(&self, opportunity: &mut ArbitrageOpportunity) -> bool {
+        let scale = self.cross_exchange_scale(opportunity);
+        if scale <= Decimal::ZERO || scale * Decimal::ONE_HUNDRED < self.config.min_fill_percentage {
+            debug!("Skipping {} [{}]: inventory covers only ${}", opportunity.exchange,
+                   opportunity.path.join(" -> "), format_usd(opportunity.required_amount * scale));
+            return false;
+        }
+        if scale < Decimal::ONE {
+            debug!("Shrinking {} position from ${} to ${} to fit inventory", opportunity.exchange,
+                   format_usd(opportunity.required_amount), format_usd(opportunity.required_amount * scale));
+            opportunity.scale(scale);
+        }
+        true
+    }
+
+    /// Assets below `refill_below_percentage` of their rebalance target on
+    /// `exchange`, with the quantity that brings each back to its target.
+    pub fn refills(&self, exchange: &str) -> Vec<(String, Decimal)> {
+        let rebalance = &self.config.rebalance;
+        let mut refills: Vec<(String, Decimal)> = rebalance.targets.iter()
+            .filter_map(|(asset, target)| {
+                let available = self.available(exchange, asset);
+                let floor = *target * rebalance.refill_below_percentage / Decimal::ONE_HUNDRED;
+                (available < floor).then(|| (asset.clone(), *target - available))
+            })
+            .collect();
+        refills.sort();
+        refills
+    }
+
+    /// Credits a completed top-up until the next reload.
+    pub fn record_transfer(&self, exchange: &str, asset: &str, amount: Decimal) {
+        *self.balances.entry((exchange.to_string(), asset.to_string())).or_insert(Decimal::ZERO) += amount;
+    }
+
+    /// Moves balances by executed fills until the next reload.
+    pub fn record_fills(&self, fills: &[LegFill]) {
+        for fill in fills.iter().filter(|f| f.filled_quantity > Decimal::ZERO) {
+            let Some((base, quote)) = split_symbol(&fill.symbol) else { continue };
+            let notional = fill.average_price.map(|p| p * fill.filled_quantity).unwrap_or(Decimal::ZERO);
+            let (base_change, quote_change) = match fill.side {
+                OrderSide::Buy => (fill.filled_quantity, -notional),
+                OrderSide::Sell => (-fill.filled_quantity, notional),
+            };
+            for (asset, change) in [(base, base_change), (quote, quote_change)] {
+                *self.balances.entry((fill.exchange.clone(), asset.to_string())).or_insert(Decimal::ZERO) += change;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::risk::Strategy;
+    use crate::arbitrage::ExecutionStep;
+    use crate::exchanges::new_client_order_id;
+
+    fn step(exchange: &str, side: OrderSide, price: i64) -> ExecutionStep {
+        ExecutionStep {
+            exchange: exchange.to_string(),
+            action: String::new(),
+            symbol: "SOLUSDT".to_string(),
+            side,
+            quantity: Decimal::from(10),
+            expected_price: Decimal::from(price),
+            fees: Decimal::ZERO,
+            client_order_id: new_client_order_id(),
+        }
+    }
+
+    /// Sells 10 SOL on Binance at 101 and buys 10 on Bybit at 100.
+    fn opportunity() -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            exchange: "Binance->Bybit".to_string(),
+            strategy: Strategy::CrossExchange,
+            path: Vec::new(),
+            profit_percentage: Decimal::ONE,
+            net_profit_percentage: Decimal::ONE,
+            required_amount: Decimal::from(1000),
+            estimated_profit_usd: Decimal::from(10),
+            risk_score: 0.0,
+            execution_steps: vec![step("Binance", OrderSide::Sell, 101), step("Bybit", OrderSide::Buy, 100)],
+            leg_quotes: Vec::new(),
+            timestamp: Utc::now(),
+            scan_id: 0,
+        }
+    }
+
+    fn balance(asset: &str, free: i64) -> Balance {
+        Balance { asset: asset.to_string(), free: Decimal::from(free), locked: Decimal::ZERO }
+    }
+
+    fn tracker(max_skew_percentage: i64, binance: &[Balance], bybit: &[Balance]) -> InventoryTracker {
+        let tracker = InventoryTracker::new(InventoryConfig {
+            enabled: true,
+            max_skew_percentage: Decimal::from(max_skew_percentage),
+            ..InventoryConfig::default()
+        });
+        tracker.update("Binance", binance, Utc::now());
+        tracker.update("Bybit", bybit, Utc::now());
+        tracker
+    }
+
+    #[test]
+    fn test_opportunity_is_sized_to_held_balances() {
+        // 4 SOL to sell on Binance, plenty of USDT to buy with on Bybit
+        let limited = tracker(100, &[balance("SOL", 4)], &[balance("USDT", 5000)]);
+        assert_eq!(limited.cross_exchange_scale(&opportunity()), Decimal::from_str_exact("0.4").unwrap());
+        let mut shrunk = opportunity();
+        assert!(limited.fit(&mut shrunk));
+        assert_eq!(shrunk.execution_steps[1].quantity, Decimal::from(4));
+
+        // No USDT on Bybit, so nothing can be bought there
+        let no_quote = tracker(100, &[balance("SOL", 20), balance("USDT", 1000)], &[balance("SOL", 20)]);
+        assert!(!no_quote.fit(&mut opportunity()));
+    }
+
+    #[test]
+    fn test_skew_limits_trades_that_pile_up_on_one_venue() {
+        let skewed = |bybit_sol: i64| tracker(80,
+            &[balance("SOL", 100 - bybit_sol), balance("USDT", 5000)],
+            &[balance("SOL", bybit_sol), balance("USDT", 5000)]);
+
+        // Bybit holds 70 of 100 SOL; buying 10 more leaves it with exactly 80%
+        assert_eq!(skewed(70).skew_percentage("Bybit", "SOL"), Some(Decimal::from(70)));
+        assert_eq!(skewed(70).cross_exchange_scale(&opportunity()), Decimal::ONE);
+        assert_eq!(skewed(75).cross_exchange_scale(&opportunity()), Decimal::from_str_exact("0.5").unwrap());
+        assert_eq!(skewed(90).cross_exchange_scale(&opportunity()), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_fills_move_inventory_between_refreshes() {
+        let tracker = tracker(100, &[balance("SOL", 10)], &[balance("USDT", 1000)]);
+        let fill = |exchange: &str, side: OrderSide, price: i64| LegFill {
+            exchange: exchange.to_string(),
+            symbol: "SOLUSDT".to_string(),
+            side,
+            client_order_ids: Vec::new(),
+            requested_quantity: Decimal::from(10),
+            filled_quantity: Decimal::from(10),
+            average_price: Some(Decimal::from(price)),
+        };
+        tracker.record_fills(&[fill("Binance", OrderSide::Sell, 101), fill("Bybit", OrderSide::Buy, 100)]);
+
+        assert_eq!(tracker.available("Binance", "SOL"), Decimal::ZERO);
+        assert_eq!(tracker.available("Binance", "USDT"), Decimal::from(1010));
+        assert_eq!(tracker.available("Bybit", "SOL"), Decimal::from(10));
+        assert_eq!(tracker.available("Bybit", "USDT"), Decimal::ZERO);
+        assert_eq!(tracker.cross_exchange_scale(&opportunity()), Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_assets_below_the_refill_line_are_topped_up_to_target() {
+        let mut config = InventoryConfig { enabled: true, ..InventoryConfig::default() };
+        config.rebalance.targets = [("SOL".to_string(), Decimal::from(20)), ("USDT".to_string(), Decimal::from(2000))].into();
+        let tracker = InventoryTracker::new(config);
+        // SOL sits at half its target, USDT well below
+        tracker.update("Binance", &[balance("SOL", 10), balance("USDT", 400)], Utc::now());
+
+        assert_eq!(tracker.refills("Binance"), vec![("USDT".to_string(), Decimal::from(1600))]);
+        assert_eq!(tracker.refills("Bybit").len(), 2);
+
+        tracker.record_transfer("Binance", "USDT", Decimal::from(1600));
+        assert!(tracker.refills("Binance").is_empty());
+    }
+}
@@ -0,0 +1,201 @@
+use crate::report::ExecutionReport;
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{error, info, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Mutex;
+
+const STATE_PATH: &str = "risk/kill_switch.json";
+
+/// Persisted so that neither a restart nor a new UTC day re-enables
+/// execution after the switch has tripped.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct PersistedState {
+    tripped_at: Option<DateTime<Utc>>,
+    reason: Option<String>,
+    /// Losses before the last manual reset no longer count
+    reset_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    day: NaiveDate,
+    realized_pnl: Decimal,
+    state: PersistedState,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KillSwitchStatus {
+    pub tripped: bool,
+    pub tripped_at: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+    pub daily_realized_pnl_usd: Decimal,
+    pub max_daily_loss: Decimal,
+}
+
+/// Stops all execution once the realized loss of the current UTC day
+/// reaches `risk.max_daily_loss`. Unlike the circuit breakers it never
+/// closes on its own; it stays tripped until reset through the control API.
+#[derive(Debug)]
+pub struct KillSwitch {
+    max_daily_loss: Decimal,
+    inner: Mutex<Inner>,
+    store: Option<LocalStore>,
+}
+
+impl KillSwitch {
+    /// A switch with a limit of zero or less never trips.
+    pub fn new(max_daily_loss: Decimal) -> Self {
+        Self {
+            max_daily_loss,
+            inner: Mutex::new(Inner {
+                day: Utc::now().date_naive(),
+                realized_pnl: Decimal::ZERO,
+                state: PersistedState::default(),
+            }),
+            store: None,
+        }
+    }
+
+    /// Loads the tripped state saved by an earlier run and persists every
+    /// change to `store`.
+    pub fn with_store(mut self, store: LocalStore) -> Result<Self> {
+        if let Some(state) = store.read_json::<PersistedState>(Path::new(STATE_PATH))? {
+            if let Some(tripped_at) = state.tripped_at {
+                warn!("Kill switch tripped at {} is still active: {}", tripped_at, state.reason.as_deref().unwrap_or_default());
+            }
+            self.inner.get_mut().unwrap_or_else(|e| e.into_inner()).state = state;
+        }
+        self.store = Some(store);
+        Ok(self)
+    }
+
+    /// Adds today's past executions, so a restart does not forget losses
+    /// already realized.
+    pub fn seed(&self, reports: &[ExecutionReport]) {
+        for report in reports {
+            self.record_at(report.realized_pnl_usd(), report.executed_at);
+        }
+    }
+
+    pub fn is_tripped(&self) -> bool {
+        self.lock().state.tripped_at.is_some()
+    }
+
+    /// Adds the realized profit (negative for a loss) of one execution.
+    /// Returns true when this execution tripped the switch.
+    pub fn record(&self, pnl_usd: Decimal) -> bool {
+        self.record_at(pnl_usd, Utc::now())
+    }
+
+    fn record_at(&self, pnl_usd: Decimal, at: DateTime<Utc>) -> bool {
+        let mut inner = self.lock();
+        if inner.state.reset_at.is_some_and(|reset_at| at <= reset_at) {
+            return false;
+        }
+        if at.date_naive() != inner.day {
+            if at.date_naive() < inner.day {
+                return false;
+            }
+            inner.day = at.date_naive();
+            inner.realized_pnl = Decimal::ZERO;
+        }
+        inner.realized_pnl += pnl_usd;
+
+        let breached = self.max_daily_loss > Decimal::ZERO && -inner.realized_pnl >= self.max_daily_loss;
+        if !breached || inner.state.tripped_at.is_some() {
+            return false;
+        }
+        let reason = format!("realized loss of {} USD today reached max_daily_loss {}",
+                             (-inner.realized_pnl).round_dp(2), self.max_daily_loss);
+        error!("KILL SWITCH TRIPPED: {}. Execution stopped until reset via POST /control/kill-switch/reset", reason);
+        inner.state.tripped_at = Some(at);
+        inner.state.reason = Some(reason);
+        self.persist(&inner.state);
+        true
+    }
+
+    /// Re-enables execution and starts counting losses afresh.
+    pub fn reset(&self) {
+        let mut inner = self.lock();
+        info!("Kill switch reset after realized P&L of {} USD today", inner.realized_pnl.round_dp(2));
+        inner.realized_pnl = Decimal::ZERO;
+        inner.state = PersistedState { tripped_at: None, reason: None, reset_at: Some(Utc::now()) };
+        self.persist(&inner.state);
+    }
+
+    pub fn status(&self) -> KillSwitchStatus {
+        let inner = self.lock();
+        let today = Utc::now().date_naive();
+        KillSwitchStatus {
+            tripped: inner.state.tripped_at.is_some(),
+            tripped_at: inner.state.tripped_at,
+            reason: inner.state.reason.clone(),
+            daily_realized_pnl_usd: if inner.day == today { inner.realized_pnl } else { Decimal::ZERO },
+            max_daily_loss: self.max_daily_loss,
+        }
+    }
+
+    fn persist(&self, state: &PersistedState) {
+        let Some(store) = &self.store else { return };
+        if let Err(e) = store.write_json(Path::new(STATE_PATH), state) {
+            warn!("Failed to persist kill switch state: {}", e);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trips_on_daily_loss_and_stays_tripped() {
+        let switch = KillSwitch::new(Decimal::from(100));
+        assert!(!switch.record(Decimal::from(-60)));
+        assert!(!switch.record(Decimal::from(20)));
+        assert!(!switch.is_tripped());
+
+        assert!(switch.record(Decimal::from(-70)));
+        assert!(switch.is_tripped());
+        // A later profit does not re-enable execution
+        assert!(!switch.record(Decimal::from(500)));
+        assert!(switch.is_tripped());
+
+        switch.reset();
+        assert!(!switch.is_tripped());
+        assert_eq!(switch.status().daily_realized_pnl_usd, Decimal::ZERO);
+    }
+
+    #[test]
+    fn test_losses_of_earlier_days_do_not_count() {
+        let switch = KillSwitch::new(Decimal::from(100));
+        switch.record_at(Decimal::from(-90), Utc::now() - chrono::Duration::days(1));
+        assert!(!switch.record(Decimal::from(-50)));
+        assert!(!switch.is_tripped());
+    }
+
+    #[test]
+    fn test_tripped_state_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path()).unwrap();
+
+        let switch = KillSwitch::new(Decimal::from(100)).with_store(store.clone()).unwrap();
+        switch.record(Decimal::from(-150));
+
+        let restarted = KillSwitch::new(Decimal::from(100)).with_store(store.clone()).unwrap();
+        assert!(restarted.is_tripped());
+        restarted.reset();
+
+        // Losses from before the reset are not counted again on the next start
+        let again = KillSwitch::new(Decimal::from(100)).with_store(store).unwrap();
+        again.record_at(Decimal::from(-150), Utc::now() - chrono::Duration::minutes(1));
+        assert!(!again.is_tripped());
+    }
+}
@@ -0,0 +1,101 @@
+use crate::exchanges::PublicTrade;
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use std::collections::VecDeque;
+
+/// Rolling hourly traded volume per `exchange:symbol`, estimated from
+/// recent-trade samples. Each sample only covers the last few hundred
+/// trades, so its volume is scaled to an hourly rate over the time it
+/// spans, and the rates of the samples taken in the last hour are averaged.
+#[derive(Debug, Default)]
+pub struct TradeVolumeTracker {
+    /// (sampled at, base quantity per hour) per `exchange:symbol`
+    rates: DashMap<String, VecDeque<(DateTime<Utc>, Decimal)>>,
+}
+
+impl TradeVolumeTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a recent-trades sample taken at `now`. Trades older than an
+    /// hour are ignored; a sample spanning less than a second counts as one
+    /// second, so a burst of trades does not blow up the rate.
+    pub fn record_trades(&self, exchange: &str, symbol: &str, trades: &[PublicTrade], now: DateTime<Utc>) {
+        let hour_ago = now - Duration::hours(1);
+        let recent = trades.iter().filter(|t| t.timestamp > hour_ago);
+        let Some(oldest) = recent.clone().map(|t| t.timestamp).min() else {
+            // No trades for an hour
+            self.push_rate(exchange, symbol, Decimal::ZERO, now);
+            return;
+        };
+
+        let quantity: Decimal = recent.map(|t| t.quantity).sum();
+        let span_ms = now.signed_duration_since(oldest).num_milliseconds().max(1000);
+        let rate = quantity * Decimal::from(3_600_000) / Decimal::from(span_ms);
+        self.push_rate(exchange, symbol, rate, now);
+    }
+
+    fn push_rate(&self, exchange: &str, symbol: &str, rate: Decimal, now: DateTime<Utc>) {
+        let mut rates = self.rates.entry(format!("{}:{}", exchange, symbol)).or_default();
+        rates.push_back((now, rate));
+        while rates.front().is_some_and(|(at, _)| now.signed_duration_since(*at) > Duration::hours(1)) {
+            rates.pop_front();
+        }
+    }
+
+    /// Estimated base-asset quantity traded per hour, or `None` without a
+    /// sample from the last hour.
+    pub fn hourly_volume(&self, exchange: &str, symbol: &str, now: DateTime<Utc>) -> Option<Decimal> {
+        let rates = self.rates.get(&format!("{}:{}", exchange, symbol))?;
+        let recent: Vec<Decimal> = rates.iter()
+            .filter(|(at, _)| now.signed_duration_since(*at) <= Duration::hours(1))
+            .map(|(_, rate)| *rate)
+            .collect();
+        if recent.is_empty() {
+            return None;
+        }
+        Some(recent.iter().sum::<Decimal>() / Decimal::from(recent.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(quantity: i64, seconds_ago: i64, now: DateTime<Utc>) -> PublicTrade {
+        PublicTrade {
+            price: Decimal::from(100),
+            quantity: Decimal::from(quantity),
+            timestamp: now - Duration::seconds(seconds_ago),
+        }
+    }
+
+    #[test]
+    fn test_sample_is_scaled_to_hourly_rate() {
+        let tracker = TradeVolumeTracker::new();
+        let now = Utc::now();
+        // 6 units over the last 60 seconds
+        tracker.record_trades("Binance", "BTCUSDT", &[trade(1, 60, now), trade(5, 10, now)], now);
+        assert_eq!(tracker.hourly_volume("Binance", "BTCUSDT", now), Some(Decimal::from(360)));
+        assert_eq!(tracker.hourly_volume("Bybit", "BTCUSDT", now), None);
+    }
+
+    #[test]
+    fn test_rates_average_over_the_last_hour() {
+        let tracker = TradeVolumeTracker::new();
+        let now = Utc::now();
+        let expired = now - Duration::minutes(90);
+        let earlier = now - Duration::minutes(30);
+        tracker.record_trades("Bybit", "ETHBTC", &[trade(1000, 60, expired)], expired);
+        tracker.record_trades("Bybit", "ETHBTC", &[trade(6, 60, earlier)], earlier);
+        tracker.record_trades("Bybit", "ETHBTC", &[trade(12, 60, now)], now);
+        assert_eq!(tracker.hourly_volume("Bybit", "ETHBTC", now), Some(Decimal::from(540)));
+
+        // Trades older than an hour count as no volume
+        tracker.record_trades("Bybit", "ETHBTC", &[trade(5, 7200, now)], now);
+        assert_eq!(tracker.hourly_volume("Bybit", "ETHBTC", now), Some(Decimal::from(360)));
+        assert_eq!(tracker.hourly_volume("Bybit", "ETHBTC", now + Duration::hours(2)), None);
+    }
+}
@@ -1,16 +1,79 @@
-use crate::exchanges::{PriceMap, OrderRequest, OrderSide, OrderType, TradingFees};
+pub mod breaker;
+pub mod correlation;
+pub mod depeg;
+pub mod fiat;
+pub mod inventory;
+pub mod kill_switch;
+pub mod liquidity;
+pub mod outage;
+pub mod price_cache;
+pub mod queue;
+pub mod risk;
+pub mod spread;
+pub mod tick_filter;
+pub mod triangular;
+pub mod warmup;
+
+use crate::config::{
+    Config, FiatConfig, ListingConfig, OutageConfig, ParticipationConfig, RiskScoringConfig, StablecoinConfig, StrategyLimits, TickFilterConfig, WarmupConfig,
+};
+use crate::executor::{ExecutionResult, Executor, LastLook};
+use crate::ledger::ExecutionLedger;
+use crate::metrics::MarketMetrics;
+use crate::portfolio::PortfolioSnapshot;
+use crate::currency::CurrencyConverter;
+use crate::precision::{format_percentage, PricePrecision};
+use crate::reconcile::ReconciliationReport;
+use crate::report::{ExecutionReport, ReportStore};
+use crate::exchanges::{new_client_order_id, BookTicker, BookTickerMap, ExchangeError, OrderRequest, OrderSide, OrderType, TimeInForce};
+use crate::exchanges::fees::FeeModel;
+use crate::exchanges::depth::OrderBookStore;
+use crate::exchanges::snapshot::PriceLookup;
+use breaker::CircuitBreakers;
+use depeg::DepegGuard;
+use fiat::FiatCycle;
+use inventory::InventoryTracker;
+use kill_switch::KillSwitch;
+use outage::OutageDetector;
+use price_cache::{PriceCache, PriceEntry};
+use liquidity::TradeVolumeTracker;
+use risk::{CompositeRiskScorer, RiskInputs, Strategy};
+use spread::SpreadTracker;
+use tick_filter::TickFilter;
+use triangular::{cycle_profit_percentages, stablecoin_paths, TrianglePrices};
+use warmup::WarmupTracker;
 use anyhow::Result;
-use log::{info, warn};
+use log::{debug, error, info, warn};
 use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 use rust_decimal::prelude::*;
-use std::collections::HashMap;
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
-use std::sync::Arc;
+use sha2::{Digest, Sha256};
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Cached prices older than this are not compared against new quotes.
+pub const PRICE_AGE_LIMIT_SECONDS: i64 = 30;
+
+/// Triangles checked on each exchange as `A/USDT`, `B/A`, `B/USDT`.
+pub const TRIANGULAR_PATHS: [(&str, &str, &str); 3] = [
+    ("BTCUSDT", "ETHBTC", "ETHUSDT"),
+    ("BTCUSDT", "BNBBTC", "BNBUSDT"),
+    ("ETHUSDT", "ADAETH", "ADAUSDT"),
+];
+
+/// A triangle completed by a symbol an exchange listed while the bot ran.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListingPath {
+    pub exchange: String,
+    pub path: (String, String, String),
+    pub listed_at: DateTime<Utc>,
+}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArbitrageOpportunity {
     pub exchange: String,
+    pub strategy: Strategy,
     pub path: Vec<String>,
     pub profit_percentage: Decimal,
     pub net_profit_percentage: Decimal, // After fees
@@ -18,74 +81,224 @@ pub struct ArbitrageOpportunity {
     pub estimated_profit_usd: Decimal,
     pub risk_score: f32,
     pub execution_steps: Vec<ExecutionStep>,
+    /// Top of book of each quoted leg at detection time
+    #[serde(default)]
+    pub leg_quotes: Vec<LegQuote>,
     pub timestamp: DateTime<Utc>,
+    /// Identifies the scan that produced the opportunity (scan start, ms since epoch)
+    pub scan_id: i64,
 }
 
-#[derive(Debug, Clone)]
-pub struct ExecutionStep {
-    pub action: String,
-    pub symbol: String,
-    pub side: OrderSide,
-    pub quantity: Decimal,
-    pub expected_price: Decimal,
-    pub fees: Decimal,
+impl ArbitrageOpportunity {
+    /// Stable hash of venue, legs, expected prices and scan, used to make
+    /// sure the same opportunity is never executed twice.
+    pub fn idempotency_key(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(self.exchange.as_bytes());
+        for step in &self.execution_steps {
+            hasher.update(format!("|{}:{:?}:{}", step.symbol, step.side, step.expected_price.normalize()).as_bytes());
+        }
+        hasher.update(format!("|{}", self.scan_id).as_bytes());
+        hex::encode(hasher.finalize())
+    }
+    
+    /// Resizes the position by `factor`, keeping prices and percentages.
+    pub fn scale(&mut self, factor: Decimal) {
+        self.required_amount *= factor;
+        self.estimated_profit_usd *= factor;
+        for step in &mut self.execution_steps {
+            step.quantity *= factor;
+            step.fees *= factor;
+        }
+    }
+    
+    /// One-line summary with amounts in `currency`'s home currency; prices
+    /// in the path are already formatted at exchange precision.
+    pub fn describe(&self, currency: &CurrencyConverter) -> String {
+        format!("{} [{}]: net {}% (gross {}%), est. {} on {}, risk {:.2}",
+                self.exchange, self.path.join(" -> "),
+                format_percentage(self.net_profit_percentage), format_percentage(self.profit_percentage),
+                currency.format(self.estimated_profit_usd), currency.format(self.required_amount), self.risk_score)
+    }
+    
+    /// Rounds every leg's quantity down to its exchange's lot size. Each
+    /// leg spends what the previous one delivered, so the shrink rounding
+    /// causes carries over to the later legs before they are rounded.
+    pub fn quantize(&mut self, precision: &PricePrecision) {
+        let mut carry = Decimal::ONE;
+        for step in &mut self.execution_steps {
+            if step.quantity <= Decimal::ZERO {
+                continue;
+            }
+            let quantity = precision.quantize_qty(&step.exchange, &step.symbol, step.quantity * carry);
+            carry = quantity / step.quantity;
+            step.fees *= carry;
+            step.quantity = quantity;
+        }
+    }
 }
 
-pub struct ArbitrageEngine {
-    min_profit_threshold: Decimal,
-    max_position_size: Decimal,
-    trading_pairs: Vec<String>,
-    fees: TradingFees,
-    price_cache: Arc<DashMap<String, (Decimal, DateTime<Utc>)>>,
-    opportunity_history: Arc<DashMap<String, Vec<ArbitrageOpportunity>>>,
-    circuit_breaker: CircuitBreaker,
+/// One-line summary for logs and notifications, with amounts in USDT.
+impl std::fmt::Display for ArbitrageOpportunity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.describe(&CurrencyConverter::default()))
+    }
 }
 
-#[derive(Debug, Clone)]
-struct CircuitBreaker {
-    failure_count: Arc<std::sync::Mutex<u32>>,
-    last_failure: Arc<std::sync::Mutex<Option<DateTime<Utc>>>>,
-    threshold: u32,
-    reset_timeout: chrono::Duration,
+/// Best bid/offer of one leg's symbol when the opportunity was detected,
+/// so executable spreads can be told apart from last-price artifacts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LegQuote {
+    pub exchange: String,
+    pub symbol: String,
+    pub bid_price: Decimal,
+    pub ask_price: Decimal,
+    /// Top-of-book notional per side, in the symbol's quote currency
+    pub bid_depth: Decimal,
+    pub ask_depth: Decimal,
+    /// Ask minus bid relative to the mid price
+    pub spread_percentage: Decimal,
 }
 
-impl CircuitBreaker {
-    fn new(threshold: u32, reset_timeout_minutes: i64) -> Self {
+impl LegQuote {
+    pub fn new(exchange: &str, ticker: &BookTicker) -> Self {
+        let mid = ticker.mid_price();
         Self {
-            failure_count: Arc::new(std::sync::Mutex::new(0)),
-            last_failure: Arc::new(std::sync::Mutex::new(None)),
-            threshold,
-            reset_timeout: chrono::Duration::minutes(reset_timeout_minutes),
+            exchange: exchange.to_string(),
+            symbol: ticker.symbol.clone(),
+            bid_price: ticker.bid_price,
+            ask_price: ticker.ask_price,
+            bid_depth: ticker.bid_price * ticker.bid_quantity,
+            ask_depth: ticker.ask_price * ticker.ask_quantity,
+            spread_percentage: if mid > Decimal::ZERO {
+                (ticker.ask_price - ticker.bid_price) / mid * Decimal::ONE_HUNDRED
+            } else {
+                Decimal::ZERO
+            },
         }
     }
     
-    fn is_open(&self) -> bool {
-        let count = *self.failure_count.lock().unwrap();
-        if count >= self.threshold {
-            if let Some(last_failure) = *self.last_failure.lock().unwrap() {
-                let elapsed = Utc::now().signed_duration_since(last_failure);
-                return elapsed < self.reset_timeout;
-            }
+    /// Price the leg would trade at with a market order on `side`.
+    pub fn touch_price(&self, side: &OrderSide) -> Decimal {
+        match side {
+            OrderSide::Buy => self.ask_price,
+            OrderSide::Sell => self.bid_price,
         }
-        false
     }
-    
-    fn record_failure(&self) {
-        *self.failure_count.lock().unwrap() += 1;
-        *self.last_failure.lock().unwrap() = Some(Utc::now());
+}
+
+/// Both venues' book tickers for one scan.
+struct VenueQuotes<'a> {
+    binance: &'a BookTickerMap,
+    bybit: &'a BookTickerMap,
+}
+
+impl VenueQuotes<'_> {
+    fn get(&self, exchange: &str, symbol: &str) -> Option<&BookTicker> {
+        match exchange {
+            "Binance" => self.binance.get(symbol),
+            "Bybit" => self.bybit.get(symbol),
+            _ => None,
+        }
     }
     
-    fn reset(&self) {
-        *self.failure_count.lock().unwrap() = 0;
-        *self.last_failure.lock().unwrap() = None;
+    /// Quotes for each step whose symbol is quoted on its venue.
+    fn for_steps(&self, steps: &[ExecutionStep]) -> Vec<LegQuote> {
+        steps.iter()
+            .filter_map(|step| self.get(&step.exchange, &step.symbol).map(|t| LegQuote::new(&step.exchange, t)))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionStep {
+    pub exchange: String,
+    pub action: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub quantity: Decimal,
+    pub expected_price: Decimal,
+    pub fees: Decimal,
+    pub client_order_id: String,
+}
+
+impl ExecutionStep {
+    /// Market order for this step, tagged with the step's client order ID so
+    /// a timed-out submission can be found again instead of resent.
+    pub fn order_request(&self) -> OrderRequest {
+        OrderRequest {
+            symbol: self.symbol.clone(),
+            side: self.side.clone(),
+            quantity: self.quantity,
+            quote_quantity: None,
+            price: None,
+            order_type: OrderType::Market,
+            time_in_force: TimeInForce::Gtc,
+            client_order_id: Some(self.client_order_id.clone()),
+        }
+    }
+}
+
+pub struct ArbitrageEngine {
+    cross_exchange: StrategyLimits,
+    triangular: StrategyLimits,
+    trading_pairs: Vec<String>,
+    /// Stable-stable triangles, checked against their own threshold
+    stablecoin_paths: Vec<(String, String, String)>,
+    stablecoin_min_profit_threshold: Decimal,
+    depeg: DepegGuard,
+    fiat: FiatConfig,
+    listings: ListingConfig,
+    /// Triangles of new listings, checked on their exchange only
+    listing_paths: RwLock<Vec<ListingPath>>,
+    tick_filter: TickFilter,
+    fees: Arc<FeeModel>,
+    price_cache: Arc<PriceCache>,
+    opportunity_history: Arc<DashMap<String, Vec<ArbitrageOpportunity>>>,
+    /// `monitoring.opportunity_history_days`; 0 keeps every opportunity
+    opportunity_history_days: i64,
+    circuit_breakers: Arc<CircuitBreakers>,
+    outages: Arc<OutageDetector>,
+    inventory: Option<Arc<InventoryTracker>>,
+    kill_switch: Arc<KillSwitch>,
+    max_cycle_slippage_percentage: Decimal,
+    spread_tracker: SpreadTracker,
+    spread_zscore_threshold: f64,
+    ledger: Option<Arc<ExecutionLedger>>,
+    reports: Option<ReportStore>,
+    executor: Option<Executor>,
+    risk_scorer: CompositeRiskScorer,
+    risk_inputs: RiskInputs,
+    risk_scoring: RiskScoringConfig,
+    warmup: WarmupTracker,
+    precision: Arc<PricePrecision>,
+    currency: Arc<CurrencyConverter>,
+    trade_volumes: TradeVolumeTracker,
+    min_hourly_volume_usd: Decimal,
+    participation: ParticipationConfig,
+    order_books: Option<(Arc<OrderBookStore>, std::time::Duration)>,
+    /// Largest gap between the capture times of a path's legs; zero disables
+    max_leg_skew: chrono::Duration,
+    metrics: Option<Arc<MarketMetrics>>,
+}
+
+impl Default for ArbitrageEngine {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 impl ArbitrageEngine {
     pub fn new() -> Self {
-        Self {
+        let limits = StrategyLimits {
+            enabled: true,
             min_profit_threshold: Decimal::from_str_exact("0.5").unwrap(), // 0.5% minimum profit
+            min_profit_usd: Decimal::ZERO,
             max_position_size: Decimal::from_str_exact("1000.0").unwrap(), // $1000 max position
+        };
+        Self {
+            cross_exchange: limits.clone(),
+            triangular: limits,
             trading_pairs: vec![
                 "BTCUSDT".to_string(),
                 "ETHUSDT".to_string(),
@@ -94,35 +307,437 @@ impl ArbitrageEngine {
                 "DOTUSDT".to_string(),
                 "SOLUSDT".to_string(),
             ],
-            fees: TradingFees::default(),
-            price_cache: Arc::new(DashMap::new()),
+            stablecoin_paths: Vec::new(),
+            stablecoin_min_profit_threshold: StablecoinConfig::default().min_profit_threshold,
+            depeg: DepegGuard::new(&StablecoinConfig::default()),
+            fiat: FiatConfig::default(),
+            listings: ListingConfig::default(),
+            listing_paths: RwLock::new(Vec::new()),
+            tick_filter: TickFilter::new(TickFilterConfig::default()),
+            fees: Arc::new(FeeModel::default()),
+            price_cache: Arc::new(PriceCache::new()),
+            opportunity_history: Arc::new(DashMap::new()),
+            opportunity_history_days: 7,
+            circuit_breakers: Arc::new(CircuitBreakers::new(5, 5)), // 5 failures, 5 minute reset
+            outages: Arc::new(OutageDetector::new(OutageConfig::default())),
+            inventory: None,
+            kill_switch: Arc::new(KillSwitch::new(Decimal::ZERO)),
+            max_cycle_slippage_percentage: Decimal::ONE,
+            spread_tracker: SpreadTracker::new(300, 30),
+            spread_zscore_threshold: 2.5,
+            ledger: None,
+            reports: None,
+            executor: None,
+            risk_scorer: CompositeRiskScorer::from_config(&RiskScoringConfig::default(), Decimal::from(10_000)),
+            risk_inputs: RiskInputs::new(RiskScoringConfig::default().volatility_window),
+            risk_scoring: RiskScoringConfig::default(),
+            warmup: WarmupTracker::new(WarmupConfig::default()),
+            precision: Arc::new(PricePrecision::new()),
+            currency: Arc::new(CurrencyConverter::default()),
+            trade_volumes: TradeVolumeTracker::new(),
+            min_hourly_volume_usd: Decimal::ZERO,
+            participation: ParticipationConfig::default(),
+            order_books: None,
+            max_leg_skew: chrono::Duration::milliseconds(2000),
+            metrics: None,
+        }
+    }
+    
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            cross_exchange: config.strategy_limits(Strategy::CrossExchange),
+            triangular: config.strategy_limits(Strategy::Triangular),
+            trading_pairs: config.trading.trading_pairs.clone(),
+            stablecoin_paths: if config.stablecoins.triangles_enabled {
+                stablecoin_paths(&config.stablecoins.assets)
+            } else {
+                Vec::new()
+            },
+            stablecoin_min_profit_threshold: config.stablecoins.min_profit_threshold,
+            depeg: DepegGuard::new(&config.stablecoins),
+            fiat: config.fiat.clone(),
+            listings: config.listings.clone(),
+            listing_paths: RwLock::new(Vec::new()),
+            tick_filter: TickFilter::new(config.tick_filter.clone()),
+            fees: Arc::new(FeeModel::from_config(&config.fees)),
+            price_cache: Arc::new(PriceCache::new()),
             opportunity_history: Arc::new(DashMap::new()),
-            circuit_breaker: CircuitBreaker::new(5, 5), // 5 failures, 5 minute reset
+            opportunity_history_days: config.monitoring.opportunity_history_days,
+            circuit_breakers: Arc::new(CircuitBreakers::new(
+                config.risk.circuit_breaker_threshold,
+                config.risk.circuit_breaker_reset_minutes,
+            )),
+            outages: Arc::new(OutageDetector::new(config.exchanges.outage.clone())),
+            inventory: config.inventory.enabled.then(|| Arc::new(InventoryTracker::new(config.inventory.clone()))),
+            kill_switch: Arc::new(KillSwitch::new(config.risk.max_daily_loss)),
+            max_cycle_slippage_percentage: config.risk.max_cycle_slippage_percentage,
+            spread_tracker: SpreadTracker::new(
+                config.trading.spread_window_size,
+                config.trading.spread_min_samples,
+            ),
+            spread_zscore_threshold: config.trading.spread_zscore_threshold,
+            ledger: None,
+            reports: None,
+            executor: None,
+            risk_scorer: CompositeRiskScorer::from_config(&config.risk.scoring, config.trading.min_liquidity_usd),
+            risk_inputs: RiskInputs::new(config.risk.scoring.volatility_window),
+            risk_scoring: config.risk.scoring.clone(),
+            warmup: WarmupTracker::new(config.warmup.clone()),
+            precision: Arc::new(PricePrecision::new()),
+            currency: Arc::new(CurrencyConverter::default()),
+            trade_volumes: TradeVolumeTracker::new(),
+            min_hourly_volume_usd: config.liquidity.min_hourly_volume_usd,
+            participation: config.risk.participation.clone(),
+            order_books: None,
+            max_leg_skew: chrono::Duration::milliseconds(config.trading.max_leg_skew_ms as i64),
+            metrics: None,
+        }
+    }
+    
+    /// Lowers every strategy's profit threshold to at most `threshold`, e.g.
+    /// to detect down to the lowest alert threshold in watch-only mode.
+    pub fn with_min_profit_threshold(mut self, threshold: Decimal) -> Self {
+        for limits in [&mut self.cross_exchange, &mut self.triangular] {
+            limits.min_profit_threshold = limits.min_profit_threshold.min(threshold);
+        }
+        self.stablecoin_min_profit_threshold = self.stablecoin_min_profit_threshold.min(threshold);
+        self
+    }
+    
+    /// Settings `strategy` is detected with.
+    pub fn limits(&self, strategy: Strategy) -> &StrategyLimits {
+        match strategy {
+            Strategy::CrossExchange => &self.cross_exchange,
+            Strategy::Triangular => &self.triangular,
+        }
+    }
+    
+    /// Every triangle checked on each exchange as `A/USDT`, `B/A`, `B/USDT`:
+    /// `TRIANGULAR_PATHS` plus the stable-stable ones when enabled.
+    pub fn triangle_paths(&self) -> Vec<(String, String, String)> {
+        if !self.triangular.enabled {
+            return Vec::new();
+        }
+        TRIANGULAR_PATHS.iter()
+            .map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string()))
+            .chain(self.stablecoin_paths.iter().cloned())
+            .collect()
+    }
+    
+    /// Adds the triangles a symbol listed on `exchange` at `listed_at`
+    /// completes, leaving out those already checked there. Returns the
+    /// ones added.
+    pub fn register_listing_paths(
+        &self,
+        exchange: &str,
+        paths: Vec<(String, String, String)>,
+        listed_at: DateTime<Utc>,
+    ) -> Vec<(String, String, String)> {
+        let configured = self.triangle_paths();
+        let mut listing_paths = self.listing_paths.write().unwrap_or_else(PoisonError::into_inner);
+        let mut added = Vec::new();
+        for path in paths {
+            let known = configured.contains(&path)
+                || listing_paths.iter().any(|listing| listing.exchange == exchange && listing.path == path);
+            if !known {
+                listing_paths.push(ListingPath { exchange: exchange.to_string(), path: path.clone(), listed_at });
+                added.push(path);
+            }
+        }
+        added
+    }
+    
+    /// Triangles registered for new listings.
+    pub fn listing_paths(&self) -> Vec<ListingPath> {
+        self.listing_paths.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+    
+    /// Threshold of a listing triangle at `now`: `listings.min_profit_threshold`
+    /// for `boost_minutes` after the listing, when lower, then the
+    /// triangular one.
+    fn listing_threshold(&self, listed_at: DateTime<Utc>, now: DateTime<Utc>) -> Decimal {
+        let boosted = now.signed_duration_since(listed_at) < chrono::Duration::minutes(self.listings.boost_minutes as i64);
+        match self.listings.min_profit_threshold {
+            Some(threshold) if boosted => threshold.min(self.triangular.min_profit_threshold),
+            _ => self.triangular.min_profit_threshold,
+        }
+    }
+    
+    /// Every market fiat cycles may trade when `fiat.enabled`. Each fiat is
+    /// listed under one of its two FX symbols; the other is never priced.
+    pub fn fiat_symbols(&self) -> Vec<String> {
+        if !self.fiat.enabled || !self.triangular.enabled {
+            return Vec::new();
+        }
+        let mut symbols = Vec::new();
+        for fiat in &self.fiat.currencies {
+            symbols.extend([format!("{}USDT", fiat), format!("USDT{}", fiat)]);
+            for asset in &self.fiat.assets {
+                symbols.extend([format!("{}USDT", asset), format!("{}{}", asset, fiat)]);
+            }
+        }
+        symbols
+    }
+    
+    /// Enables live order submission; without an executor opportunities are only logged.
+    pub fn with_executor(mut self, executor: Executor) -> Self {
+        self.executor = Some(executor);
+        self
+    }
+    
+    /// Attaches the persistent ledger used to reject duplicate executions.
+    pub fn with_ledger(mut self, ledger: Arc<ExecutionLedger>) -> Self {
+        self.ledger = Some(ledger);
+        self
+    }
+    
+    /// Replaces the kill switch, e.g. with one that persists its state.
+    /// Call before `with_reports` so today's losses are counted.
+    pub fn with_kill_switch(mut self, kill_switch: Arc<KillSwitch>) -> Self {
+        self.kill_switch = kill_switch;
+        self
+    }
+    
+    /// Attaches the store execution reports are persisted to. Past reports
+    /// seed the fill-history risk scorer and today's realized loss.
+    pub fn with_reports(mut self, reports: ReportStore) -> Self {
+        match reports.reports() {
+            Ok(history) => {
+                for report in &history {
+                    self.risk_inputs.record_execution(&report.exchange, report.completed);
+                }
+                let today = Utc::now().date_naive();
+                let today: Vec<_> = history.into_iter().filter(|r| r.executed_at.date_naive() == today).collect();
+                self.kill_switch.seed(&today);
+            }
+            Err(e) => warn!("Failed to load execution history for risk scoring: {}", e),
+        }
+        self.reports = Some(reports);
+        self
+    }
+    
+    /// Shares tick size metadata so opportunity paths print prices at
+    /// exchange precision.
+    pub fn with_precision(mut self, precision: Arc<PricePrecision>) -> Self {
+        self.precision = precision;
+        self
+    }
+    
+    /// Shares the home currency converter, which logged amounts are shown
+    /// in and which the scanned prices keep at the current FX rate.
+    pub fn with_currency(mut self, currency: Arc<CurrencyConverter>) -> Self {
+        self.currency = currency;
+        self
+    }
+    
+    /// Local order books used to cap leg sizes at a share of visible depth.
+    /// Books older than `max_age` are ignored.
+    pub fn with_order_books(mut self, store: Arc<OrderBookStore>, max_age: std::time::Duration) -> Self {
+        self.order_books = Some((store, max_age));
+        self
+    }
+    
+    /// Series that execution results are counted in for `/metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<MarketMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Inputs for the risk scorers, for callers that observe latency or depth.
+    pub fn risk_inputs(&self) -> &RiskInputs {
+        &self.risk_inputs
+    }
+    
+    /// Symbols' warmup state, for callers that fetch candles.
+    pub fn warmup(&self) -> &WarmupTracker {
+        &self.warmup
+    }
+    
+    /// Traded volume per symbol, for callers that sample recent trades.
+    pub fn trade_volumes(&self) -> &TradeVolumeTracker {
+        &self.trade_volumes
+    }
+    
+    /// Largest fraction of the opportunity's size every leg can take under
+    /// the participation limits. Legs without book or volume data are not
+    /// limited.
+    fn participation_scale(&self, opportunity: &ArbitrageOpportunity) -> Decimal {
+        let limits = &self.participation;
+        let now = Utc::now();
+        let mut scale = Decimal::ONE;
+        
+        for step in opportunity.execution_steps.iter().filter(|s| s.quantity > Decimal::ZERO) {
+            if limits.max_depth_percentage > Decimal::ZERO {
+                if let Some((store, max_age)) = &self.order_books {
+                    if let Some(book) = store.fresh(&step.exchange, &step.symbol, limits.depth_levels, *max_age) {
+                        let levels = match step.side {
+                            OrderSide::Buy => &book.asks,
+                            OrderSide::Sell => &book.bids,
+                        };
+                        let depth: Decimal = levels.iter().map(|(_, quantity)| *quantity).sum();
+                        let allowed = depth * limits.max_depth_percentage / Decimal::ONE_HUNDRED;
+                        scale = scale.min(allowed / step.quantity);
+                    }
+                }
+            }
+            if limits.max_volume_percentage > Decimal::ZERO {
+                if let Some(volume) = self.trade_volumes.hourly_volume(&step.exchange, &step.symbol, now) {
+                    let allowed = volume * limits.max_volume_percentage / Decimal::ONE_HUNDRED;
+                    scale = scale.min(allowed / step.quantity);
+                }
+            }
+        }
+        scale
+    }
+    
+    /// Shrinks the opportunity to the participation limits. Returns false
+    /// when it would end up below `min_position_size`.
+    fn fit_to_participation(&self, opportunity: &mut ArbitrageOpportunity) -> bool {
+        let scale = self.participation_scale(opportunity);
+        if scale >= Decimal::ONE {
+            return true;
+        }
+        if opportunity.required_amount * scale < self.participation.min_position_size {
+            debug!("Skipping {} [{}]: participation limits allow only {}", opportunity.exchange,
+                   opportunity.path.join(" -> "), self.currency.format(opportunity.required_amount * scale));
+            return false;
+        }
+        debug!("Shrinking {} position from {} to {} to fit participation limits", opportunity.exchange,
+               self.currency.format(opportunity.required_amount), self.currency.format(opportunity.required_amount * scale));
+        opportunity.scale(scale);
+        true
+    }
+    
+    /// Whether the opportunity, at its final size, is expected to make at
+    /// least its strategy's `min_profit_usd`.
+    fn clears_min_profit_usd(&self, opportunity: &ArbitrageOpportunity) -> bool {
+        let min_profit_usd = self.limits(opportunity.strategy).min_profit_usd;
+        if min_profit_usd <= Decimal::ZERO || opportunity.estimated_profit_usd >= min_profit_usd {
+            return true;
+        }
+        debug!("Skipping {} [{}]: estimated profit {} is below the {} minimum", opportunity.exchange,
+               opportunity.path.join(" -> "), self.currency.format(opportunity.estimated_profit_usd), self.currency.format(min_profit_usd));
+        false
+    }
+    
+    /// First leg trading less than `liquidity.min_hourly_volume_usd`, with
+    /// its hourly volume in USD. Legs without volume data are not judged.
+    fn thin_leg<'a>(&self, opportunity: &'a ArbitrageOpportunity) -> Option<(&'a ExecutionStep, Decimal)> {
+        if self.min_hourly_volume_usd <= Decimal::ZERO {
+            return None;
         }
+        let now = Utc::now();
+        opportunity.execution_steps.iter()
+            .filter(|step| step.quantity > Decimal::ZERO)
+            .find_map(|step| {
+                let volume = self.trade_volumes.hourly_volume(&step.exchange, &step.symbol, now)?;
+                // Every leg moves roughly the position's USD value
+                let volume_usd = volume * opportunity.required_amount / step.quantity;
+                (volume_usd < self.min_hourly_volume_usd).then_some((step, volume_usd))
+            })
+    }
+    
+    pub fn is_circuit_open(&self) -> bool {
+        self.circuit_breakers.is_open()
+    }
+    
+    /// Global and per-exchange breakers, shared so scan errors and the
+    /// admin server can trip, inspect and reset them.
+    pub fn circuit_breakers(&self) -> Arc<CircuitBreakers> {
+        self.circuit_breakers.clone()
+    }
+    
+    pub fn outages(&self) -> Arc<OutageDetector> {
+        self.outages.clone()
+    }
+    
+    /// Held balances cross-exchange opportunities are sized to, when
+    /// `inventory.enabled`; the scan loop keeps them loaded.
+    pub fn inventory(&self) -> Option<Arc<InventoryTracker>> {
+        self.inventory.clone()
+    }
+    
+    /// Fee schedule of each venue; startup replaces it with the account's
+    /// rates when `fees.fetch_account_rates` is set.
+    pub fn fee_model(&self) -> Arc<FeeModel> {
+        self.fees.clone()
+    }
+    
+    /// Daily loss kill switch, shared with the control API for resets.
+    pub fn kill_switch(&self) -> Arc<KillSwitch> {
+        self.kill_switch.clone()
+    }
+    
+    /// Runs cross-exchange and triangular detection on one scan's prices,
+    /// given either as `PriceMap`s or as `PriceSnapshot`s. Without quotes,
+    /// cross-exchange pairs are compared at last price on both sides.
+    pub async fn analyze_opportunities<P: PriceLookup>(
+        &self,
+        binance_prices: &P,
+        bybit_prices: &P,
+    ) -> Result<Vec<ArbitrageOpportunity>> {
+        self.analyze_opportunities_with_quotes(binance_prices, bybit_prices, &BookTickerMap::new(), &BookTickerMap::new()).await
     }
     
-    pub async fn analyze_opportunities(
+    /// Like `analyze_opportunities`, but cross-exchange pairs quoted on both
+    /// venues buy at the ask and sell at the bid, so a spread that only
+    /// exists between last prices is not reported.
+    pub async fn analyze_opportunities_with_quotes<P: PriceLookup>(
         &self,
-        binance_prices: &PriceMap,
-        bybit_prices: &PriceMap,
-    ) -> Result<()> {
-        if self.circuit_breaker.is_open() {
+        binance_prices: &P,
+        bybit_prices: &P,
+        binance_quotes: &BookTickerMap,
+        bybit_quotes: &BookTickerMap,
+    ) -> Result<Vec<ArbitrageOpportunity>> {
+        if self.circuit_breakers.is_open() {
             warn!("Circuit breaker is open, skipping opportunity analysis");
-            return Ok(());
+            return Ok(Vec::new());
+        }
+        
+        let mut opportunities = Vec::new();
+        let scan_id = Utc::now().timestamp_millis();
+        
+        let binance_prices = &self.tick_filter.screen("Binance", binance_prices);
+        let bybit_prices = &self.tick_filter.screen("Bybit", bybit_prices);
+        for (exchange, prices) in [("Binance", binance_prices), ("Bybit", bybit_prices)] {
+            self.risk_inputs.observe_prices(exchange, prices);
+            self.depeg.observe(exchange, prices);
+            self.currency.observe(prices);
         }
+        let quotes = VenueQuotes { binance: binance_quotes, bybit: bybit_quotes };
         
         // Check for cross-exchange arbitrage opportunities
-        for pair in &self.trading_pairs {
+        let cross_exchange_pairs: &[String] = if self.cross_exchange.enabled { &self.trading_pairs } else { &[] };
+        for pair in cross_exchange_pairs {
             if let (Some(binance_price), Some(bybit_price)) = 
-                (binance_prices.get(pair), bybit_prices.get(pair)) {
+                (binance_prices.price(pair), bybit_prices.price(pair)) {
+                
+                // (bid, ask) per venue; last price on both sides unless both venues are quoted
+                let (binance_quote, bybit_quote) = match (binance_quotes.get(pair), bybit_quotes.get(pair)) {
+                    (Some(binance), Some(bybit)) => (
+                        (binance.bid_price, binance.ask_price),
+                        (bybit.bid_price, bybit.ask_price),
+                    ),
+                    _ => ((binance_price, binance_price), (bybit_price, bybit_price)),
+                };
+                let binance_mid = (binance_quote.0 + binance_quote.1) / Decimal::TWO;
+                let bybit_mid = (bybit_quote.0 + bybit_quote.1) / Decimal::TWO;
+                let binance_time = binance_quotes.get(pair).and_then(|q| q.timestamp).or_else(|| binance_prices.exchange_time(pair));
+                let bybit_time = bybit_quotes.get(pair).and_then(|q| q.timestamp).or_else(|| bybit_prices.exchange_time(pair));
                 
                 // Validate price freshness
-                if !self.is_price_fresh(pair, *binance_price, *bybit_price) {
+                if !self.is_price_fresh(pair, [("Binance", binance_mid, binance_time), ("Bybit", bybit_mid, bybit_time)]) {
+                    continue;
+                }
+                if let Some(skew) = self.excess_leg_skew([
+                    binance_time.or_else(|| binance_prices.captured_at(pair)),
+                    bybit_time.or_else(|| bybit_prices.captured_at(pair)),
+                ]) {
+                    debug!("Skipping {}: Binance and Bybit prices captured {}ms apart", pair, skew.num_milliseconds());
                     continue;
                 }
                 
-                let price_diff = (binance_price - bybit_price).abs();
-                let avg_price = (binance_price + bybit_price) / Decimal::TWO;
+                let avg_price = (binance_mid + bybit_mid) / Decimal::TWO;
                 
                 // Prevent division by zero
                 if avg_price == Decimal::ZERO {
@@ -130,234 +745,429 @@ impl ArbitrageEngine {
                     continue;
                 }
                 
-                let gross_profit_percentage = (price_diff / avg_price) * Decimal::ONE_HUNDRED;
+                // Sell at one venue's bid, buy at the other's ask; negative when neither direction crosses
+                let (sell_exchange, buy_exchange, sell_price, buy_price) = if binance_quote.0 - bybit_quote.1 >= bybit_quote.0 - binance_quote.1 {
+                    ("Binance", "Bybit", binance_quote.0, bybit_quote.1)
+                } else {
+                    ("Bybit", "Binance", bybit_quote.0, binance_quote.1)
+                };
+                let gross_profit_percentage = ((sell_price - buy_price) / avg_price) * Decimal::ONE_HUNDRED;
                 
                 // Calculate net profit after fees
-                let total_fees = self.fees.taker_fee * Decimal::TWO; // Two trades
+                let (sell_fee, buy_fee) = (self.fees.taker_fee(sell_exchange), self.fees.taker_fee(buy_exchange));
+                let total_fees = sell_fee + buy_fee; // Two trades
                 let net_profit_percentage = gross_profit_percentage - (total_fees * Decimal::ONE_HUNDRED);
                 
-                if net_profit_percentage > self.min_profit_threshold {
-                    let (sell_exchange, buy_exchange, sell_price, buy_price) = if binance_price > bybit_price {
-                        ("Binance", "Bybit", *binance_price, *bybit_price)
-                    } else {
-                        ("Bybit", "Binance", *bybit_price, *binance_price)
-                    };
-                    
-                    let quantity = self.max_position_size / sell_price;
+                // Track the signed spread so persistent venue offsets don't register as opportunities
+                let signed_spread = ((binance_mid - bybit_mid) / avg_price * Decimal::ONE_HUNDRED)
+                    .to_f64()
+                    .unwrap_or(0.0);
+                let spread_zscore = self.spread_tracker.observe(pair, signed_spread);
+                let is_spread_outlier = spread_zscore
+                    .map(|z| z.abs() >= self.spread_zscore_threshold)
+                    .unwrap_or(false);
+                
+                if net_profit_percentage > self.cross_exchange.min_profit_threshold && !is_spread_outlier {
+                    debug!("Ignoring {} spread {:.4}% (z-score {:?}) as within normal range", 
+                           pair, signed_spread, spread_zscore);
+                    continue;
+                }
+                
+                if net_profit_percentage > self.cross_exchange.min_profit_threshold {
+                    let quantity = self.cross_exchange.max_position_size / sell_price;
                     let estimated_profit = (sell_price - buy_price) * quantity - 
-                                         (sell_price * quantity * self.fees.taker_fee) -
-                                         (buy_price * quantity * self.fees.taker_fee);
+                                         (sell_price * quantity * sell_fee) -
+                                         (buy_price * quantity * buy_fee);
                     
                     let execution_steps = vec![
                         ExecutionStep {
+                            exchange: sell_exchange.to_string(),
                             action: format!("Sell on {}", sell_exchange),
                             symbol: pair.clone(),
                             side: OrderSide::Sell,
                             quantity,
                             expected_price: sell_price,
-                            fees: sell_price * quantity * self.fees.taker_fee,
+                            fees: sell_price * quantity * sell_fee,
+                            client_order_id: new_client_order_id(),
                         },
                         ExecutionStep {
+                            exchange: buy_exchange.to_string(),
                             action: format!("Buy on {}", buy_exchange),
                             symbol: pair.clone(),
                             side: OrderSide::Buy,
                             quantity,
                             expected_price: buy_price,
-                            fees: buy_price * quantity * self.fees.taker_fee,
+                            fees: buy_price * quantity * buy_fee,
+                            client_order_id: new_client_order_id(),
                         },
                     ];
                     
-                    let opportunity = ArbitrageOpportunity {
+                    let leg_quotes = quotes.for_steps(&execution_steps);
+                    let mut opportunity = ArbitrageOpportunity {
                         exchange: format!("{}->{}", sell_exchange, buy_exchange),
+                        strategy: Strategy::CrossExchange,
                         path: vec![
-                            format!("Sell {} on {} at {}", pair, sell_exchange, sell_price),
-                            format!("Buy {} on {} at {}", pair, buy_exchange, buy_price)
+                            format!("Sell {} on {} at {}", pair, sell_exchange,
+                                    self.precision.format_price(sell_exchange, pair, sell_price)),
+                            format!("Buy {} on {} at {}", pair, buy_exchange,
+                                    self.precision.format_price(buy_exchange, pair, buy_price))
                         ],
                         profit_percentage: gross_profit_percentage,
                         net_profit_percentage,
-                        required_amount: self.max_position_size,
+                        required_amount: self.cross_exchange.max_position_size,
                         estimated_profit_usd: estimated_profit,
-                        risk_score: self.calculate_risk_score(&price_diff, &avg_price),
+                        risk_score: 0.0,
                         execution_steps,
+                        leg_quotes,
                         timestamp: Utc::now(),
+                        scan_id,
                     };
+                    if self.inventory.as_ref().is_some_and(|inventory| !inventory.fit(&mut opportunity)) {
+                        continue;
+                    }
+                    if !self.fit_to_participation(&mut opportunity) || !self.clears_min_profit_usd(&opportunity) {
+                        continue;
+                    }
+                    opportunity.quantize(&self.precision);
+                    opportunity.risk_score = self.risk_scorer.score(&opportunity, &self.risk_inputs);
                     
-                    info!("Arbitrage opportunity found: {:?}", opportunity);
+                    if let Some((step, volume_usd)) = self.thin_leg(&opportunity) {
+                        debug!("Skipping {}: {} on {} trades only {}/h", opportunity.exchange, step.symbol, step.exchange, self.currency.format(volume_usd));
+                        continue;
+                    }
+                    
+                    info!("Arbitrage opportunity found (spread z-score {:.2}): {}", 
+                          spread_zscore.unwrap_or_default(), opportunity.describe(&self.currency));
                     // self.execute_arbitrage(&opportunity).await?;
+                    opportunities.push(opportunity);
                 }
             }
         }
         
         // Check for triangular arbitrage within each exchange
-        self.check_triangular_arbitrage(binance_prices, "Binance").await?;
-        self.check_triangular_arbitrage(bybit_prices, "Bybit").await?;
+        opportunities.extend(self.check_triangular_arbitrage(binance_prices, "Binance", &quotes, scan_id).await?);
+        opportunities.extend(self.check_triangular_arbitrage(bybit_prices, "Bybit", &quotes, scan_id).await?);
+        opportunities.extend(self.check_fiat_arbitrage(binance_prices, "Binance", &quotes, scan_id));
+        opportunities.extend(self.check_fiat_arbitrage(bybit_prices, "Bybit", &quotes, scan_id));
         
-        Ok(())
+        Ok(opportunities)
     }
     
-    async fn check_triangular_arbitrage(&self, prices: &PriceMap, exchange: &str) -> Result<()> {
-        // Common triangular arbitrage paths
-        let triangular_paths = vec![
-            ("BTCUSDT", "ETHBTC", "ETHUSDT"),
-            ("BTCUSDT", "BNBBTC", "BNBUSDT"),
-            ("ETHUSDT", "ADAETH", "ADAUSDT"),
-        ];
+    async fn check_triangular_arbitrage(
+        &self,
+        prices: &impl PriceLookup,
+        exchange: &str,
+        quotes: &VenueQuotes<'_>,
+        scan_id: i64,
+    ) -> Result<Vec<ArbitrageOpportunity>> {
+        let mut opportunities = Vec::new();
+        if !self.triangular.enabled {
+            return Ok(opportunities);
+        }
         
-        for (pair1, pair2, pair3) in triangular_paths {
+        let now = Utc::now();
+        let listing_paths: Vec<_> = self.listing_paths.read().unwrap_or_else(PoisonError::into_inner).iter()
+            .filter(|listing| listing.exchange == exchange)
+            .map(|listing| (listing.path.clone(), self.listing_threshold(listing.listed_at, now)))
+            .collect();
+        let paths = TRIANGULAR_PATHS.iter()
+            .map(|&(a, b, c)| ((a, b, c), self.triangular.min_profit_threshold))
+            .chain(self.stablecoin_paths.iter()
+                .map(|(a, b, c)| ((a.as_str(), b.as_str(), c.as_str()), self.stablecoin_min_profit_threshold)))
+            .chain(listing_paths.iter()
+                .map(|((a, b, c), threshold)| ((a.as_str(), b.as_str(), c.as_str()), *threshold)));
+        for ((pair1, pair2, pair3), min_profit_threshold) in paths {
             if let (Some(price1), Some(price2), Some(price3)) = 
-                (prices.get(pair1), prices.get(pair2), prices.get(pair3)) {
+                (prices.price(pair1), prices.price(pair2), prices.price(pair3)) {
                 
                 // Prevent division by zero
-                if *price1 == Decimal::ZERO || *price2 == Decimal::ZERO || *price3 == Decimal::ZERO {
+                if price1 == Decimal::ZERO || price2 == Decimal::ZERO || price3 == Decimal::ZERO {
+                    continue;
+                }
+                if let Some(skew) = self.excess_leg_skew([pair1, pair2, pair3].map(|pair| prices.captured_at(pair))) {
+                    debug!("Skipping {} {}/{}/{}: legs captured {}ms apart", exchange, pair1, pair2, pair3, skew.num_milliseconds());
                     continue;
                 }
                 
-                // Calculate triangular arbitrage profit
-                // Example: BTCUSDT=50000, ETHBTC=0.06, ETHUSDT=3000
-                // Forward path: USDT -> BTC -> ETH -> USDT
-                // 1 USDT -> 1/50000 BTC -> (1/50000)*0.06 ETH -> (1/50000)*0.06*3000 USDT = 0.0036 USDT
-                let forward_result = (Decimal::ONE / price1) * price2 * price3;
-                let forward_gross_profit = (forward_result - Decimal::ONE) * Decimal::ONE_HUNDRED;
+                let triangle = TrianglePrices { a_usdt: price1, b_a: price2, b_usdt: price3 };
+                let taker_fee = self.fees.taker_fee(exchange);
+                // Share of each leg's proceeds the next leg can trade
+                let keep = self.fees.received_factor(exchange);
+                let triangular_fees = taker_fee * Decimal::from(3);
                 
-                // Account for three trading fees (3 trades in triangular arbitrage)
-                let triangular_fees = self.fees.taker_fee * Decimal::from(3);
-                let forward_net_profit = forward_gross_profit - (triangular_fees * Decimal::ONE_HUNDRED);
+                // Forward path: USDT -> BTC -> ETH -> USDT
+                let forward_result = triangle.forward_return();
+                let (forward_gross_profit, forward_net_profit) =
+                    cycle_profit_percentages(forward_result, taker_fee, 3);
                 
-                // Reverse path: USDT -> ETH -> BTC -> USDT  
-                // 1 USDT -> 1/3000 ETH -> (1/3000)/0.06 BTC -> ((1/3000)/0.06)*50000 USDT
-                let reverse_result = (Decimal::ONE / price3) * (Decimal::ONE / price2) * price1;
-                let reverse_gross_profit = (reverse_result - Decimal::ONE) * Decimal::ONE_HUNDRED;
-                let reverse_net_profit = reverse_gross_profit - (triangular_fees * Decimal::ONE_HUNDRED);
+                // Reverse path: USDT -> ETH -> BTC -> USDT
+                let reverse_result = triangle.reverse_return();
+                let (reverse_gross_profit, reverse_net_profit) =
+                    cycle_profit_percentages(reverse_result, taker_fee, 3);
                 
-                if forward_net_profit > self.min_profit_threshold {
+                if forward_net_profit > min_profit_threshold {
                     let base_currency = pair1.replace("USDT", "");
                     let quote_currency = pair3.replace("USDT", "");
                     
-                    let usdt_amount = self.max_position_size;
+                    let usdt_amount = self.triangular.max_position_size;
                     let estimated_profit = usdt_amount * (forward_result - Decimal::ONE) - 
                                          (usdt_amount * triangular_fees);
                     
                     let execution_steps = vec![
                         ExecutionStep {
+                            exchange: exchange.to_string(),
                             action: format!("Buy {} with USDT", base_currency),
                             symbol: pair1.to_string(),
                             side: OrderSide::Buy,
                             quantity: usdt_amount / price1,
-                            expected_price: *price1,
-                            fees: usdt_amount * self.fees.taker_fee,
+                            expected_price: price1,
+                            fees: usdt_amount * taker_fee,
+                            client_order_id: new_client_order_id(),
                         },
                         ExecutionStep {
+                            exchange: exchange.to_string(),
                             action: format!("Trade {} to {}", base_currency, quote_currency),
                             symbol: pair2.to_string(),
-                            side: OrderSide::Sell,
-                            quantity: usdt_amount / price1,
-                            expected_price: *price2,
-                            fees: (usdt_amount / price1) * price2 * self.fees.taker_fee,
+                            side: OrderSide::Buy,
+                            quantity: (usdt_amount / price1) * keep / price2,
+                            expected_price: price2,
+                            fees: (usdt_amount / price1) * keep * taker_fee,
+                            client_order_id: new_client_order_id(),
                         },
                         ExecutionStep {
+                            exchange: exchange.to_string(),
                             action: format!("Sell {} for USDT", quote_currency),
                             symbol: pair3.to_string(),
                             side: OrderSide::Sell,
-                            quantity: (usdt_amount / price1) * price2,
-                            expected_price: *price3,
-                            fees: ((usdt_amount / price1) * price2) * price3 * self.fees.taker_fee,
+                            quantity: (usdt_amount / price1) * keep / price2 * keep,
+                            expected_price: price3,
+                            fees: (usdt_amount / price1) * keep / price2 * keep * price3 * taker_fee,
+                            client_order_id: new_client_order_id(),
                         },
                     ];
                     
-                    let opportunity = ArbitrageOpportunity {
+                    let leg_quotes = quotes.for_steps(&execution_steps);
+                    let mut opportunity = ArbitrageOpportunity {
                         exchange: exchange.to_string(),
+                        strategy: Strategy::Triangular,
                         path: vec![
-                            format!("Buy {} with USDT at {}", base_currency, price1),
-                            format!("Trade {} to {} via {} at {}", base_currency, quote_currency, pair2, price2),
-                            format!("Sell {} for USDT at {}", quote_currency, price3),
+                            format!("Buy {} with USDT at {}", base_currency, self.precision.format_price(exchange, pair1, price1)),
+                            format!("Trade {} to {} via {} at {}", base_currency, quote_currency, pair2,
+                                    self.precision.format_price(exchange, pair2, price2)),
+                            format!("Sell {} for USDT at {}", quote_currency, self.precision.format_price(exchange, pair3, price3)),
                         ],
                         profit_percentage: forward_gross_profit,
                         net_profit_percentage: forward_net_profit,
-                        required_amount: self.max_position_size,
+                        required_amount: usdt_amount,
                         estimated_profit_usd: estimated_profit,
-                        risk_score: self.calculate_triangular_risk_score(price1, price2, price3),
+                        risk_score: 0.0,
                         execution_steps,
+                        leg_quotes,
                         timestamp: Utc::now(),
+                        scan_id,
                     };
+                    if !self.fit_to_participation(&mut opportunity) || !self.clears_min_profit_usd(&opportunity) {
+                        continue;
+                    }
+                    opportunity.quantize(&self.precision);
+                    opportunity.risk_score = self.risk_scorer.score(&opportunity, &self.risk_inputs);
+                    
+                    if let Some((step, volume_usd)) = self.thin_leg(&opportunity) {
+                        debug!("Skipping {}: {} on {} trades only {}/h", opportunity.exchange, step.symbol, step.exchange, self.currency.format(volume_usd));
+                        continue;
+                    }
                     
-                    info!("Triangular arbitrage opportunity (forward): {:?}", opportunity);
+                    info!("Triangular arbitrage opportunity (forward): {}", opportunity.describe(&self.currency));
                     self.record_opportunity(&opportunity);
-                } else if reverse_net_profit > self.min_profit_threshold {
+                    opportunities.push(opportunity);
+                } else if reverse_net_profit > min_profit_threshold {
                     let base_currency = pair1.replace("USDT", "");
                     let quote_currency = pair3.replace("USDT", "");
                     
-                    let usdt_amount = self.max_position_size;
+                    let usdt_amount = self.triangular.max_position_size;
                     let estimated_profit = usdt_amount * (reverse_result - Decimal::ONE) - 
                                          (usdt_amount * triangular_fees);
                     
                     let execution_steps = vec![
                         ExecutionStep {
+                            exchange: exchange.to_string(),
                             action: format!("Buy {} with USDT", quote_currency),
                             symbol: pair3.to_string(),
                             side: OrderSide::Buy,
                             quantity: usdt_amount / price3,
-                            expected_price: *price3,
-                            fees: usdt_amount * self.fees.taker_fee,
+                            expected_price: price3,
+                            fees: usdt_amount * taker_fee,
+                            client_order_id: new_client_order_id(),
                         },
                         ExecutionStep {
+                            exchange: exchange.to_string(),
                             action: format!("Trade {} to {}", quote_currency, base_currency),
                             symbol: pair2.to_string(),
-                            side: OrderSide::Buy,
-                            quantity: (usdt_amount / price3) / price2,
-                            expected_price: *price2,
-                            fees: (usdt_amount / price3) * self.fees.taker_fee,
+                            side: OrderSide::Sell,
+                            quantity: (usdt_amount / price3) * keep,
+                            expected_price: price2,
+                            fees: (usdt_amount / price3) * keep * price2 * taker_fee,
+                            client_order_id: new_client_order_id(),
                         },
                         ExecutionStep {
+                            exchange: exchange.to_string(),
                             action: format!("Sell {} for USDT", base_currency),
                             symbol: pair1.to_string(),
                             side: OrderSide::Sell,
-                            quantity: (usdt_amount / price3) / price2,
-                            expected_price: *price1,
-                            fees: ((usdt_amount / price3) / price2) * price1 * self.fees.taker_fee,
+                            quantity: (usdt_amount / price3) * keep * price2 * keep,
+                            expected_price: price1,
+                            fees: (usdt_amount / price3) * keep * price2 * keep * price1 * taker_fee,
+                            client_order_id: new_client_order_id(),
                         },
                     ];
                     
-                    let opportunity = ArbitrageOpportunity {
+                    let leg_quotes = quotes.for_steps(&execution_steps);
+                    let mut opportunity = ArbitrageOpportunity {
                         exchange: exchange.to_string(),
+                        strategy: Strategy::Triangular,
                         path: vec![
-                            format!("Buy {} with USDT at {}", quote_currency, price3),
-                            format!("Trade {} to {} via {} at {}", quote_currency, base_currency, pair2, price2),
-                            format!("Sell {} for USDT at {}", base_currency, price1),
+                            format!("Buy {} with USDT at {}", quote_currency, self.precision.format_price(exchange, pair3, price3)),
+                            format!("Trade {} to {} via {} at {}", quote_currency, base_currency, pair2,
+                                    self.precision.format_price(exchange, pair2, price2)),
+                            format!("Sell {} for USDT at {}", base_currency, self.precision.format_price(exchange, pair1, price1)),
                         ],
                         profit_percentage: reverse_gross_profit,
                         net_profit_percentage: reverse_net_profit,
-                        required_amount: self.max_position_size,
+                        required_amount: usdt_amount,
                         estimated_profit_usd: estimated_profit,
-                        risk_score: self.calculate_triangular_risk_score(price1, price2, price3),
+                        risk_score: 0.0,
                         execution_steps,
+                        leg_quotes,
                         timestamp: Utc::now(),
+                        scan_id,
                     };
+                    if !self.fit_to_participation(&mut opportunity) || !self.clears_min_profit_usd(&opportunity) {
+                        continue;
+                    }
+                    opportunity.quantize(&self.precision);
+                    opportunity.risk_score = self.risk_scorer.score(&opportunity, &self.risk_inputs);
                     
-                    info!("Triangular arbitrage opportunity (reverse): {:?}", opportunity);
+                    if let Some((step, volume_usd)) = self.thin_leg(&opportunity) {
+                        debug!("Skipping {}: {} on {} trades only {}/h", opportunity.exchange, step.symbol, step.exchange, self.currency.format(volume_usd));
+                        continue;
+                    }
+                    
+                    info!("Triangular arbitrage opportunity (reverse): {}", opportunity.describe(&self.currency));
                     self.record_opportunity(&opportunity);
+                    opportunities.push(opportunity);
                 }
             }
         }
         
-        Ok(())
+        Ok(opportunities)
     }
     
-    fn is_price_fresh(&self, symbol: &str, price1: Decimal, price2: Decimal) -> bool {
-        // Check if prices have been updated recently and are reasonable
-        let price_age_limit = chrono::Duration::seconds(30);
-        let now = Utc::now();
+    /// Compares each asset's USDT market with its fiat markets on one venue,
+    /// with fiat prices normalized to USDT through the venue's FX pair.
+    fn check_fiat_arbitrage(
+        &self,
+        prices: &impl PriceLookup,
+        exchange: &str,
+        quotes: &VenueQuotes<'_>,
+        scan_id: i64,
+    ) -> Vec<ArbitrageOpportunity> {
+        let mut opportunities = Vec::new();
+        if !self.fiat.enabled || !self.triangular.enabled {
+            return opportunities;
+        }
         
-        if let Some((cached_price, timestamp)) = self.price_cache.get(symbol) {
-            let age = now.signed_duration_since(*timestamp);
-            if age > price_age_limit {
-                return false;
+        for fiat in &self.fiat.currencies {
+            for asset in &self.fiat.assets {
+                let Some(cycle) = FiatCycle::lookup(prices, asset, fiat) else { continue };
+                let forward_return = cycle.forward_return();
+                let forward = forward_return > Decimal::ONE;
+                let cycle_return = if forward { forward_return } else { cycle.reverse_return() };
+                let fees = self.fees.venue(exchange);
+                let (gross_profit, net_profit) = cycle_profit_percentages(cycle_return, fees.taker_fee(), 3);
+                if net_profit <= self.triangular.min_profit_threshold {
+                    continue;
+                }
+                
+                let usdt_amount = self.triangular.max_position_size;
+                let execution_steps = cycle.steps(exchange, forward, usdt_amount, &fees);
+                if let Some(skew) = self.excess_leg_skew(execution_steps.iter().map(|step| prices.captured_at(&step.symbol))) {
+                    debug!("Skipping {}{} cycle on {}: legs captured {}ms apart", asset, fiat, exchange, skew.num_milliseconds());
+                    continue;
+                }
+                let mut opportunity = ArbitrageOpportunity {
+                    exchange: exchange.to_string(),
+                    strategy: Strategy::Triangular,
+                    path: cycle.path(exchange, forward, &self.precision),
+                    profit_percentage: gross_profit,
+                    net_profit_percentage: net_profit,
+                    required_amount: usdt_amount,
+                    estimated_profit_usd: usdt_amount * net_profit / Decimal::ONE_HUNDRED,
+                    risk_score: 0.0,
+                    leg_quotes: quotes.for_steps(&execution_steps),
+                    execution_steps,
+                    timestamp: Utc::now(),
+                    scan_id,
+                };
+                if !self.fit_to_participation(&mut opportunity) || !self.clears_min_profit_usd(&opportunity) {
+                    continue;
+                }
+                opportunity.quantize(&self.precision);
+                opportunity.risk_score = self.risk_scorer.score(&opportunity, &self.risk_inputs);
+                
+                if let Some((step, volume_usd)) = self.thin_leg(&opportunity) {
+                    debug!("Skipping {}: {} on {} trades only {}/h", opportunity.exchange, step.symbol, step.exchange, self.currency.format(volume_usd));
+                    continue;
+                }
+                
+                let usdt_symbol = format!("{}USDT", asset);
+                info!("Fiat arbitrage opportunity ({}{} at {} USDT vs {}): {}", asset, fiat,
+                      self.precision.format_price(exchange, &usdt_symbol, cycle.normalized_price()),
+                      self.precision.format_price(exchange, &usdt_symbol, cycle.a_usdt), opportunity.describe(&self.currency));
+                self.record_opportunity(&opportunity);
+                opportunities.push(opportunity);
             }
         }
+        opportunities
+    }
+    
+    /// Gap between the earliest and latest capture time of a path's legs,
+    /// when it exceeds `max_leg_skew`. Legs without a known time are not
+    /// compared.
+    fn excess_leg_skew(&self, captured_at: impl IntoIterator<Item = Option<DateTime<Utc>>>) -> Option<chrono::Duration> {
+        if self.max_leg_skew.is_zero() {
+            return None;
+        }
+        let times: Vec<DateTime<Utc>> = captured_at.into_iter().flatten().collect();
+        let skew = times.iter().max()?.signed_duration_since(*times.iter().min()?);
+        (skew > self.max_leg_skew).then_some(skew)
+    }
+    
+    /// Caches each venue's price of `symbol` with its exchange time, then
+    /// checks both are current and within 10% of each other. A venue whose
+    /// previous price is too old makes this scan the new baseline.
+    fn is_price_fresh(&self, symbol: &str, prices: [(&str, Decimal, Option<DateTime<Utc>>); 2]) -> bool {
+        let price_age_limit = chrono::Duration::seconds(PRICE_AGE_LIMIT_SECONDS);
+        let now = Utc::now();
         
-        // Update cache
-        self.price_cache.insert(symbol.to_string(), ((price1 + price2) / Decimal::TWO, now));
+        let mut fresh = true;
+        for (exchange, price, exchange_time) in prices {
+            let as_of = exchange_time.unwrap_or(now);
+            let previous = self.price_cache.observe(exchange, symbol, price, exchange_time, now);
+            if previous.is_some_and(|p| as_of.signed_duration_since(p.as_of()) > price_age_limit) {
+                // The last observation is too old to compare against; this one becomes the new baseline
+                fresh = false;
+            }
+            if now.signed_duration_since(as_of) > price_age_limit {
+                debug!("{} price of {} is {}s old by exchange time", exchange, symbol, now.signed_duration_since(as_of).num_seconds());
+                fresh = false;
+            }
+        }
+        if !fresh {
+            return false;
+        }
         
         // Check for reasonable price variance (not more than 10% difference)
+        let [(_, price1, _), (_, price2, _)] = prices;
         let max_variance = Decimal::from_str_exact("0.1").unwrap();
         let price_diff = (price1 - price2).abs();
         let avg_price = (price1 + price2) / Decimal::TWO;
@@ -370,37 +1180,25 @@ impl ArbitrageEngine {
         false
     }
     
-    fn calculate_risk_score(&self, price_diff: &Decimal, avg_price: &Decimal) -> f32 {
-        // Higher price difference = higher risk due to potential stale data or market volatility
-        if *avg_price == Decimal::ZERO {
-            return 1.0; // Maximum risk
-        }
-        
-        let variance = price_diff / avg_price;
-        let variance_f32 = variance.to_f32().unwrap_or(1.0);
-        
-        // Risk score from 0.0 (low risk) to 1.0 (high risk)
-        (variance_f32 * 10.0).min(1.0)
+    /// Per-exchange prices seen by cross-exchange detection, with their
+    /// exchange and receive times.
+    pub fn price_cache(&self) -> Arc<PriceCache> {
+        self.price_cache.clone()
     }
     
-    fn calculate_triangular_risk_score(&self, price1: &Decimal, price2: &Decimal, price3: &Decimal) -> f32 {
-        // Triangular arbitrage has higher complexity risk
-        let base_risk = 0.3; // Base risk for triangular trades
-        
-        // Add risk based on price volatility estimation
-        let prices = vec![*price1, *price2, *price3];
-        let avg = prices.iter().sum::<Decimal>() / Decimal::from(prices.len());
-        
-        if avg == Decimal::ZERO {
-            return 1.0;
-        }
-        
-        let variance = prices.iter()
-            .map(|p| (*p - avg).abs() / avg)
-            .map(|v| v.to_f32().unwrap_or(0.0))
-            .sum::<f32>() / prices.len() as f32;
-        
-        (base_risk + variance).min(1.0)
+    /// How old the last price of `symbol` on `exchange` is, by the
+    /// exchange's own timestamp when it sent one.
+    pub fn price_age(&self, exchange: &str, symbol: &str) -> Option<chrono::Duration> {
+        self.price_cache.age(exchange, symbol, Utc::now())
+    }
+    
+    /// Cached prices for warm-start persistence.
+    pub fn price_cache_entries(&self) -> Vec<PriceEntry> {
+        self.price_cache.entries(None)
+    }
+    
+    pub fn restore_price_cache(&self, entries: impl IntoIterator<Item = PriceEntry>) {
+        self.price_cache.restore(entries);
     }
     
     fn record_opportunity(&self, opportunity: &ArbitrageOpportunity) {
@@ -408,31 +1206,419 @@ impl ArbitrageEngine {
         
         self.opportunity_history
             .entry(key)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(opportunity.clone());
         
-        // Cleanup old records (keep only last 7 days)
-        let cutoff = Utc::now() - chrono::Duration::days(7);
+        // Cleanup records older than the configured history
+        if self.opportunity_history_days == 0 {
+            return;
+        }
+        let cutoff = Utc::now() - chrono::Duration::days(self.opportunity_history_days);
         self.opportunity_history.retain(|_, opportunities| {
             opportunities.retain(|opp| opp.timestamp > cutoff);
             !opportunities.is_empty()
         });
     }
     
+    /// Finishes or unwinds executions a crash interrupted, before any new
+    /// one starts. Returns how many there were.
+    pub async fn recover_executions(&self) -> Result<usize> {
+        match &self.executor {
+            Some(executor) => executor.recover().await,
+            None => Ok(0),
+        }
+    }
+    
+    /// Startup reconciliation of open orders and balances; `None` while
+    /// execution is disabled.
+    pub async fn reconcile_accounts(&self, expected: Option<&PortfolioSnapshot>) -> Result<Option<ReconciliationReport>> {
+        match &self.executor {
+            Some(executor) => executor.reconcile(expected).await.map(Some),
+            None => Ok(None),
+        }
+    }
+    
     pub async fn execute_arbitrage(&self, opportunity: &ArbitrageOpportunity) -> Result<()> {
-        if self.circuit_breaker.is_open() {
+        if self.kill_switch.is_tripped() {
+            warn!("Daily loss kill switch is tripped, skipping arbitrage execution");
+            return Ok(());
+        }
+        
+        if self.circuit_breakers.is_open() {
             warn!("Circuit breaker is open, skipping arbitrage execution");
             return Ok(());
         }
         
-        if opportunity.risk_score > 0.7 {
-            warn!("Risk score too high ({:.2}), skipping execution", opportunity.risk_score);
+        if let Some(step) = opportunity.execution_steps.iter().find(|s| self.outages.is_excluded(&s.exchange)) {
+            warn!("{} is excluded after an outage, skipping arbitrage execution", step.exchange);
+            return Ok(());
+        }
+        
+        if let Some(step) = opportunity.execution_steps.iter().find(|s| self.circuit_breakers.is_exchange_open(&s.exchange)) {
+            warn!("{} circuit breaker is open, skipping arbitrage execution", step.exchange);
+            return Ok(());
+        }
+        
+        if let Some((asset, deviation)) = self.depeg.depegged(opportunity) {
+            warn!("{} is {:.4}% off its peg, skipping arbitrage execution", asset, deviation);
+            return Ok(());
+        }
+        
+        if let Some((step, observations)) = self.warmup.cold_leg(opportunity, &self.risk_inputs) {
+            warn!("{} on {} is still warming up ({} of {} observations), skipping arbitrage execution",
+                  step.symbol, step.exchange, observations, self.warmup.config().min_observations);
+            return Ok(());
+        }
+        
+        let max_risk_score = self.risk_scoring.max_score(opportunity.strategy);
+        if opportunity.risk_score > max_risk_score {
+            warn!("Risk score too high ({:.2} > {:.2} for {:?}), skipping execution", 
+                  opportunity.risk_score, max_risk_score, opportunity.strategy);
             return Ok(());
         }
         
-        warn!("Arbitrage execution is disabled for safety. Opportunity: {:?}", opportunity);
-        // Implementation would go here for actual trading
-        // This requires careful risk management and testing
+        if let Some(executor) = &self.executor {
+            let look = match executor.last_look(opportunity).await {
+                Ok(look) => look,
+                Err(e) => LastLook::Evaporated(format!("last look failed: {:#}", e)),
+            };
+            if let Some(metrics) = &self.metrics {
+                metrics.record_last_look(opportunity, &look);
+            }
+            if let Some(reason) = look.reason() {
+                warn!("Opportunity {} before execution, skipping: {}", look.label(), reason);
+                return Ok(());
+            }
+        }
+        
+        if let Some(ledger) = &self.ledger {
+            let key = opportunity.idempotency_key();
+            if !ledger.try_claim(&key, &opportunity.exchange)? {
+                warn!("Opportunity {} was already executed, skipping duplicate", key);
+                return Ok(());
+            }
+        }
+        
+        let executor = match &self.executor {
+            Some(executor) => executor,
+            None => {
+                warn!("Arbitrage execution is disabled for safety. Opportunity: {}", opportunity);
+                return Ok(());
+            }
+        };
+        
+        let result = match executor.execute(opportunity).await {
+            Ok(result) => result,
+            Err(e) => {
+                // Order rejections count against the venue that rejected them
+                match e.downcast_ref::<ExchangeError>() {
+                    Some(ExchangeError::ApiError { exchange, .. }) => {
+                        self.circuit_breakers.record_exchange_failure(exchange, &e.to_string());
+                    }
+                    _ => self.circuit_breakers.record_failure(&format!("execution error: {:#}", e)),
+                }
+                return Err(e);
+            }
+        };
+        match &result {
+            ExecutionResult::Completed(fills) => {
+                info!("Executed {} on {} across {} legs", opportunity.path.join(" -> "), opportunity.exchange, fills.len());
+                self.circuit_breakers.record_success();
+            }
+            ExecutionResult::Abandoned { fills, reason } => {
+                warn!("Execution on {} abandoned after {} legs: {}", opportunity.exchange, fills.len(), reason);
+                self.circuit_breakers.record_failure(&format!("execution abandoned: {}", reason));
+            }
+        }
+        
+        let (ExecutionResult::Completed(fills) | ExecutionResult::Abandoned { fills, .. }) = &result;
+        if let Some(inventory) = &self.inventory {
+            inventory.record_fills(fills);
+        }
+        if opportunity.strategy == Strategy::CrossExchange {
+            self.risk_inputs.record_cross_exchange_fills(fills);
+        }
+        self.risk_inputs.record_execution(&opportunity.exchange, matches!(result, ExecutionResult::Completed(_)));
+        let report = ExecutionReport::new(opportunity, &result);
+        if let Some(metrics) = &self.metrics {
+            metrics.record_execution(opportunity, &report);
+        }
+        self.record_report(report);
+        
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// Trips the breaker on abnormal slippage and the kill switch on the
+    /// daily loss limit, then persists the report and compares recent
+    /// slippage with the configured profit threshold, which does not
+    /// account for slippage.
+    fn record_report(&self, report: ExecutionReport) {
+        let slippage = report.cycle_slippage_percentage();
+        info!("Execution slippage on {}: {:.4}% over {} legs", report.exchange, slippage, report.legs.len());
+        if slippage > self.max_cycle_slippage_percentage {
+            self.circuit_breakers.record_failure(&format!("slippage {:.4}% on {}", slippage, report.exchange));
+        }
+        if self.kill_switch.record(report.realized_pnl_usd()) {
+            error!("Execution halted: daily loss limit reached after {} on {}", report.path.join(" -> "), report.exchange);
+        }
+        
+        let Some(reports) = &self.reports else { return };
+        if let Err(e) = reports.record(&report) {
+            warn!("Failed to persist execution report: {}", e);
+            return;
+        }
+        
+        let threshold = self.cross_exchange.min_profit_threshold.min(self.triangular.min_profit_threshold);
+        match reports.slippage_stats(Utc::now() - chrono::Duration::hours(24)) {
+            Ok(stats) if stats.p95_cycle_slippage_percentage > threshold => {
+                warn!("p95 slippage over the last 24h ({:.4}%, {} executions) exceeds min_profit_threshold ({}%)",
+                      stats.p95_cycle_slippage_percentage, stats.executions, threshold);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Failed to compute slippage stats: {}", e),
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::{BookTicker, PriceMap};
+    
+    fn engine() -> ArbitrageEngine {
+        let mut config = Config::default();
+        config.trading.trading_pairs = vec!["BTCUSDT".to_string()];
+        config.trading.spread_min_samples = 2;
+        config.trading.spread_zscore_threshold = 1.0;
+        ArbitrageEngine::from_config(&config)
+    }
+    
+    fn prices(btc: i64) -> PriceMap {
+        PriceMap::from([("BTCUSDT".to_string(), Decimal::from(btc))])
+    }
+    
+    /// BTC, ETH and USDT priced for a 2% gain before fees going round the
+    /// triangle.
+    fn triangle_prices() -> PriceMap {
+        [("BTCUSDT", "50000"), ("ETHBTC", "0.06"), ("ETHUSDT", "3060")].into_iter()
+            .map(|(symbol, price)| (symbol.to_string(), Decimal::from_str_exact(price).unwrap()))
+            .collect()
+    }
+    
+    fn quotes(bid: i64, ask: i64) -> BookTickerMap {
+        let ticker = BookTicker::parse("BTCUSDT", &bid.to_string(), "1", &ask.to_string(), "1").unwrap();
+        BookTickerMap::from([("BTCUSDT".to_string(), ticker)])
+    }
+    
+    #[tokio::test]
+    async fn test_cross_exchange_trades_at_bid_and_ask() {
+        let engine = engine();
+        for _ in 0..2 {
+            engine.analyze_opportunities_with_quotes(&prices(60000), &prices(60000), &quotes(59990, 60010), &quotes(59990, 60010)).await.unwrap();
+        }
+        
+        // Last prices 2% apart, but Bybit's bid is still below Binance's ask
+        let inside_spread = engine
+            .analyze_opportunities_with_quotes(&prices(60000), &prices(61200), &quotes(59000, 61000), &quotes(60900, 61500))
+            .await
+            .unwrap();
+        assert!(inside_spread.is_empty());
+        
+        // Bybit's bid crosses Binance's ask by 2%
+        let crossed = engine
+            .analyze_opportunities_with_quotes(&prices(60000), &prices(61200), &quotes(59990, 60010), &quotes(61210, 61230))
+            .await
+            .unwrap();
+        assert_eq!(crossed.len(), 1);
+        let steps = &crossed[0].execution_steps;
+        assert_eq!((steps[0].exchange.as_str(), steps[0].expected_price), ("Bybit", Decimal::from(61210)));
+        assert_eq!((steps[1].exchange.as_str(), steps[1].expected_price), ("Binance", Decimal::from(60010)));
+        
+        let leg_quotes = &crossed[0].leg_quotes;
+        assert_eq!(leg_quotes.len(), 2);
+        assert_eq!((leg_quotes[0].exchange.as_str(), leg_quotes[0].touch_price(&steps[0].side)), ("Bybit", Decimal::from(61210)));
+        assert_eq!(leg_quotes[1].ask_depth, Decimal::from(60010));
+        assert_eq!(leg_quotes[1].spread_percentage.round_dp(4), Decimal::from_str_exact("0.0333").unwrap());
+    }
+    
+    #[tokio::test]
+    async fn test_unquoted_legs_have_no_leg_quotes() {
+        let engine = ArbitrageEngine::new();
+        let prices = triangle_prices();
+        let binance_quotes = BookTickerMap::from([(
+            "ETHUSDT".to_string(),
+            BookTicker::parse("ETHUSDT", "3059", "2", "3061", "3").unwrap(),
+        )]);
+        
+        let found = engine
+            .analyze_opportunities_with_quotes(&prices, &PriceMap::new(), &binance_quotes, &BookTickerMap::new())
+            .await
+            .unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].leg_quotes, vec![LegQuote::new("Binance", &binance_quotes["ETHUSDT"])]);
+    }
+    
+    #[tokio::test]
+    async fn test_leg_quantities_follow_lot_sizes() {
+        let dec = |v: &str| Decimal::from_str_exact(v).unwrap();
+        let precision = Arc::new(PricePrecision::new());
+        precision.set_step_sizes("Binance", std::collections::HashMap::from([
+            ("BTCUSDT".to_string(), dec("0.00001")),
+            ("ETHBTC".to_string(), dec("0.0001")),
+            ("ETHUSDT".to_string(), dec("0.0001")),
+        ]));
+        let engine = ArbitrageEngine::new().with_precision(precision);
+        let prices = triangle_prices();
+        
+        let found = engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        let steps = &found[0].execution_steps;
+        for (step, lot) in steps.iter().zip(["0.00001", "0.0001", "0.0001"]) {
+            assert!(step.quantity > Decimal::ZERO);
+            assert_eq!(step.quantity % dec(lot), Decimal::ZERO, "{} {}", step.symbol, step.quantity);
+        }
+        // No leg spends more than the rounded leg before it delivered
+        let keep = dec("0.999");
+        assert!(steps[1].quantity * dec("0.06") <= steps[0].quantity * keep);
+        assert!(steps[2].quantity <= steps[1].quantity * keep);
+    }
+    
+    #[tokio::test]
+    async fn test_thinly_traded_legs_are_skipped() {
+        let mut config = Config::default();
+        config.liquidity.min_hourly_volume_usd = Decimal::from(50_000);
+        let engine = ArbitrageEngine::from_config(&config);
+        let prices = triangle_prices();
+        let trade = |quantity: &str| crate::exchanges::PublicTrade {
+            price: Decimal::ONE,
+            quantity: Decimal::from_str_exact(quantity).unwrap(),
+            timestamp: Utc::now() - chrono::Duration::minutes(30),
+        };
+        
+        // Without volume data the legs are not judged
+        assert_eq!(engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().len(), 1);
+        
+        // 10 ETH/h against a leg of 1/3 ETH for a $1000 position is $30k/h
+        engine.trade_volumes().record_trades("Binance", "ETHUSDT", &[trade("5")], Utc::now());
+        assert!(engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+        
+        engine.trade_volumes().record_trades("Binance", "ETHUSDT", &[trade("50")], Utc::now());
+        assert_eq!(engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_position_shrinks_to_participation_limits() {
+        let mut config = Config::default();
+        config.risk.participation.max_depth_percentage = Decimal::from(10);
+        config.risk.participation.min_position_size = Decimal::from(100);
+        // Fees paid in BNB leave every leg the full proceeds of the one before
+        config.fees.binance.pay_in_bnb = true;
+        let store = Arc::new(OrderBookStore::new());
+        let engine = ArbitrageEngine::from_config(&config)
+            .with_order_books(store.clone(), std::time::Duration::from_secs(5));
+        let prices = triangle_prices();
+        let book = |bids: Vec<(Decimal, Decimal)>| crate::exchanges::depth::LocalOrderBook::from_snapshot(
+            "ETHUSDT",
+            &crate::exchanges::depth::DepthSnapshot { last_update_id: 1, bids, asks: Vec::new() },
+        );
+        
+        // The ETHUSDT sell leg of 1/3 ETH may take 10% of 2 ETH of bids
+        store.insert("Binance", book(vec![(Decimal::from(3060), Decimal::ONE), (Decimal::from(3059), Decimal::ONE)]));
+        let found = engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].required_amount.round_dp(6), Decimal::from(600));
+        assert_eq!(found[0].execution_steps[2].quantity.round_dp(6), Decimal::from_str_exact("0.2").unwrap());
+        assert_eq!(found[0].execution_steps[0].quantity.round_dp(6), Decimal::from_str_exact("0.012").unwrap());
+        
+        // Too thin to reach the minimum position size
+        store.insert("Binance", book(vec![(Decimal::from(3060), Decimal::from_str_exact("0.1").unwrap())]));
+        assert!(engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_usd_threshold_applies_at_the_position_size() {
+        let prices = triangle_prices();
+        let mut config = Config::default();
+        config.trading.min_profit_threshold = Decimal::ZERO;
+        config.trading.min_profit_usd = Decimal::from(20);
+        
+        // 2% gross less three 0.1% fees is $17 on $1000
+        assert!(ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+        
+        config.trading.max_position_size = Decimal::from(2000);
+        let found = ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].estimated_profit_usd.round_dp(6), Decimal::from(34));
+        
+        // Both thresholds have to be met
+        config.trading.min_profit_threshold = Decimal::from(2);
+        assert!(ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_strategies_run_with_their_own_settings() {
+        let prices = triangle_prices();
+        let mut config = Config::default();
+        config.strategies.triangular.max_position_size = Some(Decimal::from(250));
+        let found = ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].required_amount, Decimal::from(250));
+        
+        // 1.7% net is below a 2% triangular threshold, whatever trading.min_profit_threshold says
+        config.strategies.triangular.min_profit_threshold = Some(Decimal::from(2));
+        assert!(ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+        
+        config.strategies.triangular = crate::config::StrategyConfig::default();
+        config.strategies.triangular.enabled = false;
+        let engine = ArbitrageEngine::from_config(&config);
+        assert!(engine.triangle_paths().is_empty());
+        assert!(engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_paths_with_skewed_legs_are_skipped() {
+        use crate::exchanges::snapshot::{PriceSnapshot, SymbolTable};
+        
+        let symbols = Arc::new(SymbolTable::new());
+        let now = Utc::now();
+        let snapshot = |eth_captured_at: DateTime<Utc>| {
+            let mut prices = PriceSnapshot::new(symbols.clone());
+            prices.insert_at("BTCUSDT", Decimal::from(50000), now);
+            prices.insert_at("ETHBTC", Decimal::from_str_exact("0.06").unwrap(), now);
+            prices.insert_at("ETHUSDT", Decimal::from(3060), eth_captured_at);
+            prices
+        };
+        let empty = PriceSnapshot::new(symbols.clone());
+        let mut config = Config::default();
+        config.trading.max_leg_skew_ms = 1000;
+        
+        let in_sync = snapshot(now - chrono::Duration::milliseconds(800));
+        assert_eq!(ArbitrageEngine::from_config(&config).analyze_opportunities(&in_sync, &empty).await.unwrap().len(), 1);
+        
+        let skewed = snapshot(now - chrono::Duration::seconds(3));
+        assert!(ArbitrageEngine::from_config(&config).analyze_opportunities(&skewed, &empty).await.unwrap().is_empty());
+        
+        config.trading.max_leg_skew_ms = 0;
+        assert_eq!(ArbitrageEngine::from_config(&config).analyze_opportunities(&skewed, &empty).await.unwrap().len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_opportunity_history_keeps_the_configured_days() {
+        let prices = triangle_prices();
+        let mut config = Config::default();
+        config.monitoring.opportunity_history_days = 30;
+        let engine = ArbitrageEngine::from_config(&config);
+        
+        let found = engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        let mut old = found[0].clone();
+        old.timestamp = Utc::now() - chrono::Duration::days(10);
+        engine.record_opportunity(&old);
+        let recorded = |engine: &ArbitrageEngine| engine.opportunity_history.iter().map(|entry| entry.len()).sum::<usize>();
+        assert_eq!(recorded(&engine), found.len() + 1);
+        
+        config.monitoring.opportunity_history_days = 7;
+        let engine = ArbitrageEngine::from_config(&config);
+        engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        engine.record_opportunity(&old);
+        engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        assert_eq!(recorded(&engine), found.len() * 2);
+    }
+}
@@ -0,0 +1,192 @@
+use crate::config::OutageConfig;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use log::{info, warn};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+struct Exclusion {
+    since: DateTime<Utc>,
+    reason: String,
+    last_probe: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct VenueHealth {
+    /// Outcome and latency of the most recent requests, oldest first
+    samples: VecDeque<(bool, Duration)>,
+    excluded: Option<Exclusion>,
+}
+
+impl VenueHealth {
+    fn error_rate_percentage(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let failures = self.samples.iter().filter(|(ok, _)| !ok).count();
+        failures as f64 * 100.0 / self.samples.len() as f64
+    }
+
+    fn average_latency_ms(&self) -> f64 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        let total: Duration = self.samples.iter().map(|(_, latency)| *latency).sum();
+        total.as_secs_f64() * 1000.0 / self.samples.len() as f64
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OutageState {
+    pub exchange: String,
+    pub excluded: bool,
+    pub excluded_since: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+    pub error_rate_percentage: f64,
+    pub average_latency_ms: f64,
+    pub samples: usize,
+}
+
+/// Tracks the error rate and latency of each exchange's market data
+/// requests over a rolling window and excludes a venue from scanning and
+/// execution once either crosses its limit. An excluded venue is only
+/// probed every `probe_interval_seconds`; the first successful probe
+/// brings it back.
+#[derive(Debug)]
+pub struct OutageDetector {
+    config: OutageConfig,
+    venues: DashMap<String, VenueHealth>,
+}
+
+impl OutageDetector {
+    pub fn new(config: OutageConfig) -> Self {
+        Self { config, venues: DashMap::new() }
+    }
+
+    pub fn is_excluded(&self, exchange: &str) -> bool {
+        self.venues.get(exchange).is_some_and(|v| v.excluded.is_some())
+    }
+
+    /// Whether to send `exchange` a request at `now`: always for included
+    /// venues, once per probe interval for excluded ones.
+    pub fn should_request(&self, exchange: &str, now: DateTime<Utc>) -> bool {
+        let Some(mut venue) = self.venues.get_mut(exchange) else { return true };
+        let Some(exclusion) = venue.excluded.as_mut() else { return true };
+        let interval = chrono::Duration::seconds(self.config.probe_interval_seconds as i64);
+        if now.signed_duration_since(exclusion.last_probe) < interval {
+            return false;
+        }
+        exclusion.last_probe = now;
+        true
+    }
+
+    pub fn record(&self, exchange: &str, ok: bool, latency: Duration, now: DateTime<Utc>) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut venue = self.venues.entry(exchange.to_string()).or_default();
+        if venue.excluded.is_some() {
+            if ok {
+                info!("{} answered a probe in {:.0}ms, resuming scanning and execution", exchange, latency.as_secs_f64() * 1000.0);
+                *venue = VenueHealth::default();
+            }
+            return;
+        }
+
+        venue.samples.push_back((ok, latency));
+        while venue.samples.len() > self.config.window_size {
+            venue.samples.pop_front();
+        }
+        if venue.samples.len() < self.config.min_requests {
+            return;
+        }
+
+        let error_rate = venue.error_rate_percentage();
+        let average_latency = venue.average_latency_ms();
+        let reason = if error_rate >= self.config.max_error_rate_percentage {
+            format!("{:.0}% of the last {} requests failed", error_rate, venue.samples.len())
+        } else if self.config.max_latency_ms > 0 && average_latency > self.config.max_latency_ms as f64 {
+            format!("average latency {:.0}ms over the last {} requests", average_latency, venue.samples.len())
+        } else {
+            return;
+        };
+        warn!("Excluding {} from scanning and execution: {}; probing every {}s", exchange, reason, self.config.probe_interval_seconds);
+        venue.excluded = Some(Exclusion { since: now, reason, last_probe: now });
+    }
+
+    pub fn states(&self) -> Vec<OutageState> {
+        let mut states: Vec<OutageState> = self.venues.iter()
+            .map(|entry| {
+                let venue = entry.value();
+                OutageState {
+                    exchange: entry.key().clone(),
+                    excluded: venue.excluded.is_some(),
+                    excluded_since: venue.excluded.as_ref().map(|e| e.since),
+                    reason: venue.excluded.as_ref().map(|e| e.reason.clone()),
+                    error_rate_percentage: venue.error_rate_percentage(),
+                    average_latency_ms: venue.average_latency_ms(),
+                    samples: venue.samples.len(),
+                }
+            })
+            .collect();
+        states.sort_by(|a, b| a.exchange.cmp(&b.exchange));
+        states
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detector() -> OutageDetector {
+        OutageDetector::new(OutageConfig {
+            window_size: 4,
+            min_requests: 4,
+            max_error_rate_percentage: 50.0,
+            max_latency_ms: 1000,
+            probe_interval_seconds: 30,
+            ..OutageConfig::default()
+        })
+    }
+
+    #[test]
+    fn test_error_rate_excludes_until_probe_succeeds() {
+        let detector = detector();
+        let now = Utc::now();
+        let fast = Duration::from_millis(50);
+        for ok in [true, false, true] {
+            detector.record("Bybit", ok, fast, now);
+        }
+        assert!(!detector.is_excluded("Bybit"));
+        detector.record("Bybit", false, fast, now);
+        assert!(detector.is_excluded("Bybit"));
+        assert!(!detector.is_excluded("Binance"));
+
+        // Excluded venues are only probed once per interval
+        assert!(!detector.should_request("Bybit", now + chrono::Duration::seconds(10)));
+        let probe_at = now + chrono::Duration::seconds(31);
+        assert!(detector.should_request("Bybit", probe_at));
+        assert!(!detector.should_request("Bybit", probe_at));
+        detector.record("Bybit", false, fast, probe_at);
+        assert!(detector.is_excluded("Bybit"));
+
+        let probe_at = probe_at + chrono::Duration::seconds(31);
+        assert!(detector.should_request("Bybit", probe_at));
+        detector.record("Bybit", true, fast, probe_at);
+        assert!(!detector.is_excluded("Bybit"));
+        assert_eq!(detector.states()[0].samples, 0);
+    }
+
+    #[test]
+    fn test_slow_venue_is_excluded() {
+        let detector = detector();
+        for _ in 0..4 {
+            detector.record("Binance", true, Duration::from_millis(1500), Utc::now());
+        }
+        let state = &detector.states()[0];
+        assert!(state.excluded);
+        assert!(state.reason.as_deref().unwrap().starts_with("average latency 1500ms"));
+    }
+}
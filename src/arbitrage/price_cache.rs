@@ -0,0 +1,130 @@
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+/// Last price of a symbol on one exchange, with when the exchange says it
+/// was current and when the bot received it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceEntry {
+    pub exchange: String,
+    pub symbol: String,
+    pub price: Decimal,
+    /// Event time from the exchange payload, when it carries one
+    pub exchange_time: Option<DateTime<Utc>>,
+    pub received_at: DateTime<Utc>,
+}
+
+impl PriceEntry {
+    /// The time the price was current: the exchange's event time when
+    /// known, otherwise when it arrived.
+    pub fn as_of(&self) -> DateTime<Utc> {
+        self.exchange_time.unwrap_or(self.received_at)
+    }
+
+    /// Age of the data at `now`. Negative when the exchange clock runs
+    /// ahead of the local one.
+    pub fn age(&self, now: DateTime<Utc>) -> Duration {
+        now.signed_duration_since(self.as_of())
+    }
+}
+
+/// A cached price with its age, as served by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceAge {
+    #[serde(flatten)]
+    pub entry: PriceEntry,
+    pub age_ms: i64,
+}
+
+/// Per-exchange, per-symbol price cache aged by exchange event times, so
+/// a quote that sat in a slow response or a lagging feed does not count
+/// as fresh just because it arrived recently.
+#[derive(Debug, Default)]
+pub struct PriceCache {
+    entries: DashMap<(String, String), PriceEntry>,
+}
+
+impl PriceCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores `price` and returns the entry it replaced.
+    pub fn observe(
+        &self,
+        exchange: &str,
+        symbol: &str,
+        price: Decimal,
+        exchange_time: Option<DateTime<Utc>>,
+        received_at: DateTime<Utc>,
+    ) -> Option<PriceEntry> {
+        let entry = PriceEntry { exchange: exchange.to_string(), symbol: symbol.to_string(), price, exchange_time, received_at };
+        self.entries.insert((exchange.to_string(), symbol.to_string()), entry)
+    }
+
+    pub fn get(&self, exchange: &str, symbol: &str) -> Option<PriceEntry> {
+        self.entries.get(&(exchange.to_string(), symbol.to_string())).map(|entry| entry.clone())
+    }
+
+    /// Age at `now` of the last price of `symbol` on `exchange`.
+    pub fn age(&self, exchange: &str, symbol: &str, now: DateTime<Utc>) -> Option<Duration> {
+        self.get(exchange, symbol).map(|entry| entry.age(now))
+    }
+
+    /// Every cached price, optionally of one exchange, sorted by exchange
+    /// and symbol.
+    pub fn entries(&self, exchange: Option<&str>) -> Vec<PriceEntry> {
+        let mut entries: Vec<PriceEntry> = self.entries.iter()
+            .filter(|entry| exchange.is_none_or(|exchange| entry.exchange.eq_ignore_ascii_case(exchange)))
+            .map(|entry| entry.value().clone())
+            .collect();
+        entries.sort_by(|a, b| (&a.exchange, &a.symbol).cmp(&(&b.exchange, &b.symbol)));
+        entries
+    }
+
+    /// `entries` with their ages at `now`.
+    pub fn ages(&self, exchange: Option<&str>, now: DateTime<Utc>) -> Vec<PriceAge> {
+        self.entries(exchange).into_iter()
+            .map(|entry| PriceAge { age_ms: entry.age(now).num_milliseconds(), entry })
+            .collect()
+    }
+
+    pub fn restore(&self, entries: impl IntoIterator<Item = PriceEntry>) {
+        for entry in entries {
+            self.entries.insert((entry.exchange.clone(), entry.symbol.clone()), entry);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exchanges_are_aged_separately_by_event_time() {
+        let cache = PriceCache::new();
+        let now: DateTime<Utc> = "2024-05-01T12:00:10Z".parse().unwrap();
+        cache.observe("Binance", "BTCUSDT", Decimal::from(64000), None, now);
+        let replaced = cache.observe("Bybit", "BTCUSDT", Decimal::from(64010), Some(now - Duration::seconds(8)), now);
+        assert!(replaced.is_none());
+
+        assert_eq!(cache.age("Binance", "BTCUSDT", now), Some(Duration::zero()));
+        assert_eq!(cache.age("Bybit", "BTCUSDT", now), Some(Duration::seconds(8)));
+        assert_eq!(cache.age("Bybit", "ETHUSDT", now), None);
+
+        let bybit = cache.entries(Some("bybit"));
+        assert_eq!(bybit.len(), 1);
+        assert_eq!(bybit[0].price, Decimal::from(64010));
+        assert_eq!(cache.entries(None).iter().map(|e| e.exchange.as_str()).collect::<Vec<_>>(), ["Binance", "Bybit"]);
+        assert_eq!(cache.ages(Some("Bybit"), now)[0].age_ms, 8000);
+    }
+}
@@ -0,0 +1,315 @@
+use super::{ArbitrageEngine, ArbitrageOpportunity};
+use crate::config::{ExecutionQueueConfig, QueueOverflow};
+use crate::executor::LastLook;
+use crate::metrics::{self, write_metric, MarketMetrics};
+use chrono::{Duration, Utc};
+use log::{error, info, warn};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize)]
+pub struct QueueStats {
+    pub queued: usize,
+    /// Pushes that got a place, including newer detections of a queued route
+    pub enqueued: u64,
+    /// Taken by a worker
+    pub dequeued: u64,
+    /// Dropped at dequeue for being older than the maximum opportunity age
+    pub expired: u64,
+    /// Pushed out of the full queue to make room for a new one
+    pub evicted: u64,
+    /// Turned away by the full queue
+    pub rejected: u64,
+}
+
+/// Detected opportunities waiting for an execution worker, most profitable
+/// first, so the scan loop can hand them off and move on to the next scan
+/// instead of waiting for each execution. Holds at most `capacity`; its
+/// overflow policy decides what gives way when it is full. A route already
+/// queued is replaced by its newer detection rather than queued twice.
+/// Opportunities older than the maximum opportunity age by their detection
+/// time are dropped instead of executed, since their prices have likely
+/// moved, and counted as expired last looks.
+#[derive(Debug)]
+pub struct OpportunityQueue {
+    capacity: usize,
+    overflow: QueueOverflow,
+    max_age: Duration,
+    entries: Mutex<Vec<(String, ArbitrageOpportunity)>>,
+    ready: Notify,
+    enqueued: AtomicU64,
+    dequeued: AtomicU64,
+    expired: AtomicU64,
+    evicted: AtomicU64,
+    rejected: AtomicU64,
+    metrics: Option<Arc<MarketMetrics>>,
+}
+
+impl OpportunityQueue {
+    pub fn new(config: &ExecutionQueueConfig, max_age_ms: u64) -> Self {
+        Self {
+            capacity: config.capacity.max(1),
+            overflow: config.overflow,
+            max_age: Duration::milliseconds(max_age_ms as i64),
+            entries: Mutex::new(Vec::new()),
+            ready: Notify::new(),
+            enqueued: AtomicU64::new(0),
+            dequeued: AtomicU64::new(0),
+            expired: AtomicU64::new(0),
+            evicted: AtomicU64::new(0),
+            rejected: AtomicU64::new(0),
+            metrics: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<MarketMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Queues `opportunity`, returning whether it got a place.
+    pub fn push(&self, opportunity: ArbitrageOpportunity) -> bool {
+        let key = route_key(&opportunity);
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.iter_mut().find(|(k, _)| *k == key) {
+            entry.1 = opportunity;
+        } else if entries.len() < self.capacity {
+            entries.push((key, opportunity));
+        } else {
+            let victim = match self.overflow {
+                QueueOverflow::DropLeastProfitable => entries.iter().enumerate()
+                    .min_by(|(_, a), (_, b)| priority(&a.1).cmp(&priority(&b.1)))
+                    .map(|(index, _)| index)
+                    .filter(|&index| priority(&entries[index].1) < priority(&opportunity)),
+                QueueOverflow::DropOldest => entries.iter().enumerate()
+                    .min_by_key(|(_, (_, queued))| queued.timestamp)
+                    .map(|(index, _)| index),
+                QueueOverflow::RejectNew => None,
+            };
+            let Some(victim) = victim else {
+                self.rejected.fetch_add(1, Ordering::Relaxed);
+                return false;
+            };
+            self.evicted.fetch_add(1, Ordering::Relaxed);
+            entries[victim] = (key, opportunity);
+        }
+        drop(entries);
+        self.enqueued.fetch_add(1, Ordering::Relaxed);
+        self.ready.notify_one();
+        true
+    }
+
+    /// The most profitable opportunity still fresh enough to execute.
+    pub fn pop(&self) -> Option<ArbitrageOpportunity> {
+        let oldest = Utc::now() - self.max_age;
+        let mut entries = self.entries.lock().unwrap();
+        let (fresh, expired): (Vec<_>, Vec<_>) = entries.drain(..).partition(|(_, opportunity)| opportunity.timestamp >= oldest);
+        *entries = fresh;
+        if !expired.is_empty() {
+            self.expired.fetch_add(expired.len() as u64, Ordering::Relaxed);
+            info!("Dropped {} queued opportunities older than {}ms", expired.len(), self.max_age.num_milliseconds());
+            if let Some(metrics) = &self.metrics {
+                let look = LastLook::Expired("expired in the execution queue".to_string());
+                for (_, opportunity) in &expired {
+                    metrics.record_last_look(opportunity, &look);
+                }
+            }
+        }
+        let (best, _) = entries.iter().enumerate()
+            .max_by(|(_, a), (_, b)| priority(&a.1).cmp(&priority(&b.1)))?;
+        self.dequeued.fetch_add(1, Ordering::Relaxed);
+        Some(entries.swap_remove(best).1)
+    }
+
+    /// Waits for the next opportunity; `None` once `cancel` fires.
+    pub async fn next(&self, cancel: &CancellationToken) -> Option<ArbitrageOpportunity> {
+        loop {
+            if let Some(opportunity) = self.pop() {
+                return Some(opportunity);
+            }
+            tokio::select! {
+                _ = self.ready.notified() => {}
+                _ = cancel.cancelled() => return None,
+            }
+        }
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        QueueStats {
+            queued: self.entries.lock().unwrap().len(),
+            enqueued: self.enqueued.load(Ordering::Relaxed),
+            dequeued: self.dequeued.load(Ordering::Relaxed),
+            expired: self.expired.load(Ordering::Relaxed),
+            evicted: self.evicted.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Prometheus text exposition of the queue's depth and throughput.
+    pub fn prometheus_metrics(&self) -> String {
+        let stats = self.stats();
+        let dropped: Vec<_> = [("expired", stats.expired), ("evicted", stats.evicted), ("rejected", stats.rejected)]
+            .into_iter()
+            .map(|(reason, count)| (vec![reason.to_string()], count as f64))
+            .collect();
+
+        let mut out = String::new();
+        write_metric(&mut out, &metrics::EXECUTION_QUEUE_DEPTH, &[(Vec::new(), stats.queued as f64)]);
+        write_metric(&mut out, &metrics::EXECUTION_QUEUE_ENQUEUED, &[(Vec::new(), stats.enqueued as f64)]);
+        write_metric(&mut out, &metrics::EXECUTION_QUEUE_DEQUEUED, &[(Vec::new(), stats.dequeued as f64)]);
+        write_metric(&mut out, &metrics::EXECUTION_QUEUE_DROPPED, &dropped);
+        out
+    }
+}
+
+/// Venue and legs, without prices or scan, so the same route detected on
+/// two scans is one entry.
+fn route_key(opportunity: &ArbitrageOpportunity) -> String {
+    let mut key = opportunity.exchange.clone();
+    for step in &opportunity.execution_steps {
+        key.push_str(&format!("|{}:{}:{:?}", step.exchange, step.symbol, step.side));
+    }
+    key
+}
+
+fn priority(opportunity: &ArbitrageOpportunity) -> (rust_decimal::Decimal, rust_decimal::Decimal) {
+    (opportunity.estimated_profit_usd, opportunity.net_profit_percentage)
+}
+
+/// Spawns `workers` tasks that execute queued opportunities until `cancel`
+/// fires. An execution error is logged and the worker moves on; the
+/// engine's circuit breakers have already counted it.
+pub fn spawn_workers(queue: Arc<OpportunityQueue>, engine: Arc<ArbitrageEngine>, workers: usize, cancel: CancellationToken) {
+    for worker in 0..workers.max(1) {
+        let (queue, engine, cancel) = (queue.clone(), engine.clone(), cancel.clone());
+        tokio::spawn(async move {
+            while let Some(opportunity) = queue.next(&cancel).await {
+                if let Err(e) = engine.execute_arbitrage(&opportunity).await {
+                    error!("Execution worker {} failed on {}: {:#}", worker, opportunity.exchange, e);
+                }
+            }
+            if queue.stats().queued > 0 {
+                warn!("Execution worker {} stopped with opportunities still queued", worker);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::risk::Strategy;
+    use crate::arbitrage::ExecutionStep;
+    use crate::exchanges::OrderSide;
+    use rust_decimal::Decimal;
+
+    fn opportunity(symbol: &str, profit_usd: i64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            exchange: "Binance".to_string(),
+            strategy: Strategy::Triangular,
+            path: Vec::new(),
+            profit_percentage: Decimal::ONE,
+            net_profit_percentage: Decimal::ONE,
+            required_amount: Decimal::from(1000),
+            estimated_profit_usd: Decimal::from(profit_usd),
+            risk_score: 0.0,
+            execution_steps: vec![ExecutionStep {
+                exchange: "Binance".to_string(),
+                action: String::new(),
+                symbol: symbol.to_string(),
+                side: OrderSide::Buy,
+                quantity: Decimal::ONE,
+                expected_price: Decimal::ONE,
+                fees: Decimal::ZERO,
+                client_order_id: String::new(),
+            }],
+            leg_quotes: Vec::new(),
+            timestamp: Utc::now(),
+            scan_id: 0,
+        }
+    }
+
+    fn queue(capacity: usize) -> OpportunityQueue {
+        queue_with(capacity, QueueOverflow::DropLeastProfitable)
+    }
+
+    fn queue_with(capacity: usize, overflow: QueueOverflow) -> OpportunityQueue {
+        OpportunityQueue::new(&ExecutionQueueConfig { enabled: true, capacity, workers: 1, overflow }, 1000)
+    }
+
+    #[test]
+    fn test_most_profitable_first_and_full_queue_keeps_the_best() {
+        let queue = queue(2);
+        assert!(queue.push(opportunity("BTCUSDT", 3)));
+        assert!(queue.push(opportunity("ETHUSDT", 1)));
+        // Full: a better one pushes out ETHUSDT, a worse one is turned away
+        assert!(queue.push(opportunity("SOLUSDT", 5)));
+        assert!(!queue.push(opportunity("XRPUSDT", 2)));
+        // A newer detection of a queued route replaces it
+        assert!(queue.push(opportunity("BTCUSDT", 4)));
+
+        let order: Vec<_> = std::iter::from_fn(|| queue.pop())
+            .map(|o| (o.execution_steps[0].symbol.clone(), o.estimated_profit_usd))
+            .collect();
+        assert_eq!(order, [("SOLUSDT".to_string(), Decimal::from(5)), ("BTCUSDT".to_string(), Decimal::from(4))]);
+        assert_eq!(queue.stats(), QueueStats { queued: 0, enqueued: 4, dequeued: 2, expired: 0, evicted: 1, rejected: 1 });
+    }
+
+    #[test]
+    fn test_overflow_policies() {
+        let oldest = queue_with(2, QueueOverflow::DropOldest);
+        let mut first = opportunity("BTCUSDT", 9);
+        first.timestamp = Utc::now() - Duration::milliseconds(100);
+        oldest.push(first);
+        oldest.push(opportunity("ETHUSDT", 1));
+        // Less profitable than both, but the newest
+        assert!(oldest.push(opportunity("SOLUSDT", 0)));
+        let symbols: Vec<_> = std::iter::from_fn(|| oldest.pop()).map(|o| o.execution_steps[0].symbol.clone()).collect();
+        assert_eq!(symbols, ["ETHUSDT", "SOLUSDT"]);
+
+        let reject = queue_with(1, QueueOverflow::RejectNew);
+        assert!(reject.push(opportunity("BTCUSDT", 1)));
+        assert!(!reject.push(opportunity("ETHUSDT", 9)));
+        assert_eq!(reject.pop().unwrap().execution_steps[0].symbol, "BTCUSDT");
+
+        let metrics = reject.prometheus_metrics();
+        assert!(metrics.contains("arbitrage_execution_queue_depth 0"));
+        assert!(metrics.contains("arbitrage_execution_queue_enqueued_total 1"));
+        assert!(metrics.contains("arbitrage_execution_queue_dequeued_total 1"));
+        assert!(metrics.contains("arbitrage_execution_queue_dropped_total{reason=\"rejected\"} 1"));
+        assert!(metrics.contains("arbitrage_execution_queue_dropped_total{reason=\"evicted\"} 0"));
+    }
+
+    #[test]
+    fn test_stale_opportunities_expire_instead_of_executing() {
+        let metrics = Arc::new(MarketMetrics::new());
+        let queue = queue(4).with_metrics(metrics.clone());
+        let mut stale = opportunity("BTCUSDT", 9);
+        stale.timestamp = Utc::now() - Duration::seconds(2);
+        queue.push(stale);
+        queue.push(opportunity("ETHUSDT", 1));
+
+        assert_eq!(queue.pop().unwrap().execution_steps[0].symbol, "ETHUSDT");
+        assert!(queue.pop().is_none());
+        assert_eq!(queue.stats().expired, 1);
+        assert!(metrics.prometheus_metrics().contains(r#"result="expired"} 1"#));
+    }
+
+    #[tokio::test]
+    async fn test_next_waits_for_a_push_or_cancel() {
+        let queue = Arc::new(queue(4));
+        let cancel = CancellationToken::new();
+        let waiting = tokio::spawn({
+            let (queue, cancel) = (queue.clone(), cancel.clone());
+            async move { queue.next(&cancel).await }
+        });
+        tokio::task::yield_now().await;
+        queue.push(opportunity("BTCUSDT", 1));
+        assert!(waiting.await.unwrap().is_some());
+
+        cancel.cancel();
+        assert!(queue.next(&cancel).await.is_none());
+    }
+}
@@ -0,0 +1,467 @@
+use super::correlation::CorrelationMatrix;
+use super::ArbitrageOpportunity;
+use crate::config::RiskScoringConfig;
+use crate::exchanges::OrderSide;
+use crate::executor::LegFill;
+use crate::exchanges::snapshot::PriceLookup;
+use crate::symbols::split_symbol;
+use dashmap::DashMap;
+use rust_decimal::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Which detector produced an opportunity; risk limits are set per strategy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Strategy {
+    CrossExchange,
+    Triangular,
+}
+
+/// Market and execution observations the scorers draw on. Updated by the
+/// engine as scans and executions happen; every input is optional and
+/// scorers without data abstain instead of guessing.
+#[derive(Debug)]
+pub struct RiskInputs {
+    /// Recent returns per `exchange:symbol`
+    returns: DashMap<String, VecDeque<f64>>,
+    last_prices: DashMap<String, Decimal>,
+    /// Last ticker request latency per exchange
+    latency: DashMap<String, Duration>,
+    /// Top-of-book notional in USD per `exchange:symbol`
+    depth_usd: DashMap<String, Decimal>,
+    /// (completed, total) executions per opportunity venue
+    fills: DashMap<String, (u32, u32)>,
+    /// Net quote notional bought through cross-exchange trades per `exchange:symbol`
+    inventory_flow: DashMap<String, f64>,
+    /// Historical statistics of the assets in `scoring.correlation`
+    correlations: RwLock<Option<CorrelationMatrix>>,
+    window_size: usize,
+}
+
+impl RiskInputs {
+    pub fn new(window_size: usize) -> Self {
+        Self {
+            returns: DashMap::new(),
+            last_prices: DashMap::new(),
+            latency: DashMap::new(),
+            depth_usd: DashMap::new(),
+            fills: DashMap::new(),
+            inventory_flow: DashMap::new(),
+            correlations: RwLock::new(None),
+            window_size: window_size.max(2),
+        }
+    }
+
+    pub fn observe_price(&self, exchange: &str, symbol: &str, price: Decimal) {
+        self.observe_keyed(&format!("{}:{}", exchange, symbol), price);
+    }
+    
+    /// Observes every price of one exchange, reusing a single key buffer so
+    /// symbols seen before cost no allocation.
+    pub fn observe_prices(&self, exchange: &str, prices: &impl PriceLookup) {
+        let mut key = String::with_capacity(32);
+        prices.for_each_price(|symbol, price| {
+            key.clear();
+            key.push_str(exchange);
+            key.push(':');
+            key.push_str(symbol);
+            self.observe_keyed(&key, price);
+        });
+    }
+    
+    fn observe_keyed(&self, key: &str, price: Decimal) {
+        let previous = self.last_prices.get_mut(key).map(|mut last| std::mem::replace(&mut *last, price));
+        let Some(previous) = previous else {
+            self.last_prices.insert(key.to_string(), price);
+            return;
+        };
+        
+        if previous > Decimal::ZERO {
+            let ret = ((price - previous) / previous).to_f64().unwrap_or(0.0);
+            let mut samples = match self.returns.get_mut(key) {
+                Some(samples) => samples,
+                None => self.returns.entry(key.to_string()).or_default(),
+            };
+            samples.push_back(ret);
+            while samples.len() > self.window_size {
+                samples.pop_front();
+            }
+        }
+    }
+
+    pub fn record_latency(&self, exchange: &str, latency: Duration) {
+        self.latency.insert(exchange.to_string(), latency);
+    }
+
+    pub fn record_depth(&self, exchange: &str, symbol: &str, depth_usd: Decimal) {
+        self.depth_usd.insert(format!("{}:{}", exchange, symbol), depth_usd);
+    }
+
+    pub fn record_execution(&self, venue: &str, completed: bool) {
+        let mut entry = self.fills.entry(venue.to_string()).or_insert((0, 0));
+        if completed {
+            entry.0 += 1;
+        }
+        entry.1 += 1;
+    }
+
+    /// Adds the fills of a cross-exchange execution to the net notional
+    /// each venue has bought of the symbol.
+    pub fn record_cross_exchange_fills(&self, fills: &[LegFill]) {
+        for fill in fills {
+            let notional = fill.average_price
+                .map(|p| (p * fill.filled_quantity).to_f64().unwrap_or(0.0))
+                .unwrap_or(0.0);
+            let change = match fill.side {
+                OrderSide::Buy => notional,
+                OrderSide::Sell => -notional,
+            };
+            *self.inventory_flow.entry(format!("{}:{}", fill.exchange, fill.symbol)).or_insert(0.0) += change;
+        }
+    }
+
+    /// Replaces the historical volatility and correlation statistics.
+    pub fn set_correlation_matrix(&self, matrix: CorrelationMatrix) {
+        *self.correlations.write().unwrap() = Some(matrix);
+    }
+
+    /// Per-scan returns seen for `symbol` on `exchange`, up to the window.
+    pub fn observations(&self, exchange: &str, symbol: &str) -> usize {
+        self.returns.get(&format!("{}:{}", exchange, symbol)).map_or(0, |samples| samples.len())
+    }
+
+    /// Standard deviation of per-scan returns in percent, once at least a
+    /// handful of returns have been seen.
+    pub fn volatility_pct(&self, exchange: &str, symbol: &str) -> Option<f64> {
+        let samples = self.returns.get(&format!("{}:{}", exchange, symbol))?;
+        if samples.len() < 5 {
+            return None;
+        }
+
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        Some(variance.sqrt() * 100.0)
+    }
+}
+
+/// Scores one aspect of an opportunity's risk from 0.0 (safe) to 1.0, or
+/// returns `None` when it has no data to judge by.
+pub trait RiskScorer: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> Option<f32>;
+}
+
+/// Penalizes positions that are large relative to visible top-of-book depth.
+pub struct DepthScorer {
+    pub min_liquidity_usd: Decimal,
+}
+
+impl RiskScorer for DepthScorer {
+    fn name(&self) -> &'static str {
+        "depth"
+    }
+
+    fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> Option<f32> {
+        let thinnest = opportunity.execution_steps.iter()
+            .filter_map(|s| inputs.depth_usd.get(&format!("{}:{}", s.exchange, s.symbol)).map(|d| *d))
+            .min()?;
+
+        if thinnest < self.min_liquidity_usd || thinnest <= Decimal::ZERO {
+            return Some(1.0);
+        }
+        Some((opportunity.required_amount / thinnest).to_f32().unwrap_or(1.0).min(1.0))
+    }
+}
+
+/// Scales with the most volatile leg relative to `max_volatility_pct`.
+pub struct VolatilityScorer {
+    pub max_volatility_pct: f64,
+}
+
+impl RiskScorer for VolatilityScorer {
+    fn name(&self) -> &'static str {
+        "volatility"
+    }
+
+    fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> Option<f32> {
+        let worst = opportunity.execution_steps.iter()
+            .filter_map(|s| inputs.volatility_pct(&s.exchange, &s.symbol))
+            .fold(None, |acc: Option<f64>, v| Some(acc.map_or(v, |a| a.max(v))))?;
+        Some((worst / self.max_volatility_pct).min(1.0) as f32)
+    }
+}
+
+/// Slow venues mean the prices an opportunity was found at are older by
+/// the time orders land.
+pub struct LatencyScorer {
+    pub max_latency: Duration,
+}
+
+impl RiskScorer for LatencyScorer {
+    fn name(&self) -> &'static str {
+        "latency"
+    }
+
+    fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> Option<f32> {
+        let slowest = opportunity.execution_steps.iter()
+            .filter_map(|s| inputs.latency.get(&s.exchange).map(|l| *l))
+            .max()?;
+        Some((slowest.as_secs_f32() / self.max_latency.as_secs_f32()).min(1.0))
+    }
+}
+
+/// Share of past executions on the same venue that were abandoned.
+pub struct FillHistoryScorer {
+    pub min_executions: u32,
+}
+
+impl RiskScorer for FillHistoryScorer {
+    fn name(&self) -> &'static str {
+        "fill_history"
+    }
+
+    fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> Option<f32> {
+        let (completed, total) = *inputs.fills.get(&opportunity.exchange)?;
+        if total < self.min_executions {
+            return None;
+        }
+        Some(1.0 - completed as f32 / total as f32)
+    }
+}
+
+/// Penalizes cross-exchange trades that keep moving inventory towards the
+/// same venue: the net notional the buying venue would have bought after
+/// this trade, relative to `max_skew_usd`. Trades in the other direction
+/// rebalance holdings and score zero.
+pub struct InventorySkewScorer {
+    pub max_skew_usd: f64,
+}
+
+impl RiskScorer for InventorySkewScorer {
+    fn name(&self) -> &'static str {
+        "inventory_skew"
+    }
+
+    fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> Option<f32> {
+        if opportunity.strategy != Strategy::CrossExchange {
+            return None;
+        }
+        let buy = opportunity.execution_steps.iter().find(|s| s.side == OrderSide::Buy)?;
+        let net = *inputs.inventory_flow.get(&format!("{}:{}", buy.exchange, buy.symbol))?;
+        let after = net + (buy.quantity * buy.expected_price).to_f64().unwrap_or(0.0);
+        Some((after / self.max_skew_usd).clamp(0.0, 1.0) as f32)
+    }
+}
+
+/// Penalizes triangles whose assets are volatile and move independently,
+/// since a leg that fills late then lands on a price the others did not
+/// follow: the highest historical volatility on the path relative to
+/// `max_volatility_pct`, times how far the least correlated pair is from
+/// moving together. Abstains for cross-exchange trades and for paths with
+/// fewer than two tracked assets.
+pub struct CorrelationScorer {
+    pub max_volatility_pct: f64,
+}
+
+impl RiskScorer for CorrelationScorer {
+    fn name(&self) -> &'static str {
+        "correlation"
+    }
+
+    fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> Option<f32> {
+        if opportunity.strategy != Strategy::Triangular {
+            return None;
+        }
+        let matrix = inputs.correlations.read().unwrap();
+        let assets: BTreeSet<&str> = opportunity.execution_steps.iter()
+            .filter_map(|s| split_symbol(&s.symbol))
+            .flat_map(|(base, quote)| [base, quote])
+            .filter(|asset| *asset != "USDT")
+            .collect();
+        let (volatility, correlation) = matrix.as_ref()?.worst_of(&assets);
+        let (volatility, correlation) = (volatility?, correlation?);
+        Some(((volatility / self.max_volatility_pct).min(1.0) * (1.0 - correlation).clamp(0.0, 1.0)) as f32)
+    }
+}
+
+/// Weighted average of the scorers that have an opinion. With no data at
+/// all an opportunity gets `default_score`.
+pub struct CompositeRiskScorer {
+    scorers: Vec<(Box<dyn RiskScorer>, f32)>,
+    default_score: f32,
+}
+
+impl CompositeRiskScorer {
+    pub fn new(default_score: f32) -> Self {
+        Self { scorers: Vec::new(), default_score }
+    }
+
+    pub fn from_config(config: &RiskScoringConfig, min_liquidity_usd: Decimal) -> Self {
+        Self::new(config.default_score)
+            .with(DepthScorer { min_liquidity_usd }, config.depth_weight)
+            .with(VolatilityScorer { max_volatility_pct: config.max_volatility_pct }, config.volatility_weight)
+            .with(LatencyScorer { max_latency: Duration::from_millis(config.max_latency_ms) }, config.latency_weight)
+            .with(FillHistoryScorer { min_executions: config.min_executions }, config.fill_history_weight)
+            .with(InventorySkewScorer { max_skew_usd: config.max_inventory_skew_usd }, config.inventory_skew_weight)
+            .with(CorrelationScorer { max_volatility_pct: config.correlation.max_volatility_pct }, config.correlation_weight)
+    }
+
+    /// Adds a scorer; a zero weight disables it.
+    pub fn with(mut self, scorer: impl RiskScorer + 'static, weight: f32) -> Self {
+        if weight > 0.0 {
+            self.scorers.push((Box::new(scorer), weight));
+        }
+        self
+    }
+
+    pub fn score(&self, opportunity: &ArbitrageOpportunity, inputs: &RiskInputs) -> f32 {
+        let (weighted, total_weight) = self.scorers.iter()
+            .filter_map(|(scorer, weight)| scorer.score(opportunity, inputs).map(|s| (s * weight, *weight)))
+            .fold((0.0, 0.0), |(ws, tw), (s, w)| (ws + s, tw + w));
+
+        if total_weight <= 0.0 {
+            return self.default_score;
+        }
+        (weighted / total_weight).clamp(0.0, 1.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::ExecutionStep;
+    use crate::exchanges::{new_client_order_id, OrderSide};
+    use chrono::Utc;
+
+    fn opportunity() -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            exchange: "Binance".to_string(),
+            strategy: Strategy::Triangular,
+            path: vec![],
+            profit_percentage: Decimal::ONE,
+            net_profit_percentage: Decimal::ONE,
+            required_amount: Decimal::from(1000),
+            estimated_profit_usd: Decimal::TEN,
+            risk_score: 0.0,
+            execution_steps: vec![ExecutionStep {
+                exchange: "Binance".to_string(),
+                action: "Buy BTC with USDT".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                side: OrderSide::Buy,
+                quantity: Decimal::ONE,
+                expected_price: Decimal::from(1000),
+                fees: Decimal::ONE,
+                client_order_id: new_client_order_id(),
+            }],
+            leg_quotes: Vec::new(),
+            timestamp: Utc::now(),
+            scan_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_no_data_uses_default_score() {
+        let scorer = CompositeRiskScorer::from_config(&RiskScoringConfig::default(), Decimal::from(10_000));
+        assert_eq!(scorer.score(&opportunity(), &RiskInputs::new(10)), 0.5);
+    }
+
+    #[test]
+    fn test_weighted_average_of_available_scores() {
+        let inputs = RiskInputs::new(10);
+        inputs.record_latency("Binance", Duration::from_millis(500));
+        inputs.record_depth("Binance", "BTCUSDT", Decimal::from(4000));
+
+        let scorer = CompositeRiskScorer::new(0.5)
+            .with(DepthScorer { min_liquidity_usd: Decimal::from(1000) }, 1.0)
+            .with(LatencyScorer { max_latency: Duration::from_secs(1) }, 3.0);
+        // depth 1000/4000 = 0.25, latency 0.5; (0.25 + 1.5) / 4
+        assert!((scorer.score(&opportunity(), &inputs) - 0.4375).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_fill_history_and_volatility() {
+        let inputs = RiskInputs::new(10);
+        for completed in [true, true, false, true] {
+            inputs.record_execution("Binance", completed);
+        }
+        let fills = FillHistoryScorer { min_executions: 4 };
+        assert_eq!(fills.score(&opportunity(), &inputs), Some(0.25));
+
+        let volatility = VolatilityScorer { max_volatility_pct: 1.0 };
+        assert_eq!(volatility.score(&opportunity(), &inputs), None);
+        for price in [100, 101, 100, 101, 100, 101, 100] {
+            inputs.observe_price("Binance", "BTCUSDT", Decimal::from(price));
+        }
+        assert!(volatility.score(&opportunity(), &inputs).unwrap() > 0.9);
+    }
+
+    #[test]
+    fn test_inventory_skew_grows_with_same_direction_trades() {
+        let inputs = RiskInputs::new(10);
+        let fill = |exchange: &str, side: OrderSide| LegFill {
+            exchange: exchange.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side,
+            client_order_ids: Vec::new(),
+            requested_quantity: Decimal::ONE,
+            filled_quantity: Decimal::ONE,
+            average_price: Some(Decimal::from(1000)),
+        };
+        let cross_exchange = |buy_on: &str, sell_on: &str| {
+            let mut opportunity = opportunity();
+            opportunity.strategy = Strategy::CrossExchange;
+            let mut sell = opportunity.execution_steps[0].clone();
+            sell.exchange = sell_on.to_string();
+            sell.side = OrderSide::Sell;
+            opportunity.execution_steps[0].exchange = buy_on.to_string();
+            opportunity.execution_steps.push(sell);
+            opportunity
+        };
+        let scorer = InventorySkewScorer { max_skew_usd: 5000.0 };
+        assert_eq!(scorer.score(&cross_exchange("Bybit", "Binance"), &inputs), None);
+        assert_eq!(scorer.score(&opportunity(), &inputs), None);
+
+        for _ in 0..2 {
+            inputs.record_cross_exchange_fills(&[fill("Binance", OrderSide::Sell), fill("Bybit", OrderSide::Buy)]);
+        }
+        // Bybit has bought 2000 net; a third buy there takes it to 3000
+        assert_eq!(scorer.score(&cross_exchange("Bybit", "Binance"), &inputs), Some(0.6));
+        assert_eq!(scorer.score(&cross_exchange("Binance", "Bybit"), &inputs), Some(0.0));
+    }
+
+    #[test]
+    fn test_correlation_penalizes_volatile_independent_legs() {
+        let inputs = RiskInputs::new(10);
+        let mut triangle = opportunity();
+        for symbol in ["ETHBTC", "ETHUSDT"] {
+            let mut step = triangle.execution_steps[0].clone();
+            step.symbol = symbol.to_string();
+            triangle.execution_steps.push(step);
+        }
+        let scorer = CorrelationScorer { max_volatility_pct: 2.0 };
+        assert_eq!(scorer.score(&triangle, &inputs), None);
+
+        let correlated = |correlation: f64| CorrelationMatrix {
+            samples: 24,
+            volatility_pct: [("BTC".to_string(), 0.5), ("ETH".to_string(), 1.0)].into(),
+            correlations: [(("BTC".to_string(), "ETH".to_string()), correlation)].into(),
+            computed_at: Some(Utc::now()),
+        };
+        inputs.set_correlation_matrix(correlated(0.5));
+        assert_eq!(scorer.score(&triangle, &inputs), Some(0.25));
+        inputs.set_correlation_matrix(correlated(-0.5));
+        assert_eq!(scorer.score(&triangle, &inputs), Some(0.5));
+        inputs.set_correlation_matrix(correlated(1.0));
+        assert_eq!(scorer.score(&triangle, &inputs), Some(0.0));
+
+        // A single tracked asset says nothing about how legs move together
+        assert_eq!(scorer.score(&opportunity(), &inputs), None);
+        let mut cross_exchange = triangle.clone();
+        cross_exchange.strategy = Strategy::CrossExchange;
+        assert_eq!(scorer.score(&cross_exchange, &inputs), None);
+    }
+}
@@ -0,0 +1,94 @@
+use dashmap::DashMap;
+use std::collections::VecDeque;
+
+/// Rolling per-pair history of the Binance-vs-Bybit spread, used to judge
+/// whether the current spread is unusual rather than a persistent offset
+/// caused by fee or price-structure differences between the venues.
+#[derive(Debug)]
+pub struct SpreadTracker {
+    history: DashMap<String, VecDeque<f64>>,
+    window_size: usize,
+    min_samples: usize,
+}
+
+impl SpreadTracker {
+    pub fn new(window_size: usize, min_samples: usize) -> Self {
+        Self {
+            history: DashMap::new(),
+            window_size: window_size.max(2),
+            min_samples: min_samples.max(2),
+        }
+    }
+    
+    /// Records `spread` for `pair` and returns its z-score against the
+    /// samples seen before it, or `None` while the history is too short.
+    pub fn observe(&self, pair: &str, spread: f64) -> Option<f64> {
+        let mut samples = self.history.entry(pair.to_string()).or_default();
+        let zscore = Self::zscore(&samples, spread, self.min_samples);
+        
+        samples.push_back(spread);
+        while samples.len() > self.window_size {
+            samples.pop_front();
+        }
+        
+        zscore
+    }
+    
+    pub fn sample_count(&self, pair: &str) -> usize {
+        self.history.get(pair).map(|s| s.len()).unwrap_or(0)
+    }
+    
+    fn zscore(samples: &VecDeque<f64>, value: f64, min_samples: usize) -> Option<f64> {
+        if samples.len() < min_samples {
+            return None;
+        }
+        
+        let n = samples.len() as f64;
+        let mean = samples.iter().sum::<f64>() / n;
+        let variance = samples.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / (n - 1.0);
+        let std_dev = variance.sqrt();
+        
+        if std_dev <= f64::EPSILON {
+            // A perfectly flat history makes any deviation infinitely significant
+            return Some(if (value - mean).abs() <= f64::EPSILON { 0.0 } else { f64::INFINITY.copysign(value - mean) });
+        }
+        
+        Some((value - mean) / std_dev)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_zscore_requires_history() {
+        let tracker = SpreadTracker::new(10, 3);
+        assert_eq!(tracker.observe("BTCUSDT", 0.1), None);
+        assert_eq!(tracker.observe("BTCUSDT", 0.2), None);
+        assert_eq!(tracker.observe("BTCUSDT", 0.3), None);
+        assert!(tracker.observe("BTCUSDT", 0.2).is_some());
+    }
+    
+    #[test]
+    fn test_persistent_offset_is_not_an_outlier() {
+        let tracker = SpreadTracker::new(50, 5);
+        for i in 0..20 {
+            tracker.observe("ETHUSDT", 0.8 + (i % 2) as f64 * 0.01);
+        }
+        let z = tracker.observe("ETHUSDT", 0.805).unwrap();
+        assert!(z.abs() < 1.0);
+        
+        let z = tracker.observe("ETHUSDT", 1.5).unwrap();
+        assert!(z > 3.0);
+    }
+    
+    #[test]
+    fn test_window_is_bounded() {
+        let tracker = SpreadTracker::new(5, 2);
+        for i in 0..20 {
+            tracker.observe("BNBUSDT", i as f64);
+        }
+        assert_eq!(tracker.sample_count("BNBUSDT"), 5);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::config::TickFilterConfig;
+use crate::exchanges::snapshot::PriceLookup;
+use dashmap::DashMap;
+use log::warn;
+use rust_decimal::Decimal;
+use std::collections::{HashSet, VecDeque};
+
+/// Scale from the median absolute deviation to a normal standard deviation.
+const MAD_TO_SIGMA: Decimal = Decimal::from_parts(14826, 0, 0, false, 4);
+
+#[derive(Debug, Default)]
+struct SymbolWindow {
+    /// Recently accepted prices, oldest first
+    prices: VecDeque<Decimal>,
+    consecutive_rejections: u32,
+}
+
+fn median(values: &mut [Decimal]) -> Decimal {
+    values.sort_unstable();
+    let middle = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        (values[middle - 1] + values[middle]) / Decimal::TWO
+    } else {
+        values[middle]
+    }
+}
+
+/// Rejects ticks that jump implausibly far from a symbol's recent prices,
+/// such as fat-finger prints or API glitches, before detection sees them.
+/// Sigma is estimated from the median absolute deviation, so one bad tick
+/// cannot widen the band that lets the next one through.
+#[derive(Debug)]
+pub struct TickFilter {
+    config: TickFilterConfig,
+    windows: DashMap<(String, String), SymbolWindow>,
+}
+
+impl TickFilter {
+    pub fn new(config: TickFilterConfig) -> Self {
+        Self { config, windows: DashMap::new() }
+    }
+
+    /// Records `price` for `symbol` on `exchange` and returns whether it is
+    /// plausible. After `max_consecutive_rejections` rejections in a row the
+    /// move is taken as real and the window restarts from it.
+    pub fn accept(&self, exchange: &str, symbol: &str, price: Decimal) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        let mut window = self.windows.entry((exchange.to_string(), symbol.to_string())).or_default();
+        if window.prices.len() >= self.config.min_samples {
+            let mut prices: Vec<Decimal> = window.prices.iter().copied().collect();
+            let center = median(&mut prices);
+            let mut deviations: Vec<Decimal> = prices.iter().map(|p| (*p - center).abs()).collect();
+            let sigma = median(&mut deviations) * MAD_TO_SIGMA;
+            let deviation = (price - center).abs();
+            let min_deviation = center * self.config.min_deviation_percentage / Decimal::ONE_HUNDRED;
+            if deviation > min_deviation && deviation > sigma * self.config.max_sigma {
+                window.consecutive_rejections += 1;
+                if window.consecutive_rejections <= self.config.max_consecutive_rejections {
+                    warn!("Rejecting {} tick {} on {}: {:.2}% from the recent median {}", symbol, price, exchange,
+                          deviation / center * Decimal::ONE_HUNDRED, center);
+                    return false;
+                }
+                warn!("{} on {} held {} for {} ticks, accepting it as the new level", symbol, exchange, price,
+                      window.consecutive_rejections);
+                window.prices.clear();
+            }
+        }
+        window.consecutive_rejections = 0;
+        window.prices.push_back(price);
+        while window.prices.len() > self.config.window_size {
+            window.prices.pop_front();
+        }
+        true
+    }
+
+    /// Runs every price of one venue's scan through the filter and hides
+    /// the rejected ones.
+    pub fn screen<'a, P: PriceLookup>(&self, exchange: &str, prices: &'a P) -> ScreenedPrices<'a, P> {
+        let mut rejected = HashSet::new();
+        if self.config.enabled {
+            prices.for_each_price(|symbol, price| {
+                if !self.accept(exchange, symbol, price) {
+                    rejected.insert(symbol.to_string());
+                }
+            });
+        }
+        ScreenedPrices { prices, rejected }
+    }
+}
+
+/// One venue's prices without the ticks `TickFilter` rejected.
+pub struct ScreenedPrices<'a, P> {
+    prices: &'a P,
+    rejected: HashSet<String>,
+}
+
+impl<P> ScreenedPrices<'_, P> {
+    pub fn rejected(&self) -> &HashSet<String> {
+        &self.rejected
+    }
+}
+
+impl<P: PriceLookup> PriceLookup for ScreenedPrices<'_, P> {
+    fn price(&self, symbol: &str) -> Option<Decimal> {
+        if self.rejected.contains(symbol) {
+            return None;
+        }
+        self.prices.price(symbol)
+    }
+
+    fn for_each_price(&self, mut f: impl FnMut(&str, Decimal)) {
+        self.prices.for_each_price(|symbol, price| {
+            if !self.rejected.contains(symbol) {
+                f(symbol, price);
+            }
+        });
+    }
+
+    fn exchange_time(&self, symbol: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.prices.exchange_time(symbol)
+    }
+
+    fn captured_at(&self, symbol: &str) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.prices.captured_at(symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::PriceMap;
+
+    fn filter() -> TickFilter {
+        TickFilter::new(TickFilterConfig { enabled: true, window_size: 20, min_samples: 5, ..TickFilterConfig::default() })
+    }
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+
+    #[test]
+    fn test_fat_finger_tick_is_hidden_from_detection() {
+        let filter = filter();
+        for price in ["60000", "60010", "59990", "60005", "59995"] {
+            assert!(filter.accept("Binance", "BTCUSDT", dec(price)));
+        }
+
+        let prices = PriceMap::from([("BTCUSDT".to_string(), dec("66000")), ("ETHUSDT".to_string(), dec("3000"))]);
+        let screened = filter.screen("Binance", &prices);
+        assert_eq!(screened.price("BTCUSDT"), None);
+        assert_eq!(screened.price("ETHUSDT"), Some(dec("3000")));
+        let mut seen = Vec::new();
+        screened.for_each_price(|symbol, _| seen.push(symbol.to_string()));
+        assert_eq!(seen, ["ETHUSDT"]);
+
+        // Moves within min_deviation_percentage pass even from a flat window,
+        // and other venues keep their own history
+        assert!(filter.accept("Binance", "BTCUSDT", dec("60300")));
+        assert!(filter.accept("Bybit", "BTCUSDT", dec("66000")));
+    }
+
+    #[test]
+    fn test_persistent_move_is_accepted_as_the_new_level() {
+        let filter = filter();
+        for _ in 0..5 {
+            filter.accept("Bybit", "SOLUSDT", dec("150"));
+        }
+        for _ in 0..3 {
+            assert!(!filter.accept("Bybit", "SOLUSDT", dec("120")));
+        }
+        assert!(filter.accept("Bybit", "SOLUSDT", dec("120")));
+        assert!(filter.accept("Bybit", "SOLUSDT", dec("120.5")));
+    }
+}
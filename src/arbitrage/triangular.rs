@@ -0,0 +1,260 @@
+use crate::symbols::{split_symbol, QUOTE_ASSETS};
+use rust_decimal::Decimal;
+
+/// Prices of a triangle `A/USDT`, `B/A` and `B/USDT`, e.g. BTCUSDT, ETHBTC
+/// and ETHUSDT. The prices are consistent when `b_usdt == a_usdt * b_a`.
+#[derive(Debug, Clone, Copy)]
+pub struct TrianglePrices {
+    pub a_usdt: Decimal,
+    pub b_a: Decimal,
+    pub b_usdt: Decimal,
+}
+
+impl TrianglePrices {
+    /// USDT back per USDT spent on USDT -> A -> B -> USDT: buy A, buy B
+    /// with A, sell B. Example: BTCUSDT=50000, ETHBTC=0.06, ETHUSDT=3000
+    /// gives (1/50000)/0.06*3000 = 1.
+    pub fn forward_return(&self) -> Decimal {
+        Decimal::ONE / self.a_usdt / self.b_a * self.b_usdt
+    }
+    
+    /// USDT back per USDT spent on USDT -> B -> A -> USDT: buy B, sell B
+    /// for A, sell A. The reciprocal of `forward_return`.
+    pub fn reverse_return(&self) -> Decimal {
+        Decimal::ONE / self.b_usdt * self.b_a * self.a_usdt
+    }
+}
+
+/// Gross and net profit percentages of a cycle returning `cycle_return`
+/// per unit, paying `taker_fee` on each of `legs` trades.
+pub fn cycle_profit_percentages(cycle_return: Decimal, taker_fee: Decimal, legs: u32) -> (Decimal, Decimal) {
+    let gross = (cycle_return - Decimal::ONE) * Decimal::ONE_HUNDRED;
+    let net = gross - taker_fee * Decimal::from(legs) * Decimal::ONE_HUNDRED;
+    (gross, net)
+}
+
+/// Triangles `A/USDT`, `B/A`, `B/USDT` between every ordered pair of
+/// stablecoins other than USDT, e.g. USDCUSDT, FDUSDUSDC, FDUSDUSDT. A venue
+/// lists at most one of `B/A` and `A/B`; the other triangle is never priced.
+pub fn stablecoin_paths(assets: &[String]) -> Vec<(String, String, String)> {
+    let others: Vec<&String> = assets.iter().filter(|asset| *asset != "USDT").collect();
+    let mut paths = Vec::new();
+    for a in &others {
+        for b in others.iter().filter(|b| *b != a) {
+            paths.push((format!("{}USDT", a), format!("{}{}", b, a), format!("{}USDT", b)));
+        }
+    }
+    paths
+}
+
+/// Triangles `A/USDT`, `B/A`, `B/USDT` that a newly listed `symbol`
+/// completes, given which symbols the venue lists. `B/USDT` can close a
+/// triangle through any quote asset `A`; `B/A` needs both USDT markets.
+pub fn listing_paths(symbol: &str, is_listed: impl Fn(&str) -> bool) -> Vec<(String, String, String)> {
+    let Some((base, quote)) = split_symbol(symbol) else {
+        return Vec::new();
+    };
+    let mut paths = Vec::new();
+    if quote == "USDT" {
+        for a in QUOTE_ASSETS.iter().filter(|a| **a != "USDT" && **a != base) {
+            let (a_usdt, b_a) = (format!("{}USDT", a), format!("{}{}", base, a));
+            if is_listed(&a_usdt) && is_listed(&b_a) {
+                paths.push((a_usdt, b_a, symbol.to_string()));
+            }
+        }
+    } else {
+        let (a_usdt, b_usdt) = (format!("{}USDT", quote), format!("{}USDT", base));
+        if is_listed(&a_usdt) && is_listed(&b_usdt) {
+            paths.push((a_usdt, symbol.to_string(), b_usdt));
+        }
+    }
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::ArbitrageEngine;
+    use crate::config::Config;
+    use crate::exchanges::{OrderSide, PriceMap};
+    use crate::precision::{quantize_qty, Rounding, DEFAULT_QUANTITY_STEP};
+    use proptest::prelude::*;
+    
+    /// USDT prices from sub-cent tokens to BTC-scale quotes and cross
+    /// rates from 1e-10 to 10, with exchange-like precision.
+    fn price_pair() -> impl Strategy<Value = (Decimal, Decimal)> {
+        (1..=10_000_000_000i64, 5..=8u32, 1..=1_000_000_000i64, 8..=10u32)
+            .prop_map(|(a_usdt, a_scale, b_a, b_scale)| (Decimal::new(a_usdt, a_scale), Decimal::new(b_a, b_scale)))
+    }
+    
+    fn consistent_triangle() -> impl Strategy<Value = TrianglePrices> {
+        price_pair().prop_map(|(a_usdt, b_a)| TrianglePrices { a_usdt, b_a, b_usdt: a_usdt * b_a })
+    }
+    
+    /// Moves `b_usdt` by up to ±5% so either direction may be profitable.
+    fn mispriced_triangle() -> impl Strategy<Value = TrianglePrices> {
+        (consistent_triangle(), -5_000..=5_000i64).prop_map(|(mut prices, shift)| {
+            prices.b_usdt *= Decimal::ONE + Decimal::new(shift, 5);
+            prices
+        })
+    }
+    
+    /// 0 to 0.5% per trade
+    fn taker_fee() -> impl Strategy<Value = Decimal> {
+        (0..=50i64).prop_map(|fee| Decimal::new(fee, 4))
+    }
+    
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2_000))]
+        
+        #[test]
+        fn test_consistent_prices_have_no_profit(prices in consistent_triangle(), fee in taker_fee()) {
+            let tolerance = Decimal::new(1, 18);
+            
+            for cycle_return in [prices.forward_return(), prices.reverse_return()] {
+                let (gross, net) = cycle_profit_percentages(cycle_return, fee, 3);
+                prop_assert!(gross.abs() < tolerance, "{:?} gross {}", prices, gross);
+                if fee > Decimal::ZERO {
+                    prop_assert!(net < Decimal::ZERO, "{:?} fee {} net {}", prices, fee, net);
+                }
+            }
+        }
+        
+        #[test]
+        fn test_directions_are_reciprocal(prices in mispriced_triangle()) {
+            let tolerance = Decimal::new(1, 18);
+            let product = prices.forward_return() * prices.reverse_return();
+            prop_assert!((product - Decimal::ONE).abs() < tolerance, "{:?} product {}", prices, product);
+        }
+        
+        #[test]
+        fn test_both_directions_never_profitable_after_fees(prices in mispriced_triangle(), fee in taker_fee()) {
+            let (_, forward_net) = cycle_profit_percentages(prices.forward_return(), fee, 3);
+            let (_, reverse_net) = cycle_profit_percentages(prices.reverse_return(), fee, 3);
+            prop_assert!(
+                !(forward_net > Decimal::ZERO && reverse_net > Decimal::ZERO),
+                "{:?} fee {} forward {} reverse {}", prices, fee, forward_net, reverse_net
+            );
+        }
+        
+        #[test]
+        fn test_net_profit_falls_as_fees_rise(prices in mispriced_triangle(), a in taker_fee(), b in taker_fee()) {
+            let (low, high) = (a.min(b), a.max(b));
+            
+            for cycle_return in [prices.forward_return(), prices.reverse_return()] {
+                let (gross_low, net_low) = cycle_profit_percentages(cycle_return, low, 3);
+                let (gross_high, net_high) = cycle_profit_percentages(cycle_return, high, 3);
+                prop_assert_eq!(gross_low, gross_high);
+                prop_assert!(net_low >= net_high, "fee {} net {} vs fee {} net {}", low, net_low, high, net_high);
+                if low < high {
+                    prop_assert!(net_low > net_high);
+                }
+                prop_assert!(net_low <= gross_low);
+            }
+        }
+        
+        #[test]
+        fn test_richer_exit_price_raises_forward_return(prices in mispriced_triangle()) {
+            let richer = TrianglePrices { b_usdt: prices.b_usdt * Decimal::new(1001, 3), ..prices };
+            prop_assert!(richer.forward_return() > prices.forward_return());
+            prop_assert!(richer.reverse_return() < prices.reverse_return());
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_engine_follows_cycle_direction() {
+        let engine = ArbitrageEngine::new();
+        let dec = |v: &str| Decimal::from_str_exact(v).unwrap();
+        let lot = |quantity: Decimal| quantize_qty(quantity, DEFAULT_QUANTITY_STEP, Rounding::Down);
+        let prices = |eth_usdt: &str| -> PriceMap {
+            [("BTCUSDT", "50000"), ("ETHBTC", "0.06"), ("ETHUSDT", eth_usdt)].into_iter()
+                .map(|(symbol, price)| (symbol.to_string(), dec(price)))
+                .collect()
+        };
+        
+        let consistent = engine.analyze_opportunities(&prices("3000"), &PriceMap::new()).await.unwrap();
+        assert!(consistent.is_empty());
+        
+        // ETH is 2% rich in USDT: buy BTC, buy ETH with BTC, sell ETH
+        let forward = engine.analyze_opportunities(&prices("3060"), &PriceMap::new()).await.unwrap();
+        assert_eq!(forward.len(), 1);
+        let steps = &forward[0].execution_steps;
+        assert_eq!(steps.iter().map(|s| s.symbol.as_str()).collect::<Vec<_>>(), ["BTCUSDT", "ETHBTC", "ETHUSDT"]);
+        assert!(steps.iter().map(|s| s.side.clone()).eq([OrderSide::Buy, OrderSide::Buy, OrderSide::Sell]));
+        // Each leg trades what the one before received after the 0.1% fee, down to the lot size
+        assert_eq!(steps[2].quantity, lot(steps[1].quantity * dec("0.999")));
+        assert_eq!(forward[0].profit_percentage.round_dp(6), dec("2"));
+        
+        // ETH is 2% cheap in USDT: buy ETH, sell ETH for BTC, sell BTC
+        let reverse = engine.analyze_opportunities(&prices("2940"), &PriceMap::new()).await.unwrap();
+        assert_eq!(reverse.len(), 1);
+        let steps = &reverse[0].execution_steps;
+        assert_eq!(steps.iter().map(|s| s.symbol.as_str()).collect::<Vec<_>>(), ["ETHUSDT", "ETHBTC", "BTCUSDT"]);
+        assert!(steps.iter().map(|s| s.side.clone()).eq([OrderSide::Buy, OrderSide::Sell, OrderSide::Sell]));
+        assert_eq!(steps[1].quantity, lot(steps[0].quantity * dec("0.999")));
+        assert_eq!(steps[2].quantity, lot(steps[1].quantity * dec("0.06") * dec("0.999")));
+    }
+    
+    #[tokio::test]
+    async fn test_stablecoin_triangles_use_their_own_threshold() {
+        let assets = ["USDT", "USDC", "FDUSD"].map(String::from);
+        assert_eq!(stablecoin_paths(&assets), vec![
+            ("USDCUSDT".to_string(), "FDUSDUSDC".to_string(), "FDUSDUSDT".to_string()),
+            ("FDUSDUSDT".to_string(), "USDCFDUSD".to_string(), "USDCUSDT".to_string()),
+        ]);
+        
+        let dec = |v: &str| Decimal::from_str_exact(v).unwrap();
+        // FDUSD is 0.4% rich in USDT against its USDC cross, 0.1% after fees
+        let prices: PriceMap = [("USDCUSDT", "1"), ("FDUSDUSDC", "1"), ("FDUSDUSDT", "1.004")].into_iter()
+            .map(|(symbol, price)| (symbol.to_string(), dec(price)))
+            .collect();
+        let mut config = Config::default();
+        config.stablecoins.assets = assets.to_vec();
+        assert!(ArbitrageEngine::from_config(&config).analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+        
+        config.stablecoins.triangles_enabled = true;
+        let engine = ArbitrageEngine::from_config(&config);
+        let found = engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].execution_steps[1].symbol, "FDUSDUSDC");
+        assert!(found[0].net_profit_percentage < config.trading.min_profit_threshold);
+    }
+    
+    #[tokio::test]
+    async fn test_new_listings_get_triangles_with_a_boosted_threshold() {
+        let listed = ["BTCUSDT", "ETHUSDT", "WIFBTC", "WIFETH"];
+        let is_listed = |symbol: &str| listed.contains(&symbol);
+        assert_eq!(listing_paths("WIFUSDT", is_listed), vec![
+            ("BTCUSDT".to_string(), "WIFBTC".to_string(), "WIFUSDT".to_string()),
+            ("ETHUSDT".to_string(), "WIFETH".to_string(), "WIFUSDT".to_string()),
+        ]);
+        assert_eq!(listing_paths("WIFBTC", |symbol| symbol == "BTCUSDT" || symbol == "WIFUSDT"),
+                   vec![("BTCUSDT".to_string(), "WIFBTC".to_string(), "WIFUSDT".to_string())]);
+        assert!(listing_paths("WIFBTC", |symbol| symbol == "BTCUSDT").is_empty());
+        
+        let dec = |v: &str| Decimal::from_str_exact(v).unwrap();
+        // WIF is 0.5% rich in USDT against its BTC cross, 0.2% after fees
+        let prices: PriceMap = [("BTCUSDT", "50000"), ("WIFBTC", "0.00006"), ("WIFUSDT", "3.015")].into_iter()
+            .map(|(symbol, price)| (symbol.to_string(), dec(price)))
+            .collect();
+        let mut config = Config::default();
+        config.listings.enabled = true;
+        config.listings.min_profit_threshold = Some(dec("0.1"));
+        let path = ("BTCUSDT".to_string(), "WIFBTC".to_string(), "WIFUSDT".to_string());
+        
+        let engine = ArbitrageEngine::from_config(&config);
+        let now = chrono::Utc::now();
+        assert_eq!(engine.register_listing_paths("Binance", vec![path.clone()], now), vec![path.clone()]);
+        assert!(engine.register_listing_paths("Binance", vec![path.clone()], now).is_empty());
+        let found = engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap();
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].execution_steps[1].symbol, "WIFBTC");
+        // Only checked on the venue that listed it
+        assert!(engine.analyze_opportunities(&PriceMap::new(), &prices).await.unwrap().is_empty());
+        
+        // Past the boost the triangular threshold applies again
+        let engine = ArbitrageEngine::from_config(&config);
+        engine.register_listing_paths("Binance", vec![path], now - chrono::Duration::minutes(31));
+        assert!(engine.analyze_opportunities(&prices, &PriceMap::new()).await.unwrap().is_empty());
+    }
+}
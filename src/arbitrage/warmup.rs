@@ -0,0 +1,137 @@
+use super::risk::RiskInputs;
+use super::{ArbitrageOpportunity, ExecutionStep};
+use crate::config::WarmupConfig;
+use crate::exchanges::Kline;
+use dashmap::DashMap;
+use rust_decimal::prelude::*;
+
+/// Statistics of the one-minute candles a symbol was warmed up with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CandleStats {
+    returns: usize,
+    volatility_pct: Option<f64>,
+}
+
+/// Keeps symbols out of execution until the bot has watched them long
+/// enough to judge their risk: `min_observations` price returns, from
+/// scans or candles, and a volatility estimate. Candles let pairs present
+/// at startup trade right away; a new listing has few and waits for scans.
+#[derive(Debug)]
+pub struct WarmupTracker {
+    config: WarmupConfig,
+    /// Keyed by exchange and symbol; an entry means candles were fetched
+    candles: DashMap<(String, String), CandleStats>,
+}
+
+impl WarmupTracker {
+    pub fn new(config: WarmupConfig) -> Self {
+        Self { config, candles: DashMap::new() }
+    }
+
+    pub fn config(&self) -> &WarmupConfig {
+        &self.config
+    }
+
+    /// Whether candles should be fetched for `symbol` on `exchange`: it is
+    /// still cold and none have been fetched yet.
+    pub fn needs_candles(&self, exchange: &str, symbol: &str, inputs: &RiskInputs) -> bool {
+        self.config.enabled
+            && self.config.candles > 0
+            && !self.candles.contains_key(&(exchange.to_string(), symbol.to_string()))
+            && !self.is_warm(exchange, symbol, inputs)
+    }
+
+    /// Records the close-to-close returns of `klines`, oldest first. An
+    /// empty slice marks the symbol as fetched without adding anything.
+    pub fn seed(&self, exchange: &str, symbol: &str, klines: &[Kline]) {
+        let returns: Vec<f64> = klines.windows(2)
+            .filter(|pair| pair[0].close > Decimal::ZERO)
+            .map(|pair| ((pair[1].close - pair[0].close) / pair[0].close).to_f64().unwrap_or(0.0))
+            .collect();
+        let volatility_pct = (returns.len() >= 5).then(|| {
+            let n = returns.len() as f64;
+            let mean = returns.iter().sum::<f64>() / n;
+            let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (n - 1.0);
+            variance.sqrt() * 100.0
+        });
+        let stats = CandleStats { returns: returns.len(), volatility_pct };
+        self.candles.insert((exchange.to_string(), symbol.to_string()), stats);
+    }
+
+    /// Returns seen for `symbol` on `exchange`, from scans and candles.
+    pub fn observations(&self, exchange: &str, symbol: &str, inputs: &RiskInputs) -> usize {
+        let candles = self.candle_stats(exchange, symbol).map_or(0, |stats| stats.returns);
+        inputs.observations(exchange, symbol) + candles
+    }
+
+    fn candle_stats(&self, exchange: &str, symbol: &str) -> Option<CandleStats> {
+        self.candles.get(&(exchange.to_string(), symbol.to_string())).map(|stats| *stats)
+    }
+
+    pub fn is_warm(&self, exchange: &str, symbol: &str, inputs: &RiskInputs) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        let has_volatility = inputs.volatility_pct(exchange, symbol).is_some()
+            || self.candle_stats(exchange, symbol).is_some_and(|stats| stats.volatility_pct.is_some());
+        has_volatility && self.observations(exchange, symbol, inputs) >= self.config.min_observations
+    }
+
+    /// The first leg of `opportunity` on a symbol that is still warming up,
+    /// with the returns seen for it so far.
+    pub fn cold_leg<'a>(&self, opportunity: &'a ArbitrageOpportunity, inputs: &RiskInputs) -> Option<(&'a ExecutionStep, usize)> {
+        opportunity.execution_steps.iter()
+            .find(|step| !self.is_warm(&step.exchange, &step.symbol, inputs))
+            .map(|step| (step, self.observations(&step.exchange, &step.symbol, inputs)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+
+    fn klines(count: usize) -> Vec<Kline> {
+        let start = Utc::now() - Duration::minutes(count as i64);
+        (0..count).map(|minute| {
+            let close = Decimal::from(100 + (minute % 3) as i64);
+            Kline {
+                open_time: start + Duration::minutes(minute as i64),
+                open: close,
+                high: close,
+                low: close,
+                close,
+                volume: Decimal::ONE,
+            }
+        }).collect()
+    }
+
+    #[test]
+    fn test_candles_or_scans_warm_a_symbol_up() {
+        let warmup = WarmupTracker::new(WarmupConfig { enabled: true, min_observations: 10, candles: 60 });
+        let inputs = RiskInputs::new(60);
+        assert!(!warmup.is_warm("Binance", "BTCUSDT", &inputs));
+        assert!(warmup.needs_candles("Binance", "BTCUSDT", &inputs));
+
+        warmup.seed("Binance", "BTCUSDT", &klines(11));
+        assert!(warmup.is_warm("Binance", "BTCUSDT", &inputs));
+        assert!(!warmup.needs_candles("Binance", "BTCUSDT", &inputs));
+        // Candles are per venue
+        assert!(!warmup.is_warm("Bybit", "BTCUSDT", &inputs));
+
+        // A fresh listing with a few candles tops up with scans
+        warmup.seed("Binance", "WIFUSDT", &klines(4));
+        assert!(!warmup.needs_candles("Binance", "WIFUSDT", &inputs));
+        for price in [100, 101, 100, 102, 101, 100, 101] {
+            inputs.observe_price("Binance", "WIFUSDT", Decimal::from(price));
+        }
+        assert_eq!(warmup.observations("Binance", "WIFUSDT", &inputs), 9);
+        assert!(!warmup.is_warm("Binance", "WIFUSDT", &inputs));
+        inputs.observe_price("Binance", "WIFUSDT", Decimal::from(102));
+        assert!(warmup.is_warm("Binance", "WIFUSDT", &inputs));
+
+        let disabled = WarmupTracker::new(WarmupConfig::default());
+        assert!(disabled.is_warm("Bybit", "SOLUSDT", &inputs));
+        assert!(!disabled.needs_candles("Bybit", "SOLUSDT", &inputs));
+    }
+}
@@ -0,0 +1,173 @@
+use crate::arbitrage::ArbitrageEngine;
+use crate::config::Config;
+use crate::replay::MarketSnapshot;
+use anyhow::Result;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// The parameters a sweep varies; everything else comes from the base config.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BacktestParams {
+    pub min_profit_threshold: Decimal,
+    pub max_position_size: Decimal,
+    pub max_slippage_percentage: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestResult {
+    pub params: BacktestParams,
+    pub trades: usize,
+    /// Trades whose slippage exceeded the limit and were unwound at it
+    pub abandoned: usize,
+    /// Opportunities over the strategy's risk score limit
+    pub skipped: usize,
+    pub net_pnl_usd: Decimal,
+    pub max_drawdown_usd: Decimal,
+    /// Notional traded per venue, each leg counted at the position size
+    pub volume_usd: BTreeMap<String, Decimal>,
+}
+
+/// Replays recorded market snapshots through the engine and simulates
+/// executing every opportunity it accepts.
+///
+/// Fills are modelled from observed cycle slippage: the n-th simulated
+/// trade realizes the n-th sample (cycling), so every parameter set sees
+/// the same slippage sequence. A trade whose slippage exceeds
+/// `max_slippage_percentage` is assumed to be unwound at that limit,
+/// losing it instead of earning the opportunity's profit. Without samples
+/// fills are assumed to match the scanned prices.
+pub struct Backtester<'a> {
+    snapshots: &'a [MarketSnapshot],
+    slippage_samples: Vec<Decimal>,
+}
+
+impl<'a> Backtester<'a> {
+    pub fn new(snapshots: &'a [MarketSnapshot], slippage_samples: Vec<Decimal>) -> Self {
+        Self { snapshots, slippage_samples }
+    }
+    
+    pub async fn run(&self, base: &Config, params: BacktestParams) -> Result<BacktestResult> {
+        let mut config = base.clone();
+        config.trading.min_profit_threshold = params.min_profit_threshold;
+        config.trading.max_position_size = params.max_position_size;
+        config.trading.max_slippage_percentage = params.max_slippage_percentage;
+        let engine = ArbitrageEngine::from_config(&config);
+        
+        let mut result = BacktestResult {
+            params,
+            trades: 0,
+            abandoned: 0,
+            skipped: 0,
+            net_pnl_usd: Decimal::ZERO,
+            max_drawdown_usd: Decimal::ZERO,
+            volume_usd: BTreeMap::new(),
+        };
+        let mut peak = Decimal::ZERO;
+        
+        for snapshot in self.snapshots {
+            for opportunity in engine.analyze_opportunities(&snapshot.binance, &snapshot.bybit).await? {
+                if opportunity.risk_score > config.risk.scoring.max_score(opportunity.strategy) {
+                    result.skipped += 1;
+                    continue;
+                }
+                
+                let slippage = match self.slippage_samples.len() {
+                    0 => Decimal::ZERO,
+                    n => self.slippage_samples[(result.trades + result.abandoned) % n],
+                };
+                let pnl_percentage = if slippage > params.max_slippage_percentage {
+                    result.abandoned += 1;
+                    -params.max_slippage_percentage
+                } else {
+                    result.trades += 1;
+                    opportunity.net_profit_percentage - slippage
+                };
+                
+                result.net_pnl_usd += opportunity.required_amount * pnl_percentage / Decimal::ONE_HUNDRED;
+                for step in &opportunity.execution_steps {
+                    *result.volume_usd.entry(step.exchange.clone()).or_default() += opportunity.required_amount;
+                }
+                peak = peak.max(result.net_pnl_usd);
+                result.max_drawdown_usd = result.max_drawdown_usd.max(peak - result.net_pnl_usd);
+            }
+        }
+        
+        Ok(result)
+    }
+    
+    /// Backtests every combination in `config.optimizer`, best first: by
+    /// net PnL, then by smaller drawdown.
+    pub async fn sweep(&self, config: &Config) -> Result<Vec<BacktestResult>> {
+        let grid = &config.optimizer;
+        let mut results = Vec::new();
+        for min_profit_threshold in &grid.min_profit_thresholds {
+            for max_position_size in &grid.max_position_sizes {
+                for max_slippage_percentage in &grid.max_slippage_percentages {
+                    let params = BacktestParams {
+                        min_profit_threshold: *min_profit_threshold,
+                        max_position_size: *max_position_size,
+                        max_slippage_percentage: *max_slippage_percentage,
+                    };
+                    results.push(self.run(config, params).await?);
+                }
+            }
+        }
+        
+        results.sort_by(|a, b| b.net_pnl_usd.cmp(&a.net_pnl_usd)
+            .then(a.max_drawdown_usd.cmp(&b.max_drawdown_usd)));
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::PriceMap;
+    use chrono::Utc;
+    
+    fn snapshot(binance_btc: i64, bybit_btc: i64) -> MarketSnapshot {
+        MarketSnapshot {
+            captured_at: Utc::now(),
+            binance: PriceMap::from([("BTCUSDT".to_string(), Decimal::from(binance_btc))]),
+            bybit: PriceMap::from([("BTCUSDT".to_string(), Decimal::from(bybit_btc))]),
+            opportunities: Vec::new(),
+        }
+    }
+    
+    fn config() -> Config {
+        let mut config = Config::default();
+        config.trading.trading_pairs = vec!["BTCUSDT".to_string()];
+        // Two flat samples are enough history for the gap to count as an outlier
+        config.trading.spread_min_samples = 2;
+        config.trading.spread_zscore_threshold = 1.0;
+        config.risk.scoring.cross_exchange_max_score = 1.0;
+        config
+    }
+    
+    fn params(max_slippage: &str) -> BacktestParams {
+        BacktestParams {
+            min_profit_threshold: Decimal::from_str_exact("0.1").unwrap(),
+            max_position_size: Decimal::from(1000),
+            max_slippage_percentage: Decimal::from_str_exact(max_slippage).unwrap(),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_slippage_limit_turns_trades_into_losses() {
+        // A flat spread, then a 2% gap between venues, well above fees
+        let snapshots = vec![snapshot(60000, 60000), snapshot(60000, 60000), snapshot(60000, 61200), snapshot(60000, 61200)];
+        let samples = vec![Decimal::from_str_exact("0.1").unwrap(), Decimal::ONE];
+        let backtester = Backtester::new(&snapshots, samples);
+        
+        let loose = backtester.run(&config(), params("2")).await.unwrap();
+        assert_eq!((loose.trades, loose.abandoned), (2, 0));
+        assert!(loose.net_pnl_usd > Decimal::ZERO);
+        assert_eq!(loose.volume_usd.keys().collect::<Vec<_>>(), ["Binance", "Bybit"]);
+        
+        let tight = backtester.run(&config(), params("0.5")).await.unwrap();
+        assert_eq!((tight.trades, tight.abandoned), (1, 1));
+        assert!(tight.net_pnl_usd < loose.net_pnl_usd);
+        assert!(tight.max_drawdown_usd > Decimal::ZERO);
+    }
+}
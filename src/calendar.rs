@@ -0,0 +1,145 @@
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// A scheduled macro event (FOMC, CPI, ...) around which execution is halted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarEvent {
+    pub name: String,
+    pub time: DateTime<Utc>,
+}
+
+/// Set of volatility events plus the guard band applied around each one.
+#[derive(Debug, Clone)]
+pub struct EventCalendar {
+    events: Vec<CalendarEvent>,
+    before: chrono::Duration,
+    after: chrono::Duration,
+}
+
+impl EventCalendar {
+    pub fn new(mut events: Vec<CalendarEvent>, minutes_before: i64, minutes_after: i64) -> Self {
+        events.sort_by_key(|e| e.time);
+        Self {
+            events,
+            before: chrono::Duration::minutes(minutes_before),
+            after: chrono::Duration::minutes(minutes_after),
+        }
+    }
+    
+    /// Loads events from a `.ics` file or a JSON array of `{name, time}` objects.
+    pub fn load_events(path: &str) -> Result<Vec<CalendarEvent>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read calendar {}: {}", path, e))?;
+        
+        let is_ics = Path::new(path)
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("ics"))
+            .unwrap_or(false);
+        
+        if is_ics {
+            Self::parse_ics(&content)
+        } else {
+            Ok(serde_json::from_str(&content)?)
+        }
+    }
+    
+    pub fn events(&self) -> &[CalendarEvent] {
+        &self.events
+    }
+    
+    /// Returns the event whose guard band contains `now`, if any.
+    pub fn active_event(&self, now: &DateTime<Utc>) -> Option<&CalendarEvent> {
+        self.events
+            .iter()
+            .find(|e| *now >= e.time - self.before && *now <= e.time + self.after)
+    }
+    
+    /// Parses the VEVENT blocks of an iCalendar file, using SUMMARY as the
+    /// name and DTSTART as the time. Floating times are treated as UTC.
+    pub fn parse_ics(content: &str) -> Result<Vec<CalendarEvent>> {
+        let mut events = Vec::new();
+        let mut summary: Option<String> = None;
+        let mut start: Option<DateTime<Utc>> = None;
+        let mut in_event = false;
+        
+        for line in content.lines().map(str::trim_end) {
+            match line {
+                "BEGIN:VEVENT" => {
+                    in_event = true;
+                    summary = None;
+                    start = None;
+                }
+                "END:VEVENT" => {
+                    if let Some(time) = start.take() {
+                        events.push(CalendarEvent {
+                            name: summary.take().unwrap_or_else(|| "Unnamed event".to_string()),
+                            time,
+                        });
+                    }
+                    in_event = false;
+                }
+                _ if in_event => {
+                    let Some((key, value)) = line.split_once(':') else { continue };
+                    // Property parameters (e.g. DTSTART;TZID=...) follow a semicolon
+                    let name = key.split(';').next().unwrap_or(key);
+                    match name {
+                        "SUMMARY" => summary = Some(value.to_string()),
+                        "DTSTART" => start = Some(Self::parse_ics_time(value)?),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        
+        Ok(events)
+    }
+    
+    fn parse_ics_time(value: &str) -> Result<DateTime<Utc>> {
+        let value = value.trim_end_matches('Z');
+        if let Ok(time) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S") {
+            return Ok(time.and_utc());
+        }
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|time| time.and_utc())
+            .ok_or_else(|| anyhow::anyhow!("Invalid DTSTART value: {}", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    
+    #[test]
+    fn test_parse_ics() {
+        let ics = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:FOMC Rate Decision\r\nDTSTART:20240501T180000Z\r\nEND:VEVENT\r\nBEGIN:VEVENT\r\nDTSTART;VALUE=DATE:20240515\r\nSUMMARY:CPI\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let events = EventCalendar::parse_ics(ics).unwrap();
+        
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].name, "FOMC Rate Decision");
+        assert_eq!(events[0].time, Utc.with_ymd_and_hms(2024, 5, 1, 18, 0, 0).unwrap());
+        assert_eq!(events[1].name, "CPI");
+        assert_eq!(events[1].time, Utc.with_ymd_and_hms(2024, 5, 15, 0, 0, 0).unwrap());
+    }
+    
+    #[test]
+    fn test_guard_band() {
+        let fomc = Utc.with_ymd_and_hms(2024, 5, 1, 18, 0, 0).unwrap();
+        let calendar = EventCalendar::new(
+            vec![CalendarEvent { name: "FOMC".to_string(), time: fomc }],
+            30,
+            60,
+        );
+        
+        assert!(calendar.active_event(&(fomc - chrono::Duration::minutes(31))).is_none());
+        assert!(calendar.active_event(&(fomc - chrono::Duration::minutes(30))).is_some());
+        assert!(calendar.active_event(&(fomc + chrono::Duration::minutes(60))).is_some());
+        assert!(calendar.active_event(&(fomc + chrono::Duration::minutes(61))).is_none());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::exchanges::KlineInterval;
+use anyhow::Result;
+use chrono::NaiveDate;
+
+/// How `run` and `scan` report opportunities on stdout.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Log lines only
+    #[default]
+    Text,
+    /// One JSON object per opportunity and line
+    Json,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            other => Err(anyhow::anyhow!("Unknown output format: {} (expected text or json)", other)),
+        }
+    }
+}
+
+/// Subcommands accepted by the binary. Running without arguments starts the bot.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Run {
+        output: OutputFormat,
+    },
+    /// A single detection pass that prints what it finds and exits
+    Scan {
+        output: OutputFormat,
+    },
+    DownloadKlines {
+        exchange: String,
+        symbol: String,
+        interval: KlineInterval,
+        days: i64,
+    },
+    /// Re-run the current configuration against the market snapshots recorded on `date`
+    Replay {
+        date: NaiveDate,
+    },
+    /// Grid-search `config.optimizer` over the snapshots recorded from `from` through `to`
+    Optimize {
+        from: NaiveDate,
+        to: NaiveDate,
+    },
+    /// Monte Carlo simulation of executing an opportunity with the given shape
+    StressTest {
+        legs: usize,
+        position_usd: f64,
+        net_profit_percentage: f64,
+    },
+    /// Write the Grafana dashboard for the exposed metrics to `output`, or stdout
+    DashboardExport {
+        output: Option<String>,
+    },
+    /// Validate every configured API key and report its permissions
+    CheckKeys,
+    /// Weekly shadow and live results per strategy and path over the last `weeks` weeks
+    PerformanceReport {
+        weeks: usize,
+        retire_after: usize,
+    },
+    /// Average net profit by path and UTC hour over the last `days` days of market snapshots
+    Heatmap {
+        days: u32,
+    },
+    /// Monthly PnL and volume at each configured VIP fee tier, backtested over
+    /// the last `days` days of market snapshots
+    FeeTiers {
+        days: u32,
+    },
+    /// Archive the ledger, journal, reports and snapshots to `output`, or a timestamped file
+    StateExport {
+        output: Option<String>,
+    },
+    /// Restore an archive written by `state export`; `force` replaces existing state
+    StateImport {
+        input: String,
+        force: bool,
+    },
+    /// Measure clock offset, REST round trips and stream lag per exchange;
+    /// unset values come from `config.diagnostics`
+    Diag {
+        samples: Option<usize>,
+        stream_seconds: Option<u64>,
+    },
+}
+
+impl Command {
+    pub fn parse(args: &[String]) -> Result<Self> {
+        match args.first().map(String::as_str) {
+            None => Ok(Command::Run { output: OutputFormat::Text }),
+            Some("run") => Ok(Command::Run { output: parse_output(&args[1..])? }),
+            Some("scan") => Ok(Command::Scan { output: parse_output(&args[1..])? }),
+            Some("download-klines") => {
+                if args.len() < 4 {
+                    return Err(anyhow::anyhow!(
+                        "usage: download-klines <exchange> <symbol> <interval> [days]"
+                    ));
+                }
+                let days = match args.get(4) {
+                    Some(days) => days.parse()
+                        .map_err(|_| anyhow::anyhow!("days must be an integer, got {}", days))?,
+                    None => 30,
+                };
+                
+                Ok(Command::DownloadKlines {
+                    exchange: args[1].clone(),
+                    symbol: args[2].to_uppercase(),
+                    interval: args[3].parse()?,
+                    days,
+                })
+            }
+            Some("replay") => {
+                let date = match (args.get(1).map(String::as_str), args.get(2)) {
+                    (Some("--date"), Some(date)) => date,
+                    _ => return Err(anyhow::anyhow!("usage: replay --date <YYYY-MM-DD>")),
+                };
+                Ok(Command::Replay { date: parse_date(date)? })
+            }
+            Some("optimize") => {
+                let usage = || anyhow::anyhow!("usage: optimize --from <YYYY-MM-DD> [--to <YYYY-MM-DD>]");
+                let mut from = None;
+                let mut to = None;
+                let mut rest = args[1..].iter();
+                while let Some(flag) = rest.next() {
+                    let value = rest.next().ok_or_else(usage)?;
+                    match flag.as_str() {
+                        "--from" => from = Some(parse_date(value)?),
+                        "--to" => to = Some(parse_date(value)?),
+                        _ => return Err(usage()),
+                    }
+                }
+                let from = from.ok_or_else(usage)?;
+                let to = to.unwrap_or(from);
+                if to < from {
+                    return Err(anyhow::anyhow!("--to must not be before --from"));
+                }
+                Ok(Command::Optimize { from, to })
+            }
+            Some("stress-test") => {
+                let usage = || anyhow::anyhow!("usage: stress-test --profit <percent> [--legs 3] [--position <usd>]");
+                let mut legs = 3;
+                let mut position_usd = 1000.0;
+                let mut net_profit_percentage = None;
+                let mut rest = args[1..].iter();
+                while let Some(flag) = rest.next() {
+                    let value = rest.next().ok_or_else(usage)?;
+                    let invalid = || anyhow::anyhow!("{} must be a number, got {}", flag, value);
+                    match flag.as_str() {
+                        "--legs" => legs = value.parse().map_err(|_| invalid())?,
+                        "--position" => position_usd = value.parse().map_err(|_| invalid())?,
+                        "--profit" => net_profit_percentage = Some(value.parse().map_err(|_| invalid())?),
+                        _ => return Err(usage()),
+                    }
+                }
+                if legs == 0 || position_usd <= 0.0 {
+                    return Err(anyhow::anyhow!("--legs and --position must be positive"));
+                }
+                Ok(Command::StressTest {
+                    legs,
+                    position_usd,
+                    net_profit_percentage: net_profit_percentage.ok_or_else(usage)?,
+                })
+            }
+            Some("dashboard") => match &args[1..] {
+                [action] if action == "export" => Ok(Command::DashboardExport { output: None }),
+                [action, flag, path] if action == "export" && flag == "--output" => {
+                    Ok(Command::DashboardExport { output: Some(path.clone()) })
+                }
+                _ => Err(anyhow::anyhow!("usage: dashboard export [--output <file>]")),
+            },
+            Some("check-keys") if args.len() == 1 => Ok(Command::CheckKeys),
+            Some("check-keys") => Err(anyhow::anyhow!("usage: check-keys")),
+            Some("performance-report") => {
+                let usage = || anyhow::anyhow!("usage: performance-report [--weeks 8] [--retire-after 3]");
+                let mut weeks = 8;
+                let mut retire_after = 3;
+                let mut rest = args[1..].iter();
+                while let Some(flag) = rest.next() {
+                    let value = rest.next().ok_or_else(usage)?;
+                    let invalid = || anyhow::anyhow!("{} must be an integer, got {}", flag, value);
+                    match flag.as_str() {
+                        "--weeks" => weeks = value.parse().map_err(|_| invalid())?,
+                        "--retire-after" => retire_after = value.parse().map_err(|_| invalid())?,
+                        _ => return Err(usage()),
+                    }
+                }
+                if retire_after == 0 || retire_after >= weeks {
+                    return Err(anyhow::anyhow!("--retire-after must be at least 1 and less than --weeks"));
+                }
+                Ok(Command::PerformanceReport { weeks, retire_after })
+            }
+            Some("heatmap") => {
+                let days = match &args[1..] {
+                    [] => 7,
+                    [flag, days] if flag == "--days" => days.parse()
+                        .map_err(|_| anyhow::anyhow!("--days must be an integer, got {}", days))?,
+                    _ => return Err(anyhow::anyhow!("usage: heatmap [--days 7]")),
+                };
+                if days == 0 {
+                    return Err(anyhow::anyhow!("--days must be at least 1"));
+                }
+                Ok(Command::Heatmap { days })
+            }
+            Some("fee-tiers") => {
+                let days = match &args[1..] {
+                    [] => 30,
+                    [flag, days] if flag == "--days" => days.parse()
+                        .map_err(|_| anyhow::anyhow!("--days must be an integer, got {}", days))?,
+                    _ => return Err(anyhow::anyhow!("usage: fee-tiers [--days 30]")),
+                };
+                if days == 0 {
+                    return Err(anyhow::anyhow!("--days must be at least 1"));
+                }
+                Ok(Command::FeeTiers { days })
+            }
+            Some("state") => match &args[1..] {
+                [action] if action == "export" => Ok(Command::StateExport { output: None }),
+                [action, flag, path] if action == "export" && flag == "--output" => {
+                    Ok(Command::StateExport { output: Some(path.clone()) })
+                }
+                [action, input] if action == "import" => Ok(Command::StateImport { input: input.clone(), force: false }),
+                [action, input, flag] if action == "import" && flag == "--force" => {
+                    Ok(Command::StateImport { input: input.clone(), force: true })
+                }
+                _ => Err(anyhow::anyhow!("usage: state export [--output <file>] | state import <file> [--force]")),
+            },
+            Some("diag") => {
+                let usage = || anyhow::anyhow!("usage: diag [--samples <n>] [--stream-seconds <s>]");
+                let (mut samples, mut stream_seconds) = (None, None);
+                let mut rest = args[1..].iter();
+                while let Some(flag) = rest.next() {
+                    let value = rest.next().ok_or_else(usage)?;
+                    let invalid = || anyhow::anyhow!("{} must be an integer, got {}", flag, value);
+                    match flag.as_str() {
+                        "--samples" => samples = Some(value.parse().map_err(|_| invalid())?),
+                        "--stream-seconds" => stream_seconds = Some(value.parse().map_err(|_| invalid())?),
+                        _ => return Err(usage()),
+                    }
+                }
+                if samples == Some(0) {
+                    return Err(anyhow::anyhow!("--samples must be at least 1"));
+                }
+                Ok(Command::Diag { samples, stream_seconds })
+            }
+            Some(other) => Err(anyhow::anyhow!("Unknown command: {}", other)),
+        }
+    }
+}
+
+fn parse_output(args: &[String]) -> Result<OutputFormat> {
+    match args {
+        [] => Ok(OutputFormat::Text),
+        [flag, format] if flag == "--output" => format.parse(),
+        _ => Err(anyhow::anyhow!("usage: run|scan [--output text|json]")),
+    }
+}
+
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("date must be YYYY-MM-DD, got {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+    
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(Command::parse(&[]).unwrap(), Command::Run { output: OutputFormat::Text });
+        assert_eq!(
+            Command::parse(&args(&["run", "--output", "json"])).unwrap(),
+            Command::Run { output: OutputFormat::Json }
+        );
+        assert_eq!(Command::parse(&args(&["scan"])).unwrap(), Command::Scan { output: OutputFormat::Text });
+        assert!(Command::parse(&args(&["scan", "--output", "yaml"])).is_err());
+        assert_eq!(
+            Command::parse(&args(&["download-klines", "binance", "btcusdt", "1h"])).unwrap(),
+            Command::DownloadKlines {
+                exchange: "binance".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                interval: KlineInterval::OneHour,
+                days: 30,
+            }
+        );
+        assert!(Command::parse(&args(&["download-klines", "binance"])).is_err());
+        assert!(Command::parse(&args(&["download-klines", "binance", "BTCUSDT", "7m"])).is_err());
+        assert_eq!(
+            Command::parse(&args(&["replay", "--date", "2024-05-01"])).unwrap(),
+            Command::Replay { date: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap() }
+        );
+        assert!(Command::parse(&args(&["replay", "2024-05-01"])).is_err());
+        assert!(Command::parse(&args(&["replay", "--date", "05/01/2024"])).is_err());
+        assert_eq!(
+            Command::parse(&args(&["optimize", "--from", "2024-05-01", "--to", "2024-05-07"])).unwrap(),
+            Command::Optimize {
+                from: NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(),
+                to: NaiveDate::from_ymd_opt(2024, 5, 7).unwrap(),
+            }
+        );
+        assert!(Command::parse(&args(&["optimize", "--to", "2024-05-07"])).is_err());
+        assert!(Command::parse(&args(&["optimize", "--from", "2024-05-07", "--to", "2024-05-01"])).is_err());
+        assert_eq!(
+            Command::parse(&args(&["stress-test", "--profit", "0.4", "--legs", "2"])).unwrap(),
+            Command::StressTest { legs: 2, position_usd: 1000.0, net_profit_percentage: 0.4 }
+        );
+        assert!(Command::parse(&args(&["stress-test", "--legs", "3"])).is_err());
+        assert!(Command::parse(&args(&["stress-test", "--profit", "abc"])).is_err());
+        assert_eq!(
+            Command::parse(&args(&["dashboard", "export", "--output", "arbitrage.json"])).unwrap(),
+            Command::DashboardExport { output: Some("arbitrage.json".to_string()) }
+        );
+        assert_eq!(Command::parse(&args(&["dashboard", "export"])).unwrap(), Command::DashboardExport { output: None });
+        assert!(Command::parse(&args(&["dashboard"])).is_err());
+        assert_eq!(Command::parse(&args(&["check-keys"])).unwrap(), Command::CheckKeys);
+        assert!(Command::parse(&args(&["check-keys", "binance"])).is_err());
+        assert_eq!(
+            Command::parse(&args(&["performance-report"])).unwrap(),
+            Command::PerformanceReport { weeks: 8, retire_after: 3 }
+        );
+        assert_eq!(
+            Command::parse(&args(&["performance-report", "--weeks", "12", "--retire-after", "4"])).unwrap(),
+            Command::PerformanceReport { weeks: 12, retire_after: 4 }
+        );
+        assert!(Command::parse(&args(&["performance-report", "--weeks", "3"])).is_err());
+        assert!(Command::parse(&args(&["performance-report", "--weeks"])).is_err());
+        assert_eq!(Command::parse(&args(&["heatmap"])).unwrap(), Command::Heatmap { days: 7 });
+        assert_eq!(Command::parse(&args(&["heatmap", "--days", "30"])).unwrap(), Command::Heatmap { days: 30 });
+        assert!(Command::parse(&args(&["heatmap", "--days", "0"])).is_err());
+        assert_eq!(Command::parse(&args(&["fee-tiers", "--days", "14"])).unwrap(), Command::FeeTiers { days: 14 });
+        assert!(Command::parse(&args(&["fee-tiers", "14"])).is_err());
+        assert_eq!(Command::parse(&args(&["state", "export"])).unwrap(), Command::StateExport { output: None });
+        assert_eq!(
+            Command::parse(&args(&["state", "import", "state.tar.gz", "--force"])).unwrap(),
+            Command::StateImport { input: "state.tar.gz".to_string(), force: true }
+        );
+        assert!(Command::parse(&args(&["state", "import"])).is_err());
+        assert_eq!(Command::parse(&args(&["diag"])).unwrap(), Command::Diag { samples: None, stream_seconds: None });
+        assert_eq!(
+            Command::parse(&args(&["diag", "--stream-seconds", "0", "--samples", "5"])).unwrap(),
+            Command::Diag { samples: Some(5), stream_seconds: Some(0) }
+        );
+        assert!(Command::parse(&args(&["diag", "--samples", "0"])).is_err());
+        assert!(Command::parse(&args(&["bogus"])).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+use crate::calendar::EventCalendar;
+use crate::maintenance::MaintenanceWindow;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+/// Runtime switches shared between the scan loop and the control API.
+#[derive(Debug, Default)]
+pub struct BotControl {
+    scanning_paused: AtomicBool,
+    execution_paused: AtomicBool,
+    /// Set by the SLO monitor while market data misses its objectives
+    slo_breach: Mutex<Option<String>>,
+    maintenance_windows: Vec<MaintenanceWindow>,
+    event_calendar: Option<EventCalendar>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlStatus {
+    pub scanning_paused: bool,
+    pub execution_paused: bool,
+    pub slo_breach: Option<String>,
+    pub active_maintenance_window: Option<String>,
+    pub active_calendar_event: Option<String>,
+    pub execution_allowed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseScope {
+    Scanning,
+    Execution,
+    All,
+}
+
+impl BotControl {
+    pub fn new(maintenance_windows: Vec<MaintenanceWindow>) -> Self {
+        Self {
+            maintenance_windows,
+            ..Default::default()
+        }
+    }
+    
+    pub fn with_event_calendar(mut self, calendar: EventCalendar) -> Self {
+        self.event_calendar = Some(calendar);
+        self
+    }
+    
+    pub fn pause(&self, scope: PauseScope) {
+        self.set_paused(scope, true);
+    }
+    
+    pub fn resume(&self, scope: PauseScope) {
+        self.set_paused(scope, false);
+    }
+    
+    /// Pauses execution for `reason` until called again with `None`,
+    /// independently of the control API's pause.
+    pub fn set_slo_breach(&self, reason: Option<String>) {
+        *self.slo_breach.lock().unwrap() = reason;
+    }
+    
+    pub fn is_scanning_paused(&self) -> bool {
+        self.scanning_paused.load(Ordering::Relaxed)
+    }
+    
+    pub fn active_maintenance_window(&self, now: &DateTime<Utc>) -> Option<&MaintenanceWindow> {
+        self.maintenance_windows.iter().find(|w| w.is_active(now))
+    }
+    
+    /// Explains why execution is currently blocked, or `None` when it may proceed.
+    pub fn execution_block_reason(&self, now: &DateTime<Utc>) -> Option<String> {
+        if self.execution_paused.load(Ordering::Relaxed) || self.is_scanning_paused() {
+            return Some("execution paused via control API".to_string());
+        }
+        if let Some(reason) = self.slo_breach.lock().unwrap().as_ref() {
+            return Some(format!("market data SLO violated: {}", reason));
+        }
+        if let Some(window) = self.active_maintenance_window(now) {
+            return Some(format!("maintenance window '{}' active", window.name));
+        }
+        if let Some(event) = self.event_calendar.as_ref().and_then(|c| c.active_event(now)) {
+            return Some(format!("volatility event '{}' at {}", event.name, event.time));
+        }
+        None
+    }
+    
+    pub fn is_execution_allowed(&self, now: &DateTime<Utc>) -> bool {
+        self.execution_block_reason(now).is_none()
+    }
+    
+    pub fn status(&self) -> ControlStatus {
+        let now = Utc::now();
+        let slo_breach = self.slo_breach.lock().unwrap().clone();
+        ControlStatus {
+            scanning_paused: self.is_scanning_paused(),
+            execution_paused: self.execution_paused.load(Ordering::Relaxed),
+            slo_breach,
+            active_maintenance_window: self.active_maintenance_window(&now).map(|w| w.name.clone()),
+            active_calendar_event: self.event_calendar.as_ref()
+                .and_then(|c| c.active_event(&now))
+                .map(|e| e.name.clone()),
+            execution_allowed: self.is_execution_allowed(&now),
+        }
+    }
+    
+    fn set_paused(&self, scope: PauseScope, paused: bool) {
+        if matches!(scope, PauseScope::Scanning | PauseScope::All) {
+            self.scanning_paused.store(paused, Ordering::Relaxed);
+        }
+        if matches!(scope, PauseScope::Execution | PauseScope::All) {
+            self.execution_paused.store(paused, Ordering::Relaxed);
+        }
+    }
+}
+
+impl std::str::FromStr for PauseScope {
+    type Err = anyhow::Error;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "scanning" => Ok(PauseScope::Scanning),
+            "execution" => Ok(PauseScope::Execution),
+            "all" => Ok(PauseScope::All),
+            other => Err(anyhow::anyhow!("Unknown pause scope: {}", other)),
+        }
+    }
+}
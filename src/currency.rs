@@ -0,0 +1,189 @@
+//! Conversion of the bot's USDT amounts into the home currency reports,
+//! alerts and logs are shown in.
+//!
+//! Positions, fees and PnL are all accounted in USDT. USD counts USDT at
+//! par; any other home currency is converted at the rate of its FX pair on
+//! the venues (EURUSDT or USDTTRY), read from the scanned prices. Until a
+//! rate has been seen, amounts are shown in USDT rather than guessed.
+
+use crate::arbitrage::fiat::FxRate;
+use crate::exchanges::snapshot::PriceLookup;
+use crate::exchanges::PriceMap;
+use crate::precision::format_usd;
+use crate::warm_start::CachedPrice;
+use chrono::{DateTime, Utc};
+use rust_decimal::prelude::FromPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+/// Home currency when `monitoring.home_currency` is unset.
+pub const DEFAULT_HOME_CURRENCY: &str = "USDT";
+
+/// Currencies shown with a dollar sign, converted at par.
+const DOLLARS: [&str; 2] = ["USDT", "USD"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Rate {
+    /// USDT one unit of the home currency is worth
+    usdt_per_unit: Decimal,
+    observed_at: DateTime<Utc>,
+}
+
+/// Converts USDT amounts into the home currency at the latest FX rate.
+/// Shared by the engine, which feeds it prices on every scan, and by the
+/// alerts and daily summaries that print money.
+#[derive(Debug)]
+pub struct CurrencyConverter {
+    home: String,
+    rate: RwLock<Option<Rate>>,
+}
+
+impl Default for CurrencyConverter {
+    fn default() -> Self {
+        Self::new(DEFAULT_HOME_CURRENCY)
+    }
+}
+
+impl CurrencyConverter {
+    pub fn new(home: &str) -> Self {
+        Self { home: home.to_ascii_uppercase(), rate: RwLock::new(None) }
+    }
+
+    pub fn home(&self) -> &str {
+        &self.home
+    }
+
+    fn is_dollar(&self) -> bool {
+        DOLLARS.contains(&self.home.as_str())
+    }
+
+    /// Takes the home currency's FX rate from `prices` when they list its
+    /// pair. Other prices leave the last rate in place.
+    pub fn observe(&self, prices: &impl PriceLookup) {
+        if self.is_dollar() {
+            return;
+        }
+        if let Some(fx) = FxRate::lookup(prices, &self.home) {
+            self.set_rate(fx.usdt_per_unit(), Utc::now());
+        }
+    }
+
+    /// Takes the rate from the price cache saved for warm starts, so
+    /// reports run while the bot is stopped convert at its last rate.
+    pub fn observe_cached(&self, prices: &[CachedPrice]) {
+        if self.is_dollar() {
+            return;
+        }
+        let mut latest: PriceMap = PriceMap::new();
+        let mut observed_at = HashMap::new();
+        for price in prices {
+            if observed_at.get(&price.symbol).is_none_or(|at| *at < price.updated_at) {
+                latest.insert(price.symbol.clone(), price.price);
+                observed_at.insert(price.symbol.clone(), price.updated_at);
+            }
+        }
+        if let Some(fx) = FxRate::lookup(&latest, &self.home) {
+            self.set_rate(fx.usdt_per_unit(), observed_at[&fx.symbol]);
+        }
+    }
+
+    pub fn set_rate(&self, usdt_per_unit: Decimal, observed_at: DateTime<Utc>) {
+        if usdt_per_unit > Decimal::ZERO {
+            *self.rate.write().unwrap() = Some(Rate { usdt_per_unit, observed_at });
+        }
+    }
+
+    /// When the rate in use was read, for currencies that need one.
+    pub fn rate_observed_at(&self) -> Option<DateTime<Utc>> {
+        self.rate.read().unwrap().map(|rate| rate.observed_at)
+    }
+
+    /// Currency `format` shows amounts in: the home currency, or USDT
+    /// while its rate is unknown.
+    pub fn unit(&self) -> &str {
+        if self.is_dollar() || self.rate.read().unwrap().is_some() { &self.home } else { "USDT" }
+    }
+
+    /// `usdt` in the home currency; `None` while its rate is unknown.
+    pub fn convert(&self, usdt: Decimal) -> Option<Decimal> {
+        if self.is_dollar() {
+            return Some(usdt);
+        }
+        self.rate.read().unwrap().map(|rate| usdt / rate.usdt_per_unit)
+    }
+
+    /// `usdt` in the home currency to two places: `$12.35` for dollars,
+    /// `11.40 EUR` otherwise, or `12.35 USDT` while the rate is unknown.
+    pub fn format(&self, usdt: Decimal) -> String {
+        if self.is_dollar() {
+            return format!("${}", format_usd(usdt));
+        }
+        match self.convert(usdt) {
+            Some(amount) => format!("{} {}", format_usd(amount), self.home),
+            None => format!("{} USDT", format_usd(usdt)),
+        }
+    }
+
+    /// `format` for the `f64` amounts of simulations.
+    pub fn format_f64(&self, usdt: f64) -> String {
+        self.format(Decimal::from_f64(usdt).unwrap_or_default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+
+    #[test]
+    fn test_dollars_convert_at_par() {
+        for home in ["USDT", "usd"] {
+            let converter = CurrencyConverter::new(home);
+            assert_eq!(converter.convert(dec("12.346")), Some(dec("12.346")));
+            assert_eq!(converter.format(dec("12.346")), "$12.35");
+        }
+    }
+
+    #[test]
+    fn test_converts_at_the_observed_fx_rate() {
+        let converter = CurrencyConverter::new("EUR");
+        assert_eq!(converter.convert(Decimal::from(108)), None);
+        assert_eq!(converter.format(Decimal::from(108)), "108.00 USDT");
+        assert_eq!(converter.unit(), "USDT");
+
+        converter.observe(&PriceMap::from([("EURUSDT".to_string(), dec("1.08"))]));
+        assert_eq!(converter.convert(Decimal::from(108)), Some(Decimal::from(100)));
+        assert_eq!(converter.format(Decimal::from(108)), "100.00 EUR");
+        assert!(converter.rate_observed_at().is_some());
+        assert_eq!(converter.unit(), "EUR");
+
+        // Prices without the pair keep the last rate
+        converter.observe(&PriceMap::new());
+        assert_eq!(converter.format(dec("-54")), "-50.00 EUR");
+
+        // USDT/TRY lists lira per USDT
+        let lira = CurrencyConverter::new("TRY");
+        lira.observe(&PriceMap::from([("USDTTRY".to_string(), Decimal::from(32))]));
+        assert_eq!(lira.format(Decimal::from(10)), "320.00 TRY");
+    }
+
+    #[test]
+    fn test_reports_use_the_cached_rate() {
+        let at = |minute: i64| "2024-03-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap() + chrono::Duration::minutes(minute);
+        let cached = |symbol: &str, price: &str, minute: i64| CachedPrice {
+            exchange: "Binance".to_string(),
+            symbol: symbol.to_string(),
+            price: dec(price),
+            exchange_time: None,
+            updated_at: at(minute),
+        };
+        let converter = CurrencyConverter::new("EUR");
+        converter.observe_cached(&[cached("EURUSDT", "1.08", 5), cached("EURUSDT", "1.2", 1), cached("BTCUSDT", "64000", 9)]);
+        assert_eq!(converter.format(Decimal::from(108)), "100.00 EUR");
+        assert_eq!(converter.rate_observed_at(), Some(at(5)));
+    }
+}
@@ -0,0 +1,301 @@
+//! Host readiness diagnostics behind `diag`.
+//!
+//! For each enabled exchange the local clock's offset is estimated from
+//! the server time request with the shortest round trip, the round trips
+//! of those requests give the REST latency percentiles, and the exchange's
+//! public trade stream is listened to for a while to measure how long
+//! trades take to arrive after the exchange stamped them, corrected for
+//! the clock offset. Each measurement is compared with the limits in
+//! `DiagnosticsConfig`.
+
+use crate::config::{Config, DiagnosticsConfig};
+use crate::exchanges::binance::BinanceClient;
+use crate::exchanges::bybit::BybitClient;
+use crate::exchanges::{ExchangeClient, ExchangeError};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LatencyStats {
+    pub samples: usize,
+    pub p50_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl LatencyStats {
+    /// Nearest-rank percentiles of `samples`; `None` when there are none.
+    pub fn from_samples(samples: &[f64]) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(f64::total_cmp);
+        let percentile = |q: f64| sorted[((q * sorted.len() as f64).ceil() as usize).clamp(1, sorted.len()) - 1];
+        Some(Self {
+            samples: sorted.len(),
+            p50_ms: percentile(0.5),
+            p95_ms: percentile(0.95),
+            p99_ms: percentile(0.99),
+            max_ms: sorted[sorted.len() - 1],
+        })
+    }
+}
+
+/// One server time request: when it went out and came back by the local
+/// clock, and what the exchange's clock said.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimeSample {
+    pub sent: DateTime<Utc>,
+    pub server: DateTime<Utc>,
+    pub received: DateTime<Utc>,
+}
+
+impl TimeSample {
+    pub fn round_trip_ms(&self) -> f64 {
+        (self.received - self.sent).num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0
+    }
+
+    /// How far the exchange's clock is ahead of the local one, assuming
+    /// the server read its clock halfway through the round trip.
+    pub fn offset_ms(&self) -> f64 {
+        (self.server - self.sent).num_microseconds().unwrap_or(i64::MAX) as f64 / 1000.0 - self.round_trip_ms() / 2.0
+    }
+}
+
+/// Offset of the sample with the shortest round trip, the one least
+/// distorted by network delay.
+pub fn clock_offset_ms(samples: &[TimeSample]) -> Option<f64> {
+    samples.iter()
+        .min_by(|a, b| a.round_trip_ms().total_cmp(&b.round_trip_ms()))
+        .map(TimeSample::offset_ms)
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeDiagnosis {
+    pub exchange: String,
+    /// Exchange clock minus local clock
+    pub clock_offset_ms: Option<f64>,
+    pub rest: Option<LatencyStats>,
+    /// `None` when the stream was not measured or delivered nothing
+    pub stream: Option<LatencyStats>,
+    /// Requests and connections that failed along the way
+    pub errors: Vec<String>,
+    /// Limits the host does not meet
+    pub problems: Vec<String>,
+}
+
+impl ExchangeDiagnosis {
+    pub fn ready(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    fn assess(&mut self, config: &DiagnosticsConfig) {
+        match self.clock_offset_ms {
+            None => self.problems.push("server time could not be fetched".to_string()),
+            Some(offset) if offset.abs() > config.max_clock_offset_ms as f64 => self.problems.push(format!(
+                "local clock is {:.0} ms off the exchange's (limit {} ms); sync it with NTP", offset, config.max_clock_offset_ms
+            )),
+            Some(_) => {}
+        }
+        if let Some(rest) = self.rest.filter(|rest| rest.p95_ms > config.max_rest_p95_ms as f64) {
+            self.problems.push(format!("REST p95 round trip is {:.0} ms (limit {} ms)", rest.p95_ms, config.max_rest_p95_ms));
+        }
+        match self.stream {
+            None if config.stream_seconds > 0 => self.problems.push(format!(
+                "no {} trades arrived on the stream within {} s", config.stream_symbol, config.stream_seconds
+            )),
+            Some(stream) if stream.p95_ms > config.max_stream_lag_p95_ms as f64 => self.problems.push(format!(
+                "stream p95 lag is {:.0} ms (limit {} ms)", stream.p95_ms, config.max_stream_lag_p95_ms
+            )),
+            _ => {}
+        }
+    }
+}
+
+/// Reads the exchange's timestamp off a stream message.
+type EventTime = fn(&Value) -> Option<i64>;
+
+/// Event time of a Binance trade message, raw or from a combined stream.
+pub(crate) fn binance_event_time(message: &Value) -> Option<i64> {
+    message.get("data").unwrap_or(message).get("E")?.as_i64()
+}
+
+/// Send time of a Bybit topic message; replies to operations have none.
+pub(crate) fn bybit_event_time(message: &Value) -> Option<i64> {
+    message.get("topic")?;
+    message.get("ts")?.as_i64()
+}
+
+async fn sample_server_time(client: &ExchangeClient) -> Result<TimeSample> {
+    let sent = Utc::now();
+    let started = Instant::now();
+    let server = client.get_server_time().await?;
+    let received = sent + chrono::Duration::from_std(started.elapsed())?;
+    Ok(TimeSample { sent, server, received })
+}
+
+/// Lag of every message on the stream at `url` over `duration`, in
+/// milliseconds of the exchange's clock.
+async fn stream_lags(
+    url: &str,
+    subscribe: Option<Value>,
+    event_time: EventTime,
+    duration: Duration,
+    clock_offset_ms: f64,
+) -> Result<Vec<f64>> {
+    let (mut ws, _) = connect_async(url).await
+        .map_err(|e| ExchangeError::NetworkError(format!("Stream connect to {} failed: {}", url, e)))?;
+    if let Some(request) = subscribe {
+        ws.send(Message::Text(request.to_string())).await?;
+    }
+
+    let mut lags = Vec::new();
+    let deadline = tokio::time::sleep(duration);
+    tokio::pin!(deadline);
+    loop {
+        let text = tokio::select! {
+            _ = &mut deadline => break,
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(ExchangeError::NetworkError(format!("Stream error: {}", e)).into()),
+                None => break,
+            },
+        };
+        let arrived_ms = Utc::now().timestamp_micros() as f64 / 1000.0;
+        if let Some(sent_ms) = serde_json::from_str::<Value>(&text).ok().as_ref().and_then(event_time) {
+            lags.push(arrived_ms + clock_offset_ms - sent_ms as f64);
+        }
+    }
+    let _ = ws.close(None).await;
+    Ok(lags)
+}
+
+/// Measures `client` and its trade stream under `stream_url`, the
+/// `order_books` stream URL of the exchange.
+pub async fn diagnose_exchange(client: &ExchangeClient, stream_url: &str, config: &DiagnosticsConfig) -> ExchangeDiagnosis {
+    let mut diagnosis = ExchangeDiagnosis {
+        exchange: client.name().to_string(),
+        clock_offset_ms: None,
+        rest: None,
+        stream: None,
+        errors: Vec::new(),
+        problems: Vec::new(),
+    };
+
+    let mut samples = Vec::new();
+    for _ in 0..config.rest_samples {
+        match sample_server_time(client).await {
+            Ok(sample) => samples.push(sample),
+            Err(e) => diagnosis.errors.push(format!("{:#}", e)),
+        }
+    }
+    diagnosis.clock_offset_ms = clock_offset_ms(&samples);
+    diagnosis.rest = LatencyStats::from_samples(&samples.iter().map(TimeSample::round_trip_ms).collect::<Vec<_>>());
+
+    let base = stream_url.trim_end_matches('/');
+    let stream: Option<(String, Option<Value>, EventTime)> = match client {
+        ExchangeClient::Binance(_) => Some((
+            format!("{}/ws/{}@trade", base, config.stream_symbol.to_lowercase()),
+            None,
+            binance_event_time,
+        )),
+        ExchangeClient::Bybit(_) => Some((
+            base.to_string(),
+            Some(json!({ "op": "subscribe", "args": [format!("publicTrade.{}", config.stream_symbol)] })),
+            bybit_event_time,
+        )),
+        // Nothing streams from the in-memory engine
+        ExchangeClient::Simulated(_) => None,
+    };
+    if let Some((url, subscribe, event_time)) = stream.filter(|_| config.stream_seconds > 0) {
+        let duration = Duration::from_secs(config.stream_seconds);
+        match stream_lags(&url, subscribe, event_time, duration, diagnosis.clock_offset_ms.unwrap_or_default()).await {
+            Ok(lags) => diagnosis.stream = LatencyStats::from_samples(&lags),
+            Err(e) => diagnosis.errors.push(format!("{:#}", e)),
+        }
+    }
+
+    diagnosis.assess(config);
+    diagnosis
+}
+
+/// Diagnoses every enabled exchange at the same time.
+pub async fn diagnose(config: &Config, diagnostics: &DiagnosticsConfig) -> Result<Vec<ExchangeDiagnosis>> {
+    let mut exchanges = Vec::new();
+    if config.exchanges.binance_enabled {
+        let client = BinanceClient::with_network(&config.exchanges.binance_network)?;
+        exchanges.push((ExchangeClient::Binance(Arc::new(client)), config.order_books.binance_stream_url.as_str()));
+    }
+    if config.exchanges.bybit_enabled {
+        let client = BybitClient::with_network(&config.exchanges.bybit_network)?;
+        exchanges.push((ExchangeClient::Bybit(Arc::new(client)), config.order_books.bybit_stream_url.as_str()));
+    }
+    Ok(join_all(exchanges.iter().map(|(client, stream_url)| diagnose_exchange(client, stream_url, diagnostics))).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(1_700_000_000_000 + ms).unwrap()
+    }
+
+    #[test]
+    fn test_offset_comes_from_the_fastest_round_trip() {
+        let samples = [
+            TimeSample { sent: at(0), server: at(300), received: at(400) },
+            TimeSample { sent: at(1000), server: at(1120), received: at(1040) },
+        ];
+        assert_eq!(samples[1].round_trip_ms(), 40.0);
+        assert_eq!(clock_offset_ms(&samples), Some(100.0));
+        assert_eq!(clock_offset_ms(&[]), None);
+
+        let stats = LatencyStats::from_samples(&(1..=100).rev().map(f64::from).collect::<Vec<_>>()).unwrap();
+        assert_eq!((stats.p50_ms, stats.p95_ms, stats.p99_ms, stats.max_ms), (50.0, 95.0, 99.0, 100.0));
+        assert!(LatencyStats::from_samples(&[]).is_none());
+    }
+
+    #[test]
+    fn test_limits_decide_readiness() {
+        let config = DiagnosticsConfig::default();
+        let stats = |p95_ms| LatencyStats { samples: 20, p50_ms: 10.0, p95_ms, p99_ms: p95_ms, max_ms: p95_ms };
+        let mut diagnosis = ExchangeDiagnosis {
+            exchange: "Binance".to_string(),
+            clock_offset_ms: Some(-20.0),
+            rest: Some(stats(80.0)),
+            stream: Some(stats(120.0)),
+            errors: Vec::new(),
+            problems: Vec::new(),
+        };
+        diagnosis.assess(&config);
+        assert!(diagnosis.ready());
+
+        diagnosis.clock_offset_ms = Some(-900.0);
+        diagnosis.stream = None;
+        diagnosis.assess(&config);
+        assert_eq!(diagnosis.problems.len(), 2);
+        assert!(diagnosis.problems[0].contains("NTP"));
+    }
+
+    #[test]
+    fn test_stream_event_times() {
+        let binance: Value = serde_json::from_str(r#"{"e":"trade","E":1700000000123,"s":"BTCUSDT","T":1700000000120}"#).unwrap();
+        assert_eq!(binance_event_time(&binance), Some(1700000000123));
+        let bybit: Value = serde_json::from_str(r#"{"topic":"publicTrade.BTCUSDT","ts":1700000000456,"type":"snapshot","data":[]}"#).unwrap();
+        assert_eq!(bybit_event_time(&bybit), Some(1700000000456));
+        let reply: Value = serde_json::from_str(r#"{"success":true,"op":"subscribe","conn_id":"x"}"#).unwrap();
+        assert_eq!(bybit_event_time(&reply), None);
+    }
+}
@@ -1,82 +1,313 @@
-use super::{PriceMap, TickerPrice, OrderRequest, ExchangeError};
+use super::{ApiKeyPermissions, Balance, SubAccountTransfer, TransferReceipt, SymbolInfo, BookTicker, BookTickerMap, PriceMap, OrderRequest, OrderRef, OrderState, OpenOrder, HistoricalOrder, AccountTrade, OrderSide, TimeInForce, OrderStatus, PlacementOutcome, ExchangeError, Kline, KlineInterval, PublicTrade, check_response, find_ambiguous_order, http_client, is_ambiguous_placement};
 use anyhow::Result;
 use hmac::{Hmac, Mac};
-use reqwest::{Client, ClientBuilder};
+use log::warn;
+use super::fees::AccountFeeRates;
+use super::endpoints::{EndpointPool, EndpointHealth, BINANCE_ENDPOINTS};
+use super::budget::{RequestBudget, RequestClass};
+use super::clock::ServerClock;
+use super::errors::ErrorCounters;
+use super::heartbeat::{CallStats, ExchangeHealth};
+use super::fixtures::FixtureRecorder;
+use super::request_log::RequestLog;
+use super::ws_trading::WsTradingApi;
+use super::dns::DnsCache;
+use super::depth::{parse_levels, DepthSnapshot};
+use super::pagination::{self, collect_pages, HistoryRecord, Page, MAX_PAGES};
+use super::snapshot::{PriceSnapshot, SymbolTable};
+use crate::config::{ClientNetworkConfig, WsTradingConfig};
+use crate::secrets::SecretString;
+use reqwest::Client;
 use serde_json::Value;
+use serde::Deserialize;
 use sha2::Sha256;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::env;
+use std::ops::Range;
+use std::sync::Arc;
 use std::time::Duration;
-use url::Url;
+use tokio::sync::Semaphore;
 use chrono::{DateTime, Utc};
+use futures_util::future::join_all;
 use rust_decimal::Decimal;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Records per page of `/api/v3/allOrders` and `/api/v3/myTrades`.
+const HISTORY_PAGE_SIZE: usize = 1000;
+
+/// Where the next page of an account history endpoint starts. Binance
+/// answers at most 24 hours per time window, and a full page continues
+/// from the ID after its last record, past the end of the window.
+#[derive(Debug, Clone, PartialEq)]
+enum HistoryCursor {
+    Window(DateTime<Utc>),
+    FromId(u64),
+}
+
+/// Request weight of `/api/v3/depth`, which grows with the depth asked for.
+fn depth_weight(limit: usize) -> u32 {
+    match limit {
+        0..=100 => 5,
+        101..=500 => 25,
+        501..=1000 => 50,
+        _ => 250,
+    }
+}
+
+/// `/api/v3/ticker/price` entry borrowed from the response body.
+#[derive(Deserialize)]
+struct RawTicker<'a> {
+    #[serde(borrow)]
+    symbol: Cow<'a, str>,
+    #[serde(borrow)]
+    price: Cow<'a, str>,
+}
+
+/// `/api/v3/ticker/bookTicker` entry borrowed from the response body.
+#[derive(Deserialize)]
+struct RawBookTicker<'a> {
+    #[serde(borrow)]
+    symbol: Cow<'a, str>,
+    #[serde(rename = "bidPrice", borrow)]
+    bid_price: Cow<'a, str>,
+    #[serde(rename = "bidQty", borrow)]
+    bid_quantity: Cow<'a, str>,
+    #[serde(rename = "askPrice", borrow)]
+    ask_price: Cow<'a, str>,
+    #[serde(rename = "askQty", borrow)]
+    ask_quantity: Cow<'a, str>,
+    /// Event time, in payloads that carry one
+    #[serde(rename = "E")]
+    event_time: Option<i64>,
+}
+
 pub struct BinanceClient {
-    client: Client,
-    api_key: String,
-    secret_key: String,
-    base_url: String,
+    /// Swapped for a fresh pool by `reset_connections`
+    client: std::sync::RwLock<Client>,
+    network: ClientNetworkConfig,
+    dns: Option<Arc<DnsCache>>,
+    api_key: SecretString,
+    secret_key: SecretString,
+    endpoints: EndpointPool,
     last_request_time: std::sync::Arc<std::sync::Mutex<DateTime<Utc>>>,
-    rate_limiter: std::sync::Arc<tokio::sync::Semaphore>,
+    budget: Arc<RequestBudget>,
+    errors: Arc<ErrorCounters>,
+    calls: Arc<CallStats>,
+    /// Offset added to the timestamp of signed requests
+    clock: ServerClock,
+    /// Websocket API for orders and cancels, when enabled
+    ws_trading: Option<Arc<WsTradingApi>>,
 }
 
 impl BinanceClient {
     pub fn new() -> Result<Self> {
+        Self::with_network(&ClientNetworkConfig::default())
+    }
+    
+    /// Creates the client with custom proxy, DNS and TLS settings.
+    pub fn with_network(network: &ClientNetworkConfig) -> Result<Self> {
         let api_key = env::var("BINANCE_API_KEY")
             .map_err(|_| ExchangeError::MissingCredentials("BINANCE_API_KEY not found".to_string()))?;
         let secret_key = env::var("BINANCE_SECRET_KEY")
             .map_err(|_| ExchangeError::MissingCredentials("BINANCE_SECRET_KEY not found".to_string()))?;
         
-        let client = ClientBuilder::new()
-            .timeout(Duration::from_secs(10))
-            .connect_timeout(Duration::from_secs(5))
-            .pool_idle_timeout(Duration::from_secs(90))
-            .pool_max_idle_per_host(10)
-            .build()
-            .map_err(|e| ExchangeError::NetworkError(format!("Failed to create client: {}", e)))?;
+        Self::with_credentials(api_key, secret_key, network)
+    }
+    
+    /// Creates the client with explicit credentials instead of reading them
+    /// from the environment. Market data endpoints work with empty keys.
+    pub fn with_credentials(
+        api_key: impl Into<String>,
+        secret_key: impl Into<String>,
+        network: &ClientNetworkConfig,
+    ) -> Result<Self> {
+        let dns = (network.dns_cache_seconds > 0).then(|| Arc::new(DnsCache::new("Binance", network)));
+        let client = http_client(network, dns.clone())?;
+        let calls = Arc::new(CallStats::new("Binance"));
+        let errors = Arc::new(ErrorCounters::new("Binance").with_call_stats(calls.clone()));
         
         Ok(Self {
-            client,
-            api_key,
-            secret_key,
-            base_url: "https://api.binance.com".to_string(),
+            client: std::sync::RwLock::new(client),
+            network: network.clone(),
+            dns,
+            api_key: SecretString::new(api_key),
+            secret_key: SecretString::new(secret_key),
+            endpoints: EndpointPool::new("Binance", network.endpoint_urls(BINANCE_ENDPOINTS), network.failover_threshold)
+                .with_fastest_orders(network.prefer_fastest_endpoint)
+                .with_max_in_flight(network.max_requests_per_endpoint)
+                .with_error_counters(errors.clone())
+                .with_call_stats(calls.clone()),
             last_request_time: std::sync::Arc::new(std::sync::Mutex::new(Utc::now())),
-            rate_limiter: std::sync::Arc::new(tokio::sync::Semaphore::new(10)), // 10 requests per batch
+            budget: Arc::new(RequestBudget::new("Binance", network)),
+            errors,
+            calls,
+            clock: ServerClock::new(),
+            ws_trading: None,
         })
     }
     
+    /// Also holds market data requests to `slots`, shared with the other
+    /// exchanges' clients.
+    pub fn with_shared_concurrency(mut self, slots: Arc<Semaphore>) -> Self {
+        self.budget = Arc::new(RequestBudget::new("Binance", &self.network).with_shared_slots(slots));
+        self
+    }
+    
+    /// Places and cancels orders over the websocket API at `config.binance_url`,
+    /// falling back to REST while it is disconnected. Connects in the
+    /// background, so it must be called within a tokio runtime.
+    pub fn with_ws_trading(mut self, config: &WsTradingConfig) -> Self {
+        let api = Arc::new(WsTradingApi::new("Binance", &config.binance_url, "id", config));
+        api.clone().start();
+        self.ws_trading = Some(api);
+        self
+    }
+    
+    /// Whether orders currently go over the websocket API.
+    pub fn ws_trading_connected(&self) -> bool {
+        self.ws_trading.as_ref().is_some_and(|api| api.is_connected())
+    }
+    
+    /// Writes every request this client sends to `log`.
+    pub fn with_request_log(mut self, log: Arc<RequestLog>) -> Self {
+        self.endpoints = self.endpoints.with_request_log(log);
+        self
+    }
+    
+    /// Records every answer this client receives, body included, in `fixtures`.
+    pub fn with_fixture_recorder(mut self, fixtures: Arc<FixtureRecorder>) -> Self {
+        self.endpoints = self.endpoints.with_fixture_recorder(fixtures);
+        self
+    }
+    
+    fn http(&self) -> Client {
+        self.client.read().unwrap().clone()
+    }
+    
+    /// Replaces the HTTP client, dropping every pooled connection. Requests
+    /// already in flight finish on the old pool.
+    pub fn reset_connections(&self) -> Result<()> {
+        let client = http_client(&self.network, self.dns.clone())?;
+        *self.client.write().unwrap() = client;
+        Ok(())
+    }
+    
+    /// Lightest request there is, used to keep pooled connections open.
+    /// Goes to the host orders go to, whose connections matter most.
+    pub async fn ping(&self) -> Result<()> {
+        let response = self.endpoints.send_once(|base| self.http().get(format!("{}/api/v3/ping", base))
+            .timeout(Duration::from_secs(5)))
+            .await
+            .map_err(|e| ExchangeError::NetworkError(format!("Ping failed: {}", e)))?;
+        let response = check_response(&self.errors, "/api/v3/ping", response).await?;
+        // Reading the body hands the connection back to the pool
+        response.bytes().await
+            .map_err(|e| ExchangeError::NetworkError(format!("Ping failed: {}", e.without_url())))?;
+        Ok(())
+    }
+    
+    /// Pings every configured host at once to update their latencies.
+    /// Returns how many answered.
+    pub async fn probe_endpoints(&self) -> usize {
+        self.endpoints.probe(|base| self.http().get(format!("{}/api/v3/ping", base)).timeout(Duration::from_secs(5))).await
+    }
+    
+    /// The exchange's clock, for measuring how far the local one is off.
+    pub async fn get_server_time(&self) -> Result<DateTime<Utc>> {
+        let response = self.endpoints.send(|base| self.http().get(format!("{}/api/v3/time", base))
+            .timeout(Duration::from_secs(5)))
+            .await
+            .map_err(|e| ExchangeError::NetworkError(format!("Server time request failed: {}", e)))?;
+        let response = check_response(&self.errors, "/api/v3/time", response).await?;
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse server time: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/time", e))?;
+        data.get("serverTime")
+            .and_then(|v| v.as_i64())
+            .and_then(DateTime::from_timestamp_millis)
+            .ok_or_else(|| ExchangeError::ParseError("Server time response has no serverTime".to_string()).into())
+    }
+    
+    /// Measures how far the exchange's clock is off the local one and adds
+    /// it to the timestamps of signed requests. Returns the offset in
+    /// milliseconds.
+    pub async fn sync_clock(&self) -> Result<i64> {
+        let sent = Utc::now();
+        let server = self.get_server_time().await?;
+        Ok(self.clock.sync(sent, server, Utc::now()))
+    }
+    
+    /// Lookups and latencies of the exchange's hosts, when `dns_cache_seconds` is set.
+    pub fn dns_cache(&self) -> Option<Arc<DnsCache>> {
+        self.dns.clone()
+    }
+    
+    /// Request weight spent by market data and trading over the last minute.
+    pub fn request_budget(&self) -> Arc<RequestBudget> {
+        self.budget.clone()
+    }
+    
+    /// Failed requests by endpoint and kind.
+    pub fn error_counters(&self) -> Arc<ErrorCounters> {
+        self.errors.clone()
+    }
+    
+    /// Last success, error rate and latency of this client's requests
+    /// over the last five minutes, and whether its trade websocket is up.
+    pub fn health(&self) -> ExchangeHealth {
+        self.calls.health(self.ws_trading.as_ref().map(|api| api.is_connected()))
+    }
+    
+    /// Health of the configured base URLs, active host first in use.
+    pub fn endpoint_health(&self) -> Vec<EndpointHealth> {
+        self.endpoints.health()
+    }
+    
     pub async fn get_ticker_prices(&self) -> Result<PriceMap> {
-        let _permit = self.rate_limiter.acquire().await
-            .map_err(|e| ExchangeError::RateLimitError(format!("Rate limit acquisition failed: {}", e)))?;
+        let mut price_map = HashMap::new();
+        self.fetch_ticker_prices(|symbol, price| {
+            price_map.insert(symbol.to_string(), price);
+        }).await?;
+        Ok(price_map)
+    }
+    
+    /// Same tickers as `get_ticker_prices`, written straight into a
+    /// snapshot indexed by the shared symbol table.
+    pub async fn get_price_snapshot(&self, symbols: &Arc<SymbolTable>) -> Result<PriceSnapshot> {
+        let mut snapshot = PriceSnapshot::new(symbols.clone());
+        self.fetch_ticker_prices(|symbol, price| snapshot.insert(symbol, price)).await?;
+        snapshot.set_received_at(Utc::now());
+        Ok(snapshot)
+    }
+    
+    /// Calls `on_price` for every positive ticker price, parsing the body in
+    /// place rather than into owned strings.
+    async fn fetch_ticker_prices(&self, mut on_price: impl FnMut(&str, Decimal)) -> Result<()> {
+        let _permit = self.budget.acquire(RequestClass::MarketData, 4).await?;
         
         self.enforce_rate_limit().await;
         
-        let url = format!("{}/api/v3/ticker/price", self.base_url);
-        let response = self.client.get(&url)
-            .timeout(Duration::from_secs(5))
-            .send()
+        let response = self.endpoints.send(|base| self.http().get(format!("{}/api/v3/ticker/price", base))
+            .timeout(Duration::from_secs(5)))
             .await
             .map_err(|e| ExchangeError::NetworkError(format!("Request failed: {}", e)))?;
         
-        if !response.status().is_success() {
-            return Err(ExchangeError::ApiError(format!(
-                "HTTP {}: {}", 
-                response.status(), 
-                response.text().await.unwrap_or_default()
-            )).into());
-        }
-        
-        let tickers: Vec<TickerPrice> = response.json().await
-            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse response: {}", e)))?;
+        let response = check_response(&self.errors, "/api/v3/ticker/price", response).await?;
+        let body = response.bytes().await
+            .map_err(|e| ExchangeError::NetworkError(format!("Failed to read response: {}", e.without_url())))?;
         
-        let mut price_map = HashMap::new();
+        let tickers: Vec<RawTicker> = serde_json::from_slice(&body)
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse response: {}", e)))
+            .inspect_err(|e| self.errors.record("/api/v3/ticker/price", e))?;
         let now = Utc::now();
         
-        for ticker in tickers {
-            if ticker.price > Decimal::ZERO {
-                price_map.insert(ticker.symbol, ticker.price);
+        for ticker in &tickers {
+            if let Ok(price) = ticker.price.parse::<Decimal>() {
+                if price > Decimal::ZERO {
+                    on_price(&ticker.symbol, price);
+                }
             }
         }
         
@@ -85,118 +316,791 @@ impl BinanceClient {
             *last_time = now;
         }
         
-        Ok(price_map)
+        Ok(())
     }
     
-    pub async fn get_account_info(&self) -> Result<Value> {
-        let _permit = self.rate_limiter.acquire().await
-            .map_err(|e| ExchangeError::RateLimitError(format!("Rate limit acquisition failed: {}", e)))?;
+    /// Spot symbols from exchangeInfo with their trading status and price
+    /// tick size. Only `TRADING` symbols with spot trading allowed count as
+    /// tradable.
+    pub async fn get_symbol_info(&self) -> Result<Vec<SymbolInfo>> {
+        let _permit = self.budget.acquire(RequestClass::MarketData, 20).await?;
         
         self.enforce_rate_limit().await;
         
-        let endpoint = "/api/v3/account";
-        let timestamp = chrono::Utc::now().timestamp_millis();
-        let query_string = format!("timestamp={}", timestamp);
+        let response = self.endpoints.send(|base| self.http().get(format!("{}/api/v3/exchangeInfo?permissions=SPOT", base))
+            .timeout(Duration::from_secs(15)))
+            .await
+            .map_err(|e| ExchangeError::NetworkError(format!("Request failed: {}", e)))?;
         
-        let signature = self.generate_signature(&query_string)?;
-        let url = format!("{}{}?{}&signature={}", self.base_url, endpoint, query_string, signature);
+        let response = check_response(&self.errors, "/api/v3/exchangeInfo", response).await?;
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse exchangeInfo: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/exchangeInfo", e))?;
+        
+        let symbols = data.get("symbols")
+            .and_then(|s| s.as_array())
+            .ok_or_else(|| ExchangeError::ParseError("exchangeInfo response has no symbols".to_string()))?;
         
-        let response = self.client
-            .get(&url)
-            .header("X-MBX-APIKEY", &self.api_key)
-            .timeout(Duration::from_secs(10))
-            .send()
+        Ok(symbols.iter()
+            .filter_map(|s| {
+                let symbol = s.get("symbol").and_then(|v| v.as_str())?;
+                let filter = |filter_type: &str, field: &str| s.get("filters")
+                    .and_then(|f| f.as_array())
+                    .and_then(|filters| filters.iter().find(|f| f.get("filterType").and_then(|v| v.as_str()) == Some(filter_type)))
+                    .and_then(|f| f.get(field).and_then(|v| v.as_str()))
+                    .and_then(|t| t.parse::<Decimal>().ok());
+                Some(SymbolInfo {
+                    symbol: symbol.to_string(),
+                    tradable: s.get("status").and_then(|v| v.as_str()) == Some("TRADING")
+                        && s.get("isSpotTradingAllowed").and_then(|v| v.as_bool()).unwrap_or(false),
+                    tick_size: filter("PRICE_FILTER", "tickSize"),
+                    step_size: filter("LOT_SIZE", "stepSize"),
+                })
+            })
+            .collect())
+    }
+    
+    pub async fn get_book_ticker(&self, symbol: &str) -> Result<BookTicker> {
+        let _permit = self.budget.acquire(RequestClass::MarketData, 2).await?;
+        
+        self.enforce_rate_limit().await;
+        
+        let response = self.endpoints.send(|base| self.http().get(format!("{}/api/v3/ticker/bookTicker?symbol={}", base, urlencoding::encode(symbol)))
+            .timeout(Duration::from_secs(5)))
             .await
-            .map_err(|e| ExchangeError::NetworkError(format!("Account info request failed: {}", e)))?;
+            .map_err(|e| ExchangeError::NetworkError(format!("Book ticker request failed: {}", e)))?;
+        let response = check_response(&self.errors, "/api/v3/ticker/bookTicker", response).await?;
         
-        if !response.status().is_success() {
-            return Err(ExchangeError::ApiError(format!(
-                "HTTP {}: {}", 
-                response.status(), 
-                response.text().await.unwrap_or_default()
-            )).into());
-        }
-            
-        Ok(response.json().await
-            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse account info: {}", e)))?)
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse book ticker: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/ticker/bookTicker", e))?;
+        let decimal = |key: &str| -> Result<Decimal> {
+            data.get(key)
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse::<Decimal>().ok())
+                .ok_or_else(|| ExchangeError::ParseError(format!("Invalid book ticker field {}", key)).into())
+        };
+        
+        Ok(BookTicker {
+            symbol: symbol.to_string(),
+            bid_price: decimal("bidPrice")?,
+            bid_quantity: decimal("bidQty")?,
+            ask_price: decimal("askPrice")?,
+            ask_quantity: decimal("askQty")?,
+            timestamp: data.get("E").and_then(|v| v.as_i64()).and_then(DateTime::from_timestamp_millis),
+        })
     }
     
-    pub async fn place_order(&self, order: &OrderRequest) -> Result<Value> {
-        let _permit = self.rate_limiter.acquire().await
-            .map_err(|e| ExchangeError::RateLimitError(format!("Rate limit acquisition failed: {}", e)))?;
+    /// Best bid/offer of every symbol. Symbols without a two-sided quote
+    /// are left out.
+    pub async fn get_book_tickers(&self) -> Result<BookTickerMap> {
+        let _permit = self.budget.acquire(RequestClass::MarketData, 4).await?;
+        
+        self.enforce_rate_limit().await;
+        
+        let response = self.endpoints.send(|base| self.http().get(format!("{}/api/v3/ticker/bookTicker", base))
+            .timeout(Duration::from_secs(5)))
+            .await
+            .map_err(|e| ExchangeError::NetworkError(format!("Book ticker request failed: {}", e)))?;
+        let response = check_response(&self.errors, "/api/v3/ticker/bookTicker", response).await?;
+        let body = response.bytes().await
+            .map_err(|e| ExchangeError::NetworkError(format!("Failed to read response: {}", e.without_url())))?;
+        
+        let tickers: Vec<RawBookTicker> = serde_json::from_slice(&body)
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse book tickers: {}", e)))
+            .inspect_err(|e| self.errors.record("/api/v3/ticker/bookTicker", e))?;
+        
+        Ok(tickers.iter()
+            .filter_map(|t| {
+                let ticker = BookTicker::parse(&t.symbol, &t.bid_price, &t.bid_quantity, &t.ask_price, &t.ask_quantity)?
+                    .with_timestamp(t.event_time.and_then(DateTime::from_timestamp_millis));
+                Some((ticker.symbol.clone(), ticker))
+            })
+            .collect())
+    }
+    
+    /// Most recent aggregate trades, oldest first; `limit` is at most 1000.
+    pub async fn get_recent_trades(&self, symbol: &str, limit: usize) -> Result<Vec<PublicTrade>> {
+        let _permit = self.budget.acquire(RequestClass::MarketData, 2).await?;
+        
+        self.enforce_rate_limit().await;
+        
+        let response = self.endpoints.send(|base| self.http()
+            .get(format!("{}/api/v3/aggTrades?symbol={}&limit={}", base, urlencoding::encode(symbol), limit.min(1000)))
+            .timeout(Duration::from_secs(5)))
+            .await
+            .map_err(|e| ExchangeError::NetworkError(format!("Trades request failed: {}", e)))?;
+        let response = check_response(&self.errors, "/api/v3/aggTrades", response).await?;
+        
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse trades: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/aggTrades", e))?;
+        let rows = data.as_array()
+            .ok_or_else(|| ExchangeError::ParseError("aggTrades response is not an array".to_string()))?;
+        
+        rows.iter()
+            .map(|row| {
+                let decimal = |key: &str| row.get(key)
+                    .and_then(|v| v.as_str())
+                    .and_then(|v| v.parse::<Decimal>().ok())
+                    .ok_or_else(|| ExchangeError::ParseError(format!("Invalid trade field {}", key)));
+                Ok(PublicTrade {
+                    price: decimal("p")?,
+                    quantity: decimal("q")?,
+                    timestamp: row.get("T")
+                        .and_then(|v| v.as_i64())
+                        .and_then(DateTime::from_timestamp_millis)
+                        .ok_or_else(|| ExchangeError::ParseError("Invalid trade time".to_string()))?,
+                })
+            })
+            .collect()
+    }
+    
+    /// Depth snapshot used to seed a local order book; `limit` is one of
+    /// Binance's allowed depths (5 to 5000).
+    pub async fn get_depth_snapshot(&self, symbol: &str, limit: usize) -> Result<DepthSnapshot> {
+        let _permit = self.budget.acquire(RequestClass::MarketData, depth_weight(limit)).await?;
         
         self.enforce_rate_limit().await;
         
-        let endpoint = "/api/v3/order";
-        let timestamp = chrono::Utc::now().timestamp_millis();
+        let response = self.endpoints.send(|base| self.http()
+            .get(format!("{}/api/v3/depth?symbol={}&limit={}", base, urlencoding::encode(symbol), limit))
+            .timeout(Duration::from_secs(5)))
+            .await
+            .map_err(|e| ExchangeError::NetworkError(format!("Depth request failed: {}", e)))?;
+        let response = check_response(&self.errors, "/api/v3/depth", response).await?;
         
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse depth: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/depth", e))?;
+        Ok(DepthSnapshot {
+            last_update_id: data.get("lastUpdateId")
+                .and_then(|v| v.as_u64())
+                .ok_or_else(|| ExchangeError::ParseError("Depth response has no lastUpdateId".to_string()))?,
+            bids: parse_levels(data.get("bids"))?,
+            asks: parse_levels(data.get("asks"))?,
+        })
+    }
+    
+    /// Fetches candles for `range`, paging through the 1000-candle limit of /api/v3/klines.
+    pub async fn get_klines(
+        &self,
+        symbol: &str,
+        interval: KlineInterval,
+        range: Range<DateTime<Utc>>,
+    ) -> Result<Vec<Kline>> {
+        let mut klines = Vec::new();
+        let mut start = range.start;
+        
+        while start < range.end {
+            let _permit = self.budget.acquire(RequestClass::MarketData, 2).await?;
+            
+            self.enforce_rate_limit().await;
+            
+            let response = self.endpoints.send(|base| self.http()
+                .get(format!(
+                    "{}/api/v3/klines?symbol={}&interval={}&startTime={}&endTime={}&limit=1000",
+                    base,
+                    symbol,
+                    interval.as_binance(),
+                    start.timestamp_millis(),
+                    range.end.timestamp_millis()
+                ))
+                .timeout(Duration::from_secs(10)))
+                .await
+                .map_err(|e| ExchangeError::NetworkError(format!("Kline request failed: {}", e)))?;
+            
+            let response = check_response(&self.errors, "/api/v3/klines", response).await?;
+            
+            let rows: Vec<Vec<Value>> = response.json().await
+                .map_err(|e| ExchangeError::ParseError(format!("Failed to parse klines: {}", e.without_url())))
+                .inspect_err(|e| self.errors.record("/api/v3/klines", e))?;
+            
+            if rows.is_empty() {
+                break;
+            }
+            
+            for row in &rows {
+                klines.push(Self::parse_kline(row)?);
+            }
+            
+            match klines.last() {
+                Some(last) if last.open_time >= start => {
+                    start = last.open_time.checked_add_signed(interval.duration())
+                        .ok_or_else(|| ExchangeError::ParseError("Kline open time out of range".to_string()))?;
+                }
+                _ => break,
+            }
+            
+            if let Ok(mut last_time) = self.last_request_time.lock() {
+                *last_time = Utc::now();
+            }
+        }
+        
+        klines.retain(|k| k.open_time < range.end);
+        Ok(klines)
+    }
+    
+    fn parse_kline(row: &[Value]) -> Result<Kline> {
+        let field = |idx: usize| -> Result<Decimal> {
+            row.get(idx)
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse::<Decimal>().ok())
+                .ok_or_else(|| ExchangeError::ParseError(format!("Invalid kline field {}", idx)).into())
+        };
+        let open_time = row.first()
+            .and_then(|v| v.as_i64())
+            .and_then(DateTime::<Utc>::from_timestamp_millis)
+            .ok_or_else(|| ExchangeError::ParseError("Invalid kline open time".to_string()))?;
+        
+        Ok(Kline {
+            open_time,
+            open: field(1)?,
+            high: field(2)?,
+            low: field(3)?,
+            close: field(4)?,
+            volume: field(5)?,
+        })
+    }
+    
+    pub async fn get_account_info(&self) -> Result<Value> {
+        let endpoint = "/api/v3/account";
+        let response = self.signed_request(reqwest::Method::GET, endpoint, Vec::new(), 20, Duration::from_secs(10)).await?;
+        
+        Ok(response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse account info: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record(endpoint, e))?)
+    }
+    
+    /// Non-zero spot balances from the account endpoint.
+    pub async fn get_balances(&self) -> Result<Vec<Balance>> {
+        let account = self.get_account_info().await?;
+        let balances = account.get("balances")
+            .and_then(|b| b.as_array())
+            .ok_or_else(|| ExchangeError::ParseError("Missing balances in account info".to_string()))?;
+        
+        let decimal = |entry: &Value, key: &str| entry.get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<Decimal>().ok())
+            .unwrap_or(Decimal::ZERO);
+        
+        Ok(balances.iter()
+            .filter_map(|entry| {
+                let asset = entry.get("asset")?.as_str()?.to_string();
+                Some(Balance { asset, free: decimal(entry, "free"), locked: decimal(entry, "locked") })
+            })
+            .filter(|b| b.total() > Decimal::ZERO)
+            .collect())
+    }
+    
+    pub async fn place_order(&self, order: &OrderRequest) -> Result<Value> {
         let mut params = vec![
             ("symbol", order.symbol.clone()),
             ("side", match order.side {
                 super::OrderSide::Buy => "BUY".to_string(),
                 super::OrderSide::Sell => "SELL".to_string(),
             }),
-            ("type", match order.order_type {
-                super::OrderType::Market => "MARKET".to_string(),
-                super::OrderType::Limit => "LIMIT".to_string(),
+            ("type", match (&order.order_type, order.time_in_force) {
+                (super::OrderType::Market, _) => "MARKET".to_string(),
+                (super::OrderType::Limit, TimeInForce::PostOnly) => "LIMIT_MAKER".to_string(),
+                (super::OrderType::Limit, _) => "LIMIT".to_string(),
             }),
-            ("quantity", order.quantity.to_string()),
-            ("timestamp", timestamp.to_string()),
         ];
         
+        match (&order.order_type, order.quote_quantity) {
+            (super::OrderType::Market, Some(quote_quantity)) => params.push(("quoteOrderQty", quote_quantity.to_string())),
+            _ => params.push(("quantity", order.quantity.to_string())),
+        }
+        
         if let Some(price) = &order.price {
             params.push(("price", price.to_string()));
-            params.push(("timeInForce", "GTC".to_string()));
+            // LIMIT_MAKER orders are rejected when a time-in-force is supplied
+            if order.order_type == super::OrderType::Limit && order.time_in_force != TimeInForce::PostOnly {
+                params.push(("timeInForce", order.time_in_force.as_binance().to_string()));
+            }
+        }
+        
+        if let Some(client_order_id) = &order.client_order_id {
+            params.push(("newClientOrderId", client_order_id.clone()));
+        }
+        
+        if let Some(data) = self.ws_request("order.place", params.clone()).await? {
+            return Ok(data);
+        }
+        let response = self.signed_request(reqwest::Method::POST, "/api/v3/order", params, 1, Duration::from_secs(15)).await?;
+            
+        Ok(response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse order response: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/order", e))?)
+    }
+    
+    /// Looks up an order by the client order ID it was submitted with.
+    /// Returns `None` when Binance has no record of it (code -2013).
+    pub async fn get_order_by_client_id(&self, symbol: &str, client_order_id: &str) -> Result<Option<OrderState>> {
+        let params = vec![
+            ("symbol", symbol.to_string()),
+            ("origClientOrderId", client_order_id.to_string()),
+        ];
+        let response = match self.signed_request(reqwest::Method::GET, "/api/v3/order", params, 4, Duration::from_secs(10)).await {
+            Ok(response) => response,
+            Err(e) if e.downcast_ref::<ExchangeError>().and_then(ExchangeError::code) == Some(-2013) => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse order status: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/order", e))?;
+        Ok(Some(Self::parse_order_state(&data, client_order_id)?))
+    }
+    
+    /// Every open order on the account, across all symbols.
+    pub async fn get_open_orders(&self) -> Result<Vec<OpenOrder>> {
+        let response = self.signed_request(reqwest::Method::GET, "/api/v3/openOrders", Vec::new(), 80, Duration::from_secs(10)).await?;
+        
+        let data: Vec<Value> = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse open orders: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/openOrders", e))?;
+        data.iter()
+            .map(|order| {
+                let field = |key: &str| order.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+                Ok(OpenOrder {
+                    symbol: field("symbol").to_string(),
+                    state: Self::parse_order_state(order, field("clientOrderId"))?,
+                })
+            })
+            .collect()
+    }
+    
+    /// Every order of `symbol` created in `range`, oldest first, from `/api/v3/allOrders`.
+    pub async fn get_order_history(&self, symbol: &str, range: Range<DateTime<Utc>>) -> Result<Vec<HistoricalOrder>> {
+        self.history("/api/v3/allOrders", "orderId", symbol, range, |order| {
+            let field = |key: &str| order.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+            Ok(HistoricalOrder {
+                symbol: field("symbol").to_string(),
+                side: Self::parse_side(field("side"))?,
+                state: Self::parse_order_state(order, field("clientOrderId"))?,
+                created_at: Self::parse_time(order.get("time"))?,
+            })
+        }).await
+    }
+    
+    /// Every fill of the account's `symbol` orders in `range`, oldest first,
+    /// from `/api/v3/myTrades`.
+    pub async fn get_my_trades(&self, symbol: &str, range: Range<DateTime<Utc>>) -> Result<Vec<AccountTrade>> {
+        self.history("/api/v3/myTrades", "fromId", symbol, range, |trade| {
+            let field = |key: &str| trade.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+            let decimal = |key: &str| field(key).parse::<Decimal>()
+                .map_err(|_| ExchangeError::ParseError(format!("Invalid trade field {}", key)));
+            let id = |key: &str| trade.get(key).filter(|v| v.is_u64()).map(|v| v.to_string())
+                .ok_or_else(|| ExchangeError::ParseError(format!("Invalid trade field {}", key)));
+            Ok(AccountTrade {
+                symbol: field("symbol").to_string(),
+                trade_id: id("id")?,
+                order_id: id("orderId")?,
+                side: if trade.get("isBuyer").and_then(|v| v.as_bool()).unwrap_or(false) { OrderSide::Buy } else { OrderSide::Sell },
+                price: decimal("price")?,
+                quantity: decimal("qty")?,
+                fee: decimal("commission")?,
+                fee_asset: field("commissionAsset").to_string(),
+                timestamp: Self::parse_time(trade.get("time"))?,
+            })
+        }).await
+    }
+    
+    /// Records of a paged history `endpoint` in `range`, continuing full
+    /// pages by `id_param`.
+    async fn history<T: HistoryRecord>(
+        &self,
+        endpoint: &str,
+        id_param: &str,
+        symbol: &str,
+        range: Range<DateTime<Utc>>,
+        parse: impl Fn(&Value) -> Result<T>,
+    ) -> Result<Vec<T>> {
+        collect_pages(HistoryCursor::Window(range.start), MAX_PAGES, |cursor| {
+            self.history_page(endpoint, id_param, symbol, &range, cursor, &parse)
+        }).await
+    }
+    
+    async fn history_page<T: HistoryRecord>(
+        &self,
+        endpoint: &str,
+        id_param: &str,
+        symbol: &str,
+        range: &Range<DateTime<Utc>>,
+        cursor: HistoryCursor,
+        parse: &impl Fn(&Value) -> Result<T>,
+    ) -> Result<Page<HistoryCursor, T>> {
+        let mut params = vec![("symbol", symbol.to_string()), ("limit", HISTORY_PAGE_SIZE.to_string())];
+        match &cursor {
+            HistoryCursor::Window(start) => {
+                let window = pagination::window(*start, chrono::Duration::hours(24), range);
+                params.push(("startTime", window.start.timestamp_millis().to_string()));
+                params.push(("endTime", (window.end.timestamp_millis() - 1).to_string()));
+            }
+            HistoryCursor::FromId(id) => params.push((id_param, id.to_string())),
+        }
+        let response = self.signed_request(reqwest::Method::GET, endpoint, params, 20, Duration::from_secs(10)).await?;
+        let rows: Vec<Value> = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse {}: {}", endpoint, e.without_url())))
+            .inspect_err(|e| self.errors.record(endpoint, e))?;
+        
+        let records = rows.iter().map(parse).collect::<Result<Vec<T>>>()?;
+        let full = records.len() >= HISTORY_PAGE_SIZE;
+        let last_id = records.iter().filter_map(HistoryRecord::sequence).max();
+        let past_end = records.iter().any(|record| record.time() >= range.end);
+        let items: Vec<T> = records.into_iter().filter(|record| range.contains(&record.time())).collect();
+        Ok(match (cursor, last_id) {
+            (_, Some(last_id)) if full && !past_end => Page::more(items, HistoryCursor::FromId(last_id + 1)),
+            (HistoryCursor::Window(start), _) if !full => match pagination::next_window(start, chrono::Duration::hours(24), range) {
+                Some(next) => Page::more(items, HistoryCursor::Window(next)),
+                None => Page::last(items),
+            },
+            _ => Page::last(items),
+        })
+    }
+    
+    fn parse_side(side: &str) -> Result<OrderSide> {
+        match side {
+            "BUY" => Ok(OrderSide::Buy),
+            "SELL" => Ok(OrderSide::Sell),
+            other => Err(ExchangeError::ParseError(format!("Unknown order side {:?}", other)).into()),
+        }
+    }
+    
+    fn parse_time(value: Option<&Value>) -> Result<DateTime<Utc>> {
+        Ok(value.and_then(|v| v.as_i64())
+            .and_then(DateTime::from_timestamp_millis)
+            .ok_or_else(|| ExchangeError::ParseError("Invalid record time".to_string()))?)
+    }
+    
+    /// Permissions of the configured key from `/sapi/v1/account/apiRestrictions`.
+    pub async fn get_api_key_permissions(&self) -> Result<ApiKeyPermissions> {
+        let endpoint = "/sapi/v1/account/apiRestrictions";
+        let response = self.signed_request(reqwest::Method::GET, endpoint, Vec::new(), 1, Duration::from_secs(10)).await?;
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse API restrictions: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record(endpoint, e))?;
+        
+        let flag = |key: &str| data.get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        Ok(ApiKeyPermissions {
+            read: flag("enableReading"),
+            trade: flag("enableSpotAndMarginTrading"),
+            withdraw: flag("enableWithdrawals"),
+            ip_restricted: flag("ipRestrict"),
+        })
+    }
+    
+    /// Commission rates of the account on `symbol`, including whether fees
+    /// are paid in BNB and at what discount.
+    pub async fn get_fee_rates(&self, symbol: &str) -> Result<AccountFeeRates> {
+        let endpoint = "/api/v3/account/commission";
+        let params = vec![("symbol", symbol.to_string())];
+        let response = self.signed_request(reqwest::Method::GET, endpoint, params, 20, Duration::from_secs(10)).await?;
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse commission rates: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record(endpoint, e))?;
+        
+        let rate = |group: &str, key: &str| data.get(group)
+            .and_then(|g| g.get(key))
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<Decimal>().ok());
+        let (Some(maker_fee), Some(taker_fee)) = (rate("standardCommission", "maker"), rate("standardCommission", "taker")) else {
+            return Err(ExchangeError::ParseError("Missing standard commission rates".to_string()).into());
+        };
+        // Tax commission is charged on top in some jurisdictions
+        let maker_fee = maker_fee + rate("taxCommission", "maker").unwrap_or(Decimal::ZERO);
+        let taker_fee = taker_fee + rate("taxCommission", "taker").unwrap_or(Decimal::ZERO);
+        
+        let discount = data.get("discount");
+        let enabled = |key: &str| discount.and_then(|d| d.get(key)).and_then(|v| v.as_bool()).unwrap_or(false);
+        let bnb_fee_multiplier = (enabled("enabledForAccount") && enabled("enabledForSymbol"))
+            .then(|| rate("discount", "discount"))
+            .flatten();
+        Ok(AccountFeeRates { maker_fee, taker_fee, bnb_fee_multiplier })
+    }
+    
+    pub async fn cancel_order_by_client_id(&self, symbol: &str, client_order_id: &str) -> Result<Value> {
+        let params = vec![
+            ("symbol", symbol.to_string()),
+            ("origClientOrderId", client_order_id.to_string()),
+        ];
+        if let Some(data) = self.ws_request("order.cancel", params.clone()).await? {
+            return Ok(data);
+        }
+        let response = self.signed_request(reqwest::Method::DELETE, "/api/v3/order", params, 1, Duration::from_secs(10)).await?;
+        
+        Ok(response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse cancel response: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record("/api/v3/order", e))?)
+    }
+    
+    /// Cancels every order of `orders` and returns the outcome of each.
+    /// Spot has no batch cancel by order ID, so the cancels go out at once
+    /// instead.
+    pub async fn cancel_orders_by_client_id(&self, orders: &[OrderRef]) -> Vec<Result<()>> {
+        join_all(orders.iter().map(|order| async move {
+            self.cancel_order_by_client_id(&order.symbol, &order.client_order_id).await.map(|_| ())
+        })).await
+    }
+    
+    /// Places `order` without ever resubmitting it blindly. If the request
+    /// fails ambiguously (timeout, dropped connection, an accepted response
+    /// that does not parse) the order is queried by its client ID until the
+    /// receive window has passed: resting orders are canceled, final ones are
+    /// adopted.
+    pub async fn place_order_cancel_safe(&self, order: &OrderRequest) -> Result<PlacementOutcome> {
+        let client_order_id = order.client_order_id.clone()
+            .ok_or_else(|| ExchangeError::ApiError {
+                exchange: "Binance".to_string(),
+                endpoint: "/api/v3/order".to_string(),
+                status: None,
+                code: None,
+                message: "cancel-safe placement requires a client order ID".to_string(),
+            })?;
+        
+        let sent_at = tokio::time::Instant::now();
+        let placed = self.place_order(order).await
+            .and_then(|data| Self::parse_order_state(&data, &client_order_id));
+        let error = match placed {
+            Ok(state) => return Ok(PlacementOutcome::Placed(state)),
+            Err(e) => e,
+        };
+        
+        if !is_ambiguous_placement(&error) {
+            return Err(error);
+        }
+        
+        warn!("Order {} on {} failed ambiguously ({}), checking whether it reached the exchange", 
+              client_order_id, order.symbol, error);
+        
+        let recv_window = Duration::from_millis(self.network.recv_window_ms);
+        let lookup = || self.get_order_by_client_id(&order.symbol, &client_order_id);
+        match find_ambiguous_order(sent_at, recv_window, lookup).await? {
+            None => Ok(PlacementOutcome::NotPlaced),
+            Some(state) if state.status.is_open() => {
+                self.cancel_order_by_client_id(&order.symbol, &client_order_id).await?;
+                let state = self.get_order_by_client_id(&order.symbol, &client_order_id).await?
+                    .unwrap_or(state);
+                Ok(PlacementOutcome::CanceledAfterTimeout(state))
+            }
+            Some(state) => Ok(PlacementOutcome::Adopted(state)),
+        }
+    }
+    
+    fn parse_order_state(data: &Value, client_order_id: &str) -> Result<OrderState> {
+        let decimal = |key: &str| data.get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<Decimal>().ok());
+        let status = data.get("status")
+            .and_then(|s| s.as_str())
+            .and_then(OrderStatus::from_binance)
+            .ok_or_else(|| ExchangeError::ParseError("Missing or unknown order status".to_string()))?;
+        let executed_quantity = decimal("executedQty").unwrap_or(Decimal::ZERO);
+        let average_price = decimal("cummulativeQuoteQty")
+            .filter(|_| executed_quantity > Decimal::ZERO)
+            .map(|quote| quote.checked_div(executed_quantity)
+                .ok_or_else(|| ExchangeError::ParseError("Average price out of range".to_string())))
+            .transpose()?;
+        
+        Ok(OrderState {
+            client_order_id: client_order_id.to_string(),
+            exchange_order_id: data.get("orderId").map(|id| id.to_string()),
+            status,
+            executed_quantity,
+            average_price,
+        })
+    }
+    
+    /// Moves funds between the master account and sub-accounts (or between
+    /// two sub-accounts) via `/sapi/v1/sub-account/universalTransfer`.
+    pub async fn transfer_between_sub_accounts(&self, transfer: &SubAccountTransfer) -> Result<TransferReceipt> {
+        transfer.validate()?;
+        
+        let mut params = vec![
+            ("fromAccountType", "SPOT".to_string()),
+            ("toAccountType", "SPOT".to_string()),
+            ("asset", transfer.asset.clone()),
+            ("amount", transfer.amount.to_string()),
+            ("clientTranId", transfer.transfer_id.clone()),
+        ];
+        if let Some(from) = &transfer.from_account {
+            params.push(("fromEmail", from.clone()));
+        }
+        if let Some(to) = &transfer.to_account {
+            params.push(("toEmail", to.clone()));
+        }
+        
+        let endpoint = "/sapi/v1/sub-account/universalTransfer";
+        let response = self.signed_request(reqwest::Method::POST, endpoint, params, 1, Duration::from_secs(15)).await?;
+        let data: Value = response.json().await
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse transfer response: {}", e.without_url())))
+            .inspect_err(|e| self.errors.record(endpoint, e))?;
+        
+        let transfer_id = data.get("tranId")
+            .map(|id| id.to_string())
+            .ok_or_else(|| ExchangeError::ParseError("Transfer response has no tranId".to_string()))?;
+        
+        Ok(TransferReceipt {
+            exchange: "Binance".to_string(),
+            transfer_id,
+            status: data.get("status").and_then(|v| v.as_str()).map(str::to_string),
+        })
+    }
+    
+    /// Sends a signed request and checks its response.
+    async fn signed_request(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+        params: Vec<(&str, String)>,
+        weight: u32,
+        timeout: Duration,
+    ) -> Result<reqwest::Response> {
+        match self.signed_request_once(method.clone(), endpoint, params.clone(), weight, timeout).await {
+            Err(e) if Self::is_timestamp_error(&e) => {
+                self.resync_clock(method.as_str(), endpoint, e).await?;
+                self.signed_request_once(method, endpoint, params, weight, timeout).await
+            }
+            result => result,
         }
+    }
+    
+    async fn signed_request_once(
+        &self,
+        method: reqwest::Method,
+        endpoint: &str,
+        mut params: Vec<(&str, String)>,
+        weight: u32,
+        timeout: Duration,
+    ) -> Result<reqwest::Response> {
+        let _permit = self.budget.acquire(RequestClass::Trading, weight).await?;
         
+        params.push(("recvWindow", self.network.recv_window_ms.to_string()));
+        params.push(("timestamp", self.clock.timestamp_ms().to_string()));
         let query_string = params.iter()
             .map(|(k, v)| format!("{}={}", urlencoding::encode(k), urlencoding::encode(v)))
             .collect::<Vec<_>>()
             .join("&");
             
         let signature = self.generate_signature(&query_string)?;
-        let url = format!("{}{}?{}&signature={}", self.base_url, endpoint, query_string, signature);
+        let build = |base: &str| self.http()
+            .request(method.clone(), format!("{}{}?{}&signature={}", base, endpoint, query_string, signature))
+            .header("X-MBX-APIKEY", self.api_key.expose())
+            .timeout(timeout);
+        // Only reads are retried on another host; a resent order could fill twice
+        let response = if method == reqwest::Method::GET {
+            self.endpoints.send(build).await
+        } else {
+            self.endpoints.send_once(build).await
+        };
+        let response = response.map_err(|e| ExchangeError::NetworkError(format!("{} {} failed: {}", method, endpoint, e)))?;
         
-        let response = self.client
-            .post(&url)
-            .header("X-MBX-APIKEY", &self.api_key)
-            .timeout(Duration::from_secs(15))
-            .send()
-            .await
-            .map_err(|e| ExchangeError::NetworkError(format!("Order placement failed: {}", e)))?;
+        Ok(check_response(&self.errors, endpoint, response).await?)
+    }
+    
+    /// Sends `method` over the websocket API, signed over `params` sorted
+    /// by name. Returns `None` when the API is disabled or disconnected,
+    /// and the request should go over REST.
+    async fn ws_request(&self, method: &str, mut params: Vec<(&str, String)>) -> Result<Option<Value>> {
+        let Some(api) = self.ws_trading.as_ref().filter(|api| api.is_connected()) else { return Ok(None) };
+        let _permit = self.budget.acquire(RequestClass::Trading, 1).await?;
+        
+        params.push(("apiKey", self.api_key.expose().to_string()));
+        params.push(("recvWindow", self.network.recv_window_ms.to_string()));
+        params.push(("timestamp", self.clock.timestamp_ms().to_string()));
+        params.sort_by_key(|(key, _)| *key);
+        let payload = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&");
+        let signature = self.generate_signature(&payload)?;
         
-        if !response.status().is_success() {
-            return Err(ExchangeError::ApiError(format!(
-                "Order failed - HTTP {}: {}", 
-                response.status(), 
-                response.text().await.unwrap_or_default()
-            )).into());
+        let mut request_params: serde_json::Map<String, Value> = params.into_iter()
+            .map(|(k, v)| (k.to_string(), Value::String(v)))
+            .collect();
+        request_params.insert("signature".to_string(), Value::String(signature));
+        let request = serde_json::json!({ "method": method, "params": request_params });
+        let Some(response) = api.request(request).await.inspect_err(|e| self.errors.record(method, e))? else {
+            return Ok(None);
+        };
+        
+        let status = response.get("status").and_then(|s| s.as_u64()).unwrap_or_default() as u16;
+        if status == 200 {
+            return Ok(Some(response.get("result").cloned().unwrap_or(Value::Null)));
+        }
+        let body = response.get("error").map(Value::to_string).unwrap_or_default();
+        let mut error = ExchangeError::from_response("Binance", method, status, &body);
+        if status == 429 || status == 418 {
+            error = ExchangeError::RateLimitError(error.to_string());
+        }
+        self.errors.record(method, &error);
+        Err(error.into())
+    }
+    
+    fn is_timestamp_error(error: &anyhow::Error) -> bool {
+        error.downcast_ref::<ExchangeError>().is_some_and(ExchangeError::is_timestamp_error)
+    }
+    
+    /// Resyncs the clock after `error` rejected the timestamp or signature
+    /// of a signed request, so the request can be signed again; the
+    /// exchange did not act on it, so this holds for orders too. Returns
+    /// `error` when the server time cannot be read.
+    async fn resync_clock(&self, method: &str, endpoint: &str, error: anyhow::Error) -> Result<()> {
+        match self.sync_clock().await {
+            Ok(offset) => {
+                warn!("Binance rejected {} {} ({}), resending with the clock offset resynced to {}ms", method, endpoint, error, offset);
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Binance rejected {} {} ({}) and the clock could not be resynced: {:#}", method, endpoint, error, e);
+                Err(error)
+            }
         }
-            
-        Ok(response.json().await
-            .map_err(|e| ExchangeError::ParseError(format!("Failed to parse order response: {}", e)))?)
     }
     
     fn generate_signature(&self, query_string: &str) -> Result<String> {
-        let mut mac = HmacSha256::new_from_slice(self.secret_key.as_bytes())
-            .map_err(|e| ExchangeError::SignatureError(format!("Invalid secret key: {}", e)))?;
-        mac.update(query_string.as_bytes());
-        let result = mac.finalize();
-        Ok(hex::encode(result.into_bytes()))
+        sign(&self.secret_key, query_string)
     }
     
     async fn enforce_rate_limit(&self) {
         // Binance allows 1200 requests per minute, so ~50ms between requests
         let min_interval = Duration::from_millis(50);
         
-        if let Ok(last_time) = self.last_request_time.lock() {
-            let elapsed = Utc::now().signed_duration_since(*last_time);
-            if let Ok(elapsed_std) = elapsed.to_std() {
-                if elapsed_std < min_interval {
-                    let sleep_time = min_interval - elapsed_std;
-                    tokio::time::sleep(sleep_time).await;
-                }
-            }
+        let sleep_time = match self.last_request_time.lock() {
+            Ok(last_time) => Utc::now()
+                .signed_duration_since(*last_time)
+                .to_std()
+                .ok()
+                .filter(|elapsed| *elapsed < min_interval)
+                .map(|elapsed| min_interval - elapsed),
+            Err(_) => None,
+        };
+        
+        if let Some(sleep_time) = sleep_time {
+            tokio::time::sleep(sleep_time).await;
         }
     }
-}
\ No newline at end of file
+}
+
+/// Hex HMAC-SHA256 of `payload` under `secret`.
+fn sign(secret: &SecretString, payload: &str) -> Result<String> {
+    let mut mac = HmacSha256::new_from_slice(secret.expose().as_bytes())
+        .map_err(|e| ExchangeError::SignatureError(format!("Invalid secret key: {}", e)))?;
+    mac.update(payload.as_bytes());
+    let result = mac.finalize();
+    Ok(hex::encode(result.into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_matches_the_api_documentation_example() {
+        // The example key and request from Binance's "SIGNED endpoint examples"
+        let secret = SecretString::from("NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j");
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
+        assert_eq!(
+            sign(&secret, query).unwrap(),
+            "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71"
+        );
+    }
+}
@@ -0,0 +1,277 @@
+//! Request weight budget shared by an exchange client's requests.
+//!
+//! Exchanges cap the request weight an IP may spend per minute, and the
+//! same cap covers market data polling and order traffic. The budget
+//! tracks the weight spent over a rolling minute and keeps
+//! `trading_reserve_percentage` of it for trading: market data requests
+//! wait once the rest is spent, so a busy scan can never leave an
+//! execution without the weight to place or cancel its orders. Trading
+//! requests may spend the whole budget and skip the market data
+//! concurrency limits: the client's `max_concurrent_requests` and the one
+//! shared by every exchange.
+
+use crate::config::ClientNetworkConfig;
+use crate::metrics::{self, write_metric};
+use super::ExchangeError;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, SemaphorePermit};
+use tokio::time::Instant;
+
+/// Which share of the budget a request draws on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestClass {
+    /// Prices, book tickers, depth, trades, candles and exchange info
+    MarketData,
+    /// Orders, cancels, order status and account reads
+    Trading,
+}
+
+impl RequestClass {
+    const ALL: [RequestClass; 2] = [RequestClass::MarketData, RequestClass::Trading];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RequestClass::MarketData => "market_data",
+            RequestClass::Trading => "trading",
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Held while a request is in flight; frees its market data slots on drop.
+#[derive(Debug)]
+pub struct BudgetPermit<'a> {
+    _slot: Option<SemaphorePermit<'a>>,
+    _shared_slot: Option<OwnedSemaphorePermit>,
+}
+
+#[derive(Debug)]
+pub struct RequestBudget {
+    exchange: &'static str,
+    /// Zero leaves the weight unlimited
+    weight_per_minute: u32,
+    /// Weight only trading requests may spend
+    trading_reserve: u32,
+    window: Duration,
+    spent: Mutex<VecDeque<(Instant, RequestClass, u32)>>,
+    market_data_slots: Semaphore,
+    /// Market data slots shared with the other exchanges' clients
+    shared_slots: Option<Arc<Semaphore>>,
+    waits: [AtomicU64; 2],
+}
+
+impl RequestBudget {
+    pub fn new(exchange: &'static str, network: &ClientNetworkConfig) -> Self {
+        let weight_per_minute = network.request_weight_per_minute;
+        let trading_reserve = (weight_per_minute as f64 * network.trading_reserve_percentage / 100.0).ceil() as u32;
+        Self {
+            exchange,
+            weight_per_minute,
+            trading_reserve: trading_reserve.min(weight_per_minute),
+            window: Duration::from_secs(60),
+            spent: Mutex::new(VecDeque::new()),
+            market_data_slots: Semaphore::new(network.max_concurrent_requests),
+            shared_slots: None,
+            waits: [AtomicU64::new(0), AtomicU64::new(0)],
+        }
+    }
+
+    /// Also holds market data requests to `slots`, shared with other clients.
+    pub fn with_shared_slots(mut self, slots: Arc<Semaphore>) -> Self {
+        self.shared_slots = Some(slots);
+        self
+    }
+
+    pub fn is_limited(&self) -> bool {
+        self.weight_per_minute > 0
+    }
+
+    /// Weight `class` may have in flight over the window, counting what
+    /// the other class spent.
+    pub fn allowance(&self, class: RequestClass) -> u32 {
+        match class {
+            RequestClass::MarketData => self.weight_per_minute - self.trading_reserve,
+            RequestClass::Trading => self.weight_per_minute,
+        }
+    }
+
+    /// Waits until `weight` fits in the share of `class`, then records it.
+    pub async fn acquire(&self, class: RequestClass, weight: u32) -> Result<BudgetPermit<'_>, ExchangeError> {
+        let acquire_failed = |e: tokio::sync::AcquireError| ExchangeError::RateLimitError(format!("Rate limit acquisition failed: {}", e));
+        let (slot, shared_slot) = match class {
+            RequestClass::MarketData => {
+                let slot = self.market_data_slots.acquire().await.map_err(acquire_failed)?;
+                let shared_slot = match &self.shared_slots {
+                    Some(slots) => Some(slots.clone().acquire_owned().await.map_err(acquire_failed)?),
+                    None => None,
+                };
+                (Some(slot), shared_slot)
+            }
+            RequestClass::Trading => (None, None),
+        };
+        if !self.is_limited() {
+            return Ok(BudgetPermit { _slot: slot, _shared_slot: shared_slot });
+        }
+
+        let mut waited = false;
+        while let Some(wait) = self.try_spend(class, weight) {
+            if !waited {
+                self.waits[class.index()].fetch_add(1, Ordering::Relaxed);
+                waited = true;
+            }
+            tokio::time::sleep(wait).await;
+        }
+        Ok(BudgetPermit { _slot: slot, _shared_slot: shared_slot })
+    }
+
+    /// Records `weight` if it fits, or returns how long until enough of
+    /// the window expires for it to.
+    fn try_spend(&self, class: RequestClass, weight: u32) -> Option<Duration> {
+        let now = Instant::now();
+        let mut spent = self.spent.lock().unwrap();
+        while spent.front().is_some_and(|(at, _, _)| now.duration_since(*at) >= self.window) {
+            spent.pop_front();
+        }
+
+        let limit = self.allowance(class);
+        let used: u32 = spent.iter().map(|(_, _, weight)| weight).sum();
+        // A request heavier than the whole allowance still goes through on an idle budget
+        if used + weight <= limit || used == 0 {
+            spent.push_back((now, class, weight));
+            return None;
+        }
+        let mut remaining = used;
+        for (at, _, freed) in spent.iter() {
+            remaining -= freed;
+            if remaining + weight <= limit {
+                return Some((*at + self.window).saturating_duration_since(now));
+            }
+        }
+        Some(self.window)
+    }
+
+    /// Weight `class` spent over the last minute.
+    pub fn used(&self, class: RequestClass) -> u32 {
+        let now = Instant::now();
+        self.spent.lock().unwrap().iter()
+            .filter(|(at, spent_by, _)| *spent_by == class && now.duration_since(*at) < self.window)
+            .map(|(_, _, weight)| weight)
+            .sum()
+    }
+
+    /// Share of its allowance `class` spent over the last minute.
+    pub fn utilization(&self, class: RequestClass) -> f64 {
+        match self.allowance(class) {
+            0 => 0.0,
+            allowance => self.used(class) as f64 / allowance as f64,
+        }
+    }
+
+    /// Requests of `class` that had to wait for the budget.
+    pub fn waits(&self, class: RequestClass) -> u64 {
+        self.waits[class.index()].load(Ordering::Relaxed)
+    }
+}
+
+/// Prometheus text exposition of the spent weight of `budgets`; budgets
+/// without a limit are left out.
+pub fn prometheus_metrics(budgets: &[Arc<RequestBudget>]) -> String {
+    let mut used = Vec::new();
+    let mut utilization = Vec::new();
+    let mut waits = Vec::new();
+    for budget in budgets.iter().filter(|budget| budget.is_limited()) {
+        for class in RequestClass::ALL {
+            let labels = vec![budget.exchange.to_string(), class.label().to_string()];
+            used.push((labels.clone(), budget.used(class) as f64));
+            utilization.push((labels.clone(), budget.utilization(class)));
+            waits.push((labels, budget.waits(class) as f64));
+        }
+    }
+
+    let mut out = String::new();
+    write_metric(&mut out, &metrics::REQUEST_WEIGHT_USED, &used);
+    write_metric(&mut out, &metrics::REQUEST_BUDGET_UTILIZATION, &utilization);
+    write_metric(&mut out, &metrics::REQUEST_BUDGET_WAITS, &waits);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn budget(weight_per_minute: u32, reserve_percentage: f64) -> RequestBudget {
+        let network = ClientNetworkConfig {
+            request_weight_per_minute: weight_per_minute,
+            trading_reserve_percentage: reserve_percentage,
+            ..ClientNetworkConfig::default()
+        };
+        let mut budget = RequestBudget::new("Binance", &network);
+        budget.window = Duration::from_millis(300);
+        budget
+    }
+
+    #[tokio::test]
+    async fn test_market_data_cannot_spend_the_trading_reserve() {
+        let budget = budget(100, 20.0);
+        assert_eq!(budget.allowance(RequestClass::MarketData), 80);
+        for _ in 0..4 {
+            budget.acquire(RequestClass::MarketData, 20).await.unwrap();
+        }
+        assert!(budget.try_spend(RequestClass::MarketData, 1).is_some());
+
+        // Orders still get through, right away
+        let started = Instant::now();
+        for _ in 0..20 {
+            budget.acquire(RequestClass::Trading, 1).await.unwrap();
+        }
+        assert!(started.elapsed() < Duration::from_millis(100));
+        assert_eq!(budget.used(RequestClass::Trading), 20);
+        assert!(budget.try_spend(RequestClass::Trading, 1).is_some());
+        assert_eq!(budget.utilization(RequestClass::MarketData), 1.0);
+
+        // Market data waits for the window to roll instead of failing
+        budget.acquire(RequestClass::MarketData, 20).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(200));
+        assert_eq!(budget.waits(RequestClass::MarketData), 1);
+        assert_eq!(budget.waits(RequestClass::Trading), 0);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_budget_only_caps_market_data_concurrency() {
+        let budget = budget(0, 20.0);
+        let concurrency = ClientNetworkConfig::default().max_concurrent_requests;
+        let permits: Vec<_> = futures_util::future::join_all((0..concurrency)
+            .map(|_| budget.acquire(RequestClass::MarketData, 1000))).await;
+        assert!(permits.iter().all(|permit| permit.is_ok()));
+        assert_eq!(budget.market_data_slots.available_permits(), 0);
+        drop(permits);
+        budget.acquire(RequestClass::Trading, 1000).await.unwrap();
+        assert!(prometheus_metrics(&[Arc::new(budget)]).lines().all(|line| line.starts_with('#')));
+    }
+
+    #[tokio::test]
+    async fn test_shared_slots_cap_market_data_across_clients() {
+        let shared = Arc::new(Semaphore::new(2));
+        let binance = budget(0, 20.0).with_shared_slots(shared.clone());
+        let bybit = budget(0, 20.0).with_shared_slots(shared.clone());
+        let held = [
+            binance.acquire(RequestClass::MarketData, 1).await.unwrap(),
+            bybit.acquire(RequestClass::MarketData, 1).await.unwrap(),
+        ];
+        assert_eq!(shared.available_permits(), 0);
+        let blocked = tokio::time::timeout(Duration::from_millis(50), bybit.acquire(RequestClass::MarketData, 1)).await;
+        assert!(blocked.is_err());
+        // Orders skip every market data limit
+        binance.acquire(RequestClass::Trading, 1).await.unwrap();
+
+        drop(held);
+        bybit.acquire(RequestClass::MarketData, 1).await.unwrap();
+        assert_eq!(shared.available_permits(), 2);
+    }
+}
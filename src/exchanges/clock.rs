@@ -0,0 +1,62 @@
+//! Exchange clock offset for signed requests.
+//!
+//! Signed requests carry a timestamp the exchange checks against its own
+//! clock, rejecting any that falls outside the request's receive window.
+//! A local clock that drifts turns every order into a rejection, so the
+//! offset measured against the exchange's server time is added to each
+//! timestamp. The offset is measured again whenever a request is rejected
+//! for its timestamp or signature, and the request is signed once more.
+
+use chrono::{DateTime, Utc};
+use std::sync::atomic::{AtomicI64, Ordering};
+
+#[derive(Debug, Default)]
+pub struct ServerClock {
+    offset_ms: AtomicI64,
+}
+
+impl ServerClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Milliseconds the exchange's clock runs ahead of the local one.
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms.load(Ordering::Relaxed)
+    }
+
+    /// Current time on the exchange's clock, in milliseconds since the epoch.
+    pub fn timestamp_ms(&self) -> i64 {
+        Utc::now().timestamp_millis() + self.offset_ms()
+    }
+
+    /// Takes the offset from a server time read between `sent` and
+    /// `received`, assuming it was read halfway through. Returns the offset.
+    pub fn sync(&self, sent: DateTime<Utc>, server: DateTime<Utc>, received: DateTime<Utc>) -> i64 {
+        let midpoint = sent + (received - sent) / 2;
+        let offset = (server - midpoint).num_milliseconds();
+        self.offset_ms.store(offset, Ordering::Relaxed);
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_offset_is_measured_from_the_round_trip_midpoint() {
+        let clock = ServerClock::new();
+        assert_eq!(clock.offset_ms(), 0);
+
+        let sent: DateTime<Utc> = "2024-05-01T12:00:00Z".parse().unwrap();
+        let received = sent + Duration::milliseconds(200);
+        assert_eq!(clock.sync(sent, sent + Duration::milliseconds(1_600), received), 1_500);
+        let drift = clock.timestamp_ms() - Utc::now().timestamp_millis();
+        assert!((1_490..=1_500).contains(&drift));
+
+        // A local clock running ahead gives a negative offset
+        assert_eq!(clock.sync(sent, sent - Duration::seconds(2), received), -2_100);
+    }
+}
@@ -0,0 +1,310 @@
+use super::{BookTicker, ExchangeError, OrderBook};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// REST depth snapshot a websocket diff stream is applied on top of.
+#[derive(Debug, Clone)]
+pub struct DepthSnapshot {
+    pub last_update_id: u64,
+    pub bids: Vec<(Decimal, Decimal)>,
+    pub asks: Vec<(Decimal, Decimal)>,
+}
+
+/// Result of applying one diff event to a local book.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookUpdate {
+    Applied,
+    /// Already covered by the snapshot or an earlier event; ignored
+    Stale,
+    /// An update was missed; the book must be resynced from a new snapshot
+    Gap { expected: u64, received: u64 },
+}
+
+/// Order book rebuilt locally from a snapshot plus sequenced diff events.
+/// A level with zero quantity in a diff removes that price.
+#[derive(Debug, Clone)]
+pub struct LocalOrderBook {
+    symbol: String,
+    bids: BTreeMap<Decimal, Decimal>,
+    asks: BTreeMap<Decimal, Decimal>,
+    last_update_id: u64,
+    /// Binance allows the first diff after a snapshot to start before it
+    awaiting_first_diff: bool,
+    updated_at: DateTime<Utc>,
+}
+
+impl LocalOrderBook {
+    pub fn from_snapshot(symbol: &str, snapshot: &DepthSnapshot) -> Self {
+        let mut book = Self {
+            symbol: symbol.to_string(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            last_update_id: snapshot.last_update_id,
+            awaiting_first_diff: true,
+            updated_at: Utc::now(),
+        };
+        book.apply_levels(&snapshot.bids, &snapshot.asks);
+        book
+    }
+
+    pub fn last_update_id(&self) -> u64 {
+        self.last_update_id
+    }
+
+    pub fn updated_at(&self) -> DateTime<Utc> {
+        self.updated_at
+    }
+
+    /// Applies a Binance `depthUpdate` covering update IDs `first..=last`
+    /// (`U` and `u`). Events at or below the book's ID are stale; the first
+    /// event after the snapshot must span `last_update_id + 1`, and every
+    /// later one must start right after the previous event.
+    pub fn apply_binance_diff(
+        &mut self,
+        first: u64,
+        last: u64,
+        bids: &[(Decimal, Decimal)],
+        asks: &[(Decimal, Decimal)],
+    ) -> BookUpdate {
+        if last <= self.last_update_id {
+            return BookUpdate::Stale;
+        }
+        let expected = self.last_update_id + 1;
+        let in_sequence = if self.awaiting_first_diff { first <= expected } else { first == expected };
+        if !in_sequence {
+            return BookUpdate::Gap { expected, received: first };
+        }
+
+        self.apply_levels(bids, asks);
+        self.last_update_id = last;
+        self.awaiting_first_diff = false;
+        BookUpdate::Applied
+    }
+
+    /// Applies a Bybit orderbook `delta`, whose update ID `u` increases by
+    /// one per message.
+    pub fn apply_bybit_delta(
+        &mut self,
+        update_id: u64,
+        bids: &[(Decimal, Decimal)],
+        asks: &[(Decimal, Decimal)],
+    ) -> BookUpdate {
+        if update_id <= self.last_update_id {
+            return BookUpdate::Stale;
+        }
+        let expected = self.last_update_id + 1;
+        if update_id != expected {
+            return BookUpdate::Gap { expected, received: update_id };
+        }
+
+        self.apply_levels(bids, asks);
+        self.last_update_id = update_id;
+        BookUpdate::Applied
+    }
+
+    pub fn best_bid(&self) -> Option<(Decimal, Decimal)> {
+        self.bids.iter().next_back().map(|(p, q)| (*p, *q))
+    }
+
+    pub fn best_ask(&self) -> Option<(Decimal, Decimal)> {
+        self.asks.iter().next().map(|(p, q)| (*p, *q))
+    }
+
+    /// Top `levels` of each side, best price first.
+    pub fn to_order_book(&self, levels: usize) -> OrderBook {
+        OrderBook {
+            symbol: self.symbol.clone(),
+            bids: self.bids.iter().rev().take(levels).map(|(p, q)| (*p, *q)).collect(),
+            asks: self.asks.iter().take(levels).map(|(p, q)| (*p, *q)).collect(),
+            timestamp: self.updated_at,
+        }
+    }
+
+    fn apply_levels(&mut self, bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) {
+        for (side, levels) in [(&mut self.bids, bids), (&mut self.asks, asks)] {
+            for (price, quantity) in levels {
+                if quantity.is_zero() {
+                    side.remove(price);
+                } else {
+                    side.insert(*price, *quantity);
+                }
+            }
+        }
+        self.updated_at = Utc::now();
+    }
+}
+
+/// Parses `[["price", "qty"], ...]` levels as sent by both exchanges.
+pub fn parse_levels(levels: Option<&Value>) -> Result<Vec<(Decimal, Decimal)>> {
+    let Some(levels) = levels else { return Ok(Vec::new()) };
+    let levels = levels.as_array()
+        .ok_or_else(|| ExchangeError::ParseError("Depth levels are not an array".to_string()))?;
+
+    levels.iter()
+        .map(|level| {
+            let field = |index: usize| level.get(index)
+                .and_then(|v| v.as_str())
+                .and_then(|v| v.parse::<Decimal>().ok())
+                .ok_or_else(|| ExchangeError::ParseError(format!("Invalid depth level {}", level)));
+            Ok((field(0)?, field(1)?))
+        })
+        .collect()
+}
+
+/// Live local books keyed by `exchange:symbol`, written by the depth
+/// streams and read by the engine. Books being resynced are absent.
+#[derive(Debug, Default)]
+pub struct OrderBookStore {
+    books: DashMap<String, LocalOrderBook>,
+}
+
+impl OrderBookStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&self, exchange: &str, book: LocalOrderBook) {
+        self.books.insert(format!("{}:{}", exchange, book.symbol), book);
+    }
+
+    pub fn remove(&self, exchange: &str, symbol: &str) {
+        self.books.remove(&format!("{}:{}", exchange, symbol));
+    }
+
+    /// Runs `update` on the book if it is present.
+    pub fn update(
+        &self,
+        exchange: &str,
+        symbol: &str,
+        update: impl FnOnce(&mut LocalOrderBook) -> BookUpdate,
+    ) -> Option<BookUpdate> {
+        self.books.get_mut(&format!("{}:{}", exchange, symbol)).map(|mut book| update(&mut book))
+    }
+
+    /// The book when it was updated within `max_age`.
+    pub fn fresh(&self, exchange: &str, symbol: &str, levels: usize, max_age: Duration) -> Option<OrderBook> {
+        let book = self.books.get(&format!("{}:{}", exchange, symbol))?;
+        is_fresh(&book, max_age).then(|| book.to_order_book(levels))
+    }
+
+    /// Best bid and ask of every fresh book, with its exchange.
+    pub fn fresh_tops(&self, max_age: Duration) -> Vec<(String, BookTicker)> {
+        self.books.iter()
+            .filter(|entry| is_fresh(entry.value(), max_age))
+            .filter_map(|entry| {
+                let (exchange, symbol) = entry.key().split_once(':')?;
+                let ((bid_price, bid_quantity), (ask_price, ask_quantity)) = (entry.best_bid()?, entry.best_ask()?);
+                Some((exchange.to_string(), BookTicker {
+                    symbol: symbol.to_string(),
+                    bid_price,
+                    bid_quantity,
+                    ask_price,
+                    ask_quantity,
+                    timestamp: None,
+                }))
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.books.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.books.is_empty()
+    }
+}
+
+fn is_fresh(book: &LocalOrderBook, max_age: Duration) -> bool {
+    Utc::now().signed_duration_since(book.updated_at)
+        .to_std()
+        .map(|age| age <= max_age)
+        .unwrap_or(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: &str, quantity: &str) -> (Decimal, Decimal) {
+        (Decimal::from_str_exact(price).unwrap(), Decimal::from_str_exact(quantity).unwrap())
+    }
+
+    fn snapshot(last_update_id: u64) -> DepthSnapshot {
+        DepthSnapshot {
+            last_update_id,
+            bids: vec![level("100.0", "1"), level("99.5", "2")],
+            asks: vec![level("100.5", "1"), level("101.0", "3")],
+        }
+    }
+
+    #[test]
+    fn test_binance_diff_sequencing() {
+        let mut book = LocalOrderBook::from_snapshot("BTCUSDT", &snapshot(100));
+
+        // Buffered before the snapshot and fully covered by it
+        assert_eq!(book.apply_binance_diff(90, 100, &[level("100.0", "5")], &[]), BookUpdate::Stale);
+        // First event may start before the snapshot as long as it spans 101
+        assert_eq!(book.apply_binance_diff(95, 103, &[level("100.0", "0")], &[level("100.2", "4")]), BookUpdate::Applied);
+        assert_eq!(book.best_bid(), Some(level("99.5", "2")));
+        assert_eq!(book.best_ask(), Some(level("100.2", "4")));
+
+        // Later events must be contiguous
+        assert_eq!(book.apply_binance_diff(104, 106, &[], &[]), BookUpdate::Applied);
+        assert_eq!(book.apply_binance_diff(108, 110, &[], &[]), BookUpdate::Gap { expected: 107, received: 108 });
+        assert_eq!(book.last_update_id(), 106);
+    }
+
+    #[test]
+    fn test_binance_snapshot_older_than_stream_needs_resync() {
+        let mut book = LocalOrderBook::from_snapshot("BTCUSDT", &snapshot(100));
+        assert_eq!(book.apply_binance_diff(105, 110, &[], &[]), BookUpdate::Gap { expected: 101, received: 105 });
+    }
+
+    #[test]
+    fn test_bybit_delta_sequencing() {
+        let mut book = LocalOrderBook::from_snapshot("ETHUSDT", &snapshot(7));
+
+        assert_eq!(book.apply_bybit_delta(7, &[], &[]), BookUpdate::Stale);
+        assert_eq!(book.apply_bybit_delta(8, &[level("100.2", "1")], &[level("100.5", "0")]), BookUpdate::Applied);
+        assert_eq!(book.best_bid(), Some(level("100.2", "1")));
+        assert_eq!(book.best_ask(), Some(level("101.0", "3")));
+        assert_eq!(book.apply_bybit_delta(10, &[], &[]), BookUpdate::Gap { expected: 9, received: 10 });
+
+        let top = book.to_order_book(2);
+        assert_eq!(top.bids, vec![level("100.2", "1"), level("100.0", "1")]);
+        assert_eq!(top.asks, vec![level("101.0", "3")]);
+    }
+
+    #[test]
+    fn test_store_serves_only_fresh_books() {
+        let store = OrderBookStore::new();
+        store.insert("Bybit", LocalOrderBook::from_snapshot("ETHUSDT", &snapshot(1)));
+
+        assert!(store.fresh("Bybit", "ETHUSDT", 5, Duration::from_secs(5)).is_some());
+        assert!(store.fresh("Binance", "ETHUSDT", 5, Duration::from_secs(5)).is_none());
+        assert_eq!(store.update("Bybit", "ETHUSDT", |book| book.apply_bybit_delta(2, &[], &[])), Some(BookUpdate::Applied));
+
+        let tops = store.fresh_tops(Duration::from_secs(5));
+        assert_eq!(tops.len(), 1);
+        let (exchange, top) = &tops[0];
+        assert_eq!((exchange.as_str(), top.symbol.as_str()), ("Bybit", "ETHUSDT"));
+        assert_eq!((top.bid_price, top.ask_price), (level("100.0", "1").0, level("100.5", "1").0));
+
+        store.remove("Bybit", "ETHUSDT");
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_parse_levels() {
+        let levels = serde_json::json!([["100.5", "0.25"], ["100.4", "0"]]);
+        assert_eq!(parse_levels(Some(&levels)).unwrap(), vec![level("100.5", "0.25"), level("100.4", "0")]);
+        assert!(parse_levels(None).unwrap().is_empty());
+        assert!(parse_levels(Some(&serde_json::json!([["bad"]]))).is_err());
+    }
+}
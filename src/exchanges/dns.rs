@@ -0,0 +1,238 @@
+//! DNS cache for the exchange HTTP clients.
+//!
+//! System lookups add their own latency, and occasionally a failure, in
+//! front of any request that opens a new connection, order placement
+//! included. The cache answers from memory for `dns_cache_seconds` and
+//! keeps serving the last answer when a refresh fails. After every fresh
+//! lookup each address is probed with a TCP connect, so the metrics show
+//! which of an exchange's addresses is closest and which is worth pinning
+//! through `resolve`.
+
+use crate::config::ClientNetworkConfig;
+use crate::metrics::{self, write_metric};
+use dashmap::DashMap;
+use hyper::client::connect::dns::Name;
+use log::warn;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+/// Longest a connect probe may take before the address counts as unreachable.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone)]
+struct CachedLookup {
+    addrs: Vec<SocketAddr>,
+    resolved_at: Instant,
+}
+
+/// Where a lookup was answered from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LookupSource {
+    Cache,
+    Resolver,
+    /// An expired entry, served because the refresh failed
+    Stale,
+}
+
+impl LookupSource {
+    fn label(self) -> &'static str {
+        match self {
+            LookupSource::Cache => "cache",
+            LookupSource::Resolver => "resolver",
+            LookupSource::Stale => "stale",
+        }
+    }
+}
+
+/// Cached lookups and their latencies for one exchange's hosts.
+#[derive(Debug)]
+pub struct DnsCache {
+    exchange: &'static str,
+    ttl: Duration,
+    prefer_fastest: bool,
+    probe_port: u16,
+    entries: DashMap<String, CachedLookup>,
+    lookups: DashMap<(String, LookupSource), u64>,
+    lookup_latency: DashMap<String, Duration>,
+    /// TCP connect time of each resolved address, `None` when unreachable
+    connect_latency: DashMap<(String, IpAddr), Option<Duration>>,
+}
+
+impl DnsCache {
+    pub fn new(exchange: &'static str, network: &ClientNetworkConfig) -> Self {
+        Self {
+            exchange,
+            ttl: Duration::from_secs(network.dns_cache_seconds),
+            prefer_fastest: network.prefer_fastest_address,
+            probe_port: 443,
+            entries: DashMap::new(),
+            lookups: DashMap::new(),
+            lookup_latency: DashMap::new(),
+            connect_latency: DashMap::new(),
+        }
+    }
+
+    /// Port the connect probes go to, 443 unless the endpoints use another.
+    pub fn with_probe_port(mut self, port: u16) -> Self {
+        self.probe_port = port;
+        self
+    }
+
+    /// Addresses of `host`, from the cache while it is fresh. Every fresh
+    /// lookup starts connect probes of the addresses in the background.
+    pub async fn lookup(self: &Arc<Self>, host: &str) -> std::io::Result<Vec<SocketAddr>> {
+        let cached = self.entries.get(host).map(|entry| entry.clone());
+        if let Some(entry) = cached.as_ref().filter(|entry| entry.resolved_at.elapsed() < self.ttl) {
+            self.count(host, LookupSource::Cache);
+            return Ok(self.ordered(host, entry.addrs.clone()));
+        }
+
+        let started = Instant::now();
+        let resolved = tokio::net::lookup_host((host, 0)).await.map(|addrs| addrs.collect::<Vec<_>>());
+        self.lookup_latency.insert(host.to_string(), started.elapsed());
+        match resolved {
+            Ok(addrs) if !addrs.is_empty() => {
+                self.count(host, LookupSource::Resolver);
+                self.entries.insert(host.to_string(), CachedLookup { addrs: addrs.clone(), resolved_at: Instant::now() });
+                let cache = self.clone();
+                let (probed_host, probed) = (host.to_string(), addrs.clone());
+                tokio::spawn(async move { cache.probe(&probed_host, &probed).await });
+                Ok(self.ordered(host, addrs))
+            }
+            result => {
+                let error = result.err().unwrap_or_else(|| std::io::Error::other(format!("no addresses for {}", host)));
+                let Some(entry) = cached else { return Err(error) };
+                warn!("{} DNS lookup of {} failed, keeping the last addresses: {}", self.exchange, host, error);
+                self.count(host, LookupSource::Stale);
+                Ok(self.ordered(host, entry.addrs))
+            }
+        }
+    }
+
+    /// Measures the TCP connect time of each of `addrs`.
+    pub async fn probe(&self, host: &str, addrs: &[SocketAddr]) {
+        for addr in addrs {
+            let target = SocketAddr::new(addr.ip(), self.probe_port);
+            let started = Instant::now();
+            let latency = match tokio::time::timeout(PROBE_TIMEOUT, TcpStream::connect(target)).await {
+                Ok(Ok(_)) => Some(started.elapsed()),
+                _ => None,
+            };
+            self.connect_latency.insert((host.to_string(), addr.ip()), latency);
+        }
+    }
+
+    /// Last measured connect time of `ip` for `host`.
+    pub fn connect_latency(&self, host: &str, ip: IpAddr) -> Option<Duration> {
+        self.connect_latency.get(&(host.to_string(), ip)).and_then(|latency| *latency)
+    }
+
+    /// Lookups of `host` answered from `source`.
+    pub fn lookups(&self, host: &str, source: LookupSource) -> u64 {
+        self.lookups.get(&(host.to_string(), source)).map(|count| *count).unwrap_or(0)
+    }
+
+    fn count(&self, host: &str, source: LookupSource) {
+        *self.lookups.entry((host.to_string(), source)).or_default() += 1;
+    }
+
+    /// `addrs` fastest first when `prefer_fastest_address` is set; addresses
+    /// not yet probed or unreachable go last, in resolver order.
+    fn ordered(&self, host: &str, mut addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+        if self.prefer_fastest {
+            addrs.sort_by_key(|addr| self.connect_latency(host, addr.ip()).unwrap_or(Duration::MAX));
+        }
+        addrs
+    }
+}
+
+/// `reqwest` resolver answering from a shared `DnsCache`.
+pub(crate) struct CachingResolver(pub Arc<DnsCache>);
+
+impl Resolve for CachingResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let cache = self.0.clone();
+        Box::pin(async move {
+            let addrs = cache.lookup(name.as_str()).await?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Prometheus text exposition of the lookups and latencies of `caches`.
+pub fn prometheus_metrics(caches: &[Arc<DnsCache>]) -> String {
+    let mut lookups = Vec::new();
+    let mut lookup_latency = Vec::new();
+    let mut connect_latency = Vec::new();
+    for cache in caches {
+        let exchange = cache.exchange.to_string();
+        lookups.extend(cache.lookups.iter()
+            .map(|e| (vec![exchange.clone(), e.key().0.clone(), e.key().1.label().to_string()], *e.value() as f64)));
+        lookup_latency.extend(cache.lookup_latency.iter()
+            .map(|e| (vec![exchange.clone(), e.key().clone()], e.value().as_secs_f64())));
+        connect_latency.extend(cache.connect_latency.iter()
+            .filter_map(|e| e.value().map(|latency| (vec![exchange.clone(), e.key().0.clone(), e.key().1.to_string()], latency.as_secs_f64()))));
+    }
+
+    let mut out = String::new();
+    for (def, mut samples) in [
+        (&metrics::DNS_LOOKUPS, lookups),
+        (&metrics::DNS_LOOKUP_DURATION, lookup_latency),
+        (&metrics::HOST_CONNECT_DURATION, connect_latency),
+    ] {
+        samples.sort_by(|a, b| a.0.cmp(&b.0));
+        write_metric(&mut out, def, &samples);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    fn network(ttl: u64, prefer_fastest: bool) -> ClientNetworkConfig {
+        ClientNetworkConfig { dns_cache_seconds: ttl, prefer_fastest_address: prefer_fastest, ..ClientNetworkConfig::default() }
+    }
+
+    #[tokio::test]
+    async fn test_lookups_are_cached_until_they_expire() {
+        let cache = Arc::new(DnsCache::new("Binance", &network(300, false)));
+        let first = cache.lookup("localhost").await.unwrap();
+        let second = cache.lookup("localhost").await.unwrap();
+        assert_eq!(first, second);
+        assert_eq!(cache.lookups("localhost", LookupSource::Resolver), 1);
+        assert_eq!(cache.lookups("localhost", LookupSource::Cache), 1);
+
+        let expiring = Arc::new(DnsCache::new("Binance", &network(0, false)));
+        expiring.lookup("localhost").await.unwrap();
+        expiring.lookup("localhost").await.unwrap();
+        assert_eq!(expiring.lookups("localhost", LookupSource::Resolver), 2);
+
+        let text = prometheus_metrics(&[cache]);
+        assert!(text.contains("arbitrage_dns_lookups_total{exchange=\"Binance\",host=\"localhost\",source=\"cache\"} 1"));
+        assert!(text.contains("arbitrage_dns_lookup_seconds{exchange=\"Binance\",host=\"localhost\"}"));
+    }
+
+    #[tokio::test]
+    async fn test_reachable_addresses_are_preferred() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let cache = DnsCache::new("Bybit", &network(300, true)).with_probe_port(port);
+        // Reserved for documentation, never answers
+        let unreachable: SocketAddr = "192.0.2.1:0".parse().unwrap();
+        let local: SocketAddr = "127.0.0.1:0".parse().unwrap();
+
+        cache.probe("api.bybit.com", &[local]).await;
+        cache.connect_latency.insert(("api.bybit.com".to_string(), unreachable.ip()), None);
+
+        assert!(cache.connect_latency("api.bybit.com", local.ip()).is_some());
+        assert_eq!(cache.ordered("api.bybit.com", vec![unreachable, local]), vec![local, unreachable]);
+        let text = prometheus_metrics(&[Arc::new(cache)]);
+        assert!(text.contains("arbitrage_host_connect_seconds{exchange=\"Bybit\",host=\"api.bybit.com\",address=\"127.0.0.1\"}"));
+        assert!(!text.contains("192.0.2.1"));
+    }
+}
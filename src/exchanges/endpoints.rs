@@ -0,0 +1,416 @@
+use super::errors::ErrorCounters;
+use super::fixtures::FixtureRecorder;
+use super::heartbeat::CallStats;
+use super::request_log::RequestLog;
+use crate::secrets::redact_error;
+use futures_util::future::join_all;
+use log::{info, warn};
+use reqwest::{RequestBuilder, Response};
+use serde::Serialize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+
+pub const BINANCE_ENDPOINTS: &[&str] = &[
+    "https://api.binance.com",
+    "https://api1.binance.com",
+    "https://api2.binance.com",
+    "https://api3.binance.com",
+];
+
+pub const BYBIT_ENDPOINTS: &[&str] = &[
+    "https://api.bybit.com",
+    "https://api.bytick.com",
+];
+
+/// A faster host takes over orders only when its latency is below this
+/// share of the current one's, so that noise does not flip them back and forth.
+const ORDER_HOST_SWITCH_RATIO: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy)]
+struct HostStats {
+    consecutive_failures: u32,
+    /// Moving average of request success, 1.0 = always succeeded
+    score: f64,
+    /// Moving average of successful round trips, once there has been one
+    latency_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EndpointHealth {
+    pub url: String,
+    pub active: bool,
+    pub score: f64,
+    pub consecutive_failures: u32,
+    pub latency_ms: Option<f64>,
+    /// Order placement and cancels go here
+    pub orders: bool,
+}
+
+/// Interchangeable base URLs for one exchange. Requests go to the active
+/// host; idempotent requests that fail at the transport level or with a
+/// 5xx are retried on the other hosts, best score first, so a single bad
+/// host never surfaces as a scan error. The active host is rotated out
+/// after `failover_threshold` consecutive failures.
+///
+/// Orders, which are never resent, go to the active host too, unless the
+/// pool is told to prefer the fastest host for them. It then sends them to
+/// the healthy host with the lowest measured latency, while market data
+/// keeps using whichever host is active.
+///
+/// With a `max_in_flight` limit, idempotent requests to a host wait while
+/// that many are already in flight to it. Orders never wait.
+#[derive(Debug)]
+pub struct EndpointPool {
+    exchange: &'static str,
+    urls: Vec<String>,
+    stats: Mutex<Vec<HostStats>>,
+    active: AtomicUsize,
+    failover_threshold: u32,
+    fastest_orders: bool,
+    order: AtomicUsize,
+    /// Per host, empty when requests in flight are not limited
+    host_slots: Vec<Semaphore>,
+    errors: Option<Arc<ErrorCounters>>,
+    calls: Option<Arc<CallStats>>,
+    request_log: Option<Arc<RequestLog>>,
+    fixtures: Option<Arc<FixtureRecorder>>,
+}
+
+impl EndpointPool {
+    pub fn new(exchange: &'static str, urls: Vec<String>, failover_threshold: u32) -> Self {
+        let urls: Vec<String> = urls.into_iter().map(|u| u.trim_end_matches('/').to_string()).collect();
+        let stats = vec![HostStats { consecutive_failures: 0, score: 1.0, latency_ms: None }; urls.len()];
+        Self {
+            exchange,
+            urls,
+            stats: Mutex::new(stats),
+            active: AtomicUsize::new(0),
+            failover_threshold: failover_threshold.max(1),
+            fastest_orders: false,
+            order: AtomicUsize::new(0),
+            host_slots: Vec::new(),
+            errors: None,
+            calls: None,
+            request_log: None,
+            fixtures: None,
+        }
+    }
+    
+    /// Counts every attempt that fails at the transport level in `errors`.
+    pub fn with_error_counters(mut self, errors: Arc<ErrorCounters>) -> Self {
+        self.errors = Some(errors);
+        self
+    }
+    
+    /// Records the outcome and round trip of every attempt in `calls`.
+    pub fn with_call_stats(mut self, calls: Arc<CallStats>) -> Self {
+        self.calls = Some(calls);
+        self
+    }
+    
+    /// Writes every attempt of `send` and `send_once` to `log`.
+    pub fn with_request_log(mut self, log: Arc<RequestLog>) -> Self {
+        self.request_log = Some(log);
+        self
+    }
+    
+    /// Records every answer of `send` and `send_once`, body included, in `fixtures`.
+    pub fn with_fixture_recorder(mut self, fixtures: Arc<FixtureRecorder>) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+    
+    /// Caps the idempotent requests in flight to each host; 0 leaves them
+    /// unlimited.
+    pub fn with_max_in_flight(mut self, limit: usize) -> Self {
+        self.host_slots = match limit {
+            0 => Vec::new(),
+            limit => self.urls.iter().map(|_| Semaphore::new(limit)).collect(),
+        };
+        self
+    }
+    
+    /// Sends orders to the fastest healthy host instead of the active one.
+    pub fn with_fastest_orders(mut self, enabled: bool) -> Self {
+        self.fastest_orders = enabled;
+        self
+    }
+    
+    pub fn active(&self) -> &str {
+        &self.urls[self.active.load(Ordering::Relaxed)]
+    }
+    
+    /// Host that orders are sent to.
+    pub fn order_host(&self) -> &str {
+        &self.urls[self.order_index()]
+    }
+    
+    fn order_index(&self) -> usize {
+        if self.fastest_orders {
+            self.order.load(Ordering::Relaxed)
+        } else {
+            self.active.load(Ordering::Relaxed)
+        }
+    }
+    
+    /// Sends an idempotent request, falling over to the remaining hosts on
+    /// transport errors and 5xx responses. Returns the last host's outcome
+    /// when every host failed.
+    pub async fn send<F>(&self, build: F) -> Result<Response, reqwest::Error>
+    where
+        F: Fn(&str) -> RequestBuilder,
+    {
+        let order = self.attempt_order();
+        let mut outcome = None;
+        
+        for (attempt, index) in order.iter().enumerate() {
+            let _slot = match self.host_slots.get(*index) {
+                Some(slots) => Some(slots.acquire().await.expect("host slots are never closed")),
+                None => None,
+            };
+            let started = Instant::now();
+            let result = self.dispatch(build(&self.urls[*index]), attempt + 1).await;
+            let failed = match &result {
+                Ok(response) => response.status().is_server_error(),
+                Err(e) => {
+                    self.record_transport_error(e);
+                    true
+                }
+            };
+            self.record(*index, (!failed).then(|| started.elapsed()));
+            self.record_call(&result, started);
+            
+            if !failed {
+                return result;
+            }
+            if attempt + 1 < order.len() {
+                warn!("{} request to {} failed, retrying on {}", self.exchange, self.urls[*index], self.urls[order[attempt + 1]]);
+            }
+            outcome = Some(result);
+        }
+        
+        outcome.expect("endpoint pool is never empty")
+    }
+    
+    /// Sends a non-idempotent request (order placement) to the order host
+    /// only, recording the outcome for health scoring.
+    pub async fn send_once(&self, build: impl FnOnce(&str) -> RequestBuilder) -> Result<Response, reqwest::Error> {
+        let index = self.order_index();
+        let started = Instant::now();
+        let result = self.dispatch(build(&self.urls[index]), 1).await;
+        if let Err(e) = &result {
+            self.record_transport_error(e);
+        }
+        let ok = matches!(&result, Ok(response) if !response.status().is_server_error());
+        self.record(index, ok.then(|| started.elapsed()));
+        self.record_call(&result, started);
+        result
+    }
+    
+    /// Sends `request`, logging it as the `host_attempt`th host tried when
+    /// there is a request log and recording its answer when there is a
+    /// fixture recorder. Errors lose the query string of their URL, which
+    /// holds the signature of signed requests.
+    async fn dispatch(&self, request: RequestBuilder, host_attempt: usize) -> Result<Response, reqwest::Error> {
+        if self.request_log.is_none() && self.fixtures.is_none() {
+            return request.send().await.map_err(redact_error);
+        }
+        let (client, request) = request.build_split();
+        let request = request.map_err(redact_error)?;
+        let (method, url) = (request.method().clone(), request.url().clone());
+        let started = Instant::now();
+        let result = client.execute(request).await.map_err(redact_error);
+        let latency = started.elapsed();
+        let result = match (result, &self.fixtures) {
+            (Ok(response), Some(fixtures)) => fixtures.record_response(self.exchange, &method, &url, response).await.map_err(redact_error),
+            (result, _) => result,
+        };
+        if let Some(log) = &self.request_log {
+            log.record(self.exchange, &method, &url, &result, latency, host_attempt);
+        }
+        result
+    }
+    
+    /// Sends the request `build` makes to every host at once to measure
+    /// their latency, then picks the order host again. Returns how many
+    /// hosts answered.
+    pub async fn probe<F>(&self, build: F) -> usize
+    where
+        F: Fn(&str) -> RequestBuilder,
+    {
+        let results = join_all(self.urls.iter().map(|url| {
+            let request = build(url);
+            async move {
+                let started = Instant::now();
+                let response = request.send().await.ok().filter(|r| !r.status().is_server_error())?;
+                let elapsed = started.elapsed();
+                // Reading the body hands the connection back to the pool
+                response.bytes().await.ok()?;
+                Some(elapsed)
+            }
+        })).await;
+        let answered = results.iter().filter(|r| r.is_some()).count();
+        for (index, latency) in results.into_iter().enumerate() {
+            self.record(index, latency);
+        }
+        answered
+    }
+    
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        let active = self.active.load(Ordering::Relaxed);
+        let order = self.order_index();
+        let stats = self.stats.lock().unwrap();
+        self.urls.iter().zip(stats.iter()).enumerate()
+            .map(|(i, (url, s))| EndpointHealth {
+                url: url.clone(),
+                active: i == active,
+                score: s.score,
+                consecutive_failures: s.consecutive_failures,
+                latency_ms: s.latency_ms,
+                orders: i == order,
+            })
+            .collect()
+    }
+    
+    /// Active host first, then the others by descending score.
+    fn attempt_order(&self) -> Vec<usize> {
+        let active = self.active.load(Ordering::Relaxed);
+        let stats = self.stats.lock().unwrap();
+        let mut others: Vec<usize> = (0..self.urls.len()).filter(|i| *i != active).collect();
+        others.sort_by(|a, b| stats[*b].score.total_cmp(&stats[*a].score));
+        std::iter::once(active).chain(others).collect()
+    }
+    
+    fn record_transport_error(&self, error: &reqwest::Error) {
+        if let Some(errors) = &self.errors {
+            errors.record_transport(error.url().map(|url| url.path()).unwrap_or_default());
+        }
+    }
+    
+    fn record_call(&self, result: &Result<Response, reqwest::Error>, started: Instant) {
+        let Some(calls) = &self.calls else { return };
+        match result {
+            Ok(response) if !response.status().is_server_error() => {
+                calls.record_answer(started.elapsed(), response.status().is_success());
+            }
+            _ => calls.record_failure(),
+        }
+    }
+    
+    /// Records a request to host `index`: its round trip when it
+    /// succeeded, `None` when it failed.
+    fn record(&self, index: usize, latency: Option<Duration>) {
+        let mut stats = self.stats.lock().unwrap();
+        let host = &mut stats[index];
+        let success = latency.is_some();
+        host.score = host.score * 0.7 + if success { 0.3 } else { 0.0 };
+        host.consecutive_failures = if success { 0 } else { host.consecutive_failures + 1 };
+        if let Some(latency) = latency {
+            let sample = latency.as_secs_f64() * 1000.0;
+            host.latency_ms = Some(host.latency_ms.map_or(sample, |average| average * 0.7 + sample * 0.3));
+        }
+        
+        if self.fastest_orders {
+            self.select_order_host(&stats);
+        }
+        let active = self.active.load(Ordering::Relaxed);
+        if index != active || stats[index].consecutive_failures < self.failover_threshold {
+            return;
+        }
+        
+        let best = (0..self.urls.len())
+            .filter(|i| *i != active)
+            .max_by(|a, b| stats[*a].score.total_cmp(&stats[*b].score));
+        if let Some(next) = best {
+            warn!("{} endpoint {} failed {} times in a row, switching to {}",
+                  self.exchange, self.urls[active], stats[active].consecutive_failures, self.urls[next]);
+            self.active.store(next, Ordering::Relaxed);
+        }
+    }
+    
+    /// Moves orders to the fastest healthy host when it is clearly faster
+    /// than the current one, or when the current one has failed.
+    fn select_order_host(&self, stats: &[HostStats]) {
+        let healthy = |i: usize| stats[i].consecutive_failures < self.failover_threshold;
+        let current = self.order.load(Ordering::Relaxed);
+        let fastest = (0..self.urls.len())
+            .filter(|i| healthy(*i))
+            .filter_map(|i| Some((i, stats[i].latency_ms?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1));
+        let next = match (fastest, stats[current].latency_ms) {
+            (Some((best, _)), _) if !healthy(current) => best,
+            (Some((best, best_ms)), Some(current_ms)) if best_ms < current_ms * ORDER_HOST_SWITCH_RATIO => best,
+            (Some((best, _)), None) => best,
+            (None, _) if !healthy(current) => self.active.load(Ordering::Relaxed),
+            _ => return,
+        };
+        if next != current {
+            info!("{} orders now go to {} ({})", self.exchange, self.urls[next],
+                  stats[next].latency_ms.map_or("no latency yet".to_string(), |ms| format!("{:.0} ms", ms)));
+            self.order.store(next, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn pool() -> EndpointPool {
+        EndpointPool::new("Binance", BINANCE_ENDPOINTS.iter().map(|s| s.to_string()).collect(), 2)
+    }
+    
+    fn ms(millis: u64) -> Duration {
+        Duration::from_millis(millis)
+    }
+    
+    #[test]
+    fn test_rotates_after_consecutive_failures() {
+        let pool = pool();
+        assert_eq!(pool.active(), "https://api.binance.com");
+        
+        pool.record(0, None);
+        assert_eq!(pool.active(), "https://api.binance.com");
+        pool.record(0, Some(ms(40)));
+        pool.record(0, None);
+        assert_eq!(pool.active(), "https://api.binance.com");
+        pool.record(0, None);
+        assert_ne!(pool.active(), "https://api.binance.com");
+    }
+    
+    #[test]
+    fn test_attempt_order_prefers_healthy_hosts() {
+        let pool = pool();
+        pool.record(1, None);
+        pool.record(2, Some(ms(40)));
+        
+        let order = pool.attempt_order();
+        assert_eq!(order[0], 0);
+        assert_eq!(*order.last().unwrap(), 1);
+        assert!(pool.health()[0].active);
+    }
+    
+    #[test]
+    fn test_orders_follow_the_fastest_healthy_host() {
+        let pool = pool().with_fastest_orders(true);
+        pool.record(0, Some(ms(40)));
+        pool.record(1, Some(ms(35)));
+        // Not enough faster to move orders
+        assert_eq!(pool.order_host(), "https://api.binance.com");
+        pool.record(2, Some(ms(10)));
+        assert_eq!(pool.order_host(), "https://api2.binance.com");
+        assert_eq!(pool.active(), "https://api.binance.com");
+        
+        pool.record(2, None);
+        pool.record(2, None);
+        assert_eq!(pool.order_host(), "https://api1.binance.com");
+        assert!(pool.health()[1].orders);
+        
+        // Without the preference orders stay with market data
+        let pool = self::pool();
+        pool.record(2, Some(ms(10)));
+        assert_eq!(pool.order_host(), pool.active());
+    }
+}
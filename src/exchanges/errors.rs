@@ -0,0 +1,172 @@
+//! Error counters of an exchange client's requests.
+//!
+//! The scan loop only knows how many scans failed in a row; these counters
+//! say where the failures came from. Each failure is counted by the
+//! endpoint it hit and its kind: a transport error, a body that would not
+//! parse, an error returned by the API (labelled with its code, or the
+//! HTTP status when the body carries none) or a rate limit. A transport
+//! error counts once per host tried, so a read that failed over shows up
+//! even when the next host answered.
+
+use crate::metrics::{self, write_metric};
+use super::heartbeat::CallStats;
+use super::ExchangeError;
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    Network,
+    Parse,
+    Api,
+    RateLimit,
+}
+
+impl ErrorKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            ErrorKind::Network => "network",
+            ErrorKind::Parse => "parse",
+            ErrorKind::Api => "api",
+            ErrorKind::RateLimit => "rate_limit",
+        }
+    }
+
+    /// Kind and code of `error`, or `None` for errors raised before a
+    /// request went out, such as missing credentials.
+    fn of(error: &ExchangeError) -> Option<(ErrorKind, String)> {
+        match error {
+            ExchangeError::NetworkError(_) => Some((ErrorKind::Network, String::new())),
+            ExchangeError::ParseError(_) => Some((ErrorKind::Parse, String::new())),
+            ExchangeError::ApiError { code, status, .. } => {
+                let code = code.map(|c| c.to_string()).or_else(|| status.map(|s| s.to_string())).unwrap_or_default();
+                Some((ErrorKind::Api, code))
+            }
+            ExchangeError::RateLimitError(_) => Some((ErrorKind::RateLimit, String::new())),
+            _ => None,
+        }
+    }
+}
+
+/// How often one endpoint failed one way.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ErrorCount {
+    pub exchange: String,
+    pub endpoint: String,
+    pub kind: ErrorKind,
+    /// API error code or HTTP status; empty for other kinds
+    pub code: String,
+    pub count: u64,
+}
+
+#[derive(Debug)]
+pub struct ErrorCounters {
+    exchange: &'static str,
+    counts: DashMap<(String, ErrorKind, String), u64>,
+    calls: Option<Arc<CallStats>>,
+}
+
+impl ErrorCounters {
+    pub fn new(exchange: &'static str) -> Self {
+        Self { exchange, counts: DashMap::new(), calls: None }
+    }
+
+    /// Also counts every error found in an answer against the error rate
+    /// of `calls`. Transport errors are left to the endpoint pool, which
+    /// records those attempts itself.
+    pub fn with_call_stats(mut self, calls: Arc<CallStats>) -> Self {
+        self.calls = Some(calls);
+        self
+    }
+
+    pub fn exchange(&self) -> &'static str {
+        self.exchange
+    }
+
+    /// Counts `error` against `endpoint`, the request path without its query.
+    pub fn record(&self, endpoint: &str, error: &ExchangeError) {
+        if let Some((kind, code)) = ErrorKind::of(error) {
+            *self.counts.entry((endpoint.to_string(), kind, code)).or_insert(0) += 1;
+            if let Some(calls) = &self.calls {
+                calls.record_rejection();
+            }
+        }
+    }
+
+    /// Counts a request to `endpoint` that failed before any response came back.
+    pub fn record_transport(&self, endpoint: &str) {
+        *self.counts.entry((endpoint.to_string(), ErrorKind::Network, String::new())).or_insert(0) += 1;
+    }
+
+    /// Every count, sorted by endpoint, kind and code.
+    pub fn counts(&self) -> Vec<ErrorCount> {
+        let mut counts: Vec<ErrorCount> = self.counts.iter()
+            .map(|entry| {
+                let (endpoint, kind, code) = entry.key().clone();
+                ErrorCount { exchange: self.exchange.to_string(), endpoint, kind, code, count: *entry.value() }
+            })
+            .collect();
+        counts.sort_by(|a, b| (&a.endpoint, a.kind, &a.code).cmp(&(&b.endpoint, b.kind, &b.code)));
+        counts
+    }
+
+    /// Errors of every endpoint and kind.
+    pub fn total(&self) -> u64 {
+        self.counts.iter().map(|entry| *entry.value()).sum()
+    }
+}
+
+/// Prometheus text exposition of the counts of `counters`.
+pub fn prometheus_metrics(counters: &[Arc<ErrorCounters>]) -> String {
+    let samples: Vec<(Vec<String>, f64)> = counters.iter()
+        .flat_map(|counters| counters.counts())
+        .map(|c| (vec![c.exchange, c.endpoint, c.kind.label().to_string(), c.code], c.count as f64))
+        .collect();
+    let mut out = String::new();
+    write_metric(&mut out, &metrics::EXCHANGE_ERRORS, &samples);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api_error(code: Option<i64>, status: Option<u16>) -> ExchangeError {
+        ExchangeError::ApiError {
+            exchange: "Binance".to_string(),
+            endpoint: "/api/v3/order".to_string(),
+            status,
+            code,
+            message: "rejected".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_errors_are_counted_by_endpoint_kind_and_code() {
+        let counters = ErrorCounters::new("Binance");
+        counters.record("/api/v3/ticker/price", &ExchangeError::NetworkError("timed out".to_string()));
+        counters.record("/api/v3/ticker/price", &ExchangeError::NetworkError("reset".to_string()));
+        counters.record("/api/v3/order", &api_error(Some(-2010), Some(400)));
+        counters.record("/api/v3/order", &api_error(None, Some(503)));
+        counters.record("/api/v3/exchangeInfo", &ExchangeError::ParseError("bad json".to_string()));
+        counters.record("/api/v3/account", &ExchangeError::MissingCredentials("no key".to_string()));
+
+        let counts = counters.counts();
+        let summary: Vec<(&str, ErrorKind, &str, u64)> = counts.iter()
+            .map(|c| (c.endpoint.as_str(), c.kind, c.code.as_str(), c.count))
+            .collect();
+        assert_eq!(summary, [
+            ("/api/v3/exchangeInfo", ErrorKind::Parse, "", 1),
+            ("/api/v3/order", ErrorKind::Api, "-2010", 1),
+            ("/api/v3/order", ErrorKind::Api, "503", 1),
+            ("/api/v3/ticker/price", ErrorKind::Network, "", 2),
+        ]);
+        assert_eq!(counters.total(), 5);
+
+        let metrics = prometheus_metrics(&[Arc::new(counters)]);
+        assert!(metrics.contains(r#"arbitrage_exchange_errors_total{exchange="Binance",endpoint="/api/v3/order",kind="api",code="-2010"} 1"#));
+        assert!(metrics.contains(r#"arbitrage_exchange_errors_total{exchange="Binance",endpoint="/api/v3/ticker/price",kind="network",code=""} 2"#));
+    }
+}
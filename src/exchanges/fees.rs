@@ -0,0 +1,127 @@
+use super::TradingFees;
+use crate::config::{FeeConfig, VenueFeeConfig};
+use dashmap::DashMap;
+use log::info;
+use rust_decimal::Decimal;
+use serde::Serialize;
+
+/// Fee rates of the account behind an API key, as reported by the exchange.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AccountFeeRates {
+    pub maker_fee: Decimal,
+    pub taker_fee: Decimal,
+    /// Share of the fee still charged when it is paid in BNB; `None` unless
+    /// the account pays fees that way
+    pub bnb_fee_multiplier: Option<Decimal>,
+}
+
+/// Fee schedule of one venue.
+#[derive(Debug, Clone, Default)]
+pub struct VenueFees {
+    pub rates: TradingFees,
+    /// Share of the fee charged when paid in BNB; `None` when fees are
+    /// deducted from the asset each fill receives
+    pub bnb_fee_multiplier: Option<Decimal>,
+}
+
+impl VenueFees {
+    pub fn from_config(config: &VenueFeeConfig) -> Self {
+        Self {
+            rates: TradingFees { maker_fee: config.maker_fee, taker_fee: config.taker_fee, ..TradingFees::default() },
+            bnb_fee_multiplier: config.pay_in_bnb
+                .then(|| Decimal::ONE - config.bnb_discount_percentage / Decimal::ONE_HUNDRED),
+        }
+    }
+
+    /// Taker rate after any BNB discount.
+    pub fn taker_fee(&self) -> Decimal {
+        self.rates.taker_fee * self.bnb_fee_multiplier.unwrap_or(Decimal::ONE)
+    }
+
+    /// Maker rate after any BNB discount.
+    pub fn maker_fee(&self) -> Decimal {
+        self.rates.maker_fee * self.bnb_fee_multiplier.unwrap_or(Decimal::ONE)
+    }
+
+    /// Share of a taker fill's proceeds left to trade on: all of it when
+    /// fees come out of the BNB balance, less the fee otherwise.
+    pub fn received_factor(&self) -> Decimal {
+        match self.bnb_fee_multiplier {
+            Some(_) => Decimal::ONE,
+            None => Decimal::ONE - self.taker_fee(),
+        }
+    }
+}
+
+/// Fee schedules by exchange: the configured rates until the account's
+/// tier rates replace them.
+#[derive(Debug)]
+pub struct FeeModel {
+    venues: DashMap<String, VenueFees>,
+}
+
+impl FeeModel {
+    pub fn from_config(config: &FeeConfig) -> Self {
+        let venues = DashMap::new();
+        venues.insert("Binance".to_string(), VenueFees::from_config(&config.binance));
+        venues.insert("Bybit".to_string(), VenueFees::from_config(&config.bybit));
+        Self { venues }
+    }
+
+    /// Schedule of `exchange`, or the default one for venues without a configuration.
+    pub fn venue(&self, exchange: &str) -> VenueFees {
+        self.venues.get(exchange).map(|v| v.clone()).unwrap_or_default()
+    }
+
+    pub fn taker_fee(&self, exchange: &str) -> Decimal {
+        self.venue(exchange).taker_fee()
+    }
+
+    pub fn received_factor(&self, exchange: &str) -> Decimal {
+        self.venue(exchange).received_factor()
+    }
+
+    /// Replaces `exchange`'s rates with the account's. The account also
+    /// decides whether fees are paid in BNB.
+    pub fn apply_account_rates(&self, exchange: &str, account: &AccountFeeRates) {
+        let mut venue = self.venues.entry(exchange.to_string()).or_default();
+        venue.rates.maker_fee = account.maker_fee;
+        venue.rates.taker_fee = account.taker_fee;
+        venue.bnb_fee_multiplier = account.bnb_fee_multiplier;
+        info!("{} account fees: maker {}%, taker {}%{}", exchange,
+              account.maker_fee * Decimal::ONE_HUNDRED, account.taker_fee * Decimal::ONE_HUNDRED,
+              account.bnb_fee_multiplier.map(|m| format!(", paid in BNB at {}% of the rate", m * Decimal::ONE_HUNDRED)).unwrap_or_default());
+    }
+}
+
+impl Default for FeeModel {
+    fn default() -> Self {
+        Self::from_config(&FeeConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+
+    #[test]
+    fn test_bnb_fees_are_discounted_and_leave_proceeds_whole() {
+        let mut config = FeeConfig::default();
+        config.binance.pay_in_bnb = true;
+        let model = FeeModel::from_config(&config);
+
+        assert_eq!(model.taker_fee("Binance"), dec("0.00075"));
+        assert_eq!(model.received_factor("Binance"), Decimal::ONE);
+        assert_eq!(model.taker_fee("Bybit"), dec("0.001"));
+        assert_eq!(model.received_factor("Bybit"), dec("0.999"));
+
+        // A VIP tier without BNB payment reported by the account
+        model.apply_account_rates("Binance", &AccountFeeRates { maker_fee: dec("0.0009"), taker_fee: dec("0.0008"), bnb_fee_multiplier: None });
+        assert_eq!(model.taker_fee("Binance"), dec("0.0008"));
+        assert_eq!(model.received_factor("Binance"), dec("0.9992"));
+    }
+}
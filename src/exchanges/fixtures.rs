@@ -0,0 +1,406 @@
+//! Recorded exchange sessions, for driving the bot offline.
+//!
+//! A `FixtureRecorder` writes one JSON line per answer the endpoint pools
+//! receive, body included, and one per message the depth streams read.
+//! `ReplaySession` loads such a file and `ReplayServer` serves it back from
+//! localhost: a REST request gets the recorded answers for its method, path
+//! and query in the order they were recorded, and every websocket
+//! connection is sent the messages recorded on its path. `ReplayServer::apply`
+//! points a config at the server, so the scanner, engine and depth streams
+//! run against a past session without network access.
+
+use crate::config::{Config, FixtureConfig};
+use crate::secrets::redact_query;
+use anyhow::{Context, Result};
+use futures_util::{SinkExt, StreamExt};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Server};
+use log::warn;
+use reqwest::{Method, Response, Url};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio_tungstenite::tungstenite::handshake::server::{Callback, ErrorResponse, Request as WsRequest, Response as WsResponse};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+/// Query parameters that change on every request and are ignored when
+/// matching a request to its recorded answer.
+const VOLATILE_PARAMS: &[&str] = &["timestamp", "signature", "recvWindow"];
+
+/// Path the Bybit depth stream is served on when the session recorded none.
+const DEFAULT_BYBIT_STREAM_PATH: &str = "/v5/public/spot";
+
+/// One recorded answer or stream message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FixtureEntry {
+    Rest {
+        exchange: String,
+        method: String,
+        path: String,
+        /// With secrets masked
+        query: Option<String>,
+        status: u16,
+        body: String,
+    },
+    Ws {
+        exchange: String,
+        path: String,
+        message: String,
+    },
+}
+
+#[derive(Debug)]
+struct FixtureFile {
+    file: File,
+    size: u64,
+    full: bool,
+}
+
+/// Appends the exchanges' answers to a fixture file until it reaches its
+/// size limit.
+#[derive(Debug)]
+pub struct FixtureRecorder {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Mutex<FixtureFile>,
+}
+
+impl FixtureRecorder {
+    /// Starts a new recording at `config.path`, replacing any earlier one.
+    pub fn create(config: &FixtureConfig) -> Result<Self> {
+        let path = PathBuf::from(&config.path);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let file = File::create(&path).with_context(|| format!("Failed to create fixture file {}", path.display()))?;
+        Ok(Self {
+            path,
+            max_bytes: config.max_file_mb * 1024 * 1024,
+            file: Mutex::new(FixtureFile { file, size: 0, full: false }),
+        })
+    }
+
+    /// Records `response` to a request for `url` and hands back an
+    /// equivalent one, since recording reads its body.
+    pub async fn record_response(&self, exchange: &str, method: &Method, url: &Url, response: Response) -> Result<Response, reqwest::Error> {
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        self.write(&FixtureEntry::Rest {
+            exchange: exchange.to_string(),
+            method: method.to_string(),
+            path: url.path().to_string(),
+            query: url.query().map(redact_query),
+            status: status.as_u16(),
+            body: String::from_utf8_lossy(&body).into_owned(),
+        });
+
+        let mut replayed = hyper::http::Response::new(body);
+        *replayed.status_mut() = status;
+        *replayed.headers_mut() = headers;
+        Ok(Response::from(replayed))
+    }
+
+    /// Records a text message read from the stream at `url`.
+    pub fn record_message(&self, exchange: &str, url: &str, message: &str) {
+        let path = Url::parse(url).map(|url| url.path().to_string()).unwrap_or_default();
+        self.write(&FixtureEntry::Ws { exchange: exchange.to_string(), path, message: message.to_string() });
+    }
+
+    fn write(&self, entry: &FixtureEntry) {
+        let Ok(line) = serde_json::to_string(entry) else { return };
+        let mut file = self.file.lock().unwrap();
+        if file.full {
+            return;
+        }
+        let len = line.len() as u64 + 1;
+        if file.size + len > self.max_bytes {
+            warn!("Fixture file {} reached its size limit, recording stopped", self.path.display());
+            file.full = true;
+            return;
+        }
+        match writeln!(file.file, "{}", line) {
+            Ok(()) => file.size += len,
+            Err(e) => warn!("Failed to write fixture file {}: {}", self.path.display(), e),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RecordedAnswer {
+    query: String,
+    status: u16,
+    body: String,
+}
+
+/// A recorded session, indexed for replay.
+#[derive(Debug, Default)]
+pub struct ReplaySession {
+    /// By method and path, in recorded order
+    answers: HashMap<(String, String), Vec<RecordedAnswer>>,
+    /// Stream messages by path, in recorded order
+    messages: HashMap<String, Vec<String>>,
+    bybit_stream_path: Option<String>,
+    /// Answers served so far, by method, path and matched query
+    served: Mutex<HashMap<String, usize>>,
+}
+
+impl ReplaySession {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read fixture file {}", path.display()))?;
+        let mut entries = Vec::new();
+        for (index, line) in text.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            entries.push(serde_json::from_str(line)
+                .with_context(|| format!("Invalid fixture entry at {}:{}", path.display(), index + 1))?);
+        }
+        Ok(Self::from_entries(entries))
+    }
+
+    pub fn from_entries(entries: Vec<FixtureEntry>) -> Self {
+        let mut session = Self::default();
+        for entry in entries {
+            match entry {
+                FixtureEntry::Rest { method, path, query, status, body, .. } => {
+                    let query = normalize_query(query.as_deref().unwrap_or_default());
+                    session.answers.entry((method, path)).or_default().push(RecordedAnswer { query, status, body });
+                }
+                FixtureEntry::Ws { exchange, path, message } => {
+                    if exchange == "Bybit" {
+                        session.bybit_stream_path.get_or_insert_with(|| path.clone());
+                    }
+                    session.messages.entry(path).or_default().push(message);
+                }
+            }
+        }
+        session
+    }
+
+    /// The next recorded answer to a request, preferring those recorded for
+    /// the same query and falling back to any for the same path. Once they
+    /// run out the last one keeps being served, so polling carries on.
+    pub fn answer(&self, method: &str, path: &str, query: &str) -> Option<(u16, String)> {
+        let answers = self.answers.get(&(method.to_string(), path.to_string()))?;
+        let query = normalize_query(query);
+        let exact: Vec<&RecordedAnswer> = answers.iter().filter(|answer| answer.query == query).collect();
+        let (candidates, key) = if exact.is_empty() {
+            (answers.iter().collect::<Vec<_>>(), format!("{} {}", method, path))
+        } else {
+            (exact, format!("{} {}?{}", method, path, query))
+        };
+        let mut served = self.served.lock().unwrap();
+        let count = served.entry(key).or_default();
+        let answer = candidates[(*count).min(candidates.len() - 1)];
+        *count += 1;
+        Some((answer.status, answer.body.clone()))
+    }
+
+    /// Messages recorded on the stream at `path`.
+    pub fn messages(&self, path: &str) -> &[String] {
+        self.messages.get(path).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// The recorded query without the parameters that change per request.
+fn normalize_query(query: &str) -> String {
+    query.split('&')
+        .filter(|pair| !pair.is_empty())
+        .filter(|pair| {
+            let key = pair.split_once('=').map_or(*pair, |(key, _)| key);
+            !VOLATILE_PARAMS.iter().any(|p| key.eq_ignore_ascii_case(p))
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// Serves a `ReplaySession` over HTTP and websockets on localhost until
+/// dropped.
+#[derive(Debug)]
+pub struct ReplayServer {
+    url: String,
+    ws_url: String,
+    bybit_stream_path: String,
+    cancel: CancellationToken,
+}
+
+impl ReplayServer {
+    pub fn start(session: ReplaySession) -> Result<Self> {
+        let session = Arc::new(session);
+        let cancel = CancellationToken::new();
+        let bybit_stream_path = session.bybit_stream_path.clone().unwrap_or_else(|| DEFAULT_BYBIT_STREAM_PATH.to_string());
+
+        let rest = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let url = format!("http://{}", rest.local_addr()?);
+        let answers = session.clone();
+        let make_service = make_service_fn(move |_| {
+            let session = answers.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let session = session.clone();
+                    async move { Ok::<_, Infallible>(replay_answer(&session, &request)) }
+                }))
+            }
+        });
+        let server = Server::from_tcp(rest)?
+            .serve(make_service)
+            .with_graceful_shutdown(cancel.clone().cancelled_owned());
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                warn!("Fixture replay server stopped: {}", e);
+            }
+        });
+
+        let ws = std::net::TcpListener::bind("127.0.0.1:0")?;
+        ws.set_nonblocking(true)?;
+        let ws_url = format!("ws://{}", ws.local_addr()?);
+        let ws = tokio::net::TcpListener::from_std(ws)?;
+        let stopped = cancel.clone();
+        tokio::spawn(async move {
+            loop {
+                let stream = tokio::select! {
+                    _ = stopped.cancelled() => return,
+                    accepted = ws.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(_) => continue,
+                    },
+                };
+                tokio::spawn(replay_stream(session.clone(), stream, stopped.clone()));
+            }
+        });
+
+        Ok(Self { url, ws_url, bybit_stream_path, cancel })
+    }
+
+    /// Base URL of the REST answers.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Base URL of the streams.
+    pub fn ws_url(&self) -> &str {
+        &self.ws_url
+    }
+
+    /// Sends both exchanges' REST requests and depth streams in `config`
+    /// to the server.
+    pub fn apply(&self, config: &mut Config) {
+        for network in [&mut config.exchanges.binance_network, &mut config.exchanges.bybit_network] {
+            network.endpoints = vec![self.url.clone()];
+            network.use_env_proxy = false;
+            network.proxy_url = None;
+        }
+        config.order_books.binance_stream_url = self.ws_url.clone();
+        config.order_books.bybit_stream_url = format!("{}{}", self.ws_url, self.bybit_stream_path);
+    }
+}
+
+impl Drop for ReplayServer {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+fn replay_answer(session: &ReplaySession, request: &Request<Body>) -> hyper::Response<Body> {
+    let (method, path) = (request.method().as_str(), request.uri().path());
+    let (status, body) = session.answer(method, path, request.uri().query().unwrap_or_default())
+        .unwrap_or_else(|| (404, format!(r#"{{"code":-1,"msg":"no recorded answer for {} {}"}}"#, method, path)));
+    hyper::Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .unwrap_or_else(|_| hyper::Response::new(Body::empty()))
+}
+
+/// Sends the messages recorded on the connection's path, then holds it
+/// open, ignoring what the client sends, until the client leaves.
+async fn replay_stream(session: Arc<ReplaySession>, stream: tokio::net::TcpStream, cancel: CancellationToken) {
+    let mut path = String::new();
+    let Ok(mut ws) = tokio_tungstenite::accept_hdr_async(stream, RequestedPath(&mut path)).await else { return };
+    for message in session.messages(&path) {
+        if ws.send(Message::Text(message.clone())).await.is_err() {
+            return;
+        }
+    }
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            message = ws.next() => if !matches!(message, Some(Ok(_))) {
+                return;
+            },
+        }
+    }
+}
+
+/// Keeps the path a websocket client asked for in its handshake.
+struct RequestedPath<'a>(&'a mut String);
+
+impl Callback for RequestedPath<'_> {
+    fn on_request(self, request: &WsRequest, response: WsResponse) -> Result<WsResponse, ErrorResponse> {
+        *self.0 = request.uri().path().to_string();
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rest(path: &str, query: Option<&str>, body: &str) -> FixtureEntry {
+        FixtureEntry::Rest {
+            exchange: "Binance".to_string(),
+            method: "GET".to_string(),
+            path: path.to_string(),
+            query: query.map(str::to_string),
+            status: 200,
+            body: body.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_answers_match_the_query_and_repeat_the_last() {
+        let session = ReplaySession::from_entries(vec![
+            rest("/api/v3/ticker/price", None, "first"),
+            rest("/api/v3/ticker/price", None, "second"),
+            rest("/api/v3/order", Some("symbol=BTCUSDT&timestamp=1&signature=<redacted>"), "btc"),
+            rest("/api/v3/order", Some("symbol=ETHUSDT&timestamp=2&signature=<redacted>"), "eth"),
+        ]);
+        let answer = |path: &str, query: &str| session.answer("GET", path, query).map(|(_, body)| body);
+
+        assert_eq!(answer("/api/v3/ticker/price", ""), Some("first".to_string()));
+        assert_eq!(answer("/api/v3/ticker/price", ""), Some("second".to_string()));
+        assert_eq!(answer("/api/v3/ticker/price", ""), Some("second".to_string()));
+        // The signature and timestamp of the replayed request differ
+        assert_eq!(answer("/api/v3/order", "symbol=ETHUSDT&timestamp=9&signature=ab12"), Some("eth".to_string()));
+        // No answer for the query: any for the path, in order
+        assert_eq!(answer("/api/v3/order", "symbol=SOLUSDT"), Some("btc".to_string()));
+        assert_eq!(answer("/api/v3/depth", ""), None);
+    }
+
+    #[tokio::test]
+    async fn test_recorded_stream_messages_are_replayed_on_their_path() {
+        let session = ReplaySession::from_entries(vec![
+            FixtureEntry::Ws { exchange: "Bybit".to_string(), path: "/v5/public/spot".to_string(), message: "one".to_string() },
+            FixtureEntry::Ws { exchange: "Bybit".to_string(), path: "/v5/public/spot".to_string(), message: "two".to_string() },
+        ]);
+        let server = ReplayServer::start(session).unwrap();
+        let mut config = Config::default();
+        server.apply(&mut config);
+        assert_eq!(config.exchanges.binance_network.endpoints, [server.url()]);
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(config.order_books.bybit_stream_url.as_str()).await.unwrap();
+        ws.send(Message::Text(r#"{"op":"subscribe"}"#.to_string())).await.unwrap();
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            if let Some(Ok(Message::Text(text))) = ws.next().await {
+                received.push(text);
+            }
+        }
+        assert_eq!(received, ["one", "two"]);
+    }
+}
@@ -0,0 +1,162 @@
+//! Rolling request statistics of an exchange client, for supervision.
+//!
+//! The endpoint pool records every attempt it sends: its round trip when
+//! an answer came back, or a failure when the host could not be reached or
+//! answered with a 5xx. The client's error counters add the API and parse
+//! errors found in answers. `health` turns the last five minutes of this
+//! into an `ExchangeHealth`, which embedding applications can poll to
+//! decide for themselves when an exchange is down.
+
+use crate::diag::LatencyStats;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How far back the error rate and latencies look.
+pub const WINDOW: Duration = Duration::from_secs(300);
+
+/// Samples kept at most, so a busy poller cannot grow the window unbounded.
+const MAX_SAMPLES: usize = 10_000;
+
+/// Point-in-time health of one exchange client.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ExchangeHealth {
+    pub exchange: String,
+    /// Last request the exchange answered with a 2xx status
+    pub last_success: Option<DateTime<Utc>>,
+    /// Requests sent over the last five minutes, each host tried counting once
+    pub requests: u64,
+    /// Share of those requests that failed, from 0 to 1
+    pub error_rate: f64,
+    /// Round trips of the answered requests; `None` before the first answer
+    pub latency: Option<LatencyStats>,
+    /// Whether the trade websocket is connected; `None` when it is disabled
+    pub ws_connected: Option<bool>,
+}
+
+#[derive(Debug)]
+enum Sample {
+    Answered(Duration),
+    Failed,
+    /// An error found in an answer that was already counted
+    Rejected,
+}
+
+#[derive(Debug, Default)]
+struct Window {
+    samples: VecDeque<(Instant, Sample)>,
+    last_success: Option<DateTime<Utc>>,
+}
+
+/// Request outcomes of one client over the last `WINDOW`.
+#[derive(Debug)]
+pub struct CallStats {
+    exchange: &'static str,
+    window: Duration,
+    state: Mutex<Window>,
+}
+
+impl CallStats {
+    pub fn new(exchange: &'static str) -> Self {
+        Self { exchange, window: WINDOW, state: Mutex::new(Window::default()) }
+    }
+
+    /// Records an attempt the exchange answered after `latency`; `success`
+    /// when the status was 2xx.
+    pub fn record_answer(&self, latency: Duration, success: bool) {
+        let mut state = self.state.lock().unwrap();
+        if success {
+            state.last_success = Some(Utc::now());
+        }
+        self.push(&mut state, Sample::Answered(latency));
+    }
+
+    /// Records an attempt that got no usable answer: a transport error or a 5xx.
+    pub fn record_failure(&self) {
+        self.push(&mut self.state.lock().unwrap(), Sample::Failed);
+    }
+
+    /// Records an API or parse error in an answer already recorded by
+    /// `record_answer`.
+    pub fn record_rejection(&self) {
+        self.push(&mut self.state.lock().unwrap(), Sample::Rejected);
+    }
+
+    /// Health over the window; `ws_connected` comes from the client.
+    pub fn health(&self, ws_connected: Option<bool>) -> ExchangeHealth {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+        self.expire(&mut state, now);
+
+        let mut latencies = Vec::new();
+        let (mut requests, mut errors) = (0u64, 0u64);
+        for (_, sample) in &state.samples {
+            match sample {
+                Sample::Answered(latency) => {
+                    requests += 1;
+                    latencies.push(latency.as_secs_f64() * 1000.0);
+                }
+                Sample::Failed => {
+                    requests += 1;
+                    errors += 1;
+                }
+                Sample::Rejected => errors += 1,
+            }
+        }
+        ExchangeHealth {
+            exchange: self.exchange.to_string(),
+            last_success: state.last_success,
+            requests,
+            error_rate: if requests == 0 { 0.0 } else { (errors as f64 / requests as f64).min(1.0) },
+            latency: LatencyStats::from_samples(&latencies),
+            ws_connected,
+        }
+    }
+
+    fn push(&self, state: &mut Window, sample: Sample) {
+        let now = Instant::now();
+        self.expire(state, now);
+        if state.samples.len() == MAX_SAMPLES {
+            state.samples.pop_front();
+        }
+        state.samples.push_back((now, sample));
+    }
+
+    fn expire(&self, state: &mut Window, now: Instant) {
+        while state.samples.front().is_some_and(|(at, _)| now.duration_since(*at) >= self.window) {
+            state.samples.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_covers_the_window_only() {
+        let mut stats = CallStats::new("Bybit");
+        stats.window = Duration::from_millis(200);
+        assert_eq!(stats.health(None).requests, 0);
+        assert_eq!(stats.health(None).error_rate, 0.0);
+
+        stats.record_failure();
+        std::thread::sleep(Duration::from_millis(250));
+        for ms in [10, 20, 30, 40] {
+            stats.record_answer(Duration::from_millis(ms), true);
+        }
+        // A 200 whose body carried an API error
+        stats.record_rejection();
+
+        let health = stats.health(Some(true));
+        assert_eq!(health.exchange, "Bybit");
+        assert_eq!(health.requests, 4);
+        assert_eq!(health.error_rate, 0.25);
+        assert!(health.last_success.is_some());
+        let latency = health.latency.unwrap();
+        assert_eq!((latency.p50_ms, latency.p95_ms), (20.0, 40.0));
+        assert_eq!(health.ws_connected, Some(true));
+    }
+}
@@ -0,0 +1,59 @@
+//! Keeps pooled connections to an exchange open between requests.
+//!
+//! Connections left unused for the pool idle timeout are closed, and the
+//! next request pays a TCP and TLS handshake, typically right when an
+//! opportunity has just appeared after a quiet period. A few concurrent
+//! pings per round keep enough connections open for the legs of an
+//! execution to go out in parallel.
+//!
+//! With `prefer_fastest_endpoint` every configured host is also probed on
+//! its own interval, so the endpoint pool knows which one orders should go to.
+
+use super::ExchangeClient;
+use futures_util::future::join_all;
+use log::{debug, warn};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Sends `connections` pings at once, so that each opens or refreshes a
+/// connection of its own. Returns how many succeeded.
+pub async fn warm_up(client: &ExchangeClient, connections: usize) -> usize {
+    let results = join_all((0..connections).map(|_| client.ping())).await;
+    let failed: Vec<_> = results.into_iter().filter_map(Result::err).collect();
+    if let Some(e) = failed.first() {
+        debug!("{} of {} {} keepalive pings failed: {:#}", failed.len(), connections, client.name(), e);
+    }
+    connections - failed.len()
+}
+
+/// Warms `connections` connections of `client` now and again every
+/// `interval` until `cancel` fires.
+pub async fn keep_warm(client: ExchangeClient, interval: Duration, connections: usize, cancel: CancellationToken) {
+    let mut rounds = tokio::time::interval(interval);
+    rounds.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = rounds.tick() => {}
+        }
+        if warm_up(&client, connections).await == 0 && connections > 0 {
+            warn!("Every {} keepalive ping failed", client.name());
+        }
+    }
+}
+
+/// Measures the latency of every host of `client` now and again every
+/// `interval` until `cancel` fires.
+pub async fn probe_endpoints(client: ExchangeClient, interval: Duration, cancel: CancellationToken) {
+    let mut rounds = tokio::time::interval(interval);
+    rounds.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = rounds.tick() => {}
+        }
+        if client.probe_endpoints().await == 0 {
+            warn!("No {} endpoint answered its latency probe", client.name());
+        }
+    }
+}
@@ -1,10 +1,37 @@
 pub mod binance;
+pub mod budget;
 pub mod bybit;
+pub mod clock;
+pub mod depth;
+pub mod dns;
+pub mod endpoints;
+pub mod errors;
+pub mod fees;
+pub mod fixtures;
+pub mod heartbeat;
+pub mod keepalive;
 pub mod order_book;
+pub mod pagination;
+pub mod reconnect;
+pub mod request_log;
+pub mod simulated;
+pub mod snapshot;
+pub mod streams;
+pub mod ws_trading;
 
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use crate::config::ClientNetworkConfig;
 use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+use binance::BinanceClient;
+use bybit::BybitClient;
+use simulated::SimulatedExchange;
+use fees::AccountFeeRates;
+use pagination::HistoryRecord;
 use thiserror::Error;
 use chrono::{DateTime, Utc};
 
@@ -23,6 +50,97 @@ pub struct OrderBook {
     pub timestamp: DateTime<Utc>,
 }
 
+/// Best bid/offer for a symbol.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookTicker {
+    pub symbol: String,
+    pub bid_price: Decimal,
+    pub bid_quantity: Decimal,
+    pub ask_price: Decimal,
+    pub ask_quantity: Decimal,
+    /// Event time the exchange stamped the quote with, when its payload has one
+    #[serde(default)]
+    pub timestamp: Option<DateTime<Utc>>,
+}
+
+impl BookTicker {
+    /// Parses a quote from exchange strings; `None` unless both sides are
+    /// positive and not crossed.
+    pub fn parse(symbol: &str, bid_price: &str, bid_quantity: &str, ask_price: &str, ask_quantity: &str) -> Option<Self> {
+        let ticker = Self {
+            symbol: symbol.to_string(),
+            bid_price: bid_price.parse().ok()?,
+            bid_quantity: bid_quantity.parse().ok()?,
+            ask_price: ask_price.parse().ok()?,
+            ask_quantity: ask_quantity.parse().ok()?,
+            timestamp: None,
+        };
+        (ticker.bid_price > Decimal::ZERO && ticker.ask_price >= ticker.bid_price).then_some(ticker)
+    }
+    
+    pub fn with_timestamp(mut self, timestamp: Option<DateTime<Utc>>) -> Self {
+        self.timestamp = timestamp;
+        self
+    }
+    
+    pub fn mid_price(&self) -> Decimal {
+        (self.bid_price + self.ask_price) / Decimal::TWO
+    }
+}
+
+/// Spot instrument metadata from exchange info.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    /// Open for spot trading right now
+    pub tradable: bool,
+    pub tick_size: Option<Decimal>,
+    /// Lot size quantities must be a multiple of
+    pub step_size: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Balance {
+    pub asset: String,
+    pub free: Decimal,
+    pub locked: Decimal,
+}
+
+impl Balance {
+    pub fn total(&self) -> Decimal {
+        self.free + self.locked
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Kline {
+    pub open_time: DateTime<Utc>,
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+    pub volume: Decimal,
+}
+
+/// One public trade from an exchange's recent-trades feed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicTrade {
+    pub price: Decimal,
+    /// Base-asset quantity
+    pub quantity: Decimal,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KlineInterval {
+    OneMinute,
+    FiveMinutes,
+    FifteenMinutes,
+    OneHour,
+    FourHours,
+    OneDay,
+}
+
 #[derive(Debug, Clone)]
 pub struct TradingFees {
     pub maker_fee: Decimal,
@@ -38,8 +156,16 @@ pub enum ExchangeError {
     #[error("Network error: {0}")]
     NetworkError(String),
     
-    #[error("API error: {0}")]
-    ApiError(String),
+    #[error("{exchange} {endpoint} API error (HTTP {}, code {}): {message}",
+            status.map(|s| s.to_string()).unwrap_or_else(|| "-".to_string()),
+            code.map(|c| c.to_string()).unwrap_or_else(|| "-".to_string()))]
+    ApiError {
+        exchange: String,
+        endpoint: String,
+        status: Option<u16>,
+        code: Option<i64>,
+        message: String,
+    },
     
     #[error("Parse error: {0}")]
     ParseError(String),
@@ -52,6 +178,126 @@ pub enum ExchangeError {
     
     #[error("Insufficient balance: {0}")]
     InsufficientBalance(String),
+    
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+}
+
+impl ExchangeError {
+    /// Builds an `ApiError` from a failed HTTP response, extracting the
+    /// native error code from Binance (`code`/`msg`) or Bybit
+    /// (`retCode`/`retMsg`) bodies when present.
+    pub fn from_response(exchange: &str, endpoint: &str, status: u16, body: &str) -> Self {
+        let parsed: Option<serde_json::Value> = serde_json::from_str(body).ok();
+        let code = parsed.as_ref().and_then(|v| v.get("code").or_else(|| v.get("retCode"))).and_then(|c| c.as_i64());
+        let message = parsed.as_ref()
+            .and_then(|v| v.get("msg").or_else(|| v.get("retMsg")))
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| body.chars().take(512).collect());
+        
+        ExchangeError::ApiError {
+            exchange: exchange.to_string(),
+            endpoint: endpoint.to_string(),
+            status: Some(status),
+            code,
+            message,
+        }
+    }
+    
+    /// Bybit reports most failures with HTTP 200 and a non-zero `retCode`.
+    pub fn check_bybit_ret_code(endpoint: &str, body: &serde_json::Value) -> Result<(), Self> {
+        Self::check_bybit_status(
+            endpoint,
+            body.get("retCode").and_then(|c| c.as_i64()),
+            body.get("retMsg").and_then(|m| m.as_str()).unwrap_or_default(),
+        )
+    }
+    
+    /// `check_bybit_ret_code` for responses parsed into typed structs.
+    pub fn check_bybit_status(endpoint: &str, ret_code: Option<i64>, ret_msg: &str) -> Result<(), Self> {
+        match ret_code {
+            Some(0) | None => Ok(()),
+            Some(code) => Err(ExchangeError::ApiError {
+                exchange: "Bybit".to_string(),
+                endpoint: endpoint.to_string(),
+                status: Some(200),
+                code: Some(code),
+                message: ret_msg.to_string(),
+            }),
+        }
+    }
+    
+    pub fn status(&self) -> Option<u16> {
+        match self {
+            ExchangeError::ApiError { status, .. } => *status,
+            _ => None,
+        }
+    }
+    
+    pub fn code(&self) -> Option<i64> {
+        match self {
+            ExchangeError::ApiError { code, .. } => *code,
+            _ => None,
+        }
+    }
+    
+    /// Whether repeating the same request later can reasonably succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ExchangeError::NetworkError(_) | ExchangeError::RateLimitError(_) => true,
+            ExchangeError::ApiError { status, code, .. } => {
+                let retryable_status = matches!(status, Some(s) if *s >= 500 || *s == 429 || *s == 418);
+                let retryable_code = matches!(
+                    code,
+                    // Binance: disconnected, too many requests, timeout, timestamp outside recvWindow
+                    Some(-1001) | Some(-1003) | Some(-1007) | Some(-1021)
+                    // Bybit: timestamp/recv_window, rate limit, server error
+                    | Some(10002) | Some(10006) | Some(10016)
+                );
+                retryable_status || retryable_code
+            }
+            _ => false,
+        }
+    }
+    
+    /// Whether the exchange rejected the timestamp or signature of a signed
+    /// request, which a drifting local clock causes.
+    pub fn is_timestamp_error(&self) -> bool {
+        // Binance: timestamp outside recvWindow, invalid signature;
+        // Bybit: timestamp/recv_window
+        matches!(self.code(), Some(-1021) | Some(-1022) | Some(10002))
+    }
+}
+
+/// Returns the response unchanged when it succeeded, otherwise converts it
+/// into an `ExchangeError` carrying the exchange, endpoint and status and
+/// counts it in `errors`. Rate-limit responses keep the `Retry-After`
+/// delay in their message.
+pub(crate) async fn check_response(
+    errors: &errors::ErrorCounters,
+    endpoint: &str,
+    response: reqwest::Response,
+) -> Result<reqwest::Response, ExchangeError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    
+    let retry_after = response.headers()
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok());
+    let body = response.text().await.unwrap_or_default();
+    let mut error = ExchangeError::from_response(errors.exchange(), endpoint, status.as_u16(), &body);
+    if status.as_u16() == 429 || status.as_u16() == 418 {
+        error = ExchangeError::RateLimitError(match retry_after {
+            Some(seconds) => format!("{} (retry after {}s)", error, seconds),
+            None => error.to_string(),
+        });
+    }
+    errors.record(endpoint, &error);
+    Err(error)
 }
 
 #[derive(Debug, Clone)]
@@ -59,23 +305,516 @@ pub struct OrderRequest {
     pub symbol: String,
     pub side: OrderSide,
     pub quantity: Decimal,
+    /// Amount of quote asset to spend (buy) or receive (sell) on a market
+    /// order; takes the place of `quantity` when set
+    pub quote_quantity: Option<Decimal>,
     pub price: Option<Decimal>,
     pub order_type: OrderType,
+    /// Only sent for limit orders; market orders always execute immediately
+    pub time_in_force: TimeInForce,
+    /// Idempotency key sent as Binance `newClientOrderId` / Bybit `orderLinkId`
+    pub client_order_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
 }
 
+/// Exchange-reported state of a single order.
 #[derive(Debug, Clone)]
+pub struct OrderState {
+    pub client_order_id: String,
+    pub exchange_order_id: Option<String>,
+    pub status: OrderStatus,
+    pub executed_quantity: Decimal,
+    pub average_price: Option<Decimal>,
+}
+
+/// An order to cancel, identified by its symbol and client order ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderRef {
+    pub symbol: String,
+    pub client_order_id: String,
+}
+
+/// An order still resting on the book, as listed by the open orders endpoint.
+#[derive(Debug, Clone)]
+pub struct OpenOrder {
+    pub symbol: String,
+    pub state: OrderState,
+}
+
+/// An order of the account, open or finished, from its order history.
+#[derive(Debug, Clone)]
+pub struct HistoricalOrder {
+    pub symbol: String,
+    pub side: OrderSide,
+    pub state: OrderState,
+    pub created_at: DateTime<Utc>,
+}
+
+impl HistoryRecord for HistoricalOrder {
+    fn time(&self) -> DateTime<Utc> {
+        self.created_at
+    }
+
+    fn sequence(&self) -> Option<u64> {
+        self.state.exchange_order_id.as_ref()?.parse().ok()
+    }
+}
+
+/// One fill of an order of the account.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AccountTrade {
+    pub symbol: String,
+    pub trade_id: String,
+    pub order_id: String,
+    pub side: OrderSide,
+    pub price: Decimal,
+    /// Base-asset quantity
+    pub quantity: Decimal,
+    pub fee: Decimal,
+    pub fee_asset: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl HistoryRecord for AccountTrade {
+    fn time(&self) -> DateTime<Utc> {
+        self.timestamp
+    }
+
+    fn sequence(&self) -> Option<u64> {
+        self.trade_id.parse().ok()
+    }
+}
+
+/// Result of a placement that survives ambiguous failures: when the request
+/// times out the order is looked up by its client ID and either adopted
+/// (already final) or canceled (still resting) instead of being resent.
+#[derive(Debug, Clone)]
+pub enum PlacementOutcome {
+    Placed(OrderState),
+    Adopted(OrderState),
+    CanceledAfterTimeout(OrderState),
+    NotPlaced,
+}
+
+/// Wait before the first repeated lookup of an ambiguously placed order;
+/// doubles with every miss up to `AMBIGUOUS_LOOKUP_MAX_DELAY`.
+const AMBIGUOUS_LOOKUP_DELAY: Duration = Duration::from_millis(250);
+const AMBIGUOUS_LOOKUP_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// Whether a failed placement may still have reached the book: the request
+/// timed out or dropped, or the exchange accepted it with a body that did
+/// not parse.
+pub(crate) fn is_ambiguous_placement(error: &anyhow::Error) -> bool {
+    matches!(error.downcast_ref::<ExchangeError>(), Some(ExchangeError::NetworkError(_) | ExchangeError::ParseError(_)))
+}
+
+/// Looks up an ambiguously placed order with `lookup` until it is found or
+/// the receive window of the request sent at `sent_at` has passed. The
+/// exchange rejects a request older than its window, so an order still
+/// missing from a lookup started after that can no longer arrive; before
+/// then a miss only means it has not arrived yet.
+pub(crate) async fn find_ambiguous_order<F, Fut>(
+    sent_at: tokio::time::Instant,
+    recv_window: Duration,
+    mut lookup: F,
+) -> anyhow::Result<Option<OrderState>>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = anyhow::Result<Option<OrderState>>>,
+{
+    let deadline = sent_at + recv_window;
+    let mut delay = AMBIGUOUS_LOOKUP_DELAY;
+    loop {
+        let window_passed = tokio::time::Instant::now() >= deadline;
+        if let Some(state) = lookup().await? {
+            return Ok(Some(state));
+        }
+        if window_passed {
+            return Ok(None);
+        }
+        tokio::time::sleep(delay.min(deadline.saturating_duration_since(tokio::time::Instant::now()))).await;
+        delay = (delay * 2).min(AMBIGUOUS_LOOKUP_MAX_DELAY);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderSide {
     Buy,
     Sell,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OrderType {
     Market,
     Limit,
 }
 
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TimeInForce {
+    /// Good-till-canceled: rests on the book until filled or canceled
+    #[default]
+    Gtc,
+    /// Immediate-or-cancel: fills what it can, cancels the rest
+    Ioc,
+    /// Fill-or-kill: fills completely or not at all
+    Fok,
+    /// Rejected instead of matching as taker
+    PostOnly,
+}
+
+impl TimeInForce {
+    pub fn as_binance(&self) -> &'static str {
+        match self {
+            TimeInForce::Gtc | TimeInForce::PostOnly => "GTC",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => "FOK",
+        }
+    }
+    
+    pub fn as_bybit(&self) -> &'static str {
+        match self {
+            TimeInForce::Gtc => "GTC",
+            TimeInForce::Ioc => "IOC",
+            TimeInForce::Fok => "FOK",
+            TimeInForce::PostOnly => "PostOnly",
+        }
+    }
+}
+
+/// Internal transfer between accounts of one exchange group. `None` means
+/// the master account; sub-accounts are identified by their email on
+/// Binance and their member UID on Bybit. Requires master-account keys.
+#[derive(Debug, Clone)]
+pub struct SubAccountTransfer {
+    pub asset: String,
+    pub amount: Decimal,
+    pub from_account: Option<String>,
+    pub to_account: Option<String>,
+    /// Idempotency key, reused when retrying the same transfer
+    pub transfer_id: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransferReceipt {
+    pub exchange: String,
+    pub transfer_id: String,
+    pub status: Option<String>,
+}
+
+impl SubAccountTransfer {
+    pub fn new(asset: &str, amount: Decimal, from_account: Option<String>, to_account: Option<String>) -> Self {
+        Self {
+            asset: asset.to_uppercase(),
+            amount,
+            from_account,
+            to_account,
+            transfer_id: uuid::Uuid::new_v4().to_string(),
+        }
+    }
+    
+    pub fn validate(&self) -> Result<(), ExchangeError> {
+        if self.amount <= Decimal::ZERO {
+            return Err(ExchangeError::InvalidRequest(format!("Transfer amount must be positive, got {}", self.amount)));
+        }
+        if self.from_account == self.to_account {
+            return Err(ExchangeError::InvalidRequest("Transfer source and destination are the same account".to_string()));
+        }
+        Ok(())
+    }
+}
+
+/// What an API key may do, as reported by the exchange's key endpoint.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApiKeyPermissions {
+    pub read: bool,
+    pub trade: bool,
+    pub withdraw: bool,
+    /// Whether the key only works from whitelisted IPs
+    pub ip_restricted: bool,
+}
+
 pub type PriceMap = HashMap<String, Decimal>;
+
+/// Best bid/offer per symbol for one exchange.
+pub type BookTickerMap = HashMap<String, BookTicker>;
+
+/// Runtime handle to one of the supported clients, so execution can route
+/// each leg to the venue named on it.
+#[derive(Clone)]
+pub enum ExchangeClient {
+    Binance(Arc<BinanceClient>),
+    Bybit(Arc<BybitClient>),
+    /// In-memory matching engine standing in for one of the venues
+    Simulated(Arc<SimulatedExchange>),
+}
+
+impl ExchangeClient {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExchangeClient::Binance(_) => "Binance",
+            ExchangeClient::Bybit(_) => "Bybit",
+            ExchangeClient::Simulated(client) => client.name(),
+        }
+    }
+    
+    /// Recent success, error rate and latency of the client's requests.
+    pub fn health(&self) -> heartbeat::ExchangeHealth {
+        match self {
+            ExchangeClient::Binance(client) => client.health(),
+            ExchangeClient::Bybit(client) => client.health(),
+            ExchangeClient::Simulated(client) => client.health(),
+        }
+    }
+    
+    pub async fn get_book_ticker(&self, symbol: &str) -> anyhow::Result<BookTicker> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_book_ticker(symbol).await,
+            ExchangeClient::Bybit(client) => client.get_book_ticker(symbol).await,
+            ExchangeClient::Simulated(client) => client.get_book_ticker(symbol).await,
+        }
+    }
+    
+    pub async fn get_book_tickers(&self) -> anyhow::Result<BookTickerMap> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_book_tickers().await,
+            ExchangeClient::Bybit(client) => client.get_book_tickers().await,
+            ExchangeClient::Simulated(client) => client.get_book_tickers().await,
+        }
+    }
+    
+    pub async fn get_depth_snapshot(&self, symbol: &str, limit: usize) -> anyhow::Result<depth::DepthSnapshot> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_depth_snapshot(symbol, limit).await,
+            ExchangeClient::Bybit(client) => client.get_depth_snapshot(symbol, limit).await,
+            ExchangeClient::Simulated(client) => client.get_depth_snapshot(symbol, limit).await,
+        }
+    }
+    
+    pub async fn get_recent_trades(&self, symbol: &str, limit: usize) -> anyhow::Result<Vec<PublicTrade>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_recent_trades(symbol, limit).await,
+            ExchangeClient::Bybit(client) => client.get_recent_trades(symbol, limit).await,
+            ExchangeClient::Simulated(client) => client.get_recent_trades(symbol, limit).await,
+        }
+    }
+    
+    pub async fn get_klines(&self, symbol: &str, interval: KlineInterval, range: Range<DateTime<Utc>>) -> anyhow::Result<Vec<Kline>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_klines(symbol, interval, range).await,
+            ExchangeClient::Bybit(client) => client.get_klines(symbol, interval, range).await,
+            ExchangeClient::Simulated(client) => client.get_klines(symbol, interval, range).await,
+        }
+    }
+    
+    pub async fn place_order_cancel_safe(&self, order: &OrderRequest) -> anyhow::Result<PlacementOutcome> {
+        match self {
+            ExchangeClient::Binance(client) => client.place_order_cancel_safe(order).await,
+            ExchangeClient::Bybit(client) => client.place_order_cancel_safe(order).await,
+            ExchangeClient::Simulated(client) => client.place_order_cancel_safe(order).await,
+        }
+    }
+    
+    pub async fn get_order_by_client_id(&self, symbol: &str, client_order_id: &str) -> anyhow::Result<Option<OrderState>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_order_by_client_id(symbol, client_order_id).await,
+            ExchangeClient::Bybit(client) => client.get_order_by_client_id(symbol, client_order_id).await,
+            ExchangeClient::Simulated(client) => client.get_order_by_client_id(symbol, client_order_id).await,
+        }
+    }
+    
+    pub async fn cancel_order_by_client_id(&self, symbol: &str, client_order_id: &str) -> anyhow::Result<()> {
+        match self {
+            ExchangeClient::Binance(client) => client.cancel_order_by_client_id(symbol, client_order_id).await.map(|_| ()),
+            ExchangeClient::Bybit(client) => client.cancel_order_by_client_id(symbol, client_order_id).await.map(|_| ()),
+            ExchangeClient::Simulated(client) => client.cancel_order_by_client_id(symbol, client_order_id).await.map(|_| ()),
+        }
+    }
+    
+    /// Cancels every order of `orders`, in as few requests as the exchange
+    /// allows, and returns the outcome of each in the same order. Fails
+    /// as a whole only when a batch request got no answer.
+    pub async fn cancel_orders_by_client_id(&self, orders: &[OrderRef]) -> anyhow::Result<Vec<anyhow::Result<()>>> {
+        match self {
+            ExchangeClient::Binance(client) => Ok(client.cancel_orders_by_client_id(orders).await),
+            ExchangeClient::Bybit(client) => client.cancel_orders_by_client_id(orders).await,
+            ExchangeClient::Simulated(client) => Ok(client.cancel_orders_by_client_id(orders).await),
+        }
+    }
+    
+    pub async fn get_open_orders(&self) -> anyhow::Result<Vec<OpenOrder>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_open_orders().await,
+            ExchangeClient::Bybit(client) => client.get_open_orders().await,
+            ExchangeClient::Simulated(client) => client.get_open_orders().await,
+        }
+    }
+    
+    /// Every order of `symbol` created in `range`, oldest first.
+    pub async fn get_order_history(&self, symbol: &str, range: Range<DateTime<Utc>>) -> anyhow::Result<Vec<HistoricalOrder>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_order_history(symbol, range).await,
+            ExchangeClient::Bybit(client) => client.get_order_history(symbol, range).await,
+            ExchangeClient::Simulated(client) => client.get_order_history(symbol, range).await,
+        }
+    }
+    
+    /// Every fill of the account's `symbol` orders in `range`, oldest first.
+    pub async fn get_my_trades(&self, symbol: &str, range: Range<DateTime<Utc>>) -> anyhow::Result<Vec<AccountTrade>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_my_trades(symbol, range).await,
+            ExchangeClient::Bybit(client) => client.get_my_trades(symbol, range).await,
+            ExchangeClient::Simulated(client) => client.get_my_trades(symbol, range).await,
+        }
+    }
+    
+    pub async fn get_balances(&self) -> anyhow::Result<Vec<Balance>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_balances().await,
+            ExchangeClient::Bybit(client) => client.get_balances().await,
+            ExchangeClient::Simulated(client) => client.get_balances().await,
+        }
+    }
+    
+    pub async fn transfer_between_sub_accounts(&self, transfer: &SubAccountTransfer) -> anyhow::Result<TransferReceipt> {
+        match self {
+            ExchangeClient::Binance(client) => client.transfer_between_sub_accounts(transfer).await,
+            ExchangeClient::Bybit(client) => client.transfer_between_sub_accounts(transfer).await,
+            ExchangeClient::Simulated(client) => client.transfer_between_sub_accounts(transfer).await,
+        }
+    }
+    
+    pub async fn get_api_key_permissions(&self) -> anyhow::Result<ApiKeyPermissions> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_api_key_permissions().await,
+            ExchangeClient::Bybit(client) => client.get_api_key_permissions().await,
+            ExchangeClient::Simulated(client) => client.get_api_key_permissions().await,
+        }
+    }
+    
+    pub async fn get_fee_rates(&self, symbol: &str) -> anyhow::Result<AccountFeeRates> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_fee_rates(symbol).await,
+            ExchangeClient::Bybit(client) => client.get_fee_rates(symbol).await,
+            ExchangeClient::Simulated(client) => client.get_fee_rates(symbol).await,
+        }
+    }
+    
+    pub async fn ping(&self) -> anyhow::Result<()> {
+        match self {
+            ExchangeClient::Binance(client) => client.ping().await,
+            ExchangeClient::Bybit(client) => client.ping().await,
+            ExchangeClient::Simulated(client) => client.ping().await,
+        }
+    }
+    
+    pub async fn probe_endpoints(&self) -> usize {
+        match self {
+            ExchangeClient::Binance(client) => client.probe_endpoints().await,
+            ExchangeClient::Bybit(client) => client.probe_endpoints().await,
+            ExchangeClient::Simulated(client) => client.probe_endpoints().await,
+        }
+    }
+    
+    pub async fn get_server_time(&self) -> anyhow::Result<DateTime<Utc>> {
+        match self {
+            ExchangeClient::Binance(client) => client.get_server_time().await,
+            ExchangeClient::Bybit(client) => client.get_server_time().await,
+            ExchangeClient::Simulated(client) => client.get_server_time().await,
+        }
+    }
+}
+
+/// Builds the HTTP client shared by an exchange client's requests, applying
+/// the configured pool size, proxy, DNS overrides and TLS material. Hosts
+/// without an override are resolved through `dns` when given.
+pub(crate) fn http_client(network: &ClientNetworkConfig, dns: Option<Arc<dns::DnsCache>>) -> Result<reqwest::Client, ExchangeError> {
+    let config_error = |what: &str, e: &dyn std::fmt::Display| ExchangeError::InvalidRequest(format!("{}: {}", what, e));
+    
+    let mut builder = reqwest::ClientBuilder::new()
+        .timeout(Duration::from_secs(10))
+        .connect_timeout(Duration::from_secs(5))
+        .pool_idle_timeout(Duration::from_secs(network.pool_idle_timeout_seconds))
+        .pool_max_idle_per_host(network.pool_max_idle_per_host);
+    
+    match &network.proxy_url {
+        Some(url) => {
+            let proxy = reqwest::Proxy::all(url).map_err(|e| config_error("Invalid proxy_url", &e))?;
+            builder = builder.proxy(proxy);
+        }
+        None if !network.use_env_proxy => builder = builder.no_proxy(),
+        None => {}
+    }
+    
+    if let Some(cache) = dns {
+        builder = builder.dns_resolver(Arc::new(dns::CachingResolver(cache)));
+    }
+    
+    for (host, addr) in &network.resolve {
+        let addr: SocketAddr = addr.parse().map_err(|e| config_error(&format!("Invalid resolve address for {}", host), &e))?;
+        builder = builder.resolve(host, addr);
+    }
+    
+    if let Some(path) = &network.ca_cert_path {
+        let pem = std::fs::read(path).map_err(|e| config_error(&format!("Failed to read {}", path), &e))?;
+        let cert = reqwest::Certificate::from_pem(&pem).map_err(|e| config_error("Invalid ca_cert_path", &e))?;
+        builder = builder.add_root_certificate(cert);
+    }
+    
+    if let (Some(cert_path), Some(key_path)) = (&network.client_cert_path, &network.client_key_path) {
+        let cert = std::fs::read(cert_path).map_err(|e| config_error(&format!("Failed to read {}", cert_path), &e))?;
+        let key = std::fs::read(key_path).map_err(|e| config_error(&format!("Failed to read {}", key_path), &e))?;
+        let identity = reqwest::Identity::from_pkcs8_pem(&cert, &key).map_err(|e| config_error("Invalid client certificate", &e))?;
+        builder = builder.identity(identity);
+    }
+    
+    builder.build()
+        .map_err(|e| ExchangeError::NetworkError(format!("Failed to create client: {}", e)))
+}
+
+/// Generates a client order ID accepted by both exchanges (at most 36 chars
+/// of `[A-Za-z0-9]`).
+pub fn new_client_order_id() -> String {
+    format!("arb{}", uuid::Uuid::new_v4().simple())
+}
+
+impl OrderStatus {
+    pub fn is_open(&self) -> bool {
+        matches!(self, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+    
+    pub fn from_binance(status: &str) -> Option<Self> {
+        match status {
+            "NEW" => Some(OrderStatus::New),
+            "PARTIALLY_FILLED" => Some(OrderStatus::PartiallyFilled),
+            "FILLED" => Some(OrderStatus::Filled),
+            "CANCELED" | "PENDING_CANCEL" => Some(OrderStatus::Canceled),
+            "REJECTED" => Some(OrderStatus::Rejected),
+            "EXPIRED" | "EXPIRED_IN_MATCH" => Some(OrderStatus::Expired),
+            _ => None,
+        }
+    }
+    
+    pub fn from_bybit(status: &str) -> Option<Self> {
+        match status {
+            "New" | "Untriggered" | "Created" => Some(OrderStatus::New),
+            "PartiallyFilled" => Some(OrderStatus::PartiallyFilled),
+            "Filled" => Some(OrderStatus::Filled),
+            "Cancelled" | "PartiallyFilledCanceled" => Some(OrderStatus::Canceled),
+            "Rejected" => Some(OrderStatus::Rejected),
+            "Deactivated" => Some(OrderStatus::Expired),
+            _ => None,
+        }
+    }
+}
 pub type OrderBookMap = HashMap<String, OrderBook>;
 
 impl Default for TradingFees {
@@ -86,4 +825,189 @@ impl Default for TradingFees {
             withdrawal_fee: Decimal::from_str_exact("0.0005").unwrap(), // 0.05%
         }
     }
-}
\ No newline at end of file
+}
+
+impl KlineInterval {
+    pub fn as_binance(&self) -> &'static str {
+        match self {
+            KlineInterval::OneMinute => "1m",
+            KlineInterval::FiveMinutes => "5m",
+            KlineInterval::FifteenMinutes => "15m",
+            KlineInterval::OneHour => "1h",
+            KlineInterval::FourHours => "4h",
+            KlineInterval::OneDay => "1d",
+        }
+    }
+    
+    pub fn as_bybit(&self) -> &'static str {
+        match self {
+            KlineInterval::OneMinute => "1",
+            KlineInterval::FiveMinutes => "5",
+            KlineInterval::FifteenMinutes => "15",
+            KlineInterval::OneHour => "60",
+            KlineInterval::FourHours => "240",
+            KlineInterval::OneDay => "D",
+        }
+    }
+    
+    pub fn duration(&self) -> chrono::Duration {
+        match self {
+            KlineInterval::OneMinute => chrono::Duration::minutes(1),
+            KlineInterval::FiveMinutes => chrono::Duration::minutes(5),
+            KlineInterval::FifteenMinutes => chrono::Duration::minutes(15),
+            KlineInterval::OneHour => chrono::Duration::hours(1),
+            KlineInterval::FourHours => chrono::Duration::hours(4),
+            KlineInterval::OneDay => chrono::Duration::days(1),
+        }
+    }
+}
+
+impl std::str::FromStr for KlineInterval {
+    type Err = ExchangeError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1m" => Ok(KlineInterval::OneMinute),
+            "5m" => Ok(KlineInterval::FiveMinutes),
+            "15m" => Ok(KlineInterval::FifteenMinutes),
+            "1h" => Ok(KlineInterval::OneHour),
+            "4h" => Ok(KlineInterval::FourHours),
+            "1d" => Ok(KlineInterval::OneDay),
+            other => Err(ExchangeError::ParseError(format!("Unsupported kline interval: {}", other))),
+        }
+    }
+}
+
+impl std::fmt::Display for KlineInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_binance())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_book_ticker_parse_rejects_one_sided_and_crossed_quotes() {
+        let ticker = BookTicker::parse("BTCUSDT", "60000.5", "0.2", "60001.5", "1.1").unwrap();
+        assert_eq!(ticker.mid_price(), Decimal::from(60001));
+        assert!(BookTicker::parse("BTCUSDT", "0", "0", "60001.5", "1.1").is_none());
+        assert!(BookTicker::parse("BTCUSDT", "60002", "1", "60001.5", "1.1").is_none());
+        assert!(BookTicker::parse("BTCUSDT", "", "1", "60001.5", "1.1").is_none());
+    }
+    
+    #[test]
+    fn test_error_from_binance_body() {
+        let err = ExchangeError::from_response("Binance", "/api/v3/order", 400, r#"{"code":-1121,"msg":"Invalid symbol."}"#);
+        assert_eq!(err.status(), Some(400));
+        assert_eq!(err.code(), Some(-1121));
+        assert!(!err.is_retryable());
+        assert!(!err.is_timestamp_error());
+        assert!(err.to_string().contains("Invalid symbol."));
+        
+        let err = ExchangeError::from_response("Binance", "/api/v3/account", 400, r#"{"code":-1021,"msg":"Timestamp outside recvWindow"}"#);
+        assert!(err.is_retryable());
+        assert!(err.is_timestamp_error());
+    }
+    
+    #[test]
+    fn test_client_order_id_format() {
+        let id = new_client_order_id();
+        assert!(id.len() <= 36);
+        assert!(id.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(id, new_client_order_id());
+    }
+    
+    #[test]
+    fn test_order_status_mapping() {
+        assert_eq!(OrderStatus::from_binance("PARTIALLY_FILLED"), Some(OrderStatus::PartiallyFilled));
+        assert_eq!(OrderStatus::from_bybit("PartiallyFilledCanceled"), Some(OrderStatus::Canceled));
+        assert!(OrderStatus::New.is_open());
+        assert!(!OrderStatus::Filled.is_open());
+        assert_eq!(OrderStatus::from_binance("bogus"), None);
+    }
+    
+    #[test]
+    fn test_http_client_rejects_bad_network_config() {
+        assert!(http_client(&ClientNetworkConfig::default(), None).is_ok());
+        
+        let proxied = ClientNetworkConfig {
+            proxy_url: Some("http://127.0.0.1:3128".to_string()),
+            resolve: [("api.binance.com".to_string(), "10.0.0.1:443".to_string())].into_iter().collect(),
+            ..ClientNetworkConfig::default()
+        };
+        assert!(http_client(&proxied, None).is_ok());
+        
+        let missing_ca = ClientNetworkConfig {
+            ca_cert_path: Some("/nonexistent/ca.pem".to_string()),
+            ..ClientNetworkConfig::default()
+        };
+        assert!(matches!(http_client(&missing_ca, None), Err(ExchangeError::InvalidRequest(_))));
+    }
+    
+    #[test]
+    fn test_transfer_validation() {
+        let transfer = SubAccountTransfer::new("usdt", Decimal::from(500), None, Some("arb-1@example.com".to_string()));
+        assert_eq!(transfer.asset, "USDT");
+        assert!(transfer.validate().is_ok());
+        assert!(uuid::Uuid::parse_str(&transfer.transfer_id).is_ok());
+        
+        assert!(SubAccountTransfer::new("USDT", Decimal::ZERO, None, Some("a".to_string())).validate().is_err());
+        assert!(SubAccountTransfer::new("USDT", Decimal::ONE, None, None).validate().is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_ambiguous_orders_are_looked_up_until_the_receive_window_passes() {
+        let state = OrderState {
+            client_order_id: "arb1".to_string(),
+            exchange_order_id: None,
+            status: OrderStatus::New,
+            executed_quantity: Decimal::ZERO,
+            average_price: None,
+        };
+        let window = Duration::from_millis(600);
+        
+        // Reaches the book on the third lookup
+        let lookups = std::sync::atomic::AtomicUsize::new(0);
+        let found = find_ambiguous_order(tokio::time::Instant::now(), window, || async {
+            let attempt = lookups.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok((attempt == 2).then(|| state.clone()))
+        }).await.unwrap();
+        assert_eq!(found.map(|s| s.client_order_id), Some("arb1".to_string()));
+        
+        let sent_at = tokio::time::Instant::now();
+        lookups.store(0, std::sync::atomic::Ordering::SeqCst);
+        let missing = find_ambiguous_order(sent_at, window, || async {
+            lookups.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(None)
+        }).await.unwrap();
+        assert!(missing.is_none());
+        assert!(sent_at.elapsed() >= window);
+        assert!(lookups.load(std::sync::atomic::Ordering::SeqCst) >= 3);
+        
+        assert!(is_ambiguous_placement(&ExchangeError::ParseError("truncated".to_string()).into()));
+        assert!(!is_ambiguous_placement(&ExchangeError::InsufficientBalance("USDT".to_string()).into()));
+    }
+    
+    #[test]
+    fn test_time_in_force_mapping() {
+        assert_eq!(TimeInForce::default(), TimeInForce::Gtc);
+        assert_eq!(TimeInForce::Ioc.as_binance(), "IOC");
+        assert_eq!(TimeInForce::Fok.as_bybit(), "FOK");
+        assert_eq!(TimeInForce::PostOnly.as_bybit(), "PostOnly");
+    }
+    
+    #[test]
+    fn test_error_retryability() {
+        assert!(ExchangeError::from_response("Bybit", "/v5/market/tickers", 503, "Service Unavailable").is_retryable());
+        assert!(ExchangeError::NetworkError("reset".to_string()).is_retryable());
+        assert!(!ExchangeError::ParseError("bad json".to_string()).is_retryable());
+        
+        let body = serde_json::json!({"retCode": 10006, "retMsg": "Too many visits"});
+        let err = ExchangeError::check_bybit_ret_code("/v5/order/create", &body).unwrap_err();
+        assert_eq!(err.code(), Some(10006));
+        assert!(err.is_retryable());
+        assert!(ExchangeError::check_bybit_ret_code("/v5/order/create", &serde_json::json!({"retCode": 0})).is_ok());
+    }
+}
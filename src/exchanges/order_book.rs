@@ -1,7 +1,6 @@
 use super::{OrderBook, ExchangeError};
 use anyhow::Result;
 use rust_decimal::Decimal;
-use std::collections::HashMap;
 
 pub struct OrderBookAnalyzer;
 
@@ -21,7 +20,6 @@ impl OrderBookAnalyzer {
         
         let mut remaining_quantity = quantity;
         let mut total_cost = Decimal::ZERO;
-        let mut weighted_avg_price = Decimal::ZERO;
         let mut orders_needed = 0;
         
         for (price, available_qty) in orders {
@@ -46,7 +44,7 @@ impl OrderBookAnalyzer {
             )).into());
         }
         
-        weighted_avg_price = total_cost / quantity;
+        let weighted_avg_price = total_cost / quantity;
         
         // Calculate slippage compared to best price
         let best_price = orders[0].0;
@@ -0,0 +1,109 @@
+//! Paging through the account history endpoints.
+//!
+//! Every venue pages its history differently: Binance by record ID within
+//! a 24-hour time window, Bybit by an opaque cursor within a 7-day one.
+//! Each client describes where its next page starts with a key of its own,
+//! and `collect_pages` follows the keys to the last page. Callers get the
+//! complete history of a range or an error, never a silently cut list.
+
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use std::fmt::Debug;
+use std::future::Future;
+use std::ops::Range;
+
+/// Pages followed before giving up on a history that never ends.
+pub const MAX_PAGES: usize = 1000;
+
+/// A record of an account history endpoint.
+pub trait HistoryRecord {
+    /// When it happened, for keeping to the requested range
+    fn time(&self) -> DateTime<Utc>;
+    /// Numeric ID the exchange assigned, for venues paging by ID
+    fn sequence(&self) -> Option<u64>;
+}
+
+/// One page of records and where the next one starts, `None` on the last.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Page<K, T> {
+    pub items: Vec<T>,
+    pub next: Option<K>,
+}
+
+impl<K, T> Page<K, T> {
+    pub fn last(items: Vec<T>) -> Self {
+        Self { items, next: None }
+    }
+
+    pub fn more(items: Vec<T>, next: K) -> Self {
+        Self { items, next: Some(next) }
+    }
+}
+
+/// Fetches the page at `first`, then every page after it. Fails on the
+/// first failed page, on a page pointing back at itself, and after
+/// `max_pages` pages.
+pub async fn collect_pages<K, T, F, Fut>(first: K, max_pages: usize, mut fetch: F) -> Result<Vec<T>>
+where
+    K: Clone + PartialEq + Debug,
+    F: FnMut(K) -> Fut,
+    Fut: Future<Output = Result<Page<K, T>>>,
+{
+    let mut items = Vec::new();
+    let mut key = first;
+    for _ in 0..max_pages {
+        let page = fetch(key.clone()).await?;
+        items.extend(page.items);
+        match page.next {
+            Some(next) if next == key => {
+                return Err(anyhow::anyhow!("History paging did not advance past {:?}", key));
+            }
+            Some(next) => key = next,
+            None => return Ok(items),
+        }
+    }
+    Err(anyhow::anyhow!("History paging stopped after {} pages", max_pages))
+}
+
+/// The window of at most `span` starting at `start`, cut at the end of `range`.
+pub fn window(start: DateTime<Utc>, span: Duration, range: &Range<DateTime<Utc>>) -> Range<DateTime<Utc>> {
+    start..(start + span).min(range.end)
+}
+
+/// Start of the window after the one starting at `start`, `None` once
+/// `range` is covered.
+pub fn next_window(start: DateTime<Utc>, span: Duration, range: &Range<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+    let next = start + span;
+    (next < range.end).then_some(next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_follows_keys_to_the_last_page() {
+        let records: Vec<u32> = (0..25).collect();
+        let pages = collect_pages(0usize, MAX_PAGES, |offset| {
+            let page = records[offset..].iter().take(10).copied().collect::<Vec<_>>();
+            async move {
+                Ok(if offset + page.len() < 25 { Page::more(page, offset + 10) } else { Page::last(page) })
+            }
+        }).await.unwrap();
+        assert_eq!(pages, records);
+    }
+
+    #[tokio::test]
+    async fn test_stuck_or_endless_paging_fails() {
+        let stuck = collect_pages(7, MAX_PAGES, |cursor| async move { Ok(Page::more(vec![cursor], cursor)) }).await;
+        assert!(stuck.unwrap_err().to_string().contains("did not advance"));
+
+        let endless = collect_pages(0, 3, |cursor| async move { Ok(Page::more(vec![cursor], cursor + 1)) }).await;
+        assert!(endless.unwrap_err().to_string().contains("after 3 pages"));
+
+        let range = "2024-03-01T00:00:00Z".parse().unwrap().."2024-03-02T12:00:00Z".parse().unwrap();
+        let second = next_window(range.start, Duration::days(1), &range).unwrap();
+        assert_eq!(window(second, Duration::days(1), &range).end, range.end);
+        assert_eq!(next_window(second, Duration::days(1), &range), None);
+    }
+}
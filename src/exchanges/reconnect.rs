@@ -0,0 +1,208 @@
+//! Reconnection and sync state of the websocket depth streams.
+//!
+//! A dropped stream is reconnected after a jittered delay that starts at
+//! `order_books.reconnect_delay_seconds` and doubles with every session
+//! that fails quickly, up to `max_reconnect_delay_seconds`. A session that
+//! stayed up for `STABLE_SESSION` resets it. Every symbol without a synced
+//! book is stale: all of them until their first snapshot, all of an
+//! exchange's after a disconnect, and single ones after a sequence gap.
+//! A symbol stops being stale when a fresh snapshot rebuilds its book.
+//! Reconnects, gaps and resubscriptions are counted for `/metrics`, and
+//! depth messages per symbol for the message rate alarm in `slo`.
+
+use crate::config::{OrderBookConfig, RetryPolicy};
+use crate::metrics::{self, write_metric};
+use crate::retry::backoff;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A session that lasted this long was not failing, whatever ended it.
+const STABLE_SESSION: Duration = Duration::from_secs(60);
+
+/// Delays between the sessions of one stream.
+#[derive(Debug)]
+pub struct ReconnectBackoff {
+    policy: RetryPolicy,
+    /// Sessions in a row that ended before `STABLE_SESSION`
+    failures: u32,
+}
+
+impl ReconnectBackoff {
+    pub fn from_config(config: &OrderBookConfig) -> Self {
+        Self {
+            policy: RetryPolicy {
+                max_attempts: u32::MAX,
+                initial_backoff_ms: config.reconnect_delay_seconds * 1000,
+                max_backoff_ms: config.max_reconnect_delay_seconds * 1000,
+                multiplier: 2.0,
+                jitter: 0.2,
+            },
+            failures: 0,
+        }
+    }
+
+    /// Delay before reconnecting after a session that lasted `session`.
+    pub fn next_delay(&mut self, session: Duration) -> Duration {
+        self.delay_with(session, rand::thread_rng().gen())
+    }
+
+    fn delay_with(&mut self, session: Duration, sample: f64) -> Duration {
+        self.failures = if session >= STABLE_SESSION { 1 } else { self.failures + 1 };
+        backoff(&self.policy, self.failures, sample)
+    }
+}
+
+/// Snapshot of one stream's state, for the admin server.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct StreamStatus {
+    pub exchange: String,
+    pub connected: bool,
+    pub reconnects: u64,
+    pub gaps: u64,
+    pub resubscribes: u64,
+    /// Symbols waiting for a fresh snapshot
+    pub stale_symbols: Vec<String>,
+}
+
+/// Connection state and counters of one exchange's depth stream.
+#[derive(Debug)]
+pub struct StreamHealth {
+    exchange: String,
+    connected: AtomicBool,
+    reconnects: AtomicU64,
+    gaps: AtomicU64,
+    resubscribes: AtomicU64,
+    stale: Mutex<BTreeSet<String>>,
+    /// Depth messages received per symbol since start
+    messages: Mutex<HashMap<String, u64>>,
+}
+
+impl StreamHealth {
+    /// State of a stream that has not connected yet: every symbol is stale.
+    pub fn new(exchange: &str, symbols: &[String]) -> Self {
+        Self {
+            exchange: exchange.to_string(),
+            connected: AtomicBool::new(false),
+            reconnects: AtomicU64::new(0),
+            gaps: AtomicU64::new(0),
+            resubscribes: AtomicU64::new(0),
+            stale: Mutex::new(symbols.iter().cloned().collect()),
+            messages: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+
+    pub fn record_connected(&self) {
+        self.connected.store(true, Ordering::Relaxed);
+    }
+
+    /// The connection dropped: every book of the exchange is stale.
+    pub fn record_disconnected(&self, symbols: &[String]) {
+        self.connected.store(false, Ordering::Relaxed);
+        self.stale.lock().unwrap().extend(symbols.iter().cloned());
+    }
+
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A sequence gap dropped `symbol`'s book.
+    pub fn record_gap(&self, symbol: &str) {
+        self.gaps.fetch_add(1, Ordering::Relaxed);
+        self.stale.lock().unwrap().insert(symbol.to_string());
+    }
+
+    pub fn record_resubscribe(&self) {
+        self.resubscribes.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A fresh snapshot rebuilt `symbol`'s book.
+    pub fn record_synced(&self, symbol: &str) {
+        self.stale.lock().unwrap().remove(symbol);
+    }
+
+    pub fn record_message(&self, symbol: &str) {
+        *self.messages.lock().unwrap().entry(symbol.to_string()).or_default() += 1;
+    }
+
+    /// Depth messages received per symbol since start.
+    pub fn message_counts(&self) -> HashMap<String, u64> {
+        self.messages.lock().unwrap().clone()
+    }
+
+    pub fn is_stale(&self, symbol: &str) -> bool {
+        self.stale.lock().unwrap().contains(symbol)
+    }
+
+    pub fn status(&self) -> StreamStatus {
+        StreamStatus {
+            exchange: self.exchange.clone(),
+            connected: self.connected.load(Ordering::Relaxed),
+            reconnects: self.reconnects.load(Ordering::Relaxed),
+            gaps: self.gaps.load(Ordering::Relaxed),
+            resubscribes: self.resubscribes.load(Ordering::Relaxed),
+            stale_symbols: self.stale.lock().unwrap().iter().cloned().collect(),
+        }
+    }
+}
+
+/// Prometheus text for the depth streams of every exchange.
+pub fn prometheus_metrics(streams: &[Arc<StreamHealth>]) -> String {
+    let statuses: Vec<StreamStatus> = streams.iter().map(|s| s.status()).collect();
+    let samples = |value: fn(&StreamStatus) -> f64| -> Vec<(Vec<String>, f64)> {
+        statuses.iter().map(|s| (vec![s.exchange.clone()], value(s))).collect()
+    };
+    let mut out = String::new();
+    write_metric(&mut out, &metrics::DEPTH_STREAM_CONNECTED, &samples(|s| if s.connected { 1.0 } else { 0.0 }));
+    write_metric(&mut out, &metrics::DEPTH_STREAM_STALE_SYMBOLS, &samples(|s| s.stale_symbols.len() as f64));
+    write_metric(&mut out, &metrics::DEPTH_STREAM_RECONNECTS, &samples(|s| s.reconnects as f64));
+    write_metric(&mut out, &metrics::DEPTH_STREAM_GAPS, &samples(|s| s.gaps as f64));
+    write_metric(&mut out, &metrics::DEPTH_STREAM_RESUBSCRIBES, &samples(|s| s.resubscribes as f64));
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_until_a_session_holds() {
+        let config = OrderBookConfig { reconnect_delay_seconds: 1, max_reconnect_delay_seconds: 5, ..OrderBookConfig::default() };
+        let mut backoff = ReconnectBackoff::from_config(&config);
+        let quick = Duration::from_secs(2);
+        let delays: Vec<u64> = (0..4).map(|_| backoff.delay_with(quick, 0.5).as_secs()).collect();
+        assert_eq!(delays, [1, 2, 4, 5]);
+        assert_eq!(backoff.delay_with(STABLE_SESSION, 0.5), Duration::from_secs(1));
+        assert_eq!(backoff.delay_with(quick, 0.5), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_symbols_stay_stale_until_resynced() {
+        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let health = StreamHealth::new("Binance", &symbols);
+        assert!(health.is_stale("BTCUSDT"));
+
+        health.record_connected();
+        health.record_synced("BTCUSDT");
+        health.record_synced("ETHUSDT");
+        health.record_gap("ETHUSDT");
+        assert_eq!(health.status().stale_symbols, ["ETHUSDT"]);
+
+        health.record_disconnected(&symbols);
+        health.record_reconnect();
+        let status = health.status();
+        assert!(!status.connected);
+        assert_eq!((status.reconnects, status.gaps, status.stale_symbols.len()), (1, 1, 2));
+
+        let text = prometheus_metrics(&[Arc::new(health)]);
+        assert!(text.contains("arbitrage_depth_stream_stale_symbols{exchange=\"Binance\"} 2"));
+        assert!(text.contains("arbitrage_depth_stream_reconnects_total{exchange=\"Binance\"} 1"));
+    }
+}
@@ -0,0 +1,163 @@
+//! Structured log of every request the exchange clients send.
+//!
+//! One JSON line per attempt: the method, host, endpoint and query, the
+//! status or transport error, the round trip, and how far into its retries
+//! the request was, counting both `retry` attempts and hosts tried by the
+//! endpoint pool. Signatures and keys are masked in the query, errors
+//! arrive without their query string, and headers and bodies are never
+//! written. The file is rotated by size so it can stay on in production
+//! while a signature or rate-limit problem is chased down.
+
+use crate::config::RequestLogConfig;
+use crate::retry;
+use crate::secrets::redact_query;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use log::warn;
+use reqwest::{Method, Response, Url};
+use serde::Serialize;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, Serialize)]
+struct RequestRecord<'a> {
+    at: DateTime<Utc>,
+    exchange: &'a str,
+    method: &'a str,
+    host: String,
+    endpoint: &'a str,
+    query: Option<String>,
+    status: Option<u16>,
+    latency_ms: f64,
+    /// Attempt of the surrounding `retry`, 1 for the first
+    attempt: u32,
+    /// Host tried by the endpoint pool, 1 for the first
+    host_attempt: usize,
+    error: Option<String>,
+}
+
+#[derive(Debug)]
+struct LogFile {
+    file: File,
+    size: u64,
+}
+
+#[derive(Debug)]
+pub struct RequestLog {
+    path: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    file: Mutex<LogFile>,
+}
+
+impl RequestLog {
+    pub fn open(config: &RequestLogConfig) -> Result<Self> {
+        let path = PathBuf::from(&config.path);
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let file = open_append(&path)?;
+        Ok(Self {
+            path,
+            max_bytes: config.max_file_mb * 1024 * 1024,
+            max_files: config.max_files,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Logs one attempt at a request to `url`.
+    pub fn record(
+        &self,
+        exchange: &str,
+        method: &Method,
+        url: &Url,
+        result: &Result<Response, reqwest::Error>,
+        latency: Duration,
+        host_attempt: usize,
+    ) {
+        let record = RequestRecord {
+            at: Utc::now(),
+            exchange,
+            method: method.as_str(),
+            host: url.origin().ascii_serialization(),
+            endpoint: url.path(),
+            query: url.query().map(redact_query),
+            status: result.as_ref().ok().map(|response| response.status().as_u16()),
+            latency_ms: latency.as_secs_f64() * 1000.0,
+            attempt: retry::current_attempt(),
+            host_attempt,
+            error: result.as_ref().err().map(|e| e.to_string()),
+        };
+        let Ok(line) = serde_json::to_string(&record) else { return };
+        if let Err(e) = self.write_line(&line) {
+            warn!("Failed to write request log {}: {:#}", self.path.display(), e);
+        }
+    }
+
+    fn write_line(&self, line: &str) -> Result<()> {
+        let mut file = self.file.lock().unwrap();
+        let len = line.len() as u64 + 1;
+        if file.size > 0 && file.size + len > self.max_bytes {
+            *file = self.rotate()?;
+        }
+        writeln!(file.file, "{}", line)?;
+        file.size += len;
+        Ok(())
+    }
+
+    /// Shifts `path.1` to `path.2` and so on, dropping the oldest, moves
+    /// the current file to `path.1` and opens a fresh one.
+    fn rotate(&self) -> Result<LogFile> {
+        if self.max_files == 0 {
+            fs::remove_file(&self.path)?;
+            return open_append(&self.path);
+        }
+        for index in (1..self.max_files).rev() {
+            let from = rotated_path(&self.path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        open_append(&self.path)
+    }
+}
+
+fn open_append(path: &Path) -> Result<LogFile> {
+    let file = OpenOptions::new().create(true).append(true).open(path)
+        .with_context(|| format!("Failed to open request log {}", path.display()))?;
+    let size = file.metadata()?.len();
+    Ok(LogFile { file, size })
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_rotates_by_size_and_keeps_max_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("requests.jsonl");
+        let config = RequestLogConfig { enabled: true, path: path.to_string_lossy().into_owned(), max_file_mb: 1, max_files: 2 };
+        let mut log = RequestLog::open(&config).unwrap();
+        log.max_bytes = 24;
+
+        for line in ["first line", "second line", "third line", "fourth line", "fifth line", "sixth line", "last line"] {
+            log.write_line(line).unwrap();
+        }
+        let read = |path: &Path| fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "last line\n");
+        assert_eq!(read(&rotated_path(&path, 1)), "fifth line\nsixth line\n");
+        assert_eq!(read(&rotated_path(&path, 2)), "third line\nfourth line\n");
+        assert!(!rotated_path(&path, 3).exists());
+    }
+}
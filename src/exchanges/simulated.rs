@@ -0,0 +1,617 @@
+//! Simulated exchange backed by an in-memory matching engine.
+//!
+//! Each symbol has a limit book matched with price-time priority: an
+//! incoming order takes the best opposite price first and, within a price,
+//! the order that arrived first. Market orders walk the book until filled
+//! or out of liquidity, immediate-or-cancel and fill-or-kill orders expire
+//! their remainder, post-only orders are rejected when they would take, and
+//! good-till-canceled remainders rest on the book. Liquidity from other
+//! participants is added with `add_liquidity` or `submit_external`; only
+//! orders sent through `submit` count as the account's own, move its
+//! balances and show up in its open orders. No fees are charged and nothing
+//! touches the network, so executor behavior such as partial fills, IOC
+//! expiry and unwinds can be tested deterministically.
+
+use crate::precision::{quantize_qty, Rounding, DEFAULT_QUANTITY_STEP};
+use super::depth::DepthSnapshot;
+use super::fees::AccountFeeRates;
+use super::heartbeat::{CallStats, ExchangeHealth};
+use super::{
+    new_client_order_id, AccountTrade, ApiKeyPermissions, Balance, BookTicker, ExchangeError, HistoricalOrder, Kline, KlineInterval,
+    OpenOrder, OrderRef, OrderRequest, OrderSide, OrderState, OrderStatus, OrderType, PlacementOutcome, PublicTrade,
+    SubAccountTransfer, TimeInForce, TransferReceipt,
+};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::Range;
+use std::sync::{Mutex, MutexGuard};
+
+/// Base and quote asset of a listed symbol.
+#[derive(Debug, Clone)]
+struct Market {
+    base: String,
+    quote: String,
+}
+
+/// Unfilled part of an order resting at one price.
+#[derive(Debug)]
+struct Resting {
+    client_order_id: String,
+    remaining: Decimal,
+}
+
+/// Resting orders of one symbol, each price level queued in arrival order.
+#[derive(Debug, Default)]
+struct LimitBook {
+    bids: BTreeMap<Decimal, VecDeque<Resting>>,
+    asks: BTreeMap<Decimal, VecDeque<Resting>>,
+}
+
+impl LimitBook {
+    fn side_mut(&mut self, side: &OrderSide) -> &mut BTreeMap<Decimal, VecDeque<Resting>> {
+        match side {
+            OrderSide::Buy => &mut self.bids,
+            OrderSide::Sell => &mut self.asks,
+        }
+    }
+
+    /// Best price an order on `side` would take, within `limit`.
+    fn best_opposite(&self, side: &OrderSide, limit: Option<Decimal>) -> Option<Decimal> {
+        let price = match side {
+            OrderSide::Buy => *self.asks.keys().next()?,
+            OrderSide::Sell => *self.bids.keys().next_back()?,
+        };
+        crosses(side, price, limit).then_some(price)
+    }
+
+    /// Quantity an order on `side` could take within `limit`.
+    fn available(&self, side: &OrderSide, limit: Option<Decimal>) -> Decimal {
+        let levels: Box<dyn Iterator<Item = (&Decimal, &VecDeque<Resting>)>> = match side {
+            OrderSide::Buy => Box::new(self.asks.iter()),
+            OrderSide::Sell => Box::new(self.bids.iter().rev()),
+        };
+        levels.take_while(|(price, _)| crosses(side, **price, limit))
+            .flat_map(|(_, queue)| queue.iter().map(|resting| resting.remaining))
+            .sum()
+    }
+
+    /// Aggregated levels of one side, best first.
+    fn levels(&self, side: &OrderSide, limit: usize) -> Vec<(Decimal, Decimal)> {
+        let total = |(price, queue): (&Decimal, &VecDeque<Resting>)| (*price, queue.iter().map(|r| r.remaining).sum());
+        match side {
+            OrderSide::Buy => self.bids.iter().rev().take(limit).map(total).collect(),
+            OrderSide::Sell => self.asks.iter().take(limit).map(total).collect(),
+        }
+    }
+}
+
+/// Whether an order on `side` limited at `limit` may trade at `price`.
+fn crosses(side: &OrderSide, price: Decimal, limit: Option<Decimal>) -> bool {
+    match (side, limit) {
+        (_, None) => true,
+        (OrderSide::Buy, Some(limit)) => price <= limit,
+        (OrderSide::Sell, Some(limit)) => price >= limit,
+    }
+}
+
+#[derive(Debug)]
+struct SimulatedOrder {
+    symbol: String,
+    side: OrderSide,
+    price: Option<Decimal>,
+    state: OrderState,
+    /// Quote value of the fills so far, for the average price
+    filled_notional: Decimal,
+    /// Sent through `submit` rather than by another participant
+    own: bool,
+    created_at: DateTime<Utc>,
+}
+
+impl SimulatedOrder {
+    fn fill(&mut self, price: Decimal, quantity: Decimal) {
+        self.state.executed_quantity += quantity;
+        self.filled_notional += price * quantity;
+        self.state.average_price = Some(self.filled_notional / self.state.executed_quantity);
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct MatchingEngine {
+    markets: HashMap<String, Market>,
+    books: HashMap<String, LimitBook>,
+    orders: HashMap<String, SimulatedOrder>,
+    balances: BTreeMap<String, Decimal>,
+    trades: HashMap<String, Vec<PublicTrade>>,
+    /// Fills of the account's own orders, oldest first
+    fills: Vec<AccountTrade>,
+    next_order_id: u64,
+}
+
+impl MatchingEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lists `symbol`, trading `base` against `quote`.
+    pub fn with_market(mut self, symbol: &str, base: &str, quote: &str) -> Self {
+        self.markets.insert(symbol.to_string(), Market { base: base.to_string(), quote: quote.to_string() });
+        self.books.entry(symbol.to_string()).or_default();
+        self
+    }
+
+    /// Credits the account's free balance of `asset`.
+    pub fn with_balance(mut self, asset: &str, amount: Decimal) -> Self {
+        *self.balances.entry(asset.to_string()).or_default() += amount;
+        self
+    }
+
+    /// Rests a limit order of another participant, matching it first if
+    /// it crosses the book. Returns its client order ID.
+    pub fn add_liquidity(&mut self, symbol: &str, side: OrderSide, price: Decimal, quantity: Decimal) -> Result<String, ExchangeError> {
+        let order = OrderRequest {
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            quote_quantity: None,
+            price: Some(price),
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::Gtc,
+            client_order_id: None,
+        };
+        self.submit_external(&order).map(|state| state.client_order_id)
+    }
+
+    /// Matches an order of the account and returns its state right after.
+    pub fn submit(&mut self, order: &OrderRequest) -> Result<OrderState, ExchangeError> {
+        self.place(order, true)
+    }
+
+    /// Matches an order of another participant, such as a taker hitting
+    /// one of the account's resting orders.
+    pub fn submit_external(&mut self, order: &OrderRequest) -> Result<OrderState, ExchangeError> {
+        self.place(order, false)
+    }
+
+    fn place(&mut self, order: &OrderRequest, own: bool) -> Result<OrderState, ExchangeError> {
+        let market = self.markets.get(&order.symbol).cloned()
+            .ok_or_else(|| ExchangeError::InvalidRequest(format!("Invalid symbol {}", order.symbol)))?;
+        let limit = match order.order_type {
+            OrderType::Market => None,
+            OrderType::Limit => Some(order.price.filter(|p| *p > Decimal::ZERO)
+                .ok_or_else(|| ExchangeError::InvalidRequest("Limit order without a positive price".to_string()))?),
+        };
+        let quote_budget = order.quote_quantity.filter(|_| order.order_type == OrderType::Market);
+        if quote_budget.unwrap_or(order.quantity) <= Decimal::ZERO {
+            return Err(ExchangeError::InvalidRequest(format!("Order quantity must be positive, got {}", order.quantity)));
+        }
+        let client_order_id = order.client_order_id.clone().unwrap_or_else(new_client_order_id);
+        if self.orders.contains_key(&client_order_id) {
+            return Err(ExchangeError::InvalidRequest(format!("Duplicate client order ID {}", client_order_id)));
+        }
+
+        self.next_order_id += 1;
+        self.orders.insert(client_order_id.clone(), SimulatedOrder {
+            symbol: order.symbol.clone(),
+            side: order.side.clone(),
+            price: limit,
+            state: OrderState {
+                client_order_id: client_order_id.clone(),
+                exchange_order_id: Some(self.next_order_id.to_string()),
+                status: OrderStatus::New,
+                executed_quantity: Decimal::ZERO,
+                average_price: None,
+            },
+            filled_notional: Decimal::ZERO,
+            own,
+            created_at: Utc::now(),
+        });
+
+        let book = self.books.entry(order.symbol.clone()).or_default();
+        let would_take = book.best_opposite(&order.side, limit).is_some();
+        let status = if order.time_in_force == TimeInForce::PostOnly && order.order_type == OrderType::Limit && would_take {
+            OrderStatus::Rejected
+        } else if order.time_in_force == TimeInForce::Fok && book.available(&order.side, limit) < order.quantity {
+            OrderStatus::Expired
+        } else if self.take(&client_order_id, &market, order, limit, quote_budget) {
+            OrderStatus::Filled
+        } else if order.order_type == OrderType::Market || !matches!(order.time_in_force, TimeInForce::Gtc | TimeInForce::PostOnly) {
+            OrderStatus::Expired
+        } else {
+            let executed = self.orders[&client_order_id].state.executed_quantity;
+            let book = self.books.get_mut(&order.symbol).expect("book of a listed symbol");
+            book.side_mut(&order.side).entry(limit.expect("resting orders are limits")).or_default()
+                .push_back(Resting { client_order_id: client_order_id.clone(), remaining: order.quantity - executed });
+            if executed > Decimal::ZERO { OrderStatus::PartiallyFilled } else { OrderStatus::New }
+        };
+        let placed = self.orders.get_mut(&client_order_id).expect("order just inserted");
+        placed.state.status = status;
+        Ok(placed.state.clone())
+    }
+
+    /// Fills the taker order `client_order_id` against the book and
+    /// returns whether it filled completely. A quote-sized market order is
+    /// complete once what is left of its quote buys less than the smallest
+    /// quantity step.
+    fn take(&mut self, client_order_id: &str, market: &Market, order: &OrderRequest, limit: Option<Decimal>, quote_budget: Option<Decimal>) -> bool {
+        let book = self.books.get_mut(&order.symbol).expect("book of a listed symbol");
+        let opposite = match order.side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+        let mut remaining = order.quantity;
+        let mut budget = quote_budget;
+        // Maker, price, quantity and whether the maker is now filled
+        let mut fills: Vec<(String, Decimal, Decimal, bool)> = Vec::new();
+        let complete = loop {
+            if budget.unwrap_or(remaining) <= Decimal::ZERO {
+                break true;
+            }
+            let Some(price) = book.best_opposite(&order.side, limit) else { break false };
+            let wanted = match budget {
+                Some(budget) => quantize_qty(budget / price, DEFAULT_QUANTITY_STEP, Rounding::Down),
+                None => remaining,
+            };
+            if wanted <= Decimal::ZERO {
+                break true;
+            }
+            let level = book.side_mut(&opposite);
+            let queue = level.get_mut(&price).expect("best level exists");
+            let maker = queue.front_mut().expect("levels are never empty");
+            let quantity = wanted.min(maker.remaining);
+            maker.remaining -= quantity;
+            let maker_filled = maker.remaining <= Decimal::ZERO;
+            fills.push((maker.client_order_id.clone(), price, quantity, maker_filled));
+            if maker_filled {
+                queue.pop_front();
+                if queue.is_empty() {
+                    level.remove(&price);
+                }
+            }
+            match &mut budget {
+                Some(budget) => *budget -= price * quantity,
+                None => remaining -= quantity,
+            }
+        };
+
+        for (maker_id, price, quantity, maker_filled) in fills {
+            self.settle(&maker_id, market, price, quantity);
+            if let Some(maker) = self.orders.get_mut(&maker_id) {
+                maker.state.status = if maker_filled { OrderStatus::Filled } else { OrderStatus::PartiallyFilled };
+            }
+            self.settle(client_order_id, market, price, quantity);
+            self.trades.entry(order.symbol.clone()).or_default()
+                .push(PublicTrade { price, quantity, timestamp: Utc::now() });
+        }
+        complete
+    }
+
+    /// Records a fill of `quantity` at `price` on `client_order_id` and
+    /// moves the account's balances when the order is its own.
+    fn settle(&mut self, client_order_id: &str, market: &Market, price: Decimal, quantity: Decimal) {
+        let Some(order) = self.orders.get_mut(client_order_id) else { return };
+        order.fill(price, quantity);
+        if !order.own {
+            return;
+        }
+        self.fills.push(AccountTrade {
+            symbol: order.symbol.clone(),
+            trade_id: (self.fills.len() + 1).to_string(),
+            order_id: order.state.exchange_order_id.clone().unwrap_or_default(),
+            side: order.side.clone(),
+            price,
+            quantity,
+            fee: Decimal::ZERO,
+            fee_asset: market.quote.clone(),
+            timestamp: Utc::now(),
+        });
+        let (base, quote) = match order.side {
+            OrderSide::Buy => (quantity, -(price * quantity)),
+            OrderSide::Sell => (-quantity, price * quantity),
+        };
+        *self.balances.entry(market.base.clone()).or_default() += base;
+        *self.balances.entry(market.quote.clone()).or_default() += quote;
+    }
+
+    /// Cancels an open order and returns its final state.
+    pub fn cancel(&mut self, symbol: &str, client_order_id: &str) -> Result<OrderState, ExchangeError> {
+        let order = self.orders.get_mut(client_order_id)
+            .filter(|order| order.symbol == symbol && order.state.status.is_open())
+            .ok_or_else(|| ExchangeError::InvalidRequest(format!("Unknown order sent: {} is not open on {}", client_order_id, symbol)))?;
+        if let (Some(book), Some(price)) = (self.books.get_mut(symbol), order.price) {
+            let level = book.side_mut(&order.side);
+            if let Some(queue) = level.get_mut(&price) {
+                queue.retain(|resting| resting.client_order_id != client_order_id);
+                if queue.is_empty() {
+                    level.remove(&price);
+                }
+            }
+        }
+        order.state.status = OrderStatus::Canceled;
+        Ok(order.state.clone())
+    }
+
+    pub fn order(&self, client_order_id: &str) -> Option<OrderState> {
+        self.orders.get(client_order_id).map(|order| order.state.clone())
+    }
+
+    /// The account's orders still resting on a book.
+    pub fn open_orders(&self) -> Vec<OpenOrder> {
+        let mut open: Vec<OpenOrder> = self.orders.values()
+            .filter(|order| order.own && order.state.status.is_open())
+            .map(|order| OpenOrder { symbol: order.symbol.clone(), state: order.state.clone() })
+            .collect();
+        open.sort_by(|a, b| a.state.client_order_id.cmp(&b.state.client_order_id));
+        open
+    }
+
+    /// The account's orders of `symbol` created in `range`, oldest first.
+    pub fn order_history(&self, symbol: &str, range: Range<DateTime<Utc>>) -> Vec<HistoricalOrder> {
+        let mut orders: Vec<HistoricalOrder> = self.orders.values()
+            .filter(|order| order.own && order.symbol == symbol && range.contains(&order.created_at))
+            .map(|order| HistoricalOrder {
+                symbol: order.symbol.clone(),
+                side: order.side.clone(),
+                state: order.state.clone(),
+                created_at: order.created_at,
+            })
+            .collect();
+        orders.sort_by_key(|order| (order.created_at, order.state.client_order_id.clone()));
+        orders
+    }
+
+    /// Fills of the account's `symbol` orders in `range`, oldest first.
+    pub fn account_trades(&self, symbol: &str, range: Range<DateTime<Utc>>) -> Vec<AccountTrade> {
+        self.fills.iter()
+            .filter(|fill| fill.symbol == symbol && range.contains(&fill.timestamp))
+            .cloned()
+            .collect()
+    }
+
+    /// Free balances of the account; nothing is locked by resting orders.
+    pub fn balances(&self) -> Vec<Balance> {
+        self.balances.iter()
+            .map(|(asset, free)| Balance { asset: asset.clone(), free: *free, locked: Decimal::ZERO })
+            .collect()
+    }
+
+    pub fn book_ticker(&self, symbol: &str) -> Option<BookTicker> {
+        let book = self.books.get(symbol)?;
+        let (bid_price, bid_quantity) = *book.levels(&OrderSide::Buy, 1).first()?;
+        let (ask_price, ask_quantity) = *book.levels(&OrderSide::Sell, 1).first()?;
+        Some(BookTicker { symbol: symbol.to_string(), bid_price, bid_quantity, ask_price, ask_quantity, timestamp: Some(Utc::now()) })
+    }
+
+    pub fn depth(&self, symbol: &str, limit: usize) -> DepthSnapshot {
+        let (bids, asks) = self.books.get(symbol)
+            .map(|book| (book.levels(&OrderSide::Buy, limit), book.levels(&OrderSide::Sell, limit)))
+            .unwrap_or_default();
+        DepthSnapshot { last_update_id: self.next_order_id, bids, asks }
+    }
+
+    /// The latest `limit` trades of `symbol`, oldest first.
+    pub fn recent_trades(&self, symbol: &str, limit: usize) -> Vec<PublicTrade> {
+        let trades = self.trades.get(symbol).map(Vec::as_slice).unwrap_or_default();
+        trades[trades.len().saturating_sub(limit)..].to_vec()
+    }
+}
+
+/// A venue whose orders go to a `MatchingEngine`, usable wherever an
+/// `ExchangeClient` is. It answers to the name of the exchange it stands
+/// in for, so execution steps route to it unchanged.
+#[derive(Debug)]
+pub struct SimulatedExchange {
+    name: &'static str,
+    engine: Mutex<MatchingEngine>,
+}
+
+impl SimulatedExchange {
+    pub fn new(name: &'static str, engine: MatchingEngine) -> Self {
+        Self { name, engine: Mutex::new(engine) }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Sends no requests, so its health never shows any.
+    pub fn health(&self) -> ExchangeHealth {
+        CallStats::new(self.name).health(None)
+    }
+
+    /// The engine, to add liquidity or trade against the account's orders.
+    pub fn engine(&self) -> MutexGuard<'_, MatchingEngine> {
+        self.engine.lock().unwrap()
+    }
+
+    pub async fn get_book_ticker(&self, symbol: &str) -> Result<BookTicker> {
+        Ok(self.engine().book_ticker(symbol)
+            .ok_or_else(|| ExchangeError::InvalidRequest(format!("No two-sided book for {}", symbol)))?)
+    }
+
+    pub async fn get_book_tickers(&self) -> Result<HashMap<String, BookTicker>> {
+        let engine = self.engine();
+        Ok(engine.books.keys()
+            .filter_map(|symbol| engine.book_ticker(symbol).map(|ticker| (symbol.clone(), ticker)))
+            .collect())
+    }
+
+    pub async fn get_depth_snapshot(&self, symbol: &str, limit: usize) -> Result<DepthSnapshot> {
+        Ok(self.engine().depth(symbol, limit))
+    }
+
+    pub async fn get_recent_trades(&self, symbol: &str, limit: usize) -> Result<Vec<PublicTrade>> {
+        Ok(self.engine().recent_trades(symbol, limit))
+    }
+
+    pub async fn get_klines(&self, _symbol: &str, _interval: KlineInterval, _range: Range<DateTime<Utc>>) -> Result<Vec<Kline>> {
+        Err(ExchangeError::InvalidRequest("The simulated exchange keeps no candles".to_string()).into())
+    }
+
+    /// Orders are matched in-process and cannot time out, so they are
+    /// always `Placed`.
+    pub async fn place_order_cancel_safe(&self, order: &OrderRequest) -> Result<PlacementOutcome> {
+        Ok(PlacementOutcome::Placed(self.engine().submit(order)?))
+    }
+
+    pub async fn get_order_by_client_id(&self, _symbol: &str, client_order_id: &str) -> Result<Option<OrderState>> {
+        Ok(self.engine().order(client_order_id))
+    }
+
+    pub async fn cancel_order_by_client_id(&self, symbol: &str, client_order_id: &str) -> Result<OrderState> {
+        Ok(self.engine().cancel(symbol, client_order_id)?)
+    }
+
+    pub async fn cancel_orders_by_client_id(&self, orders: &[OrderRef]) -> Vec<Result<()>> {
+        let mut engine = self.engine();
+        orders.iter()
+            .map(|order| engine.cancel(&order.symbol, &order.client_order_id).map(|_| ()).map_err(Into::into))
+            .collect()
+    }
+
+    pub async fn get_open_orders(&self) -> Result<Vec<OpenOrder>> {
+        Ok(self.engine().open_orders())
+    }
+
+    pub async fn get_balances(&self) -> Result<Vec<Balance>> {
+        Ok(self.engine().balances())
+    }
+
+    pub async fn get_order_history(&self, symbol: &str, range: Range<DateTime<Utc>>) -> Result<Vec<HistoricalOrder>> {
+        Ok(self.engine().order_history(symbol, range))
+    }
+
+    pub async fn get_my_trades(&self, symbol: &str, range: Range<DateTime<Utc>>) -> Result<Vec<AccountTrade>> {
+        Ok(self.engine().account_trades(symbol, range))
+    }
+
+    pub async fn transfer_between_sub_accounts(&self, _transfer: &SubAccountTransfer) -> Result<TransferReceipt> {
+        Err(ExchangeError::InvalidRequest("The simulated exchange has no sub-accounts".to_string()).into())
+    }
+
+    pub async fn get_api_key_permissions(&self) -> Result<ApiKeyPermissions> {
+        Ok(ApiKeyPermissions { read: true, trade: true, withdraw: false, ip_restricted: true })
+    }
+
+    pub async fn get_fee_rates(&self, _symbol: &str) -> Result<AccountFeeRates> {
+        Ok(AccountFeeRates { maker_fee: Decimal::ZERO, taker_fee: Decimal::ZERO, bnb_fee_multiplier: None })
+    }
+
+    pub async fn ping(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// There is one host, and it always answers.
+    pub async fn probe_endpoints(&self) -> usize {
+        1
+    }
+
+    pub async fn get_server_time(&self) -> Result<DateTime<Utc>> {
+        Ok(Utc::now())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+
+    fn order(side: OrderSide, order_type: OrderType, time_in_force: TimeInForce, price: Option<&str>, quantity: &str) -> OrderRequest {
+        OrderRequest {
+            symbol: "BTCUSDT".to_string(),
+            side,
+            quantity: dec(quantity),
+            quote_quantity: None,
+            price: price.map(dec),
+            order_type,
+            time_in_force,
+            client_order_id: Some(new_client_order_id()),
+        }
+    }
+
+    fn engine() -> MatchingEngine {
+        let mut engine = MatchingEngine::new()
+            .with_market("BTCUSDT", "BTC", "USDT")
+            .with_balance("USDT", dec("100000"));
+        engine.add_liquidity("BTCUSDT", OrderSide::Sell, dec("100"), dec("1")).unwrap();
+        engine.add_liquidity("BTCUSDT", OrderSide::Sell, dec("101"), dec("2")).unwrap();
+        engine.add_liquidity("BTCUSDT", OrderSide::Buy, dec("99"), dec("1")).unwrap();
+        engine
+    }
+
+    #[test]
+    fn test_orders_match_by_price_then_time() {
+        let mut engine = engine();
+        let first = engine.add_liquidity("BTCUSDT", OrderSide::Sell, dec("100"), dec("1")).unwrap();
+        let second = engine.add_liquidity("BTCUSDT", OrderSide::Sell, dec("100"), dec("1")).unwrap();
+
+        // The resting order at 100 from the setup came before both
+        let state = engine.submit(&order(OrderSide::Buy, OrderType::Market, TimeInForce::Gtc, None, "2.5")).unwrap();
+        assert_eq!(state.status, OrderStatus::Filled);
+        assert_eq!(state.average_price, Some(dec("100")));
+        assert_eq!(engine.order(&first).unwrap().status, OrderStatus::Filled);
+        let second = engine.order(&second).unwrap();
+        assert_eq!((second.status, second.executed_quantity), (OrderStatus::PartiallyFilled, dec("0.5")));
+
+        let ticker = engine.book_ticker("BTCUSDT").unwrap();
+        assert_eq!((ticker.ask_price, ticker.ask_quantity), (dec("100"), dec("0.5")));
+        let balances = engine.balances();
+        assert_eq!((balances[0].asset.as_str(), balances[0].free), ("BTC", dec("2.5")));
+        assert_eq!((balances[1].asset.as_str(), balances[1].free), ("USDT", dec("99750")));
+        assert_eq!(engine.recent_trades("BTCUSDT", 2).len(), 2);
+    }
+
+    #[test]
+    fn test_time_in_force_decides_what_happens_to_the_rest() {
+        // IOC takes what is within its limit and expires the rest
+        let mut engine = engine();
+        let state = engine.submit(&order(OrderSide::Buy, OrderType::Limit, TimeInForce::Ioc, Some("100"), "1.5")).unwrap();
+        assert_eq!((state.status, state.executed_quantity), (OrderStatus::Expired, dec("1")));
+        assert!(engine.open_orders().is_empty());
+
+        // FOK fills nothing unless it can fill everything
+        let mut engine = self::engine();
+        let state = engine.submit(&order(OrderSide::Buy, OrderType::Limit, TimeInForce::Fok, Some("101"), "3.5")).unwrap();
+        assert_eq!((state.status, state.executed_quantity), (OrderStatus::Expired, Decimal::ZERO));
+        let state = engine.submit(&order(OrderSide::Buy, OrderType::Limit, TimeInForce::Fok, Some("101"), "3")).unwrap();
+        assert_eq!((state.status, state.average_price), (OrderStatus::Filled, Some(dec("302") / dec("3"))));
+
+        // Post-only is rejected when it would take, and rests otherwise
+        let mut engine = self::engine();
+        let state = engine.submit(&order(OrderSide::Buy, OrderType::Limit, TimeInForce::PostOnly, Some("100"), "1")).unwrap();
+        assert_eq!(state.status, OrderStatus::Rejected);
+        let resting = engine.submit(&order(OrderSide::Buy, OrderType::Limit, TimeInForce::PostOnly, Some("99.5"), "1")).unwrap();
+        assert_eq!(resting.status, OrderStatus::New);
+        assert_eq!(engine.book_ticker("BTCUSDT").unwrap().bid_price, dec("99.5"));
+
+        // A GTC remainder rests until another participant takes it
+        let state = engine.submit(&order(OrderSide::Buy, OrderType::Limit, TimeInForce::Gtc, Some("100"), "1.5")).unwrap();
+        assert_eq!((state.status, state.executed_quantity), (OrderStatus::PartiallyFilled, dec("1")));
+        assert_eq!(engine.open_orders().len(), 2);
+        engine.add_liquidity("BTCUSDT", OrderSide::Sell, dec("99"), dec("0.5")).unwrap();
+        let state = engine.order(&state.client_order_id).unwrap();
+        assert_eq!((state.status, state.executed_quantity), (OrderStatus::Filled, dec("1.5")));
+
+        let canceled = engine.cancel("BTCUSDT", &resting.client_order_id).unwrap();
+        assert_eq!(canceled.status, OrderStatus::Canceled);
+        assert!(engine.cancel("BTCUSDT", &resting.client_order_id).is_err());
+        assert_eq!(engine.book_ticker("BTCUSDT").unwrap().bid_price, dec("99"));
+    }
+
+    #[test]
+    fn test_market_orders_stop_when_the_book_runs_out() {
+        let mut engine = engine();
+        let mut buy = order(OrderSide::Buy, OrderType::Market, TimeInForce::Gtc, None, "0");
+        buy.quote_quantity = Some(dec("150.5"));
+        let state = engine.submit(&buy).unwrap();
+        assert_eq!((state.status, state.executed_quantity), (OrderStatus::Filled, dec("1.5")));
+
+        let state = engine.submit(&order(OrderSide::Sell, OrderType::Market, TimeInForce::Gtc, None, "2")).unwrap();
+        assert_eq!((state.status, state.executed_quantity), (OrderStatus::Expired, dec("1")));
+        assert!(engine.submit(&OrderRequest { client_order_id: buy.client_order_id.clone(), ..buy }).is_err());
+    }
+}
@@ -0,0 +1,363 @@
+use super::PriceMap;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::{Arc, PoisonError, RwLock};
+
+/// Index of a symbol interned in a `SymbolTable`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SymbolId(u32);
+
+impl SymbolId {
+    pub fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// Symbol names interned once per process, so per-scan price data can be
+/// indexed by `SymbolId` instead of cloning and hashing a `String` for
+/// every ticker. Entries are never removed; the table only grows when an
+/// exchange lists a new pair.
+#[derive(Debug, Default)]
+pub struct SymbolTable {
+    inner: RwLock<Interned>,
+}
+
+#[derive(Debug, Default)]
+struct Interned {
+    ids: HashMap<Arc<str>, SymbolId>,
+    names: Vec<Arc<str>>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn get(&self, symbol: &str) -> Option<SymbolId> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner).ids.get(symbol).copied()
+    }
+    
+    /// Returns the symbol's ID, allocating only the first time it is seen.
+    pub fn intern(&self, symbol: &str) -> SymbolId {
+        if let Some(id) = self.get(symbol) {
+            return id;
+        }
+        
+        let mut inner = self.inner.write().unwrap_or_else(PoisonError::into_inner);
+        if let Some(id) = inner.ids.get(symbol) {
+            return *id;
+        }
+        let id = SymbolId(inner.names.len() as u32);
+        let name: Arc<str> = Arc::from(symbol);
+        inner.names.push(name.clone());
+        inner.ids.insert(name, id);
+        id
+    }
+    
+    pub fn name(&self, id: SymbolId) -> Option<Arc<str>> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner).names.get(id.index()).cloned()
+    }
+    
+    pub fn len(&self) -> usize {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner).names.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// One exchange's prices for one scan, stored densely by `SymbolId`.
+/// Unquoted symbols hold zero, which no exchange reports as a price, so
+/// filling a snapshot costs one allocation instead of one per symbol.
+///
+/// Every price carries the time it was captured: its own when inserted
+/// with one, otherwise the payload's event time, otherwise when the
+/// snapshot was received. Multi-leg detection compares these so it never
+/// prices a path from quotes taken seconds apart.
+#[derive(Debug, Clone)]
+pub struct PriceSnapshot {
+    symbols: Arc<SymbolTable>,
+    prices: Vec<Decimal>,
+    len: usize,
+    /// Event time of the payload the prices came from, when it has one
+    exchange_time: Option<DateTime<Utc>>,
+    received_at: DateTime<Utc>,
+    /// Per-symbol capture times; empty until a price is inserted with one
+    captured_at: Vec<Option<DateTime<Utc>>>,
+}
+
+impl PriceSnapshot {
+    pub fn new(symbols: Arc<SymbolTable>) -> Self {
+        let capacity = symbols.len();
+        Self {
+            symbols,
+            prices: Vec::with_capacity(capacity),
+            len: 0,
+            exchange_time: None,
+            received_at: Utc::now(),
+            captured_at: Vec::new(),
+        }
+    }
+    
+    pub fn from_price_map(symbols: Arc<SymbolTable>, prices: &PriceMap) -> Self {
+        let mut snapshot = Self::new(symbols);
+        for (symbol, price) in prices {
+            snapshot.insert(symbol, *price);
+        }
+        snapshot
+    }
+    
+    pub fn symbols(&self) -> &Arc<SymbolTable> {
+        &self.symbols
+    }
+    
+    pub fn exchange_time(&self) -> Option<DateTime<Utc>> {
+        self.exchange_time
+    }
+    
+    pub fn set_exchange_time(&mut self, exchange_time: Option<DateTime<Utc>>) {
+        self.exchange_time = exchange_time;
+    }
+    
+    pub fn received_at(&self) -> DateTime<Utc> {
+        self.received_at
+    }
+    
+    /// Stamps the snapshot with when its payload arrived; defaults to when
+    /// it was created.
+    pub fn set_received_at(&mut self, received_at: DateTime<Utc>) {
+        self.received_at = received_at;
+    }
+    
+    /// Records a price; non-positive prices are ignored.
+    pub fn insert(&mut self, symbol: &str, price: Decimal) {
+        let id = self.symbols.intern(symbol);
+        self.set(id, price);
+    }
+    
+    /// Records a price captured at `captured_at` rather than with the rest
+    /// of the payload.
+    pub fn insert_at(&mut self, symbol: &str, price: Decimal, captured_at: DateTime<Utc>) {
+        let id = self.symbols.intern(symbol);
+        if price <= Decimal::ZERO {
+            return;
+        }
+        self.set(id, price);
+        if id.index() >= self.captured_at.len() {
+            self.captured_at.resize(id.index() + 1, None);
+        }
+        self.captured_at[id.index()] = Some(captured_at);
+    }
+    
+    /// When the price of `symbol` was captured, if it is quoted.
+    pub fn captured_at(&self, symbol: &str) -> Option<DateTime<Utc>> {
+        let id = self.symbols.get(symbol)?;
+        self.get(id)?;
+        let own = self.captured_at.get(id.index()).copied().flatten();
+        Some(own.or(self.exchange_time).unwrap_or(self.received_at))
+    }
+    
+    pub fn set(&mut self, id: SymbolId, price: Decimal) {
+        if price <= Decimal::ZERO {
+            return;
+        }
+        if id.index() >= self.prices.len() {
+            self.prices.resize(id.index() + 1, Decimal::ZERO);
+        }
+        if self.prices[id.index()] == Decimal::ZERO {
+            self.len += 1;
+        }
+        self.prices[id.index()] = price;
+    }
+    
+    pub fn get(&self, id: SymbolId) -> Option<Decimal> {
+        self.prices.get(id.index()).copied().filter(|p| *p > Decimal::ZERO)
+    }
+    
+    pub fn price(&self, symbol: &str) -> Option<Decimal> {
+        self.symbols.get(symbol).and_then(|id| self.get(id))
+    }
+    
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    
+    pub fn iter(&self) -> impl Iterator<Item = (SymbolId, Decimal)> + '_ {
+        self.prices.iter()
+            .enumerate()
+            .filter(|(_, price)| **price > Decimal::ZERO)
+            .map(|(index, price)| (SymbolId(index as u32), *price))
+    }
+    
+    /// Visits every quoted symbol by name. The symbol table is read-locked
+    /// for the duration, so `f` must not intern new symbols.
+    pub fn for_each(&self, mut f: impl FnMut(&str, Decimal)) {
+        let inner = self.symbols.inner.read().unwrap_or_else(PoisonError::into_inner);
+        for (id, price) in self.iter() {
+            f(&inner.names[id.index()], price);
+        }
+    }
+    
+    /// Drops every symbol for which `keep` returns false.
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        let inner = self.symbols.inner.read().unwrap_or_else(PoisonError::into_inner);
+        for (index, price) in self.prices.iter_mut().enumerate() {
+            if *price > Decimal::ZERO && !keep(&inner.names[index]) {
+                *price = Decimal::ZERO;
+                self.len -= 1;
+            }
+        }
+    }
+    
+    /// Owned map for persistence and other consumers outside the scan path.
+    pub fn to_price_map(&self) -> PriceMap {
+        let mut map = PriceMap::with_capacity(self.len);
+        self.for_each(|symbol, price| {
+            map.insert(symbol.to_string(), price);
+        });
+        map
+    }
+}
+
+/// Read access shared by `PriceMap` and `PriceSnapshot`, so detection code
+/// runs on either.
+pub trait PriceLookup {
+    fn price(&self, symbol: &str) -> Option<Decimal>;
+    
+    fn for_each_price(&self, f: impl FnMut(&str, Decimal));
+    
+    /// When the exchange says `symbol`'s price was current, if it said.
+    fn exchange_time(&self, _symbol: &str) -> Option<DateTime<Utc>> {
+        None
+    }
+    
+    /// When `symbol`'s price was captured, if known.
+    fn captured_at(&self, symbol: &str) -> Option<DateTime<Utc>> {
+        self.exchange_time(symbol)
+    }
+}
+
+impl PriceLookup for PriceMap {
+    fn price(&self, symbol: &str) -> Option<Decimal> {
+        self.get(symbol).copied()
+    }
+    
+    fn for_each_price(&self, mut f: impl FnMut(&str, Decimal)) {
+        for (symbol, price) in self {
+            f(symbol, *price);
+        }
+    }
+}
+
+impl PriceLookup for PriceSnapshot {
+    fn price(&self, symbol: &str) -> Option<Decimal> {
+        PriceSnapshot::price(self, symbol)
+    }
+    
+    fn for_each_price(&self, f: impl FnMut(&str, Decimal)) {
+        self.for_each(f)
+    }
+    
+    fn exchange_time(&self, _symbol: &str) -> Option<DateTime<Utc>> {
+        self.exchange_time
+    }
+    
+    fn captured_at(&self, symbol: &str) -> Option<DateTime<Utc>> {
+        PriceSnapshot::captured_at(self, symbol)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+    
+    #[test]
+    fn test_interning_is_stable() {
+        let symbols = SymbolTable::new();
+        let btc = symbols.intern("BTCUSDT");
+        let eth = symbols.intern("ETHUSDT");
+        
+        assert_ne!(btc, eth);
+        assert_eq!(symbols.intern("BTCUSDT"), btc);
+        assert_eq!(symbols.get("ETHUSDT"), Some(eth));
+        assert_eq!(symbols.get("SOLUSDT"), None);
+        assert_eq!(symbols.name(eth).as_deref(), Some("ETHUSDT"));
+        assert_eq!(symbols.len(), 2);
+    }
+    
+    #[test]
+    fn test_snapshot_round_trips_price_map() {
+        let symbols = Arc::new(SymbolTable::new());
+        symbols.intern("DOGEUSDT");
+        let prices: PriceMap = [("BTCUSDT", "64000.1"), ("ETHBTC", "0.0512"), ("DEADUSDT", "0")].into_iter()
+            .map(|(symbol, price)| (symbol.to_string(), dec(price)))
+            .collect();
+        
+        let mut snapshot = PriceSnapshot::from_price_map(symbols.clone(), &prices);
+        assert_eq!(snapshot.len(), 2);
+        assert_eq!(snapshot.price("BTCUSDT"), Some(dec("64000.1")));
+        assert_eq!(snapshot.price("DOGEUSDT"), None);
+        assert_eq!(snapshot.price("DEADUSDT"), None);
+        
+        snapshot.insert("BTCUSDT", dec("64001"));
+        assert_eq!(snapshot.len(), 2);
+        
+        snapshot.retain(|symbol| symbol != "ETHBTC");
+        assert_eq!(snapshot.len(), 1);
+        assert_eq!(snapshot.to_price_map(), PriceMap::from([("BTCUSDT".to_string(), dec("64001"))]));
+    }
+    
+    #[test]
+    fn test_capture_time_falls_back_to_payload_then_receipt() {
+        let mut snapshot = PriceSnapshot::new(Arc::new(SymbolTable::new()));
+        let received_at: DateTime<Utc> = "2024-05-01T12:00:01Z".parse().unwrap();
+        let exchange_time: DateTime<Utc> = "2024-05-01T12:00:00.500Z".parse().unwrap();
+        let ticked_at: DateTime<Utc> = "2024-05-01T11:59:58Z".parse().unwrap();
+        snapshot.set_received_at(received_at);
+        snapshot.insert("BTCUSDT", dec("64000"));
+        snapshot.insert_at("ETHBTC", dec("0.0512"), ticked_at);
+        snapshot.insert_at("DEADUSDT", dec("0"), ticked_at);
+        
+        assert_eq!(snapshot.captured_at("BTCUSDT"), Some(received_at));
+        assert_eq!(snapshot.captured_at("ETHBTC"), Some(ticked_at));
+        assert_eq!(snapshot.captured_at("DEADUSDT"), None);
+        assert_eq!(snapshot.captured_at("SOLUSDT"), None);
+        
+        snapshot.set_exchange_time(Some(exchange_time));
+        assert_eq!(snapshot.captured_at("BTCUSDT"), Some(exchange_time));
+        assert_eq!(snapshot.captured_at("ETHBTC"), Some(ticked_at));
+    }
+    
+    #[tokio::test]
+    async fn test_engine_agrees_on_map_and_snapshot() {
+        let binance: PriceMap = [("BTCUSDT", "50000"), ("ETHBTC", "0.06"), ("ETHUSDT", "3060")].into_iter()
+            .map(|(symbol, price)| (symbol.to_string(), dec(price)))
+            .collect();
+        let bybit = PriceMap::new();
+        let symbols = Arc::new(SymbolTable::new());
+        
+        let from_map = crate::arbitrage::ArbitrageEngine::new().analyze_opportunities(&binance, &bybit).await.unwrap();
+        let from_snapshot = crate::arbitrage::ArbitrageEngine::new()
+            .analyze_opportunities(
+                &PriceSnapshot::from_price_map(symbols.clone(), &binance),
+                &PriceSnapshot::from_price_map(symbols, &bybit),
+            )
+            .await
+            .unwrap();
+        
+        assert_eq!(from_map.len(), 1);
+        assert_eq!(from_snapshot.len(), 1);
+        assert_eq!(from_map[0].path, from_snapshot[0].path);
+        assert_eq!(from_map[0].net_profit_percentage, from_snapshot[0].net_profit_percentage);
+    }
+}
@@ -0,0 +1,357 @@
+//! Websocket depth streams that keep `OrderBookStore` books current.
+//!
+//! Binance books start from a REST snapshot; diff events received while it
+//! loads are buffered and replayed on top. Bybit sends its own snapshot on
+//! subscribe. Either way a sequence gap drops the book until it has been
+//! rebuilt, and a dropped connection clears every book of that exchange
+//! until it reconnects, with the backoff and sync state kept in
+//! `reconnect`.
+
+use super::binance::BinanceClient;
+use super::depth::{parse_levels, BookUpdate, DepthSnapshot, LocalOrderBook, OrderBookStore};
+use super::fixtures::FixtureRecorder;
+use super::reconnect::{ReconnectBackoff, StreamHealth};
+use super::ExchangeError;
+use crate::config::OrderBookConfig;
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use log::{debug, info, warn};
+use rust_decimal::Decimal;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::task::JoinSet;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_util::sync::CancellationToken;
+
+/// Bybit spot accepts at most ten topics per subscribe request.
+const BYBIT_TOPICS_PER_REQUEST: usize = 10;
+const BYBIT_PING_INTERVAL: Duration = Duration::from_secs(20);
+
+struct BinanceDepthEvent {
+    symbol: String,
+    first_update_id: u64,
+    last_update_id: u64,
+    bids: Vec<(Decimal, Decimal)>,
+    asks: Vec<(Decimal, Decimal)>,
+}
+
+/// Keeps Binance books for `symbols` in `store` until `cancel` fires,
+/// reconnecting after failures and tracking the stream in `health`. Every
+/// message read is also recorded in `fixtures`, when given.
+pub async fn maintain_binance_books(
+    client: Arc<BinanceClient>,
+    symbols: Vec<String>,
+    config: OrderBookConfig,
+    store: Arc<OrderBookStore>,
+    health: Arc<StreamHealth>,
+    fixtures: Option<Arc<FixtureRecorder>>,
+    cancel: CancellationToken,
+) {
+    reconnect_loop("Binance", &symbols, &config, &store, &health, &cancel, || {
+        binance_session(&client, &symbols, &config, &store, &health, fixtures.as_deref(), &cancel)
+    }).await
+}
+
+/// Keeps Bybit books for `symbols` in `store` until `cancel` fires,
+/// reconnecting after failures and tracking the stream in `health`. Every
+/// message read is also recorded in `fixtures`, when given.
+pub async fn maintain_bybit_books(
+    symbols: Vec<String>,
+    config: OrderBookConfig,
+    store: Arc<OrderBookStore>,
+    health: Arc<StreamHealth>,
+    fixtures: Option<Arc<FixtureRecorder>>,
+    cancel: CancellationToken,
+) {
+    reconnect_loop("Bybit", &symbols, &config, &store, &health, &cancel, || {
+        bybit_session(&symbols, &config, &store, &health, fixtures.as_deref(), &cancel)
+    }).await
+}
+
+async fn reconnect_loop<F, Fut>(
+    exchange: &str,
+    symbols: &[String],
+    config: &OrderBookConfig,
+    store: &OrderBookStore,
+    health: &StreamHealth,
+    cancel: &CancellationToken,
+    mut session: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<()>>,
+{
+    let mut backoff = ReconnectBackoff::from_config(config);
+    while !cancel.is_cancelled() {
+        let started = Instant::now();
+        if let Err(e) = session().await {
+            warn!("{} depth stream failed: {:#}", exchange, e);
+        }
+        for symbol in symbols {
+            store.remove(exchange, symbol);
+        }
+        health.record_disconnected(symbols);
+
+        let delay = backoff.next_delay(started.elapsed());
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            _ = tokio::time::sleep(delay) => {}
+        }
+        info!("Reconnecting {} depth stream after {:?}", exchange, delay);
+        health.record_reconnect();
+    }
+}
+
+async fn binance_session(
+    client: &Arc<BinanceClient>,
+    symbols: &[String],
+    config: &OrderBookConfig,
+    store: &OrderBookStore,
+    health: &StreamHealth,
+    fixtures: Option<&FixtureRecorder>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let streams: Vec<String> = symbols.iter().map(|s| format!("{}@depth@100ms", s.to_lowercase())).collect();
+    let url = format!("{}/stream?streams={}", config.binance_stream_url.trim_end_matches('/'), streams.join("/"));
+    let (mut ws, _) = connect_async(url.as_str()).await
+        .map_err(|e| ExchangeError::NetworkError(format!("Binance depth stream connect failed: {}", e)))?;
+    info!("Binance depth stream connected for {} symbols", symbols.len());
+    health.record_connected();
+
+    // Symbols still waiting for a snapshot, with the events received meanwhile
+    let mut buffered: HashMap<String, Vec<BinanceDepthEvent>> = HashMap::new();
+    let mut snapshots = JoinSet::new();
+    for symbol in symbols {
+        buffered.insert(symbol.clone(), Vec::new());
+        request_snapshot(&mut snapshots, client, symbol, config.depth, Duration::ZERO);
+    }
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            Some(joined) = snapshots.join_next() => {
+                let (symbol, snapshot) = joined?;
+                let snapshot = match snapshot {
+                    Ok(snapshot) => snapshot,
+                    Err(e) => {
+                        warn!("Binance {} depth snapshot failed: {:#}", symbol, e);
+                        let retry_delay = Duration::from_secs(config.reconnect_delay_seconds);
+                        request_snapshot(&mut snapshots, client, &symbol, config.depth, retry_delay);
+                        continue;
+                    }
+                };
+
+                let pending = buffered.remove(&symbol).unwrap_or_default();
+                let mut book = LocalOrderBook::from_snapshot(&symbol, &snapshot);
+                match replay_buffered(&mut book, pending) {
+                    Ok(()) => {
+                        debug!("Binance {} book synced at update {}", symbol, book.last_update_id());
+                        store.insert("Binance", book);
+                        health.record_synced(&symbol);
+                    }
+                    Err(remaining) => {
+                        debug!("Binance {} snapshot {} behind the stream, refetching", symbol, snapshot.last_update_id);
+                        buffered.insert(symbol.clone(), remaining);
+                        request_snapshot(&mut snapshots, client, &symbol, config.depth, Duration::ZERO);
+                    }
+                }
+            }
+            message = ws.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Err(ExchangeError::NetworkError("Binance depth stream closed".to_string()).into());
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        return Err(ExchangeError::NetworkError(format!("Binance depth stream error: {}", e)).into());
+                    }
+                };
+                if let Some(fixtures) = fixtures {
+                    fixtures.record_message("Binance", &url, &text);
+                }
+                let event = match parse_binance_event(&text) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("Ignoring Binance depth message: {:#}", e);
+                        continue;
+                    }
+                };
+                health.record_message(&event.symbol);
+
+                if let Some(pending) = buffered.get_mut(&event.symbol) {
+                    pending.push(event);
+                    continue;
+                }
+                let update = store.update("Binance", &event.symbol, |book| {
+                    book.apply_binance_diff(event.first_update_id, event.last_update_id, &event.bids, &event.asks)
+                });
+                if let Some(BookUpdate::Gap { expected, received }) = update {
+                    warn!("Binance {} depth gap (expected update {}, got {}), resyncing", event.symbol, expected, received);
+                    store.remove("Binance", &event.symbol);
+                    health.record_gap(&event.symbol);
+                    let symbol = event.symbol.clone();
+                    buffered.insert(symbol.clone(), vec![event]);
+                    request_snapshot(&mut snapshots, client, &symbol, config.depth, Duration::ZERO);
+                }
+            }
+        }
+    }
+}
+
+fn request_snapshot(
+    snapshots: &mut JoinSet<(String, Result<DepthSnapshot>)>,
+    client: &Arc<BinanceClient>,
+    symbol: &str,
+    depth: usize,
+    delay: Duration,
+) {
+    let client = client.clone();
+    let symbol = symbol.to_string();
+    snapshots.spawn(async move {
+        tokio::time::sleep(delay).await;
+        let snapshot = client.get_depth_snapshot(&symbol, depth).await;
+        (symbol, snapshot)
+    });
+}
+
+/// Applies events buffered while the snapshot loaded. On a gap the
+/// snapshot is older than the stream, so the events from the gap on are
+/// handed back to wait for a newer one.
+fn replay_buffered(
+    book: &mut LocalOrderBook,
+    mut pending: Vec<BinanceDepthEvent>,
+) -> std::result::Result<(), Vec<BinanceDepthEvent>> {
+    for index in 0..pending.len() {
+        let event = &pending[index];
+        let update = book.apply_binance_diff(event.first_update_id, event.last_update_id, &event.bids, &event.asks);
+        if let BookUpdate::Gap { .. } = update {
+            return Err(pending.split_off(index));
+        }
+    }
+    Ok(())
+}
+
+fn parse_binance_event(text: &str) -> Result<BinanceDepthEvent> {
+    let message: Value = serde_json::from_str(text)
+        .map_err(|e| ExchangeError::ParseError(format!("Invalid depth message: {}", e)))?;
+    let data = message.get("data").unwrap_or(&message);
+    let field = |key: &str| data.get(key)
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| ExchangeError::ParseError(format!("Depth event has no {}", key)));
+
+    Ok(BinanceDepthEvent {
+        symbol: data.get("s")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| ExchangeError::ParseError("Depth event has no symbol".to_string()))?
+            .to_string(),
+        first_update_id: field("U")?,
+        last_update_id: field("u")?,
+        bids: parse_levels(data.get("b"))?,
+        asks: parse_levels(data.get("a"))?,
+    })
+}
+
+async fn bybit_session(
+    symbols: &[String],
+    config: &OrderBookConfig,
+    store: &OrderBookStore,
+    health: &StreamHealth,
+    fixtures: Option<&FixtureRecorder>,
+    cancel: &CancellationToken,
+) -> Result<()> {
+    let (mut ws, _) = connect_async(config.bybit_stream_url.as_str()).await
+        .map_err(|e| ExchangeError::NetworkError(format!("Bybit depth stream connect failed: {}", e)))?;
+    info!("Bybit depth stream connected for {} symbols", symbols.len());
+
+    let topics: Vec<String> = symbols.iter().map(|s| format!("orderbook.{}.{}", config.depth, s)).collect();
+    for chunk in topics.chunks(BYBIT_TOPICS_PER_REQUEST) {
+        ws.send(Message::Text(json!({ "op": "subscribe", "args": chunk }).to_string())).await?;
+    }
+    health.record_connected();
+
+    let mut ping = tokio::time::interval_at(tokio::time::Instant::now() + BYBIT_PING_INTERVAL, BYBIT_PING_INTERVAL);
+    loop {
+        let text = tokio::select! {
+            _ = cancel.cancelled() => return Ok(()),
+            _ = ping.tick() => {
+                ws.send(Message::Text(json!({ "op": "ping" }).to_string())).await?;
+                continue;
+            }
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => text,
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err(ExchangeError::NetworkError("Bybit depth stream closed".to_string()).into());
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    return Err(ExchangeError::NetworkError(format!("Bybit depth stream error: {}", e)).into());
+                }
+            },
+        };
+        if let Some(fixtures) = fixtures {
+            fixtures.record_message("Bybit", &config.bybit_stream_url, &text);
+        }
+
+        let message: Value = match serde_json::from_str(&text) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!("Ignoring Bybit depth message: {}", e);
+                continue;
+            }
+        };
+        if message.get("op").and_then(|v| v.as_str()) == Some("subscribe")
+            && message.get("success").and_then(|v| v.as_bool()) == Some(false) {
+            return Err(ExchangeError::InvalidRequest(format!(
+                "Bybit depth subscription rejected: {}",
+                message.get("ret_msg").and_then(|v| v.as_str()).unwrap_or_default()
+            )).into());
+        }
+        let Some(topic) = message.get("topic").and_then(|v| v.as_str()) else { continue };
+
+        if let Err(e) = apply_bybit_message(&message, store, health) {
+            warn!("Bybit {} depth message rejected: {:#}, resubscribing", topic, e);
+            let topic = topic.to_string();
+            for op in ["unsubscribe", "subscribe"] {
+                ws.send(Message::Text(json!({ "op": op, "args": [topic.as_str()] }).to_string())).await?;
+            }
+            health.record_resubscribe();
+        }
+    }
+}
+
+/// Applies a snapshot or delta; an error means the book was dropped and
+/// the topic needs a fresh snapshot.
+fn apply_bybit_message(message: &Value, store: &OrderBookStore, health: &StreamHealth) -> Result<()> {
+    let data = message.get("data")
+        .ok_or_else(|| ExchangeError::ParseError("Orderbook message has no data".to_string()))?;
+    let symbol = data.get("s")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ExchangeError::ParseError("Orderbook message has no symbol".to_string()))?;
+    health.record_message(symbol);
+    let update_id = data.get("u")
+        .and_then(|v| v.as_u64())
+        .ok_or_else(|| ExchangeError::ParseError("Orderbook message has no update id".to_string()))?;
+    let bids = parse_levels(data.get("b"))?;
+    let asks = parse_levels(data.get("a"))?;
+
+    match message.get("type").and_then(|v| v.as_str()) {
+        Some("snapshot") => {
+            store.insert("Bybit", LocalOrderBook::from_snapshot(symbol, &DepthSnapshot { last_update_id: update_id, bids, asks }));
+            health.record_synced(symbol);
+        }
+        Some("delta") => {
+            if let Some(BookUpdate::Gap { expected, received }) =
+                store.update("Bybit", symbol, |book| book.apply_bybit_delta(update_id, &bids, &asks)) {
+                store.remove("Bybit", symbol);
+                health.record_gap(symbol);
+                return Err(ExchangeError::ParseError(format!("update gap, expected {} got {}", expected, received)).into());
+            }
+        }
+        other => debug!("Ignoring Bybit orderbook message of type {:?}", other),
+    }
+    Ok(())
+}
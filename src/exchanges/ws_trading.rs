@@ -0,0 +1,191 @@
+//! Order placement and cancels over an exchange's websocket API.
+//!
+//! A request on an open connection skips the connection setup and
+//! header overhead of REST, which shortens the time a leg waits for its
+//! order to reach the book. Each request carries an ID that its response
+//! echoes, so several can be in flight at once. The connection is kept
+//! open by a background task that reconnects after `reconnect_delay_seconds`;
+//! while it is down `request` reports that nothing was sent and the
+//! client uses REST instead. A request that was sent but not answered is
+//! an error like a timed-out REST call, since the order may have been
+//! placed.
+
+use super::ExchangeError;
+use crate::config::WsTradingConfig;
+use anyhow::Result;
+use futures_util::{SinkExt, StreamExt};
+use log::{info, warn};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+
+/// Builds the login message sent first on every connection, whose answer
+/// must have `retCode` 0.
+pub type AuthMessage = Box<dyn Fn() -> Result<Value> + Send + Sync>;
+
+struct Request {
+    id: String,
+    message: Value,
+    reply: oneshot::Sender<Value>,
+}
+
+pub struct WsTradingApi {
+    exchange: &'static str,
+    url: String,
+    /// Field of requests and responses holding the request ID
+    id_field: &'static str,
+    timeout: Duration,
+    reconnect_delay: Duration,
+    /// Application-level ping, for exchanges that close quiet connections
+    ping: Option<(Duration, Value)>,
+    auth: Option<AuthMessage>,
+    /// Requests to the open connection's task; closed while it is down
+    outgoing: Mutex<Option<mpsc::UnboundedSender<Request>>>,
+    next_id: AtomicU64,
+}
+
+impl WsTradingApi {
+    pub fn new(exchange: &'static str, url: &str, id_field: &'static str, config: &WsTradingConfig) -> Self {
+        Self {
+            exchange,
+            url: url.to_string(),
+            id_field,
+            timeout: Duration::from_millis(config.request_timeout_ms),
+            reconnect_delay: Duration::from_secs(config.reconnect_delay_seconds),
+            ping: None,
+            auth: None,
+            outgoing: Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Sends `message` every `interval` while connected.
+    pub fn with_ping(mut self, interval: Duration, message: Value) -> Self {
+        self.ping = Some((interval, message));
+        self
+    }
+
+    /// Logs in with the message `auth` builds after every connect.
+    pub fn with_auth(mut self, auth: AuthMessage) -> Self {
+        self.auth = Some(auth);
+        self
+    }
+
+    /// Spawns the task that keeps the connection open for as long as the
+    /// API is alive. Must be called within a tokio runtime.
+    pub fn start(self: Arc<Self>) {
+        let api = Arc::downgrade(&self);
+        tokio::spawn(async move {
+            loop {
+                let delay = match api.upgrade() {
+                    Some(api) => api.reconnect_delay,
+                    None => return,
+                };
+                if let Err(e) = run_session(&api).await {
+                    let exchange = api.upgrade().map(|api| api.exchange).unwrap_or_default();
+                    warn!("{} trading websocket failed, using REST until it reconnects: {:#}", exchange, e);
+                }
+                tokio::time::sleep(delay).await;
+            }
+        });
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.outgoing.lock().unwrap().as_ref().is_some_and(|sender| !sender.is_closed())
+    }
+
+    /// Sends `message` with a fresh request ID and waits for its response.
+    /// Returns `None` without sending anything while disconnected.
+    pub async fn request(&self, mut message: Value) -> Result<Option<Value>, ExchangeError> {
+        let sender = self.outgoing.lock().unwrap().clone();
+        let Some(sender) = sender.filter(|sender| !sender.is_closed()) else { return Ok(None) };
+
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        message[self.id_field] = Value::String(id.clone());
+        let (reply, response) = oneshot::channel();
+        if sender.send(Request { id, message, reply }).is_err() {
+            return Ok(None);
+        }
+        match tokio::time::timeout(self.timeout, response).await {
+            Ok(Ok(response)) => Ok(Some(response)),
+            Ok(Err(_)) => Err(ExchangeError::NetworkError(format!("{} trading websocket closed before answering", self.exchange))),
+            Err(_) => Err(ExchangeError::NetworkError(format!("{} trading websocket request timed out", self.exchange))),
+        }
+    }
+}
+
+/// Connects, logs in and serves requests until the connection fails or
+/// the API is dropped.
+async fn run_session(api: &Weak<WsTradingApi>) -> Result<()> {
+    let (exchange, url, id_field, ping, timeout) = match api.upgrade() {
+        Some(api) => (api.exchange, api.url.clone(), api.id_field, api.ping.clone(), api.timeout),
+        None => return Ok(()),
+    };
+    let (mut ws, _) = tokio::time::timeout(timeout, connect_async(url.as_str())).await
+        .map_err(|_| ExchangeError::NetworkError("connect timed out".to_string()))?
+        .map_err(|e| ExchangeError::NetworkError(format!("connect failed: {}", e)))?;
+
+    let auth = api.upgrade().and_then(|api| api.auth.as_ref().map(|auth| auth()));
+    if let Some(auth) = auth {
+        ws.send(Message::Text(auth?.to_string())).await?;
+        let answer = tokio::time::timeout(timeout, ws.next()).await
+            .map_err(|_| ExchangeError::NetworkError("login timed out".to_string()))?;
+        let answer: Value = match answer {
+            Some(Ok(Message::Text(text))) => serde_json::from_str(&text)?,
+            other => return Err(ExchangeError::NetworkError(format!("no login answer: {:?}", other)).into()),
+        };
+        if answer.get("retCode").and_then(|c| c.as_i64()) != Some(0) {
+            return Err(ExchangeError::InvalidRequest(format!("login rejected: {}", answer)).into());
+        }
+    }
+
+    let (sender, mut requests) = mpsc::unbounded_channel::<Request>();
+    match api.upgrade() {
+        Some(api) => *api.outgoing.lock().unwrap() = Some(sender),
+        None => return Ok(()),
+    }
+    info!("{} trading websocket connected", exchange);
+
+    let mut pending: HashMap<String, oneshot::Sender<Value>> = HashMap::new();
+    let ping_interval = ping.as_ref().map(|(interval, _)| *interval).unwrap_or(Duration::from_secs(3600));
+    let mut ping_timer = tokio::time::interval_at(tokio::time::Instant::now() + ping_interval, ping_interval);
+    loop {
+        tokio::select! {
+            request = requests.recv() => {
+                // The API was dropped
+                let Some(request) = request else { return Ok(()) };
+                ws.send(Message::Text(request.message.to_string())).await?;
+                pending.insert(request.id, request.reply);
+            }
+            _ = ping_timer.tick(), if ping.is_some() => {
+                if let Some((_, message)) = &ping {
+                    ws.send(Message::Text(message.to_string())).await?;
+                }
+            }
+            message = ws.next() => {
+                let text = match message {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | None => {
+                        return Err(ExchangeError::NetworkError("connection closed".to_string()).into());
+                    }
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(ExchangeError::NetworkError(e.to_string()).into()),
+                };
+                let Ok(response) = serde_json::from_str::<Value>(&text) else { continue };
+                let id = match response.get(id_field) {
+                    Some(Value::String(id)) => id.clone(),
+                    Some(Value::Number(id)) => id.to_string(),
+                    _ => continue,
+                };
+                if let Some(reply) = pending.remove(&id) {
+                    let _ = reply.send(response);
+                }
+            }
+        }
+    }
+}
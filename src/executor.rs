@@ -0,0 +1,1098 @@
+use crate::arbitrage::risk::Strategy;
+use crate::arbitrage::{ArbitrageOpportunity, ExecutionStep};
+use crate::config::{CrashRecovery, ExecutionConfig, FirstLegMode, LegSubmission, MakerFallback, RetryConfig, RetryPolicy};
+use crate::exchanges::depth::OrderBookStore;
+use crate::exchanges::{
+    new_client_order_id, ExchangeClient, OrderRef, OrderRequest, OrderSide, OrderState, OrderType, PlacementOutcome, TimeInForce,
+};
+use crate::journal::{ExecutionJournal, ExecutionOutcome, JournalEvent, PendingExecution};
+use crate::portfolio::PortfolioSnapshot;
+use crate::precision::{quantize_qty, PricePrecision, Rounding, DEFAULT_QUANTITY_STEP};
+use crate::reconcile::{reconcile, ExchangeState, ReconciliationReport};
+use crate::retry::retry;
+use anyhow::Result;
+use chrono::Utc;
+use futures_util::future::try_join_all;
+use log::{error, info, warn};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Fill obtained for one execution step, possibly across several orders.
+#[derive(Debug, Clone)]
+pub struct LegFill {
+    pub exchange: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub client_order_ids: Vec<String>,
+    pub requested_quantity: Decimal,
+    pub filled_quantity: Decimal,
+    pub average_price: Option<Decimal>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ExecutionResult {
+    Completed(Vec<LegFill>),
+    Abandoned { fills: Vec<LegFill>, reason: String },
+}
+
+/// Verdict of the re-validation right before an opportunity is submitted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LastLook {
+    Passed,
+    /// Detected longer ago than `execution.max_opportunity_age_ms`
+    Expired(String),
+    /// A leg moved more than the maximum slippage, or could not be re-quoted
+    Evaporated(String),
+    /// Every leg is within slippage, but together they re-price the cycle
+    /// below its profit threshold
+    Decayed(String),
+}
+
+impl LastLook {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LastLook::Passed => "passed",
+            LastLook::Expired(_) => "expired",
+            LastLook::Evaporated(_) => "evaporated",
+            LastLook::Decayed(_) => "decayed",
+        }
+    }
+    
+    /// Why the opportunity should not be submitted; `None` when it passed.
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            LastLook::Passed => None,
+            LastLook::Expired(reason) | LastLook::Evaporated(reason) | LastLook::Decayed(reason) => Some(reason),
+        }
+    }
+}
+
+/// Submits an opportunity's steps in order, routing each one to its venue.
+pub struct Executor {
+    clients: Vec<ExchangeClient>,
+    config: ExecutionConfig,
+    precision: Arc<PricePrecision>,
+    journal: Option<Arc<ExecutionJournal>>,
+    order_books: Option<(Arc<OrderBookStore>, Duration)>,
+    retry: RetryPolicy,
+    min_profit_thresholds: HashMap<Strategy, Decimal>,
+    max_slippage_percentage: Decimal,
+}
+
+impl LegFill {
+    fn from_state(step: &ExecutionStep, requested_quantity: Decimal, state: Option<OrderState>, client_order_id: String) -> Self {
+        let (filled_quantity, average_price) = state
+            .map(|s| (s.executed_quantity, s.average_price))
+            .unwrap_or((Decimal::ZERO, None));
+        
+        Self {
+            exchange: step.exchange.clone(),
+            symbol: step.symbol.clone(),
+            side: step.side.clone(),
+            client_order_ids: vec![client_order_id],
+            requested_quantity,
+            filled_quantity,
+            average_price,
+        }
+    }
+    
+    /// Folds a follow-up fill for the same leg into this one.
+    fn merge(&mut self, other: LegFill) {
+        let notional = |fill: &LegFill| fill.average_price.map(|p| p * fill.filled_quantity).unwrap_or(Decimal::ZERO);
+        let total_quantity = self.filled_quantity + other.filled_quantity;
+        let total_notional = notional(self) + notional(&other);
+        
+        self.average_price = (total_quantity > Decimal::ZERO).then(|| total_notional / total_quantity);
+        self.filled_quantity = total_quantity;
+        self.client_order_ids.extend(other.client_order_ids);
+    }
+    
+    pub fn is_complete(&self) -> bool {
+        self.filled_quantity >= self.requested_quantity
+    }
+}
+
+/// Splits `quantity` into equal child orders of at most `max_child`, or
+/// into `max_children` larger ones when even that many would not fit.
+/// Children are rounded down to the lot `step`; the last one takes the rest.
+fn split_quantity(quantity: Decimal, max_child: Decimal, max_children: usize, step: Decimal) -> Vec<Decimal> {
+    if max_child <= Decimal::ZERO || quantity <= max_child {
+        return vec![quantity];
+    }
+    let count = (quantity / max_child).ceil().to_usize().unwrap_or(max_children).clamp(1, max_children);
+    let slice = quantize_qty(quantity / Decimal::from(count), step, Rounding::Down);
+    let mut slices = vec![slice; count - 1];
+    slices.push(quantity - slice * Decimal::from(count - 1));
+    slices
+}
+
+/// Quote amount a first-leg buy should spend, rounded down to the 8
+/// decimals both venues accept for quote precision.
+fn quote_amount(step: &ExecutionStep) -> Decimal {
+    quantize_qty(step.quantity * step.expected_price, DEFAULT_QUANTITY_STEP, Rounding::Down)
+}
+
+/// Net profit percentage left once the first leg filled at `fill`'s
+/// average price; the remaining legs are still priced as detected, and fees
+/// scale with the size, so only the first leg's slippage moves it.
+fn continued_profit_percentage(opportunity: &ArbitrageOpportunity, fill: &LegFill) -> Decimal {
+    let Some(step) = opportunity.execution_steps.first() else { return opportunity.net_profit_percentage };
+    let Some(price) = fill.average_price.filter(|_| step.expected_price > Decimal::ZERO) else {
+        return opportunity.net_profit_percentage;
+    };
+    let adverse = match step.side {
+        OrderSide::Buy => price - step.expected_price,
+        OrderSide::Sell => step.expected_price - price,
+    };
+    opportunity.net_profit_percentage - adverse / step.expected_price * Decimal::ONE_HUNDRED
+}
+
+impl Executor {
+    pub fn new(clients: Vec<ExchangeClient>, config: ExecutionConfig) -> Self {
+        Self { clients, config, precision: Arc::new(PricePrecision::new()), journal: None, order_books: None,
+               retry: RetryConfig::default().trading, min_profit_thresholds: HashMap::new(),
+               max_slippage_percentage: Decimal::ONE }
+    }
+    
+    pub fn with_precision(mut self, precision: Arc<PricePrecision>) -> Self {
+        self.precision = precision;
+        self
+    }
+    
+    /// Journals every state transition so a crash mid-execution can be
+    /// recovered on the next start.
+    pub fn with_journal(mut self, journal: Arc<ExecutionJournal>) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+    
+    /// Streamed order books to size TWAP child orders against; books older
+    /// than `max_age` are ignored and the leg is sent as one order.
+    pub fn with_order_books(mut self, store: Arc<OrderBookStore>, max_age: Duration) -> Self {
+        self.order_books = Some((store, max_age));
+        self
+    }
+    
+    /// Retry policy for order status, cancel and account requests. Order
+    /// placement is never retried.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+    
+    /// Net profit, in percent, a cycle of `strategy` must still clear after
+    /// a partial first-leg fill to be continued.
+    pub fn with_min_profit_threshold(mut self, strategy: Strategy, threshold: Decimal) -> Self {
+        self.min_profit_thresholds.insert(strategy, threshold);
+        self
+    }
+    
+    /// How far, in percent, a leg's fresh best bid/offer may be from its
+    /// detection price on the last look.
+    pub fn with_max_slippage_percentage(mut self, percentage: Decimal) -> Self {
+        self.max_slippage_percentage = percentage;
+        self
+    }
+    
+    /// Re-validates an opportunity right before it is submitted. It expires
+    /// once older than the maximum opportunity age; otherwise the best
+    /// bid/offer of every leg is re-fetched, and it evaporates if a leg's
+    /// side moved more than the maximum slippage from the price it was
+    /// detected at, or decays if the moves together bring its net profit
+    /// below the strategy's threshold.
+    pub async fn last_look(&self, opportunity: &ArbitrageOpportunity) -> Result<LastLook> {
+        let age = Utc::now() - opportunity.timestamp;
+        if age.num_milliseconds() > self.config.max_opportunity_age_ms as i64 {
+            return Ok(LastLook::Expired(format!("detected {}ms ago, past the {}ms limit", age.num_milliseconds(),
+                                                self.config.max_opportunity_age_ms)));
+        }
+        let tickers = try_join_all(opportunity.execution_steps.iter().map(|step| async move {
+            self.client(&step.exchange)?.get_book_ticker(&step.symbol).await
+        })).await?;
+        
+        let mut profit = opportunity.net_profit_percentage;
+        for (index, (step, ticker)) in opportunity.execution_steps.iter().zip(tickers).enumerate() {
+            if step.expected_price <= Decimal::ZERO {
+                continue;
+            }
+            let price = match step.side {
+                OrderSide::Buy => ticker.ask_price,
+                OrderSide::Sell => ticker.bid_price,
+            };
+            let moved = (price - step.expected_price).abs() / step.expected_price * Decimal::ONE_HUNDRED;
+            if moved > self.max_slippage_percentage {
+                return Ok(LastLook::Evaporated(format!(
+                    "leg {} ({}) on {} is now {}, {:.4}% from the detected {}", index + 1, step.symbol, step.exchange,
+                    self.precision.format_price(&step.exchange, &step.symbol, price), moved,
+                    self.precision.format_price(&step.exchange, &step.symbol, step.expected_price))));
+            }
+            let adverse = match step.side {
+                OrderSide::Buy => price - step.expected_price,
+                OrderSide::Sell => step.expected_price - price,
+            };
+            profit -= adverse / step.expected_price * Decimal::ONE_HUNDRED;
+        }
+        
+        let threshold = self.min_profit_thresholds.get(&opportunity.strategy).copied().unwrap_or(Decimal::ZERO);
+        if profit < threshold {
+            return Ok(LastLook::Decayed(format!("it re-quotes to {:.4}% net, below the {}% threshold", profit, threshold)));
+        }
+        Ok(LastLook::Passed)
+    }
+    
+    pub async fn execute(&self, opportunity: &ArbitrageOpportunity) -> Result<ExecutionResult> {
+        let key = opportunity.idempotency_key();
+        if let Some(journal) = &self.journal {
+            // Without the start record a crash could not be recovered, so do not trade
+            journal.record(JournalEvent::Started {
+                key: key.clone(),
+                exchange: opportunity.exchange.clone(),
+                steps: opportunity.execution_steps.clone(),
+            })?;
+        }
+        
+        let result = self.execute_steps(opportunity, &key).await?;
+        let outcome = match &result {
+            ExecutionResult::Completed(_) => ExecutionOutcome::Completed,
+            ExecutionResult::Abandoned { .. } => ExecutionOutcome::Abandoned,
+        };
+        self.journal(JournalEvent::Finished { key, outcome });
+        Ok(result)
+    }
+    
+    async fn execute_steps(&self, opportunity: &ArbitrageOpportunity, key: &str) -> Result<ExecutionResult> {
+        if self.config.leg_submission == LegSubmission::Concurrent
+            && opportunity.strategy == Strategy::CrossExchange
+            && opportunity.execution_steps.len() == 2 {
+            return self.execute_concurrent_steps(opportunity, key).await;
+        }
+        
+        let mut fills: Vec<LegFill> = Vec::new();
+        // Later legs shrink proportionally when the first leg only partially fills
+        let mut scale = Decimal::ONE;
+        
+        for (index, step) in opportunity.execution_steps.iter().enumerate() {
+            let client = self.client(&step.exchange)?;
+            let mut order = step.order_request();
+            order.quantity = self.precision.quantize_qty(&step.exchange, &step.symbol, order.quantity * scale);
+            
+            let fill = if index == 0 && self.config.first_leg_mode == FirstLegMode::Maker {
+                self.execute_maker_leg(client, step, order, (key, index)).await?
+            } else if let Some(slices) = self.twap_slices(step, order.quantity) {
+                self.execute_twap_leg(client, step, order, slices, (key, index)).await?
+            } else {
+                if index == 0 && self.config.quote_quantity_first_leg && step.side == OrderSide::Buy {
+                    order.quote_quantity = Some(quote_amount(step));
+                }
+                self.execute_taker_leg(client, step, order, (key, index)).await?
+            };
+            self.journal(JournalEvent::LegFilled {
+                key: key.to_string(),
+                leg: index,
+                filled_quantity: fill.filled_quantity,
+                average_price: fill.average_price,
+            });
+            
+            let average_price = fill.average_price
+                .map(|p| self.precision.format_price(&fill.exchange, &fill.symbol, p))
+                .unwrap_or_else(|| "-".to_string());
+            info!("Leg {} {:?} {} on {}: filled {}/{} @ {}", index + 1, fill.side, fill.symbol, 
+                  fill.exchange, fill.filled_quantity.normalize(), fill.requested_quantity.normalize(), average_price);
+            
+            if fill.filled_quantity <= Decimal::ZERO {
+                let reason = format!("leg {} ({}) did not fill", index + 1, step.symbol);
+                fills.push(fill);
+                return Ok(ExecutionResult::Abandoned { fills, reason });
+            }
+            
+            if index == 0 {
+                if !fill.is_complete() {
+                    if let Some(problem) = self.partial_fill_problem(opportunity, &fill) {
+                        // A crash during the unwind is left for manual handling rather than retried
+                        self.journal(JournalEvent::Recovering { key: key.to_string(), action: CrashRecovery::Unwind });
+                        let unwind = self.unwind_leg(step, fill.filled_quantity, (key, index)).await?;
+                        let reason = format!("leg 1 ({}) filled {}/{} and {}; unwound {}", step.symbol,
+                                             fill.filled_quantity.normalize(), fill.requested_quantity.normalize(),
+                                             problem, unwind.filled_quantity.normalize());
+                        fills.push(fill);
+                        return Ok(ExecutionResult::Abandoned { fills, reason });
+                    }
+                }
+                // A quote-sized buy can get slightly more base than estimated; never oversize later legs
+                scale = (fill.filled_quantity / step.quantity).min(Decimal::ONE);
+            } else if !fill.is_complete() {
+                let reason = format!("leg {} ({}) only partially filled", index + 1, step.symbol);
+                fills.push(fill);
+                return Ok(ExecutionResult::Abandoned { fills, reason });
+            }
+            fills.push(fill);
+        }
+        
+        Ok(ExecutionResult::Completed(fills))
+    }
+    
+    /// Submits both legs of a cross-exchange opportunity at once. When they
+    /// fill different quantities (one rejected or short), the excess of the
+    /// other is reversed at market so no net position is left behind.
+    async fn execute_concurrent_steps(&self, opportunity: &ArbitrageOpportunity, key: &str) -> Result<ExecutionResult> {
+        let steps = &opportunity.execution_steps;
+        let leg = |index: usize| async move {
+            let step = &steps[index];
+            let client_order_id = step.client_order_id.clone();
+            let mut order = step.order_request();
+            order.quantity = self.precision.quantize_qty(&step.exchange, &step.symbol, order.quantity);
+            let result = match self.client(&step.exchange) {
+                Ok(client) => self.execute_taker_leg(client, step, order, (key, index)).await,
+                Err(e) => Err(e),
+            };
+            result.unwrap_or_else(|e| {
+                error!("Leg {} {} on {} failed: {:#}", index + 1, step.symbol, step.exchange, e);
+                LegFill::from_state(step, step.quantity, None, client_order_id)
+            })
+        };
+        let (first, second) = tokio::join!(leg(0), leg(1));
+        let fills = vec![first, second];
+        
+        for (index, fill) in fills.iter().enumerate() {
+            self.journal(JournalEvent::LegFilled {
+                key: key.to_string(),
+                leg: index,
+                filled_quantity: fill.filled_quantity,
+                average_price: fill.average_price,
+            });
+            info!("Leg {} {:?} {} on {}: filled {}/{}", index + 1, fill.side, fill.symbol, fill.exchange,
+                  fill.filled_quantity.normalize(), fill.requested_quantity.normalize());
+        }
+        if fills.iter().all(LegFill::is_complete) {
+            return Ok(ExecutionResult::Completed(fills));
+        }
+        
+        let matched = fills.iter().map(|f| f.filled_quantity).min().unwrap_or(Decimal::ZERO);
+        let unwinds: Vec<(usize, Decimal)> = fills.iter().enumerate()
+            .map(|(index, fill)| (index, fill.filled_quantity - matched))
+            .filter(|(_, excess)| *excess > Decimal::ZERO)
+            .collect();
+        let mut reason = format!("legs filled {} and {} of {}", fills[0].filled_quantity.normalize(),
+                                 fills[1].filled_quantity.normalize(), steps[0].quantity.normalize());
+        if !unwinds.is_empty() {
+            // A crash during the unwind is left for manual handling rather than retried
+            self.journal(JournalEvent::Recovering { key: key.to_string(), action: CrashRecovery::Unwind });
+        }
+        for (index, excess) in unwinds {
+            let step = &steps[index];
+            warn!("Unwinding {} {} on {} after the other leg fell short", excess.normalize(), step.symbol, step.exchange);
+            let unwind = self.unwind_leg(step, excess, (key, index)).await?;
+            reason.push_str(&format!("; unwound {} on {}", unwind.filled_quantity.normalize(), step.exchange));
+        }
+        Ok(ExecutionResult::Abandoned { fills, reason })
+    }
+    
+    /// Why a partially filled first leg should not be continued, if it
+    /// should not: too small to carry the later legs, or no longer
+    /// profitable at the price it filled at.
+    fn partial_fill_problem(&self, opportunity: &ArbitrageOpportunity, fill: &LegFill) -> Option<String> {
+        let partial = &self.config.partial_fill;
+        let notional = fill.average_price.unwrap_or(opportunity.execution_steps[0].expected_price) * fill.filled_quantity;
+        if notional < partial.min_notional {
+            return Some(format!("its notional {} is below the {} minimum", notional.round_dp(2), partial.min_notional));
+        }
+        if partial.recheck_profit {
+            let profit = continued_profit_percentage(opportunity, fill);
+            let threshold = self.min_profit_thresholds.get(&opportunity.strategy).copied().unwrap_or(Decimal::ZERO);
+            if profit < threshold {
+                return Some(format!("the rest re-prices to {:.4}% net, below the {}% threshold", profit, threshold));
+            }
+            info!("Continuing at partial size: re-priced net profit {:.4}%", profit);
+        }
+        None
+    }
+    
+    /// Reverses `quantity` of `step` at market.
+    async fn unwind_leg(&self, step: &ExecutionStep, quantity: Decimal, leg: (&str, usize)) -> Result<LegFill> {
+        let mut order = step.order_request();
+        order.side = match step.side {
+            OrderSide::Buy => OrderSide::Sell,
+            OrderSide::Sell => OrderSide::Buy,
+        };
+        order.quantity = self.precision.quantize_qty(&step.exchange, &step.symbol, quantity);
+        order.client_order_id = Some(new_client_order_id());
+        let unwind = self.execute_taker_leg(self.client(&step.exchange)?, step, order, leg).await?;
+        if !unwind.is_complete() {
+            error!("Unwind of {} on {} filled {}/{}, resolve the position manually", step.symbol, step.exchange,
+                   unwind.filled_quantity, unwind.requested_quantity);
+        }
+        Ok(unwind)
+    }
+    
+    /// Reconciles executions a previous run left without a `Finished`
+    /// journal record against the exchanges' order state, then finishes or
+    /// unwinds them per `execution.crash_recovery`. Returns how many were
+    /// found.
+    pub async fn recover(&self) -> Result<usize> {
+        let Some(journal) = &self.journal else { return Ok(0) };
+        let pending = journal.pending()?;
+        for execution in &pending {
+            let outcome = match self.recover_execution(journal, execution.clone()).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    error!("Recovery of execution {} on {} failed, resolve it manually: {:#}", execution.key, execution.exchange, e);
+                    ExecutionOutcome::Manual
+                }
+            };
+            journal.record(JournalEvent::Finished { key: execution.key.clone(), outcome })?;
+        }
+        Ok(pending.len())
+    }
+    
+    async fn recover_execution(&self, journal: &ExecutionJournal, mut execution: PendingExecution) -> Result<ExecutionOutcome> {
+        warn!("Execution {} on {} was interrupted, reconciling {} legs", execution.key, execution.exchange, execution.steps.len());
+        if execution.recovering {
+            // A crash during recovery; its orders are not retried blindly
+            error!("Recovery of execution {} was itself interrupted, resolve it manually", execution.key);
+            return Ok(ExecutionOutcome::Manual);
+        }
+        
+        // Every order is looked up first so the open ones, across legs, are
+        // canceled together
+        let mut states: Vec<Vec<Option<OrderState>>> = Vec::new();
+        for (step, leg) in execution.steps.iter().zip(&execution.legs) {
+            let mut leg_states = Vec::new();
+            if leg.unconfirmed {
+                let client = self.client(&step.exchange)?;
+                for client_order_id in &leg.client_order_ids {
+                    leg_states.push(self.order_state(client, &step.symbol, client_order_id).await?);
+                }
+            }
+            states.push(leg_states);
+        }
+        self.cancel_open_orders(&execution, &mut states).await?;
+        
+        for (index, ((step, leg), leg_states)) in execution.steps.iter().zip(execution.legs.iter_mut()).zip(states).enumerate() {
+            if !leg.unconfirmed {
+                continue;
+            }
+            let mut fill: Option<LegFill> = None;
+            for (client_order_id, state) in leg.client_order_ids.iter().zip(leg_states) {
+                let order_fill = LegFill::from_state(step, step.quantity, state, client_order_id.clone());
+                match &mut fill {
+                    Some(fill) => fill.merge(order_fill),
+                    None => fill = Some(order_fill),
+                }
+            }
+            let fill = fill.unwrap_or_else(|| LegFill::from_state(step, step.quantity, None, String::new()));
+            info!("Reconciled leg {} {} on {}: filled {}", index + 1, step.symbol, step.exchange, fill.filled_quantity.normalize());
+            leg.filled_quantity = fill.filled_quantity;
+            leg.average_price = fill.average_price;
+            leg.unconfirmed = false;
+            self.journal(JournalEvent::LegFilled {
+                key: execution.key.clone(),
+                leg: index,
+                filled_quantity: fill.filled_quantity,
+                average_price: fill.average_price,
+            });
+        }
+        
+        if execution.is_complete() {
+            info!("Execution {} had completed before the interruption", execution.key);
+            return Ok(ExecutionOutcome::Completed);
+        }
+        let action = self.config.crash_recovery;
+        let orders = execution.recovery_orders(action);
+        if orders.is_empty() {
+            if action == CrashRecovery::Manual && execution.legs[0].filled_quantity > Decimal::ZERO {
+                error!("Execution {} left an open position: {:?}", execution.key, execution.legs);
+                return Ok(ExecutionOutcome::Manual);
+            }
+            return Ok(ExecutionOutcome::Abandoned);
+        }
+        
+        journal.record(JournalEvent::Recovering { key: execution.key.clone(), action })?;
+        for (leg, order) in orders {
+            let step = &execution.steps[leg];
+            warn!("Recovering {}: {:?} {} {} on {}", execution.key, order.side, order.quantity.normalize(), step.symbol, step.exchange);
+            let fill = self.execute_taker_leg(self.client(&step.exchange)?, step, order, (&execution.key, leg)).await?;
+            if !fill.is_complete() {
+                return Err(anyhow::anyhow!("recovery order for {} filled {}/{}", step.symbol, fill.filled_quantity, fill.requested_quantity));
+            }
+        }
+        Ok(match action {
+            CrashRecovery::Unwind => ExecutionOutcome::Unwound,
+            _ => ExecutionOutcome::Completed,
+        })
+    }
+    
+    /// Cancels the orders of `execution` that `states`, per leg, show open,
+    /// in one batch per exchange, and refreshes their states.
+    async fn cancel_open_orders(&self, execution: &PendingExecution, states: &mut [Vec<Option<OrderState>>]) -> Result<()> {
+        let mut open: Vec<(usize, usize)> = Vec::new();
+        for (leg, leg_states) in states.iter().enumerate() {
+            for (order, state) in leg_states.iter().enumerate() {
+                if state.as_ref().is_some_and(|s| s.status.is_open()) {
+                    open.push((leg, order));
+                }
+            }
+        }
+        let mut exchanges: Vec<&str> = open.iter().map(|(leg, _)| execution.steps[*leg].exchange.as_str()).collect();
+        exchanges.sort_unstable();
+        exchanges.dedup();
+        
+        for exchange in exchanges {
+            let client = self.client(exchange)?;
+            let orders: Vec<(usize, usize)> = open.iter().copied()
+                .filter(|(leg, _)| execution.steps[*leg].exchange == exchange)
+                .collect();
+            let refs: Vec<OrderRef> = orders.iter()
+                .map(|(leg, order)| OrderRef {
+                    symbol: execution.steps[*leg].symbol.clone(),
+                    client_order_id: execution.legs[*leg].client_order_ids[*order].clone(),
+                })
+                .collect();
+            for outcome in self.cancel_orders(client, &refs).await? {
+                outcome?;
+            }
+            for ((leg, order), order_ref) in orders.into_iter().zip(&refs) {
+                let state = self.order_state(client, &order_ref.symbol, &order_ref.client_order_id).await?;
+                if state.is_some() {
+                    states[leg][order] = state;
+                }
+            }
+        }
+        Ok(())
+    }
+    
+    /// Compares every venue's open orders and balances with the journal and
+    /// `expected`, the last portfolio snapshot, canceling orphaned bot orders
+    /// when configured. Run after `recover`, before any new execution.
+    pub async fn reconcile(&self, expected: Option<&PortfolioSnapshot>) -> Result<ReconciliationReport> {
+        let owners = match &self.journal {
+            Some(journal) => journal.order_owners()?,
+            None => HashMap::new(),
+        };
+        let mut states = Vec::new();
+        for client in &self.clients {
+            let (open_orders, balances) = tokio::try_join!(
+                retry(&self.retry, "Open orders request", || client.get_open_orders()),
+                retry(&self.retry, "Balance request", || client.get_balances())
+            )
+                .map_err(|e| anyhow::anyhow!("Reconciliation could not read {} account state: {:#}", client.name(), e))?;
+            states.push(ExchangeState {
+                exchange: client.name().to_string(),
+                open_orders,
+                balances,
+                order_history: Vec::new(),
+                trades: Vec::new(),
+            });
+        }
+        if let (Some(journal), Some(snapshot)) = (&self.journal, expected) {
+            if self.config.reconciliation.count_bot_fills {
+                let traded = journal.traded_symbols(snapshot.timestamp)?;
+                for (client, state) in self.clients.iter().zip(states.iter_mut()) {
+                    let symbols = traded.iter()
+                        .filter(|(exchange, _)| exchange.eq_ignore_ascii_case(client.name()))
+                        .map(|(_, symbol)| symbol);
+                    for symbol in symbols {
+                        let range = snapshot.timestamp..Utc::now();
+                        let (orders, trades) = tokio::try_join!(
+                            retry(&self.retry, "Order history request", || client.get_order_history(symbol, range.clone())),
+                            retry(&self.retry, "Trade history request", || client.get_my_trades(symbol, range.clone()))
+                        )
+                            .map_err(|e| anyhow::anyhow!("Reconciliation could not read {} {} history: {:#}", client.name(), symbol, e))?;
+                        state.order_history.extend(orders);
+                        state.trades.extend(trades);
+                    }
+                }
+            }
+        }
+        
+        let settings = &self.config.reconciliation;
+        let mut report = reconcile(&states, &owners, expected, settings.balance_tolerance_percentage);
+        if settings.cancel_orphaned_orders {
+            for client in &self.clients {
+                let orphaned: Vec<_> = report.orphaned_orders.iter_mut()
+                    .filter(|order| order.exchange.eq_ignore_ascii_case(client.name()))
+                    .collect();
+                if orphaned.is_empty() {
+                    continue;
+                }
+                let refs: Vec<OrderRef> = orphaned.iter()
+                    .map(|order| OrderRef { symbol: order.symbol.clone(), client_order_id: order.client_order_id.clone() })
+                    .collect();
+                let outcomes = match self.cancel_orders(client, &refs).await {
+                    Ok(outcomes) => outcomes,
+                    Err(e) => {
+                        error!("Failed to cancel {} orphaned orders on {}: {:#}", refs.len(), client.name(), e);
+                        continue;
+                    }
+                };
+                for (order, outcome) in orphaned.into_iter().zip(outcomes) {
+                    match outcome {
+                        Ok(()) => order.canceled = true,
+                        Err(e) => error!("Failed to cancel orphaned order {} on {}: {:#}", order.client_order_id, order.exchange, e),
+                    }
+                }
+            }
+        }
+        report.log();
+        Ok(report)
+    }
+    
+    async fn order_state(&self, client: &ExchangeClient, symbol: &str, client_order_id: &str) -> Result<Option<OrderState>> {
+        retry(&self.retry, "Order status request", || client.get_order_by_client_id(symbol, client_order_id)).await
+    }
+    
+    async fn cancel_order(&self, client: &ExchangeClient, symbol: &str, client_order_id: &str) -> Result<()> {
+        retry(&self.retry, "Cancel request", || client.cancel_order_by_client_id(symbol, client_order_id)).await
+    }
+    
+    async fn cancel_orders(&self, client: &ExchangeClient, orders: &[OrderRef]) -> Result<Vec<Result<()>>> {
+        retry(&self.retry, "Batch cancel request", || client.cancel_orders_by_client_id(orders)).await
+    }
+    
+    /// Journal writes after the start record are best effort: failing the
+    /// execution halfway would leave more exposure than a missing record.
+    fn journal(&self, event: JournalEvent) {
+        if let Some(journal) = &self.journal {
+            if let Err(e) = journal.record(event) {
+                warn!("Failed to write execution journal: {}", e);
+            }
+        }
+    }
+    
+    fn client(&self, exchange: &str) -> Result<&ExchangeClient> {
+        self.clients.iter()
+            .find(|c| c.name().eq_ignore_ascii_case(exchange))
+            .ok_or_else(|| anyhow::anyhow!("No client configured for exchange {}", exchange))
+    }
+    
+    fn journal_submitted(&self, (key, leg): (&str, usize), order: &OrderRequest) {
+        if let Some(client_order_id) = &order.client_order_id {
+            self.journal(JournalEvent::LegSubmitted {
+                key: key.to_string(),
+                leg,
+                client_order_id: client_order_id.clone(),
+                quantity: order.quantity,
+            });
+        }
+    }
+    
+    /// `leg` is the journal key and step index the order is recorded under.
+    async fn execute_taker_leg(&self, client: &ExchangeClient, step: &ExecutionStep, mut order: OrderRequest, leg: (&str, usize)) -> Result<LegFill> {
+        let client_order_id = order.client_order_id.clone().unwrap_or_else(new_client_order_id);
+        order.client_order_id = Some(client_order_id.clone());
+        self.journal_submitted(leg, &order);
+        let state = match client.place_order_cancel_safe(&order).await? {
+            PlacementOutcome::Placed(state)
+            | PlacementOutcome::Adopted(state)
+            | PlacementOutcome::CanceledAfterTimeout(state) => Some(state),
+            PlacementOutcome::NotPlaced => None,
+        };
+        
+        Ok(LegFill::from_state(step, order.quantity, state, client_order_id))
+    }
+    
+    /// Child order sizes when TWAP is enabled and the leg would take more
+    /// than `max_child_depth_percentage` of the fresh book in one order.
+    fn twap_slices(&self, step: &ExecutionStep, quantity: Decimal) -> Option<Vec<Decimal>> {
+        let twap = &self.config.twap;
+        if !twap.enabled {
+            return None;
+        }
+        let (store, max_age) = self.order_books.as_ref()?;
+        let book = store.fresh(&step.exchange, &step.symbol, twap.depth_levels, *max_age)?;
+        let levels = match step.side {
+            OrderSide::Buy => &book.asks,
+            OrderSide::Sell => &book.bids,
+        };
+        let depth: Decimal = levels.iter().map(|(_, quantity)| *quantity).sum();
+        let slices = split_quantity(quantity, depth * twap.max_child_depth_percentage / Decimal::ONE_HUNDRED, twap.max_children,
+                                    self.precision.step_size(&step.exchange, &step.symbol));
+        (slices.len() > 1).then_some(slices)
+    }
+    
+    /// Sends `slices` as IOC limit orders `twap.interval_ms` apart, each
+    /// limited to the step's expected price plus `limit_tolerance_percentage`,
+    /// and folds them into one fill. Stops at the first child that fills
+    /// nothing, since the price has then run past the limit.
+    async fn execute_twap_leg(&self, client: &ExchangeClient, step: &ExecutionStep, order: OrderRequest, slices: Vec<Decimal>, leg: (&str, usize)) -> Result<LegFill> {
+        let twap = &self.config.twap;
+        let tolerance = step.expected_price * twap.limit_tolerance_percentage / Decimal::ONE_HUNDRED;
+        // Rounded towards the expected price, so the limit stays within the tolerance
+        let (limit, rounding) = match step.side {
+            OrderSide::Buy => (step.expected_price + tolerance, Rounding::Down),
+            OrderSide::Sell => (step.expected_price - tolerance, Rounding::Up),
+        };
+        let limit = self.precision.quantize_price(&step.exchange, &step.symbol, limit, rounding);
+        info!("Splitting {} {} on {} into {} child orders limited at {}", order.quantity.normalize(), step.symbol,
+              step.exchange, slices.len(), self.precision.format_price(&step.exchange, &step.symbol, limit));
+        
+        let count = slices.len();
+        let mut fill: Option<LegFill> = None;
+        for (i, quantity) in slices.into_iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(Duration::from_millis(twap.interval_ms)).await;
+            }
+            let child = OrderRequest {
+                quantity,
+                quote_quantity: None,
+                price: Some(limit),
+                order_type: OrderType::Limit,
+                time_in_force: TimeInForce::Ioc,
+                // The first child keeps the step's client order ID
+                client_order_id: if i == 0 { order.client_order_id.clone() } else { Some(new_client_order_id()) },
+                ..order.clone()
+            };
+            let child_fill = self.execute_taker_leg(client, step, child, leg).await?;
+            let stalled = child_fill.filled_quantity <= Decimal::ZERO;
+            match &mut fill {
+                Some(fill) => fill.merge(child_fill),
+                None => fill = Some(child_fill),
+            }
+            if stalled {
+                info!("TWAP child {}/{} on {} did not fill within the limit, stopping", i + 1, count, step.symbol);
+                break;
+            }
+        }
+        
+        let mut fill = fill.unwrap_or_else(|| LegFill::from_state(step, order.quantity, None, String::new()));
+        fill.requested_quantity = order.quantity;
+        Ok(fill)
+    }
+    
+    /// Rests a post-only limit at the touch (best bid for buys, best ask for
+    /// sells) for up to `maker_max_wait_ms`, then cancels whatever is left
+    /// and applies the configured fallback to the unfilled remainder.
+    async fn execute_maker_leg(&self, client: &ExchangeClient, step: &ExecutionStep, order: OrderRequest, leg: (&str, usize)) -> Result<LegFill> {
+        let ticker = client.get_book_ticker(&step.symbol).await?;
+        let touch = match step.side {
+            OrderSide::Buy => ticker.bid_price,
+            OrderSide::Sell => ticker.ask_price,
+        };
+        
+        let client_order_id = order.client_order_id.clone().unwrap_or_else(new_client_order_id);
+        let maker_order = OrderRequest {
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::PostOnly,
+            price: Some(touch),
+            client_order_id: Some(client_order_id.clone()),
+            ..order.clone()
+        };
+        self.journal_submitted(leg, &maker_order);
+        
+        let mut state = match client.place_order_cancel_safe(&maker_order).await {
+            Ok(PlacementOutcome::Placed(state)) | Ok(PlacementOutcome::Adopted(state)) => Some(state),
+            Ok(PlacementOutcome::CanceledAfterTimeout(state)) => Some(state),
+            Ok(PlacementOutcome::NotPlaced) => None,
+            Err(e) => {
+                // Post-only orders are rejected when the touch moved and they would cross
+                warn!("Maker order for {} rejected: {:#}", step.symbol, e);
+                None
+            }
+        };
+        
+        let deadline = Instant::now() + Duration::from_millis(self.config.maker_max_wait_ms);
+        while state.as_ref().map(|s| s.status.is_open()).unwrap_or(false) {
+            if Instant::now() >= deadline {
+                self.cancel_order(client, &step.symbol, &client_order_id).await?;
+                state = self.order_state(client, &step.symbol, &client_order_id).await?.or(state);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+            state = self.order_state(client, &step.symbol, &client_order_id).await?.or(state);
+        }
+        
+        let mut fill = LegFill::from_state(step, order.quantity, state, client_order_id);
+        if fill.is_complete() {
+            return Ok(fill);
+        }
+        
+        match self.config.maker_fallback {
+            MakerFallback::Taker => {
+                let remainder = OrderRequest {
+                    quantity: self.precision.quantize_qty(&step.exchange, &step.symbol, order.quantity - fill.filled_quantity),
+                    time_in_force: TimeInForce::Gtc,
+                    client_order_id: Some(new_client_order_id()),
+                    ..order
+                };
+                info!("Maker leg on {} filled {}/{}, taking remaining {}", 
+                      step.symbol, fill.filled_quantity, fill.requested_quantity, remainder.quantity);
+                let taker_fill = self.execute_taker_leg(client, step, remainder, leg).await?;
+                fill.merge(taker_fill);
+            }
+            MakerFallback::Abandon => {
+                info!("Maker leg on {} filled {}/{} before timeout, not chasing", 
+                      step.symbol, fill.filled_quantity, fill.requested_quantity);
+            }
+        }
+        
+        Ok(fill)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::simulated::{MatchingEngine, SimulatedExchange};
+    
+    fn fill(quantity: i64, price: Option<i64>) -> LegFill {
+        LegFill {
+            exchange: "Binance".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            client_order_ids: vec![new_client_order_id()],
+            requested_quantity: Decimal::from(10),
+            filled_quantity: Decimal::from(quantity),
+            average_price: price.map(Decimal::from),
+        }
+    }
+    
+    #[test]
+    fn test_merge_weights_average_price() {
+        let mut maker = fill(4, Some(100));
+        maker.merge(fill(6, Some(110)));
+        
+        assert_eq!(maker.filled_quantity, Decimal::from(10));
+        assert_eq!(maker.average_price, Some(Decimal::from(106)));
+        assert_eq!(maker.client_order_ids.len(), 2);
+        assert!(maker.is_complete());
+    }
+    
+    #[test]
+    fn test_quote_amount() {
+        let step = ExecutionStep {
+            exchange: "Binance".to_string(),
+            action: "Buy BTC with USDT".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::from_str_exact("0.015").unwrap(),
+            expected_price: Decimal::from(65000),
+            fees: Decimal::ZERO,
+            client_order_id: new_client_order_id(),
+        };
+        assert_eq!(quote_amount(&step), Decimal::from(975));
+    }
+    
+    #[test]
+    fn test_split_quantity() {
+        let dec = |value: &str| Decimal::from_str_exact(value).unwrap();
+        let step = DEFAULT_QUANTITY_STEP;
+        assert_eq!(split_quantity(dec("0.5"), dec("1"), 5, step), vec![dec("0.5")]);
+        assert_eq!(split_quantity(dec("1"), dec("0.3"), 5, step), vec![dec("0.25"), dec("0.25"), dec("0.25"), dec("0.25")]);
+        // A book too thin for max_children safe slices still gets max_children
+        assert_eq!(split_quantity(dec("10"), dec("1"), 3, step), vec![dec("3.33333333"), dec("3.33333333"), dec("3.33333334")]);
+        assert_eq!(split_quantity(dec("1"), Decimal::ZERO, 5, step), vec![dec("1")]);
+        // A coarser lot size leaves the remainder to the last child
+        assert_eq!(split_quantity(dec("1"), dec("0.3"), 3, dec("0.01")), vec![dec("0.33"), dec("0.33"), dec("0.34")]);
+    }
+    
+    #[test]
+    fn test_twap_slices_follow_fresh_book() {
+        let store = Arc::new(OrderBookStore::new());
+        store.insert("Binance", crate::exchanges::depth::LocalOrderBook::from_snapshot(
+            "BTCUSDT",
+            &crate::exchanges::depth::DepthSnapshot {
+                last_update_id: 1,
+                bids: Vec::new(),
+                asks: vec![(Decimal::from(65000), Decimal::ONE), (Decimal::from(65010), Decimal::ONE)],
+            },
+        ));
+        let mut config = ExecutionConfig::default();
+        config.twap.enabled = true;
+        let step = ExecutionStep {
+            exchange: "Binance".to_string(),
+            action: "Buy BTC with USDT".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::ONE,
+            expected_price: Decimal::from(65000),
+            fees: Decimal::ZERO,
+            client_order_id: new_client_order_id(),
+        };
+        
+        let without_books = Executor::new(Vec::new(), config.clone());
+        assert!(without_books.twap_slices(&step, Decimal::ONE).is_none());
+        
+        // 20% of 2 BTC of asks allows 0.4 BTC per child
+        let executor = Executor::new(Vec::new(), config).with_order_books(store, Duration::from_secs(5));
+        let slices = executor.twap_slices(&step, Decimal::ONE).unwrap();
+        assert_eq!(slices.len(), 3);
+        assert_eq!(slices.iter().copied().sum::<Decimal>(), Decimal::ONE);
+        assert!(executor.twap_slices(&step, Decimal::from_str_exact("0.4").unwrap()).is_none());
+    }
+    
+    #[test]
+    fn test_merge_with_unfilled_maker() {
+        let mut maker = fill(0, None);
+        maker.merge(fill(10, Some(105)));
+        assert_eq!(maker.average_price, Some(Decimal::from(105)));
+    }
+    
+    #[test]
+    fn test_partial_first_leg_is_repriced_at_its_fill() {
+        let dec = |value: &str| Decimal::from_str_exact(value).unwrap();
+        let step = ExecutionStep {
+            exchange: "Binance".to_string(),
+            action: "Buy BTC with USDT".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::from(10),
+            expected_price: Decimal::from(100),
+            fees: Decimal::ZERO,
+            client_order_id: new_client_order_id(),
+        };
+        let opportunity = ArbitrageOpportunity {
+            exchange: "Binance".to_string(),
+            strategy: Strategy::Triangular,
+            path: Vec::new(),
+            profit_percentage: Decimal::ONE,
+            net_profit_percentage: dec("0.7"),
+            required_amount: Decimal::from(1000),
+            estimated_profit_usd: Decimal::from(7),
+            risk_score: 0.0,
+            execution_steps: vec![step],
+            leg_quotes: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            scan_id: 0,
+        };
+        let executor = Executor::new(Vec::new(), ExecutionConfig::default())
+            .with_min_profit_threshold(Strategy::Triangular, dec("0.5"));
+        
+        assert_eq!(continued_profit_percentage(&opportunity, &fill(4, None)), dec("0.7"));
+        // Bought 4 of 10 at 100.1: 0.1% worse than detected still leaves 0.6%
+        let mut slipped = fill(4, Some(100));
+        slipped.average_price = Some(dec("100.1"));
+        assert_eq!(continued_profit_percentage(&opportunity, &slipped), dec("0.6"));
+        assert!(executor.partial_fill_problem(&opportunity, &slipped).is_none());
+        
+        slipped.average_price = Some(dec("100.3"));
+        assert!(executor.partial_fill_problem(&opportunity, &slipped).unwrap().contains("below the 0.5% threshold"));
+        // 0.05 BTC at 100 is under the 10 USDT minimum
+        let dust = LegFill { filled_quantity: dec("0.05"), ..fill(0, Some(100)) };
+        assert!(executor.partial_fill_problem(&opportunity, &dust).unwrap().contains("below the 10 minimum"));
+    }
+    
+    fn step(exchange: &str, symbol: &str, side: OrderSide, quantity: &str, price: &str) -> ExecutionStep {
+        ExecutionStep {
+            exchange: exchange.to_string(),
+            action: format!("{:?} {}", side, symbol),
+            symbol: symbol.to_string(),
+            side,
+            quantity: Decimal::from_str_exact(quantity).unwrap(),
+            expected_price: Decimal::from_str_exact(price).unwrap(),
+            fees: Decimal::ZERO,
+            client_order_id: new_client_order_id(),
+        }
+    }
+    
+    fn opportunity(strategy: Strategy, execution_steps: Vec<ExecutionStep>) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            exchange: execution_steps[0].exchange.clone(),
+            strategy,
+            path: Vec::new(),
+            profit_percentage: Decimal::ONE,
+            net_profit_percentage: Decimal::ONE,
+            required_amount: Decimal::from(100),
+            estimated_profit_usd: Decimal::ONE,
+            risk_score: 0.0,
+            execution_steps,
+            leg_quotes: Vec::new(),
+            timestamp: chrono::Utc::now(),
+            scan_id: 0,
+        }
+    }
+    
+    fn simulated(name: &'static str, engine: MatchingEngine) -> (Arc<SimulatedExchange>, ExchangeClient) {
+        let exchange = Arc::new(SimulatedExchange::new(name, engine));
+        (exchange.clone(), ExchangeClient::Simulated(exchange))
+    }
+    
+    fn balance(exchange: &SimulatedExchange, asset: &str) -> Decimal {
+        exchange.engine().balances().into_iter().find(|b| b.asset == asset).map(|b| b.free).unwrap_or_default()
+    }
+    
+    #[tokio::test]
+    async fn test_thin_first_leg_is_unwound_at_market() {
+        let dec = |value: &str| Decimal::from_str_exact(value).unwrap();
+        let mut engine = MatchingEngine::new()
+            .with_market("BTCUSDT", "BTC", "USDT")
+            .with_market("ETHBTC", "ETH", "BTC")
+            .with_balance("USDT", Decimal::from(1000));
+        engine.add_liquidity("BTCUSDT", OrderSide::Sell, Decimal::from(100), dec("0.05")).unwrap();
+        engine.add_liquidity("BTCUSDT", OrderSide::Buy, Decimal::from(99), Decimal::from(5)).unwrap();
+        let (binance, client) = simulated("Binance", engine);
+        let executor = Executor::new(vec![client], ExecutionConfig::default());
+        
+        let result = executor.execute(&opportunity(Strategy::Triangular, vec![
+            step("Binance", "BTCUSDT", OrderSide::Buy, "1", "100"),
+            step("Binance", "ETHBTC", OrderSide::Buy, "20", "0.05"),
+        ])).await.unwrap();
+        
+        // 0.05 BTC is 5 USDT, under the 10 USDT minimum to carry on
+        let ExecutionResult::Abandoned { fills, reason } = result else { panic!("expected the cycle to be abandoned") };
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].filled_quantity, dec("0.05"));
+        assert!(reason.contains("unwound 0.05"), "{}", reason);
+        assert_eq!(balance(&binance, "BTC"), Decimal::ZERO);
+        assert_eq!(balance(&binance, "USDT"), dec("999.95"));
+        assert!(binance.engine().recent_trades("ETHBTC", 10).is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_legs_unwind_the_excess_on_its_own_venue() {
+        let dec = |value: &str| Decimal::from_str_exact(value).unwrap();
+        let mut binance_engine = MatchingEngine::new().with_market("BTCUSDT", "BTC", "USDT");
+        binance_engine.add_liquidity("BTCUSDT", OrderSide::Sell, Decimal::from(100), Decimal::from(5)).unwrap();
+        binance_engine.add_liquidity("BTCUSDT", OrderSide::Buy, Decimal::from(99), Decimal::from(5)).unwrap();
+        let mut bybit_engine = MatchingEngine::new().with_market("BTCUSDT", "BTC", "USDT");
+        bybit_engine.add_liquidity("BTCUSDT", OrderSide::Buy, Decimal::from(101), dec("0.4")).unwrap();
+        let (binance, binance_client) = simulated("Binance", binance_engine);
+        let (bybit, bybit_client) = simulated("Bybit", bybit_engine);
+        let config = ExecutionConfig { leg_submission: LegSubmission::Concurrent, ..ExecutionConfig::default() };
+        let executor = Executor::new(vec![binance_client, bybit_client], config);
+        
+        let result = executor.execute(&opportunity(Strategy::CrossExchange, vec![
+            step("Binance", "BTCUSDT", OrderSide::Buy, "1", "100"),
+            step("Bybit", "BTCUSDT", OrderSide::Sell, "1", "101"),
+        ])).await.unwrap();
+        
+        let ExecutionResult::Abandoned { fills, reason } = result else { panic!("expected the legs to be abandoned") };
+        let filled: Vec<Decimal> = fills.iter().map(|f| f.filled_quantity).collect();
+        assert_eq!(filled, [Decimal::ONE, dec("0.4")]);
+        assert!(reason.ends_with("unwound 0.6 on Binance"), "{}", reason);
+        // Only the bought excess is sold back; both venues end flat against each other
+        assert_eq!(balance(&binance, "BTC"), dec("0.4"));
+        assert_eq!(balance(&bybit, "BTC"), dec("-0.4"));
+        assert!(bybit.engine().book_ticker("BTCUSDT").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_legs_are_step_aligned() {
+        let dec = |value: &str| Decimal::from_str_exact(value).unwrap();
+        let mut binance_engine = MatchingEngine::new().with_market("BTCUSDT", "BTC", "USDT");
+        binance_engine.add_liquidity("BTCUSDT", OrderSide::Sell, Decimal::from(100), Decimal::from(5)).unwrap();
+        let mut bybit_engine = MatchingEngine::new().with_market("BTCUSDT", "BTC", "USDT");
+        bybit_engine.add_liquidity("BTCUSDT", OrderSide::Buy, Decimal::from(101), Decimal::from(5)).unwrap();
+        let (binance, binance_client) = simulated("Binance", binance_engine);
+        let (bybit, bybit_client) = simulated("Bybit", bybit_engine);
+        let precision = Arc::new(PricePrecision::new());
+        for exchange in ["Binance", "Bybit"] {
+            precision.set_step_sizes(exchange, [("BTCUSDT".to_string(), dec("0.001"))].into_iter().collect());
+        }
+        let config = ExecutionConfig { leg_submission: LegSubmission::Concurrent, ..ExecutionConfig::default() };
+        let executor = Executor::new(vec![binance_client, bybit_client], config).with_precision(precision);
+        
+        let result = executor.execute(&opportunity(Strategy::CrossExchange, vec![
+            step("Binance", "BTCUSDT", OrderSide::Buy, "1.23456789", "100"),
+            step("Bybit", "BTCUSDT", OrderSide::Sell, "1.23456789", "101"),
+        ])).await.unwrap();
+        
+        let ExecutionResult::Completed(fills) = result else { panic!("expected both legs to fill") };
+        let requested: Vec<Decimal> = fills.iter().map(|f| f.requested_quantity).collect();
+        assert_eq!(requested, [dec("1.234"), dec("1.234")]);
+        assert_eq!(balance(&binance, "BTC"), dec("1.234"));
+        assert_eq!(balance(&bybit, "BTC"), dec("-1.234"));
+    }
+}
@@ -0,0 +1,175 @@
+//! What each VIP fee tier of a venue would earn, and the volume it takes.
+//!
+//! The recorded market snapshots are backtested once per tier with that
+//! tier's rates on its venue, so opportunities that only clear the profit
+//! threshold at lower fees are counted too. Trades, volume and PnL are
+//! scaled from the days with snapshots to a 30-day month, the period the
+//! venues measure tier volume over. Comparing the volume a tier requires
+//! with what the bot trades, and with what all venues trade together,
+//! shows whether consolidating flow on one venue would reach a tier that
+//! pays.
+
+use crate::backtest::{BacktestParams, Backtester};
+use crate::config::{Config, FeeTier, VenueFeeConfig};
+use crate::replay::MarketSnapshot;
+use anyhow::Result;
+use rust_decimal::prelude::*;
+use serde::Serialize;
+use std::collections::BTreeSet;
+
+/// Days tier volume is measured over.
+const MONTH_DAYS: u32 = 30;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TierEstimate {
+    pub tier: FeeTier,
+    pub trades_per_month: f64,
+    /// Volume the bot would trade on the venue itself
+    pub monthly_volume_usd: Decimal,
+    /// PnL across all venues
+    pub monthly_pnl_usd: Decimal,
+}
+
+impl TierEstimate {
+    /// Whether the bot's own volume reaches the tier.
+    pub fn qualifies(&self) -> bool {
+        self.monthly_volume_usd >= self.tier.min_monthly_volume_usd
+    }
+
+    /// Volume missing to reach the tier, on top of what the bot trades there.
+    pub fn volume_shortfall_usd(&self) -> Decimal {
+        (self.tier.min_monthly_volume_usd - self.monthly_volume_usd).max(Decimal::ZERO)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VenueTierReport {
+    pub exchange: String,
+    /// Every venue's monthly volume at the configured fees: what
+    /// consolidating all flow on this venue would count towards its tier
+    pub combined_monthly_volume_usd: Decimal,
+    pub tiers: Vec<TierEstimate>,
+}
+
+impl VenueTierReport {
+    /// Highest tier the bot reaches on its own.
+    pub fn reached(&self) -> Option<&TierEstimate> {
+        self.tiers.iter().rfind(|t| t.qualifies())
+    }
+
+    /// First tier above the one reached with a positive PnL, the break-even
+    /// point worth buying volume for; `None` when none turns positive.
+    pub fn next_net_positive(&self) -> Option<&TierEstimate> {
+        let start = self.tiers.iter().rposition(|t| t.qualifies()).map_or(0, |i| i + 1);
+        self.tiers[start..].iter().find(|t| t.monthly_pnl_usd > Decimal::ZERO)
+    }
+}
+
+/// Estimates every configured tier of every venue from `snapshots`, with
+/// fills drawn from `slippage_samples` as in `optimize`.
+pub async fn simulate(config: &Config, snapshots: &[MarketSnapshot], slippage_samples: Vec<Decimal>) -> Result<Vec<VenueTierReport>> {
+    let backtester = Backtester::new(snapshots, slippage_samples);
+    let params = BacktestParams {
+        min_profit_threshold: config.trading.min_profit_threshold,
+        max_position_size: config.trading.max_position_size,
+        max_slippage_percentage: config.trading.max_slippage_percentage,
+    };
+    let days = snapshots.iter().map(|s| s.captured_at.date_naive()).collect::<BTreeSet<_>>().len().max(1);
+    let scale = Decimal::from(MONTH_DAYS) / Decimal::from(days);
+
+    let baseline = backtester.run(config, params).await?;
+    let combined_monthly_volume_usd = baseline.volume_usd.values().sum::<Decimal>() * scale;
+    let mut reports = Vec::new();
+    for exchange in ["Binance", "Bybit"] {
+        let tiers = venue_fees(config, exchange).tiers.clone();
+        if tiers.is_empty() {
+            continue;
+        }
+        let mut report = VenueTierReport { exchange: exchange.to_string(), combined_monthly_volume_usd, tiers: Vec::new() };
+        for tier in tiers {
+            let mut tiered = config.clone();
+            let fees = venue_fees_mut(&mut tiered, exchange);
+            fees.maker_fee = tier.maker_fee;
+            fees.taker_fee = tier.taker_fee;
+            let result = backtester.run(&tiered, params).await?;
+            report.tiers.push(TierEstimate {
+                trades_per_month: ((result.trades + result.abandoned) as f64) * scale.to_f64().unwrap_or(0.0),
+                monthly_volume_usd: result.volume_usd.get(exchange).copied().unwrap_or_default() * scale,
+                monthly_pnl_usd: result.net_pnl_usd * scale,
+                tier,
+            });
+        }
+        reports.push(report);
+    }
+    Ok(reports)
+}
+
+fn venue_fees<'a>(config: &'a Config, exchange: &str) -> &'a VenueFeeConfig {
+    if exchange == "Bybit" { &config.fees.bybit } else { &config.fees.binance }
+}
+
+fn venue_fees_mut<'a>(config: &'a mut Config, exchange: &str) -> &'a mut VenueFeeConfig {
+    if exchange == "Bybit" { &mut config.fees.bybit } else { &mut config.fees.binance }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::PriceMap;
+    use chrono::{DateTime, Utc};
+
+    fn snapshot(at: &str, binance_btc: i64, bybit_btc: i64) -> MarketSnapshot {
+        MarketSnapshot {
+            captured_at: at.parse::<DateTime<Utc>>().unwrap(),
+            binance: PriceMap::from([("BTCUSDT".to_string(), Decimal::from(binance_btc))]),
+            bybit: PriceMap::from([("BTCUSDT".to_string(), Decimal::from(bybit_btc))]),
+            opportunities: Vec::new(),
+        }
+    }
+
+    fn tier(name: &str, volume: i64, taker: &str) -> FeeTier {
+        let fee = Decimal::from_str_exact(taker).unwrap();
+        FeeTier { name: name.to_string(), min_monthly_volume_usd: Decimal::from(volume), maker_fee: fee, taker_fee: fee }
+    }
+
+    #[tokio::test]
+    async fn test_lower_tiers_turn_thin_spreads_profitable() {
+        let mut config = Config::default();
+        config.trading.trading_pairs = vec!["BTCUSDT".to_string()];
+        config.trading.spread_min_samples = 2;
+        config.trading.spread_zscore_threshold = 1.0;
+        config.risk.scoring.cross_exchange_max_score = 1.0;
+        config.trading.min_profit_threshold = Decimal::from_str_exact("0.1").unwrap();
+        config.trading.max_position_size = Decimal::from(1000);
+        config.fees.bybit.tiers = vec![tier("VIP0", 0, "0.001"), tier("VIP1", 100_000, "0.0002"), tier("VIP2", 1_000_000_000, "0.0001")];
+        // A 0.25% gap over two days: below two 0.1% taker fees plus the
+        // threshold, above them once Bybit's fee drops to 0.02%
+        let snapshots = vec![
+            snapshot("2024-03-01T10:00:00Z", 60000, 60000),
+            snapshot("2024-03-01T10:01:00Z", 60000, 60000),
+            snapshot("2024-03-01T10:02:00Z", 60000, 60150),
+            snapshot("2024-03-02T10:02:00Z", 60000, 60150),
+        ];
+
+        let reports = simulate(&config, &snapshots, Vec::new()).await.unwrap();
+        assert_eq!(reports.len(), 1);
+        let bybit = &reports[0];
+        assert_eq!(bybit.exchange, "Bybit");
+        assert_eq!(bybit.combined_monthly_volume_usd, Decimal::ZERO);
+
+        let [vip0, vip1, vip2] = &bybit.tiers[..] else { panic!("expected three tiers") };
+        assert_eq!(vip0.trades_per_month, 0.0);
+        assert!(vip0.qualifies());
+        assert!(vip1.monthly_pnl_usd > Decimal::ZERO);
+        // Two trades over two days, each with one Bybit leg
+        assert_eq!(vip1.trades_per_month, 30.0);
+        assert_eq!(vip1.monthly_volume_usd, Decimal::from(30_000));
+        assert!(!vip1.qualifies());
+
+        assert_eq!(bybit.reached().unwrap().tier.name, "VIP0");
+        let next = bybit.next_net_positive().unwrap();
+        assert_eq!(next.tier.name, "VIP1");
+        assert_eq!(next.volume_shortfall_usd(), Decimal::from(70_000));
+        assert!(vip2.monthly_pnl_usd > vip1.monthly_pnl_usd);
+    }
+}
@@ -0,0 +1,262 @@
+//! Per-exchange market data tasks.
+//!
+//! Each venue's prices and book tickers are collected by a task of its own
+//! and published to a `MarketStore` that the scan loop takes them from. A
+//! supervisor restarts a task that panics or stops publishing, so a failure
+//! on one venue costs that venue a few scans rather than counting against
+//! the scan loop.
+
+use crate::arbitrage::outage::OutageDetector;
+use crate::exchanges::snapshot::PriceSnapshot;
+use crate::exchanges::{BookTickerMap, ExchangeError};
+use crate::metrics::MarketMetrics;
+use anyhow::{Context, Result};
+use dashmap::DashMap;
+use log::error;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
+
+/// Timeout of each market data request.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Longest pause before restarting a feed that keeps failing.
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(30);
+
+/// One collection round of a venue.
+pub struct MarketUpdate {
+    /// Prices and book tickers, or `None` while the outage detector keeps
+    /// the venue excluded and no probe is due
+    pub market: Option<Result<(PriceSnapshot, BookTickerMap)>>,
+    /// How long the price request took
+    pub latency: Duration,
+}
+
+/// Fetches prices and book tickers of one venue unless the outage detector
+/// keeps it excluded and no probe is due. The outcome and latency feed the
+/// detector.
+pub async fn fetch_market<P, Q>(exchange: &str, outages: &OutageDetector, prices: P, quotes: Q) -> MarketUpdate
+where
+    P: Future<Output = Result<PriceSnapshot>>,
+    Q: Future<Output = Result<BookTickerMap>>,
+{
+    if !outages.should_request(exchange, chrono::Utc::now()) {
+        return MarketUpdate { market: None, latency: Duration::ZERO };
+    }
+
+    let started = Instant::now();
+    let ((prices, latency), quotes) = tokio::join!(
+        async { (timeout(REQUEST_TIMEOUT, prices).await, started.elapsed()) },
+        timeout(REQUEST_TIMEOUT, quotes)
+    );
+    // Keep the ExchangeError inside the anyhow chain so retry classification can downcast it
+    let prices = prices
+        .map_err(|_| anyhow::Error::from(ExchangeError::NetworkError(format!("{} API timeout", exchange))))
+        .and_then(|r| r.with_context(|| format!("{} API error", exchange)));
+    let quotes = quotes
+        .map_err(|_| anyhow::Error::from(ExchangeError::NetworkError(format!("{} book ticker timeout", exchange))))
+        .and_then(|r| r.with_context(|| format!("{} book ticker error", exchange)));
+    let market = prices.and_then(|prices| quotes.map(|quotes| (prices, quotes)));
+
+    outages.record(exchange, market.is_ok(), started.elapsed(), chrono::Utc::now());
+    MarketUpdate { market: Some(market), latency }
+}
+
+/// Latest round of each venue's feed, waiting to be taken by the scan loop.
+#[derive(Default)]
+pub struct MarketStore {
+    updates: DashMap<String, MarketUpdate>,
+    /// When each feed last published or was (re)started
+    heartbeats: DashMap<String, Instant>,
+    published: Notify,
+    /// Pause between two rounds of a feed, following the scan interval
+    interval_ms: AtomicU64,
+}
+
+impl MarketStore {
+    pub fn new(interval: Duration) -> Self {
+        Self { interval_ms: AtomicU64::new(interval.as_millis() as u64), ..Self::default() }
+    }
+
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms.load(Ordering::Relaxed))
+    }
+
+    pub fn set_interval(&self, interval: Duration) {
+        self.interval_ms.store(interval.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// Whether a feed has been started for `exchange`.
+    pub fn has_feed(&self, exchange: &str) -> bool {
+        self.heartbeats.contains_key(exchange)
+    }
+
+    fn heartbeat(&self, exchange: &str) {
+        self.heartbeats.insert(exchange.to_string(), Instant::now());
+    }
+
+    fn is_stalled(&self, exchange: &str, stall_timeout: Duration) -> bool {
+        self.heartbeats.get(exchange).is_some_and(|beat| beat.elapsed() > stall_timeout)
+    }
+
+    /// Replaces `exchange`'s pending update with `update`.
+    pub fn publish(&self, exchange: &str, update: MarketUpdate) {
+        self.heartbeat(exchange);
+        self.updates.insert(exchange.to_string(), update);
+        self.published.notify_waiters();
+    }
+
+    /// Takes the pending update of each of `exchanges`, waiting up to `wait`
+    /// for those that have not published since they were last taken. A
+    /// venue that has still not published by then is `None`.
+    pub async fn take(&self, exchanges: &[&str], wait: Duration) -> Vec<Option<MarketUpdate>> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let published = self.published.notified();
+            tokio::pin!(published);
+            // Register before checking so a publish in between is not missed
+            published.as_mut().enable();
+            if exchanges.iter().all(|exchange| self.updates.contains_key(*exchange)) {
+                break;
+            }
+            if tokio::time::timeout_at(deadline, published).await.is_err() {
+                break;
+            }
+        }
+        exchanges.iter().map(|exchange| self.updates.remove(*exchange).map(|(_, update)| update)).collect()
+    }
+}
+
+/// Runs `collect` for `exchange` in a task of its own until `cancel`
+/// fires, one round every `store.interval()`, each published to `store`.
+/// A task that panics, or publishes nothing for `stall_timeout`, is
+/// aborted and started again after a delay that grows while it keeps
+/// failing.
+pub async fn supervise_feed<F, Fut>(
+    exchange: String,
+    store: Arc<MarketStore>,
+    stall_timeout: Duration,
+    metrics: Arc<MarketMetrics>,
+    cancel: CancellationToken,
+    collect: F,
+) where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = MarketUpdate> + Send + 'static,
+{
+    let collect = Arc::new(collect);
+    let mut failures: u32 = 0;
+    while !cancel.is_cancelled() {
+        let started = Instant::now();
+        store.heartbeat(&exchange);
+        let mut task = tokio::spawn(run_feed(exchange.clone(), store.clone(), collect.clone()));
+        let mut checks = tokio::time::interval(Duration::from_secs(1));
+
+        let failure = loop {
+            tokio::select! {
+                _ = cancel.cancelled() => {
+                    task.abort();
+                    return;
+                }
+                result = &mut task => break match result {
+                    Err(e) if e.is_panic() => format!("panicked: {}", panic_message(e.into_panic())),
+                    Err(e) => format!("stopped: {}", e),
+                    Ok(()) => "stopped".to_string(),
+                },
+                _ = checks.tick() => {
+                    if store.is_stalled(&exchange, stall_timeout) {
+                        task.abort();
+                        break format!("published nothing for {}s", stall_timeout.as_secs());
+                    }
+                }
+            }
+        };
+        metrics.record_feed_restart(&exchange);
+
+        // A feed that ran for a while starts over from the shortest delay
+        failures = if started.elapsed() > stall_timeout { 1 } else { failures + 1 };
+        let delay = Duration::from_secs(1 << failures.min(6).saturating_sub(1)).min(MAX_RESTART_DELAY);
+        error!("{} market feed {}, restarting in {:?}", exchange, failure, delay);
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = tokio::time::sleep(delay) => {}
+        }
+    }
+}
+
+async fn run_feed<F, Fut>(exchange: String, store: Arc<MarketStore>, collect: Arc<F>)
+where
+    F: Fn() -> Fut,
+    Fut: Future<Output = MarketUpdate>,
+{
+    loop {
+        let update = collect().await;
+        store.publish(&exchange, update);
+        tokio::time::sleep(store.interval()).await;
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload.downcast_ref::<&str>().map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::snapshot::SymbolTable;
+    use std::sync::atomic::AtomicU32;
+
+    fn update() -> MarketUpdate {
+        let prices = PriceSnapshot::new(Arc::new(SymbolTable::new()));
+        MarketUpdate { market: Some(Ok((prices, BookTickerMap::new()))), latency: Duration::from_millis(5) }
+    }
+
+    #[tokio::test]
+    async fn test_take_waits_for_every_venue_or_the_deadline() {
+        let store = Arc::new(MarketStore::new(Duration::from_millis(10)));
+        store.publish("Binance", update());
+        let publisher = store.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            publisher.publish("Bybit", update());
+        });
+
+        let taken = store.take(&["Binance", "Bybit"], Duration::from_secs(5)).await;
+        assert!(taken.iter().all(Option::is_some));
+
+        // Taken updates are gone; a silent venue is missing once the wait is over
+        store.publish("Bybit", update());
+        let taken = store.take(&["Binance", "Bybit"], Duration::from_millis(50)).await;
+        assert!(taken[0].is_none() && taken[1].is_some());
+    }
+
+    #[tokio::test]
+    async fn test_panicking_feed_is_restarted() {
+        let store = Arc::new(MarketStore::new(Duration::from_millis(10)));
+        let metrics = Arc::new(MarketMetrics::new());
+        let cancel = CancellationToken::new();
+        let rounds = Arc::new(AtomicU32::new(0));
+
+        let counter = rounds.clone();
+        tokio::spawn(supervise_feed("Bybit".to_string(), store.clone(), Duration::from_secs(30), metrics.clone(), cancel.clone(),
+            move || {
+                let round = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    if round == 0 {
+                        panic!("malformed ticker");
+                    }
+                    update()
+                }
+            }));
+
+        let taken = store.take(&["Bybit"], Duration::from_secs(5)).await;
+        cancel.cancel();
+        assert!(taken[0].is_some());
+        assert!(rounds.load(Ordering::SeqCst) >= 2);
+        assert!(metrics.prometheus_metrics().contains("arbitrage_feed_restarts_total{exchange=\"Bybit\"} 1"));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::metrics::{self, write_metric};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::RwLock;
+
+/// Shared, lock-light view of the bot's health, updated by the scan loop
+/// and read by the admin server's probes.
+#[derive(Debug)]
+pub struct HealthState {
+    started_at: DateTime<Utc>,
+    last_successful_scan: RwLock<Option<DateTime<Utc>>>,
+    exchanges: DashMap<String, ExchangeStatus>,
+    circuit_breaker_open: AtomicBool,
+    last_scan_timing: RwLock<Option<ScanTiming>>,
+    scans_timed: AtomicU64,
+}
+
+/// Wall-clock time spent in the phases of the most recent scan.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct ScanTiming {
+    /// Fetching tickers from both exchanges
+    pub fetch_ms: f64,
+    /// Running the detection engine on the filtered prices
+    pub analyze_ms: f64,
+    pub total_ms: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExchangeStatus {
+    pub exchange: String,
+    pub connected: bool,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub live: bool,
+    pub ready: bool,
+    pub uptime_seconds: i64,
+    pub last_successful_scan: Option<DateTime<Utc>>,
+    pub seconds_since_last_scan: Option<i64>,
+    pub circuit_breaker_open: bool,
+    pub exchanges: Vec<ExchangeStatus>,
+    pub last_scan_timing: Option<ScanTiming>,
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthState {
+    pub fn new() -> Self {
+        Self {
+            started_at: Utc::now(),
+            last_successful_scan: RwLock::new(None),
+            exchanges: DashMap::new(),
+            circuit_breaker_open: AtomicBool::new(false),
+            last_scan_timing: RwLock::new(None),
+            scans_timed: AtomicU64::new(0),
+        }
+    }
+    
+    pub fn record_scan(&self) {
+        if let Ok(mut last) = self.last_successful_scan.write() {
+            *last = Some(Utc::now());
+        }
+    }
+    
+    pub fn record_scan_timing(&self, timing: ScanTiming) {
+        if let Ok(mut last) = self.last_scan_timing.write() {
+            *last = Some(timing);
+        }
+        self.scans_timed.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    pub fn last_scan_timing(&self) -> Option<ScanTiming> {
+        self.last_scan_timing.read().ok().and_then(|last| *last)
+    }
+    
+    /// Prometheus text exposition of scan timing and health gauges.
+    pub fn prometheus_metrics(&self, max_scan_age: chrono::Duration) -> String {
+        let report = self.report(max_scan_age);
+        let flag = |on: bool| if on { 1.0 } else { 0.0 };
+        let mut out = String::new();
+        
+        if let Some(timing) = report.last_scan_timing {
+            write_metric(&mut out, &metrics::SCAN_DURATION, &[
+                (vec!["fetch".to_string()], timing.fetch_ms / 1000.0),
+                (vec!["analyze".to_string()], timing.analyze_ms / 1000.0),
+                (vec!["total".to_string()], timing.total_ms / 1000.0),
+            ]);
+        }
+        write_metric(&mut out, &metrics::SCANS_TIMED, &[(vec![], self.scans_timed.load(Ordering::Relaxed) as f64)]);
+        write_metric(&mut out, &metrics::UPTIME, &[(vec![], report.uptime_seconds as f64)]);
+        write_metric(&mut out, &metrics::READY, &[(vec![], flag(report.ready))]);
+        write_metric(&mut out, &metrics::CIRCUIT_BREAKER_OPEN, &[(vec![], flag(report.circuit_breaker_open))]);
+        let exchanges: Vec<(Vec<String>, f64)> = report.exchanges.iter()
+            .map(|s| (vec![s.exchange.clone()], flag(s.connected)))
+            .collect();
+        write_metric(&mut out, &metrics::EXCHANGE_CONNECTED, &exchanges);
+        out
+    }
+    
+    pub fn record_exchange_success(&self, exchange: &str) {
+        let mut status = self.status_entry(exchange);
+        status.connected = true;
+        status.last_success = Some(Utc::now());
+        status.last_error = None;
+    }
+    
+    pub fn record_exchange_failure(&self, exchange: &str, error: &str) {
+        let mut status = self.status_entry(exchange);
+        status.connected = false;
+        status.last_error = Some(error.to_string());
+    }
+    
+    pub fn set_circuit_breaker_open(&self, open: bool) {
+        self.circuit_breaker_open.store(open, Ordering::Relaxed);
+    }
+    
+    /// Live while the scan loop keeps completing scans; a fresh process gets
+    /// `max_scan_age` from startup before it is considered wedged.
+    pub fn is_live(&self, max_scan_age: chrono::Duration) -> bool {
+        let reference = self.last_scan().unwrap_or(self.started_at);
+        Utc::now().signed_duration_since(reference) <= max_scan_age
+    }
+    
+    /// Ready when live, every known exchange answered its last call and the
+    /// circuit breaker is closed.
+    pub fn is_ready(&self, max_scan_age: chrono::Duration) -> bool {
+        self.last_scan().is_some()
+            && self.is_live(max_scan_age)
+            && !self.circuit_breaker_open.load(Ordering::Relaxed)
+            && self.exchanges.iter().all(|s| s.connected)
+    }
+    
+    pub fn report(&self, max_scan_age: chrono::Duration) -> HealthReport {
+        let now = Utc::now();
+        let last_scan = self.last_scan();
+        let mut exchanges: Vec<ExchangeStatus> = self.exchanges.iter().map(|s| s.clone()).collect();
+        exchanges.sort_by(|a, b| a.exchange.cmp(&b.exchange));
+        
+        HealthReport {
+            live: self.is_live(max_scan_age),
+            ready: self.is_ready(max_scan_age),
+            uptime_seconds: now.signed_duration_since(self.started_at).num_seconds(),
+            last_successful_scan: last_scan,
+            seconds_since_last_scan: last_scan.map(|t| now.signed_duration_since(t).num_seconds()),
+            circuit_breaker_open: self.circuit_breaker_open.load(Ordering::Relaxed),
+            exchanges,
+            last_scan_timing: self.last_scan_timing(),
+        }
+    }
+    
+    fn last_scan(&self) -> Option<DateTime<Utc>> {
+        self.last_successful_scan.read().ok().and_then(|last| *last)
+    }
+    
+    fn status_entry(&self, exchange: &str) -> dashmap::mapref::one::RefMut<'_, String, ExchangeStatus> {
+        self.exchanges.entry(exchange.to_string()).or_insert_with(|| ExchangeStatus {
+            exchange: exchange.to_string(),
+            connected: false,
+            last_success: None,
+            last_error: None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_readiness_transitions() {
+        let health = HealthState::new();
+        let max_age = chrono::Duration::seconds(60);
+        
+        assert!(health.is_live(max_age));
+        assert!(!health.is_ready(max_age));
+        
+        health.record_exchange_success("Binance");
+        health.record_exchange_success("Bybit");
+        health.record_scan();
+        assert!(health.is_ready(max_age));
+        
+        health.record_exchange_failure("Bybit", "timeout");
+        assert!(!health.is_ready(max_age));
+        
+        health.record_exchange_success("Bybit");
+        health.set_circuit_breaker_open(true);
+        assert!(!health.is_ready(max_age));
+        
+        let report = health.report(max_age);
+        assert!(report.circuit_breaker_open);
+        assert_eq!(report.exchanges.len(), 2);
+    }
+    
+    #[test]
+    fn test_scan_timing_metrics() {
+        let health = HealthState::new();
+        let max_age = chrono::Duration::seconds(60);
+        assert!(!health.prometheus_metrics(max_age).contains("arbitrage_scan_duration_seconds"));
+        
+        health.record_exchange_success("Binance");
+        health.record_scan_timing(ScanTiming { fetch_ms: 120.0, analyze_ms: 4.5, total_ms: 125.0 });
+        assert_eq!(health.report(max_age).last_scan_timing.map(|t| t.analyze_ms), Some(4.5));
+        
+        let metrics = health.prometheus_metrics(max_age);
+        assert!(metrics.contains("arbitrage_scan_duration_seconds{phase=\"fetch\"} 0.12\n"));
+        assert!(metrics.contains("arbitrage_scan_duration_seconds{phase=\"total\"} 0.125\n"));
+        assert!(metrics.contains("arbitrage_scans_timed 1\n"));
+        assert!(metrics.contains("arbitrage_exchange_connected{exchange=\"Binance\"} 1\n"));
+    }
+    
+    #[test]
+    fn test_stalled_scan_loop_is_not_live() {
+        let health = HealthState::new();
+        health.record_scan();
+        assert!(!health.is_live(chrono::Duration::seconds(-1)));
+    }
+}
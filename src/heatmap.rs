@@ -0,0 +1,176 @@
+//! Average net profit of detected opportunities by path and hour of day.
+//!
+//! Built from the opportunities stored with each market snapshot over the
+//! last days. Hours are UTC, like maintenance windows, so the hours a path
+//! pays best at can be kept clear of maintenance or scanned more often.
+//! A path only counts in the hours it was found in; how often it
+//! was found shows in its observation counts next to the snapshots taken.
+
+use crate::arbitrage::risk::Strategy;
+use crate::replay::{MarketSnapshot, SnapshotStore};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Timelike, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+const HOURS: usize = 24;
+
+/// Observation count and net profit total per hour.
+type HourlySums = [(usize, Decimal); HOURS];
+
+/// Average net profit per UTC hour, index 0 being 00:00-00:59.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HourlyProfit {
+    /// Times an opportunity was found, per hour
+    pub observations: Vec<usize>,
+    /// `None` for hours without observations
+    pub average_net_profit_percentage: Vec<Option<Decimal>>,
+}
+
+impl HourlyProfit {
+    fn from_sums(sums: &HourlySums) -> Self {
+        Self {
+            observations: sums.iter().map(|(count, _)| *count).collect(),
+            average_net_profit_percentage: sums.iter()
+                .map(|(count, total)| (*count > 0).then(|| total / Decimal::from(*count)))
+                .collect(),
+        }
+    }
+
+    /// Average over every observation, whatever its hour.
+    pub fn overall(&self) -> Option<Decimal> {
+        let count: usize = self.observations.iter().sum();
+        let total: Decimal = self.observations.iter().zip(&self.average_net_profit_percentage)
+            .filter_map(|(n, average)| Some(average.as_ref()? * Decimal::from(*n)))
+            .sum();
+        (count > 0).then(|| total / Decimal::from(count))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PathHeatmap {
+    pub exchange: String,
+    pub strategy: Strategy,
+    pub path: Vec<String>,
+    #[serde(flatten)]
+    pub hours: HourlyProfit,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OpportunityHeatmap {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    /// Snapshots taken per hour, for judging how often paths were found
+    pub snapshots: Vec<usize>,
+    /// Every opportunity, whatever its path
+    pub all: HourlyProfit,
+    /// Best overall average first
+    pub paths: Vec<PathHeatmap>,
+}
+
+impl OpportunityHeatmap {
+    pub fn from_snapshots(snapshots: &[MarketSnapshot], from: NaiveDate, to: NaiveDate) -> Self {
+        let mut taken = vec![0; HOURS];
+        let mut all: HourlySums = [(0, Decimal::ZERO); HOURS];
+        let mut paths: BTreeMap<(String, Vec<String>), (Strategy, HourlySums)> = BTreeMap::new();
+        for snapshot in snapshots {
+            let hour = snapshot.captured_at.hour() as usize;
+            taken[hour] += 1;
+            for opportunity in &snapshot.opportunities {
+                let (_, sums) = paths.entry((opportunity.exchange.clone(), opportunity.path.clone()))
+                    .or_insert((opportunity.strategy, [(0, Decimal::ZERO); HOURS]));
+                for sum in [&mut sums[hour], &mut all[hour]] {
+                    sum.0 += 1;
+                    sum.1 += opportunity.net_profit_percentage;
+                }
+            }
+        }
+
+        let mut paths: Vec<PathHeatmap> = paths.into_iter()
+            .map(|((exchange, path), (strategy, sums))| PathHeatmap { exchange, strategy, path, hours: HourlyProfit::from_sums(&sums) })
+            .collect();
+        paths.sort_by_key(|row| Reverse(row.hours.overall()));
+        Self { from, to, snapshots: taken, all: HourlyProfit::from_sums(&all), paths }
+    }
+
+    /// Hours with opportunities, best average net profit first.
+    pub fn best_hours(&self) -> Vec<usize> {
+        let mut hours: Vec<(usize, Decimal)> = self.all.average_net_profit_percentage.iter().enumerate()
+            .filter_map(|(hour, average)| Some((hour, (*average)?)))
+            .collect();
+        hours.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        hours.into_iter().map(|(hour, _)| hour).collect()
+    }
+}
+
+/// Heatmap of the snapshots captured over the `days` days before `now`.
+pub fn load(store: &SnapshotStore, days: u32, now: DateTime<Utc>) -> Result<OpportunityHeatmap> {
+    let since = now - Duration::days(days as i64);
+    let snapshots: Vec<MarketSnapshot> = store.load_range(since.date_naive(), now.date_naive())?
+        .into_iter()
+        .filter(|s| s.captured_at >= since && s.captured_at <= now)
+        .collect();
+    Ok(OpportunityHeatmap::from_snapshots(&snapshots, since.date_naive(), now.date_naive()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replay::OpportunitySummary;
+    use crate::storage::LocalStore;
+
+    fn snapshot(at: &str, opportunities: &[(&str, &str)]) -> MarketSnapshot {
+        MarketSnapshot {
+            captured_at: at.parse().unwrap(),
+            binance: Default::default(),
+            bybit: Default::default(),
+            opportunities: opportunities.iter()
+                .map(|(path, profit)| OpportunitySummary {
+                    exchange: "Binance".to_string(),
+                    strategy: Strategy::Triangular,
+                    path: path.split('>').map(str::to_string).collect(),
+                    net_profit_percentage: Decimal::from_str_exact(profit).unwrap(),
+                    risk_score: 0.2,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_averages_by_path_and_hour() {
+        let snapshots = [
+            snapshot("2024-03-01T14:05:00Z", &[("USDT>BTC>ETH", "0.4"), ("USDT>ETH>SOL", "0.1")]),
+            snapshot("2024-03-02T14:40:00Z", &[("USDT>BTC>ETH", "0.2")]),
+            snapshot("2024-03-02T03:00:00Z", &[("USDT>ETH>SOL", "0.3")]),
+            snapshot("2024-03-02T03:30:00Z", &[]),
+        ];
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
+        let heatmap = OpportunityHeatmap::from_snapshots(&snapshots, date, date);
+        assert_eq!((heatmap.snapshots[14], heatmap.snapshots[3], heatmap.snapshots[0]), (2, 2, 0));
+
+        let btc = &heatmap.paths[0];
+        assert_eq!(btc.path, ["USDT", "BTC", "ETH"]);
+        assert_eq!(btc.hours.observations[14], 2);
+        assert_eq!(btc.hours.average_net_profit_percentage[14], Some(Decimal::from_str_exact("0.3").unwrap()));
+        assert_eq!(btc.hours.average_net_profit_percentage[3], None);
+        assert_eq!(heatmap.paths[1].hours.overall(), Some(Decimal::from_str_exact("0.2").unwrap()));
+
+        // 0.3 at 03:00 beats the 0.7 / 3 found at 14:00
+        assert_eq!(heatmap.best_hours(), [3, 14]);
+    }
+
+    #[test]
+    fn test_load_covers_the_last_days() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SnapshotStore::new(LocalStore::new(dir.path()).unwrap());
+        for at in ["2024-03-01T09:00:00Z", "2024-03-03T10:00:00Z", "2024-03-05T11:00:00Z"] {
+            store.record(&snapshot(at, &[("USDT>BTC>ETH", "0.2")])).unwrap();
+        }
+        let heatmap = load(&store, 3, "2024-03-05T10:00:00Z".parse().unwrap()).unwrap();
+        assert_eq!(heatmap.from, NaiveDate::from_ymd_opt(2024, 3, 2).unwrap());
+        assert_eq!(heatmap.all.observations.iter().sum::<usize>(), 1);
+        assert_eq!(heatmap.all.observations[10], 1);
+    }
+}
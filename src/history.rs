@@ -0,0 +1,37 @@
+use crate::exchanges::{binance::BinanceClient, bybit::BybitClient, KlineInterval};
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::info;
+use std::ops::Range;
+
+/// Downloads candles for `range` into the local store, resuming after the
+/// newest candle already on disk so repeated runs only fetch the gap.
+pub async fn backfill_klines(
+    store: &LocalStore,
+    exchange: &str,
+    symbol: &str,
+    interval: KlineInterval,
+    range: Range<DateTime<Utc>>,
+) -> Result<usize> {
+    let start = match store.last_kline_time(exchange, symbol, interval)? {
+        Some(last) if last + interval.duration() > range.start => last + interval.duration(),
+        _ => range.start,
+    };
+    
+    if start >= range.end {
+        info!("{} {} {} already up to date", exchange, symbol, interval);
+        return Ok(0);
+    }
+    
+    let klines = match exchange.to_lowercase().as_str() {
+        "binance" => BinanceClient::new()?.get_klines(symbol, interval, start..range.end).await?,
+        "bybit" => BybitClient::new()?.get_klines(symbol, interval, start..range.end).await?,
+        other => return Err(anyhow::anyhow!("Unknown exchange: {}", other)),
+    };
+    
+    store.append_klines(exchange, symbol, interval, &klines)?;
+    info!("Stored {} {} candles for {} on {}", klines.len(), interval, symbol, exchange);
+    
+    Ok(klines.len())
+}
@@ -0,0 +1,314 @@
+use crate::arbitrage::ExecutionStep;
+use crate::config::CrashRecovery;
+use crate::exchanges::{new_client_order_id, OrderRequest, OrderSide};
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// How an execution left the journal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionOutcome {
+    Completed,
+    /// Stopped early; whatever filled was kept
+    Abandoned,
+    /// Filled legs were reversed during crash recovery
+    Unwound,
+    /// Left for the operator to resolve
+    Manual,
+}
+
+/// One execution state transition, keyed by the opportunity's idempotency key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JournalEvent {
+    Started { key: String, exchange: String, steps: Vec<ExecutionStep> },
+    /// Written before the order is sent, so a crash mid-request still
+    /// leaves its client order ID behind
+    LegSubmitted { key: String, leg: usize, client_order_id: String, quantity: Decimal },
+    LegFilled { key: String, leg: usize, filled_quantity: Decimal, average_price: Option<Decimal> },
+    /// Crash recovery started finishing or unwinding the execution
+    Recovering { key: String, action: CrashRecovery },
+    Finished { key: String, outcome: ExecutionOutcome },
+}
+
+impl JournalEvent {
+    fn key(&self) -> &str {
+        match self {
+            JournalEvent::Started { key, .. }
+            | JournalEvent::LegSubmitted { key, .. }
+            | JournalEvent::LegFilled { key, .. }
+            | JournalEvent::Recovering { key, .. }
+            | JournalEvent::Finished { key, .. } => key,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct JournalRecord {
+    at: DateTime<Utc>,
+    #[serde(flatten)]
+    event: JournalEvent,
+}
+
+/// Progress of one leg as far as the journal knows.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LegProgress {
+    pub client_order_ids: Vec<String>,
+    pub filled_quantity: Decimal,
+    pub average_price: Option<Decimal>,
+    /// Submitted orders whose fill was never recorded
+    pub unconfirmed: bool,
+}
+
+/// An execution with no `Finished` record: the process stopped while it
+/// was running.
+#[derive(Debug, Clone)]
+pub struct PendingExecution {
+    pub key: String,
+    pub exchange: String,
+    pub steps: Vec<ExecutionStep>,
+    pub legs: Vec<LegProgress>,
+    pub recovering: bool,
+}
+
+impl PendingExecution {
+    /// Market orders that take the execution back to its starting asset:
+    /// `Finish` submits what is left of the cycle, sized to the first leg's
+    /// fill, and `Unwind` reverses every filled leg, last one first. Call
+    /// once every leg's fill is confirmed.
+    pub fn recovery_orders(&self, action: CrashRecovery) -> Vec<(usize, OrderRequest)> {
+        let order = |leg: usize, side: OrderSide, quantity: Decimal| {
+            let mut order = self.steps[leg].order_request();
+            order.side = side;
+            order.quantity = quantity;
+            order.client_order_id = Some(new_client_order_id());
+            (leg, order)
+        };
+        let Some(first_fill) = self.legs.first().map(|l| l.filled_quantity).filter(|q| *q > Decimal::ZERO) else {
+            // Nothing was bought, so nothing is exposed
+            return Vec::new();
+        };
+
+        match action {
+            CrashRecovery::Finish => {
+                let scale = (first_fill / self.steps[0].quantity).min(Decimal::ONE);
+                self.steps.iter().zip(&self.legs).enumerate().skip(1)
+                    .filter_map(|(i, (step, leg))| {
+                        let remaining = step.quantity * scale - leg.filled_quantity;
+                        (remaining > Decimal::ZERO).then(|| order(i, step.side.clone(), remaining))
+                    })
+                    .collect()
+            }
+            CrashRecovery::Unwind => {
+                if self.is_complete() {
+                    return Vec::new();
+                }
+                self.legs.iter().enumerate().rev()
+                    .filter(|(_, leg)| leg.filled_quantity > Decimal::ZERO)
+                    .map(|(i, leg)| {
+                        let side = match self.steps[i].side {
+                            OrderSide::Buy => OrderSide::Sell,
+                            OrderSide::Sell => OrderSide::Buy,
+                        };
+                        order(i, side, leg.filled_quantity)
+                    })
+                    .collect()
+            }
+            CrashRecovery::Manual => Vec::new(),
+        }
+    }
+
+    /// Every leg filled its share of the first leg's fill.
+    pub fn is_complete(&self) -> bool {
+        let Some(first_fill) = self.legs.first().map(|l| l.filled_quantity).filter(|q| *q > Decimal::ZERO) else {
+            return false;
+        };
+        let scale = (first_fill / self.steps[0].quantity).min(Decimal::ONE);
+        self.steps.iter().zip(&self.legs).skip(1).all(|(step, leg)| leg.filled_quantity >= step.quantity * scale)
+    }
+}
+
+/// Append-only log of execution state transitions under
+/// `journal/executions.jsonl`. After a crash, executions without a
+/// `Finished` record are handed to crash recovery on the next start.
+#[derive(Debug)]
+pub struct ExecutionJournal {
+    store: LocalStore,
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl ExecutionJournal {
+    pub fn open(store: LocalStore) -> Self {
+        Self { store, path: Path::new("journal").join("executions.jsonl"), lock: Mutex::new(()) }
+    }
+
+    pub fn record(&self, event: JournalEvent) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        self.store.append_jsonl(&self.path, &[JournalRecord { at: Utc::now(), event }])
+    }
+
+    /// Executions started but never finished, oldest first.
+    pub fn pending(&self) -> Result<Vec<PendingExecution>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let records: Vec<JournalRecord> = self.store.read_jsonl(&self.path)?;
+
+        let mut order = Vec::new();
+        let mut pending: HashMap<String, PendingExecution> = HashMap::new();
+        for record in records {
+            let key = record.event.key().to_string();
+            match record.event {
+                JournalEvent::Started { exchange, steps, .. } => {
+                    order.push(key.clone());
+                    let legs = vec![LegProgress::default(); steps.len()];
+                    pending.insert(key.clone(), PendingExecution { key, exchange, steps, legs, recovering: false });
+                }
+                JournalEvent::LegSubmitted { leg, client_order_id, .. } => {
+                    if let Some(progress) = pending.get_mut(&key).and_then(|p| p.legs.get_mut(leg)) {
+                        progress.client_order_ids.push(client_order_id);
+                        progress.unconfirmed = true;
+                    }
+                }
+                JournalEvent::LegFilled { leg, filled_quantity, average_price, .. } => {
+                    if let Some(progress) = pending.get_mut(&key).and_then(|p| p.legs.get_mut(leg)) {
+                        progress.filled_quantity = filled_quantity;
+                        progress.average_price = average_price;
+                        progress.unconfirmed = false;
+                    }
+                }
+                JournalEvent::Recovering { .. } => {
+                    if let Some(execution) = pending.get_mut(&key) {
+                        execution.recovering = true;
+                    }
+                }
+                JournalEvent::Finished { .. } => {
+                    pending.remove(&key);
+                }
+            }
+        }
+        Ok(order.into_iter().filter_map(|key| pending.remove(&key)).collect())
+    }
+
+    /// Execution key of every client order ID the journal has seen submitted.
+    pub fn order_owners(&self) -> Result<HashMap<String, String>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let records: Vec<JournalRecord> = self.store.read_jsonl(&self.path)?;
+        Ok(records.into_iter()
+            .filter_map(|record| match record.event {
+                JournalEvent::LegSubmitted { key, client_order_id, .. } => Some((client_order_id, key)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    /// Exchange and symbol of every leg of the executions started at or
+    /// after `since`.
+    pub fn traded_symbols(&self, since: DateTime<Utc>) -> Result<BTreeSet<(String, String)>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let records: Vec<JournalRecord> = self.store.read_jsonl(&self.path)?;
+        Ok(records.into_iter()
+            .filter(|record| record.at >= since)
+            .flat_map(|record| match record.event {
+                JournalEvent::Started { steps, .. } => steps,
+                _ => Vec::new(),
+            })
+            .map(|step| (step.exchange, step.symbol))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(symbol: &str, side: OrderSide, quantity: i64) -> ExecutionStep {
+        ExecutionStep {
+            exchange: "Binance".to_string(),
+            action: String::new(),
+            symbol: symbol.to_string(),
+            side,
+            quantity: Decimal::from(quantity),
+            expected_price: Decimal::ONE,
+            fees: Decimal::ZERO,
+            client_order_id: new_client_order_id(),
+        }
+    }
+
+    fn filled(key: &str, leg: usize, quantity: i64) -> JournalEvent {
+        JournalEvent::LegFilled { key: key.to_string(), leg, filled_quantity: Decimal::from(quantity), average_price: Some(Decimal::ONE) }
+    }
+
+    fn journal_with_half_triangle() -> (tempfile::TempDir, ExecutionJournal) {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = ExecutionJournal::open(LocalStore::new(dir.path()).unwrap());
+        let steps = vec![
+            step("BTCUSDT", OrderSide::Buy, 10),
+            step("ETHBTC", OrderSide::Buy, 100),
+            step("ETHUSDT", OrderSide::Sell, 100),
+        ];
+        for key in ["done", "half"] {
+            journal.record(JournalEvent::Started { key: key.to_string(), exchange: "Binance".to_string(), steps: steps.clone() }).unwrap();
+            journal.record(JournalEvent::LegSubmitted {
+                key: key.to_string(), leg: 0, client_order_id: steps[0].client_order_id.clone(), quantity: Decimal::from(10),
+            }).unwrap();
+            journal.record(filled(key, 0, 5)).unwrap();
+            journal.record(JournalEvent::LegSubmitted {
+                key: key.to_string(), leg: 1, client_order_id: steps[1].client_order_id.clone(), quantity: Decimal::from(50),
+            }).unwrap();
+        }
+        journal.record(JournalEvent::Finished { key: "done".to_string(), outcome: ExecutionOutcome::Abandoned }).unwrap();
+        (dir, journal)
+    }
+
+    #[test]
+    fn test_unfinished_executions_are_pending() {
+        let (_dir, journal) = journal_with_half_triangle();
+        let pending = journal.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        let execution = &pending[0];
+        assert_eq!(execution.key, "half");
+        assert_eq!(execution.legs[0].filled_quantity, Decimal::from(5));
+        assert!(!execution.legs[0].unconfirmed);
+        assert!(execution.legs[1].unconfirmed);
+        assert!(!execution.recovering);
+
+        let traded = journal.traded_symbols(DateTime::<Utc>::MIN_UTC).unwrap();
+        assert_eq!(traded.len(), 3);
+        assert!(traded.contains(&("Binance".to_string(), "ETHBTC".to_string())));
+        assert!(journal.traded_symbols(Utc::now() + chrono::Duration::hours(1)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_recovery_orders() {
+        let (_dir, journal) = journal_with_half_triangle();
+        let mut execution = journal.pending().unwrap().remove(0);
+        // Reconciliation found the second leg partially filled
+        execution.legs[1].filled_quantity = Decimal::from(20);
+
+        let finish: Vec<(usize, OrderSide, Decimal)> = execution.recovery_orders(CrashRecovery::Finish).into_iter()
+            .map(|(leg, order)| (leg, order.side, order.quantity))
+            .collect();
+        assert_eq!(finish, vec![
+            (1, OrderSide::Buy, Decimal::from(30)),
+            (2, OrderSide::Sell, Decimal::from(50)),
+        ]);
+
+        let unwind: Vec<(usize, OrderSide, Decimal)> = execution.recovery_orders(CrashRecovery::Unwind).into_iter()
+            .map(|(leg, order)| (leg, order.side, order.quantity))
+            .collect();
+        assert_eq!(unwind, vec![
+            (1, OrderSide::Sell, Decimal::from(20)),
+            (0, OrderSide::Sell, Decimal::from(5)),
+        ]);
+        assert!(execution.recovery_orders(CrashRecovery::Manual).is_empty());
+
+        execution.legs[0].filled_quantity = Decimal::ZERO;
+        assert!(execution.recovery_orders(CrashRecovery::Finish).is_empty());
+    }
+}
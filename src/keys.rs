@@ -0,0 +1,158 @@
+use crate::config::Config;
+use crate::exchanges::binance::BinanceClient;
+use crate::exchanges::bybit::BybitClient;
+use crate::exchanges::{ApiKeyPermissions, ExchangeClient};
+use anyhow::Result;
+use log::warn;
+use serde::Serialize;
+use std::sync::Arc;
+
+/// Outcome of validating one exchange's API key.
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyCheck {
+    pub exchange: String,
+    /// `None` when the key is missing or the exchange rejected it
+    pub permissions: Option<ApiKeyPermissions>,
+    pub error: Option<String>,
+    pub warnings: Vec<String>,
+}
+
+impl KeyCheck {
+    fn failed(exchange: &str, error: String) -> Self {
+        Self { exchange: exchange.to_string(), permissions: None, error: Some(error), warnings: Vec::new() }
+    }
+}
+
+/// Mismatches between what a key may do and what the bot is configured
+/// to do with it.
+pub fn permission_warnings(permissions: &ApiKeyPermissions, execution_enabled: bool) -> Vec<String> {
+    let mut warnings = Vec::new();
+    if !permissions.read {
+        warnings.push("key cannot read account data".to_string());
+    }
+    if execution_enabled && !permissions.trade {
+        warnings.push("execution is enabled but the key has no spot trading permission".to_string());
+    }
+    if !execution_enabled && permissions.trade {
+        warnings.push("key can trade although execution is disabled; a read-only key is enough".to_string());
+    }
+    if permissions.withdraw {
+        warnings.push("key can withdraw funds, which the bot never needs".to_string());
+    }
+    warnings
+}
+
+/// Whether the configured mode places orders: execution is on and watch-only
+/// alerting does not override it.
+pub fn execution_enabled(config: &Config) -> bool {
+    config.trading.enable_execution && !(config.alerts.enabled && config.alerts.watch_only)
+}
+
+/// Validates `client`'s key with a cheap signed request and compares its
+/// permissions with the configured mode.
+pub async fn check_key(client: &ExchangeClient, execution_enabled: bool) -> KeyCheck {
+    match client.get_api_key_permissions().await {
+        Ok(permissions) => KeyCheck {
+            exchange: client.name().to_string(),
+            warnings: permission_warnings(&permissions, execution_enabled),
+            permissions: Some(permissions),
+            error: None,
+        },
+        Err(e) => KeyCheck::failed(client.name(), format!("{:#}", e)),
+    }
+}
+
+/// Checks the key of every enabled exchange. A key missing from the
+/// environment is reported like a rejected one.
+pub async fn check_keys(config: &Config) -> Vec<KeyCheck> {
+    let execution_enabled = execution_enabled(config);
+    let mut checks = Vec::new();
+    if config.exchanges.binance_enabled {
+        checks.push(match BinanceClient::with_network(&config.exchanges.binance_network) {
+            Ok(client) => check_key(&ExchangeClient::Binance(Arc::new(client)), execution_enabled).await,
+            Err(e) => KeyCheck::failed("Binance", e.to_string()),
+        });
+    }
+    if config.exchanges.bybit_enabled {
+        checks.push(match BybitClient::with_network(&config.exchanges.bybit_network) {
+            Ok(client) => check_key(&ExchangeClient::Bybit(Arc::new(client)), execution_enabled).await,
+            Err(e) => KeyCheck::failed("Bybit", e.to_string()),
+        });
+    }
+    checks
+}
+
+/// Startup gate for execution: every key must validate and be allowed to
+/// trade, and none may withdraw unless `allow_withdrawal` overrides it.
+/// Without execution the same mismatches are only logged.
+pub fn enforce_permissions(checks: &[KeyCheck], execution_enabled: bool, allow_withdrawal: bool) -> Result<()> {
+    let mut problems = Vec::new();
+    for check in checks {
+        let Some(permissions) = &check.permissions else {
+            let error = check.error.as_deref().unwrap_or("unknown error");
+            if execution_enabled {
+                problems.push(format!("{} key could not be validated: {}", check.exchange, error));
+            } else {
+                warn!("{} API key could not be validated: {}", check.exchange, error);
+            }
+            continue;
+        };
+        if execution_enabled && !permissions.trade {
+            problems.push(format!("{} key has no spot trading permission", check.exchange));
+        }
+        if execution_enabled && permissions.withdraw && !allow_withdrawal {
+            problems.push(format!("{} key can withdraw funds (set trading.allow_withdrawal_permission to override)", check.exchange));
+        }
+        for warning in &check.warnings {
+            warn!("{} API key: {}", check.exchange, warning);
+        }
+    }
+    if problems.is_empty() {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!("Refusing to start execution: {}", problems.join("; ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn permissions(trade: bool, withdraw: bool) -> ApiKeyPermissions {
+        ApiKeyPermissions { read: true, trade, withdraw, ip_restricted: true }
+    }
+
+    #[test]
+    fn test_permission_warnings_follow_execution_mode() {
+        assert!(permission_warnings(&permissions(true, false), true).is_empty());
+        assert!(permission_warnings(&permissions(false, false), false).is_empty());
+
+        let warnings = permission_warnings(&permissions(false, false), true);
+        assert_eq!(warnings, vec!["execution is enabled but the key has no spot trading permission"]);
+        let warnings = permission_warnings(&permissions(true, true), false);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("read-only key is enough"));
+        assert!(warnings[1].contains("withdraw"));
+    }
+
+    #[test]
+    fn test_execution_refuses_withdrawal_keys_unless_allowed() {
+        let check = |exchange: &str, permissions: Option<ApiKeyPermissions>| KeyCheck {
+            exchange: exchange.to_string(),
+            error: permissions.is_none().then(|| "invalid api key".to_string()),
+            permissions,
+            warnings: Vec::new(),
+        };
+        let checks = vec![check("Binance", Some(permissions(true, true))), check("Bybit", Some(permissions(true, false)))];
+        let error = enforce_permissions(&checks, true, false).unwrap_err().to_string();
+        assert!(error.contains("Binance key can withdraw funds"));
+        assert!(!error.contains("Bybit"));
+        assert!(enforce_permissions(&checks, true, true).is_ok());
+        assert!(enforce_permissions(&checks, false, false).is_ok());
+
+        let checks = vec![check("Binance", Some(permissions(false, false))), check("Bybit", None)];
+        let error = enforce_permissions(&checks, true, true).unwrap_err().to_string();
+        assert!(error.contains("Binance key has no spot trading permission"));
+        assert!(error.contains("Bybit key could not be validated: invalid api key"));
+        assert!(enforce_permissions(&checks, false, false).is_ok());
+    }
+}
@@ -0,0 +1,140 @@
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub key: String,
+    pub exchange: String,
+    pub claimed_at: DateTime<Utc>,
+}
+
+/// Append-only record of every opportunity handed to execution, keyed by
+/// the opportunity's idempotency key. Entries are persisted before
+/// execution starts, so a retry loop or a restart can never execute the
+/// same opportunity twice.
+///
+/// Keys embed the scan that found the opportunity, so once an opportunity is
+/// far older than anything the executor would still accept, its key can
+/// never be offered again. Only claims inside the retention window are kept
+/// in memory; the file on disk stays a complete record.
+#[derive(Debug)]
+pub struct ExecutionLedger {
+    store: LocalStore,
+    path: PathBuf,
+    retention: Duration,
+    claimed: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl ExecutionLedger {
+    pub fn open(store: LocalStore) -> Result<Self> {
+        let path = Path::new("ledger").join("executions.jsonl");
+        let retention = Duration::hours(24);
+        let cutoff = Utc::now() - retention;
+        let entries: Vec<LedgerEntry> = store.read_jsonl(&path)?;
+        let claimed = entries.into_iter()
+            .filter(|e| e.claimed_at > cutoff)
+            .map(|e| (e.key, e.claimed_at))
+            .collect();
+        
+        Ok(Self {
+            store,
+            path,
+            retention,
+            claimed: Mutex::new(claimed),
+        })
+    }
+    
+    /// How long a claim is remembered in memory. Defaults to 24 hours, which
+    /// comfortably covers restarts and journal recovery.
+    pub fn with_retention(mut self, retention: Duration) -> Self {
+        self.retention = retention;
+        let cutoff = Utc::now() - retention;
+        if let Ok(claimed) = self.claimed.get_mut() {
+            claimed.retain(|_, claimed_at| *claimed_at > cutoff);
+        }
+        self
+    }
+    
+    /// Atomically claims `key` for execution. Returns `false` when it was
+    /// already claimed, in this process or a previous one.
+    pub fn try_claim(&self, key: &str, exchange: &str) -> Result<bool> {
+        let mut claimed = self.claimed.lock()
+            .map_err(|_| anyhow::anyhow!("Execution ledger lock poisoned"))?;
+        
+        let now = Utc::now();
+        let cutoff = now - self.retention;
+        claimed.retain(|_, claimed_at| *claimed_at > cutoff);
+        if claimed.contains_key(key) {
+            return Ok(false);
+        }
+        
+        let entry = LedgerEntry {
+            key: key.to_string(),
+            exchange: exchange.to_string(),
+            claimed_at: now,
+        };
+        self.store.append_jsonl(&self.path, &[entry])?;
+        claimed.insert(key.to_string(), now);
+        
+        Ok(true)
+    }
+    
+    pub fn contains(&self, key: &str) -> bool {
+        self.claimed.lock().map(|c| c.contains_key(key)).unwrap_or(false)
+    }
+    
+    pub fn len(&self) -> usize {
+        self.claimed.lock().map(|c| c.len()).unwrap_or(0)
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_claims_survive_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let ledger = ExecutionLedger::open(LocalStore::new(dir.path()).unwrap()).unwrap();
+        
+        assert!(ledger.try_claim("abc", "Binance").unwrap());
+        assert!(!ledger.try_claim("abc", "Binance").unwrap());
+        assert!(ledger.try_claim("def", "Bybit").unwrap());
+        
+        let reopened = ExecutionLedger::open(LocalStore::new(dir.path()).unwrap()).unwrap();
+        assert_eq!(reopened.len(), 2);
+        assert!(reopened.contains("abc"));
+        assert!(!reopened.try_claim("abc", "Binance").unwrap());
+    }
+    
+    #[test]
+    fn test_expired_claims_are_dropped() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path()).unwrap();
+        let old = LedgerEntry {
+            key: "old".to_string(),
+            exchange: "Binance".to_string(),
+            claimed_at: Utc::now() - Duration::hours(48),
+        };
+        store.append_jsonl(&Path::new("ledger").join("executions.jsonl"), &[old]).unwrap();
+        
+        let ledger = ExecutionLedger::open(store.clone()).unwrap();
+        assert!(ledger.is_empty());
+        assert!(ledger.try_claim("new", "Bybit").unwrap());
+        assert!(!ledger.try_claim("new", "Bybit").unwrap());
+        
+        let short = ExecutionLedger::open(store).unwrap().with_retention(Duration::zero());
+        assert!(!short.contains("new"));
+        assert!(short.try_claim("other", "Bybit").unwrap());
+        assert_eq!(short.len(), 1);
+    }
+}
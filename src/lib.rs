@@ -1,28 +1,264 @@
+pub mod admin;
+pub mod alerts;
 pub mod exchanges;
+pub mod executor;
+pub mod fee_tiers;
+pub mod feeds;
 pub mod arbitrage;
+pub mod backtest;
+pub mod calendar;
 pub mod config;
+pub mod cli;
+pub mod control;
+pub mod currency;
+pub mod diag;
+pub mod health;
+pub mod heatmap;
+pub mod history;
+pub mod journal;
+pub mod keys;
+pub mod ledger;
+pub mod maintenance;
+pub mod metrics;
+pub mod migration;
+pub mod notify;
+pub mod performance;
+pub mod portfolio;
+pub mod precision;
+pub mod reconcile;
+pub mod replay;
+pub mod report;
+pub mod retention;
+pub mod retry;
+pub mod scanner;
+pub mod scheduler;
+pub mod secrets;
+pub mod shadow;
+pub mod slo;
+pub mod storage;
+pub mod stress;
+pub mod summary;
+pub mod symbols;
+pub mod warm_start;
+pub mod watchdog;
 
-use crate::arbitrage::ArbitrageEngine;
-use crate::exchanges::{binance::BinanceClient, bybit::BybitClient, ExchangeError};
-use anyhow::Result;
-use log::{info, error, warn};
-use backoff::{ExponentialBackoff, future::retry};
-use std::time::Duration;
+use crate::arbitrage::{ArbitrageEngine, ArbitrageOpportunity};
+use crate::arbitrage::correlation::CorrelationService;
+use crate::arbitrage::kill_switch::KillSwitch;
+use crate::arbitrage::inventory::InventoryTracker;
+use crate::arbitrage::queue::{self, OpportunityQueue};
+use crate::arbitrage::price_cache::PriceEntry;
+use crate::arbitrage::risk::Strategy;
+use crate::arbitrage::triangular::listing_paths;
+use crate::admin::AdminServer;
+use crate::alerts::AlertNotifier;
+use crate::calendar::EventCalendar;
+use crate::cli::OutputFormat;
+use crate::config::{Config, RebalanceAccounts};
+use crate::control::BotControl;
+use crate::health::{HealthState, ScanTiming};
+use crate::journal::ExecutionJournal;
+use crate::ledger::ExecutionLedger;
+use crate::maintenance::MaintenanceWindow;
+use crate::portfolio::{PortfolioSnapshot, PortfolioStore};
+use crate::currency::CurrencyConverter;
+use crate::precision::PricePrecision;
+use crate::replay::{MarketSnapshot, OpportunitySummary, SnapshotStore};
+use crate::report::ReportStore;
+use crate::secrets::SecretString;
+use crate::symbols::SymbolFilter;
+use crate::scheduler::ScanScheduler;
+use crate::metrics::MarketMetrics;
+use crate::feeds::{MarketStore, MarketUpdate};
+use crate::slo::{SloMonitor, SloTransition};
+use crate::watchdog::{ScanWatchdog, SCAN_TIMEOUT};
+use crate::shadow::{ShadowExecutor, ShadowStore};
+use crate::storage::LocalStore;
+use crate::summary::{DailySummary, SummaryCollector, SummaryDelivery};
+use crate::warm_start::{CachedPrice, WarmStartState, WarmStartStore};
+use crate::exchanges::{binance::BinanceClient, bybit::BybitClient, keepalive, BookTickerMap, ExchangeClient, KlineInterval, SubAccountTransfer};
+use crate::exchanges::heartbeat::ExchangeHealth;
+use crate::exchanges::depth::OrderBookStore;
+use crate::exchanges::fixtures::FixtureRecorder;
+use crate::exchanges::request_log::RequestLog;
+use crate::exchanges::snapshot::{PriceSnapshot, SymbolTable};
+use crate::exchanges::reconnect::StreamHealth;
+use crate::exchanges::streams;
+use crate::executor::Executor;
+use anyhow::{Context, Result};
+use log::{debug, info, error, warn};
+use std::collections::HashSet;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::time::timeout;
+use tokio_util::sync::CancellationToken;
 
 pub struct ArbitrageBot {
-    binance: BinanceClient,
-    bybit: BybitClient,
-    engine: ArbitrageEngine,
+    binance: Arc<BinanceClient>,
+    bybit: Arc<BybitClient>,
+    engine: Arc<ArbitrageEngine>,
+    config: Config,
+    scheduler: Mutex<ScanScheduler>,
+    health: Arc<HealthState>,
+    control: Arc<BotControl>,
+    portfolio: Option<PortfolioStore>,
+    reports: ReportStore,
+    symbols: SymbolFilter,
+    symbol_table: Arc<SymbolTable>,
+    precision: Arc<PricePrecision>,
+    /// Converts USDT amounts into `monitoring.home_currency` for output
+    currency: Arc<CurrencyConverter>,
+    output: OutputFormat,
+    last_symbol_refresh: Mutex<Option<Instant>>,
+    tradable_fetched_at: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+    warm_start: Option<WarmStartStore>,
+    snapshots: Option<SnapshotStore>,
+    last_market_snapshot: Mutex<Option<Instant>>,
+    last_portfolio_snapshot: Mutex<Option<Instant>>,
+    last_trade_sample: Mutex<Option<Instant>>,
+    /// Present when `risk.scoring.correlation` is enabled
+    correlations: Option<CorrelationService>,
+    last_correlation_refresh: Mutex<Option<Instant>>,
+    order_books: Option<Arc<OrderBookStore>>,
+    /// Records the exchanges' answers when `exchanges.fixtures` is enabled
+    fixtures: Option<Arc<FixtureRecorder>>,
+    book_streams: CancellationToken,
+    market_store: Arc<MarketStore>,
+    market_feeds: CancellationToken,
+    watchdog: Option<Arc<ScanWatchdog>>,
+    watchdog_task: CancellationToken,
+    slo_task: CancellationToken,
+    keepalive: CancellationToken,
+    /// Kept for the retention job when `storage.retention` is enabled
+    retention_store: Option<LocalStore>,
+    retention: CancellationToken,
+    alerts: Option<Arc<AlertNotifier>>,
+    shadow: Option<ShadowExecutor>,
+    summaries: Option<SummaryCollector>,
+    summary_delivery: Option<SummaryDelivery>,
+    metrics: Arc<MarketMetrics>,
+    /// Hands opportunities to execution workers when `execution.queue` is enabled
+    execution_queue: Option<Arc<OpportunityQueue>>,
+    execution_workers: CancellationToken,
 }
 
 impl ArbitrageBot {
     pub async fn new() -> Result<Self> {
-        let binance = BinanceClient::new()
+        Self::with_config(Config::default()).await
+    }
+    
+    pub async fn with_config(config: Config) -> Result<Self> {
+        let mut binance = BinanceClient::with_network(&config.exchanges.binance_network)
             .map_err(|e| anyhow::anyhow!("Failed to create Binance client: {}", e))?;
-        let bybit = BybitClient::new()
+        let mut bybit = BybitClient::with_network(&config.exchanges.bybit_network)
             .map_err(|e| anyhow::anyhow!("Failed to create Bybit client: {}", e))?;
-        let engine = ArbitrageEngine::new();
+        if config.exchanges.max_concurrent_requests > 0 {
+            let slots = Arc::new(tokio::sync::Semaphore::new(config.exchanges.max_concurrent_requests));
+            binance = binance.with_shared_concurrency(slots.clone());
+            bybit = bybit.with_shared_concurrency(slots);
+        }
+        if config.exchanges.request_log.enabled {
+            let log = Arc::new(RequestLog::open(&config.exchanges.request_log)?);
+            binance = binance.with_request_log(log.clone());
+            bybit = bybit.with_request_log(log);
+        }
+        let fixtures = if config.exchanges.fixtures.enabled {
+            Some(Arc::new(FixtureRecorder::create(&config.exchanges.fixtures)?))
+        } else {
+            None
+        };
+        if let Some(fixtures) = &fixtures {
+            info!("Recording exchange answers to {}", config.exchanges.fixtures.path);
+            binance = binance.with_fixture_recorder(fixtures.clone());
+            bybit = bybit.with_fixture_recorder(fixtures.clone());
+        }
+        if config.exchanges.ws_trading.enabled && config.trading.enable_execution {
+            binance = binance.with_ws_trading(&config.exchanges.ws_trading);
+            bybit = bybit.with_ws_trading(&config.exchanges.ws_trading);
+        }
+        let (binance, bybit) = (Arc::new(binance), Arc::new(bybit));
+        let store = LocalStore::open(&config.storage)?;
+        let ledger = Arc::new(ExecutionLedger::open(store.clone())?);
+        let journal = Arc::new(ExecutionJournal::open(store.clone()));
+        let reports = ReportStore::new(store.clone());
+        let kill_switch = Arc::new(KillSwitch::new(config.risk.max_daily_loss).with_store(store.clone())?);
+        let warm_start = config.storage.warm_start.then(|| WarmStartStore::new(store.clone()));
+        let snapshots = (config.storage.snapshot_interval_seconds > 0).then(|| SnapshotStore::new(store.clone()));
+        let currency = Arc::new(CurrencyConverter::new(&config.monitoring.home_currency));
+        let summary_delivery = config.monitoring.daily_summary.enabled
+            .then(|| SummaryDelivery::new(config.monitoring.daily_summary.clone(), store.clone()).with_currency(currency.clone()));
+        let shadow_store = ShadowStore::new(store.clone());
+        let retention_store = config.storage.retention.enabled.then(|| store.clone());
+        let correlations = config.risk.scoring.correlation.enabled
+            .then(|| CorrelationService::new(config.risk.scoring.correlation.clone(), store.clone()));
+        let portfolio = config.portfolio.enabled.then(|| PortfolioStore::new(store));
+        info!("Execution ledger loaded with {} recent claims", ledger.len());
+        let precision = Arc::new(PricePrecision::new());
+        let order_books = config.order_books.enabled.then(|| Arc::new(OrderBookStore::new()));
+        let metrics = Arc::new(MarketMetrics::new());
+        let mut engine = ArbitrageEngine::from_config(&config)
+            .with_metrics(metrics.clone())
+            .with_ledger(ledger)
+            .with_kill_switch(kill_switch)
+            .with_reports(reports.clone())
+            .with_precision(precision.clone())
+            .with_currency(currency.clone());
+        if let Some(store) = &order_books {
+            engine = engine.with_order_books(store.clone(), Duration::from_millis(config.order_books.max_age_ms));
+        }
+        let watch_only = config.alerts.enabled && config.alerts.watch_only;
+        if watch_only {
+            info!("Watch-only alert mode: execution disabled, {} alert rules", config.alerts.rules.len());
+            if let Some(threshold) = config.alerts.lowest_threshold() {
+                engine = engine.with_min_profit_threshold(threshold);
+            }
+        }
+        if config.trading.enable_execution && !watch_only {
+            let clients = vec![
+                ExchangeClient::Binance(binance.clone()),
+                ExchangeClient::Bybit(bybit.clone()),
+            ];
+            let mut executor = Executor::new(clients, config.execution.clone())
+                .with_precision(precision.clone())
+                .with_journal(journal)
+                .with_retry_policy(config.exchanges.retry.trading.clone())
+                .with_max_slippage_percentage(config.trading.max_slippage_percentage);
+            for strategy in [Strategy::CrossExchange, Strategy::Triangular] {
+                executor = executor.with_min_profit_threshold(strategy, config.strategy_limits(strategy).min_profit_threshold);
+            }
+            if let Some(store) = &order_books {
+                executor = executor.with_order_books(store.clone(), Duration::from_millis(config.order_books.max_age_ms));
+            }
+            engine = engine.with_executor(executor);
+        } else {
+            let pending = journal.pending()?;
+            if !pending.is_empty() {
+                warn!("{} interrupted executions in the journal are not recovered while execution is disabled", pending.len());
+            }
+        }
+        let shadow = config.execution.shadow.enabled.then(|| {
+            let clients = vec![ExchangeClient::Binance(binance.clone()), ExchangeClient::Bybit(bybit.clone())];
+            ShadowExecutor::new(clients, config.execution.shadow.depth_levels, config.trading.max_slippage_percentage)
+                .with_metrics(metrics.clone())
+                .with_store(shadow_store.clone())
+        });
+        let maintenance_windows = config.maintenance_windows.iter()
+            .map(MaintenanceWindow::from_config)
+            .collect::<Result<Vec<_>>>()?;
+        let mut control = BotControl::new(maintenance_windows);
+        
+        if config.event_guard.enabled {
+            if let Some(path) = &config.event_guard.calendar_path {
+                let events = EventCalendar::load_events(path)?;
+                info!("Loaded {} volatility events from {}", events.len(), path);
+                control = control.with_event_calendar(EventCalendar::new(
+                    events,
+                    config.event_guard.minutes_before,
+                    config.event_guard.minutes_after,
+                ));
+            }
+        }
         
         // Test connectivity
         info!("Testing exchange connectivity...");
@@ -41,44 +277,387 @@ impl ArbitrageBot {
             }
         }
         
-        Ok(Self {
+        let execution_queue = (config.trading.enable_execution && config.execution.queue.enabled).then(|| {
+            Arc::new(OpportunityQueue::new(&config.execution.queue, config.execution.max_opportunity_age_ms)
+                .with_metrics(metrics.clone()))
+        });
+        
+        let bot = Self {
             binance,
             bybit,
-            engine,
-        })
+            engine: Arc::new(engine),
+            scheduler: Mutex::new(ScanScheduler::new(config.scan.clone())),
+            health: Arc::new(HealthState::new()),
+            control: Arc::new(control),
+            portfolio,
+            reports,
+            symbols: SymbolFilter::new(config.symbols.clone()),
+            symbol_table: Arc::new(SymbolTable::new()),
+            precision,
+            currency: currency.clone(),
+            output: OutputFormat::Text,
+            last_symbol_refresh: Mutex::new(None),
+            tradable_fetched_at: Mutex::new(None),
+            warm_start,
+            snapshots,
+            last_market_snapshot: Mutex::new(None),
+            last_portfolio_snapshot: Mutex::new(None),
+            last_trade_sample: Mutex::new(None),
+            correlations,
+            last_correlation_refresh: Mutex::new(None),
+            order_books,
+            fixtures,
+            book_streams: CancellationToken::new(),
+            market_store: Arc::new(MarketStore::new(Duration::from_millis(config.scan.interval_ms))),
+            market_feeds: CancellationToken::new(),
+            watchdog: (config.scan.watchdog_timeout_seconds > 0)
+                .then(|| Arc::new(ScanWatchdog::new(Duration::from_secs(config.scan.watchdog_timeout_seconds)))),
+            watchdog_task: CancellationToken::new(),
+            slo_task: CancellationToken::new(),
+            keepalive: CancellationToken::new(),
+            retention_store,
+            retention: CancellationToken::new(),
+            alerts: config.alerts.enabled.then(|| Arc::new(AlertNotifier::new(config.alerts.clone()).with_currency(currency.clone()))),
+            shadow,
+            summaries: summary_delivery.as_ref().map(|_| SummaryCollector::new(config.monitoring.daily_summary.top_paths)),
+            summary_delivery,
+            metrics,
+            execution_queue,
+            execution_workers: CancellationToken::new(),
+            config,
+        };
+        bot.restore_warm_start();
+        Ok(bot)
+    }
+    
+    /// Reloads the price cache and tradable symbol lists saved by the last
+    /// `shutdown`. A missing or unreadable file just means a cold start.
+    fn restore_warm_start(&self) {
+        let Some(store) = &self.warm_start else { return };
+        let state = match store.load() {
+            Ok(Some(state)) => state,
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Ignoring warm-start state: {:#}", e);
+                return;
+            }
+        };
+        
+        let now = chrono::Utc::now();
+        let prices = state.fresh_prices(chrono::Duration::seconds(arbitrage::PRICE_AGE_LIMIT_SECONDS), now);
+        let restored = prices.len();
+        self.engine.restore_price_cache(prices.into_iter().map(|p| PriceEntry {
+            exchange: p.exchange,
+            symbol: p.symbol,
+            price: p.price,
+            exchange_time: p.exchange_time,
+            received_at: p.updated_at,
+        }));
+        
+        let mut restored_symbols = false;
+        if let Some(fetched_at) = state.tradable_fetched_at {
+            let age = now.signed_duration_since(fetched_at).to_std().unwrap_or_default();
+            let interval = Duration::from_secs(self.config.symbols.refresh_interval_minutes * 60);
+            if age < interval {
+                for (exchange, symbols) in state.tradable_symbols {
+                    self.symbols.set_tradable(&exchange, symbols.into_iter().collect());
+                }
+                for (exchange, tick_sizes) in state.tick_sizes {
+                    self.precision.set_tick_sizes(&exchange, tick_sizes);
+                }
+                for (exchange, step_sizes) in state.step_sizes {
+                    self.precision.set_step_sizes(&exchange, step_sizes);
+                }
+                // Schedule the next refresh as if the saved lists had been fetched by this process
+                if let Ok(mut last) = self.last_symbol_refresh.lock() {
+                    *last = Instant::now().checked_sub(age);
+                }
+                if let Ok(mut last) = self.tradable_fetched_at.lock() {
+                    *last = Some(fetched_at);
+                }
+                restored_symbols = true;
+            }
+        }
+        
+        info!("Warm start from {}: restored {} cached prices{}", state.saved_at, restored,
+              if restored_symbols { " and tradable symbol lists" } else { "" });
+    }
+    
+    /// Saves runtime caches for the next startup. Call once the scan loop
+    /// has stopped.
+    pub fn shutdown(&self) -> Result<()> {
+        self.book_streams.cancel();
+        self.market_feeds.cancel();
+        self.watchdog_task.cancel();
+        self.slo_task.cancel();
+        self.keepalive.cancel();
+        self.retention.cancel();
+        self.execution_workers.cancel();
+        let Some(store) = &self.warm_start else { return Ok(()) };
+        let state = WarmStartState {
+            saved_at: chrono::Utc::now(),
+            prices: self.engine.price_cache_entries().into_iter()
+                .map(|p| CachedPrice { exchange: p.exchange, symbol: p.symbol, price: p.price, exchange_time: p.exchange_time, updated_at: p.received_at })
+                .collect(),
+            tradable_symbols: self.symbols.tradable_snapshot(),
+            tick_sizes: self.precision.tick_sizes_snapshot(),
+            step_sizes: self.precision.step_sizes_snapshot(),
+            tradable_fetched_at: self.tradable_fetched_at.lock().ok().and_then(|t| *t),
+        };
+        store.save(&state).context("Failed to save warm-start state")?;
+        info!("Saved {} cached prices for warm start", state.prices.len());
+        Ok(())
+    }
+    
+    pub fn with_output(mut self, output: OutputFormat) -> Self {
+        self.output = output;
+        self
+    }
+    
+    pub fn health_state(&self) -> Arc<HealthState> {
+        self.health.clone()
+    }
+    
+    pub fn control(&self) -> Arc<BotControl> {
+        self.control.clone()
+    }
+    
+    /// Home currency converter, kept at the FX rate of the latest scan.
+    pub fn currency(&self) -> Arc<CurrencyConverter> {
+        self.currency.clone()
+    }
+    
+    /// Health of each exchange client, for applications that supervise
+    /// the bot themselves.
+    pub fn health(&self) -> Vec<ExchangeHealth> {
+        vec![self.binance.health(), self.bybit.health()]
+    }
+    
+    /// Local order books kept by the depth streams, when `order_books` is enabled.
+    pub fn order_books(&self) -> Option<Arc<OrderBookStore>> {
+        self.order_books.clone()
+    }
+    
+    /// Trading pairs and triangle legs the engine looks at.
+    fn scanned_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self.config.trading.trading_pairs.clone();
+        for (pair1, pair2, pair3) in self.engine.triangle_paths() {
+            symbols.extend([pair1, pair2, pair3]);
+        }
+        symbols.extend(self.engine.fiat_symbols());
+        for listing in self.engine.listing_paths() {
+            let (pair1, pair2, pair3) = listing.path;
+            symbols.extend([pair1, pair2, pair3]);
+        }
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+    
+    /// Symbols the depth streams maintain books for.
+    fn order_book_symbols(&self) -> Vec<String> {
+        if self.config.order_books.symbols.is_empty() {
+            self.scanned_symbols()
+        } else {
+            self.config.order_books.symbols.clone()
+        }
+    }
+    
+    /// Reconnection and sync state of each depth stream, Binance first;
+    /// empty when `order_books` is disabled.
+    fn depth_stream_health(&self) -> Vec<Arc<StreamHealth>> {
+        if self.order_books.is_none() {
+            return Vec::new();
+        }
+        let symbols = self.order_book_symbols();
+        ["Binance", "Bybit"].iter().map(|exchange| Arc::new(StreamHealth::new(exchange, &symbols))).collect()
+    }
+    
+    /// Market data alarms over `depth_streams` and the engine's prices,
+    /// when `slo` is enabled.
+    fn slo_monitor(&self, depth_streams: &[Arc<StreamHealth>]) -> Option<Arc<SloMonitor>> {
+        if !self.config.slo.enabled {
+            return None;
+        }
+        let symbols = if self.config.slo.symbols.is_empty() {
+            self.order_book_symbols()
+        } else {
+            self.config.slo.symbols.clone()
+        };
+        Some(Arc::new(SloMonitor::new(self.config.slo.clone(), symbols, depth_streams.to_vec(), self.engine.price_cache())))
+    }
+    
+    /// Starts the websocket depth streams feeding `order_books`, tracked in
+    /// `health`. They run until `shutdown`.
+    fn start_order_book_streams(&self, health: &[Arc<StreamHealth>]) {
+        let Some(store) = &self.order_books else { return };
+        let [binance, bybit] = health else { return };
+        
+        let symbols = self.order_book_symbols();
+        info!("Maintaining local order books for {} symbols", symbols.len());
+        
+        tokio::spawn(streams::maintain_binance_books(
+            self.binance.clone(),
+            symbols.clone(),
+            self.config.order_books.clone(),
+            store.clone(),
+            binance.clone(),
+            self.fixtures.clone(),
+            self.book_streams.clone(),
+        ));
+        tokio::spawn(streams::maintain_bybit_books(
+            symbols,
+            self.config.order_books.clone(),
+            store.clone(),
+            bybit.clone(),
+            self.fixtures.clone(),
+            self.book_streams.clone(),
+        ));
+    }
+    
+    /// Feeds the top-of-book notional of every fresh local book to the
+    /// depth risk scorer.
+    fn record_book_depth(&self) {
+        let Some(store) = &self.order_books else { return };
+        let max_age = Duration::from_millis(self.config.order_books.max_age_ms);
+        for (exchange, top) in store.fresh_tops(max_age) {
+            let depth_usd = (top.bid_price * top.bid_quantity).min(top.ask_price * top.ask_quantity);
+            self.engine.risk_inputs().record_depth(&exchange, &top.symbol, depth_usd);
+        }
     }
     
     pub async fn run(&self) -> Result<()> {
         info!("Starting triangular arbitrage bot...");
         
+        let depth_streams = self.depth_stream_health();
+        let slo = self.slo_monitor(&depth_streams);
+        if self.config.admin.enabled {
+            let addr = self.config.admin.bind_address.parse()
+                .map_err(|e| anyhow::anyhow!("Invalid admin bind address: {}", e))?;
+            let server = AdminServer::new(
+                addr,
+                self.health.clone(),
+                chrono::Duration::seconds(self.config.admin.max_scan_age_seconds),
+            )
+            .with_control(self.control.clone())
+            .with_breakers(self.engine.circuit_breakers())
+            .with_kill_switch(self.engine.kill_switch())
+            .with_outages(self.engine.outages())
+            .with_price_cache(self.engine.price_cache())
+            .with_market_metrics(self.metrics.clone())
+            .with_dns_caches(self.binance.dns_cache().into_iter().chain(self.bybit.dns_cache()).collect())
+            .with_request_budgets(vec![self.binance.request_budget(), self.bybit.request_budget()])
+            .with_error_counters(vec![self.binance.error_counters(), self.bybit.error_counters()])
+            .with_exchanges(vec![ExchangeClient::Binance(self.binance.clone()), ExchangeClient::Bybit(self.bybit.clone())])
+            .with_reports(self.reports.clone())
+            .with_depth_streams(depth_streams.clone())
+            .with_metrics(self.config.monitoring.enable_metrics);
+            let server = match &self.portfolio {
+                Some(portfolio) => server.with_portfolio(portfolio.clone()),
+                None => server,
+            };
+            let server = match &self.snapshots {
+                Some(snapshots) => server.with_snapshots(snapshots.clone()),
+                None => server,
+            };
+            let server = match &slo {
+                Some(monitor) => server.with_slo(monitor.clone()),
+                None => server,
+            };
+            let control_token = std::env::var(&self.config.admin.control_token_env).ok().filter(|token| !token.is_empty());
+            let server = match control_token {
+                Some(token) => server.with_control_token(SecretString::new(token)),
+                None if addr.ip().is_loopback() => server,
+                None => {
+                    warn!("{} is not set, the control API is refused on {}", self.config.admin.control_token_env, addr);
+                    server
+                }
+            };
+            let server = match &self.execution_queue {
+                Some(queue) => server.with_execution_queue(queue.clone()),
+                None => server,
+            };
+            tokio::spawn(async move {
+                if let Err(e) = server.serve().await {
+                    error!("Admin server stopped: {}", e);
+                }
+            });
+        }
+        
+        // Crash recovery below may already place orders
+        self.verify_key_permissions().await?;
+        if self.config.fees.fetch_account_rates {
+            self.load_account_fee_rates().await;
+        }
+        let recovered = self.engine.recover_executions().await?;
+        if recovered > 0 {
+            info!("Recovered {} interrupted executions", recovered);
+        }
+        if self.config.execution.reconciliation.enabled {
+            let expected = match &self.portfolio {
+                Some(portfolio) => portfolio.snapshots()?.pop(),
+                None => None,
+            };
+            self.engine.reconcile_accounts(expected.as_ref()).await?;
+        }
+        self.start_order_book_streams(&depth_streams);
+        self.start_market_feeds();
+        self.start_watchdog();
+        self.start_slo_monitor(slo);
+        self.start_keepalive();
+        self.start_retention();
+        self.start_execution_workers();
+        
         let mut consecutive_errors = 0;
-        let max_consecutive_errors = 10;
+        let max_consecutive_errors = self.config.risk.max_consecutive_errors;
         
         loop {
+            if self.control.is_scanning_paused() {
+                // Keep liveness green while deliberately idle
+                self.health.record_scan();
+                self.record_watchdog_scan(Duration::from_millis(self.config.scan.interval_ms));
+                tokio::time::sleep(Duration::from_millis(self.config.scan.interval_ms)).await;
+                continue;
+            }
+            
             match timeout(
-                Duration::from_secs(30), // 30 second timeout for each scan
-                self.scan_opportunities_with_retry()
+                SCAN_TIMEOUT,
+                self.scan_opportunities()
             ).await {
-                Ok(Ok(())) => {
+                Ok(Ok(opportunities_found)) => {
                     consecutive_errors = 0;
-                    tokio::time::sleep(Duration::from_millis(250)).await; // Reduced frequency for safety
+                    self.health.record_scan();
+                    let interval = match self.scheduler.lock() {
+                        Ok(mut scheduler) => scheduler.next_interval(opportunities_found),
+                        Err(_) => Duration::from_millis(self.config.scan.interval_ms),
+                    };
+                    self.market_store.set_interval(interval);
+                    self.record_watchdog_scan(interval);
+                    tokio::time::sleep(interval).await;
                 }
                 Ok(Err(e)) => {
                     consecutive_errors += 1;
-                    error!("Error scanning opportunities (attempt {}): {}", consecutive_errors, e);
+                    error!("Error scanning opportunities (attempt {}): {:#}", consecutive_errors, e);
+                    self.record_summary_error("scan error");
                     
                     if consecutive_errors >= max_consecutive_errors {
                         error!("Too many consecutive errors ({}), stopping bot", consecutive_errors);
+                        for count in [self.binance.error_counters(), self.bybit.error_counters()].iter().flat_map(|c| c.counts()) {
+                            error!("{} {} failed {} times with {} errors {}", count.exchange, count.endpoint, count.count,
+                                   count.kind.label(), count.code);
+                        }
                         return Err(anyhow::anyhow!("Bot stopped due to excessive errors"));
                     }
                     
                     // Exponential backoff on errors
                     let sleep_duration = Duration::from_secs(2_u64.pow(consecutive_errors.min(6)));
                     warn!("Sleeping for {:?} before retry", sleep_duration);
+                    self.record_watchdog_scan(sleep_duration);
                     tokio::time::sleep(sleep_duration).await;
                 }
                 Err(_) => {
-                    error!("Scan timed out after 30 seconds");
+                    error!("Scan timed out after {} seconds", SCAN_TIMEOUT.as_secs());
+                    self.record_summary_error("scan timeout");
                     consecutive_errors += 1;
                     tokio::time::sleep(Duration::from_secs(5)).await;
                 }
@@ -86,47 +665,754 @@ impl ArbitrageBot {
         }
     }
     
-    async fn scan_opportunities_with_retry(&self) -> Result<()> {
-        let backoff = ExponentialBackoff {
-            max_elapsed_time: Some(Duration::from_secs(60)),
-            max_interval: Duration::from_secs(10),
-            ..Default::default()
+    /// Checks each enabled exchange's API key against the configured mode.
+    /// Execution refuses to start with a key that cannot trade, or that can
+    /// withdraw unless `trading.allow_withdrawal_permission` is set.
+    async fn verify_key_permissions(&self) -> Result<()> {
+        let execution_enabled = keys::execution_enabled(&self.config);
+        let mut checks = Vec::new();
+        if self.config.exchanges.binance_enabled {
+            checks.push(keys::check_key(&ExchangeClient::Binance(self.binance.clone()), execution_enabled).await);
+        }
+        if self.config.exchanges.bybit_enabled {
+            checks.push(keys::check_key(&ExchangeClient::Bybit(self.bybit.clone()), execution_enabled).await);
+        }
+        keys::enforce_permissions(&checks, execution_enabled, self.config.trading.allow_withdrawal_permission)
+    }
+    
+    /// Replaces the configured fee rates with each account's tier rates.
+    /// A venue whose rates cannot be fetched keeps the configured ones.
+    async fn load_account_fee_rates(&self) {
+        let symbol = self.config.trading.trading_pairs.first().map(String::as_str).unwrap_or("BTCUSDT");
+        let fee_model = self.engine.fee_model();
+        for (enabled, client) in [
+            (self.config.exchanges.binance_enabled, ExchangeClient::Binance(self.binance.clone())),
+            (self.config.exchanges.bybit_enabled, ExchangeClient::Bybit(self.bybit.clone())),
+        ] {
+            if !enabled {
+                continue;
+            }
+            match client.get_fee_rates(symbol).await {
+                Ok(rates) => fee_model.apply_account_rates(client.name(), &rates),
+                Err(e) => warn!("Failed to load {} account fee rates, keeping configured ones: {:#}", client.name(), e),
+            }
+        }
+    }
+    
+    /// Starts one supervised market data task per exchange. From then on
+    /// scans take their prices from `market_store` instead of fetching them.
+    fn start_market_feeds(&self) {
+        let stall_timeout = Duration::from_secs(self.config.scan.feed_stall_timeout_seconds);
+        let outages = self.engine.outages();
+        
+        let (client, symbols, binance_outages) = (self.binance.clone(), self.symbol_table.clone(), outages.clone());
+        tokio::spawn(feeds::supervise_feed("Binance".to_string(), self.market_store.clone(), stall_timeout,
+                                           self.metrics.clone(), self.market_feeds.clone(), move || {
+            let (client, symbols, outages) = (client.clone(), symbols.clone(), binance_outages.clone());
+            async move {
+                feeds::fetch_market("Binance", &outages, client.get_price_snapshot(&symbols), client.get_book_tickers()).await
+            }
+        }));
+        let (client, symbols) = (self.bybit.clone(), self.symbol_table.clone());
+        tokio::spawn(feeds::supervise_feed("Bybit".to_string(), self.market_store.clone(), stall_timeout,
+                                           self.metrics.clone(), self.market_feeds.clone(), move || {
+            let (client, symbols, outages) = (client.clone(), symbols.clone(), outages.clone());
+            async move {
+                feeds::fetch_market("Bybit", &outages, client.get_price_snapshot(&symbols), client.get_book_tickers()).await
+            }
+        }));
+    }
+    
+    /// Pings each enabled exchange on its `keepalive_interval_seconds` so
+    /// its pooled connections are open when the next order goes out, and
+    /// probes its endpoints' latency when orders go to the fastest one.
+    fn start_keepalive(&self) {
+        for (enabled, network, client) in [
+            (self.config.exchanges.binance_enabled, &self.config.exchanges.binance_network, ExchangeClient::Binance(self.binance.clone())),
+            (self.config.exchanges.bybit_enabled, &self.config.exchanges.bybit_network, ExchangeClient::Bybit(self.bybit.clone())),
+        ] {
+            if !enabled {
+                continue;
+            }
+            if network.prefer_fastest_endpoint {
+                tokio::spawn(keepalive::probe_endpoints(client.clone(), Duration::from_secs(network.endpoint_probe_interval_seconds),
+                                                        self.keepalive.clone()));
+            }
+            if network.keepalive_interval_seconds == 0 {
+                continue;
+            }
+            tokio::spawn(keepalive::keep_warm(client, Duration::from_secs(network.keepalive_interval_seconds),
+                                              network.warm_connections, self.keepalive.clone()));
+        }
+    }
+    
+    fn start_execution_workers(&self) {
+        if let Some(queue) = &self.execution_queue {
+            queue::spawn_workers(queue.clone(), self.engine.clone(), self.config.execution.queue.workers,
+                                 self.execution_workers.clone());
+        }
+    }
+    
+    fn start_retention(&self) {
+        if let Some(store) = &self.retention_store {
+            tokio::spawn(retention::run(store.clone(), self.config.storage.retention.clone(),
+                                        self.config.monitoring.opportunity_history_days, self.retention.clone()));
+        }
+    }
+    
+    fn record_watchdog_scan(&self, pause: Duration) {
+        if let Some(watchdog) = &self.watchdog {
+            watchdog.record_scan(pause);
+        }
+    }
+    
+    /// Recreates the exchange clients, and with them their connection
+    /// pools, whenever the scan loop stops completing scans, and alerts
+    /// when that does not get scans going again.
+    fn start_watchdog(&self) {
+        let Some(watchdog) = self.watchdog.clone() else { return };
+        let (binance, bybit, metrics, alerts) = (self.binance.clone(), self.bybit.clone(), self.metrics.clone(), self.alerts.clone());
+        tokio::spawn(watchdog::watch(watchdog, self.watchdog_task.clone(), move |stall| {
+            let (binance, bybit, metrics, alerts) = (binance.clone(), bybit.clone(), metrics.clone(), alerts.clone());
+            async move {
+                for (exchange, reset) in [("Binance", binance.reset_connections()), ("Bybit", bybit.reset_connections())] {
+                    match reset {
+                        Ok(()) => metrics.record_client_reset(exchange),
+                        Err(e) => error!("Failed to recreate the {} client: {:#}", exchange, e),
+                    }
+                }
+                if stall.persists() {
+                    let text = format!("Scan loop stuck: no scan completed for {}s, recreating the exchange clients did not help",
+                                       stall.stuck_for.as_secs());
+                    match &alerts {
+                        Some(alerts) => alerts.notify_text(&text).await,
+                        None => error!("{}", text),
+                    }
+                }
+            }
+        }));
+    }
+    
+    /// Pauses execution while `monitor` finds market data violating its
+    /// SLOs, alerting on each breach and recovery.
+    fn start_slo_monitor(&self, monitor: Option<Arc<SloMonitor>>) {
+        let Some(monitor) = monitor else { return };
+        let (control, alerts) = (self.control.clone(), self.alerts.clone());
+        tokio::spawn(slo::watch(monitor, self.slo_task.clone(), move |transition| {
+            let (control, alerts) = (control.clone(), alerts.clone());
+            async move {
+                let text = match transition {
+                    SloTransition::Breached(violations) => {
+                        let reason = slo::summarize(&violations);
+                        control.set_slo_breach(Some(reason.clone()));
+                        format!("Execution paused, market data SLO violated: {}", reason)
+                    }
+                    SloTransition::Recovered => {
+                        control.set_slo_breach(None);
+                        "Execution resumed, market data SLOs met again".to_string()
+                    }
+                };
+                if let Some(alerts) = &alerts {
+                    alerts.notify_text(&text).await;
+                }
+            }
+        }));
+    }
+    
+    /// This round's market of each venue, `None` for a venue that is
+    /// excluded after an outage or whose feed has not delivered. Taken
+    /// from the feed tasks once they run, fetched directly otherwise.
+    async fn collect_markets(&self) -> [Option<Result<(PriceSnapshot, BookTickerMap)>>; 2] {
+        const EXCHANGES: [&str; 2] = ["Binance", "Bybit"];
+        let updates: Vec<Option<MarketUpdate>> = if EXCHANGES.iter().all(|e| self.market_store.has_feed(e)) {
+            let wait = feeds::REQUEST_TIMEOUT + self.market_store.interval();
+            let updates = self.market_store.take(&EXCHANGES, wait).await;
+            for (exchange, update) in EXCHANGES.iter().zip(&updates) {
+                if update.is_none() {
+                    warn!("No market data from the {} feed within {:?}, scanning without it", exchange, wait);
+                }
+            }
+            updates
+        } else {
+            let outages = self.engine.outages();
+            let (binance, bybit) = tokio::join!(
+                feeds::fetch_market("Binance", &outages, self.binance.get_price_snapshot(&self.symbol_table), self.binance.get_book_tickers()),
+                feeds::fetch_market("Bybit", &outages, self.bybit.get_price_snapshot(&self.symbol_table), self.bybit.get_book_tickers())
+            );
+            vec![Some(binance), Some(bybit)]
         };
         
-        retry(backoff, || async {
-            self.scan_opportunities().await.map_err(|e| {
-                match e.downcast_ref::<ExchangeError>() {
-                    Some(ExchangeError::NetworkError(_)) => backoff::Error::transient(e),
-                    Some(ExchangeError::RateLimitError(_)) => backoff::Error::transient(e),
-                    _ => backoff::Error::permanent(e),
+        let mut markets = [None, None];
+        for ((slot, exchange), update) in markets.iter_mut().zip(EXCHANGES).zip(updates) {
+            let Some(update) = update else { continue };
+            if update.market.is_some() {
+                self.engine.risk_inputs().record_latency(exchange, update.latency);
+            }
+            *slot = update.market;
+        }
+        markets
+    }
+    
+    /// Runs a single detection pass and emits the opportunities in the
+    /// configured output format. Never executes, whatever
+    /// `trading.enable_execution` says.
+    pub async fn scan_once(&self) -> Result<Vec<ArbitrageOpportunity>> {
+        self.maybe_refresh_symbol_info().await;
+        // Only detection is retried; executions below are never resubmitted
+        let policy = &self.config.exchanges.retry.market_data;
+        let opportunities = retry::retry(policy, "Scan", || self.detect_opportunities()).await?;
+        self.emit_opportunities(&opportunities)?;
+        Ok(opportunities)
+    }
+    
+    async fn scan_opportunities(&self) -> Result<usize> {
+        // Only detection is retried; executions below are never resubmitted
+        let policy = &self.config.exchanges.retry.market_data;
+        let opportunities = retry::retry(policy, "Scan", || self.detect_opportunities()).await?;
+        self.emit_opportunities(&opportunities)?;
+        self.metrics.record_opportunities(&opportunities);
+        if let Some(summaries) = &self.summaries {
+            self.deliver_summary(summaries.record_scan(&opportunities, chrono::Utc::now()));
+        }
+        if let Some(alerts) = &self.alerts {
+            alerts.notify(&opportunities).await;
+        }
+        if let Some(shadow) = &self.shadow {
+            shadow.shadow_all(&opportunities).await;
+        }
+        
+        let watch_only = self.config.alerts.enabled && self.config.alerts.watch_only;
+        if self.config.trading.enable_execution && !watch_only && !opportunities.is_empty() {
+            if let Some(reason) = self.control.execution_block_reason(&chrono::Utc::now()) {
+                info!("Skipping execution of {} opportunities: {}", opportunities.len(), reason);
+            } else if let Some(queue) = &self.execution_queue {
+                let queued = opportunities.iter().filter(|opportunity| queue.push((*opportunity).clone())).count();
+                debug!("Queued {} of {} opportunities for execution ({} waiting)", queued, opportunities.len(), queue.stats().queued);
+            } else {
+                for opportunity in &opportunities {
+                    self.engine.execute_arbitrage(opportunity).await?;
                 }
-            })
-        }).await
+            }
+        }
+        
+        Ok(opportunities.len())
     }
     
-    async fn scan_opportunities(&self) -> Result<()> {
-        // Parallel API calls for better performance
-        let (binance_result, bybit_result) = tokio::join!(
-            timeout(Duration::from_secs(10), self.binance.get_ticker_prices()),
-            timeout(Duration::from_secs(10), self.bybit.get_ticker_prices())
-        );
+    fn record_summary_error(&self, kind: &str) {
+        if let Some(summaries) = &self.summaries {
+            self.deliver_summary(summaries.record_error(kind, chrono::Utc::now()));
+        }
+    }
+    
+    /// Saves or mails a finished day's summary in the background, adding
+    /// that day's executions from the report store.
+    fn deliver_summary(&self, summary: Option<DailySummary>) {
+        let (Some(summary), Some(delivery)) = (summary, &self.summary_delivery) else { return };
+        let delivery = delivery.clone();
+        let reports = self.reports.clone();
+        tokio::spawn(async move {
+            let summary = match reports.reports() {
+                Ok(reports) => summary.with_reports(&reports),
+                Err(e) => {
+                    warn!("Failed to load execution reports for the daily summary: {}", e);
+                    summary
+                }
+            };
+            delivery.deliver(&summary).await;
+        });
+    }
+    
+    /// With JSON output, writes each opportunity as one JSON object per line
+    /// to stdout, so it can be piped into `jq` while logs go to stderr.
+    fn emit_opportunities(&self, opportunities: &[ArbitrageOpportunity]) -> Result<()> {
+        if self.output != OutputFormat::Json {
+            return Ok(());
+        }
         
-        let binance_prices = binance_result
-            .map_err(|_| anyhow::anyhow!("Binance API timeout"))?
-            .map_err(|e| anyhow::anyhow!("Binance API error: {}", e))?;
-            
-        let bybit_prices = bybit_result
-            .map_err(|_| anyhow::anyhow!("Bybit API timeout"))?
-            .map_err(|e| anyhow::anyhow!("Bybit API error: {}", e))?;
+        let stdout = std::io::stdout();
+        let mut out = stdout.lock();
+        for opportunity in opportunities {
+            serde_json::to_writer(&mut out, opportunity)?;
+            writeln!(out)?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+    
+    async fn detect_opportunities(&self) -> Result<Vec<ArbitrageOpportunity>> {
+        let scan_started = Instant::now();
+        let [binance, bybit] = self.collect_markets().await;
+        let fetch_elapsed = scan_started.elapsed();
+        
+        let breakers = self.engine.circuit_breakers();
+        let outage_detection = self.config.exchanges.outage.enabled;
+        let mut markets = [None, None];
+        let mut first_error = None;
+        for (slot, (exchange, market)) in markets.iter_mut().zip([("Binance", binance), ("Bybit", bybit)]) {
+            match market {
+                Some(Ok(market)) => {
+                    self.health.record_exchange_success(exchange);
+                    breakers.record_exchange_success(exchange);
+                    *slot = Some(market);
+                }
+                Some(Err(e)) => {
+                    self.health.record_exchange_failure(exchange, &format!("{:#}", e));
+                    breakers.record_exchange_failure(exchange, &format!("{:#}", e));
+                    self.record_summary_error(&format!("{} API", exchange));
+                    if !outage_detection {
+                        return Err(e);
+                    }
+                    // Only a scan every venue fails counts as a failed scan
+                    warn!("Scanning without {} this round: {:#}", exchange, e);
+                    first_error.get_or_insert(e);
+                }
+                None => {}
+            }
+        }
+        if markets.iter().all(Option::is_none) {
+            return Err(first_error.unwrap_or_else(|| anyhow::anyhow!("No exchange delivered market data: excluded after outages or feeds not publishing")));
+        }
+        let included = markets.each_ref().map(Option::is_some);
+        // An excluded venue takes part with no prices, so it yields no opportunities
+        let [(mut binance_prices, binance_quotes), (mut bybit_prices, bybit_quotes)] = markets
+            .map(|market| market.unwrap_or_else(|| (PriceSnapshot::new(self.symbol_table.clone()), BookTickerMap::new())));
+        
+        self.maybe_refresh_symbol_info().await;
+        self.symbols.filter_snapshot("Binance", &mut binance_prices);
+        self.symbols.filter_snapshot("Bybit", &mut bybit_prices);
         
-        if binance_prices.is_empty() || bybit_prices.is_empty() {
+        if [&binance_prices, &bybit_prices].into_iter().zip(included).any(|(prices, included)| included && prices.is_empty()) {
             return Err(anyhow::anyhow!("Received empty price data from exchanges"));
         }
         
         info!("Received prices: Binance={}, Bybit={}", binance_prices.len(), bybit_prices.len());
         
-        self.engine.analyze_opportunities(&binance_prices, &bybit_prices).await?;
+        if included.iter().all(|included| *included) {
+            // Balances on an excluded venue could not be valued
+            self.maybe_snapshot_portfolio(&binance_prices, &bybit_prices).await;
+        }
         
-        Ok(())
+        self.record_book_depth();
+        self.maybe_sample_trade_volumes().await;
+        self.maybe_refresh_correlations().await;
+        self.warm_up_symbols().await;
+        self.maybe_refresh_inventory().await;
+        let analyze_started = Instant::now();
+        let opportunities = self.engine
+            .analyze_opportunities_with_quotes(&binance_prices, &bybit_prices, &binance_quotes, &bybit_quotes)
+            .await?;
+        let timing = ScanTiming {
+            fetch_ms: fetch_elapsed.as_secs_f64() * 1000.0,
+            analyze_ms: analyze_started.elapsed().as_secs_f64() * 1000.0,
+            total_ms: scan_started.elapsed().as_secs_f64() * 1000.0,
+        };
+        debug!("Scan took {:.1}ms (fetch {:.1}ms, analyze {:.1}ms)", timing.total_ms, timing.fetch_ms, timing.analyze_ms);
+        self.health.record_scan_timing(timing);
+        self.health.set_circuit_breaker_open(self.engine.is_circuit_open());
+        self.maybe_record_market_snapshot(&binance_prices, &bybit_prices, &opportunities);
+        
+        Ok(opportunities)
+    }
+    
+    /// Reloads the balances cross-exchange opportunities are sized to when
+    /// the inventory refresh interval has passed. A venue whose request
+    /// fails keeps its previous balances.
+    async fn maybe_refresh_inventory(&self) {
+        let Some(inventory) = self.engine.inventory() else { return };
+        let now = chrono::Utc::now();
+        if !inventory.is_due(now) {
+            return;
+        }
+        
+        let (binance, bybit) = tokio::join!(self.binance.get_balances(), self.bybit.get_balances());
+        let rebalance = &self.config.inventory.rebalance;
+        let venues = [
+            (ExchangeClient::Binance(self.binance.clone()), binance, &rebalance.binance),
+            (ExchangeClient::Bybit(self.bybit.clone()), bybit, &rebalance.bybit),
+        ];
+        for (client, balances, accounts) in venues {
+            match balances {
+                Ok(balances) => inventory.update(client.name(), &balances, now),
+                Err(e) => {
+                    warn!("Failed to refresh {} inventory: {:#}", client.name(), e);
+                    continue;
+                }
+            }
+            if rebalance.enabled {
+                self.refill_inventory(&inventory, &client, accounts).await;
+            }
+        }
     }
-}
\ No newline at end of file
+    
+    /// Tops up every asset the trading account on `client`'s venue holds
+    /// too little of from the funding account, by sub-account transfer
+    /// instead of an on-chain withdrawal. A failed transfer is retried on
+    /// the next inventory refresh.
+    async fn refill_inventory(&self, inventory: &InventoryTracker, client: &ExchangeClient, accounts: &RebalanceAccounts) {
+        for (asset, amount) in inventory.refills(client.name()) {
+            let transfer = SubAccountTransfer::new(&asset, amount, accounts.funding_account.clone(), accounts.trading_account.clone());
+            match client.transfer_between_sub_accounts(&transfer).await {
+                Ok(receipt) => {
+                    info!("Moved {} {} to the {} trading account (transfer {}, status {})", amount.normalize(), asset,
+                          client.name(), receipt.transfer_id, receipt.status.as_deref().unwrap_or("unknown"));
+                    inventory.record_transfer(client.name(), &asset, amount);
+                }
+                Err(e) => warn!("Failed to move {} {} to the {} trading account: {:#}", amount.normalize(), asset, client.name(), e),
+            }
+        }
+    }
+    
+    /// Reloads the exchanges' symbol metadata (trading status and tick
+    /// sizes) when the refresh interval has passed. A failed refresh keeps
+    /// the previous data.
+    async fn maybe_refresh_symbol_info(&self) {
+        let interval = Duration::from_secs(self.config.symbols.refresh_interval_minutes * 60);
+        if !take_due(&self.last_symbol_refresh, interval) {
+            return;
+        }
+        
+        let (binance_symbols, bybit_symbols) = tokio::join!(
+            self.binance.get_symbol_info(),
+            self.bybit.get_symbol_info()
+        );
+        for (exchange, symbols) in [("Binance", binance_symbols), ("Bybit", bybit_symbols)] {
+            match symbols {
+                Ok(symbols) => {
+                    let tradable: HashSet<String> = symbols.iter()
+                        .filter(|s| s.tradable)
+                        .map(|s| s.symbol.clone())
+                        .collect();
+                    info!("{} reports {} tradable spot symbols", exchange, tradable.len());
+                    let changes = self.symbols.set_tradable(exchange, tradable);
+                    if !changes.is_empty() {
+                        info!("{} listed {:?} and delisted {:?}", exchange, changes.listed, changes.delisted);
+                    }
+                    if self.config.listings.enabled {
+                        self.register_listings(exchange, &changes.listed);
+                    }
+                    self.precision.set_tick_sizes(exchange, symbols.iter()
+                        .filter_map(|s| Some((s.symbol.clone(), s.tick_size?)))
+                        .collect());
+                    self.precision.set_step_sizes(exchange, symbols.into_iter()
+                        .filter_map(|s| Some((s.symbol, s.step_size?)))
+                        .collect());
+                    if let Ok(mut fetched_at) = self.tradable_fetched_at.lock() {
+                        *fetched_at = Some(chrono::Utc::now());
+                    }
+                    self.check_configured_markets(exchange).await;
+                }
+                Err(e) => warn!("Failed to refresh {} symbol info: {:#}", exchange, e),
+            }
+        }
+    }
+    
+    /// Adds the triangles each symbol newly listed on `exchange` completes
+    /// with markets the filter allows.
+    fn register_listings(&self, exchange: &str, listed: &[String]) {
+        let now = chrono::Utc::now();
+        for symbol in listed {
+            let paths = listing_paths(symbol, |leg| self.symbols.is_allowed(exchange, leg));
+            let added = self.engine.register_listing_paths(exchange, paths, now);
+            if !added.is_empty() {
+                let added: Vec<String> = added.iter().map(|(a, b, c)| format!("{}/{}/{}", a, b, c)).collect();
+                info!("New listing {} on {} completes triangles {}", symbol, exchange, added.join(", "));
+            }
+        }
+    }
+    
+    /// Compares the configured pairs and triangles with what `exchange`
+    /// lists, alerting on those that just lost a leg. Triangles with a
+    /// delisted leg drop out of detection with its price and come back
+    /// once it is listed again.
+    async fn check_configured_markets(&self, exchange: &str) {
+        let triangles = self.engine.triangle_paths();
+        let unavailable = self.symbols.unavailable_markets(exchange, &self.config.trading.trading_pairs, &triangles);
+        let broken_triangles = unavailable.iter().filter(|market| market.contains('/')).count();
+        info!("{}: {} of {} triangles have every leg listed", exchange, triangles.len() - broken_triangles, triangles.len());
+        
+        let (lost, restored) = self.symbols.track_unavailable(exchange, unavailable);
+        if !restored.is_empty() {
+            info!("Listed again on {}: {}", exchange, restored.join(", "));
+        }
+        if lost.is_empty() {
+            return;
+        }
+        let text = format!("Configured markets unavailable on {}: {}", exchange, lost.join(", "));
+        match &self.alerts {
+            Some(alerts) => alerts.notify_text(&text).await,
+            None => warn!("{}", text),
+        }
+    }
+    
+    /// Samples recent trades of every scanned symbol on both exchanges for
+    /// the minimum-volume check and the volume participation limit, once per
+    /// `liquidity.refresh_interval_seconds`. A failed sample leaves that
+    /// symbol's earlier estimate in place.
+    async fn maybe_sample_trade_volumes(&self) {
+        let zero = rust_decimal::Decimal::ZERO;
+        if self.config.liquidity.min_hourly_volume_usd <= zero && self.config.risk.participation.max_volume_percentage <= zero {
+            return;
+        }
+        
+        let interval = Duration::from_secs(self.config.liquidity.refresh_interval_seconds);
+        if !take_due(&self.last_trade_sample, interval) {
+            return;
+        }
+        
+        let symbols = self.scanned_symbols();
+        let samples = futures_util::future::join_all(symbols.iter().flat_map(|symbol| [
+            ExchangeClient::Binance(self.binance.clone()),
+            ExchangeClient::Bybit(self.bybit.clone()),
+        ].map(|client| async move {
+            let trades = client.get_recent_trades(symbol, 1000).await;
+            (client.name(), symbol, trades)
+        }))).await;
+        
+        let now = chrono::Utc::now();
+        for (exchange, symbol, trades) in samples {
+            match trades {
+                Ok(trades) => self.engine.trade_volumes().record_trades(exchange, symbol, &trades, now),
+                Err(e) => debug!("Failed to sample {} trades on {}: {:#}", symbol, exchange, e),
+            }
+        }
+    }
+    
+    /// Recomputes the volatility and correlation matrix the risk model scores
+    /// triangles with, once per `risk.scoring.correlation.refresh_interval_minutes`.
+    /// A failed refresh keeps the previous matrix.
+    async fn maybe_refresh_correlations(&self) {
+        let Some(service) = &self.correlations else { return };
+        
+        let interval = Duration::from_secs(service.config().refresh_interval_minutes * 60);
+        if !take_due(&self.last_correlation_refresh, interval) {
+            return;
+        }
+        
+        match service.refresh().await {
+            Ok(matrix) if matrix.samples == 0 => {
+                warn!("Not enough shared candles to compute the correlation matrix of {}", service.config().assets.join(", "));
+            }
+            Ok(matrix) => {
+                info!("Correlation matrix refreshed from {} returns of {}", matrix.samples, service.config().assets.join(", "));
+                self.engine.risk_inputs().set_correlation_matrix(matrix);
+            }
+            Err(e) => warn!("Failed to refresh the correlation matrix: {:#}", e),
+        }
+    }
+    
+    /// Fetches `warmup.candles` one-minute candles for every scanned symbol
+    /// still warming up on a venue, once per symbol. Symbols whose fetch
+    /// fails warm up from scans alone.
+    async fn warm_up_symbols(&self) {
+        let warmup = self.engine.warmup();
+        if !warmup.config().enabled || warmup.config().candles == 0 {
+            return;
+        }
+        let inputs = self.engine.risk_inputs();
+        let symbols = self.scanned_symbols();
+        let cold: Vec<(ExchangeClient, &String)> = symbols.iter()
+            .flat_map(|symbol| [
+                ExchangeClient::Binance(self.binance.clone()),
+                ExchangeClient::Bybit(self.bybit.clone()),
+            ].map(|client| (client, symbol)))
+            .filter(|(client, symbol)| warmup.needs_candles(client.name(), symbol, inputs))
+            .collect();
+        if cold.is_empty() {
+            return;
+        }
+        
+        let end = chrono::Utc::now();
+        let range = end - chrono::Duration::minutes(warmup.config().candles as i64)..end;
+        let fetched = futures_util::future::join_all(cold.into_iter().map(|(client, symbol)| {
+            let range = range.clone();
+            async move {
+                let klines = client.get_klines(symbol, KlineInterval::OneMinute, range).await;
+                (client.name(), symbol, klines)
+            }
+        })).await;
+        for (exchange, symbol, klines) in fetched {
+            match klines {
+                Ok(klines) => warmup.seed(exchange, symbol, &klines),
+                Err(e) => {
+                    debug!("Failed to fetch warmup candles of {} on {}: {:#}", symbol, exchange, e);
+                    warmup.seed(exchange, symbol, &[]);
+                }
+            }
+        }
+    }
+    
+    /// Stores the scan's prices and findings for later replay when the
+    /// snapshot interval has elapsed.
+    fn maybe_record_market_snapshot(
+        &self,
+        binance_prices: &PriceSnapshot,
+        bybit_prices: &PriceSnapshot,
+        opportunities: &[ArbitrageOpportunity],
+    ) {
+        let Some(snapshots) = &self.snapshots else { return };
+        
+        let interval = Duration::from_secs(self.config.storage.snapshot_interval_seconds);
+        if !take_due(&self.last_market_snapshot, interval) {
+            return;
+        }
+        
+        let snapshot = MarketSnapshot {
+            captured_at: chrono::Utc::now(),
+            binance: binance_prices.to_price_map(),
+            bybit: bybit_prices.to_price_map(),
+            opportunities: opportunities.iter().map(OpportunitySummary::from).collect(),
+        };
+        if let Err(e) = snapshots.record(&snapshot) {
+            warn!("Failed to record market snapshot: {:#}", e);
+        }
+    }
+    
+    /// Records a mark-to-market portfolio snapshot when the configured
+    /// interval has elapsed. Failures are logged and never abort the scan.
+    async fn maybe_snapshot_portfolio(&self, binance_prices: &PriceSnapshot, bybit_prices: &PriceSnapshot) {
+        let Some(portfolio) = &self.portfolio else { return };
+        
+        let interval = Duration::from_secs(self.config.portfolio.snapshot_interval_minutes * 60);
+        if !take_due(&self.last_portfolio_snapshot, interval) {
+            return;
+        }
+        
+        let (binance_balances, bybit_balances) = tokio::join!(
+            self.binance.get_balances(),
+            self.bybit.get_balances()
+        );
+        let (binance_balances, bybit_balances) = match (binance_balances, bybit_balances) {
+            (Ok(binance), Ok(bybit)) => (binance, bybit),
+            (Err(e), _) | (_, Err(e)) => {
+                warn!("Skipping portfolio snapshot, balance request failed: {:#}", e);
+                return;
+            }
+        };
+        
+        let snapshot = PortfolioSnapshot::mark_to_market(&[
+            ("Binance", &binance_balances, &binance_prices.to_price_map()),
+            ("Bybit", &bybit_balances, &bybit_prices.to_price_map()),
+        ]);
+        if !snapshot.unpriced_assets.is_empty() {
+            warn!("No USD price for {:?}, valued at zero", snapshot.unpriced_assets);
+        }
+        info!("Portfolio value: {} USD across {} holdings", snapshot.total_usd.round_dp(2), snapshot.holdings.len());
+        
+        if let Err(e) = portfolio.record(&snapshot) {
+            warn!("Failed to persist portfolio snapshot: {}", e);
+        }
+    }
+}
+
+/// Whether `interval` has passed since the time in `slot`, restarting it
+/// when it has. An empty slot is always due; a poisoned one never is.
+fn take_due(slot: &Mutex<Option<Instant>>, interval: Duration) -> bool {
+    let Ok(mut last) = slot.lock() else { return false };
+    let due = last.map(|t| t.elapsed() >= interval).unwrap_or(true);
+    if due {
+        *last = Some(Instant::now());
+    }
+    due
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+    
+    fn json(body: &str) -> ResponseTemplate {
+        ResponseTemplate::new(200).set_body_raw(body.as_bytes().to_vec(), "application/json")
+    }
+    
+    /// Forwards connections to `server`. While the returned flag is set,
+    /// the next request is dropped unanswered along with its connection,
+    /// so it fails at the transport level.
+    async fn flaky_proxy(server: &MockServer) -> (String, Arc<AtomicBool>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let target = *server.address();
+        let fail_next = Arc::new(AtomicBool::new(false));
+        let fail = fail_next.clone();
+        tokio::spawn(async move {
+            while let Ok((inbound, _)) = listener.accept().await {
+                let fail = fail.clone();
+                tokio::spawn(async move {
+                    let outbound = tokio::net::TcpStream::connect(target).await.unwrap();
+                    let (mut in_read, mut in_write) = inbound.into_split();
+                    let (mut out_read, mut out_write) = outbound.into_split();
+                    let responses = tokio::spawn(async move {
+                        let _ = tokio::io::copy(&mut out_read, &mut in_write).await;
+                    });
+                    let mut buf = vec![0; 8192];
+                    while let Ok(n @ 1..) = in_read.read(&mut buf).await {
+                        if fail.swap(false, Ordering::SeqCst) || out_write.write_all(&buf[..n]).await.is_err() {
+                            break;
+                        }
+                    }
+                    responses.abort();
+                });
+            }
+        });
+        (url, fail_next)
+    }
+    
+    #[tokio::test]
+    async fn test_scan_loop_retries_network_errors_during_detection() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/ticker/price"))
+            .respond_with(json(r#"[{"symbol":"BTCUSDT","price":"50000"},{"symbol":"ETHBTC","price":"0.06"},{"symbol":"ETHUSDT","price":"3000"}]"#))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/ticker/bookTicker"))
+            .respond_with(json("[]"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/v5/market/tickers"))
+            .respond_with(json(r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"symbol":"BTCUSDT","lastPrice":"50000"}]}}"#))
+            .mount(&server)
+            .await;
+        
+        // Market data needs no real keys, but the clients insist on some
+        for name in ["BINANCE_API_KEY", "BINANCE_SECRET_KEY", "BYBIT_API_KEY", "BYBIT_SECRET_KEY"] {
+            std::env::set_var(name, "test");
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = Config::default();
+        config.storage.data_dir = dir.path().to_string_lossy().into_owned();
+        config.exchanges.outage.enabled = false;
+        config.exchanges.retry.market_data.initial_backoff_ms = 1;
+        config.exchanges.binance_network.use_env_proxy = false;
+        let (binance_url, fail_next) = flaky_proxy(&server).await;
+        config.exchanges.binance_network.endpoints = vec![binance_url];
+        config.exchanges.bybit_network.use_env_proxy = false;
+        config.exchanges.bybit_network.endpoints = vec![server.uri()];
+        let bot = ArbitrageBot::with_config(config).await.unwrap();
+        let binance_market_data = || async {
+            server.received_requests().await.unwrap().iter()
+                .filter(|r| matches!(r.url.path(), "/api/v3/ticker/price" | "/api/v3/ticker/bookTicker"))
+                .count()
+        };
+        let before = binance_market_data().await;
+        
+        fail_next.store(true, Ordering::SeqCst);
+        bot.scan_opportunities().await.unwrap();
+        
+        // One request of the first attempt was dropped, the retry sent both
+        assert!(!fail_next.load(Ordering::SeqCst));
+        assert_eq!(binance_market_data().await - before, 3);
+    }
+    
+    #[test]
+    fn test_take_due_restarts_the_interval() {
+        let slot = Mutex::new(None);
+        assert!(take_due(&slot, Duration::from_secs(60)));
+        assert!(!take_due(&slot, Duration::from_secs(60)));
+        assert!(take_due(&slot, Duration::ZERO));
+    }
+}
@@ -1,11 +1,311 @@
+use triangular_arbitrage::backtest::Backtester;
+use triangular_arbitrage::cli::{Command, OutputFormat};
+use triangular_arbitrage::config::Config;
+use triangular_arbitrage::currency::CurrencyConverter;
+use triangular_arbitrage::diag;
+use triangular_arbitrage::fee_tiers;
+use triangular_arbitrage::heatmap;
+use triangular_arbitrage::history;
+use triangular_arbitrage::keys;
+use triangular_arbitrage::metrics;
+use triangular_arbitrage::migration;
+use triangular_arbitrage::performance::{self, Outcome};
+use triangular_arbitrage::precision::format_percentage;
+use triangular_arbitrage::replay::{self, SnapshotStore};
+use triangular_arbitrage::shadow::ShadowStore;
+use triangular_arbitrage::report::ReportStore;
+use triangular_arbitrage::storage::LocalStore;
+use triangular_arbitrage::stress::{self, ExecutionDistributions, OpportunityProfile};
+use triangular_arbitrage::warm_start::WarmStartStore;
 use triangular_arbitrage::ArbitrageBot;
 
+/// Home currency converter for reports run while the bot is stopped, at
+/// the FX rate of the price cache saved on its last shutdown.
+fn report_currency(config: &Config, store: &LocalStore) -> CurrencyConverter {
+    let currency = CurrencyConverter::new(&config.monitoring.home_currency);
+    match WarmStartStore::new(store.clone()).load() {
+        Ok(Some(state)) => currency.observe_cached(&state.prices),
+        Ok(None) => {}
+        Err(e) => log::warn!("Failed to load the saved price cache for {} conversion: {:#}", currency.home(), e),
+    }
+    currency
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::init();
     
-    let bot = ArbitrageBot::new().await?;
-    bot.run().await?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let config = Config::load_from_file("config.json")?;
+    
+    match Command::parse(&args)? {
+        Command::Run { output } => {
+            let bot = ArbitrageBot::with_config(config).await?.with_output(output);
+            tokio::select! {
+                result = bot.run() => result?,
+                _ = tokio::signal::ctrl_c() => log::info!("Interrupted, shutting down"),
+            }
+            bot.shutdown()?;
+        }
+        Command::Scan { output } => {
+            let bot = ArbitrageBot::with_config(config).await?.with_output(output);
+            let opportunities = bot.scan_once().await?;
+            if output == OutputFormat::Text {
+                println!("Found {} opportunities", opportunities.len());
+                let currency = bot.currency();
+                for opportunity in &opportunities {
+                    println!("  {}", opportunity.describe(&currency));
+                }
+            }
+        }
+        Command::DownloadKlines { exchange, symbol, interval, days } => {
+            let store = LocalStore::open(&config.storage)?;
+            let end = chrono::Utc::now();
+            let start = end - chrono::Duration::days(days);
+            let count = history::backfill_klines(&store, &exchange, &symbol, interval, start..end).await?;
+            println!("Downloaded {} candles into {}", count, store.location(std::path::Path::new("klines")));
+        }
+        Command::Replay { date } => {
+            let store = SnapshotStore::new(LocalStore::open(&config.storage)?);
+            let report = replay::replay_day(&config, &store, date).await?;
+            if report.snapshots == 0 {
+                println!("No market snapshots recorded on {} (enable storage.snapshot_interval_seconds)", date);
+                return Ok(());
+            }
+            
+            println!("Replayed {} snapshots from {}", report.snapshots, date);
+            println!("{} of {} historical opportunities still pass", report.still_passing.len(), report.historical);
+            for (label, list) in [("pass", &report.still_passing), ("rejected", &report.rejected), ("new", &report.new)] {
+                for opportunity in list {
+                    println!("  {:<8} {:<14} {:<40} net {:>8}%  risk {:.2}",
+                             label, opportunity.exchange, opportunity.path.join(" > "),
+                             format_percentage(opportunity.net_profit_percentage), opportunity.risk_score);
+                }
+            }
+        }
+        Command::Optimize { from, to } => {
+            let store = LocalStore::open(&config.storage)?;
+            let snapshots = SnapshotStore::new(store.clone()).load_range(from, to)?;
+            if snapshots.is_empty() {
+                println!("No market snapshots recorded from {} to {} (enable storage.snapshot_interval_seconds)", from, to);
+                return Ok(());
+            }
+            let currency = report_currency(&config, &store);
+            let slippage: Vec<_> = ReportStore::new(store).reports()?
+                .iter()
+                .filter(|r| r.completed)
+                .map(|r| r.cycle_slippage_percentage())
+                .collect();
+            
+            let results = Backtester::new(&snapshots, slippage.clone()).sweep(&config).await?;
+            println!("Backtested {} parameter sets over {} snapshots ({} slippage samples)",
+                     results.len(), snapshots.len(), slippage.len());
+            println!("{:>4}  {:>10}  {:>10}  {:>9}  {:>7}  {:>9}  {:>12}  {:>12}",
+                     "rank", "min_profit", "position", "max_slip", "trades", "abandoned", "net_pnl", "drawdown");
+            for (rank, result) in results.iter().enumerate() {
+                println!("{:>4}  {:>10}  {:>10}  {:>9}  {:>7}  {:>9}  {:>12}  {:>12}",
+                         rank + 1, result.params.min_profit_threshold, result.params.max_position_size,
+                         result.params.max_slippage_percentage, result.trades, result.abandoned,
+                         currency.format(result.net_pnl_usd), currency.format(result.max_drawdown_usd));
+            }
+        }
+        Command::StressTest { legs, position_usd, net_profit_percentage } => {
+            let store = LocalStore::open(&config.storage)?;
+            let currency = report_currency(&config, &store);
+            let reports = ReportStore::new(store).reports()?;
+            let distributions = ExecutionDistributions::from_reports(&reports);
+            let profile = OpportunityProfile { legs, position_usd, net_profit_percentage };
+            let report = stress::simulate(profile, &distributions, &config.stress_test);
+            let confidence = config.stress_test.confidence * 100.0;
+            
+            println!("Simulated {} runs from {} executions ({} slippage samples, leg failure rate {:.2}%)",
+                     report.runs, reports.len(), distributions.leg_slippage_bps.len(),
+                     distributions.leg_failure_rate * 100.0);
+            println!("  mean PnL             {:>10}", currency.format_f64(report.mean_pnl_usd));
+            println!("  probability of loss  {:>10.2}%", report.probability_of_loss * 100.0);
+            println!("  failed runs          {:>10}", report.failed_runs);
+            println!("  VaR {:.0}%              {:>10}", confidence, currency.format_f64(report.value_at_risk_usd));
+            println!("  expected shortfall   {:>10}", currency.format_f64(report.expected_shortfall_usd));
+            println!("  p{:.0} cycle slippage   {:>10.4}% (risk.max_cycle_slippage_percentage is {}%)",
+                     confidence, report.cycle_slippage_quantile_percentage, config.risk.max_cycle_slippage_percentage);
+        }
+        Command::DashboardExport { output } => {
+            let dashboard = serde_json::to_string_pretty(&metrics::grafana_dashboard())?;
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, dashboard)?;
+                    println!("Wrote Grafana dashboard to {}", path);
+                }
+                None => println!("{}", dashboard),
+            }
+        }
+        Command::CheckKeys => {
+            let checks = keys::check_keys(&config).await;
+            let yes_no = |flag: bool| if flag { "yes" } else { "no" };
+            for check in &checks {
+                match (&check.permissions, &check.error) {
+                    (Some(p), _) => println!("{:<8} read {:<3}  trade {:<3}  withdraw {:<3}  IP restricted {}",
+                                             check.exchange, yes_no(p.read), yes_no(p.trade), yes_no(p.withdraw), yes_no(p.ip_restricted)),
+                    (None, error) => println!("{:<8} invalid: {}", check.exchange, error.as_deref().unwrap_or("unknown error")),
+                }
+                for warning in &check.warnings {
+                    println!("         warning: {}", warning);
+                }
+            }
+            let failed = checks.iter().filter(|c| c.error.is_some()).count();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{} of {} API keys failed validation", failed, checks.len()));
+            }
+        }
+        Command::PerformanceReport { weeks, retire_after } => {
+            let store = LocalStore::open(&config.storage)?;
+            let currency = report_currency(&config, &store);
+            let mut outcomes: Vec<Outcome> = ShadowStore::new(store.clone()).records()?.iter().map(Outcome::from_shadow).collect();
+            outcomes.extend(ReportStore::new(store).reports()?.iter().map(Outcome::from_execution));
+            let report = performance::weekly_report(&outcomes, chrono::Utc::now(), weeks, retire_after);
+            if report.paths.is_empty() {
+                println!("No shadow checks or executions in the last {} weeks (enable execution.shadow)", weeks);
+                return Ok(());
+            }
+            
+            println!("Weekly PnL in {}, weeks starting {}", currency.unit(), report.weeks.iter().map(|w| w.to_string()).collect::<Vec<_>>().join(", "));
+            for list in [&report.strategies, &report.paths] {
+                println!("{:<7} {:<15} {:<32} {:>9} {:>12} {:>12} {:>12}",
+                         "source", "strategy", "path", "filled", "last week", "change", "trend/week");
+                for row in list {
+                    let last = row.weeks.last().cloned().unwrap_or_default();
+                    println!("{:<7} {:<15} {:<32} {:>9} {:>12} {:>12} {:>12}{}",
+                             row.source.label(), metrics::strategy_label(row.strategy), row.path.as_deref().unwrap_or("all"),
+                             format!("{}/{}", last.succeeded, last.attempts), currency.format(last.pnl_usd),
+                             currency.format(row.change_usd), currency.format(row.trend_usd_per_week),
+                             if row.retire { "  retire" } else { "" });
+                }
+                println!();
+            }
+            let retire = report.paths.iter().filter(|p| p.retire).count();
+            if retire > 0 {
+                println!("{} paths produced nothing over the last {} weeks; consider excluding their symbols", retire, retire_after);
+            }
+        }
+        Command::Heatmap { days } => {
+            let store = SnapshotStore::new(LocalStore::open(&config.storage)?);
+            let heatmap = heatmap::load(&store, days, chrono::Utc::now())?;
+            if heatmap.snapshots.iter().all(|n| *n == 0) {
+                println!("No market snapshots recorded in the last {} days (enable storage.snapshot_interval_seconds)", days);
+                return Ok(());
+            }
+            
+            println!("Average net profit % by UTC hour from {} to {} ({} snapshots)",
+                     heatmap.from, heatmap.to, heatmap.snapshots.iter().sum::<usize>());
+            println!("{:<14} {:<32} {}", "exchange", "path", (0..24).map(|h| format!("{:>5}", h)).collect::<String>());
+            let cells = |hours: &heatmap::HourlyProfit| hours.average_net_profit_percentage.iter()
+                .map(|average| match average {
+                    Some(average) => format!("{:>5}", format!("{:.2}", average)),
+                    None => format!("{:>5}", "-"),
+                })
+                .collect::<String>();
+            println!("{:<14} {:<32} {}", "all", "", cells(&heatmap.all));
+            for row in &heatmap.paths {
+                println!("{:<14} {:<32} {}", row.exchange, row.path.join(" > "), cells(&row.hours));
+            }
+            let best: Vec<String> = heatmap.best_hours().iter().take(3).map(|h| format!("{:02}:00", h)).collect();
+            if !best.is_empty() {
+                println!("Most profitable hours (UTC): {}", best.join(", "));
+            }
+        }
+        Command::FeeTiers { days } => {
+            if config.fees.binance.tiers.is_empty() && config.fees.bybit.tiers.is_empty() {
+                println!("No fee tiers configured (set fees.binance.tiers or fees.bybit.tiers)");
+                return Ok(());
+            }
+            let store = LocalStore::open(&config.storage)?;
+            let now = chrono::Utc::now();
+            let since = now - chrono::Duration::days(days as i64);
+            let snapshots: Vec<_> = SnapshotStore::new(store.clone()).load_range(since.date_naive(), now.date_naive())?
+                .into_iter()
+                .filter(|s| s.captured_at >= since)
+                .collect();
+            if snapshots.is_empty() {
+                println!("No market snapshots recorded in the last {} days (enable storage.snapshot_interval_seconds)", days);
+                return Ok(());
+            }
+            let currency = report_currency(&config, &store);
+            let slippage: Vec<_> = ReportStore::new(store).reports()?
+                .iter()
+                .filter(|r| r.completed)
+                .map(|r| r.cycle_slippage_percentage())
+                .collect();
+            
+            let reports = fee_tiers::simulate(&config, &snapshots, slippage).await?;
+            println!("Monthly estimates from {} snapshots over the last {} days", snapshots.len(), days);
+            for report in &reports {
+                println!();
+                println!("{:<8} {:<10} {:>8} {:>16} {:>10} {:>16} {:>14}",
+                         report.exchange, "tier", "taker %", "required", "trades", "volume", "pnl");
+                for estimate in &report.tiers {
+                    println!("{:<8} {:<10} {:>8} {:>16} {:>10.0} {:>16} {:>14}{}",
+                             "", estimate.tier.name, format_percentage(estimate.tier.taker_fee * rust_decimal::Decimal::ONE_HUNDRED),
+                             currency.format(estimate.tier.min_monthly_volume_usd), estimate.trades_per_month,
+                             currency.format(estimate.monthly_volume_usd), currency.format(estimate.monthly_pnl_usd),
+                             if estimate.qualifies() { "  reached" } else { "" });
+                }
+                match (report.reached(), report.next_net_positive()) {
+                    (Some(reached), _) if reached.monthly_pnl_usd > rust_decimal::Decimal::ZERO => {
+                        println!("{} is already net-positive at {}, the tier the bot reaches on its own", report.exchange, reached.tier.name)
+                    }
+                    (_, Some(next)) => println!("{} turns net-positive at {} with {} per month, {} more than the bot trades there; all venues together trade {}",
+                                                report.exchange, next.tier.name, currency.format(next.tier.min_monthly_volume_usd),
+                                                currency.format(next.volume_shortfall_usd()), currency.format(report.combined_monthly_volume_usd)),
+                    _ => println!("No configured {} tier makes the recorded opportunities net-positive", report.exchange),
+                }
+            }
+        }
+        Command::StateExport { output } => {
+            let store = LocalStore::open(&config.storage)?;
+            let output = output.unwrap_or_else(|| format!("arbitrage-state-{}.tar.gz", chrono::Utc::now().format("%Y%m%d-%H%M%S")));
+            let manifest = migration::export(&store, std::path::Path::new(&output))?;
+            println!("Exported {} state files ({} bytes) from {} to {}",
+                     manifest.files.len(), manifest.total_bytes(), store.location(std::path::Path::new("")), output);
+        }
+        Command::StateImport { input, force } => {
+            let store = LocalStore::open(&config.storage)?;
+            let manifest = migration::import(&store, std::path::Path::new(&input), force)?;
+            println!("Imported {} state files exported at {} by version {} into {}",
+                     manifest.files.len(), manifest.exported_at, manifest.bot_version, store.location(std::path::Path::new("")));
+        }
+        Command::Diag { samples, stream_seconds } => {
+            let mut diagnostics = config.diagnostics.clone();
+            diagnostics.rest_samples = samples.unwrap_or(diagnostics.rest_samples);
+            diagnostics.stream_seconds = stream_seconds.unwrap_or(diagnostics.stream_seconds);
+            println!("Measuring {} server time requests and {} s of {} trades per exchange...",
+                     diagnostics.rest_samples, diagnostics.stream_seconds, diagnostics.stream_symbol);
+            let results = diag::diagnose(&config, &diagnostics).await?;
+            let stats = |stats: Option<diag::LatencyStats>| match stats {
+                Some(s) => format!("p50 {:>7.1}  p95 {:>7.1}  p99 {:>7.1}  max {:>7.1} ms  ({} samples)",
+                                   s.p50_ms, s.p95_ms, s.p99_ms, s.max_ms, s.samples),
+                None => "-".to_string(),
+            };
+            for result in &results {
+                println!("{}", result.exchange);
+                println!("  clock offset  {}", result.clock_offset_ms.map(|o| format!("{:+.1} ms", o)).unwrap_or_else(|| "-".to_string()));
+                println!("  REST RTT      {}", stats(result.rest));
+                println!("  stream lag    {}", stats(result.stream));
+                for error in &result.errors {
+                    println!("  error: {}", error);
+                }
+                if result.ready() {
+                    println!("  ready");
+                }
+                for problem in &result.problems {
+                    println!("  not ready: {}", problem);
+                }
+            }
+            let not_ready = results.iter().filter(|r| !r.ready()).count();
+            if not_ready > 0 {
+                return Err(anyhow::anyhow!("{} of {} exchanges are not ready for trading from this host", not_ready, results.len()));
+            }
+        }
+    }
     
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,189 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Minimal five-field cron expression (`minute hour day-of-month month day-of-week`,
+/// evaluated in UTC). Each field accepts `*`, single values, `a-b` ranges,
+/// comma lists and `/n` steps. As in standard cron, when both day fields
+/// are restricted a day matches if either does: `0 2 1 * 1` runs on the
+/// 1st of the month and on every Monday.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CronSchedule {
+    minutes: Vec<u32>,
+    hours: Vec<u32>,
+    days_of_month: Vec<u32>,
+    months: Vec<u32>,
+    days_of_week: Vec<u32>,
+    /// Whether each day field is restricted, i.e. does not start with `*`
+    days_restricted: (bool, bool),
+}
+
+impl CronSchedule {
+    pub fn parse(expr: &str) -> Result<Self> {
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err(anyhow::anyhow!("Cron expression must have 5 fields: {}", expr));
+        }
+        
+        Ok(Self {
+            minutes: Self::parse_field(fields[0], 0, 59)?,
+            hours: Self::parse_field(fields[1], 0, 23)?,
+            days_of_month: Self::parse_field(fields[2], 1, 31)?,
+            months: Self::parse_field(fields[3], 1, 12)?,
+            // Sunday may be written as 0 or 7
+            days_of_week: Self::parse_field(fields[4], 0, 7)?
+                .into_iter()
+                .map(|d| d % 7)
+                .collect(),
+            days_restricted: (!fields[2].starts_with('*'), !fields[4].starts_with('*')),
+        })
+    }
+    
+    pub fn matches(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = self.days_of_month.contains(&time.day());
+        let day_of_week = self.days_of_week.contains(&time.weekday().num_days_from_sunday());
+        let day = match self.days_restricted {
+            (true, true) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        };
+        
+        self.minutes.contains(&time.minute())
+            && self.hours.contains(&time.hour())
+            && self.months.contains(&time.month())
+            && day
+    }
+    
+    fn parse_field(field: &str, min: u32, max: u32) -> Result<Vec<u32>> {
+        let mut values = Vec::new();
+        
+        for part in field.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => (range, step.parse::<u32>()
+                    .map_err(|_| anyhow::anyhow!("Invalid cron step: {}", part))?),
+                None => (part, 1),
+            };
+            if step == 0 {
+                return Err(anyhow::anyhow!("Cron step cannot be zero: {}", part));
+            }
+            
+            let (start, end) = if range == "*" {
+                (min, max)
+            } else if let Some((a, b)) = range.split_once('-') {
+                (Self::parse_value(a, min, max)?, Self::parse_value(b, min, max)?)
+            } else {
+                let value = Self::parse_value(range, min, max)?;
+                (value, if part.contains('/') { max } else { value })
+            };
+            if start > end {
+                return Err(anyhow::anyhow!("Invalid cron range: {}", part));
+            }
+            
+            values.extend((start..=end).step_by(step as usize));
+        }
+        
+        values.sort_unstable();
+        values.dedup();
+        Ok(values)
+    }
+    
+    fn parse_value(value: &str, min: u32, max: u32) -> Result<u32> {
+        let parsed = value.parse::<u32>()
+            .map_err(|_| anyhow::anyhow!("Invalid cron value: {}", value))?;
+        if parsed < min || parsed > max {
+            return Err(anyhow::anyhow!("Cron value {} out of range {}-{}", parsed, min, max));
+        }
+        Ok(parsed)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaintenanceWindowConfig {
+    pub name: String,
+    /// Cron expression for the window start, in UTC
+    pub cron: String,
+    pub duration_minutes: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct MaintenanceWindow {
+    pub name: String,
+    schedule: CronSchedule,
+    duration_minutes: u32,
+}
+
+impl MaintenanceWindow {
+    pub fn from_config(config: &MaintenanceWindowConfig) -> Result<Self> {
+        Ok(Self {
+            name: config.name.clone(),
+            schedule: CronSchedule::parse(&config.cron)?,
+            duration_minutes: config.duration_minutes,
+        })
+    }
+    
+    /// True when a scheduled start lies within the last `duration_minutes`.
+    pub fn is_active(&self, now: &DateTime<Utc>) -> bool {
+        let now = match now.with_second(0).and_then(|t| t.with_nanosecond(0)) {
+            Some(t) => t,
+            None => return false,
+        };
+        
+        (0..self.duration_minutes)
+            .map(|offset| now - chrono::Duration::minutes(offset as i64))
+            .any(|start| self.schedule.matches(&start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    
+    #[test]
+    fn test_cron_parsing() {
+        let cron = CronSchedule::parse("*/15 2-4 * * 1,3").unwrap();
+        assert_eq!(cron.minutes, vec![0, 15, 30, 45]);
+        assert_eq!(cron.hours, vec![2, 3, 4]);
+        assert_eq!(cron.days_of_week, vec![1, 3]);
+        
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert_eq!(CronSchedule::parse("0 0 * * 7").unwrap().days_of_week, vec![0]);
+    }
+    
+    #[test]
+    fn test_restricted_day_fields_match_either() {
+        let at = |day| Utc.with_ymd_and_hms(2024, 5, day, 2, 0, 0).unwrap();
+        
+        // The 1st (a Wednesday) or any Monday
+        let either = CronSchedule::parse("0 2 1 * 1").unwrap();
+        assert!(either.matches(&at(1)));
+        assert!(either.matches(&at(6)));
+        assert!(!either.matches(&at(7)));
+        
+        // With a field starting with `*`, both have to match
+        let first = CronSchedule::parse("0 2 1 * *").unwrap();
+        assert!(first.matches(&at(1)));
+        assert!(!first.matches(&at(6)));
+        let odd_mondays = CronSchedule::parse("0 2 */2 * 1").unwrap();
+        assert!(odd_mondays.matches(&at(13)));
+        assert!(!odd_mondays.matches(&at(6)));
+    }
+    
+    #[test]
+    fn test_window_activity() {
+        // Every Wednesday 02:00 UTC for 90 minutes
+        let window = MaintenanceWindow::from_config(&MaintenanceWindowConfig {
+            name: "weekly".to_string(),
+            cron: "0 2 * * 3".to_string(),
+            duration_minutes: 90,
+        }).unwrap();
+        
+        let wednesday = |h, m| Utc.with_ymd_and_hms(2024, 5, 1, h, m, 30).unwrap();
+        assert!(!window.is_active(&wednesday(1, 59)));
+        assert!(window.is_active(&wednesday(2, 0)));
+        assert!(window.is_active(&wednesday(3, 29)));
+        assert!(!window.is_active(&wednesday(3, 30)));
+        assert!(!window.is_active(&Utc.with_ymd_and_hms(2024, 5, 2, 2, 10, 0).unwrap()));
+    }
+}
@@ -0,0 +1,334 @@
+use crate::arbitrage::risk::Strategy;
+use crate::arbitrage::ArbitrageOpportunity;
+use crate::executor::LastLook;
+use crate::report::ExecutionReport;
+use crate::shadow::ShadowFill;
+use dashmap::DashMap;
+use rust_decimal::prelude::ToPrimitive;
+use serde_json::{json, Value};
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricKind {
+    Gauge,
+    Counter,
+}
+
+/// One exposed metric. Every series of a metric carries exactly `labels`,
+/// drawn from the shared vocabulary `exchange`, `symbol`, `strategy` and
+/// `path` plus a few metric-specific ones.
+#[derive(Debug, Clone, Copy)]
+pub struct MetricDef {
+    pub name: &'static str,
+    pub help: &'static str,
+    pub kind: MetricKind,
+    pub labels: &'static [&'static str],
+    /// Grafana unit of the metric's panel
+    pub unit: &'static str,
+}
+
+const fn gauge(name: &'static str, help: &'static str, labels: &'static [&'static str], unit: &'static str) -> MetricDef {
+    MetricDef { name, help, kind: MetricKind::Gauge, labels, unit }
+}
+
+const fn counter(name: &'static str, help: &'static str, labels: &'static [&'static str]) -> MetricDef {
+    MetricDef { name, help, kind: MetricKind::Counter, labels, unit: "ops" }
+}
+
+pub const SCAN_DURATION: MetricDef = gauge("arbitrage_scan_duration_seconds", "Duration of the last scan by phase.", &["phase"], "s");
+pub const SCANS_TIMED: MetricDef = gauge("arbitrage_scans_timed", "Scans whose timing has been recorded since start.", &[], "short");
+pub const UPTIME: MetricDef = gauge("arbitrage_uptime_seconds", "Seconds since the bot started.", &[], "s");
+pub const READY: MetricDef = gauge("arbitrage_ready", "1 when the readiness check passes.", &[], "bool");
+pub const CIRCUIT_BREAKER_OPEN: MetricDef = gauge("arbitrage_circuit_breaker_open", "1 while the global circuit breaker is open.", &[], "bool");
+pub const FEED_RESTARTS: MetricDef = counter("arbitrage_feed_restarts_total", "Restarts of the exchange's market data task after a panic or stall.", &["exchange"]);
+pub const DEPTH_STREAM_CONNECTED: MetricDef = gauge("arbitrage_depth_stream_connected", "1 while the exchange's websocket depth stream is connected.", &["exchange"], "bool");
+pub const DEPTH_STREAM_STALE_SYMBOLS: MetricDef = gauge("arbitrage_depth_stream_stale_symbols", "Symbols whose local book waits for a fresh snapshot.", &["exchange"], "short");
+pub const DEPTH_STREAM_RECONNECTS: MetricDef = counter("arbitrage_depth_stream_reconnects_total", "Reconnections of the exchange's websocket depth stream.", &["exchange"]);
+pub const DEPTH_STREAM_GAPS: MetricDef = counter("arbitrage_depth_stream_gaps_total", "Sequence gaps that dropped a local book until it was resynced.", &["exchange"]);
+pub const DEPTH_STREAM_RESUBSCRIBES: MetricDef = counter("arbitrage_depth_stream_resubscribes_total", "Depth topics resubscribed to get a fresh snapshot.", &["exchange"]);
+pub const SLO_BREACHED: MetricDef = gauge("arbitrage_slo_breached", "1 while execution is paused for market data SLO violations.", &[], "bool");
+pub const SLO_VIOLATIONS: MetricDef = gauge("arbitrage_slo_violations", "Symbols violating the SLO by kind (message_rate or quote_age) at the last check.", &["kind"], "short");
+pub const CLIENT_RESETS: MetricDef = counter("arbitrage_client_resets_total", "Exchange HTTP clients recreated by the scan watchdog.", &["exchange"]);
+pub const DNS_LOOKUPS: MetricDef = counter("arbitrage_dns_lookups_total", "DNS lookups of the exchange's hosts by source (cache, resolver or stale).", &["exchange", "host", "source"]);
+pub const DNS_LOOKUP_DURATION: MetricDef = gauge("arbitrage_dns_lookup_seconds", "Duration of the host's last lookup through the system resolver.", &["exchange", "host"], "s");
+pub const HOST_CONNECT_DURATION: MetricDef = gauge("arbitrage_host_connect_seconds", "TCP connect time of each resolved address of the host.", &["exchange", "host", "address"], "s");
+pub const REQUEST_WEIGHT_USED: MetricDef = gauge("arbitrage_request_weight_used", "Request weight the class spent over the last minute.", &["exchange", "class"], "short");
+pub const REQUEST_BUDGET_UTILIZATION: MetricDef = gauge("arbitrage_request_budget_utilization_ratio", "Share of its request weight allowance the class spent over the last minute.", &["exchange", "class"], "percentunit");
+pub const REQUEST_BUDGET_WAITS: MetricDef = counter("arbitrage_request_budget_waits_total", "Requests that waited for the class's request weight allowance.", &["exchange", "class"]);
+pub const EXCHANGE_ERRORS: MetricDef = counter("arbitrage_exchange_errors_total", "Failed exchange requests by endpoint, kind (network, parse, api or rate_limit) and API code.", &["exchange", "endpoint", "kind", "code"]);
+pub const EXCHANGE_CONNECTED: MetricDef = gauge("arbitrage_exchange_connected", "1 when the exchange answered its last call.", &["exchange"], "bool");
+pub const OPPORTUNITIES: MetricDef = counter("arbitrage_opportunities_total", "Opportunities detected since start.", &["exchange", "strategy", "path"]);
+pub const OPPORTUNITY_NET_PROFIT: MetricDef = gauge("arbitrage_opportunity_net_profit_percentage", "Net profit of the last opportunity on the path.", &["exchange", "strategy", "path"], "percent");
+pub const LEG_SPREAD: MetricDef = gauge("arbitrage_leg_spread_percentage", "Bid/ask spread of the symbol when it last appeared in an opportunity.", &["exchange", "symbol"], "percent");
+pub const EXECUTIONS: MetricDef = counter("arbitrage_executions_total", "Executions since start by result (completed or abandoned).", &["exchange", "strategy", "path", "result"]);
+pub const LAST_LOOKS: MetricDef = counter("arbitrage_last_looks_total", "Pre-execution re-validations by result (passed, expired, evaporated or decayed).", &["exchange", "strategy", "path", "result"]);
+pub const EXECUTION_QUEUE_DEPTH: MetricDef = gauge("arbitrage_execution_queue_depth", "Opportunities waiting in the execution queue.", &[], "short");
+pub const EXECUTION_QUEUE_ENQUEUED: MetricDef = counter("arbitrage_execution_queue_enqueued_total", "Opportunities the scan loop got into the execution queue.", &[]);
+pub const EXECUTION_QUEUE_DEQUEUED: MetricDef = counter("arbitrage_execution_queue_dequeued_total", "Opportunities execution workers took from the queue.", &[]);
+pub const EXECUTION_QUEUE_DROPPED: MetricDef = counter("arbitrage_execution_queue_dropped_total", "Opportunities dropped from the execution queue by reason (expired, evicted or rejected).", &["reason"]);
+pub const SHADOW_EXECUTIONS: MetricDef = counter("arbitrage_shadow_executions_total", "Opportunities checked against fetched order books by result (filled, thin_book or slipped).", &["exchange", "strategy", "path", "result"]);
+pub const SHADOW_SLIPPAGE: MetricDef = gauge("arbitrage_shadow_slippage_percentage", "Worst leg slippage of the path's last shadow execution.", &["exchange", "strategy", "path"], "percent");
+pub const REALIZED_PNL: MetricDef = gauge("arbitrage_realized_pnl_usd", "Estimated realized profit since start.", &["exchange", "strategy", "path"], "currencyUSD");
+
+/// Every metric the admin server exposes, in dashboard order.
+pub const REGISTRY: &[MetricDef] = &[
+    OPPORTUNITIES,
+    OPPORTUNITY_NET_PROFIT,
+    LAST_LOOKS,
+    EXECUTION_QUEUE_DEPTH,
+    EXECUTION_QUEUE_ENQUEUED,
+    EXECUTION_QUEUE_DEQUEUED,
+    EXECUTION_QUEUE_DROPPED,
+    SHADOW_EXECUTIONS,
+    SHADOW_SLIPPAGE,
+    EXECUTIONS,
+    REALIZED_PNL,
+    LEG_SPREAD,
+    EXCHANGE_CONNECTED,
+    EXCHANGE_ERRORS,
+    FEED_RESTARTS,
+    DEPTH_STREAM_CONNECTED,
+    DEPTH_STREAM_STALE_SYMBOLS,
+    DEPTH_STREAM_RECONNECTS,
+    DEPTH_STREAM_GAPS,
+    DEPTH_STREAM_RESUBSCRIBES,
+    SLO_BREACHED,
+    SLO_VIOLATIONS,
+    CLIENT_RESETS,
+    DNS_LOOKUPS,
+    DNS_LOOKUP_DURATION,
+    HOST_CONNECT_DURATION,
+    REQUEST_WEIGHT_USED,
+    REQUEST_BUDGET_UTILIZATION,
+    REQUEST_BUDGET_WAITS,
+    SCAN_DURATION,
+    SCANS_TIMED,
+    UPTIME,
+    READY,
+    CIRCUIT_BREAKER_OPEN,
+];
+
+/// Label value of a strategy, as serialized in opportunities.
+pub fn strategy_label(strategy: Strategy) -> &'static str {
+    match strategy {
+        Strategy::CrossExchange => "cross_exchange",
+        Strategy::Triangular => "triangular",
+    }
+}
+
+/// Label value of an opportunity's path: its leg symbols joined by `>`,
+/// e.g. `BTCUSDT>ETHBTC>ETHUSDT`, or `SOLUSDT` for a cross-exchange pair.
+pub fn path_label(opportunity: &ArbitrageOpportunity) -> String {
+    let mut legs: Vec<&str> = opportunity.execution_steps.iter().map(|s| s.symbol.as_str()).collect();
+    legs.dedup();
+    legs.join(">")
+}
+
+/// Appends `def` with `samples`, each given as label values in the order
+/// of `def.labels`, in Prometheus text format.
+pub fn write_metric(out: &mut String, def: &MetricDef, samples: &[(Vec<String>, f64)]) {
+    let kind = match def.kind {
+        MetricKind::Gauge => "gauge",
+        MetricKind::Counter => "counter",
+    };
+    let _ = writeln!(out, "# HELP {} {}\n# TYPE {} {}", def.name, def.help, def.name, kind);
+    for (values, value) in samples {
+        let labels: Vec<String> = def.labels.iter().zip(values)
+            .map(|(name, value)| format!("{}=\"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\"")))
+            .collect();
+        if labels.is_empty() {
+            let _ = writeln!(out, "{} {}", def.name, value);
+        } else {
+            let _ = writeln!(out, "{}{{{}}} {}", def.name, labels.join(","), value);
+        }
+    }
+}
+
+/// Opportunity and execution series, updated by the scan loop and engine.
+#[derive(Debug, Default)]
+pub struct MarketMetrics {
+    opportunities: DashMap<Vec<String>, f64>,
+    net_profit: DashMap<Vec<String>, f64>,
+    spreads: DashMap<Vec<String>, f64>,
+    last_looks: DashMap<Vec<String>, f64>,
+    shadow_executions: DashMap<Vec<String>, f64>,
+    shadow_slippage: DashMap<Vec<String>, f64>,
+    executions: DashMap<Vec<String>, f64>,
+    realized_pnl: DashMap<Vec<String>, f64>,
+    feed_restarts: DashMap<Vec<String>, f64>,
+    client_resets: DashMap<Vec<String>, f64>,
+}
+
+impl MarketMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record_opportunities(&self, opportunities: &[ArbitrageOpportunity]) {
+        for opportunity in opportunities {
+            let labels = opportunity_labels(opportunity);
+            *self.opportunities.entry(labels.clone()).or_default() += 1.0;
+            self.net_profit.insert(labels, opportunity.net_profit_percentage.to_f64().unwrap_or(0.0));
+            for quote in &opportunity.leg_quotes {
+                self.spreads.insert(vec![quote.exchange.clone(), quote.symbol.clone()],
+                                    quote.spread_percentage.to_f64().unwrap_or(0.0));
+            }
+        }
+    }
+
+    pub fn record_last_look(&self, opportunity: &ArbitrageOpportunity, look: &LastLook) {
+        let mut labels = opportunity_labels(opportunity);
+        labels.push(look.label().to_string());
+        *self.last_looks.entry(labels).or_default() += 1.0;
+    }
+
+    pub fn record_execution(&self, opportunity: &ArbitrageOpportunity, report: &ExecutionReport) {
+        let labels = opportunity_labels(opportunity);
+        let mut result = labels.clone();
+        result.push(if report.completed { "completed" } else { "abandoned" }.to_string());
+        *self.executions.entry(result).or_default() += 1.0;
+        *self.realized_pnl.entry(labels).or_default() += report.realized_pnl_usd().to_f64().unwrap_or(0.0);
+    }
+
+    pub fn record_shadow_fill(&self, opportunity: &ArbitrageOpportunity, fill: &ShadowFill) {
+        let mut labels = opportunity_labels(opportunity);
+        self.shadow_slippage.insert(labels.clone(), fill.worst_slippage_percentage().to_f64().unwrap_or(0.0));
+        labels.push(fill.outcome.label().to_string());
+        *self.shadow_executions.entry(labels).or_default() += 1.0;
+    }
+
+    pub fn record_feed_restart(&self, exchange: &str) {
+        *self.feed_restarts.entry(vec![exchange.to_string()]).or_default() += 1.0;
+    }
+
+    pub fn record_client_reset(&self, exchange: &str) {
+        *self.client_resets.entry(vec![exchange.to_string()]).or_default() += 1.0;
+    }
+
+    pub fn prometheus_metrics(&self) -> String {
+        let mut out = String::new();
+        for (def, series) in [
+            (&OPPORTUNITIES, &self.opportunities),
+            (&OPPORTUNITY_NET_PROFIT, &self.net_profit),
+            (&LAST_LOOKS, &self.last_looks),
+            (&SHADOW_EXECUTIONS, &self.shadow_executions),
+            (&SHADOW_SLIPPAGE, &self.shadow_slippage),
+            (&EXECUTIONS, &self.executions),
+            (&REALIZED_PNL, &self.realized_pnl),
+            (&LEG_SPREAD, &self.spreads),
+            (&FEED_RESTARTS, &self.feed_restarts),
+            (&CLIENT_RESETS, &self.client_resets),
+        ] {
+            let mut samples: Vec<(Vec<String>, f64)> = series.iter().map(|e| (e.key().clone(), *e.value())).collect();
+            samples.sort_by(|a, b| a.0.cmp(&b.0));
+            write_metric(&mut out, def, &samples);
+        }
+        out
+    }
+}
+
+fn opportunity_labels(opportunity: &ArbitrageOpportunity) -> Vec<String> {
+    vec![opportunity.exchange.clone(), strategy_label(opportunity.strategy).to_string(), path_label(opportunity)]
+}
+
+/// Grafana dashboard with one panel per registered metric, ready for file
+/// provisioning. Panels filter on the `exchange` and `strategy` variables
+/// where the metric has those labels.
+pub fn grafana_dashboard() -> Value {
+    let panels: Vec<Value> = REGISTRY.iter().enumerate().map(|(i, def)| {
+        let filters: Vec<String> = ["exchange", "strategy"].iter()
+            .filter(|label| def.labels.contains(label))
+            .map(|label| format!("{}=~\"${}\"", label, label))
+            .collect();
+        let selector = format!("{}{{{}}}", def.name, filters.join(","));
+        let by = def.labels.join(", ");
+        let expr = match (def.kind, def.labels.is_empty()) {
+            (MetricKind::Counter, true) => format!("sum(rate({}[$__rate_interval]))", selector),
+            (MetricKind::Counter, false) => format!("sum by ({}) (rate({}[$__rate_interval]))", by, selector),
+            (MetricKind::Gauge, _) => selector,
+        };
+        let legend = if def.labels.is_empty() {
+            def.name.to_string()
+        } else {
+            def.labels.iter().map(|l| format!("{{{{{}}}}}", l)).collect::<Vec<_>>().join(" ")
+        };
+        json!({
+            "id": i + 1,
+            "type": "timeseries",
+            "title": def.help.trim_end_matches('.'),
+            "description": def.name,
+            "datasource": { "type": "prometheus", "uid": "${datasource}" },
+            "gridPos": { "h": 8, "w": 12, "x": (i % 2) * 12, "y": (i / 2) * 8 },
+            "fieldConfig": { "defaults": { "unit": def.unit }, "overrides": [] },
+            "options": { "legend": { "displayMode": "list", "placement": "bottom" } },
+            "targets": [{
+                "refId": "A",
+                "datasource": { "type": "prometheus", "uid": "${datasource}" },
+                "expr": expr,
+                "legendFormat": legend,
+            }],
+        })
+    }).collect();
+
+    let variable = |name: &str, metric: &MetricDef| json!({
+        "name": name,
+        "label": name,
+        "type": "query",
+        "datasource": { "type": "prometheus", "uid": "${datasource}" },
+        "query": { "query": format!("label_values({}, {})", metric.name, name), "refId": name },
+        "definition": format!("label_values({}, {})", metric.name, name),
+        "refresh": 2,
+        "includeAll": true,
+        "multi": true,
+        "allValue": ".*",
+        "current": { "text": "All", "value": "$__all" },
+    });
+
+    json!({
+        "id": null,
+        "uid": "triangular-arbitrage",
+        "title": "Triangular Arbitrage",
+        "tags": ["arbitrage"],
+        "timezone": "utc",
+        "schemaVersion": 39,
+        "version": 1,
+        "refresh": "30s",
+        "time": { "from": "now-6h", "to": "now" },
+        "templating": { "list": [
+            { "name": "datasource", "label": "Data source", "type": "datasource", "query": "prometheus" },
+            variable("exchange", &OPPORTUNITIES),
+            variable("strategy", &OPPORTUNITIES),
+        ]},
+        "panels": panels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_labels_are_rendered_in_definition_order() {
+        let mut out = String::new();
+        write_metric(&mut out, &EXECUTIONS, &[(
+            vec!["Binance".to_string(), "triangular".to_string(), "BTCUSDT>ETHBTC>ETHUSDT".to_string(), "completed".to_string()],
+            2.0,
+        )]);
+        assert!(out.contains("# TYPE arbitrage_executions_total counter"));
+        assert!(out.contains("arbitrage_executions_total{exchange=\"Binance\",strategy=\"triangular\",path=\"BTCUSDT>ETHBTC>ETHUSDT\",result=\"completed\"} 2"));
+    }
+
+    #[test]
+    fn test_dashboard_covers_registry() {
+        let dashboard = grafana_dashboard();
+        let panels = dashboard["panels"].as_array().unwrap();
+        assert_eq!(panels.len(), REGISTRY.len());
+        for (panel, def) in panels.iter().zip(REGISTRY) {
+            let expr = panel["targets"][0]["expr"].as_str().unwrap();
+            assert!(expr.contains(def.name));
+            assert_eq!(expr.contains("exchange=~\"$exchange\""), def.labels.contains(&"exchange"));
+        }
+        assert_eq!(panels[0]["targets"][0]["expr"],
+                   "sum by (exchange, strategy, path) (rate(arbitrage_opportunities_total{exchange=~\"$exchange\",strategy=~\"$strategy\"}[$__rate_interval]))");
+    }
+}
@@ -0,0 +1,253 @@
+//! Export and import of the bot's state, to move it between hosts or
+//! storage backends.
+//!
+//! The archive is a gzipped tar of the state datasets and documents, laid
+//! out as they are under `storage.data_dir` with the files backend, plus a
+//! `manifest.json` listing them. It covers what cannot be rebuilt from the
+//! exchanges: the execution ledger that keeps opportunities from executing
+//! twice, the journal crash recovery reads, execution and shadow reports,
+//! portfolio snapshots, the kill switch's daily loss and the recorded
+//! market snapshots. Downloaded candles and the warm start cache are left
+//! out; the new host fetches them again.
+
+use crate::storage::LocalStore;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+
+/// Directories under the data directory that make up the state.
+pub const STATE_DIRS: [&str; 5] = ["ledger", "journal", "reports", "portfolio", "snapshots"];
+/// Single files that make up the state.
+pub const STATE_FILES: [&str; 1] = ["risk/kill_switch.json"];
+
+const MANIFEST: &str = "manifest.json";
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StateManifest {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    /// Version of the bot that wrote the archive
+    pub bot_version: String,
+    /// Paths relative to the data directory, with their sizes in bytes
+    pub files: Vec<(String, u64)>,
+}
+
+impl StateManifest {
+    pub fn total_bytes(&self) -> u64 {
+        self.files.iter().map(|(_, bytes)| bytes).sum()
+    }
+}
+
+fn is_state_path(relative: &Path) -> bool {
+    let plain = relative.components().all(|c| matches!(c, Component::Normal(_)));
+    plain && (STATE_FILES.iter().any(|file| relative == Path::new(file))
+        || STATE_DIRS.iter().any(|dir| relative.starts_with(dir)))
+}
+
+/// State datasets and documents present in `store`, sorted.
+pub fn state_files(store: &LocalStore) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for prefix in STATE_DIRS.iter().chain(&STATE_FILES) {
+        files.extend(store.backend().keys(Path::new(prefix))?);
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_dataset(relative: &Path) -> bool {
+    relative.extension().is_some_and(|ext| ext == "jsonl")
+}
+
+/// Contents of a dataset or document as they would be on disk.
+fn contents(store: &LocalStore, relative: &Path) -> Result<String> {
+    if is_dataset(relative) {
+        Ok(store.backend().read(relative)?.iter().map(|line| format!("{}\n", line)).collect())
+    } else {
+        Ok(store.backend().get(relative)?.unwrap_or_default())
+    }
+}
+
+fn restore(store: &LocalStore, relative: &Path, contents: &str) -> Result<()> {
+    if is_dataset(relative) {
+        let lines: Vec<String> = contents.lines().filter(|line| !line.trim().is_empty()).map(str::to_string).collect();
+        store.backend().replace(relative, &lines)
+    } else {
+        store.backend().put(relative, contents)
+    }
+}
+
+/// Writes the state of `store` to a gzipped tar at `output`.
+pub fn export(store: &LocalStore, output: &Path) -> Result<StateManifest> {
+    let mut manifest = StateManifest {
+        format_version: FORMAT_VERSION,
+        exported_at: Utc::now(),
+        bot_version: env!("CARGO_PKG_VERSION").to_string(),
+        files: Vec::new(),
+    };
+    let mut entries = Vec::new();
+    for file in state_files(store)? {
+        let contents = contents(store, &file)?;
+        manifest.files.push((file.to_string_lossy().replace('\\', "/"), contents.len() as u64));
+        entries.push((file, contents));
+    }
+
+    let archive = File::create(output).with_context(|| format!("Failed to create {}", output.display()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(archive, Compression::default()));
+    let manifest_json = serde_json::to_vec_pretty(&manifest)?;
+    let mtime = manifest.exported_at.timestamp().max(0) as u64;
+    for (path, data) in std::iter::once((PathBuf::from(MANIFEST), manifest_json.as_slice()))
+        .chain(entries.iter().map(|(file, contents)| (file.clone(), contents.as_bytes())))
+    {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(mtime);
+        builder.append_data(&mut header, &path, data)?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(manifest)
+}
+
+/// Restores the state in the archive at `input` into `store`. Refuses to
+/// touch storage that already holds state unless `overwrite` is set, in
+/// which case the existing state files are removed first.
+pub fn import(store: &LocalStore, input: &Path, overwrite: bool) -> Result<StateManifest> {
+    let existing = state_files(store)?;
+    if !existing.is_empty() && !overwrite {
+        return Err(anyhow::anyhow!(
+            "{} already holds {} state files; import into empty storage or pass --force to replace them",
+            store.location(Path::new("")), existing.len()
+        ));
+    }
+
+    // Check everything before touching the storage
+    let manifest = read_manifest(input)?;
+    if manifest.format_version != FORMAT_VERSION {
+        return Err(anyhow::anyhow!("Unsupported state archive format {} (expected {})", manifest.format_version, FORMAT_VERSION));
+    }
+    if let Some((path, _)) = manifest.files.iter().find(|(path, _)| !is_state_path(Path::new(path))) {
+        return Err(anyhow::anyhow!("State archive lists unexpected path {}", path));
+    }
+
+    for file in existing {
+        store.backend().remove(&file)?;
+    }
+    let mut archive = tar::Archive::new(GzDecoder::new(File::open(input)?));
+    let mut restored = Vec::new();
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let relative = entry.path()?.into_owned();
+        if relative == Path::new(MANIFEST) {
+            continue;
+        }
+        if !is_state_path(&relative) {
+            return Err(anyhow::anyhow!("State archive contains unexpected path {}", relative.display()));
+        }
+        let mut contents = String::new();
+        entry.read_to_string(&mut contents)
+            .with_context(|| format!("Failed to read {} from the state archive", relative.display()))?;
+        restore(store, &relative, &contents)?;
+        restored.push((relative.to_string_lossy().replace('\\', "/"), contents.len() as u64));
+    }
+    restored.sort();
+    let mut listed = manifest.files.clone();
+    listed.sort();
+    if restored != listed {
+        return Err(anyhow::anyhow!("State archive contents do not match its manifest; it may be truncated"));
+    }
+    Ok(manifest)
+}
+
+fn read_manifest(input: &Path) -> Result<StateManifest> {
+    let file = File::open(input).with_context(|| format!("Failed to open {}", input.display()))?;
+    let mut archive = tar::Archive::new(GzDecoder::new(file));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()? == Path::new(MANIFEST) {
+            let mut json = String::new();
+            entry.read_to_string(&mut json)?;
+            return serde_json::from_str(&json).context("Corrupt state archive manifest");
+        }
+    }
+    Err(anyhow::anyhow!("{} is not a state archive: it has no {}", input.display(), MANIFEST))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use std::fs;
+    use std::sync::Arc;
+
+    fn write(store: &LocalStore, relative: &str, contents: &str) {
+        let path = store.root().join(relative);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_state_round_trips_without_market_data() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source = LocalStore::new(source_dir.path()).unwrap();
+        write(&source, "ledger/executions.jsonl", "{\"key\":\"a\"}\n");
+        write(&source, "journal/executions.jsonl", "{}\n");
+        write(&source, "reports/executions.jsonl", "{}\n{}\n");
+        write(&source, "snapshots/2024-05-01.jsonl", "{}\n");
+        write(&source, "risk/kill_switch.json", "{}");
+        write(&source, "klines/binance/BTCUSDT_1h.jsonl", "{}\n");
+        write(&source, "cache/warm_start.json", "{}");
+        write(&source, "reports/executions.tmp", "");
+
+        let archive = source_dir.path().join("state.tar.gz");
+        let manifest = export(&source, &archive).unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, [
+            "journal/executions.jsonl",
+            "ledger/executions.jsonl",
+            "reports/executions.jsonl",
+            "risk/kill_switch.json",
+            "snapshots/2024-05-01.jsonl",
+        ]);
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let target = LocalStore::new(target_dir.path()).unwrap();
+        assert_eq!(import(&target, &archive, false).unwrap(), manifest);
+        assert_eq!(fs::read_to_string(target_dir.path().join("reports/executions.jsonl")).unwrap(), "{}\n{}\n");
+        assert!(!target_dir.path().join("klines").exists());
+
+        // Existing state is only replaced when asked to
+        assert!(import(&target, &archive, false).is_err());
+        write(&target, "portfolio/snapshots.jsonl", "{}\n");
+        import(&target, &archive, true).unwrap();
+        assert!(!target_dir.path().join("portfolio/snapshots.jsonl").exists());
+
+        // The same archive moves the state into a database
+        let database = Arc::new(SqliteStorage::open(&target_dir.path().join("arbitrage.db"), "eu-1").unwrap());
+        let sqlite = LocalStore::with_backend(target_dir.path(), database);
+        import(&sqlite, &archive, false).unwrap();
+        assert_eq!(sqlite.backend().read(Path::new("reports/executions.jsonl")).unwrap().len(), 2);
+        assert_eq!(sqlite.backend().get(Path::new("risk/kill_switch.json")).unwrap().as_deref(), Some("{}"));
+        assert_eq!(export(&sqlite, &target_dir.path().join("again.tar.gz")).unwrap().files, manifest.files);
+    }
+
+    #[test]
+    fn test_paths_outside_the_state_are_rejected() {
+        assert!(is_state_path(Path::new("reports/shadow.jsonl")));
+        assert!(is_state_path(Path::new("risk/kill_switch.json")));
+        assert!(!is_state_path(Path::new("reports/../../etc/passwd")));
+        assert!(!is_state_path(Path::new("/ledger/executions.jsonl")));
+        assert!(!is_state_path(Path::new("klines/binance/BTCUSDT_1h.jsonl")));
+
+        let dir = tempfile::tempdir().unwrap();
+        let not_an_archive = dir.path().join("state.tar.gz");
+        fs::write(&not_an_archive, "plain text").unwrap();
+        assert!(import(&LocalStore::new(dir.path().join("data")).unwrap(), &not_an_archive, false).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Notification delivery behind the `Notifier` trait.
+//!
+//! Every alert channel is a `Notifier`: the log, a file, a generic webhook,
+//! Telegram, Discord or email. Channels are wrapped in `RateLimited`, so a
+//! burst of alerts during a volatile period is cut down to a few messages
+//! per minute per channel, and the same message is not repeated within the
+//! dedup window. The next message that goes out says how many were held
+//! back in between.
+
+use crate::arbitrage::ArbitrageOpportunity;
+use crate::currency::CurrencyConverter;
+use crate::summary::{escape, send_email, SmtpConfig};
+use anyhow::{Context, Result};
+use chrono::Utc;
+use futures_util::future::BoxFuture;
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Longest message Discord accepts in `content`.
+const DISCORD_MAX_CHARS: usize = 2000;
+/// Longest message Telegram accepts in `text`.
+const TELEGRAM_MAX_CHARS: usize = 4096;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    /// Messages each channel sends at most in any 60 seconds
+    pub max_per_minute: usize,
+    /// Identical messages to a channel within this window are dropped; 0 disables
+    pub dedup_seconds: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self { max_per_minute: 20, dedup_seconds: 300 }
+    }
+}
+
+/// A message for a channel, about an opportunity or about the bot itself.
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub text: String,
+    pub opportunity: Option<ArbitrageOpportunity>,
+}
+
+impl Notification {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self { text: text.into(), opportunity: None }
+    }
+
+    /// Alert text with amounts in `currency`'s home currency.
+    pub fn opportunity(opportunity: &ArbitrageOpportunity, currency: &CurrencyConverter) -> Self {
+        Self { text: format!("Arbitrage alert: {}", opportunity.describe(currency)), opportunity: Some(opportunity.clone()) }
+    }
+}
+
+pub trait Notifier: Send + Sync {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>>;
+}
+
+/// Opportunities at info level, messages about the bot at error level.
+pub struct LogNotifier;
+
+impl Notifier for LogNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        match notification.opportunity {
+            Some(_) => info!("{}", notification.text),
+            None => error!("ALERT {}", notification.text),
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
+
+/// Appends the opportunity, or `{"text": ..., "timestamp": ...}`, as one JSON line.
+pub struct FileNotifier {
+    path: String,
+}
+
+impl FileNotifier {
+    pub fn new(path: impl Into<String>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl Notifier for FileNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(&self.path)?;
+            match &notification.opportunity {
+                Some(opportunity) => serde_json::to_writer(&mut file, opportunity)?,
+                None => serde_json::to_writer(&mut file, &serde_json::json!({ "text": notification.text, "timestamp": Utc::now() }))?,
+            }
+            writeln!(file)?;
+            Ok(())
+        })
+    }
+}
+
+/// POSTs `body` to `url`. Webhook and bot URLs carry their token in the
+/// path, so errors are returned without the URL.
+async fn post_json(http: &reqwest::Client, url: &str, body: &serde_json::Value) -> Result<()> {
+    http.post(url).json(body).send().await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| e.without_url())?;
+    Ok(())
+}
+
+/// POSTs `{"text": ..., "opportunity": ...}`, which chat webhooks such as
+/// Slack's display as a message.
+pub struct WebhookNotifier {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: impl Into<String>, http: reqwest::Client) -> Self {
+        Self { url: url.into(), http }
+    }
+}
+
+impl Notifier for WebhookNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let body = match &notification.opportunity {
+                Some(opportunity) => serde_json::json!({ "text": notification.text, "opportunity": opportunity }),
+                None => serde_json::json!({ "text": notification.text, "timestamp": Utc::now() }),
+            };
+            post_json(&self.http, &self.url, &body).await
+        })
+    }
+}
+
+/// Sends through a Telegram bot's `sendMessage`.
+pub struct TelegramNotifier {
+    api_url: String,
+    bot_token_env: String,
+    chat_id: String,
+    http: reqwest::Client,
+}
+
+impl TelegramNotifier {
+    pub fn new(api_url: impl Into<String>, bot_token_env: impl Into<String>, chat_id: impl Into<String>, http: reqwest::Client) -> Self {
+        Self { api_url: api_url.into(), bot_token_env: bot_token_env.into(), chat_id: chat_id.into(), http }
+    }
+}
+
+impl Notifier for TelegramNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let token = std::env::var(&self.bot_token_env)
+                .with_context(|| format!("{} is not set", self.bot_token_env))?;
+            let url = format!("{}/bot{}/sendMessage", self.api_url.trim_end_matches('/'), token);
+            let text: String = notification.text.chars().take(TELEGRAM_MAX_CHARS).collect();
+            let body = serde_json::json!({ "chat_id": self.chat_id, "text": text });
+            post_json(&self.http, &url, &body).await
+        })
+    }
+}
+
+/// Posts to a Discord channel webhook.
+pub struct DiscordNotifier {
+    url: String,
+    http: reqwest::Client,
+}
+
+impl DiscordNotifier {
+    pub fn new(url: impl Into<String>, http: reqwest::Client) -> Self {
+        Self { url: url.into(), http }
+    }
+}
+
+impl Notifier for DiscordNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let content: String = notification.text.chars().take(DISCORD_MAX_CHARS).collect();
+            post_json(&self.http, &self.url, &serde_json::json!({ "content": content })).await
+        })
+    }
+}
+
+pub struct EmailNotifier {
+    smtp: SmtpConfig,
+}
+
+impl EmailNotifier {
+    pub fn new(smtp: SmtpConfig) -> Self {
+        Self { smtp }
+    }
+}
+
+impl Notifier for EmailNotifier {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let subject = match notification.opportunity {
+                Some(_) => "Arbitrage alert",
+                None => "Arbitrage bot alert",
+            };
+            send_email(&self.smtp, subject, format!("<pre>{}</pre>", escape(&notification.text))).await
+        })
+    }
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    sent: VecDeque<Instant>,
+    recent: HashMap<String, Instant>,
+    suppressed: u64,
+}
+
+/// Sliding one-minute rate limit and dedup window for one channel.
+#[derive(Debug)]
+pub struct NotificationLimiter {
+    max_per_minute: usize,
+    dedup_window: Duration,
+    state: Mutex<LimiterState>,
+}
+
+impl NotificationLimiter {
+    pub fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            max_per_minute: config.max_per_minute,
+            dedup_window: Duration::from_secs(config.dedup_seconds),
+            state: Mutex::new(LimiterState { sent: VecDeque::new(), recent: HashMap::new(), suppressed: 0 }),
+        }
+    }
+
+    /// Whether `text` may be sent at `now`. When it may, returns how many
+    /// messages were held back since the last one sent.
+    pub fn admit(&self, text: &str, now: Instant) -> Option<u64> {
+        let mut state = self.state.lock().unwrap();
+        let window = self.dedup_window;
+        state.recent.retain(|_, at| now.saturating_duration_since(*at) < window);
+        while state.sent.front().is_some_and(|at| now.saturating_duration_since(*at) >= Duration::from_secs(60)) {
+            state.sent.pop_front();
+        }
+        if state.recent.contains_key(text) || state.sent.len() >= self.max_per_minute {
+            state.suppressed += 1;
+            return None;
+        }
+
+        state.sent.push_back(now);
+        if !window.is_zero() {
+            state.recent.insert(text.to_string(), now);
+        }
+        Some(std::mem::take(&mut state.suppressed))
+    }
+}
+
+/// A notifier behind a `NotificationLimiter`. Held back messages are
+/// dropped, not queued.
+pub struct RateLimited {
+    name: String,
+    inner: Box<dyn Notifier>,
+    limiter: NotificationLimiter,
+}
+
+impl RateLimited {
+    pub fn new(name: impl Into<String>, inner: Box<dyn Notifier>, config: &RateLimitConfig) -> Self {
+        Self { name: name.into(), inner, limiter: NotificationLimiter::new(config) }
+    }
+}
+
+impl Notifier for RateLimited {
+    fn send<'a>(&'a self, notification: &'a Notification) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            match self.limiter.admit(&notification.text, Instant::now()) {
+                None => {
+                    debug!("Alert to channel '{}' held back by rate limiting", self.name);
+                    Ok(())
+                }
+                Some(0) => self.inner.send(notification).await,
+                Some(suppressed) => {
+                    let mut notification = notification.clone();
+                    notification.text.push_str(&format!(" ({} more alerts held back by rate limiting)", suppressed));
+                    self.inner.send(&notification).await
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limiter_drops_duplicates_and_bursts() {
+        let limiter = NotificationLimiter::new(&RateLimitConfig { max_per_minute: 2, dedup_seconds: 300 });
+        let start = Instant::now();
+        assert_eq!(limiter.admit("stall", start), Some(0));
+        assert_eq!(limiter.admit("stall", start + Duration::from_secs(10)), None);
+        assert_eq!(limiter.admit("depeg", start + Duration::from_secs(20)), Some(1));
+        assert_eq!(limiter.admit("kill switch", start + Duration::from_secs(30)), None);
+
+        // The burst window has passed, the dedup window has not
+        assert_eq!(limiter.admit("stall", start + Duration::from_secs(90)), None);
+        assert_eq!(limiter.admit("kill switch", start + Duration::from_secs(90)), Some(2));
+        assert_eq!(limiter.admit("stall", start + Duration::from_secs(301)), Some(0));
+    }
+
+    #[test]
+    fn test_zero_dedup_window_only_limits_the_rate() {
+        let limiter = NotificationLimiter::new(&RateLimitConfig { max_per_minute: 2, dedup_seconds: 0 });
+        let start = Instant::now();
+        assert_eq!(limiter.admit("stall", start), Some(0));
+        assert_eq!(limiter.admit("stall", start), Some(0));
+        assert_eq!(limiter.admit("stall", start), None);
+    }
+}
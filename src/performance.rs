@@ -0,0 +1,247 @@
+//! Week-over-week performance of strategies and paths.
+//!
+//! Shadow checks and live executions are grouped per strategy and path into
+//! weeks starting on Monday (UTC). Each path gets its weekly PnL, the change
+//! from the previous week and a least-squares trend, so that paths whose
+//! results are decaying stand out. A path that produced earlier in the
+//! window but nothing over its last weeks is flagged for retirement.
+
+use crate::arbitrage::risk::Strategy;
+use crate::metrics::strategy_label;
+use crate::report::ExecutionReport;
+use crate::shadow::{ShadowOutcome, ShadowRecord};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Where an outcome comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// Shadow execution, PnL as estimated at detection for filled checks
+    Shadow,
+    /// Executed orders, PnL as realized
+    Live,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Shadow => "shadow",
+            Source::Live => "live",
+        }
+    }
+}
+
+/// One shadow check or execution, reduced to what the report needs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Outcome {
+    pub source: Source,
+    pub strategy: Strategy,
+    pub path: String,
+    pub at: DateTime<Utc>,
+    /// Filled in shadow, completed live
+    pub succeeded: bool,
+    pub pnl_usd: Decimal,
+}
+
+impl Outcome {
+    pub fn from_shadow(record: &ShadowRecord) -> Self {
+        let filled = record.outcome == ShadowOutcome::Filled;
+        Self {
+            source: Source::Shadow,
+            strategy: record.strategy,
+            path: record.path.clone(),
+            at: record.checked_at,
+            succeeded: filled,
+            pnl_usd: if filled { record.estimated_profit_usd } else { Decimal::ZERO },
+        }
+    }
+
+    pub fn from_execution(report: &ExecutionReport) -> Self {
+        Self {
+            source: Source::Live,
+            strategy: report.strategy(),
+            path: report.path_label(),
+            at: report.executed_at,
+            succeeded: report.completed,
+            pnl_usd: report.realized_pnl_usd(),
+        }
+    }
+}
+
+/// Monday of the week `at` falls in.
+pub fn week_start(at: DateTime<Utc>) -> NaiveDate {
+    let date = at.date_naive();
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct WeekStats {
+    pub attempts: usize,
+    pub succeeded: usize,
+    pub pnl_usd: Decimal,
+}
+
+impl WeekStats {
+    fn add(&mut self, outcome: &Outcome) {
+        self.attempts += 1;
+        self.succeeded += outcome.succeeded as usize;
+        self.pnl_usd += outcome.pnl_usd;
+    }
+}
+
+/// Weekly results of one source, strategy and path, or of a whole
+/// strategy when `path` is `None`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Performance {
+    pub source: Source,
+    pub strategy: Strategy,
+    pub path: Option<String>,
+    /// One entry per report week, oldest first
+    pub weeks: Vec<WeekStats>,
+    /// PnL of the last week less that of the week before
+    pub change_usd: Decimal,
+    /// Least-squares slope of the weekly PnL, in USD per week
+    pub trend_usd_per_week: Decimal,
+    /// Produced earlier in the window, nothing over the last weeks
+    pub retire: bool,
+}
+
+impl Performance {
+    fn new(source: Source, strategy: Strategy, path: Option<String>, weeks: Vec<WeekStats>, retire_after: usize) -> Self {
+        let pnl: Vec<Decimal> = weeks.iter().map(|week| week.pnl_usd).collect();
+        let change_usd = match pnl.as_slice() {
+            [.., previous, last] => last - previous,
+            _ => Decimal::ZERO,
+        };
+        let recent = pnl.len().saturating_sub(retire_after);
+        let retire = path.is_some()
+            && recent > 0
+            && pnl[..recent].iter().any(|p| *p > Decimal::ZERO)
+            && pnl[recent..].iter().all(|p| *p <= Decimal::ZERO);
+        Self { source, strategy, path, change_usd, trend_usd_per_week: trend(&pnl), retire, weeks }
+    }
+
+    pub fn total_pnl_usd(&self) -> Decimal {
+        self.weeks.iter().map(|week| week.pnl_usd).sum()
+    }
+}
+
+/// Least-squares slope of `values` against their index.
+fn trend(values: &[Decimal]) -> Decimal {
+    if values.len() < 2 {
+        return Decimal::ZERO;
+    }
+    let n = Decimal::from(values.len());
+    let mean_x = Decimal::from(values.len() - 1) / Decimal::TWO;
+    let mean_y = values.iter().sum::<Decimal>() / n;
+    let (mut covariance, mut variance) = (Decimal::ZERO, Decimal::ZERO);
+    for (i, value) in values.iter().enumerate() {
+        let dx = Decimal::from(i) - mean_x;
+        covariance += dx * (value - mean_y);
+        variance += dx * dx;
+    }
+    (covariance / variance).round_dp(2)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PerformanceReport {
+    /// Monday of each report week, oldest first
+    pub weeks: Vec<NaiveDate>,
+    pub strategies: Vec<Performance>,
+    pub paths: Vec<Performance>,
+}
+
+/// Groups `outcomes` into the `weeks` weeks up to and including the one of
+/// `now`. Outcomes outside them are ignored. A path is flagged for
+/// retirement when none of its last `retire_after` weeks made a profit.
+pub fn weekly_report(outcomes: &[Outcome], now: DateTime<Utc>, weeks: usize, retire_after: usize) -> PerformanceReport {
+    let current = week_start(now);
+    let starts: Vec<NaiveDate> = (0..weeks).rev().map(|i| current - Duration::weeks(i as i64)).collect();
+
+    type Key = (Source, &'static str, Option<String>);
+    let mut groups: BTreeMap<Key, (Strategy, Vec<WeekStats>)> = BTreeMap::new();
+    for outcome in outcomes {
+        let Some(week) = starts.iter().position(|start| *start == week_start(outcome.at)) else { continue };
+        let strategy = strategy_label(outcome.strategy);
+        for path in [None, Some(outcome.path.clone())] {
+            let (_, stats) = groups.entry((outcome.source, strategy, path))
+                .or_insert_with(|| (outcome.strategy, vec![WeekStats::default(); weeks]));
+            stats[week].add(outcome);
+        }
+    }
+
+    let (mut strategies, mut paths) = (Vec::new(), Vec::new());
+    for ((source, _, path), (strategy, stats)) in groups {
+        let list = if path.is_some() { &mut paths } else { &mut strategies };
+        list.push(Performance::new(source, strategy, path, stats, retire_after));
+    }
+    PerformanceReport { weeks: starts, strategies, paths }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn outcome(source: Source, path: &str, days_ago: i64, pnl: i64) -> Outcome {
+        Outcome {
+            source,
+            strategy: Strategy::Triangular,
+            path: path.to_string(),
+            at: now() - Duration::days(days_ago),
+            succeeded: pnl > 0,
+            pnl_usd: Decimal::from(pnl),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        // A Wednesday
+        Utc.with_ymd_and_hms(2024, 5, 15, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_weeks_start_on_monday() {
+        assert_eq!(week_start(now()), NaiveDate::from_ymd_opt(2024, 5, 13).unwrap());
+        assert_eq!(week_start(Utc.with_ymd_and_hms(2024, 5, 19, 23, 59, 0).unwrap()), NaiveDate::from_ymd_opt(2024, 5, 13).unwrap());
+        assert_eq!(trend(&[Decimal::from(30), Decimal::from(20), Decimal::from(10)]), Decimal::from(-10));
+    }
+
+    #[test]
+    fn test_paths_are_compared_week_over_week() {
+        let outcomes = vec![
+            outcome(Source::Live, "ETHUSDT>ETHBTC>BTCUSDT", 21, 30),
+            outcome(Source::Live, "ETHUSDT>ETHBTC>BTCUSDT", 14, 20),
+            outcome(Source::Live, "ETHUSDT>ETHBTC>BTCUSDT", 7, -5),
+            outcome(Source::Live, "ETHUSDT>ETHBTC>BTCUSDT", 1, 0),
+            outcome(Source::Live, "SOLUSDT>SOLBTC>BTCUSDT", 2, 12),
+            outcome(Source::Shadow, "ETHUSDT>ETHBTC>BTCUSDT", 2, 4),
+            // Before the window
+            outcome(Source::Live, "SOLUSDT>SOLBTC>BTCUSDT", 60, 100),
+        ];
+        let report = weekly_report(&outcomes, now(), 4, 2);
+        assert_eq!(report.weeks.first(), Some(&NaiveDate::from_ymd_opt(2024, 4, 22).unwrap()));
+        assert_eq!(report.paths.len(), 3);
+
+        let decaying = &report.paths[0];
+        assert_eq!((decaying.source, decaying.path.as_deref()), (Source::Shadow, Some("ETHUSDT>ETHBTC>BTCUSDT")));
+        let decaying = &report.paths[1];
+        assert_eq!(decaying.path.as_deref(), Some("ETHUSDT>ETHBTC>BTCUSDT"));
+        assert_eq!(decaying.weeks.iter().map(|w| w.pnl_usd).collect::<Vec<_>>(),
+                   [30, 20, -5, 0].map(Decimal::from));
+        assert_eq!(decaying.change_usd, Decimal::from(5));
+        assert!(decaying.trend_usd_per_week < Decimal::ZERO);
+        assert!(decaying.retire);
+
+        let producing = &report.paths[2];
+        assert_eq!(producing.total_pnl_usd(), Decimal::from(12));
+        assert!(!producing.retire);
+
+        let live = &report.strategies[1];
+        assert_eq!((live.source, live.path.as_ref()), (Source::Live, None));
+        assert_eq!(live.weeks[3], WeekStats { attempts: 2, succeeded: 1, pnl_usd: Decimal::from(12) });
+        assert!(!live.retire);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::exchanges::{Balance, PriceMap};
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Assets treated as worth exactly one USD when marking to market.
+const USD_STABLECOINS: &[&str] = &["USDT", "USDC", "BUSD", "FDUSD", "DAI", "TUSD", "USD"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Holding {
+    pub exchange: String,
+    pub asset: String,
+    pub quantity: Decimal,
+    pub price_usd: Option<Decimal>,
+    pub value_usd: Decimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PortfolioSnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub holdings: Vec<Holding>,
+    pub total_usd: Decimal,
+    /// Assets held but with no USD route in the supplied prices
+    pub unpriced_assets: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EquityPoint {
+    pub timestamp: DateTime<Utc>,
+    pub total_usd: Decimal,
+}
+
+impl PortfolioSnapshot {
+    /// Values every balance in USD using `prices` for the matching exchange.
+    pub fn mark_to_market(balances: &[(&str, &[Balance], &PriceMap)]) -> Self {
+        let mut holdings = Vec::new();
+        let mut unpriced_assets = Vec::new();
+        
+        for (exchange, exchange_balances, prices) in balances {
+            for balance in exchange_balances.iter() {
+                let price_usd = usd_price(&balance.asset, prices);
+                if price_usd.is_none() {
+                    unpriced_assets.push(format!("{}:{}", exchange, balance.asset));
+                }
+                
+                holdings.push(Holding {
+                    exchange: exchange.to_string(),
+                    asset: balance.asset.clone(),
+                    quantity: balance.total(),
+                    price_usd,
+                    value_usd: price_usd.map(|p| p * balance.total()).unwrap_or(Decimal::ZERO),
+                });
+            }
+        }
+        
+        Self {
+            timestamp: Utc::now(),
+            total_usd: holdings.iter().map(|h| h.value_usd).sum(),
+            holdings,
+            unpriced_assets,
+        }
+    }
+}
+
+/// USD price of `asset`: stablecoins are pegged at 1, otherwise the direct
+/// USDT/USDC market is used, falling back to routing through BTC.
+pub fn usd_price(asset: &str, prices: &PriceMap) -> Option<Decimal> {
+    if USD_STABLECOINS.contains(&asset) {
+        return Some(Decimal::ONE);
+    }
+    
+    for quote in ["USDT", "USDC"] {
+        if let Some(price) = prices.get(&format!("{}{}", asset, quote)) {
+            return Some(*price);
+        }
+    }
+    
+    let btc_usd = prices.get("BTCUSDT")?;
+    prices.get(&format!("{}BTC", asset)).map(|p| p * btc_usd)
+}
+
+/// Persists portfolio snapshots and serves the resulting equity curve.
+#[derive(Debug, Clone)]
+pub struct PortfolioStore {
+    store: LocalStore,
+}
+
+impl PortfolioStore {
+    pub fn new(store: LocalStore) -> Self {
+        Self { store }
+    }
+    
+    pub fn record(&self, snapshot: &PortfolioSnapshot) -> Result<()> {
+        self.store.append_jsonl(Path::new("portfolio/snapshots.jsonl"), std::slice::from_ref(snapshot))
+    }
+    
+    pub fn snapshots(&self) -> Result<Vec<PortfolioSnapshot>> {
+        self.store.read_jsonl(Path::new("portfolio/snapshots.jsonl"))
+    }
+    
+    pub fn equity_curve(&self, since: DateTime<Utc>) -> Result<Vec<EquityPoint>> {
+        Ok(self.snapshots()?
+            .into_iter()
+            .filter(|s| s.timestamp >= since)
+            .map(|s| EquityPoint { timestamp: s.timestamp, total_usd: s.total_usd })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn balance(asset: &str, free: i64) -> Balance {
+        Balance { asset: asset.to_string(), free: Decimal::from(free), locked: Decimal::ZERO }
+    }
+    
+    #[test]
+    fn test_mark_to_market() {
+        let mut prices = PriceMap::new();
+        prices.insert("BTCUSDT".to_string(), Decimal::from(50000));
+        prices.insert("ETHUSDT".to_string(), Decimal::from(3000));
+        prices.insert("XYZBTC".to_string(), Decimal::from_str_exact("0.0001").unwrap());
+        
+        let binance = vec![balance("USDT", 1000), balance("ETH", 2), balance("XYZ", 10)];
+        let bybit = vec![balance("BTC", 1), balance("NOPE", 5)];
+        
+        let snapshot = PortfolioSnapshot::mark_to_market(&[
+            ("Binance", &binance, &prices),
+            ("Bybit", &bybit, &prices),
+        ]);
+        
+        // 1000 + 6000 + 10 * 5 + 50000
+        assert_eq!(snapshot.total_usd, Decimal::from(57050));
+        assert_eq!(snapshot.unpriced_assets, vec!["Bybit:NOPE".to_string()]);
+        assert_eq!(snapshot.holdings.len(), 5);
+    }
+    
+    #[test]
+    fn test_equity_curve_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = PortfolioStore::new(LocalStore::new(dir.path()).unwrap());
+        let prices = PriceMap::new();
+        let balances = vec![balance("USDT", 250)];
+        
+        store.record(&PortfolioSnapshot::mark_to_market(&[("Binance", &balances, &prices)])).unwrap();
+        
+        let curve = store.equity_curve(Utc::now() - chrono::Duration::hours(1)).unwrap();
+        assert_eq!(curve.len(), 1);
+        assert_eq!(curve[0].total_usd, Decimal::from(250));
+    }
+}
@@ -0,0 +1,210 @@
+use dashmap::DashMap;
+use rust_decimal::{Decimal, RoundingStrategy};
+use std::collections::HashMap;
+
+/// Lot size assumed for symbols without exchange metadata: the 8 decimals
+/// both venues accept for any spot quantity.
+pub const DEFAULT_QUANTITY_STEP: Decimal = Decimal::from_parts(1, 0, 0, false, 8);
+
+/// Decimal places shown for prices of symbols without tick size metadata,
+/// chosen by magnitude so both BTC and sub-cent tokens stay readable.
+fn fallback_decimals(price: Decimal) -> u32 {
+    let price = price.abs();
+    if price >= Decimal::from(1000) {
+        2
+    } else if price >= Decimal::ONE {
+        4
+    } else {
+        8
+    }
+}
+
+/// Which way a value that falls between two exchange increments goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rounding {
+    Down,
+    Up,
+    Nearest,
+}
+
+impl Rounding {
+    fn strategy(self) -> RoundingStrategy {
+        match self {
+            Rounding::Down => RoundingStrategy::ToNegativeInfinity,
+            Rounding::Up => RoundingStrategy::ToPositiveInfinity,
+            Rounding::Nearest => RoundingStrategy::MidpointAwayFromZero,
+        }
+    }
+}
+
+/// `price` on a multiple of `tick`, normalized so it prints without
+/// trailing zeros. A non-positive tick leaves the price untouched.
+pub fn quantize(price: Decimal, tick: Decimal, rounding: Rounding) -> Decimal {
+    if tick <= Decimal::ZERO {
+        return price;
+    }
+    ((price / tick).round_dp_with_strategy(0, rounding.strategy()) * tick).normalize()
+}
+
+/// `quantity` on a multiple of the lot `step`. Never negative, so a
+/// quantity smaller than one step rounded down becomes zero.
+pub fn quantize_qty(quantity: Decimal, step: Decimal, rounding: Rounding) -> Decimal {
+    quantize(quantity, step, rounding).max(Decimal::ZERO)
+}
+
+fn insert_increments(map: &DashMap<String, Decimal>, exchange: &str, increments: HashMap<String, Decimal>) {
+    for (symbol, increment) in increments {
+        if increment > Decimal::ZERO {
+            map.insert(format!("{}:{}", exchange, symbol), increment);
+        }
+    }
+}
+
+fn snapshot(map: &DashMap<String, Decimal>) -> HashMap<String, HashMap<String, Decimal>> {
+    let mut snapshot: HashMap<String, HashMap<String, Decimal>> = HashMap::new();
+    for entry in map.iter() {
+        if let Some((exchange, symbol)) = entry.key().split_once(':') {
+            snapshot.entry(exchange.to_string()).or_default().insert(symbol.to_string(), *entry.value());
+        }
+    }
+    snapshot
+}
+
+/// Tick and lot sizes per `exchange:symbol`, used to print prices with the
+/// precision the exchange actually quotes instead of Decimal's full scale,
+/// and to put prices and quantities on the grid the exchange accepts.
+/// Shared by the engine, the executor and report output.
+#[derive(Debug, Default)]
+pub struct PricePrecision {
+    tick_sizes: DashMap<String, Decimal>,
+    step_sizes: DashMap<String, Decimal>,
+}
+
+impl PricePrecision {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn set_tick_sizes(&self, exchange: &str, tick_sizes: HashMap<String, Decimal>) {
+        insert_increments(&self.tick_sizes, exchange, tick_sizes);
+    }
+    
+    pub fn set_step_sizes(&self, exchange: &str, step_sizes: HashMap<String, Decimal>) {
+        insert_increments(&self.step_sizes, exchange, step_sizes);
+    }
+    
+    /// Tick sizes per exchange, for warm-start persistence.
+    pub fn tick_sizes_snapshot(&self) -> HashMap<String, HashMap<String, Decimal>> {
+        snapshot(&self.tick_sizes)
+    }
+    
+    /// Lot sizes per exchange, for warm-start persistence.
+    pub fn step_sizes_snapshot(&self) -> HashMap<String, HashMap<String, Decimal>> {
+        snapshot(&self.step_sizes)
+    }
+    
+    /// Lot size of the symbol, or `DEFAULT_QUANTITY_STEP` when the exchange
+    /// info has not been loaded.
+    pub fn step_size(&self, exchange: &str, symbol: &str) -> Decimal {
+        self.step_sizes.get(&format!("{}:{}", exchange, symbol))
+            .map(|step| *step)
+            .unwrap_or(DEFAULT_QUANTITY_STEP)
+    }
+    
+    /// Decimal places of the symbol's tick size, or a magnitude-based
+    /// default when the exchange info has not been loaded.
+    pub fn decimals(&self, exchange: &str, symbol: &str, price: Decimal) -> u32 {
+        self.tick_sizes.get(&format!("{}:{}", exchange, symbol))
+            .map(|tick| tick.normalize().scale())
+            .unwrap_or_else(|| fallback_decimals(price))
+    }
+    
+    /// `price` on the symbol's tick grid, or at the magnitude-based number
+    /// of decimals when the tick size is unknown.
+    pub fn quantize_price(&self, exchange: &str, symbol: &str, price: Decimal, rounding: Rounding) -> Decimal {
+        match self.tick_sizes.get(&format!("{}:{}", exchange, symbol)) {
+            Some(tick) => quantize(price, *tick, rounding),
+            None => price.round_dp_with_strategy(fallback_decimals(price), rounding.strategy()).normalize(),
+        }
+    }
+    
+    /// `quantity` rounded down to the symbol's lot size, so an order never
+    /// asks for more than the balance or the previous leg delivered.
+    pub fn quantize_qty(&self, exchange: &str, symbol: &str, quantity: Decimal) -> Decimal {
+        quantize_qty(quantity, self.step_size(exchange, symbol), Rounding::Down)
+    }
+    
+    pub fn format_price(&self, exchange: &str, symbol: &str, price: Decimal) -> String {
+        let decimals = self.decimals(exchange, symbol, price) as usize;
+        format!("{:.*}", decimals, price.round_dp(decimals as u32))
+    }
+}
+
+/// Percentages are shown to four places, enough for basis-point spreads.
+pub fn format_percentage(value: Decimal) -> String {
+    format!("{:.4}", value.round_dp(4))
+}
+
+pub fn format_usd(value: Decimal) -> String {
+    format!("{:.2}", value.round_dp(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+    
+    #[test]
+    fn test_formats_to_tick_size() {
+        let precision = PricePrecision::new();
+        precision.set_tick_sizes("Binance", HashMap::from([
+            ("BTCUSDT".to_string(), dec("0.01000000")),
+            ("SHIBUSDT".to_string(), dec("0.00000001")),
+        ]));
+        
+        assert_eq!(precision.format_price("Binance", "BTCUSDT", dec("64123.456789123456789")), "64123.46");
+        assert_eq!(precision.format_price("Binance", "BTCUSDT", dec("64123")), "64123.00");
+        assert_eq!(precision.format_price("Binance", "SHIBUSDT", dec("0.0000245678912")), "0.00002457");
+    }
+    
+    #[test]
+    fn test_falls_back_to_magnitude() {
+        let precision = PricePrecision::new();
+        assert_eq!(precision.format_price("Bybit", "BTCUSDT", dec("64123.456789")), "64123.46");
+        assert_eq!(precision.format_price("Bybit", "ADAUSDT", dec("0.45678912345")), "0.45678912");
+        assert_eq!(precision.format_price("Bybit", "ETHBTC", dec("0.0512345678")), "0.05123457");
+        assert_eq!(format_percentage(dec("0.123456789")), "0.1235");
+        assert_eq!(format_usd(dec("12.3456")), "12.35");
+    }
+    
+    #[test]
+    fn test_quantize_rounds_onto_the_grid() {
+        assert_eq!(quantize(dec("64123.456"), dec("0.5"), Rounding::Down), dec("64123"));
+        assert_eq!(quantize(dec("64123.456"), dec("0.5"), Rounding::Up), dec("64123.5"));
+        assert_eq!(quantize(dec("64123.25"), dec("0.5"), Rounding::Nearest), dec("64123.5"));
+        assert_eq!(quantize(dec("0.1"), Decimal::ZERO, Rounding::Down), dec("0.1"));
+        
+        // 100 / 3 has 28 significant digits; the order carries 6 decimals
+        let quantity = Decimal::from(100) / Decimal::from(3);
+        assert_eq!(quantize_qty(quantity, dec("0.000001"), Rounding::Down).to_string(), "33.333333");
+        assert_eq!(quantize_qty(dec("0.0004"), dec("0.001"), Rounding::Down), Decimal::ZERO);
+        assert_eq!(quantize_qty(dec("-0.0004"), dec("0.001"), Rounding::Down), Decimal::ZERO);
+    }
+    
+    #[test]
+    fn test_quantizes_to_exchange_increments() {
+        let precision = PricePrecision::new();
+        precision.set_tick_sizes("Binance", HashMap::from([("BTCUSDT".to_string(), dec("0.01000000"))]));
+        precision.set_step_sizes("Binance", HashMap::from([("BTCUSDT".to_string(), dec("0.00001000"))]));
+        
+        assert_eq!(precision.quantize_price("Binance", "BTCUSDT", dec("64123.4567"), Rounding::Down), dec("64123.45"));
+        assert_eq!(precision.quantize_price("Binance", "BTCUSDT", dec("64123.4512"), Rounding::Up), dec("64123.46"));
+        assert_eq!(precision.quantize_price("Bybit", "ADAUSDT", dec("0.456789129"), Rounding::Up), dec("0.45678913"));
+        assert_eq!(precision.quantize_qty("Binance", "BTCUSDT", dec("0.0153846153846")), dec("0.01538"));
+        assert_eq!(precision.quantize_qty("Bybit", "BTCUSDT", dec("0.0153846153846")), dec("0.01538461"));
+        assert_eq!(precision.step_sizes_snapshot()["Binance"]["BTCUSDT"], dec("0.00001"));
+    }
+}
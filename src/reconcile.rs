@@ -0,0 +1,340 @@
+use crate::exchanges::{AccountTrade, Balance, HistoricalOrder, OpenOrder, OrderSide};
+use crate::portfolio::PortfolioSnapshot;
+use crate::symbols::split_symbol;
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Prefix of the client order IDs `new_client_order_id` generates.
+const BOT_ORDER_PREFIX: &str = "arb";
+
+/// Open orders and balances of one exchange as fetched on startup.
+#[derive(Debug, Clone)]
+pub struct ExchangeState {
+    pub exchange: String,
+    pub open_orders: Vec<OpenOrder>,
+    pub balances: Vec<Balance>,
+    /// Orders of the journaled symbols since the snapshot, when fetched
+    pub order_history: Vec<HistoricalOrder>,
+    /// Fills of those orders since the snapshot
+    pub trades: Vec<AccountTrade>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciledOrder {
+    pub exchange: String,
+    pub symbol: String,
+    pub client_order_id: String,
+    pub executed_quantity: Decimal,
+    /// Journaled execution that submitted the order, if any
+    pub execution_key: Option<String>,
+    pub canceled: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BalanceDiscrepancy {
+    pub exchange: String,
+    pub asset: String,
+    /// Quantity in the last portfolio snapshot; `None` when it held none
+    pub expected: Option<Decimal>,
+    pub actual: Decimal,
+    pub locked: Decimal,
+    pub reason: String,
+}
+
+/// What startup reconciliation found before the scan loop started.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReconciliationReport {
+    pub checked_at: DateTime<Utc>,
+    /// Orders the bot placed that no running execution owns
+    pub orphaned_orders: Vec<ReconciledOrder>,
+    /// Orders placed by someone else, e.g. manually; never canceled
+    pub foreign_orders: Vec<ReconciledOrder>,
+    pub balance_discrepancies: Vec<BalanceDiscrepancy>,
+}
+
+impl ReconciliationReport {
+    pub fn is_clean(&self) -> bool {
+        self.orphaned_orders.is_empty() && self.foreign_orders.is_empty() && self.balance_discrepancies.is_empty()
+    }
+
+    pub fn log(&self) {
+        if self.is_clean() {
+            info!("Startup reconciliation found no open orders or balance discrepancies");
+            return;
+        }
+        for order in &self.orphaned_orders {
+            warn!("Orphaned order {} {} on {} (execution {}), filled {}, {}",
+                  order.client_order_id, order.symbol, order.exchange,
+                  order.execution_key.as_deref().unwrap_or("unknown"), order.executed_quantity.normalize(),
+                  if order.canceled { "canceled" } else { "left open" });
+        }
+        for order in &self.foreign_orders {
+            warn!("Open order {} {} on {} was not placed by the bot, left open", order.client_order_id, order.symbol, order.exchange);
+        }
+        for discrepancy in &self.balance_discrepancies {
+            warn!("Balance discrepancy on {} {}: {} (expected {}, actual {}, locked {})",
+                  discrepancy.exchange, discrepancy.asset, discrepancy.reason,
+                  discrepancy.expected.map(|e| e.normalize().to_string()).unwrap_or_else(|| "none".to_string()),
+                  discrepancy.actual.normalize(), discrepancy.locked.normalize());
+        }
+    }
+}
+
+/// Sorts every open order into orphaned (bot-placed, per the client order
+/// ID prefix or the journal) or foreign, and compares balances with the
+/// last portfolio snapshot moved by the bot's own fills since. Call after
+/// crash recovery, when no execution is running, so every bot order still
+/// open is orphaned.
+pub fn reconcile(
+    states: &[ExchangeState],
+    order_owners: &HashMap<String, String>,
+    expected: Option<&PortfolioSnapshot>,
+    tolerance_percentage: Decimal,
+) -> ReconciliationReport {
+    let mut report = ReconciliationReport {
+        checked_at: Utc::now(),
+        orphaned_orders: Vec::new(),
+        foreign_orders: Vec::new(),
+        balance_discrepancies: Vec::new(),
+    };
+
+    for state in states {
+        for order in &state.open_orders {
+            let client_order_id = &order.state.client_order_id;
+            let execution_key = order_owners.get(client_order_id).cloned();
+            let reconciled = ReconciledOrder {
+                exchange: state.exchange.clone(),
+                symbol: order.symbol.clone(),
+                client_order_id: client_order_id.clone(),
+                executed_quantity: order.state.executed_quantity,
+                canceled: false,
+                execution_key,
+            };
+            if reconciled.execution_key.is_some() || client_order_id.starts_with(BOT_ORDER_PREFIX) {
+                report.orphaned_orders.push(reconciled);
+            } else {
+                report.foreign_orders.push(reconciled);
+            }
+        }
+
+        for balance in state.balances.iter().filter(|b| b.locked > Decimal::ZERO) {
+            if state.open_orders.is_empty() {
+                report.balance_discrepancies.push(BalanceDiscrepancy {
+                    exchange: state.exchange.clone(),
+                    asset: balance.asset.clone(),
+                    expected: None,
+                    actual: balance.total(),
+                    locked: balance.locked,
+                    reason: "locked with no open orders".to_string(),
+                });
+            }
+        }
+
+        let Some(snapshot) = expected else { continue };
+        let mut held: BTreeMap<String, Decimal> = BTreeMap::new();
+        for holding in snapshot.holdings.iter().filter(|h| h.exchange.eq_ignore_ascii_case(&state.exchange)) {
+            *held.entry(holding.asset.clone()).or_default() += holding.quantity;
+        }
+        apply_bot_fills(state, order_owners, &mut held);
+        let mut actual: BTreeMap<&str, &Balance> = state.balances.iter().map(|b| (b.asset.as_str(), b)).collect();
+
+        for (asset, expected) in held {
+            let balance = actual.remove(asset.as_str());
+            let quantity = balance.map(Balance::total).unwrap_or(Decimal::ZERO);
+            let tolerance = expected * tolerance_percentage / Decimal::from(100);
+            if (quantity - expected).abs() > tolerance {
+                report.balance_discrepancies.push(BalanceDiscrepancy {
+                    exchange: state.exchange.clone(),
+                    asset,
+                    expected: Some(expected),
+                    actual: quantity,
+                    locked: balance.map(|b| b.locked).unwrap_or(Decimal::ZERO),
+                    reason: format!("changed since the snapshot at {}", snapshot.timestamp),
+                });
+            }
+        }
+        for balance in actual.into_values() {
+            report.balance_discrepancies.push(BalanceDiscrepancy {
+                exchange: state.exchange.clone(),
+                asset: balance.asset.clone(),
+                expected: None,
+                actual: balance.total(),
+                locked: balance.locked,
+                reason: format!("not held at the snapshot at {}", snapshot.timestamp),
+            });
+        }
+    }
+    report
+}
+
+/// Moves `held` by the fills of the bot's orders in `state`'s history, so
+/// trading since the snapshot is not reported as a discrepancy.
+fn apply_bot_fills(state: &ExchangeState, order_owners: &HashMap<String, String>, held: &mut BTreeMap<String, Decimal>) {
+    let bot_orders: HashSet<&str> = state.order_history.iter()
+        .filter(|order| {
+            let client_order_id = &order.state.client_order_id;
+            order_owners.contains_key(client_order_id) || client_order_id.starts_with(BOT_ORDER_PREFIX)
+        })
+        .filter_map(|order| order.state.exchange_order_id.as_deref())
+        .collect();
+    for trade in state.trades.iter().filter(|t| bot_orders.contains(t.order_id.as_str())) {
+        let Some((base, quote)) = split_symbol(&trade.symbol) else {
+            warn!("Cannot split {} into assets, its fill {} is not counted", trade.symbol, trade.trade_id);
+            continue;
+        };
+        let notional = trade.quantity * trade.price;
+        let (base_change, quote_change) = match trade.side {
+            OrderSide::Buy => (trade.quantity, -notional),
+            OrderSide::Sell => (-trade.quantity, notional),
+        };
+        *held.entry(base.to_string()).or_default() += base_change;
+        *held.entry(quote.to_string()).or_default() += quote_change;
+        *held.entry(trade.fee_asset.clone()).or_default() -= trade.fee;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::{OrderState, OrderStatus};
+    use crate::portfolio::Holding;
+
+    fn open_order(symbol: &str, client_order_id: &str) -> OpenOrder {
+        OpenOrder {
+            symbol: symbol.to_string(),
+            state: OrderState {
+                client_order_id: client_order_id.to_string(),
+                exchange_order_id: None,
+                status: OrderStatus::PartiallyFilled,
+                executed_quantity: Decimal::ONE,
+                average_price: None,
+            },
+        }
+    }
+
+    fn balance(asset: &str, free: i64, locked: i64) -> Balance {
+        Balance { asset: asset.to_string(), free: Decimal::from(free), locked: Decimal::from(locked) }
+    }
+
+    fn holding(exchange: &str, asset: &str, quantity: i64) -> Holding {
+        Holding {
+            exchange: exchange.to_string(),
+            asset: asset.to_string(),
+            quantity: Decimal::from(quantity),
+            price_usd: None,
+            value_usd: Decimal::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_bot_orders_are_orphaned_and_others_foreign() {
+        let states = vec![ExchangeState {
+            exchange: "Binance".to_string(),
+            open_orders: vec![
+                open_order("ETHBTC", "arb0123"),
+                open_order("BTCUSDT", "legacy-id"),
+                open_order("SOLUSDT", "web_manual"),
+            ],
+            balances: Vec::new(),
+            order_history: Vec::new(),
+            trades: Vec::new(),
+        }];
+        let owners = HashMap::from([("legacy-id".to_string(), "key1".to_string())]);
+
+        let report = reconcile(&states, &owners, None, Decimal::ONE);
+
+        let orphaned: Vec<(&str, Option<&str>)> = report.orphaned_orders.iter()
+            .map(|o| (o.client_order_id.as_str(), o.execution_key.as_deref()))
+            .collect();
+        assert_eq!(orphaned, vec![("arb0123", None), ("legacy-id", Some("key1"))]);
+        assert_eq!(report.foreign_orders.len(), 1);
+        assert_eq!(report.foreign_orders[0].symbol, "SOLUSDT");
+    }
+
+    #[test]
+    fn test_balances_are_compared_with_the_snapshot() {
+        let snapshot = PortfolioSnapshot {
+            timestamp: Utc::now(),
+            holdings: vec![
+                holding("Binance", "USDT", 1000),
+                holding("Binance", "BTC", 2),
+                holding("Bybit", "USDT", 500),
+            ],
+            total_usd: Decimal::ZERO,
+            unpriced_assets: Vec::new(),
+        };
+        let states = vec![ExchangeState {
+            exchange: "Binance".to_string(),
+            open_orders: Vec::new(),
+            // USDT within 1%, BTC gone, ETH new and locked without an order
+            balances: vec![balance("USDT", 995, 0), balance("ETH", 0, 3)],
+            order_history: Vec::new(),
+            trades: Vec::new(),
+        }];
+
+        let report = reconcile(&states, &HashMap::new(), Some(&snapshot), Decimal::ONE);
+
+        let found: Vec<(&str, Option<Decimal>, Decimal)> = report.balance_discrepancies.iter()
+            .map(|d| (d.asset.as_str(), d.expected, d.actual))
+            .collect();
+        assert_eq!(found, vec![
+            ("ETH", None, Decimal::from(3)),
+            ("BTC", Some(Decimal::from(2)), Decimal::ZERO),
+            ("ETH", None, Decimal::from(3)),
+        ]);
+        assert!(report.balance_discrepancies[0].reason.contains("no open orders"));
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_bot_fills_since_the_snapshot_are_expected() {
+        let snapshot = PortfolioSnapshot {
+            timestamp: Utc::now(),
+            holdings: vec![holding("Binance", "USDT", 1000)],
+            total_usd: Decimal::ZERO,
+            unpriced_assets: Vec::new(),
+        };
+        let order = |client_order_id: &str, exchange_order_id: &str| HistoricalOrder {
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            state: OrderState { exchange_order_id: Some(exchange_order_id.to_string()), ..open_order("BTCUSDT", client_order_id).state },
+            created_at: snapshot.timestamp,
+        };
+        let trade = |order_id: &str, quantity: &str| AccountTrade {
+            symbol: "BTCUSDT".to_string(),
+            trade_id: format!("t{}", order_id),
+            order_id: order_id.to_string(),
+            side: OrderSide::Buy,
+            price: Decimal::from(50000),
+            quantity: Decimal::from_str_exact(quantity).unwrap(),
+            fee: Decimal::from_str_exact("0.00001").unwrap(),
+            fee_asset: "BTC".to_string(),
+            timestamp: snapshot.timestamp,
+        };
+        let states = vec![ExchangeState {
+            exchange: "Binance".to_string(),
+            open_orders: Vec::new(),
+            // The bot bought 0.01 BTC; a manual 0.002 BTC buy is not counted
+            balances: vec![balance("USDT", 400, 0), Balance {
+                asset: "BTC".to_string(),
+                free: Decimal::from_str_exact("0.01199").unwrap(),
+                locked: Decimal::ZERO,
+            }],
+            order_history: vec![order("arb01", "1"), order("web_manual", "2")],
+            trades: vec![trade("1", "0.01"), trade("2", "0.002")],
+        }];
+
+        let report = reconcile(&states, &HashMap::new(), Some(&snapshot), Decimal::ONE);
+
+        let found: Vec<(&str, Option<Decimal>)> = report.balance_discrepancies.iter()
+            .map(|d| (d.asset.as_str(), d.expected.map(|e| e.normalize())))
+            .collect();
+        assert_eq!(found, vec![
+            ("BTC", Some(Decimal::from_str_exact("0.00999").unwrap())),
+            ("USDT", Some(Decimal::from(500))),
+        ]);
+    }
+}
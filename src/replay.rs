@@ -0,0 +1,210 @@
+use crate::arbitrage::risk::Strategy;
+use crate::arbitrage::{ArbitrageEngine, ArbitrageOpportunity};
+use crate::config::Config;
+use crate::exchanges::PriceMap;
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Identity and headline numbers of an opportunity, kept with each
+/// snapshot so a replay can tell which past findings still hold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpportunitySummary {
+    pub exchange: String,
+    pub strategy: Strategy,
+    pub path: Vec<String>,
+    pub net_profit_percentage: Decimal,
+    pub risk_score: f32,
+}
+
+impl OpportunitySummary {
+    pub fn key(&self) -> String {
+        format!("{}:{}", self.exchange, self.path.join(">"))
+    }
+}
+
+impl From<&ArbitrageOpportunity> for OpportunitySummary {
+    fn from(opportunity: &ArbitrageOpportunity) -> Self {
+        Self {
+            exchange: opportunity.exchange.clone(),
+            strategy: opportunity.strategy,
+            path: opportunity.path.clone(),
+            net_profit_percentage: opportunity.net_profit_percentage,
+            risk_score: opportunity.risk_score,
+        }
+    }
+}
+
+/// The filtered ticker prices of one scan and what the engine found in them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketSnapshot {
+    pub captured_at: DateTime<Utc>,
+    pub binance: PriceMap,
+    pub bybit: PriceMap,
+    pub opportunities: Vec<OpportunitySummary>,
+}
+
+/// Market snapshots stored as one JSON-lines file per UTC day.
+#[derive(Debug, Clone)]
+pub struct SnapshotStore {
+    store: LocalStore,
+}
+
+impl SnapshotStore {
+    pub fn new(store: LocalStore) -> Self {
+        Self { store }
+    }
+    
+    pub fn record(&self, snapshot: &MarketSnapshot) -> Result<()> {
+        self.store.append_jsonl(&Self::day_path(snapshot.captured_at.date_naive()), std::slice::from_ref(snapshot))
+    }
+    
+    pub fn load_day(&self, date: NaiveDate) -> Result<Vec<MarketSnapshot>> {
+        self.store.read_jsonl(&Self::day_path(date))
+    }
+    
+    /// Snapshots of every day from `from` through `to`, in capture order.
+    pub fn load_range(&self, from: NaiveDate, to: NaiveDate) -> Result<Vec<MarketSnapshot>> {
+        let mut snapshots = Vec::new();
+        for date in from.iter_days().take_while(|d| *d <= to) {
+            snapshots.extend(self.load_day(date)?);
+        }
+        snapshots.sort_by_key(|s| s.captured_at);
+        Ok(snapshots)
+    }
+    
+    /// Days with recorded snapshots, oldest first.
+    pub fn days(&self) -> Result<Vec<NaiveDate>> {
+        Ok(self.store.keys(Path::new("snapshots"))?.iter()
+            .filter_map(|key| key.file_stem()?.to_str()?.parse().ok())
+            .collect())
+    }
+    
+    /// Rewrites the snapshots of `date`, as retention does when downsampling.
+    pub fn replace_day(&self, date: NaiveDate, snapshots: &[MarketSnapshot]) -> Result<()> {
+        self.store.replace_jsonl(&Self::day_path(date), snapshots)
+    }
+    
+    pub fn remove_day(&self, date: NaiveDate) -> Result<()> {
+        self.store.remove(&Self::day_path(date))
+    }
+    
+    fn day_path(date: NaiveDate) -> PathBuf {
+        PathBuf::from("snapshots").join(format!("{}.jsonl", date.format("%Y-%m-%d")))
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub snapshots: usize,
+    /// Distinct opportunities found at the time
+    pub historical: usize,
+    /// Historical opportunities the current configuration still finds
+    pub still_passing: Vec<OpportunitySummary>,
+    /// Historical opportunities the current configuration rejects
+    pub rejected: Vec<OpportunitySummary>,
+    /// Opportunities only the current configuration finds
+    pub new: Vec<OpportunitySummary>,
+}
+
+/// Re-runs the engine built from `config` over every snapshot recorded on
+/// `date`, in capture order, and compares its findings with the stored ones.
+/// Opportunities are matched by exchange and path; for each, the best
+/// historical and replayed variant is reported.
+pub async fn replay_day(config: &Config, store: &SnapshotStore, date: NaiveDate) -> Result<ReplayReport> {
+    let mut snapshots = store.load_day(date)?;
+    snapshots.sort_by_key(|s| s.captured_at);
+    
+    let engine = ArbitrageEngine::from_config(config);
+    let mut historical: HashMap<String, OpportunitySummary> = HashMap::new();
+    let mut replayed: HashMap<String, OpportunitySummary> = HashMap::new();
+    
+    for snapshot in &snapshots {
+        for opportunity in &snapshot.opportunities {
+            keep_best(&mut historical, opportunity.clone());
+        }
+        for opportunity in engine.analyze_opportunities(&snapshot.binance, &snapshot.bybit).await? {
+            keep_best(&mut replayed, OpportunitySummary::from(&opportunity));
+        }
+    }
+    
+    let historical_keys: HashSet<&String> = historical.keys().collect();
+    let mut report = ReplayReport {
+        snapshots: snapshots.len(),
+        historical: historical.len(),
+        ..ReplayReport::default()
+    };
+    for (key, opportunity) in &historical {
+        match replayed.get(key) {
+            Some(current) => report.still_passing.push(current.clone()),
+            None => report.rejected.push(opportunity.clone()),
+        }
+    }
+    report.new = replayed.into_iter()
+        .filter(|(key, _)| !historical_keys.contains(key))
+        .map(|(_, opportunity)| opportunity)
+        .collect();
+    
+    for list in [&mut report.still_passing, &mut report.rejected, &mut report.new] {
+        list.sort_by_key(|o| std::cmp::Reverse(o.net_profit_percentage));
+    }
+    Ok(report)
+}
+
+pub(crate) fn keep_best(best: &mut HashMap<String, OpportunitySummary>, opportunity: OpportunitySummary) {
+    let key = opportunity.key();
+    match best.get(&key) {
+        Some(existing) if existing.net_profit_percentage >= opportunity.net_profit_percentage => {}
+        _ => {
+            best.insert(key, opportunity);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn summary(path: &str, profit: i64) -> OpportunitySummary {
+        OpportunitySummary {
+            exchange: "Binance-Bybit".to_string(),
+            strategy: Strategy::CrossExchange,
+            path: vec![path.to_string()],
+            net_profit_percentage: Decimal::from(profit),
+            risk_score: 0.3,
+        }
+    }
+    
+    #[test]
+    fn test_snapshots_are_stored_per_day() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = SnapshotStore::new(LocalStore::new(dir.path()).unwrap());
+        let captured_at = "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        store.record(&MarketSnapshot {
+            captured_at,
+            binance: PriceMap::from([("BTCUSDT".to_string(), Decimal::from(60000))]),
+            bybit: PriceMap::new(),
+            opportunities: vec![summary("BTCUSDT", 1)],
+        }).unwrap();
+        
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        assert_eq!(store.load_day(day).unwrap().len(), 1);
+        assert!(store.load_day(day.succ_opt().unwrap()).unwrap().is_empty());
+    }
+    
+    #[test]
+    fn test_keep_best_by_profit() {
+        let mut best = HashMap::new();
+        keep_best(&mut best, summary("BTCUSDT", 1));
+        keep_best(&mut best, summary("BTCUSDT", 3));
+        keep_best(&mut best, summary("BTCUSDT", 2));
+        keep_best(&mut best, summary("ETHUSDT", 1));
+        
+        assert_eq!(best.len(), 2);
+        assert_eq!(best[&summary("BTCUSDT", 0).key()].net_profit_percentage, Decimal::from(3));
+    }
+}
@@ -0,0 +1,326 @@
+use crate::arbitrage::risk::Strategy;
+use crate::arbitrage::{ArbitrageOpportunity, ExecutionStep};
+use crate::executor::{ExecutionResult, LegFill};
+use crate::exchanges::OrderSide;
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Expected versus actual outcome of one execution step.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegReport {
+    pub exchange: String,
+    pub symbol: String,
+    pub side: String,
+    pub expected_quantity: Decimal,
+    pub filled_quantity: Decimal,
+    pub expected_price: Decimal,
+    pub actual_price: Option<Decimal>,
+    pub expected_fees: Decimal,
+    /// Expected fee rate applied to the filled notional; order queries do
+    /// not return the commission actually charged
+    pub estimated_fees: Decimal,
+    /// Adverse price movement versus the expected price in basis points,
+    /// positive when the fill was worse than expected
+    pub slippage_bps: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub key: String,
+    pub exchange: String,
+    pub path: Vec<String>,
+    pub executed_at: DateTime<Utc>,
+    pub completed: bool,
+    pub abandon_reason: Option<String>,
+    pub expected_profit_percentage: Decimal,
+    pub legs: Vec<LegReport>,
+}
+
+/// Aggregate slippage over a set of execution reports. Slippage is not in
+/// the scanner's net profit estimate, so `p95_cycle_slippage_percentage`
+/// is the margin `min_profit_threshold` needs on top of fees.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SlippageStats {
+    pub executions: usize,
+    pub legs: usize,
+    pub mean_leg_slippage_bps: Decimal,
+    pub max_leg_slippage_bps: Decimal,
+    pub mean_cycle_slippage_percentage: Decimal,
+    pub p95_cycle_slippage_percentage: Decimal,
+}
+
+impl LegReport {
+    fn new(step: &ExecutionStep, fill: Option<&LegFill>) -> Self {
+        let filled_quantity = fill.map(|f| f.filled_quantity).unwrap_or(Decimal::ZERO);
+        let actual_price = fill.and_then(|f| f.average_price);
+        
+        let expected_notional = step.quantity * step.expected_price;
+        let estimated_fees = match actual_price {
+            Some(price) if expected_notional > Decimal::ZERO => (step.fees * (price * filled_quantity) / expected_notional).round_dp(8),
+            _ => Decimal::ZERO,
+        };
+        
+        let slippage_bps = actual_price
+            .filter(|_| step.expected_price > Decimal::ZERO)
+            .map(|price| {
+                let diff = match step.side {
+                    OrderSide::Buy => price - step.expected_price,
+                    OrderSide::Sell => step.expected_price - price,
+                };
+                (diff / step.expected_price * Decimal::from(10_000)).round_dp(4)
+            });
+        
+        Self {
+            exchange: step.exchange.clone(),
+            symbol: step.symbol.clone(),
+            side: format!("{:?}", step.side),
+            expected_quantity: step.quantity,
+            filled_quantity,
+            expected_price: step.expected_price,
+            actual_price,
+            expected_fees: step.fees,
+            estimated_fees,
+            slippage_bps,
+        }
+    }
+}
+
+impl ExecutionReport {
+    pub fn new(opportunity: &ArbitrageOpportunity, result: &ExecutionResult) -> Self {
+        let (fills, abandon_reason) = match result {
+            ExecutionResult::Completed(fills) => (fills, None),
+            ExecutionResult::Abandoned { fills, reason } => (fills, Some(reason.clone())),
+        };
+        
+        let legs = opportunity.execution_steps.iter()
+            .enumerate()
+            .map(|(i, step)| LegReport::new(step, fills.get(i)))
+            .collect();
+        
+        Self {
+            key: opportunity.idempotency_key(),
+            exchange: opportunity.exchange.clone(),
+            path: opportunity.path.clone(),
+            executed_at: Utc::now(),
+            completed: abandon_reason.is_none(),
+            abandon_reason,
+            expected_profit_percentage: opportunity.net_profit_percentage,
+            legs,
+        }
+    }
+    
+    /// Strategy of the execution; cross-exchange opportunities are the ones
+    /// whose exchange names both venues, as in `Binance->Bybit`.
+    pub fn strategy(&self) -> Strategy {
+        if self.exchange.contains("->") { Strategy::CrossExchange } else { Strategy::Triangular }
+    }
+    
+    /// Leg symbols joined by `>`, as in the metrics' `path` label.
+    pub fn path_label(&self) -> String {
+        let mut legs: Vec<&str> = self.legs.iter().map(|l| l.symbol.as_str()).collect();
+        legs.dedup();
+        legs.join(">")
+    }
+    
+    /// Summed slippage of all filled legs, as a percentage of notional.
+    pub fn cycle_slippage_percentage(&self) -> Decimal {
+        self.legs.iter().filter_map(|l| l.slippage_bps).sum::<Decimal>() / Decimal::from(100)
+    }
+    
+    /// Profit the opportunity was expected to make on the first leg's filled
+    /// notional, to compare against `realized_pnl_usd`.
+    pub fn theoretical_pnl_usd(&self) -> Decimal {
+        self.first_leg_notional()
+            .map(|notional| (notional * self.expected_profit_percentage / Decimal::ONE_HUNDRED).round_dp(8))
+            .unwrap_or(Decimal::ZERO)
+    }
+    
+    fn first_leg_notional(&self) -> Option<Decimal> {
+        self.legs.first().and_then(|leg| leg.actual_price.map(|price| price * leg.filled_quantity))
+    }
+    
+    /// Estimated realized profit in USD, measured on the first leg's filled
+    /// notional (always a USD-quoted pair). A completed cycle earns its
+    /// expected net profit less slippage; an abandoned one has realized the
+    /// fees and slippage of the legs that filled, while the inventory it
+    /// left behind is not counted.
+    pub fn realized_pnl_usd(&self) -> Decimal {
+        let Some(notional) = self.first_leg_notional() else { return Decimal::ZERO };
+        
+        let cost_percentage = if self.completed {
+            self.cycle_slippage_percentage() - self.expected_profit_percentage
+        } else {
+            self.legs.iter()
+                .filter_map(|leg| {
+                    let filled_notional = leg.actual_price? * leg.filled_quantity;
+                    let fee_percentage = if filled_notional > Decimal::ZERO {
+                        leg.estimated_fees / filled_notional * Decimal::ONE_HUNDRED
+                    } else {
+                        Decimal::ZERO
+                    };
+                    Some(fee_percentage + leg.slippage_bps.unwrap_or(Decimal::ZERO) / Decimal::ONE_HUNDRED)
+                })
+                .sum()
+        };
+        (-notional * cost_percentage / Decimal::ONE_HUNDRED).round_dp(8)
+    }
+}
+
+impl SlippageStats {
+    pub fn from_reports(reports: &[ExecutionReport]) -> Self {
+        let leg_slippage: Vec<Decimal> = reports.iter()
+            .flat_map(|r| r.legs.iter().filter_map(|l| l.slippage_bps))
+            .collect();
+        if leg_slippage.is_empty() {
+            return Self { executions: reports.len(), ..Self::default() };
+        }
+        
+        let mut cycles: Vec<Decimal> = reports.iter().map(|r| r.cycle_slippage_percentage()).collect();
+        cycles.sort();
+        let p95_index = ((cycles.len() as f64 * 0.95).ceil() as usize).saturating_sub(1);
+        
+        Self {
+            executions: reports.len(),
+            legs: leg_slippage.len(),
+            mean_leg_slippage_bps: (leg_slippage.iter().sum::<Decimal>() / Decimal::from(leg_slippage.len())).round_dp(4),
+            max_leg_slippage_bps: leg_slippage.iter().copied().max().unwrap_or(Decimal::ZERO),
+            mean_cycle_slippage_percentage: (cycles.iter().sum::<Decimal>() / Decimal::from(cycles.len())).round_dp(6),
+            p95_cycle_slippage_percentage: cycles[p95_index],
+        }
+    }
+}
+
+/// Execution reports persisted as `reports/executions.jsonl` in the local store.
+#[derive(Debug, Clone)]
+pub struct ReportStore {
+    store: LocalStore,
+}
+
+impl ReportStore {
+    pub fn new(store: LocalStore) -> Self {
+        Self { store }
+    }
+    
+    pub fn record(&self, report: &ExecutionReport) -> Result<()> {
+        self.store.append_jsonl(Path::new("reports/executions.jsonl"), std::slice::from_ref(report))
+    }
+    
+    pub fn reports(&self) -> Result<Vec<ExecutionReport>> {
+        self.store.read_jsonl(Path::new("reports/executions.jsonl"))
+    }
+    
+    pub fn slippage_stats(&self, since: DateTime<Utc>) -> Result<SlippageStats> {
+        let reports: Vec<ExecutionReport> = self.reports()?
+            .into_iter()
+            .filter(|r| r.executed_at >= since)
+            .collect();
+        Ok(SlippageStats::from_reports(&reports))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchanges::new_client_order_id;
+    
+    fn step(side: OrderSide, price: i64) -> ExecutionStep {
+        ExecutionStep {
+            exchange: "Binance".to_string(),
+            action: "test".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side,
+            quantity: Decimal::ONE,
+            expected_price: Decimal::from(price),
+            fees: Decimal::from(price) / Decimal::from(1000),
+            client_order_id: new_client_order_id(),
+        }
+    }
+    
+    fn fill(step: &ExecutionStep, price: i64) -> LegFill {
+        LegFill {
+            exchange: step.exchange.clone(),
+            symbol: step.symbol.clone(),
+            side: step.side.clone(),
+            client_order_ids: vec![step.client_order_id.clone()],
+            requested_quantity: step.quantity,
+            filled_quantity: step.quantity,
+            average_price: Some(Decimal::from(price)),
+        }
+    }
+    
+    #[test]
+    fn test_slippage_sign_follows_side() {
+        let buy = step(OrderSide::Buy, 10_000);
+        let report = LegReport::new(&buy, Some(&fill(&buy, 10_010)));
+        assert_eq!(report.slippage_bps, Some(Decimal::from(10)));
+        assert_eq!(report.estimated_fees, Decimal::from_str_exact("10.01").unwrap());
+        
+        let sell = step(OrderSide::Sell, 10_000);
+        let report = LegReport::new(&sell, Some(&fill(&sell, 10_010)));
+        assert_eq!(report.slippage_bps, Some(Decimal::from(-10)));
+        
+        let unfilled = LegReport::new(&sell, None);
+        assert_eq!(unfilled.slippage_bps, None);
+        assert_eq!(unfilled.filled_quantity, Decimal::ZERO);
+    }
+    
+    #[test]
+    fn test_stats_aggregate_cycles() {
+        let buy = step(OrderSide::Buy, 10_000);
+        let report = |price: i64| ExecutionReport {
+            key: "k".to_string(),
+            exchange: "Binance".to_string(),
+            path: vec![],
+            executed_at: Utc::now(),
+            completed: true,
+            abandon_reason: None,
+            expected_profit_percentage: Decimal::ONE,
+            legs: vec![LegReport::new(&buy, Some(&fill(&buy, price)))],
+        };
+        
+        let stats = SlippageStats::from_reports(&[report(10_000), report(10_020)]);
+        assert_eq!(stats.executions, 2);
+        assert_eq!(stats.mean_leg_slippage_bps, Decimal::from(10));
+        assert_eq!(stats.max_leg_slippage_bps, Decimal::from(20));
+        assert_eq!(stats.p95_cycle_slippage_percentage, Decimal::from_str_exact("0.2").unwrap());
+        
+        assert_eq!(SlippageStats::from_reports(&[]).legs, 0);
+    }
+    
+    #[test]
+    fn test_realized_pnl() {
+        let buy = step(OrderSide::Buy, 10_000);
+        let sell = step(OrderSide::Sell, 10_000);
+        let report = |completed: bool, legs: Vec<LegReport>| ExecutionReport {
+            key: "k".to_string(),
+            exchange: "Binance".to_string(),
+            path: vec![],
+            executed_at: Utc::now(),
+            completed,
+            abandon_reason: None,
+            expected_profit_percentage: Decimal::ONE,
+            legs,
+        };
+        
+        // 1% expected on 10,000 notional, 10 bps lost to slippage
+        let completed = report(true, vec![
+            LegReport::new(&buy, Some(&fill(&buy, 10_010))),
+            LegReport::new(&sell, Some(&fill(&sell, 10_000))),
+        ]);
+        assert_eq!(completed.realized_pnl_usd(), Decimal::from_str_exact("90.09").unwrap());
+        assert_eq!(completed.theoretical_pnl_usd(), Decimal::from_str_exact("100.1").unwrap());
+        
+        // First leg filled at the expected price, paying its 0.1% fee; second leg never filled
+        let abandoned = report(false, vec![
+            LegReport::new(&buy, Some(&fill(&buy, 10_000))),
+            LegReport::new(&sell, None),
+        ]);
+        assert_eq!(abandoned.realized_pnl_usd(), Decimal::from(-10));
+        
+        assert_eq!(report(false, vec![LegReport::new(&buy, None)]).realized_pnl_usd(), Decimal::ZERO);
+    }
+}
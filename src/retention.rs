@@ -0,0 +1,214 @@
+//! Scheduled retention for the recorded market data.
+//!
+//! Market snapshots are kept at full resolution for `full_resolution_days`,
+//! then downsampled to one snapshot per minute: the last price of each
+//! symbol in the minute and the best variant of each opportunity found in
+//! it. Snapshot days and shadow records older than
+//! `monitoring.opportunity_history_days` are deleted. Execution reports,
+//! the ledger, the journal and portfolio snapshots are never touched, and
+//! order books are only ever held in memory.
+
+use crate::replay::{keep_best, MarketSnapshot, SnapshotStore};
+use crate::shadow::ShadowStore;
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionConfig {
+    pub enabled: bool,
+    /// How often the retention job runs
+    pub interval_hours: u64,
+    /// Days of snapshots kept at full resolution before downsampling
+    pub full_resolution_days: u32,
+}
+
+impl Default for RetentionConfig {
+    fn default() -> Self {
+        Self { enabled: false, interval_hours: 6, full_resolution_days: 2 }
+    }
+}
+
+impl RetentionConfig {
+    /// Checks the settings against `history_days`, the configured
+    /// `monitoring.opportunity_history_days`.
+    pub fn validate(&self, history_days: i64) -> Result<()> {
+        if self.interval_hours == 0 {
+            return Err(anyhow::anyhow!("storage.retention.interval_hours must be greater than 0"));
+        }
+        if self.full_resolution_days == 0 {
+            return Err(anyhow::anyhow!("storage.retention.full_resolution_days must be at least 1"));
+        }
+        if history_days < 0 {
+            return Err(anyhow::anyhow!("monitoring.opportunity_history_days cannot be negative"));
+        }
+        if history_days != 0 && history_days <= self.full_resolution_days as i64 {
+            return Err(anyhow::anyhow!(
+                "monitoring.opportunity_history_days must be 0 or greater than storage.retention.full_resolution_days"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// What one retention run removed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RetentionReport {
+    /// Snapshot days deleted
+    pub days_removed: usize,
+    /// Snapshots merged away by downsampling
+    pub snapshots_merged: usize,
+    pub shadow_records_removed: usize,
+}
+
+impl RetentionReport {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// One snapshot per minute, stamped with the start of the minute. Prices
+/// are the last seen in the minute; opportunities the best of each path.
+pub fn downsample(snapshots: &[MarketSnapshot]) -> Vec<MarketSnapshot> {
+    let mut minutes: BTreeMap<DateTime<Utc>, Vec<&MarketSnapshot>> = BTreeMap::new();
+    for snapshot in snapshots {
+        let minute = snapshot.captured_at.duration_trunc(Duration::minutes(1)).unwrap_or(snapshot.captured_at);
+        minutes.entry(minute).or_default().push(snapshot);
+    }
+
+    minutes.into_iter().map(|(minute, mut group)| {
+        group.sort_by_key(|s| s.captured_at);
+        let mut bar = MarketSnapshot { captured_at: minute, binance: HashMap::new(), bybit: HashMap::new(), opportunities: Vec::new() };
+        let mut best = HashMap::new();
+        for snapshot in group {
+            bar.binance.extend(snapshot.binance.iter().map(|(symbol, price)| (symbol.clone(), *price)));
+            bar.bybit.extend(snapshot.bybit.iter().map(|(symbol, price)| (symbol.clone(), *price)));
+            for opportunity in &snapshot.opportunities {
+                keep_best(&mut best, opportunity.clone());
+            }
+        }
+        bar.opportunities = best.into_values().collect();
+        bar.opportunities.sort_by(|a, b| b.net_profit_percentage.cmp(&a.net_profit_percentage).then_with(|| a.key().cmp(&b.key())));
+        bar
+    }).collect()
+}
+
+/// Applies `config` to the data in `store` as of `now`, deleting what is
+/// older than `history_days`; 0 keeps everything.
+pub fn compact(store: &LocalStore, config: &RetentionConfig, history_days: i64, now: DateTime<Utc>) -> Result<RetentionReport> {
+    let mut report = RetentionReport::default();
+    let today = now.date_naive();
+    let downsample_before = today - Duration::days(config.full_resolution_days as i64);
+    let delete_before = (history_days > 0).then(|| today - Duration::days(history_days));
+
+    let snapshots = SnapshotStore::new(store.clone());
+    for day in snapshots.days()? {
+        if delete_before.is_some_and(|cutoff| day < cutoff) {
+            snapshots.remove_day(day)?;
+            report.days_removed += 1;
+        } else if day < downsample_before {
+            let recorded = snapshots.load_day(day)?;
+            let bars = downsample(&recorded);
+            if bars.len() < recorded.len() {
+                snapshots.replace_day(day, &bars)?;
+                report.snapshots_merged += recorded.len() - bars.len();
+            }
+        }
+    }
+
+    if history_days > 0 {
+        let cutoff = now - Duration::days(history_days);
+        report.shadow_records_removed = ShadowStore::new(store.clone()).retain_since(cutoff)?;
+    }
+    Ok(report)
+}
+
+/// Runs `compact` every `interval_hours`, starting right away, until cancelled.
+pub async fn run(store: LocalStore, config: RetentionConfig, history_days: i64, cancel: CancellationToken) {
+    let mut rounds = tokio::time::interval(std::time::Duration::from_secs(config.interval_hours * 3600));
+    rounds.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = rounds.tick() => {}
+        }
+        let (store, config) = (store.clone(), config.clone());
+        match tokio::task::spawn_blocking(move || compact(&store, &config, history_days, Utc::now())).await {
+            Ok(Ok(report)) if report.is_empty() => {}
+            Ok(Ok(report)) => info!(
+                "Retention removed {} snapshot days and {} shadow records, merged {} snapshots into minute bars",
+                report.days_removed, report.shadow_records_removed, report.snapshots_merged
+            ),
+            Ok(Err(e)) => warn!("Retention run failed: {:#}", e),
+            Err(e) => warn!("Retention run panicked: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::risk::Strategy;
+    use crate::replay::OpportunitySummary;
+    use rust_decimal::Decimal;
+
+    fn snapshot(at: &str, price: i64, profit: Option<i64>) -> MarketSnapshot {
+        MarketSnapshot {
+            captured_at: at.parse().unwrap(),
+            binance: HashMap::from([("BTCUSDT".to_string(), Decimal::from(price))]),
+            bybit: HashMap::new(),
+            opportunities: profit.into_iter().map(|profit| OpportunitySummary {
+                exchange: "Binance".to_string(),
+                strategy: Strategy::Triangular,
+                path: vec!["ETHUSDT".to_string(), "ETHBTC".to_string(), "BTCUSDT".to_string()],
+                net_profit_percentage: Decimal::from(profit),
+                risk_score: 0.2,
+            }).collect(),
+        }
+    }
+
+    #[test]
+    fn test_downsample_keeps_last_price_and_best_opportunity() {
+        let bars = downsample(&[
+            snapshot("2024-05-01T12:00:40Z", 60010, Some(1)),
+            snapshot("2024-05-01T12:00:05Z", 60000, Some(3)),
+            snapshot("2024-05-01T12:00:50Z", 60020, None),
+            snapshot("2024-05-01T12:01:10Z", 60030, None),
+        ]);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].captured_at, "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap());
+        assert_eq!(bars[0].binance["BTCUSDT"], Decimal::from(60020));
+        assert_eq!(bars[0].opportunities.len(), 1);
+        assert_eq!(bars[0].opportunities[0].net_profit_percentage, Decimal::from(3));
+        assert!(bars[1].opportunities.is_empty());
+    }
+
+    #[test]
+    fn test_compact_downsamples_then_deletes_old_days() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path()).unwrap();
+        let snapshots = SnapshotStore::new(store.clone());
+        for at in ["2024-01-01T00:00:01Z", "2024-05-01T12:00:01Z", "2024-05-01T12:00:31Z", "2024-05-30T12:00:01Z", "2024-05-30T12:00:31Z"] {
+            snapshots.record(&snapshot(at, 60000, None)).unwrap();
+        }
+
+        let config = RetentionConfig { enabled: true, full_resolution_days: 7, ..RetentionConfig::default() };
+        let now = "2024-06-01T00:00:00Z".parse().unwrap();
+        let report = compact(&store, &config, 90, now).unwrap();
+        assert_eq!(report, RetentionReport { days_removed: 1, snapshots_merged: 1, shadow_records_removed: 0 });
+        let days: Vec<String> = snapshots.days().unwrap().iter().map(|d| d.to_string()).collect();
+        assert_eq!(days, ["2024-05-01", "2024-05-30"]);
+        assert_eq!(snapshots.load_day("2024-05-01".parse().unwrap()).unwrap().len(), 1);
+        assert_eq!(snapshots.load_day("2024-05-30".parse().unwrap()).unwrap().len(), 2);
+
+        // A second run has nothing left to do
+        assert!(compact(&store, &config, 90, now).unwrap().is_empty());
+        assert!(config.validate(7).is_err());
+        assert!(config.validate(0).is_ok());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::config::RetryPolicy;
+use crate::exchanges::ExchangeError;
+use anyhow::Result;
+use log::warn;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// Backoff before retry number `retry` (1 for the first), with `sample`
+/// in `[0, 1)` spreading it by up to `jitter` either way.
+pub(crate) fn backoff(policy: &RetryPolicy, retry: u32, sample: f64) -> Duration {
+    let base = policy.initial_backoff_ms as f64 * policy.multiplier.powi(retry.saturating_sub(1) as i32);
+    let base = base.min(policy.max_backoff_ms as f64);
+    let spread = 1.0 + policy.jitter * (2.0 * sample - 1.0);
+    Duration::from_millis((base * spread).max(0.0) as u64)
+}
+
+tokio::task_local! {
+    static ATTEMPT: u32;
+}
+
+/// Attempt of the innermost `retry` the current task is running, 1 for
+/// the first and outside of any retry.
+pub fn current_attempt() -> u32 {
+    ATTEMPT.try_with(|attempt| *attempt).unwrap_or(1)
+}
+
+fn is_retryable(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<ExchangeError>().is_some_and(ExchangeError::is_retryable)
+}
+
+/// Runs `operation` until it succeeds, fails with an error that is not
+/// retryable, or `policy.max_attempts` is used up. Only wrap idempotent
+/// requests; order placement must never go through here.
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, what: &str, mut operation: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut attempt = 1;
+    loop {
+        match ATTEMPT.scope(attempt, operation()).await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_attempts && is_retryable(&e) => {
+                let delay = backoff(policy, attempt, rand::thread_rng().gen());
+                warn!("{} failed (attempt {}/{}), retrying in {:?}: {:#}", what, attempt, policy.max_attempts, delay, e);
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy { max_attempts, initial_backoff_ms: 1, max_backoff_ms: 4, multiplier: 2.0, jitter: 0.5 }
+    }
+
+    #[test]
+    fn test_backoff_grows_to_the_cap_within_jitter() {
+        let policy = RetryPolicy { initial_backoff_ms: 100, max_backoff_ms: 1_000, ..policy(5) };
+        assert_eq!(backoff(&policy, 1, 0.5), Duration::from_millis(100));
+        assert_eq!(backoff(&policy, 3, 0.5), Duration::from_millis(400));
+        assert_eq!(backoff(&policy, 10, 0.5), Duration::from_millis(1_000));
+        assert_eq!(backoff(&policy, 3, 0.0), Duration::from_millis(200));
+        assert_eq!(backoff(&policy, 3, 1.0), Duration::from_millis(600));
+    }
+
+    #[tokio::test]
+    async fn test_only_retryable_errors_are_retried() {
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = retry(&policy(3), "Request", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ExchangeError::NetworkError("reset".to_string()).into())
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        let calls = AtomicU32::new(0);
+        let result: Result<()> = retry(&policy(3), "Request", || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(ExchangeError::ParseError("bad body".to_string()).into())
+        }).await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_operations_see_their_attempt() {
+        let seen = std::sync::Mutex::new(Vec::new());
+        let _: Result<()> = retry(&policy(3), "Request", || async {
+            seen.lock().unwrap().push(current_attempt());
+            Err(ExchangeError::NetworkError("reset".to_string()).into())
+        }).await;
+        assert_eq!(*seen.lock().unwrap(), [1, 2, 3]);
+        assert_eq!(current_attempt(), 1);
+    }
+}
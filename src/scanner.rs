@@ -0,0 +1,174 @@
+//! Embeddable opportunity detection without the bot loop.
+//!
+//! ```no_run
+//! use tokio_util::sync::CancellationToken;
+//! use triangular_arbitrage::config::Config;
+//! use triangular_arbitrage::scanner::Scanner;
+//!
+//! # async fn example() -> anyhow::Result<()> {
+//! let cancel = CancellationToken::new();
+//! let mut scanner = Scanner::new(&Config::default())?
+//!     .on_opportunity(|opportunity| println!("{}", opportunity))
+//!     .on_error(|e| eprintln!("scan failed: {:#}", e));
+//! scanner.run_until(cancel.clone()).await;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::arbitrage::{ArbitrageEngine, ArbitrageOpportunity};
+use crate::config::Config;
+use crate::exchanges::{binance::BinanceClient, bybit::BybitClient};
+use crate::exchanges::snapshot::SymbolTable;
+use crate::symbols::SymbolFilter;
+use anyhow::{Context, Result};
+use log::warn;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+type OpportunityHook = Box<dyn FnMut(&ArbitrageOpportunity) + Send>;
+type ErrorHook = Box<dyn FnMut(&anyhow::Error) + Send>;
+
+/// Fetches prices from both exchanges, applies the symbol filter and runs
+/// the detection engine, handing results to callbacks. Unlike
+/// `ArbitrageBot` it never executes, persists or serves anything, and
+/// needs no API credentials.
+pub struct Scanner {
+    binance: Arc<BinanceClient>,
+    bybit: Arc<BybitClient>,
+    engine: ArbitrageEngine,
+    symbols: SymbolFilter,
+    symbol_table: Arc<SymbolTable>,
+    require_trading_status: bool,
+    symbol_refresh_interval: Duration,
+    last_symbol_refresh: Option<Instant>,
+    interval: Duration,
+    on_opportunity: Option<OpportunityHook>,
+    on_error: Option<ErrorHook>,
+}
+
+impl Scanner {
+    /// Creates a scanner with credential-less clients using the network
+    /// settings from `config`.
+    pub fn new(config: &Config) -> Result<Self> {
+        let binance = BinanceClient::with_credentials("", "", &config.exchanges.binance_network)
+            .context("Failed to create Binance client")?;
+        let bybit = BybitClient::with_credentials("", "", &config.exchanges.bybit_network)
+            .context("Failed to create Bybit client")?;
+        Ok(Self::with_clients(config, Arc::new(binance), Arc::new(bybit)))
+    }
+    
+    /// Creates a scanner sharing existing clients.
+    pub fn with_clients(config: &Config, binance: Arc<BinanceClient>, bybit: Arc<BybitClient>) -> Self {
+        Self {
+            binance,
+            bybit,
+            engine: ArbitrageEngine::from_config(config),
+            symbols: SymbolFilter::new(config.symbols.clone()),
+            symbol_table: Arc::new(SymbolTable::new()),
+            require_trading_status: config.symbols.require_trading_status,
+            symbol_refresh_interval: Duration::from_secs(config.symbols.refresh_interval_minutes * 60),
+            last_symbol_refresh: None,
+            interval: Duration::from_millis(config.scan.interval_ms),
+            on_opportunity: None,
+            on_error: None,
+        }
+    }
+    
+    /// Called once per opportunity found, in detection order.
+    pub fn on_opportunity(mut self, hook: impl FnMut(&ArbitrageOpportunity) + Send + 'static) -> Self {
+        self.on_opportunity = Some(Box::new(hook));
+        self
+    }
+    
+    /// Called when a scan fails; without a hook failures are logged.
+    pub fn on_error(mut self, hook: impl FnMut(&anyhow::Error) + Send + 'static) -> Self {
+        self.on_error = Some(Box::new(hook));
+        self
+    }
+    
+    /// Time between scans in `run_until`, `scan.interval_ms` by default.
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+    
+    pub fn engine(&self) -> &ArbitrageEngine {
+        &self.engine
+    }
+    
+    /// Runs one detection pass, invoking `on_opportunity` for each result.
+    /// Errors are returned rather than passed to `on_error`.
+    pub async fn scan(&mut self) -> Result<Vec<ArbitrageOpportunity>> {
+        self.maybe_refresh_tradable_symbols().await;
+        
+        let (mut binance_prices, mut bybit_prices, binance_quotes, bybit_quotes) = tokio::try_join!(
+            self.binance.get_price_snapshot(&self.symbol_table),
+            self.bybit.get_price_snapshot(&self.symbol_table),
+            self.binance.get_book_tickers(),
+            self.bybit.get_book_tickers()
+        )?;
+        self.symbols.filter_snapshot("Binance", &mut binance_prices);
+        self.symbols.filter_snapshot("Bybit", &mut bybit_prices);
+        
+        let opportunities = self.engine
+            .analyze_opportunities_with_quotes(&binance_prices, &bybit_prices, &binance_quotes, &bybit_quotes)
+            .await?;
+        if let Some(hook) = self.on_opportunity.as_mut() {
+            for opportunity in &opportunities {
+                hook(opportunity);
+            }
+        }
+        Ok(opportunities)
+    }
+    
+    /// Scans every interval until `cancel` fires. A failed scan is reported
+    /// to `on_error` and the loop carries on.
+    pub async fn run_until(&mut self, cancel: CancellationToken) {
+        while !cancel.is_cancelled() {
+            let result = tokio::select! {
+                _ = cancel.cancelled() => break,
+                result = self.scan() => result,
+            };
+            if let Err(e) = result {
+                match self.on_error.as_mut() {
+                    Some(hook) => hook(&e),
+                    None => warn!("Scan failed: {:#}", e),
+                }
+            }
+            
+            tokio::select! {
+                _ = cancel.cancelled() => break,
+                _ = tokio::time::sleep(self.interval) => {}
+            }
+        }
+    }
+    
+    async fn maybe_refresh_tradable_symbols(&mut self) {
+        if !self.require_trading_status {
+            return;
+        }
+        if self.last_symbol_refresh.is_some_and(|t| t.elapsed() < self.symbol_refresh_interval) {
+            return;
+        }
+        self.last_symbol_refresh = Some(Instant::now());
+        
+        let (binance_symbols, bybit_symbols) = tokio::join!(
+            self.binance.get_symbol_info(),
+            self.bybit.get_symbol_info()
+        );
+        for (exchange, symbols) in [("Binance", binance_symbols), ("Bybit", bybit_symbols)] {
+            match symbols {
+                Ok(symbols) => {
+                    let tradable: HashSet<String> = symbols.into_iter()
+                        .filter(|s| s.tradable)
+                        .map(|s| s.symbol)
+                        .collect();
+                    self.symbols.set_tradable(exchange, tradable);
+                }
+                Err(e) => warn!("Failed to refresh {} symbol info: {:#}", exchange, e),
+            }
+        }
+    }
+}
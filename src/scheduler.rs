@@ -0,0 +1,94 @@
+use crate::config::ScanConfig;
+use std::time::{Duration, Instant};
+
+/// Decides how long the bot sleeps between scans. In fixed mode this is
+/// always `interval_ms`; in adaptive mode the interval drops to the minimum
+/// while opportunities keep appearing and backs off towards the maximum
+/// during quiet periods to save rate-limit budget.
+#[derive(Debug)]
+pub struct ScanScheduler {
+    config: ScanConfig,
+    current: Duration,
+    last_opportunity: Option<Instant>,
+}
+
+impl ScanScheduler {
+    pub fn new(config: ScanConfig) -> Self {
+        let current = Duration::from_millis(config.interval_ms);
+        Self {
+            config,
+            current,
+            last_opportunity: None,
+        }
+    }
+    
+    /// Returns the delay before the next scan given how many opportunities
+    /// the scan that just finished produced.
+    pub fn next_interval(&mut self, opportunities_found: usize) -> Duration {
+        self.next_interval_at(opportunities_found, Instant::now())
+    }
+    
+    fn next_interval_at(&mut self, opportunities_found: usize, now: Instant) -> Duration {
+        if !self.config.adaptive {
+            return Duration::from_millis(self.config.interval_ms);
+        }
+        
+        let min = Duration::from_millis(self.config.min_interval_ms);
+        let max = Duration::from_millis(self.config.max_interval_ms);
+        
+        if opportunities_found > 0 {
+            self.last_opportunity = Some(now);
+        }
+        
+        let recently_active = self.last_opportunity
+            .map(|seen| now.duration_since(seen) < Duration::from_secs(self.config.hot_period_seconds))
+            .unwrap_or(false);
+        
+        self.current = if recently_active {
+            min
+        } else {
+            (self.current * 2).clamp(min, max)
+        };
+        
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn adaptive_config() -> ScanConfig {
+        ScanConfig {
+            interval_ms: 250,
+            adaptive: true,
+            min_interval_ms: 100,
+            max_interval_ms: 2000,
+            hot_period_seconds: 30,
+            feed_stall_timeout_seconds: 30,
+            watchdog_timeout_seconds: 60,
+        }
+    }
+    
+    #[test]
+    fn test_fixed_interval() {
+        let mut scheduler = ScanScheduler::new(ScanConfig { adaptive: false, ..adaptive_config() });
+        assert_eq!(scheduler.next_interval(0), Duration::from_millis(250));
+        assert_eq!(scheduler.next_interval(3), Duration::from_millis(250));
+    }
+    
+    #[test]
+    fn test_adaptive_backs_off_and_recovers() {
+        let mut scheduler = ScanScheduler::new(adaptive_config());
+        let start = Instant::now();
+        
+        assert_eq!(scheduler.next_interval_at(0, start), Duration::from_millis(500));
+        assert_eq!(scheduler.next_interval_at(0, start), Duration::from_millis(1000));
+        assert_eq!(scheduler.next_interval_at(0, start), Duration::from_millis(2000));
+        assert_eq!(scheduler.next_interval_at(0, start), Duration::from_millis(2000));
+        
+        assert_eq!(scheduler.next_interval_at(1, start), Duration::from_millis(100));
+        assert_eq!(scheduler.next_interval_at(0, start + Duration::from_secs(10)), Duration::from_millis(100));
+        assert_eq!(scheduler.next_interval_at(0, start + Duration::from_secs(31)), Duration::from_millis(200));
+    }
+}
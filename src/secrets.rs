@@ -0,0 +1,85 @@
+//! Keeping credentials out of logs, errors and debug output.
+//!
+//! API keys and secrets are held as `SecretString`, which only gives up
+//! its value through `expose`. Signed requests put their signature in the
+//! query string, and reqwest errors quote the full URL, so errors leaving
+//! the endpoint pool have their query stripped and the request log masks
+//! the sensitive parameters.
+
+use std::fmt;
+
+/// Replaces the value of every masked query parameter.
+pub const REDACTED: &str = "<redacted>";
+
+/// Query parameters whose values are masked.
+const SECRET_PARAMS: &[&str] = &["signature", "sign", "api_key", "apikey", "secret", "token"];
+
+/// A credential that never shows up in `Debug` output.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self(value.into())
+    }
+
+    /// The credential itself, for signing and request headers only.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SecretString({})", REDACTED)
+    }
+}
+
+impl From<String> for SecretString {
+    fn from(value: String) -> Self {
+        Self(value)
+    }
+}
+
+impl From<&str> for SecretString {
+    fn from(value: &str) -> Self {
+        Self(value.to_string())
+    }
+}
+
+/// `query` with the values of signatures, keys and tokens masked.
+pub fn redact_query(query: &str) -> String {
+    query.split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SECRET_PARAMS.iter().any(|p| key.eq_ignore_ascii_case(p)) => format!("{}={}", key, REDACTED),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// `error` with the query string dropped from the URL it quotes.
+pub fn redact_error(mut error: reqwest::Error) -> reqwest::Error {
+    if let Some(url) = error.url_mut() {
+        url.set_query(None);
+    }
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_secrets_stay_out_of_debug_output_and_queries() {
+        let key = SecretString::from("live-api-key");
+        assert_eq!(key.expose(), "live-api-key");
+        assert_eq!(format!("{:?}", key), "SecretString(<redacted>)");
+
+        assert_eq!(
+            redact_query("symbol=BTCUSDT&timestamp=1&signature=abc123"),
+            "symbol=BTCUSDT&timestamp=1&signature=<redacted>"
+        );
+        assert_eq!(redact_query("api_key=k&sign=s&limit=5"), "api_key=<redacted>&sign=<redacted>&limit=5");
+    }
+}
@@ -0,0 +1,299 @@
+//! Shadow execution: checks whether detected opportunities could have been
+//! filled, without sending orders.
+//!
+//! Right after detection the order book of every leg is fetched and each
+//! leg's quantity is walked through the side it would take. An opportunity
+//! counts as filled when every leg finds its quantity in the fetched levels
+//! at an average price within `trading.max_slippage_percentage` of the
+//! detected one. It costs one depth request per leg, far less than paper
+//! trading, and shows how much of what is detected is really there.
+
+use crate::arbitrage::risk::Strategy;
+use crate::arbitrage::{ArbitrageOpportunity, ExecutionStep};
+use crate::exchanges::{ExchangeClient, OrderSide};
+use crate::metrics::{self, MarketMetrics};
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures_util::future::{join_all, try_join_all};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::sync::Arc;
+
+/// Whether a shadowed opportunity could have been filled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShadowOutcome {
+    Filled,
+    /// A leg's quantity was not in the fetched levels
+    ThinBook,
+    /// Every leg fit, but at least one only beyond the slippage limit
+    Slipped,
+}
+
+impl ShadowOutcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            ShadowOutcome::Filled => "filled",
+            ShadowOutcome::ThinBook => "thin_book",
+            ShadowOutcome::Slipped => "slipped",
+        }
+    }
+}
+
+/// One leg walked through its side of the book.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowLeg {
+    /// Quantity the fetched levels hold for the leg, at most its own
+    pub fillable_quantity: Decimal,
+    /// Average price of the fillable quantity; `None` for an empty side
+    pub average_price: Option<Decimal>,
+    /// How much worse than the detected price `average_price` is, in percent
+    pub slippage_percentage: Decimal,
+}
+
+impl ShadowLeg {
+    /// Walks `step`'s quantity through `levels`, best first, of the side the
+    /// step takes: asks for a buy, bids for a sell.
+    pub fn walk(step: &ExecutionStep, levels: &[(Decimal, Decimal)]) -> Self {
+        let mut remaining = step.quantity;
+        let mut cost = Decimal::ZERO;
+        for (price, quantity) in levels {
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+            let taken = remaining.min(*quantity);
+            cost += taken * price;
+            remaining -= taken;
+        }
+        let fillable_quantity = step.quantity - remaining.max(Decimal::ZERO);
+        let average_price = (fillable_quantity > Decimal::ZERO).then(|| cost / fillable_quantity);
+        let slippage_percentage = match average_price {
+            Some(average) if step.expected_price > Decimal::ZERO => {
+                let worse = match step.side {
+                    OrderSide::Buy => average - step.expected_price,
+                    OrderSide::Sell => step.expected_price - average,
+                };
+                worse / step.expected_price * Decimal::ONE_HUNDRED
+            }
+            _ => Decimal::ZERO,
+        };
+        Self { fillable_quantity, average_price, slippage_percentage }
+    }
+}
+
+/// Outcome of one shadowed opportunity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowFill {
+    pub outcome: ShadowOutcome,
+    pub legs: Vec<ShadowLeg>,
+}
+
+impl ShadowFill {
+    pub fn evaluate(steps: &[ExecutionStep], legs: Vec<ShadowLeg>, max_slippage_percentage: Decimal) -> Self {
+        let outcome = if steps.iter().zip(&legs).any(|(step, leg)| leg.fillable_quantity < step.quantity) {
+            ShadowOutcome::ThinBook
+        } else if legs.iter().any(|leg| leg.slippage_percentage > max_slippage_percentage) {
+            ShadowOutcome::Slipped
+        } else {
+            ShadowOutcome::Filled
+        };
+        Self { outcome, legs }
+    }
+
+    /// Slippage of the leg that fared worst, in percent.
+    pub fn worst_slippage_percentage(&self) -> Decimal {
+        self.legs.iter().map(|leg| leg.slippage_percentage).max().unwrap_or(Decimal::ZERO)
+    }
+}
+
+/// One shadowed opportunity as persisted for reporting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadowRecord {
+    pub checked_at: DateTime<Utc>,
+    pub exchange: String,
+    pub strategy: Strategy,
+    /// Leg symbols joined by `>`, as in the metrics' `path` label
+    pub path: String,
+    pub outcome: ShadowOutcome,
+    pub expected_profit_percentage: Decimal,
+    pub estimated_profit_usd: Decimal,
+    pub worst_slippage_percentage: Decimal,
+}
+
+impl ShadowRecord {
+    pub fn new(opportunity: &ArbitrageOpportunity, fill: &ShadowFill) -> Self {
+        Self {
+            checked_at: Utc::now(),
+            exchange: opportunity.exchange.clone(),
+            strategy: opportunity.strategy,
+            path: metrics::path_label(opportunity),
+            outcome: fill.outcome,
+            expected_profit_percentage: opportunity.net_profit_percentage,
+            estimated_profit_usd: opportunity.estimated_profit_usd,
+            worst_slippage_percentage: fill.worst_slippage_percentage(),
+        }
+    }
+}
+
+/// Shadow records persisted as `reports/shadow.jsonl` in the local store.
+#[derive(Debug, Clone)]
+pub struct ShadowStore {
+    store: LocalStore,
+}
+
+impl ShadowStore {
+    pub fn new(store: LocalStore) -> Self {
+        Self { store }
+    }
+
+    pub fn record(&self, records: &[ShadowRecord]) -> Result<()> {
+        self.store.append_jsonl(Path::new("reports/shadow.jsonl"), records)
+    }
+
+    pub fn records(&self) -> Result<Vec<ShadowRecord>> {
+        self.store.read_jsonl(Path::new("reports/shadow.jsonl"))
+    }
+
+    /// Drops the records checked before `cutoff`. Returns how many were dropped.
+    pub fn retain_since(&self, cutoff: DateTime<Utc>) -> Result<usize> {
+        let records = self.records()?;
+        let kept: Vec<&ShadowRecord> = records.iter().filter(|record| record.checked_at >= cutoff).collect();
+        let dropped = records.len() - kept.len();
+        if dropped > 0 {
+            self.store.replace_jsonl(Path::new("reports/shadow.jsonl"), &kept)?;
+        }
+        Ok(dropped)
+    }
+}
+
+pub struct ShadowExecutor {
+    clients: Vec<ExchangeClient>,
+    depth_levels: usize,
+    max_slippage_percentage: Decimal,
+    metrics: Option<Arc<MarketMetrics>>,
+    store: Option<ShadowStore>,
+}
+
+impl ShadowExecutor {
+    pub fn new(clients: Vec<ExchangeClient>, depth_levels: usize, max_slippage_percentage: Decimal) -> Self {
+        Self { clients, depth_levels, max_slippage_percentage, metrics: None, store: None }
+    }
+
+    pub fn with_metrics(mut self, metrics: Arc<MarketMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn with_store(mut self, store: ShadowStore) -> Self {
+        self.store = Some(store);
+        self
+    }
+
+    fn client(&self, exchange: &str) -> Result<&ExchangeClient> {
+        self.clients.iter()
+            .find(|c| c.name().eq_ignore_ascii_case(exchange))
+            .ok_or_else(|| anyhow::anyhow!("No client configured for exchange {}", exchange))
+    }
+
+    /// Fetches the book of every leg of `opportunity` and walks the legs
+    /// through them.
+    pub async fn shadow(&self, opportunity: &ArbitrageOpportunity) -> Result<ShadowFill> {
+        let books = try_join_all(opportunity.execution_steps.iter().map(|step| async move {
+            self.client(&step.exchange)?.get_depth_snapshot(&step.symbol, self.depth_levels).await
+        })).await?;
+        let legs = opportunity.execution_steps.iter().zip(&books)
+            .map(|(step, book)| ShadowLeg::walk(step, match step.side {
+                OrderSide::Buy => &book.asks,
+                OrderSide::Sell => &book.bids,
+            }))
+            .collect();
+        Ok(ShadowFill::evaluate(&opportunity.execution_steps, legs, self.max_slippage_percentage))
+    }
+
+    /// Shadows every opportunity of a scan at once and records the
+    /// outcomes. A failed depth request leaves its opportunity unrecorded.
+    pub async fn shadow_all(&self, opportunities: &[ArbitrageOpportunity]) {
+        let fills = join_all(opportunities.iter().map(|opportunity| self.shadow(opportunity))).await;
+        let mut records = Vec::new();
+        for (opportunity, fill) in opportunities.iter().zip(fills) {
+            let fill = match fill {
+                Ok(fill) => fill,
+                Err(e) => {
+                    warn!("Shadow execution of {} failed: {:#}", opportunity.exchange, e);
+                    continue;
+                }
+            };
+            info!("Shadow execution {}: {} ({:.4}% worst leg slippage)", fill.outcome.label(), opportunity,
+                  fill.worst_slippage_percentage());
+            if let Some(metrics) = &self.metrics {
+                metrics.record_shadow_fill(opportunity, &fill);
+            }
+            records.push(ShadowRecord::new(opportunity, &fill));
+        }
+        if let (Some(store), false) = (&self.store, records.is_empty()) {
+            if let Err(e) = store.record(&records) {
+                warn!("Failed to save shadow execution records: {:#}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str_exact(value).unwrap()
+    }
+
+    fn step(side: OrderSide, quantity: &str, expected_price: &str) -> ExecutionStep {
+        ExecutionStep {
+            exchange: "Binance".to_string(),
+            action: String::new(),
+            symbol: "ETHUSDT".to_string(),
+            side,
+            quantity: dec(quantity),
+            expected_price: dec(expected_price),
+            fees: Decimal::ZERO,
+            client_order_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_legs_are_walked_through_the_book() {
+        let asks = [(dec("3000"), dec("1")), (dec("3003"), dec("1"))];
+        let buy = step(OrderSide::Buy, "1.5", "3000");
+        let leg = ShadowLeg::walk(&buy, &asks);
+        assert_eq!(leg.fillable_quantity, dec("1.5"));
+        assert_eq!(leg.average_price, Some(dec("3001")));
+        assert_eq!(leg.slippage_percentage.round_dp(4), dec("0.0333"));
+
+        // A sell filled above the detected bid has negative slippage
+        let sell = step(OrderSide::Sell, "1", "2999");
+        let leg = ShadowLeg::walk(&sell, &[(dec("3000"), dec("2"))]);
+        assert!(leg.slippage_percentage < Decimal::ZERO);
+
+        let thin = ShadowLeg::walk(&step(OrderSide::Buy, "3", "3000"), &asks);
+        assert_eq!(thin.fillable_quantity, dec("2"));
+        assert_eq!(ShadowLeg::walk(&buy, &[]).average_price, None);
+    }
+
+    #[test]
+    fn test_outcome_needs_every_leg_at_size_and_within_slippage() {
+        let steps = vec![step(OrderSide::Buy, "1", "3000"), step(OrderSide::Sell, "1", "3000")];
+        let leg = |fillable: &str, slippage: &str| ShadowLeg {
+            fillable_quantity: dec(fillable),
+            average_price: Some(dec("3000")),
+            slippage_percentage: dec(slippage),
+        };
+
+        let filled = ShadowFill::evaluate(&steps, vec![leg("1", "0.05"), leg("1", "-0.01")], dec("0.1"));
+        assert_eq!(filled.outcome, ShadowOutcome::Filled);
+        assert_eq!(filled.worst_slippage_percentage(), dec("0.05"));
+        assert_eq!(ShadowFill::evaluate(&steps, vec![leg("1", "0.05"), leg("1", "0.2")], dec("0.1")).outcome, ShadowOutcome::Slipped);
+        assert_eq!(ShadowFill::evaluate(&steps, vec![leg("0.4", "0"), leg("1", "0.2")], dec("0.1")).outcome, ShadowOutcome::ThinBook);
+    }
+}
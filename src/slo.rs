@@ -0,0 +1,349 @@
+//! Service level alarms on the market data the bot trades on.
+//!
+//! Two objectives are checked every second for the watched symbols: the
+//! rate of websocket depth messages, where a symbol that goes quiet is
+//! stale even while its stream stays connected, and the end-to-end age of
+//! the cached quotes, measured from the exchange's event time where the
+//! payload carries one. Any violation pauses execution through
+//! `BotControl`; it resumes once every objective has held again for
+//! `recovery_seconds`. Scanning carries on meanwhile, so the opportunities
+//! missed stay visible.
+
+use crate::arbitrage::price_cache::PriceCache;
+use crate::exchanges::reconnect::StreamHealth;
+use crate::metrics::{self, write_metric};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SloConfig {
+    pub enabled: bool,
+    /// Symbols watched; empty follows the order book symbols
+    pub symbols: Vec<String>,
+    /// Fewest depth messages a symbol may receive per minute; 0 disables
+    /// the rate objective
+    pub min_messages_per_minute: f64,
+    /// Period the message rate is averaged over
+    pub rate_window_seconds: u64,
+    /// Oldest a cached quote may be; 0 disables the age objective
+    pub max_quote_age_ms: u64,
+    /// Time every objective must hold before execution resumes
+    pub recovery_seconds: u64,
+}
+
+impl Default for SloConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            symbols: Vec::new(),
+            min_messages_per_minute: 30.0,
+            rate_window_seconds: 60,
+            max_quote_age_ms: 5_000,
+            recovery_seconds: 30,
+        }
+    }
+}
+
+impl SloConfig {
+    pub fn validate(&self) -> Result<()> {
+        if !self.min_messages_per_minute.is_finite() || self.min_messages_per_minute < 0.0 {
+            return Err(anyhow::anyhow!("slo.min_messages_per_minute cannot be negative"));
+        }
+        if self.min_messages_per_minute == 0.0 && self.max_quote_age_ms == 0 {
+            return Err(anyhow::anyhow!("slo needs min_messages_per_minute or max_quote_age_ms"));
+        }
+        if self.rate_window_seconds == 0 {
+            return Err(anyhow::anyhow!("slo.rate_window_seconds must be greater than 0"));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SloKind {
+    MessageRate,
+    QuoteAge,
+}
+
+impl SloKind {
+    fn label(self) -> &'static str {
+        match self {
+            SloKind::MessageRate => "message_rate",
+            SloKind::QuoteAge => "quote_age",
+        }
+    }
+}
+
+/// A symbol missing an objective at the last check.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SloViolation {
+    pub kind: SloKind,
+    pub exchange: String,
+    pub symbol: String,
+    /// Messages per minute, or quote age in milliseconds
+    pub value: f64,
+    pub limit: f64,
+}
+
+impl fmt::Display for SloViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            SloKind::MessageRate => write!(f, "{} {} received {:.1} depth messages/min, below {}",
+                                           self.exchange, self.symbol, self.value, self.limit),
+            SloKind::QuoteAge => write!(f, "{} {} quote is {:.0}ms old, above {}ms",
+                                        self.exchange, self.symbol, self.value, self.limit),
+        }
+    }
+}
+
+/// Change of the alarm found by `SloMonitor::check`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SloTransition {
+    /// Violations while execution was allowed
+    Breached(Vec<SloViolation>),
+    /// Every objective held for `recovery_seconds`
+    Recovered,
+}
+
+/// The alarm's state, as served by the admin API.
+#[derive(Debug, Clone, Serialize)]
+pub struct SloStatus {
+    pub breached: bool,
+    pub breached_since: Option<DateTime<Utc>>,
+    pub violations: Vec<SloViolation>,
+}
+
+/// Message counts of every stream by symbol, in stream order.
+type MessageCounts = Vec<HashMap<String, u64>>;
+
+#[derive(Debug, Default)]
+struct SloState {
+    /// Counts taken at each check of the last window, oldest first
+    samples: VecDeque<(Instant, MessageCounts)>,
+    violations: Vec<SloViolation>,
+    breached_since: Option<DateTime<Utc>>,
+    healthy_since: Option<Instant>,
+}
+
+pub struct SloMonitor {
+    config: SloConfig,
+    symbols: Vec<String>,
+    streams: Vec<Arc<StreamHealth>>,
+    prices: Arc<PriceCache>,
+    state: Mutex<SloState>,
+}
+
+impl SloMonitor {
+    /// Watches `symbols` on `streams` and in `prices`.
+    pub fn new(config: SloConfig, symbols: Vec<String>, streams: Vec<Arc<StreamHealth>>, prices: Arc<PriceCache>) -> Self {
+        Self { config, symbols, streams, prices, state: Mutex::new(SloState::default()) }
+    }
+
+    /// Samples the message counts and quote ages at `now`, `wall` being
+    /// the same moment in UTC. Message rates are only judged once a full
+    /// `rate_window_seconds` has been sampled.
+    pub fn check(&self, now: Instant, wall: DateTime<Utc>) -> Option<SloTransition> {
+        let mut state = self.state.lock().unwrap();
+        let window = Duration::from_secs(self.config.rate_window_seconds);
+        state.samples.push_back((now, self.streams.iter().map(|s| s.message_counts()).collect()));
+        while state.samples.len() > 2 && now.saturating_duration_since(state.samples[1].0) >= window {
+            state.samples.pop_front();
+        }
+
+        let mut violations = Vec::new();
+        if self.config.min_messages_per_minute > 0.0 {
+            let (oldest, first) = &state.samples[0];
+            let elapsed = now.saturating_duration_since(*oldest);
+            if elapsed >= window {
+                let last = &state.samples[state.samples.len() - 1].1;
+                let minutes = elapsed.as_secs_f64() / 60.0;
+                for ((stream, first), last) in self.streams.iter().zip(first).zip(last) {
+                    // Symbols a venue never sent a message for are not listed there
+                    for symbol in self.symbols.iter().filter(|symbol| last.contains_key(*symbol)) {
+                        let received = last[symbol] - first.get(symbol).copied().unwrap_or(0);
+                        let rate = received as f64 / minutes;
+                        if rate < self.config.min_messages_per_minute {
+                            violations.push(SloViolation {
+                                kind: SloKind::MessageRate,
+                                exchange: stream.exchange().to_string(),
+                                symbol: symbol.clone(),
+                                value: rate,
+                                limit: self.config.min_messages_per_minute,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        if self.config.max_quote_age_ms > 0 {
+            for exchange in ["Binance", "Bybit"] {
+                for symbol in &self.symbols {
+                    let Some(age) = self.prices.age(exchange, symbol, wall) else { continue };
+                    if age.num_milliseconds() > self.config.max_quote_age_ms as i64 {
+                        violations.push(SloViolation {
+                            kind: SloKind::QuoteAge,
+                            exchange: exchange.to_string(),
+                            symbol: symbol.clone(),
+                            value: age.num_milliseconds() as f64,
+                            limit: self.config.max_quote_age_ms as f64,
+                        });
+                    }
+                }
+            }
+        }
+
+        let breached = state.breached_since.is_some();
+        state.violations = violations.clone();
+        if !violations.is_empty() {
+            state.healthy_since = None;
+            if breached {
+                return None;
+            }
+            state.breached_since = Some(wall);
+            return Some(SloTransition::Breached(violations));
+        }
+        if !breached {
+            return None;
+        }
+        let healthy_since = *state.healthy_since.get_or_insert(now);
+        if now.saturating_duration_since(healthy_since) < Duration::from_secs(self.config.recovery_seconds) {
+            return None;
+        }
+        state.breached_since = None;
+        state.healthy_since = None;
+        Some(SloTransition::Recovered)
+    }
+
+    pub fn status(&self) -> SloStatus {
+        let state = self.state.lock().unwrap();
+        SloStatus {
+            breached: state.breached_since.is_some(),
+            breached_since: state.breached_since,
+            violations: state.violations.clone(),
+        }
+    }
+
+    pub fn prometheus_metrics(&self) -> String {
+        let status = self.status();
+        let mut out = String::new();
+        write_metric(&mut out, &metrics::SLO_BREACHED, &[(Vec::new(), if status.breached { 1.0 } else { 0.0 })]);
+        let violations: Vec<(Vec<String>, f64)> = [SloKind::MessageRate, SloKind::QuoteAge].into_iter()
+            .map(|kind| (vec![kind.label().to_string()], status.violations.iter().filter(|v| v.kind == kind).count() as f64))
+            .collect();
+        write_metric(&mut out, &metrics::SLO_VIOLATIONS, &violations);
+        out
+    }
+}
+
+/// One line describing `violations` for logs, alerts and the control API.
+pub fn summarize(violations: &[SloViolation]) -> String {
+    match violations {
+        [] => "no SLO violations".to_string(),
+        [only] => only.to_string(),
+        [first, rest @ ..] => format!("{} and {} more", first, rest.len()),
+    }
+}
+
+/// Checks `monitor` every second until `cancel` fires, handing each
+/// transition to `on_transition`.
+pub async fn watch<F, Fut>(monitor: Arc<SloMonitor>, cancel: CancellationToken, on_transition: F)
+where
+    F: Fn(SloTransition) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut checks = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = checks.tick() => {}
+        }
+        let Some(transition) = monitor.check(Instant::now(), Utc::now()) else { continue };
+        match &transition {
+            SloTransition::Breached(violations) => error!("Market data SLO violated, pausing execution: {}", summarize(violations)),
+            SloTransition::Recovered => info!("Market data SLOs met again, resuming execution"),
+        }
+        on_transition(transition).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+
+    fn monitor(config: SloConfig) -> (SloMonitor, Arc<StreamHealth>, Arc<PriceCache>) {
+        let symbols = vec!["BTCUSDT".to_string(), "ETHUSDT".to_string()];
+        let stream = Arc::new(StreamHealth::new("Binance", &symbols));
+        let prices = Arc::new(PriceCache::new());
+        (SloMonitor::new(config, symbols, vec![stream.clone()], prices.clone()), stream, prices)
+    }
+
+    #[test]
+    fn test_quiet_symbol_breaches_once_the_window_is_full() {
+        let config = SloConfig { enabled: true, min_messages_per_minute: 30.0, max_quote_age_ms: 0, rate_window_seconds: 10, recovery_seconds: 5, ..SloConfig::default() };
+        let (monitor, stream, _) = monitor(config);
+        let start = Instant::now();
+        let wall = Utc::now();
+        let at = |seconds: u64| start + Duration::from_secs(seconds);
+
+        // 1 BTC message a second, ETH silent after its first 5 messages
+        for second in 0..=10 {
+            stream.record_message("BTCUSDT");
+            if second < 5 {
+                stream.record_message("ETHUSDT");
+            }
+            let transition = monitor.check(at(second), wall);
+            if second < 10 {
+                assert_eq!(transition, None, "judged before the window was full at {}s", second);
+            } else {
+                let Some(SloTransition::Breached(violations)) = transition else { panic!("expected a breach") };
+                assert_eq!(violations.len(), 1);
+                assert_eq!((violations[0].kind, violations[0].symbol.as_str()), (SloKind::MessageRate, "ETHUSDT"));
+                // 4 messages between the first and the last sample
+                assert!((violations[0].value - 24.0).abs() < 1e-9);
+            }
+        }
+        assert!(monitor.status().breached);
+
+        // ETH back at 3 messages a second: recovered once that held for
+        // recovery_seconds
+        for second in 11..=20 {
+            stream.record_message("BTCUSDT");
+            for _ in 0..3 {
+                stream.record_message("ETHUSDT");
+            }
+            let transition = monitor.check(at(second), wall);
+            let expected = (second == 16).then_some(SloTransition::Recovered);
+            assert_eq!(transition, expected, "unexpected transition at {}s", second);
+        }
+        assert!(!monitor.status().breached);
+        assert!(monitor.prometheus_metrics().contains("arbitrage_slo_breached 0"));
+    }
+
+    #[test]
+    fn test_old_quotes_breach_until_refreshed() {
+        let config = SloConfig { enabled: true, min_messages_per_minute: 0.0, max_quote_age_ms: 3_000, recovery_seconds: 0, ..SloConfig::default() };
+        let (monitor, _, prices) = monitor(config);
+        let now = Utc::now();
+        let start = Instant::now();
+        prices.observe("Binance", "BTCUSDT", Decimal::from(64000), Some(now - chrono::Duration::seconds(1)), now);
+        prices.observe("Bybit", "ETHUSDT", Decimal::from(3000), Some(now - chrono::Duration::seconds(8)), now);
+
+        let Some(SloTransition::Breached(violations)) = monitor.check(start, now) else { panic!("expected a breach") };
+        assert_eq!(summarize(&violations), "Bybit ETHUSDT quote is 8000ms old, above 3000ms");
+        assert!(monitor.prometheus_metrics().contains("arbitrage_slo_violations{kind=\"quote_age\"} 1"));
+
+        prices.observe("Bybit", "ETHUSDT", Decimal::from(3000), Some(now), now);
+        assert_eq!(monitor.check(start + Duration::from_secs(1), now), Some(SloTransition::Recovered));
+    }
+}
@@ -0,0 +1,349 @@
+mod postgres;
+mod sqlite;
+
+pub use self::postgres::PostgresStorage;
+pub use self::sqlite::SqliteStorage;
+
+use crate::config::StorageConfig;
+use crate::exchanges::{ExchangeError, Kline, KlineInterval};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Where the store keeps its records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    /// JSON-lines files under `storage.data_dir`
+    #[default]
+    Files,
+    Sqlite,
+    Postgres,
+}
+
+/// Persistence behind `LocalStore`. Keys are relative paths such as
+/// `reports/executions.jsonl`: keys ending in `.jsonl` are append-only
+/// datasets of one JSON record per line, any other key is a document
+/// replaced as a whole.
+pub trait Storage: Send + Sync + Debug {
+    fn append(&self, key: &Path, lines: &[String]) -> Result<()>;
+    
+    /// Lines of a dataset in the order they were appended; empty when missing.
+    fn read(&self, key: &Path) -> Result<Vec<String>>;
+    
+    /// Replaces every line of a dataset atomically.
+    fn replace(&self, key: &Path, lines: &[String]) -> Result<()>;
+    
+    /// Replaces a document atomically.
+    fn put(&self, key: &Path, contents: &str) -> Result<()>;
+    
+    fn get(&self, key: &Path) -> Result<Option<String>>;
+    
+    /// Removes a dataset or document; missing keys are not an error.
+    fn remove(&self, key: &Path) -> Result<()>;
+    
+    /// Datasets and documents under `prefix`, sorted.
+    fn keys(&self, prefix: &Path) -> Result<Vec<PathBuf>>;
+    
+    /// Where `key` is kept, for messages.
+    fn location(&self, key: &Path) -> String;
+}
+
+/// Dataset and document keys are stored with `/` separators everywhere.
+fn key_name(key: &Path) -> String {
+    key.to_string_lossy().replace('\\', "/")
+}
+
+/// Files under a root directory, the default backend.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+    
+    fn path(&self, key: &Path) -> Result<PathBuf> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Ok(path)
+    }
+}
+
+impl Storage for FileStorage {
+    fn append(&self, key: &Path, lines: &[String]) -> Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(self.path(key)?)?;
+        for line in lines {
+            writeln!(file, "{}", line)?;
+        }
+        Ok(())
+    }
+    
+    fn read(&self, key: &Path) -> Result<Vec<String>> {
+        let path = self.root.join(key);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        
+        let reader = BufReader::new(fs::File::open(&path)?);
+        let mut lines = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if !line.trim().is_empty() {
+                lines.push(line);
+            }
+        }
+        Ok(lines)
+    }
+    
+    fn replace(&self, key: &Path, lines: &[String]) -> Result<()> {
+        let contents: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        self.put(key, &contents)
+    }
+    
+    /// Writes to a temporary file first so a crash mid-write never leaves
+    /// a truncated file behind.
+    fn put(&self, key: &Path, contents: &str) -> Result<()> {
+        let path = self.path(key)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+    
+    fn get(&self, key: &Path) -> Result<Option<String>> {
+        let path = self.root.join(key);
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(fs::read_to_string(&path)?))
+    }
+    
+    fn remove(&self, key: &Path) -> Result<()> {
+        match fs::remove_file(self.root.join(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+    
+    fn keys(&self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let mut keys = Vec::new();
+        if self.root.join(prefix).is_file() {
+            keys.push(prefix.to_path_buf());
+        }
+        let mut pending = vec![prefix.to_path_buf()];
+        while let Some(relative) = pending.pop() {
+            let path = self.root.join(&relative);
+            if !path.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(&path)? {
+                let entry = entry?;
+                let child = relative.join(entry.file_name());
+                if entry.file_type()?.is_dir() {
+                    pending.push(child);
+                } else if child.extension().is_none_or(|ext| ext != "tmp") {
+                    keys.push(child);
+                }
+            }
+        }
+        keys.sort();
+        Ok(keys)
+    }
+    
+    fn location(&self, key: &Path) -> String {
+        self.root.join(key).display().to_string()
+    }
+}
+
+/// Store for downloaded market data and the bot's own records. Each
+/// dataset is a sequence of JSON records, so it can be appended to cheaply
+/// and inspected with standard tools; where they are kept depends on the
+/// backend, JSON-lines files under `root` by default.
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+    backend: Arc<dyn Storage>,
+}
+
+impl LocalStore {
+    /// A store keeping its records as files under `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Result<Self> {
+        let root = root.into();
+        let backend = Arc::new(FileStorage::new(&root)?);
+        Ok(Self { root, backend })
+    }
+    
+    /// A store on the backend `config` selects.
+    pub fn open(config: &StorageConfig) -> Result<Self> {
+        let root = PathBuf::from(&config.data_dir);
+        fs::create_dir_all(&root)?;
+        let backend: Arc<dyn Storage> = match config.backend {
+            StorageBackend::Files => Arc::new(FileStorage::new(&root)?),
+            StorageBackend::Sqlite => {
+                let path = config.database_url.clone().map(PathBuf::from).unwrap_or_else(|| root.join("arbitrage.db"));
+                Arc::new(SqliteStorage::open(&path, &config.bot_id)?)
+            }
+            StorageBackend::Postgres => {
+                let url = match &config.database_url {
+                    Some(url) => url.clone(),
+                    None => std::env::var(&config.database_url_env)
+                        .map_err(|_| anyhow::anyhow!("storage.database_url is unset and {} is not set", config.database_url_env))?,
+                };
+                Arc::new(PostgresStorage::connect(&url, &config.bot_id)?)
+            }
+        };
+        Ok(Self { root, backend })
+    }
+    
+    pub fn with_backend(root: impl Into<PathBuf>, backend: Arc<dyn Storage>) -> Self {
+        Self { root: root.into(), backend }
+    }
+    
+    /// The data directory; with a database backend only local scratch
+    /// files live here.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+    
+    pub fn backend(&self) -> &dyn Storage {
+        self.backend.as_ref()
+    }
+    
+    /// Where `relative` is kept, for messages.
+    pub fn location(&self, relative: &Path) -> String {
+        self.backend.location(relative)
+    }
+    
+    pub fn append_klines(
+        &self,
+        exchange: &str,
+        symbol: &str,
+        interval: KlineInterval,
+        klines: &[Kline],
+    ) -> Result<()> {
+        self.append_jsonl(&Self::kline_path(exchange, symbol, interval), klines)
+    }
+    
+    pub fn load_klines(&self, exchange: &str, symbol: &str, interval: KlineInterval) -> Result<Vec<Kline>> {
+        self.read_jsonl(&Self::kline_path(exchange, symbol, interval))
+    }
+    
+    pub fn last_kline_time(
+        &self,
+        exchange: &str,
+        symbol: &str,
+        interval: KlineInterval,
+    ) -> Result<Option<DateTime<Utc>>> {
+        Ok(self.load_klines(exchange, symbol, interval)?
+            .iter()
+            .map(|k| k.open_time)
+            .max())
+    }
+    
+    fn kline_path(exchange: &str, symbol: &str, interval: KlineInterval) -> PathBuf {
+        PathBuf::from("klines")
+            .join(exchange.to_lowercase())
+            .join(format!("{}_{}.jsonl", symbol, interval))
+    }
+    
+    pub(crate) fn append_jsonl<T: Serialize>(&self, relative: &Path, records: &[T]) -> Result<()> {
+        let lines = records.iter()
+            .map(|record| serde_json::to_string(record)
+                .map_err(|e| ExchangeError::ParseError(format!("Failed to serialize record: {}", e))))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.backend.append(relative, &lines)
+    }
+    
+    /// Replaces a single JSON document atomically.
+    pub(crate) fn write_json<T: Serialize>(&self, relative: &Path, value: &T) -> Result<()> {
+        let json = serde_json::to_string_pretty(value)
+            .map_err(|e| ExchangeError::ParseError(format!("Failed to serialize {}: {}", relative.display(), e)))?;
+        self.backend.put(relative, &json)
+    }
+    
+    /// Replaces a text file such as a rendered report, atomically like `write_json`.
+    pub(crate) fn write_text(&self, relative: &Path, contents: &str) -> Result<()> {
+        self.backend.put(relative, contents)
+    }
+    
+    pub(crate) fn read_json<T: DeserializeOwned>(&self, relative: &Path) -> Result<Option<T>> {
+        let Some(data) = self.backend.get(relative)? else { return Ok(None) };
+        Ok(Some(serde_json::from_str(&data)
+            .map_err(|e| ExchangeError::ParseError(format!("Corrupt {}: {}", self.location(relative), e)))?))
+    }
+    
+    /// Rewrites a dataset with `records`, atomically like `write_json`.
+    pub(crate) fn replace_jsonl<T: Serialize>(&self, relative: &Path, records: &[T]) -> Result<()> {
+        let lines = records.iter()
+            .map(|record| serde_json::to_string(record)
+                .map_err(|e| ExchangeError::ParseError(format!("Failed to serialize record: {}", e))))
+            .collect::<Result<Vec<_>, _>>()?;
+        self.backend.replace(relative, &lines)
+    }
+    
+    pub(crate) fn remove(&self, relative: &Path) -> Result<()> {
+        self.backend.remove(relative)
+    }
+    
+    /// Datasets and documents under `prefix`, sorted.
+    pub(crate) fn keys(&self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        self.backend.keys(prefix)
+    }
+    
+    pub(crate) fn read_jsonl<T: DeserializeOwned>(&self, relative: &Path) -> Result<Vec<T>> {
+        let mut records = Vec::new();
+        for line in self.backend.read(relative)? {
+            records.push(serde_json::from_str(&line)
+                .map_err(|e| ExchangeError::ParseError(format!("Corrupt record in {}: {}", self.location(relative), e)))?);
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    
+    fn kline(minute: i64, close: i64) -> Kline {
+        Kline {
+            open_time: DateTime::<Utc>::from_timestamp(minute * 60, 0).unwrap(),
+            open: Decimal::from(close),
+            high: Decimal::from(close),
+            low: Decimal::from(close),
+            close: Decimal::from(close),
+            volume: Decimal::ONE,
+        }
+    }
+    
+    #[test]
+    fn test_kline_append_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = LocalStore::new(dir.path()).unwrap();
+        
+        store.append_klines("Binance", "BTCUSDT", KlineInterval::OneMinute, &[kline(1, 100), kline(2, 101)]).unwrap();
+        store.append_klines("Binance", "BTCUSDT", KlineInterval::OneMinute, &[kline(3, 102)]).unwrap();
+        
+        let loaded = store.load_klines("Binance", "BTCUSDT", KlineInterval::OneMinute).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded[2].close, Decimal::from(102));
+        assert_eq!(
+            store.last_kline_time("Binance", "BTCUSDT", KlineInterval::OneMinute).unwrap(),
+            Some(kline(3, 102).open_time)
+        );
+        assert!(store.load_klines("Bybit", "BTCUSDT", KlineInterval::OneMinute).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,173 @@
+use super::{key_name, Storage};
+use anyhow::Result;
+use postgres::Client;
+use postgres_native_tls::MakeTlsConnector;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Mutex;
+use tokio::runtime::{Handle, RuntimeFlavor};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS records (
+        id BIGSERIAL PRIMARY KEY,
+        bot_id TEXT NOT NULL,
+        dataset TEXT NOT NULL,
+        recorded_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+        body JSONB NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS records_by_dataset ON records (bot_id, dataset, id);
+    CREATE TABLE IF NOT EXISTS documents (
+        bot_id TEXT NOT NULL,
+        key TEXT NOT NULL,
+        updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+        body TEXT NOT NULL,
+        PRIMARY KEY (bot_id, key)
+    );
+";
+
+type Job = Box<dyn FnOnce(&mut Client) + Send>;
+
+/// Records and documents in a Postgres database shared by any number of
+/// bots, each row tagged with the bot that wrote it. Record bodies are
+/// JSONB, ready for analytics queries.
+///
+/// The blocking client runs on a thread of its own, since it drives its
+/// own runtime and cannot be called from inside the bot's.
+#[derive(Debug)]
+pub struct PostgresStorage {
+    bot_id: String,
+    /// Host and database, without credentials
+    target: String,
+    jobs: Mutex<Sender<Job>>,
+}
+
+impl PostgresStorage {
+    pub fn connect(url: &str, bot_id: &str) -> Result<Self> {
+        let config: postgres::Config = url.parse()?;
+        let target = format!("{:?}/{}", config.get_hosts(), config.get_dbname().unwrap_or_default());
+        let tls = MakeTlsConnector::new(native_tls::TlsConnector::new()?);
+        let (jobs, queue) = mpsc::channel::<Job>();
+        let (ready, connected) = mpsc::channel();
+        std::thread::Builder::new().name("postgres-storage".to_string()).spawn(move || {
+            let mut client = match config.connect(tls).and_then(|mut client| client.batch_execute(SCHEMA).map(|()| client)) {
+                Ok(client) => {
+                    let _ = ready.send(Ok(()));
+                    client
+                }
+                Err(e) => {
+                    let _ = ready.send(Err(e));
+                    return;
+                }
+            };
+            for job in queue {
+                job(&mut client);
+            }
+        })?;
+        connected.recv()?.map_err(|e| anyhow::anyhow!("Failed to connect to Postgres at {}: {}", target, e))?;
+        Ok(Self { bot_id: bot_id.to_string(), target, jobs: Mutex::new(jobs) })
+    }
+
+    /// Runs `query` on the connection thread and waits for its result.
+    /// Called from a task on the bot's runtime, the wait goes through
+    /// `block_in_place`, so the worker's other tasks move to another
+    /// thread instead of stalling until the query returns.
+    fn run<T, F>(&self, query: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Client) -> Result<T, postgres::Error> + Send + 'static,
+    {
+        let (done, result) = mpsc::channel();
+        self.jobs.lock().unwrap()
+            .send(Box::new(move |client| { let _ = done.send(query(client)); }))
+            .map_err(|_| anyhow::anyhow!("Postgres connection thread has stopped"))?;
+        let result = match Handle::try_current().map(|runtime| runtime.runtime_flavor()) {
+            Ok(RuntimeFlavor::MultiThread) => tokio::task::block_in_place(|| result.recv()),
+            // A current-thread runtime has no other worker to hand its tasks to
+            _ => result.recv(),
+        };
+        Ok(result??)
+    }
+}
+
+impl Storage for PostgresStorage {
+    fn append(&self, key: &Path, lines: &[String]) -> Result<()> {
+        let (bot_id, dataset, lines) = (self.bot_id.clone(), key_name(key), lines.to_vec());
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            let insert = transaction.prepare("INSERT INTO records (bot_id, dataset, body) VALUES ($1, $2, $3::text::jsonb)")?;
+            for line in &lines {
+                transaction.execute(&insert, &[&bot_id, &dataset, line])?;
+            }
+            transaction.commit()
+        })
+    }
+
+    fn read(&self, key: &Path) -> Result<Vec<String>> {
+        let (bot_id, dataset) = (self.bot_id.clone(), key_name(key));
+        self.run(move |client| {
+            let rows = client.query("SELECT body::text FROM records WHERE bot_id = $1 AND dataset = $2 ORDER BY id", &[&bot_id, &dataset])?;
+            Ok(rows.iter().map(|row| row.get(0)).collect())
+        })
+    }
+
+    fn replace(&self, key: &Path, lines: &[String]) -> Result<()> {
+        let (bot_id, dataset, lines) = (self.bot_id.clone(), key_name(key), lines.to_vec());
+        self.run(move |client| {
+            let mut transaction = client.transaction()?;
+            transaction.execute("DELETE FROM records WHERE bot_id = $1 AND dataset = $2", &[&bot_id, &dataset])?;
+            let insert = transaction.prepare("INSERT INTO records (bot_id, dataset, body) VALUES ($1, $2, $3::text::jsonb)")?;
+            for line in &lines {
+                transaction.execute(&insert, &[&bot_id, &dataset, line])?;
+            }
+            transaction.commit()
+        })
+    }
+
+    fn put(&self, key: &Path, contents: &str) -> Result<()> {
+        let (bot_id, key, contents) = (self.bot_id.clone(), key_name(key), contents.to_string());
+        self.run(move |client| {
+            client.execute(
+                "INSERT INTO documents (bot_id, key, body) VALUES ($1, $2, $3)
+                 ON CONFLICT (bot_id, key) DO UPDATE SET updated_at = now(), body = excluded.body",
+                &[&bot_id, &key, &contents],
+            ).map(|_| ())
+        })
+    }
+
+    fn get(&self, key: &Path) -> Result<Option<String>> {
+        let (bot_id, key) = (self.bot_id.clone(), key_name(key));
+        self.run(move |client| {
+            let row = client.query_opt("SELECT body FROM documents WHERE bot_id = $1 AND key = $2", &[&bot_id, &key])?;
+            Ok(row.map(|row| row.get(0)))
+        })
+    }
+
+    fn remove(&self, key: &Path) -> Result<()> {
+        let (bot_id, key) = (self.bot_id.clone(), key_name(key));
+        self.run(move |client| {
+            client.execute("DELETE FROM records WHERE bot_id = $1 AND dataset = $2", &[&bot_id, &key])?;
+            client.execute("DELETE FROM documents WHERE bot_id = $1 AND key = $2", &[&bot_id, &key]).map(|_| ())
+        })
+    }
+
+    fn keys(&self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let (bot_id, prefix) = (self.bot_id.clone(), key_name(prefix));
+        let below = format!("{}/%", prefix.replace('%', "\\%").replace('_', "\\_"));
+        self.run(move |client| {
+            let rows = client.query(
+                "SELECT DISTINCT dataset FROM records WHERE bot_id = $1 AND (dataset = $2 OR dataset LIKE $3)
+                 UNION SELECT key FROM documents WHERE bot_id = $1 AND (key = $2 OR key LIKE $3)
+                 ORDER BY 1",
+                &[&bot_id, &prefix, &below],
+            )?;
+            Ok(rows.iter().map(|row| PathBuf::from(row.get::<_, String>(0))).collect())
+        })
+    }
+
+    fn location(&self, key: &Path) -> String {
+        match key_name(key) {
+            key if key.is_empty() => format!("postgres {} (bot {})", self.target, self.bot_id),
+            key => format!("postgres {} ({}, bot {})", self.target, key, self.bot_id),
+        }
+    }
+}
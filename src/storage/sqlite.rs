@@ -0,0 +1,170 @@
+use super::{key_name, Storage};
+use anyhow::Result;
+use chrono::Utc;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS records (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        bot_id TEXT NOT NULL,
+        dataset TEXT NOT NULL,
+        recorded_at TEXT NOT NULL,
+        body TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS records_by_dataset ON records (bot_id, dataset, id);
+    CREATE TABLE IF NOT EXISTS documents (
+        bot_id TEXT NOT NULL,
+        key TEXT NOT NULL,
+        updated_at TEXT NOT NULL,
+        body TEXT NOT NULL,
+        PRIMARY KEY (bot_id, key)
+    );
+";
+
+/// Records and documents in one SQLite file, each row tagged with the
+/// bot that wrote it. Record bodies are JSON text, so they can be queried
+/// with SQLite's JSON functions.
+#[derive(Debug)]
+pub struct SqliteStorage {
+    path: PathBuf,
+    bot_id: String,
+    connection: Mutex<Connection>,
+}
+
+impl SqliteStorage {
+    pub fn open(path: &Path, bot_id: &str) -> Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let connection = Connection::open(path)?;
+        // Lets several bots on one host share the file
+        connection.busy_timeout(std::time::Duration::from_secs(5))?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(SCHEMA)?;
+        Ok(Self { path: path.to_path_buf(), bot_id: bot_id.to_string(), connection: Mutex::new(connection) })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn append(&self, key: &Path, lines: &[String]) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO records (bot_id, dataset, recorded_at, body) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let now = Utc::now().to_rfc3339();
+            for line in lines {
+                insert.execute(params![self.bot_id, key_name(key), now, line])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn read(&self, key: &Path) -> Result<Vec<String>> {
+        let connection = self.connection.lock().unwrap();
+        let mut select = connection.prepare_cached("SELECT body FROM records WHERE bot_id = ?1 AND dataset = ?2 ORDER BY id")?;
+        let lines = select.query_map(params![self.bot_id, key_name(key)], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(lines)
+    }
+
+    fn replace(&self, key: &Path, lines: &[String]) -> Result<()> {
+        let mut connection = self.connection.lock().unwrap();
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM records WHERE bot_id = ?1 AND dataset = ?2", params![self.bot_id, key_name(key)])?;
+        {
+            let mut insert = transaction.prepare_cached(
+                "INSERT INTO records (bot_id, dataset, recorded_at, body) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            let now = Utc::now().to_rfc3339();
+            for line in lines {
+                insert.execute(params![self.bot_id, key_name(key), now, line])?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn put(&self, key: &Path, contents: &str) -> Result<()> {
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO documents (bot_id, key, updated_at, body) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT (bot_id, key) DO UPDATE SET updated_at = excluded.updated_at, body = excluded.body",
+            params![self.bot_id, key_name(key), Utc::now().to_rfc3339(), contents],
+        )?;
+        Ok(())
+    }
+
+    fn get(&self, key: &Path) -> Result<Option<String>> {
+        let body = self.connection.lock().unwrap()
+            .query_row("SELECT body FROM documents WHERE bot_id = ?1 AND key = ?2", params![self.bot_id, key_name(key)], |row| row.get(0))
+            .optional()?;
+        Ok(body)
+    }
+
+    fn remove(&self, key: &Path) -> Result<()> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute("DELETE FROM records WHERE bot_id = ?1 AND dataset = ?2", params![self.bot_id, key_name(key)])?;
+        connection.execute("DELETE FROM documents WHERE bot_id = ?1 AND key = ?2", params![self.bot_id, key_name(key)])?;
+        Ok(())
+    }
+
+    fn keys(&self, prefix: &Path) -> Result<Vec<PathBuf>> {
+        let prefix = key_name(prefix);
+        let connection = self.connection.lock().unwrap();
+        let mut select = connection.prepare_cached(
+            "SELECT DISTINCT dataset FROM records WHERE bot_id = ?1
+             UNION SELECT key FROM documents WHERE bot_id = ?1",
+        )?;
+        let mut keys: Vec<PathBuf> = select.query_map(params![self.bot_id], |row| row.get::<_, String>(0))?
+            .filter_map(|key| key.ok())
+            .filter(|key| key == &prefix || key.starts_with(&format!("{}/", prefix)))
+            .map(PathBuf::from)
+            .collect();
+        keys.sort();
+        Ok(keys)
+    }
+
+    fn location(&self, key: &Path) -> String {
+        match key_name(key) {
+            key if key.is_empty() => format!("{} (bot {})", self.path.display(), self.bot_id),
+            key => format!("{} ({}, bot {})", self.path.display(), key, self.bot_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bots_sharing_a_database_keep_their_own_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("arbitrage.db");
+        let first = SqliteStorage::open(&path, "eu-1").unwrap();
+        let second = SqliteStorage::open(&path, "us-1").unwrap();
+        let dataset = Path::new("reports/executions.jsonl");
+
+        first.append(dataset, &["{\"n\":1}".to_string(), "{\"n\":2}".to_string()]).unwrap();
+        second.append(dataset, &["{\"n\":3}".to_string()]).unwrap();
+        first.append(dataset, &["{\"n\":4}".to_string()]).unwrap();
+        assert_eq!(first.read(dataset).unwrap(), ["{\"n\":1}", "{\"n\":2}", "{\"n\":4}"]);
+        assert_eq!(second.read(dataset).unwrap(), ["{\"n\":3}"]);
+
+        let document = Path::new("risk/kill_switch.json");
+        first.put(document, "{\"loss\":1}").unwrap();
+        first.put(document, "{\"loss\":2}").unwrap();
+        assert_eq!(first.get(document).unwrap().as_deref(), Some("{\"loss\":2}"));
+        assert_eq!(second.get(document).unwrap(), None);
+
+        assert_eq!(first.keys(Path::new("reports")).unwrap(), [PathBuf::from("reports/executions.jsonl")]);
+        assert_eq!(first.keys(Path::new("risk/kill_switch.json")).unwrap().len(), 1);
+        assert!(first.keys(Path::new("rep")).unwrap().is_empty());
+        first.remove(dataset).unwrap();
+        assert!(first.read(dataset).unwrap().is_empty());
+        assert_eq!(second.read(dataset).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::config::StressTestConfig;
+use crate::report::ExecutionReport;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::prelude::*;
+use serde::Serialize;
+
+/// The opportunity shape being stress-tested.
+#[derive(Debug, Clone, Copy)]
+pub struct OpportunityProfile {
+    pub legs: usize,
+    pub position_usd: f64,
+    /// Expected net profit after fees, in percent
+    pub net_profit_percentage: f64,
+}
+
+/// Empirical execution outcomes drawn on by the simulation.
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionDistributions {
+    /// Adverse slippage of filled legs in basis points
+    pub leg_slippage_bps: Vec<f64>,
+    /// Share of attempted legs that did not fill completely
+    pub leg_failure_rate: f64,
+}
+
+impl ExecutionDistributions {
+    pub fn from_reports(reports: &[ExecutionReport]) -> Self {
+        let legs: Vec<_> = reports.iter().flat_map(|r| &r.legs).collect();
+        let failed = legs.iter().filter(|l| l.filled_quantity < l.expected_quantity).count();
+        Self {
+            leg_slippage_bps: legs.iter()
+                .filter_map(|l| l.slippage_bps.and_then(|s| s.to_f64()))
+                .collect(),
+            leg_failure_rate: if legs.is_empty() { 0.0 } else { failed as f64 / legs.len() as f64 },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StressReport {
+    pub runs: usize,
+    pub failed_runs: usize,
+    pub mean_pnl_usd: f64,
+    pub probability_of_loss: f64,
+    /// Loss not exceeded at the configured confidence, as a positive amount
+    pub value_at_risk_usd: f64,
+    /// Mean loss beyond the VaR
+    pub expected_shortfall_usd: f64,
+    /// Cycle slippage at the confidence quantile over completed runs, a
+    /// starting point for `risk.max_cycle_slippage_percentage`
+    pub cycle_slippage_quantile_percentage: f64,
+}
+
+/// Simulates executing `profile` `config.runs` times. Each leg fails with
+/// the empirical leg failure rate; a failure strands the position, which
+/// is unwound at `unwind_loss_percentage` on top of the slippage already
+/// incurred. Completed legs draw their slippage from the empirical samples.
+pub fn simulate(
+    profile: OpportunityProfile,
+    distributions: &ExecutionDistributions,
+    config: &StressTestConfig,
+) -> StressReport {
+    let mut rng = match config.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    
+    let mut pnls = Vec::with_capacity(config.runs);
+    let mut cycle_slippage = Vec::new();
+    let mut failed_runs = 0;
+    
+    for _ in 0..config.runs {
+        let mut slippage_bps = 0.0;
+        let mut failed = false;
+        for _ in 0..profile.legs {
+            if rng.gen::<f64>() < distributions.leg_failure_rate {
+                failed = true;
+                break;
+            }
+            if !distributions.leg_slippage_bps.is_empty() {
+                slippage_bps += distributions.leg_slippage_bps[rng.gen_range(0..distributions.leg_slippage_bps.len())];
+            }
+        }
+        
+        let pnl_percentage = if failed {
+            failed_runs += 1;
+            -(slippage_bps / 100.0) - config.unwind_loss_percentage
+        } else {
+            cycle_slippage.push(slippage_bps / 100.0);
+            profile.net_profit_percentage - slippage_bps / 100.0
+        };
+        pnls.push(profile.position_usd * pnl_percentage / 100.0);
+    }
+    
+    pnls.sort_by(f64::total_cmp);
+    cycle_slippage.sort_by(f64::total_cmp);
+    
+    let runs = pnls.len().max(1);
+    let tail = ((1.0 - config.confidence) * runs as f64).ceil().max(1.0) as usize;
+    let tail = &pnls[..tail.min(pnls.len())];
+    let value_at_risk = tail.last().map(|p| (-p).max(0.0)).unwrap_or(0.0);
+    let expected_shortfall = if tail.is_empty() {
+        0.0
+    } else {
+        (-tail.iter().sum::<f64>() / tail.len() as f64).max(0.0)
+    };
+    
+    StressReport {
+        runs: pnls.len(),
+        failed_runs,
+        mean_pnl_usd: pnls.iter().sum::<f64>() / runs as f64,
+        probability_of_loss: pnls.iter().filter(|p| **p < 0.0).count() as f64 / runs as f64,
+        value_at_risk_usd: value_at_risk,
+        expected_shortfall_usd: expected_shortfall,
+        cycle_slippage_quantile_percentage: quantile(&cycle_slippage, config.confidence),
+    }
+}
+
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.0;
+    }
+    let index = ((sorted.len() - 1) as f64 * q).round() as usize;
+    sorted[index.min(sorted.len() - 1)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn profile() -> OpportunityProfile {
+        OpportunityProfile { legs: 3, position_usd: 1000.0, net_profit_percentage: 0.4 }
+    }
+    
+    fn config() -> StressTestConfig {
+        StressTestConfig { seed: Some(7), ..StressTestConfig::default() }
+    }
+    
+    #[test]
+    fn test_no_slippage_or_failures_never_loses() {
+        let report = simulate(profile(), &ExecutionDistributions::default(), &config());
+        assert_eq!(report.failed_runs, 0);
+        assert_eq!(report.probability_of_loss, 0.0);
+        assert_eq!(report.value_at_risk_usd, 0.0);
+        assert!((report.mean_pnl_usd - 4.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_failures_and_slippage_create_tail_losses() {
+        let distributions = ExecutionDistributions {
+            leg_slippage_bps: vec![0.0, 5.0, 20.0],
+            leg_failure_rate: 0.05,
+        };
+        let report = simulate(profile(), &distributions, &config());
+        
+        assert!(report.failed_runs > 0);
+        assert!(report.probability_of_loss > 0.1 && report.probability_of_loss < 1.0);
+        // Failures lose at least the unwind cost
+        assert!(report.value_at_risk_usd >= 1000.0 * config().unwind_loss_percentage / 100.0);
+        assert!(report.expected_shortfall_usd >= report.value_at_risk_usd);
+        assert!(report.cycle_slippage_quantile_percentage > 0.0);
+        
+        let again = simulate(profile(), &distributions, &config());
+        assert_eq!(again.failed_runs, report.failed_runs);
+    }
+}
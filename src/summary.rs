@@ -0,0 +1,415 @@
+use crate::arbitrage::ArbitrageOpportunity;
+use crate::currency::CurrencyConverter;
+use crate::report::ExecutionReport;
+use crate::storage::LocalStore;
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use log::{info, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DailySummaryConfig {
+    pub enabled: bool,
+    /// Write `summaries/<date>.html` under `storage.data_dir`
+    pub save_to_disk: bool,
+    /// Number of most frequent paths listed
+    pub top_paths: usize,
+    pub smtp: Option<SmtpConfig>,
+}
+
+impl Default for DailySummaryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            save_to_disk: true,
+            top_paths: 10,
+            smtp: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    #[serde(default = "default_smtp_port")]
+    pub port: u16,
+    /// Upgrade the connection with STARTTLS; only disable for local relays
+    #[serde(default = "default_starttls")]
+    pub starttls: bool,
+    #[serde(default)]
+    pub username: Option<String>,
+    /// Environment variable holding the password, so it stays out of the config file
+    #[serde(default = "default_password_env")]
+    pub password_env: String,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+fn default_smtp_port() -> u16 {
+    587
+}
+
+fn default_starttls() -> bool {
+    true
+}
+
+fn default_password_env() -> String {
+    "SMTP_PASSWORD".to_string()
+}
+
+impl SmtpConfig {
+    pub fn validate(&self) -> Result<()> {
+        if self.to.is_empty() {
+            return Err(anyhow::anyhow!("smtp.to needs at least one recipient"));
+        }
+        for address in std::iter::once(&self.from).chain(&self.to) {
+            address.parse::<lettre::message::Mailbox>()
+                .map_err(|e| anyhow::anyhow!("Invalid email address '{}': {}", address, e))?;
+        }
+        Ok(())
+    }
+}
+
+impl DailySummaryConfig {
+    pub fn validate(&self) -> Result<()> {
+        let Some(smtp) = &self.smtp else { return Ok(()) };
+        smtp.validate().context("Invalid monitoring.daily_summary settings")
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PathSummary {
+    pub exchange: String,
+    pub legs: String,
+    pub opportunities: u64,
+    pub best_net_profit_percentage: Decimal,
+}
+
+/// One UTC day of bot activity.
+#[derive(Debug, Clone, Serialize)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub uptime_seconds: i64,
+    pub scans: u64,
+    pub opportunities: u64,
+    pub top_paths: Vec<PathSummary>,
+    pub executions: usize,
+    pub completed_executions: usize,
+    /// Profit the executed opportunities were expected to make at detection
+    pub theoretical_pnl_usd: Decimal,
+    pub realized_pnl_usd: Decimal,
+    pub errors: BTreeMap<String, u64>,
+}
+
+#[derive(Debug)]
+struct DayStats {
+    date: NaiveDate,
+    first_seen: DateTime<Utc>,
+    last_seen: DateTime<Utc>,
+    scans: u64,
+    paths: HashMap<(String, String), PathSummary>,
+    errors: BTreeMap<String, u64>,
+}
+
+impl DayStats {
+    fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            date: now.date_naive(),
+            first_seen: now,
+            last_seen: now,
+            scans: 0,
+            paths: HashMap::new(),
+            errors: BTreeMap::new(),
+        }
+    }
+}
+
+/// Counts scans, opportunities and errors per UTC day, and hands out the
+/// finished day's summary once the first event of the next day arrives.
+#[derive(Debug)]
+pub struct SummaryCollector {
+    top_paths: usize,
+    day: Mutex<Option<DayStats>>,
+}
+
+impl SummaryCollector {
+    pub fn new(top_paths: usize) -> Self {
+        Self { top_paths, day: Mutex::new(None) }
+    }
+
+    /// Records a completed scan; returns the previous day's stats when
+    /// this is the first event of a new day.
+    pub fn record_scan(&self, opportunities: &[ArbitrageOpportunity], now: DateTime<Utc>) -> Option<DailySummary> {
+        let mut day = self.day.lock().unwrap_or_else(|e| e.into_inner());
+        let (day, finished) = self.roll_over(&mut day, now);
+        day.scans += 1;
+        for opportunity in opportunities {
+            let mut legs: Vec<&str> = opportunity.execution_steps.iter().map(|s| s.symbol.as_str()).collect();
+            legs.dedup();
+            let legs = legs.join(" -> ");
+            let entry = day.paths.entry((opportunity.exchange.clone(), legs.clone())).or_insert_with(|| PathSummary {
+                exchange: opportunity.exchange.clone(),
+                legs,
+                opportunities: 0,
+                best_net_profit_percentage: opportunity.net_profit_percentage,
+            });
+            entry.opportunities += 1;
+            entry.best_net_profit_percentage = entry.best_net_profit_percentage.max(opportunity.net_profit_percentage);
+        }
+        finished
+    }
+
+    /// Counts an error under `kind`, e.g. the exchange whose request failed.
+    pub fn record_error(&self, kind: &str, now: DateTime<Utc>) -> Option<DailySummary> {
+        let mut day = self.day.lock().unwrap_or_else(|e| e.into_inner());
+        let (day, finished) = self.roll_over(&mut day, now);
+        *day.errors.entry(kind.to_string()).or_default() += 1;
+        finished
+    }
+
+    /// The stats of `now`'s day, plus the summary of the day it replaced.
+    fn roll_over<'a>(&self, day: &'a mut Option<DayStats>, now: DateTime<Utc>) -> (&'a mut DayStats, Option<DailySummary>) {
+        let finished = match day.take() {
+            Some(mut current) if now.date_naive() <= current.date => {
+                current.last_seen = now;
+                *day = Some(current);
+                None
+            }
+            Some(mut finished) => {
+                // The bot ran through midnight, so the old day lasted until its end
+                finished.last_seen = now.date_naive().and_hms_opt(0, 0, 0).map(|t| t.and_utc()).unwrap_or(now);
+                Some(self.summarize(finished))
+            }
+            None => None,
+        };
+        (day.get_or_insert_with(|| DayStats::new(now)), finished)
+    }
+
+    fn summarize(&self, day: DayStats) -> DailySummary {
+        let mut top_paths: Vec<PathSummary> = day.paths.into_values().collect();
+        top_paths.sort_by(|a, b| b.opportunities.cmp(&a.opportunities).then_with(|| a.legs.cmp(&b.legs)));
+        let opportunities = top_paths.iter().map(|p| p.opportunities).sum();
+        top_paths.truncate(self.top_paths);
+        DailySummary {
+            date: day.date,
+            uptime_seconds: day.last_seen.signed_duration_since(day.first_seen).num_seconds(),
+            scans: day.scans,
+            opportunities,
+            top_paths,
+            executions: 0,
+            completed_executions: 0,
+            theoretical_pnl_usd: Decimal::ZERO,
+            realized_pnl_usd: Decimal::ZERO,
+            errors: day.errors,
+        }
+    }
+}
+
+impl DailySummary {
+    /// Adds the executions of the summary's day from `reports`.
+    pub fn with_reports(mut self, reports: &[ExecutionReport]) -> Self {
+        for report in reports.iter().filter(|r| r.executed_at.date_naive() == self.date) {
+            self.executions += 1;
+            self.completed_executions += usize::from(report.completed);
+            self.theoretical_pnl_usd += report.theoretical_pnl_usd();
+            self.realized_pnl_usd += report.realized_pnl_usd();
+        }
+        self
+    }
+
+    /// The summary as an HTML page, with P&L in `currency`'s home currency.
+    pub fn render_html(&self, currency: &CurrencyConverter) -> String {
+        let mut html = String::new();
+        let _ = write!(html, "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Arbitrage summary {date}</title></head>\n<body>\n<h1>Arbitrage summary {date}</h1>\n", date = self.date);
+        html.push_str("<table>\n");
+        let hours = self.uptime_seconds as f64 / 3600.0;
+        for (label, value) in [
+            ("Uptime", format!("{:.1} h", hours)),
+            ("Scans", self.scans.to_string()),
+            ("Opportunities", self.opportunities.to_string()),
+            ("Executions", format!("{} ({} completed)", self.executions, self.completed_executions)),
+            ("Theoretical P&amp;L", currency.format(self.theoretical_pnl_usd)),
+            ("Realized P&amp;L", currency.format(self.realized_pnl_usd)),
+        ] {
+            let _ = writeln!(html, "<tr><th align=\"left\">{}</th><td>{}</td></tr>", label, escape(&value));
+        }
+        html.push_str("</table>\n<h2>Top paths</h2>\n");
+        if self.top_paths.is_empty() {
+            html.push_str("<p>No opportunities.</p>\n");
+        } else {
+            html.push_str("<table>\n<tr><th>Exchange</th><th>Legs</th><th>Opportunities</th><th>Best net %</th></tr>\n");
+            for path in &self.top_paths {
+                let _ = writeln!(html, "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                                 escape(&path.exchange), escape(&path.legs), path.opportunities, path.best_net_profit_percentage.round_dp(4));
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("<h2>Errors</h2>\n");
+        if self.errors.is_empty() {
+            html.push_str("<p>None.</p>\n");
+        } else {
+            html.push_str("<table>\n");
+            for (kind, count) in &self.errors {
+                let _ = writeln!(html, "<tr><td>{}</td><td>{}</td></tr>", escape(kind), count);
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body></html>\n");
+        html
+    }
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// Saves and mails finished daily summaries.
+#[derive(Debug, Clone)]
+pub struct SummaryDelivery {
+    config: DailySummaryConfig,
+    store: LocalStore,
+    currency: Arc<CurrencyConverter>,
+}
+
+impl SummaryDelivery {
+    pub fn new(config: DailySummaryConfig, store: LocalStore) -> Self {
+        Self { config, store, currency: Arc::new(CurrencyConverter::default()) }
+    }
+
+    /// Shows P&L in the home currency of `currency`.
+    pub fn with_currency(mut self, currency: Arc<CurrencyConverter>) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    pub async fn deliver(&self, summary: &DailySummary) {
+        let html = summary.render_html(&self.currency);
+        if self.config.save_to_disk {
+            let path = PathBuf::from("summaries").join(format!("{}.html", summary.date));
+            match self.store.write_text(&path, &html) {
+                Ok(()) => info!("Daily summary for {} saved to {}", summary.date, self.store.location(&path)),
+                Err(e) => warn!("Failed to save daily summary: {}", e),
+            }
+        }
+        if let Some(smtp) = &self.config.smtp {
+            match send_email(smtp, &format!("Arbitrage summary {}", summary.date), html).await {
+                Ok(()) => info!("Daily summary for {} sent to {}", summary.date, smtp.to.join(", ")),
+                Err(e) => warn!("Failed to email daily summary: {:#}", e),
+            }
+        }
+    }
+}
+
+pub(crate) async fn send_email(smtp: &SmtpConfig, subject: &str, html: String) -> Result<()> {
+    use lettre::message::header::ContentType;
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+    let mut builder = if smtp.starttls {
+        AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&smtp.host)?
+    } else {
+        AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&smtp.host)
+    };
+    builder = builder.port(smtp.port);
+    if let Some(username) = &smtp.username {
+        let password = std::env::var(&smtp.password_env)
+            .with_context(|| format!("{} is not set", smtp.password_env))?;
+        builder = builder.credentials(Credentials::new(username.clone(), password));
+    }
+
+    let mut message = Message::builder().from(smtp.from.parse()?).subject(subject);
+    for to in &smtp.to {
+        message = message.to(to.parse()?);
+    }
+    let message = message.header(ContentType::TEXT_HTML).body(html)?;
+    builder.build().send(message).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arbitrage::risk::Strategy;
+    use crate::arbitrage::ExecutionStep;
+    use crate::exchanges::OrderSide;
+
+    fn opportunity(exchange: &str, symbols: &[&str], net_profit: i64) -> ArbitrageOpportunity {
+        ArbitrageOpportunity {
+            exchange: exchange.to_string(),
+            strategy: Strategy::Triangular,
+            path: Vec::new(),
+            profit_percentage: Decimal::from(net_profit),
+            net_profit_percentage: Decimal::from(net_profit),
+            required_amount: Decimal::from(1000),
+            estimated_profit_usd: Decimal::ONE,
+            risk_score: 0.0,
+            execution_steps: symbols.iter().map(|symbol| ExecutionStep {
+                exchange: exchange.to_string(),
+                action: String::new(),
+                symbol: symbol.to_string(),
+                side: OrderSide::Buy,
+                quantity: Decimal::ONE,
+                expected_price: Decimal::ONE,
+                fees: Decimal::ZERO,
+                client_order_id: String::new(),
+            }).collect(),
+            leg_quotes: Vec::new(),
+            timestamp: Utc::now(),
+            scan_id: 0,
+        }
+    }
+
+    #[test]
+    fn test_summary_is_handed_out_at_day_rollover() {
+        let collector = SummaryCollector::new(1);
+        let day = "2024-03-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let triangle = opportunity("Binance", &["BTCUSDT", "ETHBTC", "ETHUSDT"], 1);
+        let pair = opportunity("Binance->Bybit", &["SOLUSDT", "SOLUSDT"], 2);
+
+        assert!(collector.record_scan(&[triangle.clone(), pair], day).is_none());
+        assert!(collector.record_scan(&[triangle], day + chrono::Duration::hours(1)).is_none());
+        assert!(collector.record_error("Bybit", day + chrono::Duration::hours(2)).is_none());
+
+        let summary = collector.record_scan(&[], day + chrono::Duration::hours(15)).unwrap();
+        assert_eq!(summary.date, day.date_naive());
+        assert_eq!(summary.scans, 2);
+        assert_eq!(summary.opportunities, 3);
+        assert_eq!(summary.uptime_seconds, 14 * 3600);
+        assert_eq!(summary.top_paths.len(), 1);
+        assert_eq!(summary.top_paths[0].legs, "BTCUSDT -> ETHBTC -> ETHUSDT");
+        assert_eq!(summary.errors.get("Bybit"), Some(&1));
+
+        let html = summary.render_html(&CurrencyConverter::default());
+        assert!(html.contains("BTCUSDT -&gt; ETHBTC -&gt; ETHUSDT"));
+        assert!(html.contains("<td>Bybit</td><td>1</td>"));
+        assert!(html.contains("<th align=\"left\">Realized P&amp;L</th><td>$0.00</td>"));
+
+        let euro = CurrencyConverter::new("EUR");
+        euro.set_rate(Decimal::from_str_exact("1.08").unwrap(), day);
+        let summary = DailySummary { realized_pnl_usd: Decimal::from(54), ..summary };
+        assert!(summary.render_html(&euro).contains("<th align=\"left\">Realized P&amp;L</th><td>50.00 EUR</td>"));
+    }
+
+    #[test]
+    fn test_invalid_recipient_is_rejected() {
+        let config = DailySummaryConfig {
+            smtp: Some(SmtpConfig {
+                host: "smtp.example.com".to_string(),
+                port: 587,
+                starttls: true,
+                username: None,
+                password_env: default_password_env(),
+                from: "bot@example.com".to_string(),
+                to: vec!["not an address".to_string()],
+            }),
+            ..DailySummaryConfig::default()
+        };
+        assert!(config.validate().is_err());
+    }
+}
@@ -0,0 +1,271 @@
+use crate::exchanges::PriceMap;
+use crate::exchanges::snapshot::PriceSnapshot;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// Quote assets recognised when splitting a symbol into base and quote,
+/// longest first so `FDUSD` wins over `USD`-suffixed matches.
+pub const QUOTE_ASSETS: &[&str] = &["FDUSD", "USDT", "USDC", "BUSD", "TUSD", "EUR", "TRY", "GBP", "BTC", "ETH", "BNB"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SymbolFilterConfig {
+    /// When non-empty, only symbols matching one of these patterns are used
+    pub allowlist: Vec<String>,
+    /// Symbols matching any of these patterns are never used
+    pub blacklist: Vec<String>,
+    /// Drop leveraged tokens such as BTCUP, ETHDOWN or BTC3L
+    pub exclude_leveraged_tokens: bool,
+    /// Drop symbols the exchange does not report as open for spot trading
+    pub require_trading_status: bool,
+    pub refresh_interval_minutes: u64,
+}
+
+impl Default for SymbolFilterConfig {
+    fn default() -> Self {
+        Self {
+            allowlist: Vec::new(),
+            blacklist: Vec::new(),
+            exclude_leveraged_tokens: true,
+            require_trading_status: true,
+            refresh_interval_minutes: 60,
+        }
+    }
+}
+
+/// What replacing one exchange's tradable list changed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ListingChanges {
+    /// Symbols that were not tradable before, sorted; empty for the first list
+    pub listed: Vec<String>,
+    /// Symbols that no longer are, sorted
+    pub delisted: Vec<String>,
+}
+
+impl ListingChanges {
+    pub fn is_empty(&self) -> bool {
+        self.listed.is_empty() && self.delisted.is_empty()
+    }
+}
+
+/// Decides which ticker symbols take part in opportunity detection.
+/// Patterns are matched case-insensitively and may use `*` as a wildcard.
+#[derive(Debug)]
+pub struct SymbolFilter {
+    config: SymbolFilterConfig,
+    tradable: DashMap<String, HashSet<String>>,
+    /// Configured markets per exchange missing a leg at the last check
+    unavailable: DashMap<String, HashSet<String>>,
+}
+
+impl SymbolFilter {
+    pub fn new(config: SymbolFilterConfig) -> Self {
+        Self { config, tradable: DashMap::new(), unavailable: DashMap::new() }
+    }
+    
+    /// Replaces the set of symbols `exchange` currently reports as tradable
+    /// and returns how it differs from the previous one.
+    pub fn set_tradable(&self, exchange: &str, symbols: HashSet<String>) -> ListingChanges {
+        let mut changes = ListingChanges::default();
+        if let Some(previous) = self.tradable.insert(exchange.to_string(), symbols) {
+            let current = self.tradable.get(exchange).expect("just inserted");
+            changes.listed = current.difference(&previous).cloned().collect();
+            changes.delisted = previous.difference(&current).cloned().collect();
+            changes.listed.sort();
+            changes.delisted.sort();
+        }
+        changes
+    }
+    
+    /// Whether `exchange` lists `symbol` as tradable; true until its first
+    /// list arrives.
+    pub fn is_listed(&self, exchange: &str, symbol: &str) -> bool {
+        self.tradable.get(exchange).is_none_or(|tradable| tradable.contains(symbol))
+    }
+    
+    /// Configured markets of `exchange` with a leg it does not list: each of
+    /// `pairs`, and each of `triangles` written as `A/B/C`.
+    pub fn unavailable_markets(&self, exchange: &str, pairs: &[String], triangles: &[(String, String, String)]) -> Vec<String> {
+        let pairs = pairs.iter()
+            .filter(|pair| !self.is_listed(exchange, pair))
+            .cloned();
+        let triangles = triangles.iter()
+            .filter(|(a, b, c)| [a, b, c].iter().any(|leg| !self.is_listed(exchange, leg)))
+            .map(|(a, b, c)| format!("{}/{}/{}", a, b, c));
+        pairs.chain(triangles).collect()
+    }
+    
+    /// Records `markets` as the unavailable ones of `exchange` and returns
+    /// those newly unavailable since the last call, then those back again.
+    pub fn track_unavailable(&self, exchange: &str, markets: Vec<String>) -> (Vec<String>, Vec<String>) {
+        let current: HashSet<String> = markets.into_iter().collect();
+        let previous = self.unavailable.insert(exchange.to_string(), current.clone()).unwrap_or_default();
+        let mut lost: Vec<String> = current.difference(&previous).cloned().collect();
+        let mut restored: Vec<String> = previous.difference(&current).cloned().collect();
+        lost.sort();
+        restored.sort();
+        (lost, restored)
+    }
+    
+    /// Tradable symbol lists per exchange, for warm-start persistence.
+    pub fn tradable_snapshot(&self) -> HashMap<String, Vec<String>> {
+        self.tradable.iter()
+            .map(|entry| {
+                let mut symbols: Vec<String> = entry.value().iter().cloned().collect();
+                symbols.sort();
+                (entry.key().clone(), symbols)
+            })
+            .collect()
+    }
+    
+    pub fn is_allowed(&self, exchange: &str, symbol: &str) -> bool {
+        if !self.config.allowlist.is_empty() && !self.config.allowlist.iter().any(|p| glob_match(p, symbol)) {
+            return false;
+        }
+        if self.config.blacklist.iter().any(|p| glob_match(p, symbol)) {
+            return false;
+        }
+        if self.config.exclude_leveraged_tokens && is_leveraged_token(symbol) {
+            return false;
+        }
+        if self.config.require_trading_status {
+            // Without exchange info yet, fall back to the ticker list
+            if let Some(tradable) = self.tradable.get(exchange) {
+                return tradable.contains(symbol);
+            }
+        }
+        true
+    }
+    
+    pub fn filter(&self, exchange: &str, prices: PriceMap) -> PriceMap {
+        prices.into_iter()
+            .filter(|(symbol, _)| self.is_allowed(exchange, symbol))
+            .collect()
+    }
+    
+    /// `filter` for a snapshot, dropping disallowed symbols in place.
+    pub fn filter_snapshot(&self, exchange: &str, prices: &mut PriceSnapshot) {
+        prices.retain(|symbol| self.is_allowed(exchange, symbol));
+    }
+}
+
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern = pattern.to_ascii_uppercase();
+    let text = text.to_ascii_uppercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+    
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+/// Base and quote asset of an uppercase symbol such as `ETHBTC`, when it
+/// ends in a recognised quote asset.
+pub fn split_symbol(symbol: &str) -> Option<(&str, &str)> {
+    QUOTE_ASSETS.iter()
+        .find_map(|quote| symbol.strip_suffix(quote).filter(|b| !b.is_empty()).map(|base| (base, *quote)))
+}
+
+/// Heuristic for leveraged tokens, which neither venue flags in its
+/// instrument data: Binance's `UP`/`DOWN`/`BULL`/`BEAR` bases and Bybit's
+/// `2L`/`3S`-style suffixes.
+pub fn is_leveraged_token(symbol: &str) -> bool {
+    let symbol = symbol.to_ascii_uppercase();
+    let Some((base, _)) = split_symbol(&symbol) else {
+        return false;
+    };
+    
+    let bytes = base.as_bytes();
+    if bytes.len() >= 3 && bytes[bytes.len() - 2].is_ascii_digit() && matches!(bytes[bytes.len() - 1], b'L' | b'S') {
+        return true;
+    }
+    
+    ["UP", "DOWN", "BULL", "BEAR"].iter().any(|suffix| {
+        // Require a real base in front so JUP or SUN-style names are kept
+        base.strip_suffix(suffix).map(|b| b.len() >= 3).unwrap_or(false)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_leveraged_token_detection() {
+        for symbol in ["BTCUPUSDT", "ETHDOWNUSDT", "BTC3LUSDT", "ETH2SUSDT", "XRPBULLUSDT"] {
+            assert!(is_leveraged_token(symbol), "{}", symbol);
+        }
+        for symbol in ["BTCUSDT", "JUPUSDT", "ETHBTC", "SUSHIUSDT", "1INCHUSDT"] {
+            assert!(!is_leveraged_token(symbol), "{}", symbol);
+        }
+    }
+    
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*USDT", "btcusdt"));
+        assert!(glob_match("BTC*", "BTCUSDT"));
+        assert!(glob_match("*DOGE*", "DOGEUSDT"));
+        assert!(glob_match("ETHBTC", "ETHBTC"));
+        assert!(!glob_match("ETH*USDC", "ETHUSDT"));
+        assert!(!glob_match("BTC", "BTCUSDT"));
+    }
+    
+    #[test]
+    fn test_filter_combines_lists_and_status() {
+        let filter = SymbolFilter::new(SymbolFilterConfig {
+            allowlist: vec!["*USDT".to_string(), "*BTC".to_string()],
+            blacklist: vec!["LUNA*".to_string()],
+            ..SymbolFilterConfig::default()
+        });
+        assert!(filter.is_allowed("Binance", "BTCUSDT"));
+        assert!(!filter.is_allowed("Binance", "BTCEUR"));
+        assert!(!filter.is_allowed("Binance", "LUNAUSDT"));
+        assert!(!filter.is_allowed("Binance", "BTCUPUSDT"));
+        
+        filter.set_tradable("Binance", ["ETHBTC".to_string()].into_iter().collect());
+        assert!(filter.is_allowed("Binance", "ETHBTC"));
+        assert!(!filter.is_allowed("Binance", "BTCUSDT"));
+        assert!(filter.is_allowed("Bybit", "BTCUSDT"));
+    }
+    
+    #[test]
+    fn test_delisting_reports_the_configured_markets_it_breaks() {
+        let set = |symbols: &[&str]| symbols.iter().map(|s| s.to_string()).collect::<HashSet<String>>();
+        let filter = SymbolFilter::new(SymbolFilterConfig::default());
+        let pairs = vec!["BTCUSDT".to_string(), "SOLUSDT".to_string()];
+        let triangles = vec![("BTCUSDT".to_string(), "ETHBTC".to_string(), "ETHUSDT".to_string())];
+        assert!(filter.unavailable_markets("Binance", &pairs, &triangles).is_empty());
+        
+        let first = filter.set_tradable("Binance", set(&["BTCUSDT", "ETHBTC", "ETHUSDT", "SOLUSDT"]));
+        assert!(first.is_empty());
+        let unavailable = filter.unavailable_markets("Binance", &pairs, &triangles);
+        assert_eq!(filter.track_unavailable("Binance", unavailable), (vec![], vec![]));
+        
+        let changes = filter.set_tradable("Binance", set(&["BTCUSDT", "ETHUSDT", "SOLUSDT", "WIFUSDT"]));
+        assert_eq!(changes, ListingChanges { listed: vec!["WIFUSDT".to_string()], delisted: vec!["ETHBTC".to_string()] });
+        let unavailable = filter.unavailable_markets("Binance", &pairs, &triangles);
+        assert_eq!(unavailable, ["BTCUSDT/ETHBTC/ETHUSDT"]);
+        assert_eq!(filter.track_unavailable("Binance", unavailable.clone()), (unavailable.clone(), vec![]));
+        // Reported once, not on every refresh it stays down
+        assert_eq!(filter.track_unavailable("Binance", unavailable.clone()), (vec![], vec![]));
+        
+        filter.set_tradable("Binance", set(&["BTCUSDT", "ETHBTC", "ETHUSDT"]));
+        let unavailable = filter.unavailable_markets("Binance", &pairs, &triangles);
+        assert_eq!(filter.track_unavailable("Binance", unavailable), (vec!["SOLUSDT".to_string()], vec!["BTCUSDT/ETHBTC/ETHUSDT".to_string()]));
+    }
+}
@@ -0,0 +1,135 @@
+use crate::storage::LocalStore;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+const STATE_PATH: &str = "cache/warm_start.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedPrice {
+    /// Empty in files written before prices were cached per exchange
+    #[serde(default)]
+    pub exchange: String,
+    pub symbol: String,
+    pub price: Decimal,
+    /// Event time from the exchange payload, when it carried one
+    #[serde(default)]
+    pub exchange_time: Option<DateTime<Utc>>,
+    /// When the price was received
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Runtime caches saved on shutdown so a restart does not begin with an
+/// empty price cache or re-download exchange info it fetched moments ago.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WarmStartState {
+    pub saved_at: DateTime<Utc>,
+    pub prices: Vec<CachedPrice>,
+    /// Tradable symbols per exchange, as last reported by exchange info
+    pub tradable_symbols: HashMap<String, Vec<String>>,
+    pub tradable_fetched_at: Option<DateTime<Utc>>,
+    /// Price tick sizes per exchange and symbol, fetched with the tradable lists
+    #[serde(default)]
+    pub tick_sizes: HashMap<String, HashMap<String, Decimal>>,
+    /// Quantity lot sizes per exchange and symbol, fetched alongside
+    #[serde(default)]
+    pub step_sizes: HashMap<String, HashMap<String, Decimal>>,
+}
+
+impl WarmStartState {
+    /// Entries of `prices` no older than `max_age`, aged by exchange time
+    /// when known; older ones would only be discarded by the freshness
+    /// check anyway. Entries without an exchange mixed both venues and are
+    /// dropped.
+    pub fn fresh_prices(&self, max_age: chrono::Duration, now: DateTime<Utc>) -> Vec<CachedPrice> {
+        self.prices.iter()
+            .filter(|p| !p.exchange.is_empty())
+            .filter(|p| now.signed_duration_since(p.exchange_time.unwrap_or(p.updated_at)) <= max_age)
+            .cloned()
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct WarmStartStore {
+    store: LocalStore,
+}
+
+impl WarmStartStore {
+    pub fn new(store: LocalStore) -> Self {
+        Self { store }
+    }
+    
+    pub fn save(&self, state: &WarmStartState) -> Result<()> {
+        self.store.write_json(Path::new(STATE_PATH), state)
+    }
+    
+    pub fn load(&self) -> Result<Option<WarmStartState>> {
+        self.store.read_json(Path::new(STATE_PATH))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_round_trip_and_fresh_prices() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = WarmStartStore::new(LocalStore::new(dir.path()).unwrap());
+        assert!(store.load().unwrap().is_none());
+        
+        let now = Utc::now();
+        let state = WarmStartState {
+            saved_at: now,
+            prices: vec![
+                CachedPrice {
+                    exchange: "Binance".to_string(),
+                    symbol: "BTCUSDT".to_string(),
+                    price: Decimal::from(60000),
+                    exchange_time: None,
+                    updated_at: now,
+                },
+                CachedPrice {
+                    exchange: "Bybit".to_string(),
+                    symbol: "ETHUSDT".to_string(),
+                    price: Decimal::from(3000),
+                    exchange_time: None,
+                    updated_at: now - chrono::Duration::minutes(10),
+                },
+                // Received just now, but the exchange stamped it a minute ago
+                CachedPrice {
+                    exchange: "Bybit".to_string(),
+                    symbol: "BTCUSDT".to_string(),
+                    price: Decimal::from(60010),
+                    exchange_time: Some(now - chrono::Duration::minutes(1)),
+                    updated_at: now,
+                },
+            ],
+            tradable_symbols: HashMap::from([("Binance".to_string(), vec!["BTCUSDT".to_string()])]),
+            tradable_fetched_at: Some(now),
+            tick_sizes: HashMap::new(),
+            step_sizes: HashMap::new(),
+        };
+        store.save(&state).unwrap();
+        
+        let loaded = store.load().unwrap().unwrap();
+        assert_eq!(loaded.prices.len(), 3);
+        assert_eq!(loaded.tradable_symbols["Binance"], vec!["BTCUSDT".to_string()]);
+        
+        let fresh = loaded.fresh_prices(chrono::Duration::seconds(30), now);
+        assert_eq!(fresh.len(), 1);
+        assert_eq!(fresh[0].symbol, "BTCUSDT");
+        assert_eq!(fresh[0].exchange, "Binance");
+        
+        // Files from before per-exchange caching still load, without their prices
+        let legacy: WarmStartState = serde_json::from_str(&format!(
+            r#"{{"saved_at":"{now}","prices":[{{"symbol":"BTCUSDT","price":"60000","updated_at":"{now}"}}],"tradable_symbols":{{}},"tradable_fetched_at":null}}"#,
+            now = now.to_rfc3339()
+        )).unwrap();
+        assert!(legacy.fresh_prices(chrono::Duration::seconds(30), now).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! Detection of a scan loop that stopped completing scans.
+//!
+//! Every scan is bounded by `SCAN_TIMEOUT`, so a loop that keeps timing out
+//! is usually waiting on connections that a wedged HTTP pool never hands
+//! out. The watchdog notices the missing scans and has the exchange clients
+//! recreated; when scans stay stuck after that, the stall is alerted.
+
+use log::{error, info};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+/// Longest a single scan may take before it is abandoned.
+pub const SCAN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A scan loop found stuck by `ScanWatchdog::check`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stall {
+    /// Time since the last completed scan, less the pause that followed it
+    pub stuck_for: Duration,
+    /// Client resets during this stall, this one included
+    pub resets: u32,
+}
+
+impl Stall {
+    /// Whether the clients were already recreated once without scans
+    /// completing again.
+    pub fn persists(&self) -> bool {
+        self.resets == 2
+    }
+}
+
+#[derive(Debug)]
+struct WatchState {
+    last_scan: Instant,
+    /// Deliberate pause after `last_scan`, which never counts as stuck
+    pause: Duration,
+    resets: u32,
+}
+
+#[derive(Debug)]
+pub struct ScanWatchdog {
+    timeout: Duration,
+    state: Mutex<WatchState>,
+}
+
+impl ScanWatchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            state: Mutex::new(WatchState { last_scan: Instant::now(), pause: Duration::ZERO, resets: 0 }),
+        }
+    }
+
+    /// Records a completed scan, failed or not, and the pause the loop takes
+    /// before starting the next one.
+    pub fn record_scan(&self, pause: Duration) {
+        let mut state = self.state.lock().unwrap();
+        if state.resets > 0 {
+            info!("Scans are completing again after {} exchange client resets", state.resets);
+        }
+        *state = WatchState { last_scan: Instant::now(), pause, resets: 0 };
+    }
+
+    /// Returns a stall once for every further `timeout` that passes at
+    /// `now` without a completed scan.
+    pub fn check(&self, now: Instant) -> Option<Stall> {
+        let mut state = self.state.lock().unwrap();
+        let stuck_for = now.saturating_duration_since(state.last_scan + state.pause);
+        if stuck_for <= self.timeout * (state.resets + 1) {
+            return None;
+        }
+        state.resets += 1;
+        Some(Stall { stuck_for, resets: state.resets })
+    }
+}
+
+/// Checks `watchdog` every second until `cancel` fires, handing each stall
+/// to `on_stall`.
+pub async fn watch<F, Fut>(watchdog: Arc<ScanWatchdog>, cancel: CancellationToken, on_stall: F)
+where
+    F: Fn(Stall) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    let mut checks = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => return,
+            _ = checks.tick() => {}
+        }
+        if let Some(stall) = watchdog.check(Instant::now()) {
+            error!("No scan completed for {}s, recreating the exchange clients", stall.stuck_for.as_secs());
+            on_stall(stall).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stall_resets_once_per_timeout_and_persists_after_the_first() {
+        let watchdog = ScanWatchdog::new(Duration::from_secs(60));
+        watchdog.record_scan(Duration::from_secs(5));
+        let scanned = Instant::now();
+
+        // The pause after a scan is not a stall
+        assert_eq!(watchdog.check(scanned + Duration::from_secs(64)), None);
+        let first = watchdog.check(scanned + Duration::from_secs(66)).unwrap();
+        assert_eq!(first.resets, 1);
+        assert!(!first.persists());
+        assert_eq!(watchdog.check(scanned + Duration::from_secs(100)), None);
+        assert!(watchdog.check(scanned + Duration::from_secs(126)).unwrap().persists());
+        let third = watchdog.check(scanned + Duration::from_secs(186)).unwrap();
+        assert_eq!(third.resets, 3);
+        assert!(!third.persists());
+
+        watchdog.record_scan(Duration::ZERO);
+        assert_eq!(watchdog.check(Instant::now() + Duration::from_secs(30)), None);
+    }
+}
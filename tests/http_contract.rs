@@ -0,0 +1,1453 @@
+//! Contract tests for the exchange clients against canned HTTP responses.
+//!
+//! Each test starts a `wiremock` server, mounts the responses it expects
+//! to be asked for, points a client at it through
+//! `ClientNetworkConfig::endpoints` and checks how responses are parsed,
+//! how requests are signed and how failures map onto `ExchangeError`.
+
+use hmac::{Hmac, Mac};
+use rust_decimal::Decimal;
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use triangular_arbitrage::alerts::{AlertChannel, AlertConfig, AlertNotifier};
+use triangular_arbitrage::arbitrage::risk::Strategy;
+use triangular_arbitrage::arbitrage::{ArbitrageOpportunity, ExecutionStep};
+use triangular_arbitrage::config::{ClientNetworkConfig, Config, DiagnosticsConfig, ExecutionConfig, FixtureConfig, LegSubmission, RequestLogConfig, WsTradingConfig};
+use triangular_arbitrage::diag;
+use triangular_arbitrage::executor::{ExecutionResult, Executor, LastLook};
+use triangular_arbitrage::exchanges::fees::{AccountFeeRates, FeeModel};
+use triangular_arbitrage::exchanges::fixtures::{FixtureRecorder, ReplayServer, ReplaySession};
+use triangular_arbitrage::exchanges::budget;
+use triangular_arbitrage::exchanges::keepalive;
+use triangular_arbitrage::exchanges::request_log::RequestLog;
+use triangular_arbitrage::exchanges::snapshot::SymbolTable;
+use triangular_arbitrage::exchanges::{
+    binance::BinanceClient, bybit::BybitClient, new_client_order_id, ApiKeyPermissions, ExchangeClient, ExchangeError, OrderRef, OrderRequest,
+    OrderSide, OrderType, TimeInForce,
+};
+use triangular_arbitrage::keys;
+use triangular_arbitrage::metrics::MarketMetrics;
+use triangular_arbitrage::notify::RateLimitConfig;
+use triangular_arbitrage::scanner::Scanner;
+use triangular_arbitrage::shadow::{ShadowExecutor, ShadowOutcome};
+use wiremock::matchers::{body_json, header, method, path, query_param};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+/// A response with `body` as JSON, the way the exchanges answer.
+fn json(status: u16, body: &str) -> ResponseTemplate {
+    ResponseTemplate::new(status).set_body_raw(body.as_bytes().to_vec(), "application/json")
+}
+
+async fn requests(server: &MockServer) -> Vec<Request> {
+    server.received_requests().await.expect("request recording should be on")
+}
+
+async fn count_requests(server: &MockServer, route: &str) -> usize {
+    requests(server).await.iter().filter(|request| request.url.path() == route).count()
+}
+
+/// The decoded value of query parameter `name`.
+fn query_value(request: &Request, name: &str) -> Option<String> {
+    request.url.query_pairs()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.into_owned())
+}
+
+/// The query string exactly as sent.
+fn raw_query(request: &Request) -> &str {
+    request.url.query().unwrap_or_default()
+}
+
+fn header_value<'a>(request: &'a Request, name: &str) -> &'a str {
+    request.headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default()
+}
+
+/// Forwards TCP connections to a mock server and counts them, since the
+/// mock server does not report how many connections a client opened.
+struct ConnectionCounter {
+    url: String,
+    connections: Arc<AtomicUsize>,
+}
+
+impl ConnectionCounter {
+    async fn start(server: &MockServer) -> Self {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let target = *server.address();
+        let connections = Arc::new(AtomicUsize::new(0));
+        
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            while let Ok((mut inbound, _)) = listener.accept().await {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    if let Ok(mut outbound) = tokio::net::TcpStream::connect(target).await {
+                        let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    }
+                });
+            }
+        });
+        Self { url, connections }
+    }
+    
+    fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+/// Websocket API stub: answers every request with `answer` and records
+/// it, closing each connection after it answered `close_after` orders.
+struct WsStub {
+    url: String,
+    messages: Arc<Mutex<Vec<serde_json::Value>>>,
+}
+
+impl WsStub {
+    fn start(answer: fn(&serde_json::Value) -> serde_json::Value, close_after: usize) -> Self {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        let listener = tokio::net::TcpListener::from_std(listener).unwrap();
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        
+        let recorder = messages.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let recorder = recorder.clone();
+                tokio::spawn(async move {
+                    use futures_util::{SinkExt, StreamExt};
+                    use tokio_tungstenite::tungstenite::Message;
+                    let Ok(mut ws) = tokio_tungstenite::accept_async(stream).await else { return };
+                    let mut orders = 0;
+                    while let Some(Ok(Message::Text(text))) = ws.next().await {
+                        let message: serde_json::Value = serde_json::from_str(&text).unwrap();
+                        recorder.lock().unwrap().push(message.clone());
+                        if ws.send(Message::Text(answer(&message).to_string())).await.is_err() {
+                            return;
+                        }
+                        if message.get("op").and_then(|op| op.as_str()) != Some("auth") {
+                            orders += 1;
+                        }
+                        if orders == close_after {
+                            let _ = ws.close(None).await;
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        Self { url, messages }
+    }
+    
+    fn messages(&self) -> Vec<serde_json::Value> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+fn network(urls: &[&str]) -> ClientNetworkConfig {
+    ClientNetworkConfig {
+        use_env_proxy: false,
+        endpoints: urls.iter().map(|u| u.to_string()).collect(),
+        ..ClientNetworkConfig::default()
+    }
+}
+
+fn exchange_error(error: anyhow::Error) -> ExchangeError {
+    error.downcast::<ExchangeError>().expect("error should be an ExchangeError")
+}
+
+fn hmac_hex(secret: &str, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(payload.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn dec(value: &str) -> Decimal {
+    Decimal::from_str_exact(value).unwrap()
+}
+
+#[tokio::test]
+async fn test_binance_ticker_prices_parse_and_skip_zero() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(200, r#"[{"symbol":"BTCUSDT","price":"64000.10"},{"symbol":"ETHBTC","price":"0.05120000"},{"symbol":"DEADUSDT","price":"0.00000000"}]"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let prices = client.get_ticker_prices().await.unwrap();
+    
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices["BTCUSDT"], dec("64000.10"));
+    assert_eq!(prices["ETHBTC"], dec("0.0512"));
+    assert!(!prices.contains_key("DEADUSDT"));
+}
+
+#[tokio::test]
+async fn test_binance_error_body_maps_to_api_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/bookTicker"))
+        .and(query_param("symbol", "NOPE"))
+        .respond_with(json(400, r#"{"code":-1121,"msg":"Invalid symbol."}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let error = exchange_error(client.get_book_ticker("NOPE").await.unwrap_err());
+    
+    match &error {
+        ExchangeError::ApiError { exchange, endpoint, message, .. } => {
+            assert_eq!(exchange, "Binance");
+            assert_eq!(endpoint, "/api/v3/ticker/bookTicker");
+            assert_eq!(message, "Invalid symbol.");
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert_eq!(error.status(), Some(400));
+    assert_eq!(error.code(), Some(-1121));
+    assert!(!error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_rate_limit_keeps_retry_after() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(429, r#"{"code":-1003,"msg":"Too many requests."}"#).insert_header("retry-after", "7"))
+        .mount(&server)
+        .await;
+    let client = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let error = exchange_error(client.get_ticker_prices().await.unwrap_err());
+    
+    match &error {
+        ExchangeError::RateLimitError(message) => {
+            assert!(message.contains("code -1003"), "{}", message);
+            assert!(message.ends_with("(retry after 7s)"), "{}", message);
+        }
+        other => panic!("unexpected error: {:?}", other),
+    }
+    assert!(error.is_retryable());
+}
+
+#[tokio::test]
+async fn test_malformed_json_maps_to_parse_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(200, r#"[{"symbol":"BTCUSDT","#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/tickers"))
+        .respond_with(json(200, "<html>maintenance</html>"))
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    for error in [binance.get_ticker_prices().await.unwrap_err(), bybit.get_ticker_prices().await.unwrap_err()] {
+        assert!(matches!(exchange_error(error), ExchangeError::ParseError(_)));
+    }
+}
+
+#[tokio::test]
+async fn test_bybit_ticker_prices_parse() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/tickers"))
+        .and(query_param("category", "spot"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","list":[
+            {"symbol":"BTCUSDT","lastPrice":"64001.5"},
+            {"symbol":"ETHUSDT","lastPrice":"3100.25"},
+            {"symbol":"DEADUSDT","lastPrice":"0"}
+        ]}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let prices = client.get_ticker_prices().await.unwrap();
+    
+    assert_eq!(prices.len(), 2);
+    assert_eq!(prices["BTCUSDT"], dec("64001.5"));
+    assert_eq!(prices["ETHUSDT"], dec("3100.25"));
+}
+
+#[tokio::test]
+async fn test_book_tickers_parse_and_skip_one_sided_quotes() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/bookTicker"))
+        .respond_with(json(200, r#"[
+            {"symbol":"BTCUSDT","bidPrice":"64000.10","bidQty":"0.5","askPrice":"64000.20","askQty":"1.25"},
+            {"symbol":"DEADUSDT","bidPrice":"0.00000000","bidQty":"0","askPrice":"0.00000000","askQty":"0"}
+        ]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/tickers"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"ETHUSDT","lastPrice":"3100.25","bid1Price":"3100.2","bid1Size":"4","ask1Price":"3100.3","ask1Size":"2.5"},
+            {"symbol":"NEWUSDT","lastPrice":"1.5"}
+        ]},"time":1714564800123}"#))
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let binance_quotes = binance.get_book_tickers().await.unwrap();
+    assert_eq!(binance_quotes.len(), 1);
+    let btc = &binance_quotes["BTCUSDT"];
+    assert_eq!((btc.bid_price, btc.bid_quantity, btc.ask_price, btc.ask_quantity),
+               (dec("64000.10"), dec("0.5"), dec("64000.20"), dec("1.25")));
+    
+    let bybit_quotes = bybit.get_book_tickers().await.unwrap();
+    assert_eq!(bybit_quotes.len(), 1);
+    let eth = &bybit_quotes["ETHUSDT"];
+    assert_eq!((eth.bid_price, eth.ask_price), (dec("3100.2"), dec("3100.3")));
+    
+    // Quotes carry the exchange's timestamp where the payload has one
+    assert_eq!(btc.timestamp, None);
+    assert_eq!(eth.timestamp.map(|t| t.timestamp_millis()), Some(1714564800123));
+    let snapshot = bybit.get_price_snapshot(&Arc::new(SymbolTable::new())).await.unwrap();
+    assert_eq!(snapshot.exchange_time(), eth.timestamp);
+}
+
+#[tokio::test]
+async fn test_recent_trades_parse_oldest_first() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/aggTrades"))
+        .and(query_param("limit", "1000"))
+        .respond_with(json(200, r#"[
+            {"a":26129,"p":"64000.10","q":"0.015","f":27781,"l":27781,"T":1714560000000,"m":true},
+            {"a":26130,"p":"64000.20","q":"0.250","f":27782,"l":27783,"T":1714560000500,"m":false}
+        ]"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/recent-trade"))
+        .and(query_param("limit", "60"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","list":[
+            {"execId":"2","symbol":"ETHUSDT","price":"3100.3","size":"1.5","side":"Buy","time":"1714560001000"},
+            {"execId":"1","symbol":"ETHUSDT","price":"3100.2","size":"0.4","side":"Sell","time":"1714560000000"}
+        ]}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let binance_trades = binance.get_recent_trades("BTCUSDT", 5000).await.unwrap();
+    assert_eq!(binance_trades.len(), 2);
+    assert_eq!((binance_trades[1].price, binance_trades[1].quantity), (dec("64000.20"), dec("0.25")));
+    
+    let bybit_trades = bybit.get_recent_trades("ETHUSDT", 1000).await.unwrap();
+    assert_eq!(bybit_trades.iter().map(|t| t.quantity).collect::<Vec<_>>(), vec![dec("0.4"), dec("1.5")]);
+}
+
+#[tokio::test]
+async fn test_price_snapshots_share_symbol_ids() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(200, r#"[{"symbol":"BTCUSDT","price":"64000.10"},{"symbol":"ETHBTC","price":"0.0512"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/tickers"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"ETHUSDT","lastPrice":"3100.25"},
+            {"symbol":"BTCUSDT","lastPrice":"64001.5"}
+        ]}}"#))
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let symbols = Arc::new(SymbolTable::new());
+    
+    let binance_prices = binance.get_price_snapshot(&symbols).await.unwrap();
+    let bybit_prices = bybit.get_price_snapshot(&symbols).await.unwrap();
+    
+    assert_eq!(symbols.len(), 3);
+    let btc = symbols.get("BTCUSDT").unwrap();
+    assert_eq!(binance_prices.get(btc), Some(dec("64000.10")));
+    assert_eq!(bybit_prices.get(btc), Some(dec("64001.5")));
+    assert_eq!(binance_prices.price("ETHUSDT"), None);
+    assert_eq!(bybit_prices.to_price_map(), bybit.get_ticker_prices().await.unwrap());
+}
+
+#[tokio::test]
+async fn test_bybit_ret_code_maps_to_api_error() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/tickers"))
+        .respond_with(json(200, r#"{"retCode":10006,"retMsg":"Too many visits!","result":{}}"#))
+        .mount(&server)
+        .await;
+    let client = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let error = exchange_error(client.get_ticker_prices().await.unwrap_err());
+    
+    assert_eq!(error.status(), Some(200));
+    assert_eq!(error.code(), Some(10006));
+    assert!(error.is_retryable());
+    assert!(error.to_string().contains("Too many visits!"));
+}
+
+#[tokio::test]
+async fn test_binance_signed_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/account"))
+        .and(header("x-mbx-apikey", "test-key"))
+        .respond_with(json(200, r#"{"balances":[
+            {"asset":"BTC","free":"0.50000000","locked":"0.10000000"},
+            {"asset":"ETH","free":"0.00000000","locked":"0.00000000"}
+        ]}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    
+    let balances = client.get_balances().await.unwrap();
+    
+    assert_eq!(balances.len(), 1);
+    assert_eq!(balances[0].asset, "BTC");
+    assert_eq!(balances[0].free, dec("0.5"));
+    assert_eq!(balances[0].locked, dec("0.1"));
+    
+    let request = &requests(&server).await[0];
+    let (signed, signature) = raw_query(request).rsplit_once("&signature=").expect("query should be signed");
+    assert!(query_value(request, "timestamp").is_some());
+    assert_eq!(signature, hmac_hex("test-secret", signed));
+}
+
+#[tokio::test]
+async fn test_bybit_signed_request() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/account/wallet-balance"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"accountType":"UNIFIED","coin":[
+            {"coin":"USDT","walletBalance":"1500.5","locked":"500"},
+            {"coin":"ETH","walletBalance":"0","locked":"0"}
+        ]}]}}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/account/fee-rate"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"BTCUSDT","takerFeeRate":"0.001","makerFeeRate":"0.001"}
+        ]}}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/order/realtime"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"nextPageCursor":"","list":[]}}"#))
+        .mount(&server)
+        .await;
+    let client = BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    
+    let balances = client.get_balances().await.unwrap();
+    
+    assert_eq!(balances.len(), 1);
+    assert_eq!(balances[0].asset, "USDT");
+    assert_eq!(balances[0].free, dec("1000.5"));
+    assert_eq!(balances[0].locked, dec("500"));
+    client.get_account_info().await.unwrap();
+    client.get_fee_rates("BTCUSDT").await.unwrap();
+    client.get_open_orders().await.unwrap();
+    client.get_order_by_client_id("BTCUSDT", "arb 1/2").await.unwrap();
+    
+    // All signed the v5 way, over the query exactly as sent, with the
+    // signature in a header only
+    let requests = requests(&server).await;
+    let queries: Vec<&str> = requests.iter().map(raw_query).collect();
+    assert_eq!(queries, [
+        "accountType=UNIFIED",
+        "accountType=UNIFIED",
+        "category=spot&symbol=BTCUSDT",
+        "category=spot&limit=50",
+        "category=spot&symbol=BTCUSDT&orderLinkId=arb%201%2F2",
+    ]);
+    for request in &requests {
+        assert!(query_value(request, "signature").is_none());
+        assert_eq!(header_value(request, "x-bapi-api-key"), "test-key");
+        let payload = format!(
+            "{}{}{}{}",
+            header_value(request, "x-bapi-timestamp"),
+            "test-key",
+            header_value(request, "x-bapi-recv-window"),
+            raw_query(request),
+        );
+        assert_eq!(header_value(request, "x-bapi-sign"), hmac_hex("test-secret", &payload));
+    }
+}
+
+#[tokio::test]
+async fn test_open_orders_parse() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/openOrders"))
+        .respond_with(json(200, r#"[
+            {"symbol":"ETHBTC","orderId":42,"clientOrderId":"arb0123","status":"PARTIALLY_FILLED","executedQty":"0.50000000","cummulativeQuoteQty":"0.02500000"}
+        ]"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/order/realtime"))
+        .and(query_param("category", "spot"))
+        .and(query_param("limit", "50"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"SOLUSDT","orderId":"9001","orderLinkId":"manual-1","orderStatus":"New","cumExecQty":"0","avgPrice":""}
+        ],"nextPageCursor":""}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    
+    let binance_orders = binance.get_open_orders().await.unwrap();
+    let bybit_orders = bybit.get_open_orders().await.unwrap();
+    
+    assert_eq!(binance_orders.len(), 1);
+    assert_eq!(binance_orders[0].symbol, "ETHBTC");
+    assert_eq!(binance_orders[0].state.client_order_id, "arb0123");
+    assert_eq!(binance_orders[0].state.executed_quantity, dec("0.5"));
+    assert_eq!(binance_orders[0].state.average_price, Some(dec("0.05")));
+    assert_eq!(bybit_orders.len(), 1);
+    assert_eq!(bybit_orders[0].symbol, "SOLUSDT");
+    assert_eq!(bybit_orders[0].state.client_order_id, "manual-1");
+    assert_eq!(bybit_orders[0].state.average_price, None);
+    
+    let requests = requests(&server).await;
+    assert!(query_value(&requests[0], "signature").is_some());
+    assert_eq!(raw_query(&requests[1]), "category=spot&limit=50");
+}
+
+#[tokio::test]
+async fn test_trade_history_parses_and_keeps_to_the_range() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/myTrades"))
+        .and(query_param("startTime", "1709251200000"))
+        .and(query_param("endTime", "1709294399999"))
+        .and(query_param("limit", "1000"))
+        .respond_with(json(200, r#"[
+            {"symbol":"ETHBTC","id":7,"orderId":42,"price":"0.05","qty":"0.5","commission":"0.0005","commissionAsset":"ETH","time":1709280000000,"isBuyer":true},
+            {"symbol":"ETHBTC","id":8,"orderId":43,"price":"0.05","qty":"0.1","commission":"0.0001","commissionAsset":"ETH","time":1709298000000,"isBuyer":false}
+        ]"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/execution/list"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"SOLUSDT","execId":"e2","orderId":"9002","side":"Sell","execPrice":"101","execQty":"2","execFee":"0.2","feeCurrency":"USDT","execTime":"1709283600000"},
+            {"symbol":"SOLUSDT","execId":"e1","orderId":"9001","side":"Buy","execPrice":"100","execQty":"2","execFee":"0.002","feeCurrency":"SOL","execTime":"1709280000000"}
+        ],"nextPageCursor":""}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let range = "2024-03-01T00:00:00Z".parse().unwrap().."2024-03-01T12:00:00Z".parse().unwrap();
+    
+    let binance_trades = binance.get_my_trades("ETHBTC", range.clone()).await.unwrap();
+    let bybit_trades = bybit.get_my_trades("SOLUSDT", range).await.unwrap();
+    
+    assert_eq!(binance_trades.len(), 1);
+    assert_eq!((binance_trades[0].trade_id.as_str(), binance_trades[0].order_id.as_str()), ("7", "42"));
+    assert_eq!(binance_trades[0].side, OrderSide::Buy);
+    assert_eq!(binance_trades[0].fee, dec("0.0005"));
+    let bybit_ids: Vec<&str> = bybit_trades.iter().map(|t| t.trade_id.as_str()).collect();
+    assert_eq!(bybit_ids, ["e1", "e2"]);
+    assert_eq!(bybit_trades[1].side, OrderSide::Sell);
+    assert_eq!(bybit_trades[1].price, dec("101"));
+    
+    let requests = requests(&server).await;
+    assert!(raw_query(&requests[1]).starts_with("category=spot&symbol=SOLUSDT&startTime=1709251200000"));
+}
+
+#[tokio::test]
+async fn test_api_key_permissions_parse() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/sapi/v1/account/apiRestrictions"))
+        .respond_with(json(200, r#"{
+            "ipRestrict":false,"createTime":1698645219000,"enableReading":true,"enableWithdrawals":true,
+            "enableInternalTransfer":false,"enableMargin":false,"enableFutures":false,"permitsUniversalTransfer":false,
+            "enableSpotAndMarginTrading":true
+        }"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/user/query-api"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"","result":{
+            "id":"13770661","readOnly":0,"ips":["203.0.113.10"],"userID":24617703,
+            "permissions":{"Spot":["SpotTrade"],"Wallet":["AccountTransfer","SubMemberTransfer"],"ContractTrade":[]}
+        }}"#))
+        .mount(&server)
+        .await;
+    let binance = ExchangeClient::Binance(Arc::new(
+        BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()));
+    let bybit = ExchangeClient::Bybit(Arc::new(
+        BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()));
+    
+    let binance_check = keys::check_key(&binance, false).await;
+    let bybit_check = keys::check_key(&bybit, true).await;
+    
+    assert_eq!(binance_check.permissions, Some(ApiKeyPermissions { read: true, trade: true, withdraw: true, ip_restricted: false }));
+    assert_eq!(binance_check.warnings.len(), 2);
+    assert_eq!(bybit_check.permissions, Some(ApiKeyPermissions { read: true, trade: true, withdraw: false, ip_restricted: true }));
+    assert!(bybit_check.warnings.is_empty());
+    assert!(query_value(&requests(&server).await[0], "signature").is_some());
+}
+
+#[tokio::test]
+async fn test_account_fee_rates_parse() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/account/commission"))
+        .respond_with(json(200, r#"{"symbol":"BTCUSDT",
+            "standardCommission":{"maker":"0.00090000","taker":"0.00100000","buyer":"0.00000000","seller":"0.00000000"},
+            "taxCommission":{"maker":"0.00000000","taker":"0.00000000","buyer":"0.00000000","seller":"0.00000000"},
+            "discount":{"enabledForAccount":true,"enabledForSymbol":true,"discountAsset":"BNB","discount":"0.75000000"}
+        }"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/account/fee-rate"))
+        .and(query_param("symbol", "BTCUSDT"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"BTCUSDT","takerFeeRate":"0.0006","makerFeeRate":"0.0001"}
+        ]}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let binance = ExchangeClient::Binance(Arc::new(
+        BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()));
+    let bybit = ExchangeClient::Bybit(Arc::new(
+        BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()));
+    let dec = |v: &str| Decimal::from_str_exact(v).unwrap();
+    
+    let binance_rates = binance.get_fee_rates("BTCUSDT").await.unwrap();
+    assert_eq!(binance_rates, AccountFeeRates { maker_fee: dec("0.0009"), taker_fee: dec("0.001"), bnb_fee_multiplier: Some(dec("0.75")) });
+    let bybit_rates = bybit.get_fee_rates("BTCUSDT").await.unwrap();
+    assert_eq!(bybit_rates, AccountFeeRates { maker_fee: dec("0.0001"), taker_fee: dec("0.0006"), bnb_fee_multiplier: None });
+    
+    let model = FeeModel::default();
+    model.apply_account_rates("Binance", &binance_rates);
+    assert_eq!(model.taker_fee("Binance"), dec("0.00075"));
+    assert_eq!(model.received_factor("Binance"), Decimal::ONE);
+}
+
+#[tokio::test]
+async fn test_concurrent_legs_unwind_when_one_is_rejected() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v3/order"))
+        .respond_with(json(400, r#"{"code":-2010,"msg":"Account has insufficient balance for requested action."}"#))
+        .mount(&server)
+        .await;
+    // The buy and the sell reversing it
+    Mock::given(method("POST"))
+        .and(path("/v5/order/create"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"orderId":"1","orderLinkId":"arb1"}}"#))
+        .expect(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/order/realtime"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"SOLUSDT","orderId":"1","orderStatus":"Filled","cumExecQty":"2","avgPrice":"150"}
+        ]}}"#))
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let config = ExecutionConfig { leg_submission: LegSubmission::Concurrent, ..ExecutionConfig::default() };
+    let executor = Executor::new(vec![ExchangeClient::Binance(Arc::new(binance)), ExchangeClient::Bybit(Arc::new(bybit))], config);
+    
+    let step = |exchange: &str, side: OrderSide, price: i64| ExecutionStep {
+        exchange: exchange.to_string(),
+        action: String::new(),
+        symbol: "SOLUSDT".to_string(),
+        side,
+        quantity: Decimal::from(2),
+        expected_price: Decimal::from(price),
+        fees: Decimal::ZERO,
+        client_order_id: new_client_order_id(),
+    };
+    let opportunity = ArbitrageOpportunity {
+        exchange: "Binance->Bybit".to_string(),
+        strategy: Strategy::CrossExchange,
+        path: Vec::new(),
+        profit_percentage: Decimal::ONE,
+        net_profit_percentage: Decimal::ONE,
+        required_amount: Decimal::from(300),
+        estimated_profit_usd: Decimal::from(3),
+        risk_score: 0.0,
+        execution_steps: vec![step("Binance", OrderSide::Sell, 152), step("Bybit", OrderSide::Buy, 150)],
+        leg_quotes: Vec::new(),
+        timestamp: chrono::Utc::now(),
+        scan_id: 0,
+    };
+    
+    match executor.execute(&opportunity).await.unwrap() {
+        ExecutionResult::Abandoned { fills, reason } => {
+            assert_eq!(fills[0].filled_quantity, Decimal::ZERO);
+            assert_eq!(fills[1].filled_quantity, Decimal::from(2));
+            assert!(reason.ends_with("unwound 2 on Bybit"), "{}", reason);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_last_look_skips_legs_that_moved() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/bookTicker"))
+        .and(query_param("symbol", "SOLUSDT"))
+        .respond_with(json(200, r#"{"symbol":"SOLUSDT","bidPrice":"151.90","bidQty":"40","askPrice":"152.00","askQty":"25"}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/tickers"))
+        .and(query_param("symbol", "SOLUSDT"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"SOLUSDT","bid1Price":"149.9","bid1Size":"30","ask1Price":"150.1","ask1Size":"12"}
+        ]}}"#))
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let clients = vec![ExchangeClient::Binance(Arc::new(binance)), ExchangeClient::Bybit(Arc::new(bybit))];
+    let executor = Executor::new(clients.clone(), ExecutionConfig::default())
+        .with_max_slippage_percentage(dec("0.1"));
+    
+    let step = |exchange: &str, side: OrderSide, price: &str| ExecutionStep {
+        exchange: exchange.to_string(),
+        action: String::new(),
+        symbol: "SOLUSDT".to_string(),
+        side,
+        quantity: Decimal::from(2),
+        expected_price: dec(price),
+        fees: Decimal::ZERO,
+        client_order_id: new_client_order_id(),
+    };
+    let opportunity = |bybit_ask: &str| ArbitrageOpportunity {
+        exchange: "Binance->Bybit".to_string(),
+        strategy: Strategy::CrossExchange,
+        path: Vec::new(),
+        profit_percentage: Decimal::ONE,
+        net_profit_percentage: Decimal::ONE,
+        required_amount: Decimal::from(300),
+        estimated_profit_usd: Decimal::from(3),
+        risk_score: 0.0,
+        execution_steps: vec![step("Binance", OrderSide::Sell, "152"), step("Bybit", OrderSide::Buy, bybit_ask)],
+        leg_quotes: Vec::new(),
+        timestamp: chrono::Utc::now(),
+        scan_id: 0,
+    };
+    
+    // Sells are checked against the bid and buys against the ask
+    assert_eq!(executor.last_look(&opportunity("150")).await.unwrap(), LastLook::Passed);
+    match executor.last_look(&opportunity("149.5")).await.unwrap() {
+        LastLook::Evaporated(reason) => assert!(reason.starts_with("leg 2 (SOLUSDT) on Bybit is now 150.1"), "{}", reason),
+        other => panic!("unexpected last look: {:?}", other),
+    }
+    assert_eq!(count_requests(&server, "/api/v3/ticker/bookTicker").await, 2);
+    
+    // Both legs are within slippage, but together they cost 0.13% of the 1% profit
+    let strict = Executor::new(clients, ExecutionConfig::default())
+        .with_max_slippage_percentage(dec("0.1"))
+        .with_min_profit_threshold(Strategy::CrossExchange, dec("0.9"));
+    match strict.last_look(&opportunity("150")).await.unwrap() {
+        LastLook::Decayed(reason) => assert!(reason.starts_with("it re-quotes to 0.8675% net"), "{}", reason),
+        other => panic!("unexpected last look: {:?}", other),
+    }
+    
+    // Too old to be worth re-quoting
+    let mut stale = opportunity("150");
+    stale.timestamp = chrono::Utc::now() - chrono::Duration::seconds(5);
+    assert!(matches!(executor.last_look(&stale).await.unwrap(), LastLook::Expired(_)));
+    assert_eq!(count_requests(&server, "/api/v3/ticker/bookTicker").await, 3);
+}
+
+#[tokio::test]
+async fn test_read_fails_over_on_server_error() {
+    let failing = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(503, r#"{"code":-1001,"msg":"Internal error; unable to process your request."}"#))
+        .expect(1)
+        .mount(&failing)
+        .await;
+    let healthy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(200, r#"[{"symbol":"BTCUSDT","price":"64000"}]"#))
+        .expect(1)
+        .mount(&healthy)
+        .await;
+    let client = BinanceClient::with_credentials("", "", &network(&[&failing.uri(), &healthy.uri()])).unwrap();
+    
+    let prices = client.get_ticker_prices().await.unwrap();
+    
+    assert_eq!(prices["BTCUSDT"], dec("64000"));
+}
+
+#[tokio::test]
+async fn test_server_error_on_every_endpoint_is_retryable() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+        .mount(&server)
+        .await;
+    let client = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    let error = exchange_error(client.get_ticker_prices().await.unwrap_err());
+    
+    assert_eq!(error.status(), Some(502));
+    assert_eq!(error.code(), None);
+    assert!(error.is_retryable());
+    assert!(error.to_string().contains("Bad Gateway"));
+}
+
+#[tokio::test]
+async fn test_reset_connections_opens_a_fresh_pool() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/tickers"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"category":"spot","list":[{"symbol":"BTCUSDT","lastPrice":"64000"}]}}"#))
+        .expect(3)
+        .mount(&server)
+        .await;
+    let server = ConnectionCounter::start(&server).await;
+    let client = BybitClient::with_credentials("", "", &network(&[&server.url])).unwrap();
+    
+    client.get_ticker_prices().await.unwrap();
+    client.get_ticker_prices().await.unwrap();
+    assert_eq!(server.connections(), 1);
+    
+    client.reset_connections().unwrap();
+    let prices = client.get_ticker_prices().await.unwrap();
+    
+    assert_eq!(prices["BTCUSDT"], dec("64000"));
+    assert_eq!(server.connections(), 2);
+}
+
+#[tokio::test]
+async fn test_keepalive_pings_warm_and_reuse_connections() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ping"))
+        .respond_with(json(200, "{}"))
+        .expect(6)
+        .mount(&server)
+        .await;
+    let binance = ConnectionCounter::start(&server).await;
+    let client = ExchangeClient::Binance(Arc::new(BinanceClient::with_credentials("", "", &network(&[&binance.url])).unwrap()));
+    
+    assert_eq!(keepalive::warm_up(&client, 3).await, 3);
+    assert_eq!(binance.connections(), 3);
+    assert_eq!(keepalive::warm_up(&client, 3).await, 3);
+    assert_eq!(binance.connections(), 3);
+    
+    let bybit = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/time"))
+        .respond_with(json(200, r#"{"retCode":10002,"retMsg":"invalid request","result":{}}"#))
+        .mount(&bybit)
+        .await;
+    let client = BybitClient::with_credentials("", "", &network(&[&bybit.uri()])).unwrap();
+    assert!(client.ping().await.is_err());
+}
+
+#[tokio::test]
+async fn test_shadow_execution_walks_each_leg_through_its_book() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/depth"))
+        .respond_with(json(200, r#"{"lastUpdateId":7,"bids":[["151.95","1.5"],["151.80","5"]],"asks":[["152.05","3"]]}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/orderbook"))
+        .and(query_param("category", "spot"))
+        .and(query_param("limit", "20"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"s":"SOLUSDT",
+            "b":[["149.95","4"]],"a":[["150.05","1"],["150.10","0.5"]],"ts":1700000000000,"u":42,"seq":1}}"#))
+        .mount(&server)
+        .await;
+    let binance = BinanceClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    let metrics = Arc::new(MarketMetrics::new());
+    let shadow = ShadowExecutor::new(vec![ExchangeClient::Binance(Arc::new(binance)), ExchangeClient::Bybit(Arc::new(bybit))],
+                                     20, dec("0.1"))
+        .with_metrics(metrics.clone());
+    
+    let step = |exchange: &str, side: OrderSide, quantity: &str, price: &str| ExecutionStep {
+        exchange: exchange.to_string(),
+        action: String::new(),
+        symbol: "SOLUSDT".to_string(),
+        side,
+        quantity: dec(quantity),
+        expected_price: dec(price),
+        fees: Decimal::ZERO,
+        client_order_id: new_client_order_id(),
+    };
+    let opportunity = |quantity: &str| ArbitrageOpportunity {
+        exchange: "Binance->Bybit".to_string(),
+        strategy: Strategy::CrossExchange,
+        path: Vec::new(),
+        profit_percentage: Decimal::ONE,
+        net_profit_percentage: Decimal::ONE,
+        required_amount: Decimal::from(300),
+        estimated_profit_usd: Decimal::from(3),
+        risk_score: 0.0,
+        execution_steps: vec![step("Binance", OrderSide::Sell, quantity, "152"), step("Bybit", OrderSide::Buy, quantity, "150")],
+        leg_quotes: Vec::new(),
+        timestamp: chrono::Utc::now(),
+        scan_id: 0,
+    };
+    
+    // Bybit's asks hold only 1.5
+    let thin = shadow.shadow(&opportunity("2")).await.unwrap();
+    assert_eq!(thin.outcome, ShadowOutcome::ThinBook);
+    assert_eq!(thin.legs[0].average_price, Some(dec("151.9125")));
+    assert_eq!(thin.legs[1].fillable_quantity, dec("1.5"));
+    
+    shadow.shadow_all(&[opportunity("1.5")]).await;
+    let text = metrics.prometheus_metrics();
+    assert!(text.contains("arbitrage_shadow_executions_total{exchange=\"Binance->Bybit\",strategy=\"cross_exchange\",path=\"SOLUSDT\",result=\"filled\"} 1"), "{}", text);
+}
+
+#[tokio::test]
+async fn test_telegram_and_discord_alerts_are_rate_limited() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/botcontract-token/sendMessage"))
+        .respond_with(json(200, r#"{"ok":true}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/webhooks/1/discord"))
+        .respond_with(ResponseTemplate::new(204))
+        .mount(&server)
+        .await;
+    std::env::set_var("CONTRACT_TELEGRAM_TOKEN", "contract-token");
+    let config = AlertConfig {
+        enabled: true,
+        default_channel: "telegram".to_string(),
+        channels: HashMap::from([
+            ("telegram".to_string(), AlertChannel::Telegram {
+                chat_id: "-100200".to_string(),
+                bot_token_env: "CONTRACT_TELEGRAM_TOKEN".to_string(),
+                api_url: server.uri(),
+            }),
+            ("discord".to_string(), AlertChannel::Discord { url: format!("{}/api/webhooks/1/discord", server.uri()) }),
+        ]),
+        rate_limit: RateLimitConfig { max_per_minute: 2, dedup_seconds: 300 },
+        ..AlertConfig::default()
+    };
+    config.validate().unwrap();
+    
+    let telegram = AlertNotifier::new(config.clone());
+    for text in ["Scans stalled", "Scans stalled", "Kill switch tripped", "Depeg detected"] {
+        telegram.notify_text(text).await;
+    }
+    assert_eq!(count_requests(&server, "/botcontract-token/sendMessage").await, 2);
+    
+    let discord = AlertNotifier::new(AlertConfig { default_channel: "discord".to_string(), ..config });
+    discord.notify_text("Scans stalled").await;
+    assert_eq!(count_requests(&server, "/api/webhooks/1/discord").await, 1);
+}
+
+#[tokio::test]
+async fn test_diag_measures_clock_offset_from_server_time() {
+    let ahead = chrono::Utc::now().timestamp_millis() + 5_000;
+    let binance = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/time"))
+        .respond_with(json(200, &format!(r#"{{"serverTime":{}}}"#, ahead)))
+        .expect(3)
+        .mount(&binance)
+        .await;
+    let bybit = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/market/time"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"timeSecond":"1","timeNano":"1"},"time":"not a number"}"#))
+        .mount(&bybit)
+        .await;
+    let config = DiagnosticsConfig { rest_samples: 3, stream_seconds: 0, ..DiagnosticsConfig::default() };
+    
+    let client = ExchangeClient::Binance(Arc::new(BinanceClient::with_credentials("", "", &network(&[&binance.uri()])).unwrap()));
+    let result = diag::diagnose_exchange(&client, "ws://127.0.0.1:1", &config).await;
+    assert_eq!(result.rest.unwrap().samples, 3);
+    let offset = result.clock_offset_ms.unwrap();
+    assert!(offset > 4_000.0 && offset < 5_100.0, "offset {}", offset);
+    assert!(result.stream.is_none());
+    assert_eq!(result.problems.len(), 1);
+    assert!(result.problems[0].contains("NTP"));
+    
+    let client = ExchangeClient::Bybit(Arc::new(BybitClient::with_credentials("", "", &network(&[&bybit.uri()])).unwrap()));
+    let result = diag::diagnose_exchange(&client, "ws://127.0.0.1:1", &config).await;
+    assert_eq!(result.errors.len(), 3);
+    assert!(!result.ready());
+}
+
+#[tokio::test]
+async fn test_orders_go_to_the_fastest_endpoint() {
+    let slow = MockServer::start().await;
+    let fast = MockServer::start().await;
+    for (server, delay) in [(&slow, 150), (&fast, 0)] {
+        Mock::given(method("GET"))
+            .and(path("/api/v3/ping"))
+            .respond_with(json(200, "{}").set_delay(std::time::Duration::from_millis(delay)))
+            .mount(server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/api/v3/ticker/price"))
+            .respond_with(json(200, r#"[{"symbol":"BTCUSDT","price":"64000"}]"#))
+            .mount(server)
+            .await;
+    }
+    let network = ClientNetworkConfig { prefer_fastest_endpoint: true, ..network(&[&slow.uri(), &fast.uri()]) };
+    let client = BinanceClient::with_credentials("", "", &network).unwrap();
+    
+    assert_eq!(client.probe_endpoints().await, 2);
+    let health = client.endpoint_health();
+    assert!(health[0].active && !health[0].orders);
+    assert!(health[1].orders);
+    assert!(health[1].latency_ms.unwrap() < health[0].latency_ms.unwrap());
+    
+    // Market data stays on the active host, pings follow the orders
+    client.get_ticker_prices().await.unwrap();
+    client.ping().await.unwrap();
+    let paths = |requests: Vec<Request>| requests.iter().map(|r| r.url.path().to_string()).collect::<Vec<_>>();
+    assert_eq!(paths(requests(&slow).await), ["/api/v3/ping", "/api/v3/ticker/price"]);
+    assert_eq!(paths(requests(&fast).await), ["/api/v3/ping", "/api/v3/ping"]);
+}
+
+#[tokio::test]
+async fn test_market_data_cannot_spend_the_trading_reserve() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/exchangeInfo"))
+        .respond_with(json(200, r#"{"symbols":[]}"#))
+        .expect(4)
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/api/v3/order"))
+        .and(query_param("origClientOrderId", "arb-1"))
+        .respond_with(json(200, r#"{"symbol":"BTCUSDT","status":"CANCELED"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let network = ClientNetworkConfig { request_weight_per_minute: 100, trading_reserve_percentage: 20.0, ..network(&[&server.uri()]) };
+    let client = BinanceClient::with_credentials("test-key", "test-secret", &network).unwrap();
+    
+    // exchangeInfo weighs 20, so four calls use up the market data share
+    for _ in 0..4 {
+        client.get_symbol_info().await.unwrap();
+    }
+    let throttled = tokio::time::timeout(std::time::Duration::from_millis(200), client.get_symbol_info()).await;
+    assert!(throttled.is_err(), "market data should wait for the window to roll");
+    
+    let started = std::time::Instant::now();
+    client.cancel_order_by_client_id("BTCUSDT", "arb-1").await.unwrap();
+    assert!(started.elapsed() < std::time::Duration::from_millis(200));
+    
+    let metrics = budget::prometheus_metrics(&[client.request_budget()]);
+    assert!(metrics.contains(r#"arbitrage_request_weight_used{exchange="Binance",class="market_data"} 80"#));
+    assert!(metrics.contains(r#"arbitrage_request_weight_used{exchange="Binance",class="trading"} 1"#));
+    assert!(metrics.contains(r#"arbitrage_request_budget_utilization_ratio{exchange="Binance",class="market_data"} 1"#));
+    assert!(metrics.contains(r#"arbitrage_request_budget_waits_total{exchange="Binance",class="market_data"} 1"#));
+}
+
+#[tokio::test]
+async fn test_requests_per_endpoint_wait_for_a_free_slot() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(200, r#"[{"symbol":"BTCUSDT","price":"64000"}]"#).set_delay(std::time::Duration::from_millis(100)))
+        .expect(3)
+        .mount(&server)
+        .await;
+    let network = ClientNetworkConfig { max_requests_per_endpoint: 1, ..network(&[&server.uri()]) };
+    let client = BinanceClient::with_credentials("", "", &network).unwrap();
+    
+    let started = std::time::Instant::now();
+    let results = futures_util::future::join_all((0..3).map(|_| client.get_ticker_prices())).await;
+    assert!(results.iter().all(|result| result.is_ok()));
+    assert!(started.elapsed() >= std::time::Duration::from_millis(300), "requests to one host should go one at a time");
+}
+
+#[tokio::test]
+async fn test_failed_requests_are_counted_by_endpoint_and_kind() {
+    let server = MockServer::start().await;
+    for (route, response) in [
+        ("/api/v3/ticker/price", json(200, r#"[{"symbol":"BTCUSDT","price":"64000"}]"#)),
+        ("/api/v3/ticker/bookTicker", json(400, r#"{"code":-1121,"msg":"Invalid symbol."}"#)),
+        ("/api/v3/exchangeInfo", json(200, "<html>maintenance</html>")),
+        ("/v5/market/tickers", json(200, r#"{"retCode":10001,"retMsg":"params error","result":{}}"#)),
+    ] {
+        Mock::given(method("GET")).and(path(route)).respond_with(response).mount(&server).await;
+    }
+    // The first host refuses connections until two failures rotate it out
+    let binance = BinanceClient::with_credentials("", "", &network(&["http://127.0.0.1:1", &server.uri()])).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap();
+    
+    binance.get_ticker_prices().await.unwrap();
+    binance.get_book_ticker("NOPE").await.unwrap_err();
+    binance.get_symbol_info().await.unwrap_err();
+    bybit.get_book_ticker("NOPE").await.unwrap_err();
+    
+    let counts = |client: Arc<triangular_arbitrage::exchanges::errors::ErrorCounters>| client.counts().into_iter()
+        .map(|c| format!("{} {} {} {}", c.endpoint, c.kind.label(), c.code, c.count))
+        .collect::<Vec<_>>();
+    assert_eq!(counts(binance.error_counters()), [
+        "/api/v3/exchangeInfo parse  1",
+        "/api/v3/ticker/bookTicker network  1",
+        "/api/v3/ticker/bookTicker api -1121 1",
+        "/api/v3/ticker/price network  1",
+    ]);
+    assert_eq!(counts(bybit.error_counters()), ["/v5/market/tickers api 10001 1"]);
+}
+
+#[tokio::test]
+async fn test_request_log_records_attempts_without_secrets() {
+    let failing = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/account"))
+        .respond_with(json(503, r#"{"code":-1001,"msg":"Internal error"}"#))
+        .mount(&failing)
+        .await;
+    let healthy = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/account"))
+        .respond_with(json(200, r#"{"balances":[{"asset":"BTC","free":"0.5","locked":"0"}]}"#))
+        .mount(&healthy)
+        .await;
+    let dir = tempfile::tempdir().unwrap();
+    let config = RequestLogConfig { enabled: true, path: dir.path().join("requests.jsonl").to_string_lossy().into_owned(), ..RequestLogConfig::default() };
+    let log = Arc::new(RequestLog::open(&config).unwrap());
+    let client = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&failing.uri(), &healthy.uri()])).unwrap()
+        .with_request_log(log);
+    
+    client.get_balances().await.unwrap();
+    
+    let contents = std::fs::read_to_string(&config.path).unwrap();
+    let records: Vec<serde_json::Value> = contents.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["status"], 503);
+    assert_eq!(records[1]["status"], 200);
+    assert_eq!(records[1]["host_attempt"], 2);
+    assert_eq!(records[1]["attempt"], 1);
+    assert_eq!(records[1]["method"], "GET");
+    assert_eq!(records[1]["endpoint"], "/api/v3/account");
+    assert!(records[1]["query"].as_str().unwrap().ends_with("&signature=<redacted>"));
+    
+    let signature = query_value(&requests(&healthy).await[0], "signature").unwrap();
+    assert!(!contents.contains(&signature) && !contents.contains("test-key"));
+}
+
+#[tokio::test]
+async fn test_client_health_reflects_recent_requests() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/ticker/price"))
+        .respond_with(json(200, r#"[{"symbol":"BTCUSDT","price":"64000.00"}]"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/api/v3/order"))
+        .respond_with(json(400, r#"{"code":-2010,"msg":"Account has insufficient balance for requested action."}"#))
+        .mount(&server)
+        .await;
+    let client = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let idle = client.health();
+    assert_eq!((idle.requests, idle.error_rate, idle.last_success, idle.latency), (0, 0.0, None, None));
+    assert_eq!(idle.ws_connected, None);
+    
+    for _ in 0..3 {
+        client.get_ticker_prices().await.unwrap();
+    }
+    assert!(client.place_order(&limit_order("BTCUSDT", "arb1")).await.is_err());
+    
+    let health = client.health();
+    assert_eq!(health.exchange, "Binance");
+    assert_eq!(health.requests, 4);
+    assert_eq!(health.error_rate, 0.25);
+    assert!(health.last_success.is_some());
+    assert_eq!(health.latency.unwrap().samples, 4);
+}
+
+#[tokio::test]
+async fn test_signed_request_errors_do_not_quote_the_signature() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/api/v3/account"))
+        .respond_with(json(200, "<html>maintenance</html>"))
+        .mount(&server)
+        .await;
+    let garbled = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let unreachable = BinanceClient::with_credentials("test-key", "test-secret", &network(&["http://127.0.0.1:1"])).unwrap();
+    
+    for error in [garbled.get_account_info().await.unwrap_err(), unreachable.get_account_info().await.unwrap_err()] {
+        let message = format!("{:#}", error);
+        assert!(!message.contains("signature") && !message.contains("timestamp="), "{}", message);
+    }
+    assert!(query_value(&requests(&server).await[0], "signature").is_some());
+}
+
+#[tokio::test]
+async fn test_timestamp_rejections_resync_the_clock_and_resign_once() {
+    let server_time = chrono::Utc::now().timestamp_millis() + 600_000;
+    let server = MockServer::start().await;
+    for (route, response) in [
+        ("/api/v3/time", json(200, &format!(r#"{{"serverTime":{}}}"#, server_time))),
+        ("/api/v3/account", json(400, r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#)),
+        ("/v5/market/time", json(200, &format!(r#"{{"retCode":0,"retMsg":"OK","result":{{}},"time":{}}}"#, server_time))),
+        ("/v5/account/wallet-balance", json(200, r#"{"retCode":10002,"retMsg":"invalid request, please check your server timestamp or recv_window param","result":{}}"#)),
+    ] {
+        Mock::given(method("GET")).and(path(route)).respond_with(response).mount(&server).await;
+    }
+    let network = ClientNetworkConfig { recv_window_ms: 2500, ..network(&[&server.uri()]) };
+    let binance = BinanceClient::with_credentials("test-key", "test-secret", &network).unwrap();
+    let bybit = BybitClient::with_credentials("test-key", "test-secret", &network).unwrap();
+    
+    let error = exchange_error(binance.get_balances().await.unwrap_err());
+    assert_eq!(error.code(), Some(-1021));
+    let error = exchange_error(bybit.get_balances().await.unwrap_err());
+    assert_eq!(error.code(), Some(10002));
+    
+    let requests = requests(&server).await;
+    let paths: Vec<&str> = requests.iter().map(|r| r.url.path()).collect();
+    assert_eq!(paths, [
+        "/api/v3/account", "/api/v3/time", "/api/v3/account",
+        "/v5/account/wallet-balance", "/v5/market/time", "/v5/account/wallet-balance",
+    ]);
+    let timestamp = |request: &Request| query_value(request, "timestamp")
+        .unwrap_or_else(|| header_value(request, "x-bapi-timestamp").to_string())
+        .parse::<i64>()
+        .unwrap();
+    for (first, resent) in [(&requests[0], &requests[2]), (&requests[3], &requests[5])] {
+        // The resent request carries the exchange's time, not the local one
+        assert!(timestamp(first) < server_time - 500_000);
+        assert!((timestamp(resent) - server_time).abs() < 5_000);
+    }
+    assert_eq!(query_value(&requests[2], "recvWindow").as_deref(), Some("2500"));
+    assert_eq!(header_value(&requests[5], "x-bapi-recv-window"), "2500");
+}
+
+#[tokio::test]
+async fn test_orphaned_orders_are_canceled_in_one_batch() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/v5/order/realtime"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"nextPageCursor":"","list":[
+            {"symbol":"SOLUSDT","orderId":"1","orderLinkId":"arb1","orderStatus":"New","cumExecQty":"0","avgPrice":"0"},
+            {"symbol":"ETHUSDT","orderId":"2","orderLinkId":"arb2","orderStatus":"PartiallyFilled","cumExecQty":"0.1","avgPrice":"3000"},
+            {"symbol":"BTCUSDT","orderId":"3","orderLinkId":"manual","orderStatus":"New","cumExecQty":"0","avgPrice":"0"}
+        ]}}"#))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v5/account/wallet-balance"))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"coin":[]}]}}"#))
+        .mount(&server)
+        .await;
+    // Both orphans in one request, the manual order left alone
+    Mock::given(method("POST"))
+        .and(path("/v5/order/cancel-batch"))
+        .and(body_json(serde_json::json!({
+            "category": "spot",
+            "request": [
+                { "symbol": "SOLUSDT", "orderLinkId": "arb1" },
+                { "symbol": "ETHUSDT", "orderLinkId": "arb2" },
+            ],
+        })))
+        .respond_with(json(200, r#"{"retCode":0,"retMsg":"OK",
+            "result":{"list":[{"symbol":"SOLUSDT","orderLinkId":"arb1"},{"symbol":"ETHUSDT","orderLinkId":"arb2"}]},
+            "retExtInfo":{"list":[{"code":0,"msg":"OK"},{"code":170213,"msg":"Order does not exist."}]}}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    // Binance spot has no batch cancel, so each order gets its own request
+    Mock::given(method("DELETE"))
+        .and(path("/api/v3/order"))
+        .respond_with(json(200, r#"{"symbol":"SOLUSDT","status":"CANCELED"}"#))
+        .expect(2)
+        .mount(&server)
+        .await;
+    let bybit = BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap();
+    let executor = Executor::new(vec![ExchangeClient::Bybit(Arc::new(bybit))], ExecutionConfig::default());
+    
+    let report = executor.reconcile(None).await.unwrap();
+    
+    let canceled: Vec<(&str, bool)> = report.orphaned_orders.iter().map(|o| (o.client_order_id.as_str(), o.canceled)).collect();
+    assert_eq!(canceled, [("arb1", true), ("arb2", false)]);
+    let cancels = requests(&server).await.into_iter().filter(|r| r.url.path().starts_with("/v5/order/cancel")).count();
+    assert_eq!(cancels, 1);
+    
+    let binance = ExchangeClient::Binance(Arc::new(
+        BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()));
+    let orders = ["arb1", "arb2"].map(|id| OrderRef { symbol: "SOLUSDT".to_string(), client_order_id: id.to_string() });
+    let outcomes = binance.cancel_orders_by_client_id(&orders).await.unwrap();
+    assert!(outcomes.iter().all(Result::is_ok));
+}
+
+fn limit_order(symbol: &str, client_order_id: &str) -> OrderRequest {
+    OrderRequest {
+        symbol: symbol.to_string(),
+        side: OrderSide::Buy,
+        quantity: dec("1"),
+        quote_quantity: None,
+        price: Some(dec("150")),
+        order_type: OrderType::Limit,
+        time_in_force: TimeInForce::Ioc,
+        client_order_id: Some(client_order_id.to_string()),
+    }
+}
+
+async fn wait_until(condition: impl Fn() -> bool) {
+    for _ in 0..200 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    panic!("condition never held");
+}
+
+#[tokio::test]
+async fn test_orders_go_over_the_websocket_api_and_fall_back_to_rest() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/api/v3/order"))
+        .and(query_param("newClientOrderId", "arb2"))
+        .respond_with(json(200, r#"{"symbol":"SOLUSDT","orderId":9,"status":"NEW"}"#))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let ws = WsStub::start(|message| match message["method"].as_str() {
+        Some(_) => serde_json::json!({ "id": message["id"], "status": 200, "result": { "symbol": "SOLUSDT", "orderId": 7, "status": "FILLED" } }),
+        None if message["op"] == "auth" => serde_json::json!({ "op": "auth", "retCode": 0, "retMsg": "" }),
+        None => serde_json::json!({ "reqId": message["reqId"], "op": message["op"], "retCode": 0, "retMsg": "OK",
+            "data": { "orderId": "5", "orderLinkId": "arb3" } }),
+    }, 1);
+    let config = WsTradingConfig {
+        enabled: true,
+        binance_url: ws.url.clone(),
+        bybit_url: ws.url.clone(),
+        request_timeout_ms: 2000,
+        reconnect_delay_seconds: 60,
+    };
+    let binance = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()
+        .with_ws_trading(&config);
+    wait_until(|| binance.ws_trading_connected()).await;
+    
+    let placed = binance.place_order(&limit_order("SOLUSDT", "arb1")).await.unwrap();
+    assert_eq!(placed["orderId"], 7);
+    assert!(requests(&server).await.is_empty());
+    let request = &ws.messages()[0];
+    assert_eq!(request["method"], "order.place");
+    let params = request["params"].as_object().unwrap();
+    assert_eq!(params["apiKey"], "test-key");
+    assert_eq!(params["newClientOrderId"], "arb1");
+    // Signed over every other parameter, sorted by name
+    let payload = params.iter()
+        .filter(|(key, _)| *key != "signature")
+        .map(|(key, value)| format!("{}={}", key, value.as_str().unwrap()))
+        .collect::<Vec<_>>()
+        .join("&");
+    assert!(payload.starts_with("apiKey=test-key&newClientOrderId=arb1&price=150&quantity=1&recvWindow="));
+    assert_eq!(params["signature"], hmac_hex("test-secret", &payload).as_str());
+    
+    // The stub hangs up after one order; the next one goes over REST
+    wait_until(|| !binance.ws_trading_connected()).await;
+    let placed = binance.place_order(&limit_order("SOLUSDT", "arb2")).await.unwrap();
+    assert_eq!(placed["orderId"], 9);
+    
+    let bybit = BybitClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()
+        .with_ws_trading(&config);
+    wait_until(|| bybit.ws_trading_connected()).await;
+    bybit.place_order(&limit_order("SOLUSDT", "arb3")).await.unwrap();
+    let messages = ws.messages();
+    let (auth, order) = (&messages[1], &messages[2]);
+    assert_eq!(auth["op"], "auth");
+    assert_eq!(auth["args"][0], "test-key");
+    let expires = auth["args"][1].as_i64().unwrap();
+    assert_eq!(auth["args"][2], hmac_hex("test-secret", &format!("GET/realtime{}", expires)).as_str());
+    assert_eq!(order["op"], "order.create");
+    assert_eq!(order["args"][0]["category"], "spot");
+    assert_eq!(order["args"][0]["orderLinkId"], "arb3");
+    assert!(order["header"]["X-BAPI-TIMESTAMP"].is_string());
+    assert_eq!(requests(&server).await.len(), 1);
+}
+
+#[tokio::test]
+async fn test_recorded_session_replays_through_the_scanner() {
+    let server = MockServer::start().await;
+    for (route, response) in [
+        // ETHBTC is 3% below BTCUSDT and ETHUSDT imply
+        ("/api/v3/ticker/price", json(200, r#"[{"symbol":"BTCUSDT","price":"64000"},{"symbol":"ETHBTC","price":"0.047"},{"symbol":"ETHUSDT","price":"3100"}]"#)),
+        ("/api/v3/ticker/bookTicker", json(200, r#"[
+            {"symbol":"BTCUSDT","bidPrice":"63999.9","bidQty":"2","askPrice":"64000.1","askQty":"2"},
+            {"symbol":"ETHUSDT","bidPrice":"3099.9","bidQty":"20","askPrice":"3100.1","askQty":"20"}
+        ]"#)),
+        ("/v5/market/tickers", json(200, r#"{"retCode":0,"retMsg":"OK","result":{"list":[
+            {"symbol":"BTCUSDT","lastPrice":"64900","bid1Price":"64899","bid1Size":"2","ask1Price":"64901","ask1Size":"2"},
+            {"symbol":"ETHUSDT","lastPrice":"3100","bid1Price":"3099.9","bid1Size":"20","ask1Price":"3100.1","ask1Size":"20"}
+        ]}}"#)),
+        ("/api/v3/account", json(200, r#"{"balances":[{"asset":"USDT","free":"1000","locked":"0"}]}"#)),
+    ] {
+        Mock::given(method("GET")).and(path(route)).respond_with(response).mount(&server).await;
+    }
+    let dir = tempfile::tempdir().unwrap();
+    let fixtures = FixtureConfig { enabled: true, path: dir.path().join("session.jsonl").to_string_lossy().into_owned(), ..FixtureConfig::default() };
+    let mut config = Config::default();
+    config.symbols.require_trading_status = false;
+    
+    let recorder = Arc::new(FixtureRecorder::create(&fixtures).unwrap());
+    let binance = BinanceClient::with_credentials("test-key", "test-secret", &network(&[&server.uri()])).unwrap()
+        .with_fixture_recorder(recorder.clone());
+    let bybit = BybitClient::with_credentials("", "", &network(&[&server.uri()])).unwrap()
+        .with_fixture_recorder(recorder);
+    let balances = binance.get_balances().await.unwrap();
+    let binance = Arc::new(binance);
+    let recorded = Scanner::with_clients(&config, binance, Arc::new(bybit)).scan().await.unwrap();
+    assert!(!recorded.is_empty());
+    let served = requests(&server).await.len();
+    
+    let contents = std::fs::read_to_string(&fixtures.path).unwrap();
+    assert!(contents.contains("signature=<redacted>") && !contents.contains("test-secret"));
+    let replay = ReplayServer::start(ReplaySession::load(&fixtures.path).unwrap()).unwrap();
+    replay.apply(&mut config);
+    let binance = BinanceClient::with_credentials("test-key", "test-secret", &config.exchanges.binance_network).unwrap();
+    let bybit = BybitClient::with_credentials("", "", &config.exchanges.bybit_network).unwrap();
+    
+    // A fresh signature and timestamp still find the recorded answer
+    assert_eq!(binance.get_balances().await.unwrap().len(), balances.len());
+    let replayed = Scanner::with_clients(&config, Arc::new(binance), Arc::new(bybit)).scan().await.unwrap();
+    let summary = |opportunities: &[ArbitrageOpportunity]| opportunities.iter()
+        .map(|o| (o.exchange.clone(), o.path.clone(), o.net_profit_percentage))
+        .collect::<Vec<_>>();
+    assert_eq!(summary(&replayed), summary(&recorded));
+    assert_eq!(requests(&server).await.len(), served);
+}
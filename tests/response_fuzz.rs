@@ -0,0 +1,316 @@
+//! Property tests feeding malformed responses to the exchange clients.
+//!
+//! A stub server answers every request of both clients with one generated
+//! body, and every client call that reads a response is made against it.
+//! Valid responses cut off at a random byte must fail with `ParseError`.
+//! Arbitrary JSON, built from the fields the parsers look for, and valid
+//! responses with one value swapped for arbitrary JSON may parse or fail
+//! with an `ExchangeError`, but must never panic. `PROPTEST_CASES` sets the
+//! number of bodies per property (default 64).
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use futures_util::future::{join_all, BoxFuture};
+use futures_util::FutureExt;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use proptest::prelude::*;
+use proptest::sample::Index;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+use rust_decimal::Decimal;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use triangular_arbitrage::config::ClientNetworkConfig;
+use triangular_arbitrage::exchanges::{
+    binance::BinanceClient, bybit::BybitClient, ExchangeError, KlineInterval, OrderRef, OrderRequest, OrderSide, OrderType,
+    SubAccountTransfer, TimeInForce,
+};
+
+/// Valid responses of the endpoints the clients parse.
+const SAMPLES: &[&str] = &[
+    r#"[{"symbol":"BTCUSDT","price":"64000.10"},{"symbol":"ETHBTC","price":"0.05120000"}]"#,
+    r#"{"symbol":"BTCUSDT","bidPrice":"64000.00","bidQty":"1.5","askPrice":"64000.10","askQty":"0.2","E":1700000000000}"#,
+    r#"{"symbols":[{"symbol":"BTCUSDT","status":"TRADING","isSpotTradingAllowed":true,
+        "filters":[{"filterType":"PRICE_FILTER","tickSize":"0.01"},{"filterType":"LOT_SIZE","stepSize":"0.00001"}]}]}"#,
+    r#"{"lastUpdateId":42,"bids":[["64000.00","1.5"]],"asks":[["64000.10","0.2"]]}"#,
+    r#"[{"p":"64000.00","q":"0.01","T":1700000000000}]"#,
+    r#"[[1700000000000,"64000","64100","63900","64050","12.5",1700000059999,"800000",100,"6","384000","0"]]"#,
+    r#"{"serverTime":1700000000000,"balances":[{"asset":"BTC","free":"0.5","locked":"0"}]}"#,
+    r#"{"symbol":"BTCUSDT","orderId":7,"clientOrderId":"arb1","status":"PARTIALLY_FILLED",
+        "executedQty":"0.5","cummulativeQuoteQty":"32000"}"#,
+    r#"{"enableReading":true,"enableSpotAndMarginTrading":true,"enableWithdrawals":false,"ipRestrict":true}"#,
+    r#"{"symbol":"BTCUSDT","standardCommission":{"maker":"0.001","taker":"0.001"},"discount":{"enabledForAccount":true,
+        "enabledForSymbol":true,"discount":"0.75"}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"symbol":"BTCUSDT","lastPrice":"64000","bid1Price":"63999",
+        "bid1Size":"1","ask1Price":"64001","ask1Size":"2"}]},"time":1700000000000}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"nextPageCursor":"","list":[{"symbol":"BTCUSDT","orderId":"1",
+        "orderLinkId":"arb1","orderStatus":"PartiallyFilled","cumExecQty":"0.5","avgPrice":"64000"}]}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"coin":[{"coin":"BTC","walletBalance":"0.5","locked":"0"}]}]}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"symbol":"BTCUSDT","status":"Trading",
+        "priceFilter":{"tickSize":"0.01"},"lotSizeFilter":{"basePrecision":"0.000001"}}]}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"s":"BTCUSDT","b":[["63999","1"]],"a":[["64001","2"]],"u":42}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"list":[["1700000000000","64000","64100","63900","64050","12.5","800000"]]}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"price":"64000","size":"0.01","time":"1700000000000"}]}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"symbol":"BTCUSDT","orderLinkId":"arb1"}]},
+        "retExtInfo":{"list":[{"code":0,"msg":"OK"}]}}"#,
+    r#"{"retCode":0,"retMsg":"OK","result":{"readOnly":0,"ips":["*"],"permissions":{"Spot":["SpotTrade"],"Wallet":[]},
+        "list":[{"symbol":"BTCUSDT","makerFeeRate":"0.001","takerFeeRate":"0.001"}],"transferId":"t1","status":"SUCCESS"}}"#,
+];
+
+/// Fields the parsers read, so generated objects reach past the top level.
+const KEYS: &[&str] = &[
+    "symbol", "symbols", "price", "bidPrice", "bidQty", "askPrice", "askQty", "E", "T", "p", "q", "status", "filters",
+    "filterType", "tickSize", "stepSize", "isSpotTradingAllowed", "lastUpdateId", "bids", "asks", "serverTime", "balances", "asset",
+    "free", "locked", "orderId", "clientOrderId", "executedQty", "cummulativeQuoteQty", "code", "msg", "retCode",
+    "retMsg", "result", "list", "time", "orderLinkId", "orderStatus", "cumExecQty", "avgPrice", "coin", "walletBalance",
+    "b", "a", "u", "size", "retExtInfo", "permissions", "readOnly", "ips", "makerFeeRate", "takerFeeRate", "discount",
+    "transferId", "tranId", "nextPageCursor", "lastPrice", "bid1Price", "ask1Price", "lotSizeFilter", "basePrecision",
+];
+
+/// Strings that parse as edge-case numbers, or nearly do.
+const NUMBERS: &[&str] = &[
+    "0", "-1", "1", "0.00000001", "79228162514264337593543950335", "-79228162514264337593543950335",
+    "0.0000000000000000000000000001", "1e3", "NaN", "", " 1", "9223372036854775807", "8210298412799999",
+];
+
+/// Millisecond timestamps at and beyond the edges of what chrono represents.
+const TIMESTAMPS: &[i64] = &[i64::MIN, -8_334_632_851_200_001, -1, 0, 8_210_298_412_799_999, i64::MAX];
+
+fn leaf() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        prop::sample::select(TIMESTAMPS).prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        prop::sample::select(NUMBERS).prop_map(Value::from),
+        any::<String>().prop_map(Value::String),
+    ]
+}
+
+fn json() -> impl Strategy<Value = Value> {
+    leaf().prop_recursive(4, 64, 6, |inner| prop_oneof![
+        prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+        prop::collection::vec((prop::sample::select(KEYS), inner), 0..8)
+            .prop_map(|fields| Value::Object(fields.into_iter().map(|(key, value)| (key.to_string(), value)).collect())),
+    ])
+}
+
+/// Number of values in `value`, the root included.
+fn count(value: &Value) -> usize {
+    1 + match value {
+        Value::Array(items) => items.iter().map(count).sum(),
+        Value::Object(fields) => fields.values().map(count).sum(),
+        _ => 0,
+    }
+}
+
+/// Replaces the `n`th value of `value` in pre-order, counting down `n`.
+fn replace_nth(value: &mut Value, n: &mut usize, replacement: &Value) -> bool {
+    if *n == 0 {
+        *value = replacement.clone();
+        return true;
+    }
+    *n -= 1;
+    match value {
+        Value::Array(items) => items.iter_mut().any(|item| replace_nth(item, n, replacement)),
+        Value::Object(fields) => fields.values_mut().any(|item| replace_nth(item, n, replacement)),
+        _ => false,
+    }
+}
+
+fn sample() -> impl Strategy<Value = &'static str> {
+    prop::sample::select(SAMPLES)
+}
+
+/// A valid response cut off before its last byte.
+fn truncated() -> impl Strategy<Value = String> {
+    (sample(), any::<Index>()).prop_map(|(body, cut)| body[..cut.index(body.len())].to_string())
+}
+
+/// A valid response with one value, possibly the whole body, replaced.
+fn mutated() -> impl Strategy<Value = String> {
+    (sample(), any::<Index>(), json()).prop_map(|(body, at, replacement)| {
+        let mut value: Value = serde_json::from_str(body).unwrap();
+        let mut n = at.index(count(&value));
+        replace_nth(&mut value, &mut n, &replacement);
+        value.to_string()
+    })
+}
+
+/// Both clients pointed at a stub server that answers with `body`.
+struct Fixture {
+    runtime: tokio::runtime::Runtime,
+    body: Arc<Mutex<String>>,
+    binance: BinanceClient,
+    bybit: BybitClient,
+}
+
+impl Fixture {
+    fn start() -> Self {
+        let runtime = tokio::runtime::Builder::new_multi_thread().worker_threads(2).enable_all().build().unwrap();
+        let _entered = runtime.enter();
+        let body = Arc::new(Mutex::new(String::new()));
+        let served = body.clone();
+        let make_service = make_service_fn(move |_| {
+            let served = served.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |_: Request<Body>| {
+                    let body = served.lock().unwrap().clone();
+                    async move {
+                        Ok::<_, Infallible>(Response::builder()
+                            .header("content-type", "application/json")
+                            .body(Body::from(body))
+                            .unwrap())
+                    }
+                }))
+            }
+        });
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let network = ClientNetworkConfig {
+            use_env_proxy: false,
+            endpoints: vec![format!("http://{}", server.local_addr())],
+            // Every case spends a minute's worth of weight; don't wait for it
+            request_weight_per_minute: 0,
+            // Unparseable placements are looked up for the whole window
+            recv_window_ms: 1,
+            ..ClientNetworkConfig::default()
+        };
+        runtime.spawn(server);
+        Self {
+            binance: BinanceClient::with_credentials("test-key", "test-secret", &network).unwrap(),
+            bybit: BybitClient::with_credentials("test-key", "test-secret", &network).unwrap(),
+            runtime,
+            body,
+        }
+    }
+
+    /// Makes every call that reads a response while the server answers
+    /// with `body`, and returns each call's name and outcome.
+    fn call_all(&self, body: &str) -> Vec<(&'static str, anyhow::Result<()>)> {
+        *self.body.lock().unwrap() = body.to_string();
+        let (binance, bybit) = (&self.binance, &self.bybit);
+        let order = OrderRequest {
+            symbol: "BTCUSDT".to_string(),
+            side: OrderSide::Buy,
+            quantity: Decimal::ONE,
+            quote_quantity: None,
+            price: Some(Decimal::from(64000)),
+            order_type: OrderType::Limit,
+            time_in_force: TimeInForce::Ioc,
+            client_order_id: Some("arb1".to_string()),
+        };
+        let orders = [OrderRef { symbol: "BTCUSDT".to_string(), client_order_id: "arb1".to_string() }];
+        let transfer = SubAccountTransfer::new("USDT", Decimal::ONE, None, Some("sub".to_string()));
+        let range = Utc::now() - ChronoDuration::hours(1)..Utc::now();
+        let klines = (KlineInterval::OneMinute, range);
+        let (order, orders, transfer, klines) = (&order, &orders[..], &transfer, &klines);
+
+        let calls: Vec<(&'static str, BoxFuture<'_, anyhow::Result<()>>)> = vec![
+            ("binance.get_server_time", binance.get_server_time().map(|r| r.map(drop)).boxed()),
+            ("binance.sync_clock", binance.sync_clock().map(|r| r.map(drop)).boxed()),
+            ("binance.get_ticker_prices", binance.get_ticker_prices().map(|r| r.map(drop)).boxed()),
+            ("binance.get_symbol_info", binance.get_symbol_info().map(|r| r.map(drop)).boxed()),
+            ("binance.get_book_ticker", binance.get_book_ticker("BTCUSDT").map(|r| r.map(drop)).boxed()),
+            ("binance.get_book_tickers", binance.get_book_tickers().map(|r| r.map(drop)).boxed()),
+            ("binance.get_recent_trades", binance.get_recent_trades("BTCUSDT", 10).map(|r| r.map(drop)).boxed()),
+            ("binance.get_depth_snapshot", binance.get_depth_snapshot("BTCUSDT", 5).map(|r| r.map(drop)).boxed()),
+            ("binance.get_klines", binance.get_klines("BTCUSDT", klines.0, klines.1.clone()).map(|r| r.map(drop)).boxed()),
+            ("binance.get_balances", binance.get_balances().map(|r| r.map(drop)).boxed()),
+            ("binance.get_open_orders", binance.get_open_orders().map(|r| r.map(drop)).boxed()),
+            ("binance.get_order_by_client_id", binance.get_order_by_client_id("BTCUSDT", "arb1").map(|r| r.map(drop)).boxed()),
+            ("binance.get_api_key_permissions", binance.get_api_key_permissions().map(|r| r.map(drop)).boxed()),
+            ("binance.get_fee_rates", binance.get_fee_rates("BTCUSDT").map(|r| r.map(drop)).boxed()),
+            ("binance.place_order_cancel_safe", binance.place_order_cancel_safe(order).map(|r| r.map(drop)).boxed()),
+            ("binance.cancel_order_by_client_id", binance.cancel_order_by_client_id("BTCUSDT", "arb1").map(|r| r.map(drop)).boxed()),
+            ("binance.cancel_orders_by_client_id", binance.cancel_orders_by_client_id(orders)
+                .map(|outcomes| outcomes.into_iter().collect::<anyhow::Result<()>>()).boxed()),
+            ("binance.transfer_between_sub_accounts", binance.transfer_between_sub_accounts(transfer).map(|r| r.map(drop)).boxed()),
+            ("bybit.get_server_time", bybit.get_server_time().map(|r| r.map(drop)).boxed()),
+            ("bybit.sync_clock", bybit.sync_clock().map(|r| r.map(drop)).boxed()),
+            ("bybit.get_ticker_prices", bybit.get_ticker_prices().map(|r| r.map(drop)).boxed()),
+            ("bybit.get_symbol_info", bybit.get_symbol_info().map(|r| r.map(drop)).boxed()),
+            ("bybit.get_book_ticker", bybit.get_book_ticker("BTCUSDT").map(|r| r.map(drop)).boxed()),
+            ("bybit.get_book_tickers", bybit.get_book_tickers().map(|r| r.map(drop)).boxed()),
+            ("bybit.get_recent_trades", bybit.get_recent_trades("BTCUSDT", 10).map(|r| r.map(drop)).boxed()),
+            ("bybit.get_depth_snapshot", bybit.get_depth_snapshot("BTCUSDT", 5).map(|r| r.map(drop)).boxed()),
+            ("bybit.get_klines", bybit.get_klines("BTCUSDT", klines.0, klines.1.clone()).map(|r| r.map(drop)).boxed()),
+            ("bybit.get_balances", bybit.get_balances().map(|r| r.map(drop)).boxed()),
+            ("bybit.get_open_orders", bybit.get_open_orders().map(|r| r.map(drop)).boxed()),
+            ("bybit.get_order_by_client_id", bybit.get_order_by_client_id("BTCUSDT", "arb1").map(|r| r.map(drop)).boxed()),
+            ("bybit.get_api_key_permissions", bybit.get_api_key_permissions().map(|r| r.map(drop)).boxed()),
+            ("bybit.get_fee_rates", bybit.get_fee_rates("BTCUSDT").map(|r| r.map(drop)).boxed()),
+            ("bybit.place_order_cancel_safe", bybit.place_order_cancel_safe(order).map(|r| r.map(drop)).boxed()),
+            ("bybit.place_orders", bybit.place_orders(std::slice::from_ref(order)).map(|r| r.map(drop)).boxed()),
+            ("bybit.cancel_order_by_client_id", bybit.cancel_order_by_client_id("BTCUSDT", "arb1").map(|r| r.map(drop)).boxed()),
+            ("bybit.cancel_orders_by_client_id", bybit.cancel_orders_by_client_id(orders).map(|r| r.map(drop)).boxed()),
+            ("bybit.transfer_between_sub_accounts", bybit.transfer_between_sub_accounts(transfer).map(|r| r.map(drop)).boxed()),
+        ];
+        let (names, futures): (Vec<_>, Vec<_>) = calls.into_iter().unzip();
+        // Concurrently, so the clients' request spacing does not add up
+        let outcomes = self.runtime.block_on(join_all(futures));
+        names.into_iter().zip(outcomes).collect()
+    }
+}
+
+fn cases() -> u32 {
+    std::env::var("PROPTEST_CASES").ok().and_then(|cases| cases.parse().ok()).unwrap_or(64)
+}
+
+fn run(strategy: impl Strategy<Value = String>, check: impl Fn(&str, anyhow::Error) -> Result<(), TestCaseError>) {
+    let fixture = Fixture::start();
+    let mut runner = TestRunner::new(Config { cases: cases(), failure_persistence: None, ..Config::default() });
+    let outcome = runner.run(&strategy, |body| {
+        for (call, outcome) in fixture.call_all(&body) {
+            if let Err(error) = outcome {
+                check(call, error)?;
+            }
+        }
+        Ok(())
+    });
+    if let Err(failure) = outcome {
+        panic!("{}", failure);
+    }
+}
+
+#[test]
+fn test_truncated_responses_fail_with_parse_errors() {
+    run(truncated(), |call, error| {
+        let parse_error = matches!(error.downcast_ref::<ExchangeError>(), Some(ExchangeError::ParseError(_)));
+        prop_assert!(parse_error, "{} failed with {:#}", call, error);
+        Ok(())
+    });
+}
+
+#[test]
+fn test_malformed_responses_fail_without_panicking() {
+    run(prop_oneof![json().prop_map(|value| value.to_string()), mutated()], |call, error| {
+        prop_assert!(error.downcast_ref::<ExchangeError>().is_some(), "{} failed with an untyped error: {:#}", call, error);
+        Ok(())
+    });
+}
+
+#[test]
+fn test_out_of_range_values_fail_with_parse_errors() {
+    let earliest = DateTime::<Utc>::MIN_UTC.timestamp_millis();
+    let latest = DateTime::<Utc>::MAX_UTC.timestamp_millis();
+    let fixture = Fixture::start();
+    let cases = [
+        // Paging past the last candle would overflow the date
+        ("binance.get_klines", format!(r#"[[{},"1","1","1","1","1"]]"#, latest)),
+        ("bybit.get_klines", format!(r#"{{"retCode":0,"retMsg":"OK","result":{{"list":[["{}","1","1","1","1","1"]]}}}}"#, earliest)),
+        // The average price overflows a decimal
+        ("binance.get_order_by_client_id", r#"{"orderId":7,"status":"FILLED","executedQty":"0.0000000000000000000000000001",
+            "cummulativeQuoteQty":"79228162514264337593543950335"}"#.to_string()),
+    ];
+    for (call, body) in cases {
+        let outcomes = fixture.call_all(&body);
+        let (_, outcome) = outcomes.into_iter().find(|(name, _)| *name == call).unwrap();
+        let error = outcome.expect_err(call);
+        assert!(
+            matches!(error.downcast_ref::<ExchangeError>(), Some(ExchangeError::ParseError(_))),
+            "{} failed with {:#}", call, error
+        );
+    }
+}